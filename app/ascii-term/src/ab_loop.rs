@@ -0,0 +1,132 @@
+//! A-B リピート（2 つの再生位置の間を繰り返し再生する）
+//!
+//! `a` キーを押すたびに A（始点）、B（終点）、解除の順に切り替わる。
+//! `--ab-loop START:END` で始めから区間を指定することもできる
+
+use std::str::FromStr;
+use std::time::Duration;
+
+/// これより短い区間は繰り返さない（移動ばかりになるため）
+const MIN_LOOP_LENGTH: Duration = Duration::from_millis(200);
+
+/// 繰り返す区間
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AbLoop {
+    pub start: Duration,
+    pub end: Duration,
+}
+
+impl AbLoop {
+    /// 終点が始点より十分後ろでなければ None
+    pub fn new(start: Duration, end: Duration) -> Option<Self> {
+        (end >= start + MIN_LOOP_LENGTH).then_some(Self { start, end })
+    }
+
+    /// 再生位置が終点に達していれば、戻る位置（始点）
+    pub fn restart_at(&self, position: Duration) -> Option<Duration> {
+        (position >= self.end).then_some(self.start)
+    }
+}
+
+/// `12.5:20` のような秒数の組（`--ab-loop`）
+impl FromStr for AbLoop {
+    type Err = String;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        let (start, end) = text
+            .split_once(':')
+            .ok_or_else(|| format!("'{}' is not START:END in seconds", text))?;
+        let start = crate::arg_values::parse_seconds(start)?;
+        let end = crate::arg_values::parse_seconds(end)?;
+        AbLoop::new(Duration::from_secs_f64(start), Duration::from_secs_f64(end)).ok_or_else(|| {
+            format!(
+                "END must be at least {:.1}s after START",
+                MIN_LOOP_LENGTH.as_secs_f64()
+            )
+        })
+    }
+}
+
+/// `a` キーで付けた印
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum AbMarks {
+    #[default]
+    Off,
+    /// A だけ付けた（B を待っている）
+    Start(Duration),
+    Looping(AbLoop),
+}
+
+impl AbMarks {
+    /// 再生位置に次の印を付ける
+    ///
+    /// B が A より前（または近すぎる）なら、付け直した A として扱う
+    pub fn mark(&mut self, position: Duration) {
+        *self = match *self {
+            AbMarks::Off => AbMarks::Start(position),
+            AbMarks::Start(start) => match AbLoop::new(start, position) {
+                Some(ab_loop) => AbMarks::Looping(ab_loop),
+                None => AbMarks::Start(position),
+            },
+            AbMarks::Looping(_) => AbMarks::Off,
+        };
+    }
+
+    /// 繰り返している区間
+    pub fn active(&self) -> Option<AbLoop> {
+        match self {
+            AbMarks::Looping(ab_loop) => Some(*ab_loop),
+            _ => None,
+        }
+    }
+}
+
+impl From<Option<AbLoop>> for AbMarks {
+    fn from(ab_loop: Option<AbLoop>) -> Self {
+        ab_loop.map_or(AbMarks::Off, AbMarks::Looping)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn secs(seconds: f64) -> Duration {
+        Duration::from_secs_f64(seconds)
+    }
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(
+            "12.5:20".parse(),
+            Ok(AbLoop {
+                start: secs(12.5),
+                end: secs(20.0)
+            })
+        );
+        assert!("20".parse::<AbLoop>().is_err());
+        assert!("20:10".parse::<AbLoop>().is_err());
+        assert!("-1:10".parse::<AbLoop>().is_err());
+        assert!("1:1.1".parse::<AbLoop>().is_err());
+    }
+
+    #[test]
+    fn test_marks_cycle() {
+        let mut marks = AbMarks::default();
+        marks.mark(secs(10.0));
+        assert_eq!(marks, AbMarks::Start(secs(10.0)));
+        assert_eq!(marks.active(), None);
+
+        // A より前で押すと A を付け直す
+        marks.mark(secs(4.0));
+        assert_eq!(marks, AbMarks::Start(secs(4.0)));
+
+        marks.mark(secs(8.0));
+        let ab_loop = marks.active().unwrap();
+        assert_eq!(ab_loop.restart_at(secs(7.9)), None);
+        assert_eq!(ab_loop.restart_at(secs(8.0)), Some(secs(4.0)));
+
+        marks.mark(secs(6.0));
+        assert_eq!(marks, AbMarks::Off);
+    }
+}
@@ -0,0 +1,143 @@
+//! アルバム表示
+//!
+//! ディレクトリ再生時に、アーティスト・アルバム名とトラック一覧を表示する。
+//! 再生中のトラックは強調表示する
+
+use std::time::Duration;
+
+use crate::canvas::{TextCanvas, truncate};
+use crate::playlist::{Playlist, track_title};
+use crate::renderer::RenderedFrame;
+use crate::status_bar::format_time;
+
+const HEADER_COLOR: [u8; 3] = [255, 255, 255];
+const RULE_COLOR: [u8; 3] = [96, 96, 96];
+const CURRENT_COLOR: [u8; 3] = [255, 200, 64];
+const PLAYED_COLOR: [u8; 3] = [110, 110, 110];
+const UPCOMING_COLOR: [u8; 3] = [190, 190, 190];
+
+/// ヘッダー（タイトル行 + 区切り線）の行数
+const HEADER_ROWS: usize = 2;
+
+/// アルバム表示のフレームを生成
+pub fn render_album_view(
+    playlist: &Playlist,
+    elapsed: Duration,
+    width: u32,
+    height: u32,
+) -> RenderedFrame {
+    let mut canvas = TextCanvas::new(width, height);
+    let width = canvas.width();
+
+    let artist = playlist.album_artist().unwrap_or("Unknown Artist");
+    let album = playlist.album_title().unwrap_or("Unknown Album");
+    let header = format!(" ♪ {} — {}", artist, album);
+    canvas.put_str(0, 0, &truncate(&header, width), HEADER_COLOR);
+    canvas.fill_row(1, '─', RULE_COLOR);
+
+    let visible_rows = canvas.height().saturating_sub(HEADER_ROWS);
+    if visible_rows == 0 {
+        return canvas.into_frame();
+    }
+
+    // 再生中のトラックが常に見えるようにスクロールする
+    let current = playlist.current_index();
+    let first = current
+        .saturating_sub(visible_rows / 2)
+        .min(playlist.len().saturating_sub(visible_rows));
+
+    for (row, (index, entry)) in playlist
+        .entries()
+        .iter()
+        .enumerate()
+        .skip(first)
+        .take(visible_rows)
+        .enumerate()
+    {
+        let (marker, color) = if index == current {
+            ("▶", CURRENT_COLOR)
+        } else if index < current {
+            (" ", PLAYED_COLOR)
+        } else {
+            (" ", UPCOMING_COLOR)
+        };
+
+        let number = entry.info.tags.track_number.unwrap_or(index as u32 + 1);
+        let time = if index == current {
+            format!(
+                "{} / {}",
+                format_time(elapsed),
+                format_time(entry.info.duration.unwrap_or_default())
+            )
+        } else {
+            format_time(entry.info.duration.unwrap_or_default())
+        };
+
+        let prefix = format!(" {} {:02}. ", marker, number);
+        let title_width = width.saturating_sub(prefix.chars().count() + time.chars().count() + 2);
+        let line = format!(
+            "{}{:<title_width$}  {}",
+            prefix,
+            truncate(&track_title(entry), title_width),
+            time
+        );
+
+        canvas.put_str(0, HEADER_ROWS + row, &line, color);
+    }
+
+    canvas.into_frame()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use codec::{MediaFile, MediaInfo, MediaTags, MediaType};
+
+    fn track(title: &str, number: u32) -> MediaFile {
+        MediaFile {
+            path: format!("{}.mp3", title),
+            media_type: MediaType::Audio,
+            info: MediaInfo {
+                duration: Some(Duration::from_secs(185)),
+                has_audio: true,
+                tags: MediaTags {
+                    title: Some(title.to_string()),
+                    artist: Some("Artist".to_string()),
+                    album: Some("Album".to_string()),
+                    track_number: Some(number),
+                    ..Default::default()
+                },
+                ..Default::default()
+            },
+        }
+    }
+
+    fn rows(frame: &RenderedFrame) -> Vec<String> {
+        frame.text().lines().map(str::to_string).collect()
+    }
+
+    #[test]
+    fn test_album_view_highlights_current_track() {
+        let mut playlist = Playlist::new(vec![track("One", 1), track("Two", 2), track("Three", 3)]);
+        playlist.set_current(1);
+
+        let frame = render_album_view(&playlist, Duration::from_secs(42), 40, 6);
+        let rows = rows(&frame);
+
+        assert_eq!(frame.cells.len(), 40 * 6);
+        assert!(rows[0].contains("Artist — Album"));
+        assert!(rows[3].contains("▶ 02. Two"));
+        assert!(rows[3].trim_end().ends_with("0:42 / 3:05"));
+        assert!(!rows[2].contains('▶'));
+    }
+
+    #[test]
+    fn test_album_view_scrolls_to_current_track() {
+        let tracks = (1..=20).map(|n| track(&format!("Track{}", n), n)).collect();
+        let mut playlist = Playlist::new(tracks);
+        playlist.set_current(15);
+
+        let frame = render_album_view(&playlist, Duration::ZERO, 40, 6);
+        assert!(rows(&frame).iter().any(|row| row.contains("▶ 16. Track16")));
+    }
+}
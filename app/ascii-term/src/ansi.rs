@@ -0,0 +1,369 @@
+//! レンダリング結果を ANSI エスケープシーケンスへ変換する
+//!
+//! ターミナル表示に限らず、録画やファイル出力でも同じバイト列を使えるよう、
+//! 出力先に依存しない純粋な関数として実装する
+
+use std::fmt::Write;
+
+use clap::ValueEnum;
+use serde::Deserialize;
+
+use crate::canvas;
+use crate::palette;
+use crate::renderer::{Cell, RenderedFrame, Rgb};
+
+/// 出力する色の種類
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ColorMode {
+    /// 24 ビットカラー（`ESC[38;2;r;g;bm`）
+    Truecolor,
+    /// 256 色（`ESC[38;5;nm`）
+    Ansi256,
+    /// 16 色（`ESC[31m` など）
+    Ansi16,
+    /// 色なし（文字のみ）
+    Mono,
+}
+
+impl ColorMode {
+    /// 出力できる色のうち最も近い色（truecolor と色なしではそのまま）
+    pub fn quantize(self, rgb: Rgb) -> Rgb {
+        match self {
+            ColorMode::Truecolor | ColorMode::Mono => rgb,
+            ColorMode::Ansi256 => palette::ansi256_rgb(palette::nearest_ansi256(rgb)),
+            ColorMode::Ansi16 => palette::ansi16_rgb(palette::nearest_ansi16(rgb)),
+        }
+    }
+}
+
+/// 文字色・背景色を既定に戻す
+const RESET: &str = "\x1b[0m";
+
+/// 変わっていないセルがこの数以下なら、カーソルを移動せずに書き直す
+const MERGE_GAP: usize = 4;
+
+/// 背景色だけを既定に戻す
+const DEFAULT_BACKGROUND: &str = "\x1b[49m";
+
+/// フレームを ANSI エスケープシーケンスに変換する
+///
+/// 各行の先頭でカーソルを行頭へ移動するため、出力をそのまま書き込めば
+/// 画面の左上から描画される
+pub fn encode(frame: &RenderedFrame, mode: ColorMode) -> Vec<u8> {
+    encode_at(frame, mode, 0, 0)
+}
+
+/// フレームを指定した位置（0 始まりの列・行）を左上として描画するシーケンスに変換する
+///
+/// 映像の上に重ねる小さな表示（スコープなど）に使う
+pub fn encode_at(frame: &RenderedFrame, mode: ColorMode, column: u16, row: u16) -> Vec<u8> {
+    encode_rows(frame, mode, |out, y| {
+        out.push_str(&format!("\x1b[{};{}H", row as usize + y + 1, column + 1));
+    })
+}
+
+/// 前のフレームから変わったセルだけを描くシーケンスに変換する
+///
+/// 変わったセルの並びごとにカーソルを移動して書き直す。間の変わっていないセルが
+/// `MERGE_GAP` 個以下なら、移動するよりも短いのでまとめて書き直す。
+/// `previous` が画面にそのまま描かれていて、大きさが同じ場合だけ使うこと
+pub fn encode_changes(previous: &RenderedFrame, frame: &RenderedFrame, mode: ColorMode) -> Vec<u8> {
+    let mut out = String::new();
+    for (y, (before, row)) in previous.rows().zip(frame.rows()).enumerate() {
+        let mut x = 0;
+        while x < row.len() {
+            if before.get(x) == Some(&row[x]) {
+                x += 1;
+                continue;
+            }
+
+            // 変わったセルから、変わっていないセルが MERGE_GAP 個を超えて続く手前まで
+            let start = x;
+            let mut end = x + 1;
+            let mut unchanged = 0;
+            while end < row.len() && unchanged <= MERGE_GAP {
+                if before.get(end) == Some(&row[end]) {
+                    unchanged += 1;
+                } else {
+                    unchanged = 0;
+                }
+                end += 1;
+            }
+            let end = end - unchanged;
+
+            let _ = write!(out, "\x1b[{};{}H", y + 1, start + 1);
+            push_row(&mut out, &row[start..end], mode);
+            x = end;
+        }
+    }
+    out.into_bytes()
+}
+
+/// フレームを改行区切りの ANSI テキストに変換する
+///
+/// 先頭でカーソルを左上へ戻し、以降の行は CR LF で区切る
+pub fn encode_lines(frame: &RenderedFrame, mode: ColorMode) -> Vec<u8> {
+    encode_rows(frame, mode, |out, row| {
+        out.push_str(if row == 0 { "\x1b[H" } else { "\r\n" });
+    })
+}
+
+fn encode_rows(
+    frame: &RenderedFrame,
+    mode: ColorMode,
+    start_row: impl Fn(&mut String, usize),
+) -> Vec<u8> {
+    let bytes_per_cell = match mode {
+        ColorMode::Truecolor => 8,
+        ColorMode::Ansi256 | ColorMode::Ansi16 => 4,
+        ColorMode::Mono => 1,
+    };
+    let mut out = String::with_capacity(frame.cells.len() * bytes_per_cell);
+
+    for (y, row) in frame.rows().enumerate() {
+        start_row(&mut out, y);
+        push_row(&mut out, row, mode);
+    }
+
+    out.into_bytes()
+}
+
+/// 1 行分のセルを書き出す
+///
+/// 色は前のセルから変わったときだけ指定し（同じ色が続く部分は文字だけになる）、
+/// 行末で既定の色に戻す
+fn push_row(out: &mut String, row: &[Cell], mode: ColorMode) {
+    if mode == ColorMode::Mono {
+        out.extend(row.iter().map(|cell| canvas::printable(cell.ch)));
+        return;
+    }
+
+    let mut fg = None;
+    // None はターミナルの既定の背景
+    let mut bg = None;
+    for cell in row {
+        if fg != Some(cell.fg) {
+            push_color(out, mode, cell.fg, false);
+            fg = Some(cell.fg);
+        }
+        if bg != cell.bg {
+            match cell.bg {
+                Some(rgb) => push_color(out, mode, rgb, true),
+                None => out.push_str(DEFAULT_BACKGROUND),
+            }
+            bg = cell.bg;
+        }
+        // 制御文字のセルがあっても、エスケープシーケンスとして解釈させない
+        out.push(canvas::printable(cell.ch));
+    }
+    if !row.is_empty() {
+        out.push_str(RESET);
+    }
+}
+
+/// 文字色（`background` なら背景色）を指定するシーケンス
+fn push_color(out: &mut String, mode: ColorMode, [r, g, b]: Rgb, background: bool) {
+    let _ = match mode {
+        ColorMode::Truecolor => {
+            let target = if background { 48 } else { 38 };
+            write!(out, "\x1b[{};2;{};{};{}m", target, r, g, b)
+        }
+        ColorMode::Ansi256 => {
+            let target = if background { 48 } else { 38 };
+            write!(
+                out,
+                "\x1b[{};5;{}m",
+                target,
+                palette::nearest_ansi256([r, g, b])
+            )
+        }
+        ColorMode::Ansi16 => {
+            // 30〜37 / 40〜47 と、明るい色の 90〜97 / 100〜107
+            let index = palette::nearest_ansi16([r, g, b]);
+            let base = match (index < 8, background) {
+                (true, false) => 30,
+                (true, true) => 40,
+                (false, false) => 90 - 8,
+                (false, true) => 100 - 8,
+            };
+            write!(out, "\x1b[{}m", base + index as u32)
+        }
+        ColorMode::Mono => Ok(()),
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rendered(cells: &[Cell], width: u32, height: u32) -> RenderedFrame {
+        RenderedFrame {
+            cells: cells.to_vec(),
+            width,
+            height,
+            sixel: None,
+        }
+    }
+
+    fn encode_str(frame: &RenderedFrame, mode: ColorMode) -> String {
+        String::from_utf8(encode(frame, mode)).unwrap()
+    }
+
+    #[test]
+    fn test_encode_truecolor() {
+        let frame = rendered(
+            &[Cell::new('#', [255, 0, 0]), Cell::new('.', [1, 2, 3])],
+            2,
+            1,
+        );
+        assert_eq!(
+            encode_str(&frame, ColorMode::Truecolor),
+            "\x1b[1;1H\x1b[38;2;255;0;0m#\x1b[38;2;1;2;3m.\x1b[0m"
+        );
+    }
+
+    #[test]
+    fn test_encode_same_color_once_per_row() {
+        let cells: Vec<Cell> = "abcd".chars().map(|ch| Cell::new(ch, [9; 3])).collect();
+        assert_eq!(
+            encode_str(&rendered(&cells, 2, 2), ColorMode::Truecolor),
+            "\x1b[1;1H\x1b[38;2;9;9;9mab\x1b[0m\x1b[2;1H\x1b[38;2;9;9;9mcd\x1b[0m"
+        );
+    }
+
+    #[test]
+    fn test_encode_background() {
+        let cell = Cell {
+            bg: Some([7, 8, 9]),
+            ..Cell::new('x', [1, 2, 3])
+        };
+        assert_eq!(
+            encode_str(&rendered(&[cell], 1, 1), ColorMode::Truecolor),
+            "\x1b[1;1H\x1b[38;2;1;2;3m\x1b[48;2;7;8;9mx\x1b[0m"
+        );
+
+        // 背景のないセルが続けば既定の背景に戻す
+        let frame = rendered(&[cell, cell, Cell::new('y', [1, 2, 3])], 3, 1);
+        assert_eq!(
+            encode_str(&frame, ColorMode::Truecolor),
+            "\x1b[1;1H\x1b[38;2;1;2;3m\x1b[48;2;7;8;9mxx\x1b[49my\x1b[0m"
+        );
+    }
+
+    #[test]
+    fn test_encode_mono_has_no_color() {
+        let cells: Vec<Cell> = "abcd".chars().map(|ch| Cell::new(ch, [9; 3])).collect();
+        assert_eq!(
+            encode_str(&rendered(&cells, 2, 2), ColorMode::Mono),
+            "\x1b[1;1Hab\x1b[2;1Hcd"
+        );
+    }
+
+    #[test]
+    fn test_encode_at_offset() {
+        let cells: Vec<Cell> = "abcd".chars().map(|ch| Cell::new(ch, [9; 3])).collect();
+        let encoded = encode_at(&rendered(&cells, 2, 2), ColorMode::Mono, 10, 3);
+        assert_eq!(
+            String::from_utf8(encoded).unwrap(),
+            "\x1b[4;11Hab\x1b[5;11Hcd"
+        );
+    }
+
+    #[test]
+    fn test_encode_lines() {
+        let cells: Vec<Cell> = "abcd".chars().map(|ch| Cell::new(ch, [9; 3])).collect();
+        let encoded = encode_lines(&rendered(&cells, 2, 2), ColorMode::Mono);
+        assert_eq!(String::from_utf8(encoded).unwrap(), "\x1b[Hab\r\ncd");
+    }
+
+    #[test]
+    fn test_encode_changes_only() {
+        let before: Vec<Cell> = "abcdefghijkl"
+            .chars()
+            .map(|ch| Cell::new(ch, [9; 3]))
+            .collect();
+        let mut after = before.clone();
+        after[1].ch = 'X';
+        after[3].ch = 'Y';
+        after[11].ch = 'Z';
+        let encoded = encode_changes(
+            &rendered(&before, 12, 1),
+            &rendered(&after, 12, 1),
+            ColorMode::Mono,
+        );
+        // 近い変更はまとめ、離れた変更はカーソルを移動して書く
+        assert_eq!(
+            String::from_utf8(encoded).unwrap(),
+            "\x1b[1;2HXcY\x1b[1;12HZ"
+        );
+
+        let encoded = encode_changes(
+            &rendered(&before, 6, 2),
+            &rendered(&before, 6, 2),
+            ColorMode::Truecolor,
+        );
+        assert!(encoded.is_empty());
+    }
+
+    #[test]
+    fn test_encode_changes_colors() {
+        let before = [Cell::new('a', [1, 1, 1]), Cell::new('b', [1, 1, 1])];
+        let after = [Cell::new('a', [1, 1, 1]), Cell::new('b', [2, 2, 2])];
+        let encoded = encode_changes(
+            &rendered(&before, 2, 1),
+            &rendered(&after, 2, 1),
+            ColorMode::Truecolor,
+        );
+        assert_eq!(
+            String::from_utf8(encoded).unwrap(),
+            "\x1b[1;2H\x1b[38;2;2;2;2mb\x1b[0m"
+        );
+    }
+
+    #[test]
+    fn test_encode_palette_modes() {
+        let cells = [
+            Cell {
+                ch: 'a',
+                fg: [255, 0, 0],
+                bg: Some([0, 0, 0]),
+            },
+            Cell::new('b', [128, 128, 128]),
+        ];
+        assert_eq!(
+            encode_str(&rendered(&cells, 2, 1), ColorMode::Ansi256),
+            "\x1b[1;1H\x1b[38;5;196m\x1b[48;5;16ma\x1b[38;5;244m\x1b[49mb\x1b[0m"
+        );
+        assert_eq!(
+            encode_str(&rendered(&cells, 2, 1), ColorMode::Ansi16),
+            "\x1b[1;1H\x1b[91m\x1b[40ma\x1b[90m\x1b[49mb\x1b[0m"
+        );
+    }
+
+    #[test]
+    fn test_quantize() {
+        assert_eq!(ColorMode::Truecolor.quantize([1, 2, 3]), [1, 2, 3]);
+        assert_eq!(ColorMode::Ansi256.quantize([250, 10, 5]), [255, 0, 0]);
+        assert_eq!(ColorMode::Ansi16.quantize([10, 200, 10]), [0, 205, 0]);
+    }
+
+    #[test]
+    fn test_encode_empty_frame() {
+        assert_eq!(encode_str(&rendered(&[], 0, 0), ColorMode::Truecolor), "");
+        assert_eq!(encode_str(&rendered(&[], 3, 0), ColorMode::Mono), "");
+    }
+
+    #[test]
+    fn test_encode_multibyte_chars() {
+        let frame = rendered(
+            &[Cell::new('█', [0, 0, 0]), Cell::new('▀', [255, 255, 255])],
+            2,
+            1,
+        );
+        assert_eq!(
+            encode_str(&frame, ColorMode::Truecolor),
+            "\x1b[1;1H\x1b[38;2;0;0;0m█\x1b[38;2;255;255;255m▀\x1b[0m"
+        );
+    }
+}
@@ -0,0 +1,598 @@
+//! `.ansimovie`：描画結果の録画形式（`--emit ansimovie`、`replay` で再生する）
+//!
+//! 全セルを持つキーフレームと、前のフレームから変わったセルだけを持つ差分フレームを
+//! zstd で圧縮して並べる。キーフレームの位置の索引を末尾に置くので、長い録画でも
+//! 先頭から読まずに移動できる
+//!
+//! # 形式（バージョン 1）
+//!
+//! 数値はすべてリトルエンディアン。
+//!
+//! ```text
+//! ヘッダー   "ANSIMOVI"（8 バイト） バージョン u32
+//! レコード…  種類 u8  時刻 u64（最初のフレームからのマイクロ秒）
+//!            圧縮後の長さ u32  zstd で圧縮した内容
+//! 末尾       索引レコードの位置 u64  "AMIX"（4 バイト）
+//! ```
+//!
+//! レコードの種類と、圧縮前の内容：
+//!
+//! - 0 キーフレーム：幅 u32、高さ u32、行優先で `幅 * 高さ` 個のセル
+//! - 1 差分フレーム：区間の数 u32、区間ごとに先頭のセル番号 u32・セル数 u32・セル。
+//!   直前のフレームと同じ大きさのときだけ使い、書かれていないセルは直前のまま
+//! - 2 索引：キーフレームの数 u32、キーフレームごとに時刻 u64・レコードの位置 u64。
+//!   フレームのレコードの後に 1 つだけ置く（時刻は 0）
+//!
+//! セルは 11 バイトで、文字（Unicode のコードポイント）u32・文字色 RGB 3 バイト・
+//! 背景色の有無 u8（1 なら背景色あり）・背景色 RGB 3 バイト（なければ 0）。
+//!
+//! 録画が途中で止まって末尾がないファイルも、先頭からレコードをたどって読める
+//! （途中で切れたレコードは捨てる）。
+
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::time::Duration;
+
+use anyhow::{Context, Result, bail};
+
+use crate::renderer::{Cell, RenderedFrame};
+
+/// ファイルの先頭
+pub const MAGIC: &[u8; 8] = b"ANSIMOVI";
+const VERSION: u32 = 1;
+/// ファイルの末尾
+const TRAILER_MAGIC: &[u8; 4] = b"AMIX";
+
+const HEADER_LEN: u64 = 12;
+const RECORD_HEADER_LEN: u64 = 13;
+const TRAILER_LEN: u64 = 12;
+const CELL_LEN: usize = 11;
+
+/// この数のフレームごとにキーフレームを入れる（30 fps で 5 秒）
+const KEYFRAME_INTERVAL: u32 = 150;
+
+/// zstd の圧縮レベル（録画しながら圧縮しても遅れない程度）
+const COMPRESSION_LEVEL: i32 = 3;
+
+/// レコードの種類
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum RecordKind {
+    Keyframe = 0,
+    Delta = 1,
+    Index = 2,
+}
+
+impl RecordKind {
+    fn from_byte(byte: u8) -> Result<Self> {
+        Ok(match byte {
+            0 => RecordKind::Keyframe,
+            1 => RecordKind::Delta,
+            2 => RecordKind::Index,
+            _ => bail!("Unknown record type {}", byte),
+        })
+    }
+}
+
+/// キーフレームの時刻と、そのレコードのファイル内の位置
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct IndexEntry {
+    pub timestamp: Duration,
+    pub offset: u64,
+}
+
+/// 描画結果を `.ansimovie` として書き出す
+pub struct AnsiMovieWriter<W: Write> {
+    out: W,
+    /// 次に書くレコードの位置
+    offset: u64,
+    previous: Option<RenderedFrame>,
+    /// 最後のキーフレームから書いた差分フレームの数
+    since_keyframe: u32,
+    index: Vec<IndexEntry>,
+    finished: bool,
+}
+
+impl<W: Write> AnsiMovieWriter<W> {
+    pub fn new(mut out: W) -> io::Result<Self> {
+        out.write_all(MAGIC)?;
+        out.write_all(&VERSION.to_le_bytes())?;
+        Ok(Self {
+            out,
+            offset: HEADER_LEN,
+            previous: None,
+            since_keyframe: 0,
+            index: Vec::new(),
+            finished: false,
+        })
+    }
+
+    /// フレームを 1 つ書き込む
+    ///
+    /// 大きさが変わったとき、前のキーフレームから離れたとき、半分より多くのセルが
+    /// 変わったときはキーフレームにする
+    pub fn write_frame(&mut self, timestamp: Duration, frame: &RenderedFrame) -> io::Result<()> {
+        let delta = self
+            .previous
+            .as_ref()
+            .filter(|_| self.since_keyframe < KEYFRAME_INTERVAL)
+            .and_then(|previous| encode_delta(previous, frame));
+        let (kind, payload) = match delta {
+            Some(payload) => {
+                self.since_keyframe += 1;
+                (RecordKind::Delta, payload)
+            }
+            None => {
+                self.index.push(IndexEntry {
+                    timestamp,
+                    offset: self.offset,
+                });
+                self.since_keyframe = 0;
+                (RecordKind::Keyframe, encode_keyframe(frame))
+            }
+        };
+        self.write_record(kind, timestamp, &payload)?;
+
+        match &mut self.previous {
+            Some(previous) => {
+                previous.cells.clone_from(&frame.cells);
+                previous.width = frame.width;
+                previous.height = frame.height;
+            }
+            None => {
+                self.previous = Some(RenderedFrame {
+                    cells: frame.cells.clone(),
+                    width: frame.width,
+                    height: frame.height,
+                    sixel: None,
+                })
+            }
+        }
+        Ok(())
+    }
+
+    /// 索引と末尾を書き込む（2 回目以降は何もしない）
+    pub fn finish(&mut self) -> io::Result<()> {
+        if self.finished {
+            return Ok(());
+        }
+        self.finished = true;
+        let mut payload = Vec::with_capacity(4 + self.index.len() * 16);
+        payload.extend_from_slice(&(self.index.len() as u32).to_le_bytes());
+        for entry in &self.index {
+            payload.extend_from_slice(&(entry.timestamp.as_micros() as u64).to_le_bytes());
+            payload.extend_from_slice(&entry.offset.to_le_bytes());
+        }
+        let index_offset = self.offset;
+        self.write_record(RecordKind::Index, Duration::ZERO, &payload)?;
+        self.out.write_all(&index_offset.to_le_bytes())?;
+        self.out.write_all(TRAILER_MAGIC)?;
+        self.out.flush()
+    }
+
+    pub fn flush(&mut self) -> io::Result<()> {
+        self.out.flush()
+    }
+
+    fn write_record(
+        &mut self,
+        kind: RecordKind,
+        timestamp: Duration,
+        payload: &[u8],
+    ) -> io::Result<()> {
+        let compressed = zstd::bulk::compress(payload, COMPRESSION_LEVEL)?;
+        self.out.write_all(&[kind as u8])?;
+        self.out
+            .write_all(&(timestamp.as_micros() as u64).to_le_bytes())?;
+        self.out
+            .write_all(&(compressed.len() as u32).to_le_bytes())?;
+        self.out.write_all(&compressed)?;
+        self.offset += RECORD_HEADER_LEN + compressed.len() as u64;
+        Ok(())
+    }
+}
+
+fn encode_keyframe(frame: &RenderedFrame) -> Vec<u8> {
+    let mut payload = Vec::with_capacity(8 + frame.cells.len() * CELL_LEN);
+    payload.extend_from_slice(&frame.width.to_le_bytes());
+    payload.extend_from_slice(&frame.height.to_le_bytes());
+    for cell in &frame.cells {
+        push_cell(&mut payload, cell);
+    }
+    payload
+}
+
+/// 変わったセルの区間を並べる。キーフレームにしたほうがよければ None
+fn encode_delta(previous: &RenderedFrame, frame: &RenderedFrame) -> Option<Vec<u8>> {
+    if (previous.width, previous.height) != (frame.width, frame.height)
+        || previous.cells.len() != frame.cells.len()
+    {
+        return None;
+    }
+    let mut runs = Vec::new();
+    let mut changed = 0;
+    let mut x = 0;
+    while x < frame.cells.len() {
+        if previous.cells[x] == frame.cells[x] {
+            x += 1;
+            continue;
+        }
+        let start = x;
+        while x < frame.cells.len() && previous.cells[x] != frame.cells[x] {
+            x += 1;
+        }
+        runs.push(start..x);
+        changed += x - start;
+    }
+    if changed * 2 > frame.cells.len() {
+        return None;
+    }
+
+    let mut payload = Vec::with_capacity(4 + runs.len() * 8 + changed * CELL_LEN);
+    payload.extend_from_slice(&(runs.len() as u32).to_le_bytes());
+    for run in runs {
+        payload.extend_from_slice(&(run.start as u32).to_le_bytes());
+        payload.extend_from_slice(&(run.len() as u32).to_le_bytes());
+        for cell in &frame.cells[run] {
+            push_cell(&mut payload, cell);
+        }
+    }
+    Some(payload)
+}
+
+fn push_cell(payload: &mut Vec<u8>, cell: &Cell) {
+    payload.extend_from_slice(&(cell.ch as u32).to_le_bytes());
+    payload.extend_from_slice(&cell.fg);
+    payload.push(u8::from(cell.bg.is_some()));
+    payload.extend_from_slice(&cell.bg.unwrap_or_default());
+}
+
+/// 圧縮前の内容を先頭から読む
+struct Payload<'a>(&'a [u8]);
+
+impl Payload<'_> {
+    fn take(&mut self, len: usize) -> Result<&[u8]> {
+        if self.0.len() < len {
+            bail!("Record is shorter than its contents");
+        }
+        let (head, rest) = self.0.split_at(len);
+        self.0 = rest;
+        Ok(head)
+    }
+
+    fn u32(&mut self) -> Result<u32> {
+        Ok(u32::from_le_bytes(self.take(4)?.try_into()?))
+    }
+
+    fn u64(&mut self) -> Result<u64> {
+        Ok(u64::from_le_bytes(self.take(8)?.try_into()?))
+    }
+
+    fn cell(&mut self) -> Result<Cell> {
+        let bytes = self.take(CELL_LEN)?;
+        let code = u32::from_le_bytes(bytes[0..4].try_into()?);
+        let ch = char::from_u32(code).with_context(|| format!("Invalid character {:#x}", code))?;
+        Ok(Cell {
+            ch,
+            fg: [bytes[4], bytes[5], bytes[6]],
+            bg: (bytes[7] != 0).then_some([bytes[8], bytes[9], bytes[10]]),
+        })
+    }
+}
+
+/// `.ansimovie` を読み、フレームを順に取り出す
+pub struct AnsiMovieReader<R: Read + Seek> {
+    input: R,
+    index: Vec<IndexEntry>,
+    current: Option<RenderedFrame>,
+    /// 最後に読んだフレームがキーフレームだった
+    keyframe: bool,
+}
+
+impl<R: Read + Seek> AnsiMovieReader<R> {
+    pub fn open(mut input: R) -> Result<Self> {
+        let mut header = [0; HEADER_LEN as usize];
+        input
+            .read_exact(&mut header)
+            .context("Not an .ansimovie file")?;
+        if &header[..8] != MAGIC {
+            bail!("Not an .ansimovie file");
+        }
+        let version = u32::from_le_bytes(header[8..].try_into()?);
+        if version != VERSION {
+            bail!(
+                "Unsupported .ansimovie version {} (expected {})",
+                version,
+                VERSION
+            );
+        }
+
+        let mut reader = Self {
+            input,
+            index: Vec::new(),
+            current: None,
+            keyframe: false,
+        };
+        reader.index = match reader.read_index()? {
+            Some(index) => index,
+            None => reader.scan_index()?,
+        };
+        reader.input.seek(SeekFrom::Start(HEADER_LEN))?;
+        Ok(reader)
+    }
+
+    /// キーフレームの索引（時刻順）
+    pub fn index(&self) -> &[IndexEntry] {
+        &self.index
+    }
+
+    /// 最後に `next_frame` で読んだフレームがキーフレームかどうか
+    pub fn at_keyframe(&self) -> bool {
+        self.keyframe
+    }
+
+    /// 次のフレーム。最後まで読んだら None
+    pub fn next_frame(&mut self) -> Result<Option<(Duration, RenderedFrame)>> {
+        let Some((kind, timestamp, payload)) = self.read_record()? else {
+            return Ok(None);
+        };
+        let mut payload = Payload(&payload);
+        self.keyframe = kind == RecordKind::Keyframe;
+        match kind {
+            RecordKind::Index => return Ok(None),
+            RecordKind::Keyframe => {
+                let width = payload.u32()?;
+                let height = payload.u32()?;
+                let cells = (0..width as usize * height as usize)
+                    .map(|_| payload.cell())
+                    .collect::<Result<Vec<_>>>()?;
+                self.current = Some(RenderedFrame {
+                    cells,
+                    width,
+                    height,
+                    sixel: None,
+                });
+            }
+            RecordKind::Delta => {
+                let Some(current) = &mut self.current else {
+                    bail!("Delta frame before any keyframe");
+                };
+                for _ in 0..payload.u32()? {
+                    let start = payload.u32()? as usize;
+                    let len = payload.u32()? as usize;
+                    let Some(cells) = current.cells.get_mut(start..start + len) else {
+                        bail!("Delta frame writes outside the frame");
+                    };
+                    for cell in cells {
+                        *cell = payload.cell()?;
+                    }
+                }
+            }
+        }
+        Ok(self.current.clone().map(|frame| (timestamp, frame)))
+    }
+
+    /// `position` 以前で最後のキーフレームへ移動する（次の `next_frame` がそのフレーム）
+    pub fn seek(&mut self, position: Duration) -> Result<()> {
+        let keyframe = self
+            .index
+            .partition_point(|entry| entry.timestamp <= position);
+        let offset = match keyframe.checked_sub(1) {
+            Some(i) => self.index[i].offset,
+            None => HEADER_LEN,
+        };
+        self.input.seek(SeekFrom::Start(offset))?;
+        self.current = None;
+        Ok(())
+    }
+
+    /// レコードを 1 つ読む。ファイルの終わり（途中で切れたレコードを含む）なら None
+    fn read_record(&mut self) -> Result<Option<(RecordKind, Duration, Vec<u8>)>> {
+        let Some((kind, timestamp, len)) = self.read_record_header()? else {
+            return Ok(None);
+        };
+        let mut compressed = vec![0; len as usize];
+        match self.input.read_exact(&mut compressed) {
+            Ok(()) => {}
+            Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => return Ok(None),
+            Err(e) => return Err(e.into()),
+        }
+        let payload = zstd::stream::decode_all(compressed.as_slice())
+            .context("Failed to decompress a record")?;
+        Ok(Some((kind, timestamp, payload)))
+    }
+
+    fn read_record_header(&mut self) -> Result<Option<(RecordKind, Duration, u32)>> {
+        let mut header = [0; RECORD_HEADER_LEN as usize];
+        match self.input.read_exact(&mut header) {
+            Ok(()) => {}
+            Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => return Ok(None),
+            Err(e) => return Err(e.into()),
+        }
+        let kind = RecordKind::from_byte(header[0])?;
+        let timestamp = Duration::from_micros(u64::from_le_bytes(header[1..9].try_into()?));
+        let len = u32::from_le_bytes(header[9..].try_into()?);
+        Ok(Some((kind, timestamp, len)))
+    }
+
+    /// 末尾から索引を読む。末尾がなければ None
+    fn read_index(&mut self) -> Result<Option<Vec<IndexEntry>>> {
+        let len = self.input.seek(SeekFrom::End(0))?;
+        if len < HEADER_LEN + TRAILER_LEN {
+            return Ok(None);
+        }
+        self.input.seek(SeekFrom::End(-(TRAILER_LEN as i64)))?;
+        let mut trailer = [0; TRAILER_LEN as usize];
+        self.input.read_exact(&mut trailer)?;
+        if &trailer[8..] != TRAILER_MAGIC {
+            return Ok(None);
+        }
+        let offset = u64::from_le_bytes(trailer[..8].try_into()?);
+        self.input.seek(SeekFrom::Start(offset))?;
+        let Some((RecordKind::Index, _, payload)) = self.read_record()? else {
+            bail!("The index of the .ansimovie file is broken");
+        };
+
+        let mut payload = Payload(&payload);
+        let index = (0..payload.u32()?)
+            .map(|_| {
+                Ok(IndexEntry {
+                    timestamp: Duration::from_micros(payload.u64()?),
+                    offset: payload.u64()?,
+                })
+            })
+            .collect::<Result<_>>()?;
+        Ok(Some(index))
+    }
+
+    /// 末尾のないファイルで、レコードをたどってキーフレームを探す
+    fn scan_index(&mut self) -> Result<Vec<IndexEntry>> {
+        let file_len = self.input.seek(SeekFrom::End(0))?;
+        let mut index = Vec::new();
+        let mut offset = HEADER_LEN;
+        self.input.seek(SeekFrom::Start(offset))?;
+        while let Some((kind, timestamp, len)) = self.read_record_header()? {
+            // 途中で切れたレコードは数えない
+            if offset + RECORD_HEADER_LEN + len as u64 > file_len {
+                break;
+            }
+            match kind {
+                RecordKind::Index => break,
+                RecordKind::Keyframe => index.push(IndexEntry { timestamp, offset }),
+                RecordKind::Delta => {}
+            }
+            offset = self.input.seek(SeekFrom::Current(len as i64))?;
+        }
+        Ok(index)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::*;
+
+    fn frame(width: u32, height: u32, ch: char) -> RenderedFrame {
+        RenderedFrame {
+            cells: vec![Cell::new(ch, [10, 20, 30]); (width * height) as usize],
+            width,
+            height,
+            sixel: None,
+        }
+    }
+
+    fn ms(millis: u64) -> Duration {
+        Duration::from_millis(millis)
+    }
+
+    /// キーフレーム、差分、大きさの変わったキーフレームの順に書く
+    fn sample_frames() -> Vec<(Duration, RenderedFrame)> {
+        let first = frame(40, 10, '.');
+        let mut second = first.clone();
+        second.cells[5] = Cell {
+            bg: Some([0, 0, 255]),
+            ..Cell::new('█', [255, 0, 0])
+        };
+        second.cells[399] = Cell::new('あ', [0, 255, 0]);
+        vec![
+            (Duration::ZERO, first),
+            (ms(33), second),
+            (ms(66), frame(20, 5, '#')),
+        ]
+    }
+
+    fn write(frames: &[(Duration, RenderedFrame)], finish: bool) -> Vec<u8> {
+        let mut writer = AnsiMovieWriter::new(Vec::new()).unwrap();
+        for (timestamp, frame) in frames {
+            writer.write_frame(*timestamp, frame).unwrap();
+        }
+        if finish {
+            writer.finish().unwrap();
+        }
+        writer.out
+    }
+
+    fn read_all(bytes: Vec<u8>) -> Vec<(Duration, RenderedFrame)> {
+        let mut reader = AnsiMovieReader::open(Cursor::new(bytes)).unwrap();
+        let mut frames = Vec::new();
+        while let Some(frame) = reader.next_frame().unwrap() {
+            frames.push(frame);
+        }
+        frames
+    }
+
+    fn assert_same(actual: &[(Duration, RenderedFrame)], expected: &[(Duration, RenderedFrame)]) {
+        assert_eq!(actual.len(), expected.len());
+        for ((time, frame), (expected_time, expected)) in actual.iter().zip(expected) {
+            assert_eq!(time, expected_time);
+            assert_eq!(
+                (frame.width, frame.height),
+                (expected.width, expected.height)
+            );
+            assert_eq!(frame.cells, expected.cells);
+        }
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let frames = sample_frames();
+        let bytes = write(&frames, true);
+        assert_same(&read_all(bytes.clone()), &frames);
+
+        // 2 フレーム目は差分なので、索引はキーフレームの 2 つだけ
+        let reader = AnsiMovieReader::open(Cursor::new(bytes)).unwrap();
+        let times: Vec<_> = reader.index().iter().map(|entry| entry.timestamp).collect();
+        assert_eq!(times, [Duration::ZERO, ms(66)]);
+    }
+
+    #[test]
+    fn test_unfinished_recording() {
+        let frames = sample_frames();
+        let mut bytes = write(&frames, false);
+        // 途中で切れた最後のレコードは捨てる
+        bytes.truncate(bytes.len() - 3);
+
+        let reader = AnsiMovieReader::open(Cursor::new(bytes.clone())).unwrap();
+        assert_eq!(reader.index().len(), 1);
+        assert_same(&read_all(bytes), &frames[..2]);
+    }
+
+    #[test]
+    fn test_seek_to_keyframe() {
+        let frames = sample_frames();
+        let mut reader = AnsiMovieReader::open(Cursor::new(write(&frames, true))).unwrap();
+
+        reader.seek(ms(50)).unwrap();
+        let (time, frame) = reader.next_frame().unwrap().unwrap();
+        assert_eq!(time, Duration::ZERO);
+        assert_eq!(frame.cells, frames[0].1.cells);
+        assert!(reader.at_keyframe());
+        reader.next_frame().unwrap();
+        assert!(!reader.at_keyframe());
+
+        reader.seek(ms(70)).unwrap();
+        assert_eq!(reader.next_frame().unwrap().unwrap().0, ms(66));
+        assert!(reader.next_frame().unwrap().is_none());
+    }
+
+    #[test]
+    fn test_delta_frames_are_small() {
+        let mut frames = Vec::new();
+        let mut current = frame(200, 60, ' ');
+        for i in 0..100 {
+            current.cells[i * 7] = Cell::new('*', [255, 255, 255]);
+            frames.push((ms(i as u64 * 33), current.clone()));
+        }
+        let bytes = write(&frames, true);
+        // 全フレームを非圧縮で持つ大きさの 1% 未満
+        assert!(bytes.len() < 100 * 200 * 60 * CELL_LEN / 100);
+        assert_same(&read_all(bytes), &frames);
+    }
+
+    #[test]
+    fn test_rejects_other_files() {
+        assert!(AnsiMovieReader::open(Cursor::new(b"{\"version\": 2}".to_vec())).is_err());
+        let mut bytes = write(&sample_frames(), true);
+        bytes[8] = 9;
+        assert!(AnsiMovieReader::open(Cursor::new(bytes)).is_err());
+    }
+}
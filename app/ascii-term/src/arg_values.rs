@@ -0,0 +1,128 @@
+//! コマンドライン引数の値の検証
+//!
+//! clap の `value_parser` に渡す関数と、メディアから読み取った値にも使う
+//! 実行時の補正をまとめる
+
+/// フレームレートの範囲
+pub const MIN_FPS: f64 = 0.1;
+pub const MAX_FPS: f64 = 240.0;
+
+/// フレームレートが分からないときに使う値
+pub const DEFAULT_FPS: f64 = 30.0;
+
+fn parse_number(text: &str) -> Result<f64, String> {
+    text.trim()
+        .parse::<f64>()
+        .ok()
+        .filter(|value| value.is_finite())
+        .ok_or_else(|| format!("'{}' is not a number", text))
+}
+
+/// `--fps`（0.1〜240）
+pub fn parse_fps(text: &str) -> Result<f64, String> {
+    let fps = parse_number(text)?;
+    if !(MIN_FPS..=MAX_FPS).contains(&fps) {
+        return Err(format!("must be between {} and {}", MIN_FPS, MAX_FPS));
+    }
+    Ok(fps)
+}
+
+/// 0 以上の秒数（`--chat-at` など）
+pub fn parse_seconds(text: &str) -> Result<f64, String> {
+    let seconds = parse_number(text)?;
+    if seconds < 0.0 {
+        return Err("must not be negative".to_string());
+    }
+    Ok(seconds)
+}
+
+/// 0 より大きい秒数（`--title-card` など）
+pub fn parse_positive_seconds(text: &str) -> Result<f64, String> {
+    let seconds = parse_number(text)?;
+    if seconds <= 0.0 {
+        return Err("must be greater than 0".to_string());
+    }
+    Ok(seconds)
+}
+
+/// `parse_size` で受け付ける一辺の最大値（RGB の画像の大きさが u32 に収まるように）
+pub const MAX_SIZE_SIDE: u32 = 16384;
+
+/// `640x480` のような大きさ（`--camera-size` と `--rawvideo-size`）
+pub fn parse_size(text: &str) -> Result<(u32, u32), String> {
+    let invalid = || format!("'{}' is not a size like 640x480", text);
+    let (width, height) = text.trim().split_once(['x', 'X']).ok_or_else(invalid)?;
+    let width: u32 = width.parse().map_err(|_| invalid())?;
+    let height: u32 = height.parse().map_err(|_| invalid())?;
+    if width == 0 || height == 0 {
+        return Err("width and height must be greater than 0".to_string());
+    }
+    if width > MAX_SIZE_SIDE || height > MAX_SIZE_SIDE {
+        return Err(format!(
+            "width and height must be at most {}",
+            MAX_SIZE_SIDE
+        ));
+    }
+    Ok((width, height))
+}
+
+/// 再生に使うフレームレート
+///
+/// 指定値、メディアの値の順に使えるものを選び、範囲外は丸める
+/// （壊れたメディアの 0 fps などで 1 フレームの長さが無限にならないように）
+pub fn frame_rate(forced: Option<f64>, detected: Option<f64>) -> f64 {
+    [forced, detected]
+        .into_iter()
+        .flatten()
+        .find(|fps| fps.is_finite() && *fps > 0.0)
+        .unwrap_or(DEFAULT_FPS)
+        .clamp(MIN_FPS, MAX_FPS)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_fps() {
+        assert_eq!(parse_fps("24"), Ok(24.0));
+        assert_eq!(parse_fps(" 0.5 "), Ok(0.5));
+        assert!(parse_fps("0").is_err());
+        assert!(parse_fps("-30").is_err());
+        assert!(parse_fps("1000").is_err());
+        assert!(parse_fps("NaN").is_err());
+        assert!(parse_fps("fast").is_err());
+    }
+
+    #[test]
+    fn test_parse_seconds() {
+        assert_eq!(parse_seconds("0"), Ok(0.0));
+        assert_eq!(parse_seconds("12.5"), Ok(12.5));
+        assert!(parse_seconds("-1").is_err());
+        assert!(parse_seconds("inf").is_err());
+        assert_eq!(parse_positive_seconds("3"), Ok(3.0));
+        assert!(parse_positive_seconds("0").is_err());
+    }
+
+    #[test]
+    fn test_parse_size() {
+        assert_eq!(parse_size("640x480"), Ok((640, 480)));
+        assert_eq!(parse_size(" 1280X720 "), Ok((1280, 720)));
+        assert!(parse_size("0x480").is_err());
+        assert!(parse_size("640").is_err());
+        assert!(parse_size("640x").is_err());
+        assert!(parse_size("-640x480").is_err());
+        assert_eq!(parse_size("16384x16384"), Ok((16384, 16384)));
+        assert!(parse_size("65536x65536").is_err());
+    }
+
+    #[test]
+    fn test_frame_rate_falls_back_and_clamps() {
+        assert_eq!(frame_rate(Some(12.0), Some(25.0)), 12.0);
+        assert_eq!(frame_rate(None, Some(25.0)), 25.0);
+        assert_eq!(frame_rate(None, Some(0.0)), DEFAULT_FPS);
+        assert_eq!(frame_rate(None, Some(f64::NAN)), DEFAULT_FPS);
+        assert_eq!(frame_rate(None, Some(1000.0)), MAX_FPS);
+        assert_eq!(frame_rate(None, None), DEFAULT_FPS);
+    }
+}
@@ -0,0 +1,478 @@
+//! 音声の出力先（バックエンド）
+//!
+//! どのバックエンドでも、再生制御（一時停止・音量・キュー）は rodio の `Sink` が担い、
+//! バックエンドは `Sink` から出てくるサンプルを出力先へ流すだけにする。
+//! 既定の rodio が使えない環境では `--audio-backend` で切り替える
+//!
+//! - `rodio`: rodio の出力ストリーム（既定）
+//! - `cpal`: cpal の出力ストリームを直接開く
+//! - `pulse`: `pacat` に PCM を渡す（PulseAudio / PipeWire）
+//! - `aplay`: `aplay` に PCM を渡す（ALSA）
+//!
+//! `--audio-out` を指定した場合は、デバイスの代わりに WAV ファイル（または FIFO）へ書き出す
+
+use std::any::Any;
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command, Stdio};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use anyhow::{Context, Result};
+use clap::ValueEnum;
+use rodio::cpal::traits::{DeviceTrait, HostTrait, StreamTrait};
+use rodio::cpal::{self, FromSample, SampleFormat, SizedSample};
+use rodio::queue::SourcesQueueOutput;
+use rodio::source::UniformSourceIterator;
+use rodio::{OutputStream, Sink};
+use serde::Deserialize;
+
+/// 外部プレイヤー・ファイルに渡す PCM の形式
+const PCM_SAMPLE_RATE: u32 = 48_000;
+const PCM_CHANNELS: u16 = 2;
+
+/// 1 回に書き込むサンプル数
+const PCM_CHUNK_SAMPLES: usize = 1024;
+
+/// `Sink` から出てくるサンプルを、決まった形式に変換したもの
+type UniformOutput = UniformSourceIterator<SourcesQueueOutput<f32>, f32>;
+
+/// 音声の出力先
+pub trait AudioBackend {
+    fn name(&self) -> &'static str;
+
+    /// 出力先を開き、そこへつながった `Sink` を返す
+    ///
+    /// 2 つ目の値を保持している間だけ再生が続く
+    fn connect(&self) -> Result<(Sink, Box<dyn Any>)>;
+}
+
+/// `--audio-backend` で選べるバックエンド
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, ValueEnum, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum AudioBackendKind {
+    #[default]
+    Rodio,
+    Cpal,
+    Pulse,
+    Aplay,
+}
+
+impl AudioBackendKind {
+    pub fn backend(self) -> Box<dyn AudioBackend> {
+        match self {
+            AudioBackendKind::Rodio => Box::new(RodioBackend),
+            AudioBackendKind::Cpal => Box::new(CpalBackend),
+            AudioBackendKind::Pulse => Box::new(PipeBackend::pulse()),
+            AudioBackendKind::Aplay => Box::new(PipeBackend::aplay()),
+        }
+    }
+}
+
+/// 音声をどこへ出すか
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AudioOutput {
+    /// 音声デバイス
+    Device(AudioBackendKind),
+    /// WAV ファイルまたは FIFO
+    File(PathBuf),
+}
+
+impl Default for AudioOutput {
+    fn default() -> Self {
+        AudioOutput::Device(AudioBackendKind::default())
+    }
+}
+
+impl AudioOutput {
+    pub fn backend(&self) -> Box<dyn AudioBackend> {
+        match self {
+            AudioOutput::Device(kind) => kind.backend(),
+            AudioOutput::File(path) => Box::new(WavFileBackend::new(path)),
+        }
+    }
+}
+
+/// rodio の既定の出力ストリーム
+pub struct RodioBackend;
+
+impl AudioBackend for RodioBackend {
+    fn name(&self) -> &'static str {
+        "rodio"
+    }
+
+    fn connect(&self) -> Result<(Sink, Box<dyn Any>)> {
+        let (stream, handle) = OutputStream::try_default()
+            .map_err(|e| anyhow::anyhow!("Failed to initialize audio stream: {}", e))?;
+        let sink = Sink::try_new(&handle)
+            .map_err(|e| anyhow::anyhow!("Failed to create audio sink: {}", e))?;
+        Ok((sink, Box::new(stream)))
+    }
+}
+
+/// cpal の既定の出力デバイスに直接書き込む
+pub struct CpalBackend;
+
+impl CpalBackend {
+    fn build_stream<T>(
+        device: &cpal::Device,
+        config: &cpal::StreamConfig,
+        mut samples: UniformOutput,
+    ) -> Result<cpal::Stream>
+    where
+        T: SizedSample + FromSample<f32>,
+    {
+        let stream = device.build_output_stream(
+            config,
+            move |data: &mut [T], _: &cpal::OutputCallbackInfo| {
+                for out in data.iter_mut() {
+                    *out = T::from_sample(samples.next().unwrap_or(0.0));
+                }
+            },
+            |e| eprintln!("Audio stream error: {}", e),
+            None,
+        )?;
+        Ok(stream)
+    }
+}
+
+impl AudioBackend for CpalBackend {
+    fn name(&self) -> &'static str {
+        "cpal"
+    }
+
+    fn connect(&self) -> Result<(Sink, Box<dyn Any>)> {
+        let device = cpal::default_host()
+            .default_output_device()
+            .ok_or_else(|| anyhow::anyhow!("No audio output device"))?;
+        let supported = device
+            .default_output_config()
+            .context("Failed to get the output device configuration")?;
+        let config = supported.config();
+
+        let (sink, queue) = Sink::new_idle();
+        let samples = UniformSourceIterator::new(queue, config.channels, config.sample_rate.0);
+        let stream = match supported.sample_format() {
+            SampleFormat::F32 => Self::build_stream::<f32>(&device, &config, samples),
+            SampleFormat::I16 => Self::build_stream::<i16>(&device, &config, samples),
+            SampleFormat::U16 => Self::build_stream::<u16>(&device, &config, samples),
+            format => Err(anyhow::anyhow!("Unsupported sample format: {}", format)),
+        }?;
+        stream.play().context("Failed to start the audio stream")?;
+        Ok((sink, Box::new(stream)))
+    }
+}
+
+/// 32 ビット浮動小数点の PCM を外部プレイヤーの標準入力へ書き込む
+pub struct PipeBackend {
+    name: &'static str,
+    program: &'static str,
+    args: Vec<String>,
+}
+
+impl PipeBackend {
+    pub fn pulse() -> Self {
+        Self {
+            name: "pulse",
+            program: "pacat",
+            args: vec![
+                "--playback".to_string(),
+                "--format=float32le".to_string(),
+                format!("--rate={}", PCM_SAMPLE_RATE),
+                format!("--channels={}", PCM_CHANNELS),
+            ],
+        }
+    }
+
+    pub fn aplay() -> Self {
+        Self {
+            name: "aplay",
+            program: "aplay",
+            args: vec![
+                "-q".to_string(),
+                "-t".to_string(),
+                "raw".to_string(),
+                "-f".to_string(),
+                "FLOAT_LE".to_string(),
+                "-r".to_string(),
+                PCM_SAMPLE_RATE.to_string(),
+                "-c".to_string(),
+                PCM_CHANNELS.to_string(),
+            ],
+        }
+    }
+}
+
+impl AudioBackend for PipeBackend {
+    fn name(&self) -> &'static str {
+        self.name
+    }
+
+    fn connect(&self) -> Result<(Sink, Box<dyn Any>)> {
+        let mut child = Command::new(self.program)
+            .args(&self.args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::null())
+            .spawn()
+            .with_context(|| format!("Failed to start {}", self.program))?;
+        let stdin = child
+            .stdin
+            .take()
+            .ok_or_else(|| anyhow::anyhow!("Failed to open the input of {}", self.program))?;
+
+        let (sink, queue) = Sink::new_idle();
+        let mut samples: UniformOutput =
+            UniformSourceIterator::new(queue, PCM_CHANNELS, PCM_SAMPLE_RATE);
+        let stop = Arc::new(AtomicBool::new(false));
+        let thread_stop = stop.clone();
+        let program = self.program;
+
+        // 外部プレイヤーのバッファが埋まると書き込みで待つので、再生の速さに合う
+        thread::spawn(move || {
+            let mut writer = BufWriter::new(stdin);
+            let mut bytes = Vec::with_capacity(PCM_CHUNK_SAMPLES * 4);
+            while !thread_stop.load(Ordering::Relaxed) {
+                bytes.clear();
+                for sample in samples.by_ref().take(PCM_CHUNK_SAMPLES) {
+                    bytes.extend_from_slice(&sample.to_le_bytes());
+                }
+                if let Err(e) = writer.write_all(&bytes).and_then(|_| writer.flush()) {
+                    if !thread_stop.load(Ordering::Relaxed) {
+                        eprintln!("Warning: {} stopped accepting audio: {}", program, e);
+                    }
+                    break;
+                }
+            }
+        });
+
+        Ok((sink, Box::new(PipeOutput { child, stop })))
+    }
+}
+
+/// 16 ビット PCM の WAV として書き出す
+///
+/// 映像と同じ速さで再生が進むよう、実時間に合わせて書き込む。FIFO の場合は
+/// 読み手が開くまで待つため、書き込み用のスレッドで開く
+pub struct WavFileBackend {
+    path: PathBuf,
+}
+
+impl WavFileBackend {
+    pub fn new(path: &Path) -> Self {
+        Self {
+            path: path.to_path_buf(),
+        }
+    }
+}
+
+impl AudioBackend for WavFileBackend {
+    fn name(&self) -> &'static str {
+        "file"
+    }
+
+    fn connect(&self) -> Result<(Sink, Box<dyn Any>)> {
+        let (sink, queue) = Sink::new_idle();
+        let samples: UniformOutput =
+            UniformSourceIterator::new(queue, PCM_CHANNELS, PCM_SAMPLE_RATE);
+        let stop = Arc::new(AtomicBool::new(false));
+        let thread_stop = stop.clone();
+
+        if is_fifo(&self.path) {
+            let path = self.path.clone();
+            thread::spawn(move || {
+                let written = File::options()
+                    .write(true)
+                    .open(&path)
+                    .and_then(|file| write_wav(file, samples, &thread_stop));
+                if let Err(e) = written {
+                    eprintln!(
+                        "Warning: Failed to write audio to {}: {}",
+                        path.display(),
+                        e
+                    );
+                }
+            });
+            return Ok((sink, Box::new(FileOutput { stop, thread: None })));
+        }
+
+        let file = File::create(&self.path)
+            .with_context(|| format!("Failed to create {}", self.path.display()))?;
+        let path = self.path.clone();
+        let thread = thread::spawn(move || {
+            if let Err(e) = write_wav(file, samples, &thread_stop) {
+                eprintln!(
+                    "Warning: Failed to write audio to {}: {}",
+                    path.display(),
+                    e
+                );
+            }
+        });
+        Ok((
+            sink,
+            Box::new(FileOutput {
+                stop,
+                thread: Some(thread),
+            }),
+        ))
+    }
+}
+
+#[cfg(unix)]
+fn is_fifo(path: &Path) -> bool {
+    use std::os::unix::fs::FileTypeExt;
+    fs::metadata(path).is_ok_and(|meta| meta.file_type().is_fifo())
+}
+
+#[cfg(not(unix))]
+fn is_fifo(_path: &Path) -> bool {
+    false
+}
+
+/// 16 ビット・ステレオ・48 kHz の WAV ヘッダー
+fn wav_header(data_len: u32) -> [u8; 44] {
+    let block_align = PCM_CHANNELS * 2;
+    let byte_rate = PCM_SAMPLE_RATE * block_align as u32;
+    let mut header = [0u8; 44];
+    header[0..4].copy_from_slice(b"RIFF");
+    header[4..8].copy_from_slice(&data_len.saturating_add(36).to_le_bytes());
+    header[8..16].copy_from_slice(b"WAVEfmt ");
+    header[16..20].copy_from_slice(&16u32.to_le_bytes());
+    header[20..22].copy_from_slice(&1u16.to_le_bytes());
+    header[22..24].copy_from_slice(&PCM_CHANNELS.to_le_bytes());
+    header[24..28].copy_from_slice(&PCM_SAMPLE_RATE.to_le_bytes());
+    header[28..32].copy_from_slice(&byte_rate.to_le_bytes());
+    header[32..34].copy_from_slice(&block_align.to_le_bytes());
+    header[34..36].copy_from_slice(&16u16.to_le_bytes());
+    header[36..40].copy_from_slice(b"data");
+    header[40..44].copy_from_slice(&data_len.to_le_bytes());
+    header
+}
+
+fn to_pcm16(sample: f32) -> i16 {
+    (sample.clamp(-1.0, 1.0) * i16::MAX as f32) as i16
+}
+
+/// 止められるまで書き込み、最後に（シークできれば）ヘッダーの長さを書き直す
+fn write_wav(file: File, mut samples: UniformOutput, stop: &AtomicBool) -> io::Result<()> {
+    let mut writer = BufWriter::new(file);
+    // 長さが分からない間は最大値にしておく（FIFO の読み手はそのまま読める）
+    writer.write_all(&wav_header(u32::MAX))?;
+
+    let frame_bytes = PCM_CHANNELS as u64 * 2;
+    let started = Instant::now();
+    let mut data_len = 0u64;
+    let mut bytes = Vec::with_capacity(PCM_CHUNK_SAMPLES * 2);
+    while !stop.load(Ordering::Relaxed) {
+        bytes.clear();
+        for sample in samples.by_ref().take(PCM_CHUNK_SAMPLES) {
+            bytes.extend_from_slice(&to_pcm16(sample).to_le_bytes());
+        }
+        writer.write_all(&bytes)?;
+        data_len += bytes.len() as u64;
+
+        let written =
+            Duration::from_secs_f64((data_len / frame_bytes) as f64 / PCM_SAMPLE_RATE as f64);
+        if let Some(wait) = written.checked_sub(started.elapsed()) {
+            thread::sleep(wait);
+        }
+    }
+
+    let mut file = writer.into_inner().map_err(|e| e.into_error())?;
+    if let Ok(data_len) = u32::try_from(data_len)
+        && file.seek(SeekFrom::Start(0)).is_ok()
+    {
+        file.write_all(&wav_header(data_len))?;
+    }
+    Ok(())
+}
+
+/// 書き出し中のファイル。破棄すると書き込みを止める
+struct FileOutput {
+    stop: Arc<AtomicBool>,
+    /// 通常のファイルでは、ヘッダーを書き直し終えるまで待つ
+    thread: Option<thread::JoinHandle<()>>,
+}
+
+impl Drop for FileOutput {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+/// 外部プレイヤーのプロセス。破棄すると終了させる
+struct PipeOutput {
+    child: Child,
+    stop: Arc<AtomicBool>,
+}
+
+impl Drop for PipeOutput {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_backend_names() {
+        for kind in AudioBackendKind::value_variants() {
+            let name = kind.to_possible_value().unwrap().get_name().to_string();
+            assert_eq!(kind.backend().name(), name);
+        }
+    }
+
+    #[test]
+    fn test_wav_header() {
+        let header = wav_header(4800);
+        assert_eq!(&header[0..4], b"RIFF");
+        assert_eq!(u32::from_le_bytes(header[4..8].try_into().unwrap()), 4836);
+        assert_eq!(&header[8..16], b"WAVEfmt ");
+        // PCM・2 チャンネル・48 kHz・192000 バイト/秒・16 ビット
+        assert_eq!(u16::from_le_bytes([header[20], header[21]]), 1);
+        assert_eq!(u16::from_le_bytes([header[22], header[23]]), 2);
+        assert_eq!(
+            u32::from_le_bytes(header[24..28].try_into().unwrap()),
+            48_000
+        );
+        assert_eq!(
+            u32::from_le_bytes(header[28..32].try_into().unwrap()),
+            192_000
+        );
+        assert_eq!(u16::from_le_bytes([header[34], header[35]]), 16);
+        assert_eq!(u32::from_le_bytes(header[40..44].try_into().unwrap()), 4800);
+        assert_eq!(
+            u32::from_le_bytes(wav_header(u32::MAX)[4..8].try_into().unwrap()),
+            u32::MAX
+        );
+    }
+
+    #[test]
+    fn test_pcm16_conversion() {
+        assert_eq!(to_pcm16(0.0), 0);
+        assert_eq!(to_pcm16(1.0), i16::MAX);
+        assert_eq!(to_pcm16(-2.0), -i16::MAX);
+        assert_eq!(to_pcm16(0.5), 16383);
+    }
+
+    #[test]
+    fn test_pipe_commands_use_float_pcm() {
+        let pulse = PipeBackend::pulse();
+        assert_eq!(pulse.program, "pacat");
+        assert!(pulse.args.contains(&"--format=float32le".to_string()));
+        assert!(pulse.args.contains(&"--rate=48000".to_string()));
+
+        let aplay = PipeBackend::aplay();
+        assert_eq!(aplay.program, "aplay");
+        assert_eq!(aplay.args.join(" "), "-q -t raw -f FLOAT_LE -r 48000 -c 2");
+    }
+}
@@ -2,72 +2,97 @@
 
 use std::sync::Arc;
 use std::sync::atomic::{AtomicBool, Ordering};
-use std::thread;
 use std::time::Duration;
 
 use anyhow::Result;
-use crossbeam_channel::Sender;
-use rodio::OutputStream;
 
 use codec::audio::AudioDecoder;
+use codec::network::ErrorRecovery;
+use codec::source::AudioGenerator;
 
+use super::backend::AudioOutput;
+use super::ring_buffer::Producer;
+
+/// ジェネレーターが 1 回に生成するフレーム数
+const GENERATOR_CHUNK_FRAMES: usize = 4096;
+
+/// デコードするトラック
+pub(super) struct DecodeOptions {
+    pub file_path: String,
+    pub sample_rate: u32,
+    pub channels: u16,
+    /// コンテナに書かれた長さ（デコードできた割合の表示に使う）
+    pub expected_duration: Option<Duration>,
+    /// デコードを始める位置
+    pub start_position: Duration,
+}
+
+/// ファイルの音声をデコードしてリングバッファへ書き込む
+///
+/// バッファが埋まっている間は書き込み側で待つ。終わると `producer` が破棄され、
+/// 読み出し側は残りを再生し終えたところで終端する。
+/// 開けなかったとき、または途中でデコードできなくなったときはエラー
 pub(super) fn decode_audio_loop(
-    file_path: String,
-    sample_rate: u32,
-    channels: u16,
-    sender: Sender<Vec<f32>>,
+    options: DecodeOptions,
+    producer: Producer,
     stop_signal: Arc<AtomicBool>,
-    is_finished: Arc<AtomicBool>,
-    expected_duration: Option<Duration>,
-) {
+) -> Result<()> {
+    let DecodeOptions {
+        file_path,
+        sample_rate,
+        channels,
+        expected_duration,
+        start_position,
+    } = options;
     println!("Audio decode loop started");
 
-    let mut decoder = match AudioDecoder::new(&file_path) {
-        Ok(d) => d,
-        Err(e) => {
-            eprintln!("Failed to create audio decoder: {}", e);
-            is_finished.store(true, Ordering::Relaxed);
-            return;
-        }
-    };
+    let mut decoder = AudioDecoder::new(&file_path, None)
+        .map_err(|e| anyhow::anyhow!("Failed to create audio decoder: {}", e))?;
+
+    if !start_position.is_zero()
+        && let Err(e) = decoder.seek(start_position)
+    {
+        eprintln!("Failed to seek audio to {:?}: {}", start_position, e);
+    }
 
+    let mut recovery = ErrorRecovery::for_path(&file_path);
     let mut total_samples_sent = 0u64;
     let start_time = std::time::Instant::now();
     let expected_duration_secs = expected_duration.map(|d| d.as_secs_f64()).unwrap_or(0.0);
 
     println!("Expected duration: {:.1}s", expected_duration_secs);
 
+    let mut failure = None;
     while !stop_signal.load(Ordering::Relaxed) {
-        if sender.len() > 15 {
-            thread::sleep(Duration::from_millis(5));
-            continue;
-        }
-
         match decoder.decode_one() {
-            Ok(Some(frame)) => match frame.samples_as_f32() {
-                Ok(samples) if !samples.is_empty() => {
-                    total_samples_sent += samples.len() as u64;
-                    if sender.send(samples).is_err() {
-                        break;
+            Ok(Some(frame)) => {
+                recovery.on_success();
+                match frame.samples_as_f32() {
+                    Ok(samples) if !samples.is_empty() => {
+                        if !producer.push_all(&samples, &stop_signal) {
+                            break;
+                        }
+                        total_samples_sent += samples.len() as u64;
+                    }
+                    Ok(_) => {}
+                    Err(e) => {
+                        eprintln!("Audio frame conversion error: {}", e);
                     }
                 }
-                Ok(_) => {}
-                Err(e) => {
-                    eprintln!("Audio frame conversion error: {}", e);
-                }
-            },
+            }
             Ok(None) => {
                 println!("Audio stream EOF");
                 break;
             }
+            Err(e) if recovery.on_error(&e) => continue,
             Err(e) => {
-                eprintln!("Audio decode error: {}", e);
+                failure = Some(anyhow::anyhow!("Audio decode error: {}", e));
                 break;
             }
         }
     }
 
-    is_finished.store(true, Ordering::Relaxed);
+    drop(producer);
 
     let final_elapsed = start_time.elapsed();
     let final_audio_time = total_samples_sent as f64 / (sample_rate as f64 * channels as f64);
@@ -83,18 +108,40 @@ pub(super) fn decode_audio_loop(
     println!("Expected duration: {:.1}s", expected_duration_secs);
     println!("Coverage: {:.1}%", coverage);
     println!("Real time: {:.1}s", final_elapsed.as_secs_f64());
+    if recovery.recovered_errors() > 0 {
+        println!("Skipped decode errors: {}", recovery.recovered_errors());
+    }
     println!("=== End Audio Statistics ===");
+    failure.map_or(Ok(()), Err)
+}
+
+/// 手続き的な音声ジェネレーターから PCM を生成し続ける（停止されるまで終わらない）
+pub(super) fn generate_audio_loop(
+    mut generator: Box<dyn AudioGenerator>,
+    producer: Producer,
+    stop_signal: Arc<AtomicBool>,
+) {
+    let mut samples = vec![0.0; GENERATOR_CHUNK_FRAMES * generator.channels() as usize];
+
+    while !stop_signal.load(Ordering::Relaxed) {
+        generator.fill(&mut samples);
+        if !producer.push_all(&samples, &stop_signal) {
+            break;
+        }
+    }
 }
 
-pub fn diagnose_audio_system() -> Result<()> {
+pub fn diagnose_audio_system(output: &AudioOutput) -> Result<()> {
     println!("=== Audio System Diagnostics ===");
 
-    match OutputStream::try_default() {
-        Ok((_stream, _handle)) => {
-            println!("✓ Default audio device is available");
+    let backend = output.backend();
+    match backend.connect() {
+        Ok(_output) => {
+            println!("✓ Audio backend {} is available", backend.name());
         }
         Err(e) => {
-            println!("✗ Default audio device failed: {}", e);
+            println!("✗ Audio backend {} failed: {:#}", backend.name(), e);
+            println!("  Try another one with --audio-backend (rodio, cpal, pulse, aplay)");
             return Err(anyhow::anyhow!("Audio system not available"));
         }
     }
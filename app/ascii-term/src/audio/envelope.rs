@@ -0,0 +1,110 @@
+//! 再生の開始・一時停止・停止・シークでの音量の立ち上がりと立ち下がり
+//!
+//! 出力を急に始めたり止めたりするとクリックノイズが出るので、音量を `ramp` の
+//! 長さだけなめらかに変えてから止める。音量はキュー内のソースで共有するので、
+//! ギャップレスで次のトラックへ移るときには変わらない
+
+use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// 音量が 0 になるのを待つ間隔
+const POLL_INTERVAL: Duration = Duration::from_millis(2);
+
+/// 出力が止まっていて音量が変わらない場合に、`ramp` に加えて待つ時間
+const WAIT_MARGIN: Duration = Duration::from_millis(100);
+
+#[derive(Debug)]
+pub(super) struct GainEnvelope {
+    ramp: Duration,
+    /// 音量を 1 へ上げるか（false なら 0 へ下げる）
+    open: AtomicBool,
+    /// 現在の音量（`f32` のビット列）
+    gain: AtomicU32,
+}
+
+impl GainEnvelope {
+    /// 音量 0 で閉じた状態から始める
+    pub(super) fn new(ramp: Duration) -> Self {
+        Self {
+            ramp,
+            open: AtomicBool::new(false),
+            gain: AtomicU32::new(0.0f32.to_bits()),
+        }
+    }
+
+    pub(super) fn open(&self) {
+        self.open.store(true, Ordering::Relaxed);
+    }
+
+    pub(super) fn close(&self) {
+        self.open.store(false, Ordering::Relaxed);
+    }
+
+    fn gain(&self) -> f32 {
+        f32::from_bits(self.gain.load(Ordering::Relaxed))
+    }
+
+    /// 閉じて、出力された音量が 0 になるまで待つ（最長で `ramp` より少し長い時間）
+    pub(super) fn close_and_wait(&self) {
+        self.close();
+        let deadline = Instant::now() + self.ramp + WAIT_MARGIN;
+        while self.gain() > 0.0 && Instant::now() < deadline {
+            thread::sleep(POLL_INTERVAL);
+        }
+    }
+
+    /// 1 サンプルごとの音量の変化量
+    pub(super) fn step(&self, sample_rate: u32, channels: u16) -> f32 {
+        let samples = self.ramp.as_secs_f32() * sample_rate as f32 * channels as f32;
+        if samples < 1.0 { 1.0 } else { 1.0 / samples }
+    }
+
+    /// 音量を `step` だけ目標へ近づけて、`sample` に掛ける
+    pub(super) fn apply(&self, sample: f32, step: f32) -> f32 {
+        let current = self.gain();
+        let gain = if self.open.load(Ordering::Relaxed) {
+            (current + step).min(1.0)
+        } else {
+            (current - step).max(0.0)
+        };
+        if gain != current {
+            self.gain.store(gain.to_bits(), Ordering::Relaxed);
+        }
+        sample * gain
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ramps_up_and_down() {
+        // 10 ms を 1 チャンネル 1000 Hz で 10 サンプル
+        let envelope = GainEnvelope::new(Duration::from_millis(10));
+        let step = envelope.step(1000, 1);
+        assert_eq!(envelope.apply(1.0, step), 0.0);
+
+        envelope.open();
+        let rising: Vec<f32> = (0..12).map(|_| envelope.apply(1.0, step)).collect();
+        assert!((rising[0] - 0.1).abs() < 1e-6);
+        assert!(rising.windows(2).all(|pair| pair[0] <= pair[1]));
+        assert_eq!(rising[11], 1.0);
+
+        envelope.close();
+        let falling: Vec<f32> = (0..12).map(|_| envelope.apply(1.0, step)).collect();
+        assert!((falling[0] - 0.9).abs() < 1e-6);
+        assert_eq!(falling[11], 0.0);
+        // 音量が 0 になっていれば待たない
+        envelope.close_and_wait();
+    }
+
+    #[test]
+    fn test_zero_ramp_switches_immediately() {
+        let envelope = GainEnvelope::new(Duration::ZERO);
+        let step = envelope.step(48000, 2);
+        envelope.open();
+        assert_eq!(envelope.apply(0.5, step), 0.5);
+    }
+}
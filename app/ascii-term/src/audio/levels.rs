@@ -0,0 +1,233 @@
+//! 再生中の音声レベル（チャンネルごとのピーク・RMS）と最近のサンプル
+//!
+//! 出力デバイスへ渡すサンプルから一定間隔ごとに集計し、描画側のスレッドから
+//! 読めるようにアトミック変数で公開する。視覚化のために、左右を混ぜた最近の
+//! サンプルも少しずつまとめて共有のバッファへ書き込む
+
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicU32, Ordering};
+
+/// 1 秒あたりの更新回数
+const UPDATES_PER_SECOND: u32 = 10;
+
+/// 残しておく最近のサンプル数（モノラル）
+pub const RECENT_SAMPLES: usize = 4096;
+
+/// 最近のサンプルを共有のバッファへまとめて書き込む単位
+const RECENT_CHUNK: usize = 256;
+
+/// 1 チャンネルのレベル（0.0〜1.0 の振幅）
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct ChannelLevel {
+    pub peak: f32,
+    pub rms: f32,
+}
+
+/// 出力した最近のサンプル（左右を混ぜたもの、古い順）
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct RecentAudio {
+    pub samples: Vec<f32>,
+    pub sample_rate: u32,
+}
+
+/// 最近のサンプルの環状バッファ
+#[derive(Debug, Default)]
+struct RecentBuffer {
+    samples: Vec<f32>,
+    /// 次に書き込む位置（バッファが一杯になってから使う）
+    next: usize,
+    sample_rate: u32,
+}
+
+/// 左右チャンネルの最新のレベル
+#[derive(Debug, Default)]
+pub struct AudioLevels {
+    peak: [AtomicU32; 2],
+    rms: [AtomicU32; 2],
+    recent: Mutex<RecentBuffer>,
+}
+
+impl AudioLevels {
+    /// 最新のレベル（左、右）
+    pub fn get(&self) -> [ChannelLevel; 2] {
+        [0, 1].map(|channel| ChannelLevel {
+            peak: f32::from_bits(self.peak[channel].load(Ordering::Relaxed)),
+            rms: f32::from_bits(self.rms[channel].load(Ordering::Relaxed)),
+        })
+    }
+
+    fn set(&self, levels: [ChannelLevel; 2]) {
+        for (channel, level) in levels.iter().enumerate() {
+            self.peak[channel].store(level.peak.to_bits(), Ordering::Relaxed);
+            self.rms[channel].store(level.rms.to_bits(), Ordering::Relaxed);
+        }
+    }
+
+    /// 無音に戻す（一時停止・停止時）
+    pub fn reset(&self) {
+        self.set([ChannelLevel::default(); 2]);
+        if let Ok(mut recent) = self.recent.lock() {
+            recent.samples.clear();
+            recent.next = 0;
+        }
+    }
+
+    /// 最近のサンプル（最大 `RECENT_SAMPLES` 個）
+    pub fn recent(&self) -> RecentAudio {
+        let Ok(recent) = self.recent.lock() else {
+            return RecentAudio::default();
+        };
+        let (newer, older) = recent.samples.split_at(recent.next);
+        RecentAudio {
+            samples: [older, newer].concat(),
+            sample_rate: recent.sample_rate,
+        }
+    }
+
+    fn push_recent(&self, samples: &[f32], sample_rate: u32) {
+        let Ok(mut recent) = self.recent.lock() else {
+            return;
+        };
+        recent.sample_rate = sample_rate;
+        for &sample in samples {
+            if recent.samples.len() < RECENT_SAMPLES {
+                recent.samples.push(sample);
+            } else {
+                let next = recent.next;
+                recent.samples[next] = sample;
+                recent.next = (next + 1) % RECENT_SAMPLES;
+            }
+        }
+    }
+}
+
+/// インターリーブされたサンプルを集計し、一定間隔で `AudioLevels` を更新する
+pub(super) struct LevelMeter {
+    channels: usize,
+    sample_rate: u32,
+    /// 集計する 1 チャンネルあたりのサンプル数
+    window: usize,
+    peak: [f32; 2],
+    sum_squares: [f32; 2],
+    /// 現在の区間で受け取ったサンプル数（全チャンネル）
+    samples: usize,
+    /// 混ぜている途中の 1 フレームの合計
+    mix: f32,
+    /// まだ共有のバッファへ書き込んでいない、左右を混ぜたサンプル
+    pending: Vec<f32>,
+}
+
+impl LevelMeter {
+    pub(super) fn new(sample_rate: u32, channels: u16) -> Self {
+        Self {
+            channels: channels.max(1) as usize,
+            sample_rate,
+            window: (sample_rate / UPDATES_PER_SECOND).max(1) as usize,
+            peak: [0.0; 2],
+            sum_squares: [0.0; 2],
+            samples: 0,
+            mix: 0.0,
+            pending: Vec::with_capacity(RECENT_CHUNK),
+        }
+    }
+
+    /// 1 サンプルを加える。区間が終わったら `levels` を更新する
+    pub(super) fn push(&mut self, sample: f32, levels: &AudioLevels) {
+        // モノラルは左右に、3 チャンネル以上は先頭の 2 チャンネルだけを使う
+        let channel = self.samples % self.channels;
+        let targets: &[usize] = match (self.channels, channel) {
+            (1, _) => &[0, 1],
+            (_, 0) => &[0],
+            (_, 1) => &[1],
+            _ => &[],
+        };
+        for &target in targets {
+            self.peak[target] = self.peak[target].max(sample.abs());
+            self.sum_squares[target] += sample * sample;
+        }
+        if channel < 2 {
+            self.mix += sample;
+        }
+        if channel + 1 == self.channels {
+            self.pending.push(self.mix / self.channels.min(2) as f32);
+            self.mix = 0.0;
+            if self.pending.len() >= RECENT_CHUNK {
+                levels.push_recent(&self.pending, self.sample_rate);
+                self.pending.clear();
+            }
+        }
+
+        self.samples += 1;
+        if self.samples >= self.window * self.channels {
+            let frames = self.window as f32;
+            levels.set([0, 1].map(|target| ChannelLevel {
+                peak: self.peak[target].min(1.0),
+                rms: (self.sum_squares[target] / frames).sqrt().min(1.0),
+            }));
+            self.peak = [0.0; 2];
+            self.sum_squares = [0.0; 2];
+            self.samples = 0;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_stereo_levels() {
+        let levels = AudioLevels::default();
+        let mut meter = LevelMeter::new(100, 2);
+        // 左は振幅 0.5 の矩形波、右は無音
+        for i in 0..10 {
+            let left = if i % 2 == 0 { 0.5 } else { -0.5 };
+            meter.push(left, &levels);
+            meter.push(0.0, &levels);
+        }
+
+        let [left, right] = levels.get();
+        assert!((left.peak - 0.5).abs() < 1e-6);
+        assert!((left.rms - 0.5).abs() < 1e-6);
+        assert_eq!(right, ChannelLevel::default());
+    }
+
+    #[test]
+    fn test_mono_feeds_both_channels() {
+        let levels = AudioLevels::default();
+        let mut meter = LevelMeter::new(40, 1);
+        for _ in 0..4 {
+            meter.push(0.25, &levels);
+        }
+
+        let [left, right] = levels.get();
+        assert_eq!(left, right);
+        assert!((left.peak - 0.25).abs() < 1e-6);
+
+        levels.reset();
+        assert_eq!(levels.get(), [ChannelLevel::default(); 2]);
+    }
+
+    #[test]
+    fn test_recent_samples_mix_and_wrap() {
+        let levels = AudioLevels::default();
+        let mut meter = LevelMeter::new(48_000, 2);
+        for i in 0..RECENT_SAMPLES + RECENT_CHUNK {
+            meter.push(i as f32, &levels);
+            meter.push(i as f32 + 1.0, &levels);
+        }
+
+        let recent = levels.recent();
+        assert_eq!(recent.sample_rate, 48_000);
+        assert_eq!(recent.samples.len(), RECENT_SAMPLES);
+        // 古いものから並び、左右の平均になる
+        assert_eq!(recent.samples[0], RECENT_CHUNK as f32 + 0.5);
+        assert_eq!(
+            recent.samples[RECENT_SAMPLES - 1],
+            (RECENT_SAMPLES + RECENT_CHUNK - 1) as f32 + 0.5
+        );
+
+        levels.reset();
+        assert!(levels.recent().samples.is_empty());
+    }
+}
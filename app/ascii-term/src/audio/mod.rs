@@ -1,12 +1,22 @@
 //! オーディオ再生サブシステム
 //!
+//! - `backend`: 音声の出力先（rodio・cpal・外部プレイヤー）
 //! - `source`: デコードスレッドから PCM を供給する rodio `Source` アダプタ
 //! - `player`: 再生制御を担う `AudioPlayer`
+//! - `envelope`: 再生の開始・停止・シークでの音量の立ち上がりと立ち下がり
 //! - `decode_loop`: バックグラウンドのデコードループと診断
+//! - `levels`: 再生中の音声レベル（VU メーター用）と最近のサンプル（視覚化用）
+//! - `ring_buffer`: デコードスレッドと出力の間の固定長のリングバッファ
 
+mod backend;
 mod decode_loop;
+mod envelope;
+mod levels;
 mod player;
+mod ring_buffer;
 mod source;
 
+pub use backend::{AudioBackendKind, AudioOutput};
 pub use decode_loop::diagnose_audio_system;
+pub use levels::{ChannelLevel, RecentAudio};
 pub use player::AudioPlayer;
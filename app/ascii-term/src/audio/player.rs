@@ -1,34 +1,155 @@
 //! オーディオ再生の制御
 
-use std::sync::Arc;
-use std::sync::atomic::{AtomicBool, Ordering};
+use std::any::Any;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
 use std::thread;
+use std::time::Duration;
 
 use anyhow::Result;
-use crossbeam_channel::{Sender, unbounded};
-use rodio::{OutputStream, Sink};
+use rodio::{Sink, Source};
 
 use codec::MediaFile;
+use codec::source::AudioGenerator;
 
-use super::decode_loop::decode_audio_loop;
-use super::source::DirectAudioSource;
+use super::backend::AudioOutput;
+use super::decode_loop::{DecodeOptions, decode_audio_loop, generate_audio_loop};
+use super::envelope::GainEnvelope;
+use super::levels::{AudioLevels, ChannelLevel, RecentAudio};
+use super::ring_buffer::ring_buffer;
+use super::source::{DirectAudioSource, Fade};
+
+/// デコードしておく音声の長さ（出力までの遅延もこれで決まる）
+const DECODE_AHEAD: Duration = Duration::from_millis(300);
+
+/// 最初のトラックのどこから何サンプル出力したか（再生位置の計算用）
+struct TrackClock {
+    start: Duration,
+    played: Arc<AtomicU64>,
+    sample_rate: u32,
+    channels: u16,
+}
 
 pub struct AudioPlayer {
-    _stream: OutputStream,
+    /// 出力先（保持している間だけ再生が続く）
+    _output: Box<dyn Any>,
     sink: Sink,
     is_muted: Arc<AtomicBool>,
     original_volume: f32,
-    _audio_sender: Option<Sender<Vec<f32>>>,
-    decoder_thread: Option<thread::JoinHandle<()>>,
+    decoder_threads: Vec<thread::JoinHandle<()>>,
     stop_signal: Arc<AtomicBool>,
-    _is_finished: Arc<AtomicBool>,
+    queued_tracks: usize,
     sample_rate: u32,
+    /// 音声を遅らせる時間（ミリ秒、負の値で早める）
+    delay_ms: i64,
+    /// 再生中のサンプルのレベル
+    levels: Arc<AudioLevels>,
+    /// トラックの始まりと終わりをフェードする長さ
+    fade: Duration,
+    /// 再生の開始・一時停止・停止・シークの前後の音量
+    envelope: Arc<GainEnvelope>,
+    /// キューの先頭のトラックの再生位置（生成した音声では None）
+    track_clock: Option<TrackClock>,
+    /// 最初に開けなかった、または途中でデコードできなくなったトラックの番号
+    failed_track: Arc<Mutex<Option<usize>>>,
 }
 
 impl AudioPlayer {
-    pub fn new(file_path: &str) -> Result<Self> {
+    /// `delay_ms` だけ映像からずらして再生するプレイヤーを作成（負の値で早める）
+    ///
+    /// `fade` が 0 でなければ、各トラックの始まりと終わりをその長さだけフェードする。
+    /// 再生の開始・一時停止・停止・シークでは `ramp` の長さだけ音量を上げ下げする
+    pub fn new(
+        file_path: &str,
+        delay_ms: i64,
+        fade: Duration,
+        ramp: Duration,
+        output: &AudioOutput,
+    ) -> Result<Self> {
         println!("Initializing audio player for: {}", file_path);
 
+        let mut player = Self::with_empty_queue(ramp, output)?;
+        player.delay_ms = delay_ms;
+        player.fade = fade;
+        player.sample_rate = player.enqueue_from(file_path, Duration::ZERO, delay_ms)?;
+
+        println!("Audio player initialized successfully");
+        Ok(player)
+    }
+
+    /// 手続き的な音声ジェネレーター（テストトーンなど）を再生するプレイヤーを作成
+    pub fn from_generator(
+        generator: Box<dyn AudioGenerator>,
+        ramp: Duration,
+        output: &AudioOutput,
+    ) -> Result<Self> {
+        let mut player = Self::with_empty_queue(ramp, output)?;
+        let sample_rate = generator.sample_rate();
+        let channels = generator.channels();
+
+        let (producer, consumer) = ring_buffer(sample_rate, channels, DECODE_AHEAD);
+        player.sink.append(DirectAudioSource::new(
+            consumer,
+            sample_rate,
+            channels,
+            player.levels.clone(),
+            Fade::default(),
+            player.envelope.clone(),
+            Arc::default(),
+        ));
+
+        let stop_signal = player.stop_signal.clone();
+        player.decoder_threads.push(thread::spawn(move || {
+            generate_audio_loop(generator, producer, stop_signal);
+        }));
+        player.queued_tracks = 1;
+        player.sample_rate = sample_rate;
+
+        Ok(player)
+    }
+
+    /// 出力デバイスを開き、一時停止状態の空のキューを用意する
+    fn with_empty_queue(ramp: Duration, output: &AudioOutput) -> Result<Self> {
+        let backend = output.backend();
+        let (sink, output) = backend
+            .connect()
+            .map_err(|e| anyhow::anyhow!("{} audio backend: {:#}", backend.name(), e))?;
+        println!("Audio backend: {}", backend.name());
+
+        sink.set_volume(1.0);
+        sink.pause();
+
+        Ok(Self {
+            _output: output,
+            sink,
+            is_muted: Arc::new(AtomicBool::new(false)),
+            original_volume: 1.0,
+            decoder_threads: Vec::new(),
+            stop_signal: Arc::new(AtomicBool::new(false)),
+            queued_tracks: 0,
+            sample_rate: 0,
+            delay_ms: 0,
+            levels: Arc::new(AudioLevels::default()),
+            fade: Duration::ZERO,
+            envelope: Arc::new(GainEnvelope::new(ramp)),
+            track_clock: None,
+            failed_track: Arc::default(),
+        })
+    }
+
+    /// 現在のトラックの後ろに次のトラックを追加する
+    ///
+    /// rodio の `Sink` はキューに積まれたソースを隙間なく連続再生するため、
+    /// 再生中に次のトラックを追加しておけばギャップレス再生になる。
+    /// 戻り値はトラックのサンプルレート
+    pub fn enqueue(&mut self, file_path: &str) -> Result<u32> {
+        self.enqueue_from(file_path, Duration::ZERO, 0)
+    }
+
+    /// トラックを `start` の位置からデコードしてキューに追加する
+    ///
+    /// `delay_ms` が正なら先頭に無音を入れ、負ならその分だけ先の位置からデコードする
+    fn enqueue_from(&mut self, file_path: &str, start: Duration, delay_ms: i64) -> Result<u32> {
         let media_file = MediaFile::open(file_path)?;
         if !media_file.info.has_audio {
             return Err(anyhow::anyhow!("Media file has no audio stream"));
@@ -42,86 +163,138 @@ impl AudioPlayer {
             sample_rate, channels, media_file.info.duration
         );
 
-        let (_stream, stream_handle) = OutputStream::try_default()
-            .map_err(|e| anyhow::anyhow!("Failed to initialize audio stream: {}", e))?;
-
-        let sink = Sink::try_new(&stream_handle)
-            .map_err(|e| anyhow::anyhow!("Failed to create audio sink: {}", e))?;
+        // 書き込み側はデコードスレッドだけが保持する。EOF でスレッドが終了すると
+        // ソースは残りを再生し終えたところで終端する
+        let (producer, consumer) = ring_buffer(sample_rate, channels, DECODE_AHEAD);
 
-        let (audio_sender, audio_receiver) = unbounded();
-        let stop_signal = Arc::new(AtomicBool::new(false));
-        let is_finished = Arc::new(AtomicBool::new(false));
-
-        let audio_source =
-            DirectAudioSource::new(audio_receiver, sample_rate, channels, is_finished.clone());
-
-        sink.append(audio_source);
-        sink.set_volume(1.0);
-        sink.pause();
+        let delay = Duration::from_millis(delay_ms.unsigned_abs());
+        let start = if delay_ms < 0 { start + delay } else { start };
+        let remaining = media_file
+            .info
+            .duration
+            .map(|duration| duration.saturating_sub(start));
+        let fade = Fade::new(self.fade, remaining, sample_rate, channels);
+        let played = Arc::new(AtomicU64::new(0));
+        if self.queued_tracks == 0 {
+            self.track_clock = Some(TrackClock {
+                start,
+                played: played.clone(),
+                sample_rate,
+                channels,
+            });
+        }
+        let audio_source = DirectAudioSource::new(
+            consumer,
+            sample_rate,
+            channels,
+            self.levels.clone(),
+            fade,
+            self.envelope.clone(),
+            played,
+        );
+        if delay_ms > 0 {
+            self.sink.append(audio_source.delay(delay));
+        } else {
+            self.sink.append(audio_source);
+        }
 
-        let file_path_clone = file_path.to_string();
-        let decoder_stop_signal = stop_signal.clone();
-        let decoder_sender = audio_sender.clone();
-        let decoder_is_finished = is_finished.clone();
-        let expected_duration = media_file.info.duration;
+        let options = DecodeOptions {
+            file_path: file_path.to_string(),
+            sample_rate,
+            channels,
+            expected_duration: media_file.info.duration,
+            start_position: start,
+        };
+        let decoder_stop_signal = self.stop_signal.clone();
+        let failed_track = self.failed_track.clone();
+        let track = self.queued_tracks;
 
         let decoder_thread = thread::spawn(move || {
-            decode_audio_loop(
-                file_path_clone,
-                sample_rate,
-                channels,
-                decoder_sender,
-                decoder_stop_signal,
-                decoder_is_finished,
-                expected_duration,
-            );
+            if let Err(e) = decode_audio_loop(options, producer, decoder_stop_signal) {
+                eprintln!("{:#}", e);
+                if let Ok(mut failed_track) = failed_track.lock() {
+                    failed_track.get_or_insert(track);
+                }
+            }
         });
 
-        println!("Audio player initialized successfully");
-
-        Ok(Self {
-            _stream,
-            sink,
-            is_muted: Arc::new(AtomicBool::new(false)),
-            original_volume: 1.0,
-            _audio_sender: Some(audio_sender),
-            decoder_thread: Some(decoder_thread),
-            stop_signal,
-            _is_finished: is_finished,
-            sample_rate,
-        })
+        self.decoder_threads.push(decoder_thread);
+        self.queued_tracks += 1;
+        Ok(sample_rate)
     }
 
     pub fn play(&mut self) -> Result<()> {
         println!("Starting audio playback at {} Hz", self.sample_rate);
+        self.envelope.open();
         self.sink.play();
         Ok(())
     }
 
     pub fn pause(&mut self) -> Result<()> {
         println!("Pausing audio playback");
+        self.ramp_down();
         self.sink.pause();
+        self.levels.reset();
         Ok(())
     }
 
     pub fn resume(&mut self) -> Result<()> {
         println!("Resuming audio playback");
+        self.envelope.open();
         self.sink.play();
         Ok(())
     }
 
     pub fn stop(&mut self) -> Result<()> {
         println!("Stopping audio playback");
+        self.ramp_down();
         self.stop_signal.store(true, Ordering::Relaxed);
         self.sink.stop();
+        self.levels.reset();
+
+        for thread in self.decoder_threads.drain(..) {
+            let _ = thread.join();
+        }
+
+        Ok(())
+    }
+
+    /// 再生位置を移動する
+    ///
+    /// キューに積まれたトラックを破棄し、`file_path` を `position` から
+    /// デコードし直す。一時停止中であれば一時停止のまま。
+    /// 移動の前に音量を下げ、移動先から上げ直す
+    pub fn seek(&mut self, file_path: &str, position: Duration) -> Result<()> {
+        let was_playing = self.is_playing();
+        self.ramp_down();
 
-        if let Some(thread) = self.decoder_thread.take() {
+        self.stop_signal.store(true, Ordering::Relaxed);
+        self.sink.clear();
+        for thread in self.decoder_threads.drain(..) {
             let _ = thread.join();
         }
 
+        self.stop_signal = Arc::new(AtomicBool::new(false));
+        if let Ok(mut failed_track) = self.failed_track.lock() {
+            *failed_track = None;
+        }
+        self.queued_tracks = 0;
+        self.enqueue_from(file_path, position, self.delay_ms)?;
+
+        if was_playing {
+            self.envelope.open();
+            self.sink.play();
+        }
         Ok(())
     }
 
+    /// 再生中なら音量を 0 まで下げる（一時停止中や再生し終えた後は何もしない）
+    fn ramp_down(&self) {
+        if self.is_playing() && !self.sink.empty() {
+            self.envelope.close_and_wait();
+        }
+    }
+
     pub fn mute(&mut self) -> Result<()> {
         println!("Muting audio");
         self.is_muted.store(true, Ordering::Relaxed);
@@ -151,12 +324,80 @@ impl AudioPlayer {
     pub fn is_muted(&self) -> bool {
         self.is_muted.load(Ordering::Relaxed)
     }
+
+    /// ミュートしていないときの音量（0.0〜1.0）
+    pub fn volume(&self) -> f32 {
+        self.original_volume
+    }
+
+    /// 音量を変える（ミュート中は解除したときに反映する）
+    pub fn set_volume(&mut self, volume: f32) {
+        self.original_volume = volume.clamp(0.0, 1.0);
+        if !self.is_muted() {
+            self.sink.set_volume(self.original_volume);
+        }
+    }
+
+    /// キュー内のすべてのトラックを再生し終えたかどうか
+    pub fn is_finished(&self) -> bool {
+        self.sink.empty()
+    }
+
+    /// 再生中のトラックの番号（`enqueue` した順、0 始まり）
+    pub fn current_track(&self) -> usize {
+        self.queued_tracks.saturating_sub(self.sink.len().max(1))
+    }
+
+    /// 映像が表示しているべき位置（最初のトラック、またはシーク先のトラックの再生位置）
+    ///
+    /// 出力したサンプル数から求め、`delay_ms` の分だけずらす。まだ音が出ていない場合や
+    /// 再生し終えた後は None
+    pub fn position(&self) -> Option<Duration> {
+        let clock = self.track_clock.as_ref()?;
+        let samples = clock.played.load(Ordering::Relaxed);
+        if samples == 0 || self.sink.empty() {
+            return None;
+        }
+        let played = Duration::from_secs_f64(
+            samples as f64 / (clock.sample_rate as f64 * clock.channels as f64),
+        );
+        let position = clock.start + played;
+        let delay = Duration::from_millis(self.delay_ms.unsigned_abs());
+        Some(if self.delay_ms < 0 {
+            position.saturating_sub(delay)
+        } else {
+            position + delay
+        })
+    }
+
+    /// 左右チャンネルの最新のレベル
+    pub fn levels(&self) -> [ChannelLevel; 2] {
+        self.levels.get()
+    }
+
+    /// 最近出力したサンプル（視覚化用）
+    pub fn recent_audio(&self) -> RecentAudio {
+        self.levels.recent()
+    }
+
+    /// 最初に開けなかった、または途中でデコードできなくなったトラックの番号
+    /// （`enqueue` した順、0 始まり）
+    ///
+    /// `seek` でキューを積み直すと None に戻る
+    pub fn failed_track(&self) -> Option<usize> {
+        self.failed_track.lock().ok().and_then(|track| *track)
+    }
+
+    /// 再生待ちのトラック数（再生中のトラックを除く）
+    pub fn pending_tracks(&self) -> usize {
+        self.sink.len().saturating_sub(1)
+    }
 }
 
 impl Drop for AudioPlayer {
     fn drop(&mut self) {
         self.stop_signal.store(true, Ordering::Relaxed);
-        if let Some(thread) = self.decoder_thread.take() {
+        for thread in self.decoder_threads.drain(..) {
             let _ = thread.join();
         }
     }
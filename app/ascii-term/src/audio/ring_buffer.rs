@@ -0,0 +1,192 @@
+//! デコードスレッドから出力デバイスへ PCM を渡す固定長のリングバッファ
+//!
+//! 書き込み側（デコードスレッド）と読み出し側（rodio の `Source`）が 1 つずつの
+//! ロックフリーなキュー。容量をミリ秒で決めるため、先読みする量（＝遅延）が
+//! 一定になり、一時停止中はバッファが埋まった所で書き込み側が待つ。
+//! 移動するときは読み出し側を捨てるだけで、書き込み側はすぐに止まる
+
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, AtomicU32, AtomicUsize, Ordering};
+use std::time::Duration;
+
+struct Shared {
+    /// f32 のビット列
+    samples: Box<[AtomicU32]>,
+    /// 次に読む位置（読み出し側だけが進める）
+    read: AtomicUsize,
+    /// 次に書く位置（書き込み側だけが進める）
+    write: AtomicUsize,
+    /// 書き込み側がなくなった（これ以上サンプルは来ない）
+    producer_closed: AtomicBool,
+    /// 読み出し側がなくなった（書いても再生されない）
+    consumer_closed: AtomicBool,
+}
+
+impl Shared {
+    fn len(&self) -> usize {
+        self.write.load(Ordering::Acquire) - self.read.load(Ordering::Acquire)
+    }
+}
+
+/// `duration` 分のサンプルを持てるリングバッファを作る
+pub(super) fn ring_buffer(
+    sample_rate: u32,
+    channels: u16,
+    duration: Duration,
+) -> (Producer, Consumer) {
+    let capacity = (sample_rate as f64 * channels as f64 * duration.as_secs_f64()).ceil() as usize;
+    with_capacity(capacity.max(channels.max(1) as usize))
+}
+
+fn with_capacity(capacity: usize) -> (Producer, Consumer) {
+    let shared = Arc::new(Shared {
+        samples: (0..capacity).map(|_| AtomicU32::new(0)).collect(),
+        read: AtomicUsize::new(0),
+        write: AtomicUsize::new(0),
+        producer_closed: AtomicBool::new(false),
+        consumer_closed: AtomicBool::new(false),
+    });
+    (
+        Producer {
+            shared: shared.clone(),
+        },
+        Consumer { shared },
+    )
+}
+
+/// 書き込み側。破棄すると読み出し側は残りを読み終えたところで終端する
+pub(super) struct Producer {
+    shared: Arc<Shared>,
+}
+
+impl Producer {
+    /// 空いている分だけ書き込み、書き込んだサンプル数を返す
+    pub(super) fn push(&self, samples: &[f32]) -> usize {
+        let shared = &self.shared;
+        let capacity = shared.samples.len();
+        let write = shared.write.load(Ordering::Relaxed);
+        let free = capacity - (write - shared.read.load(Ordering::Acquire));
+        let count = samples.len().min(free);
+
+        for (offset, sample) in samples[..count].iter().enumerate() {
+            shared.samples[(write + offset) % capacity].store(sample.to_bits(), Ordering::Relaxed);
+        }
+        shared.write.store(write + count, Ordering::Release);
+        count
+    }
+
+    /// すべて書き込むまで待つ。`stop` が立つか読み出し側がなくなったら false
+    pub(super) fn push_all(&self, mut samples: &[f32], stop: &AtomicBool) -> bool {
+        loop {
+            samples = &samples[self.push(samples)..];
+            if samples.is_empty() {
+                return true;
+            }
+            if stop.load(Ordering::Relaxed) || self.is_abandoned() {
+                return false;
+            }
+            std::thread::sleep(Duration::from_millis(2));
+        }
+    }
+
+    /// 読み出し側がなくなったかどうか
+    pub(super) fn is_abandoned(&self) -> bool {
+        self.shared.consumer_closed.load(Ordering::Acquire)
+    }
+}
+
+impl Drop for Producer {
+    fn drop(&mut self) {
+        self.shared.producer_closed.store(true, Ordering::Release);
+    }
+}
+
+/// 読み出し側
+pub(super) struct Consumer {
+    shared: Arc<Shared>,
+}
+
+impl Consumer {
+    /// 次のサンプル。バッファが空なら None
+    pub(super) fn pop(&self) -> Option<f32> {
+        let shared = &self.shared;
+        let read = shared.read.load(Ordering::Relaxed);
+        if read == shared.write.load(Ordering::Acquire) {
+            return None;
+        }
+        let bits = shared.samples[read % shared.samples.len()].load(Ordering::Relaxed);
+        shared.read.store(read + 1, Ordering::Release);
+        Some(f32::from_bits(bits))
+    }
+
+    /// 書き込み側がなくなり、すべて読み終えたかどうか
+    pub(super) fn is_drained(&self) -> bool {
+        // 書き込み側が閉じた後に残りがないことを確かめる（順序が逆だと取りこぼす）
+        self.shared.producer_closed.load(Ordering::Acquire) && self.shared.len() == 0
+    }
+}
+
+impl Drop for Consumer {
+    fn drop(&mut self) {
+        self.shared.consumer_closed.store(true, Ordering::Release);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_push_pop_wraps_around() {
+        let (producer, consumer) = with_capacity(4);
+        assert_eq!(producer.push(&[1.0, 2.0, 3.0]), 3);
+        assert_eq!(consumer.pop(), Some(1.0));
+        assert_eq!(consumer.pop(), Some(2.0));
+        // 残り 1 つなので、3 つ空いている
+        assert_eq!(producer.push(&[4.0, 5.0, 6.0, 7.0]), 3);
+        let rest: Vec<f32> = std::iter::from_fn(|| consumer.pop()).collect();
+        assert_eq!(rest, [3.0, 4.0, 5.0, 6.0]);
+        assert_eq!(consumer.pop(), None);
+    }
+
+    #[test]
+    fn test_capacity_from_duration() {
+        let (producer, _consumer) = ring_buffer(1000, 2, Duration::from_millis(100));
+        assert_eq!(producer.push(&[0.0; 300]), 200);
+    }
+
+    #[test]
+    fn test_close_semantics() {
+        let (producer, consumer) = with_capacity(4);
+        producer.push(&[0.5]);
+        drop(producer);
+        assert!(!consumer.is_drained());
+        assert_eq!(consumer.pop(), Some(0.5));
+        assert!(consumer.is_drained());
+
+        let (producer, consumer) = with_capacity(2);
+        drop(consumer);
+        assert!(producer.is_abandoned());
+        assert!(!producer.push_all(&[0.0; 4], &AtomicBool::new(false)));
+    }
+
+    #[test]
+    fn test_transfer_between_threads() {
+        let (producer, consumer) = with_capacity(64);
+        let writer = std::thread::spawn(move || {
+            let samples: Vec<f32> = (0..10_000).map(|i| i as f32).collect();
+            assert!(producer.push_all(&samples, &AtomicBool::new(false)));
+        });
+
+        let mut received = Vec::new();
+        while !consumer.is_drained() {
+            match consumer.pop() {
+                Some(sample) => received.push(sample),
+                None => std::thread::yield_now(),
+            }
+        }
+        writer.join().unwrap();
+        assert_eq!(received.len(), 10_000);
+        assert!(received.iter().enumerate().all(|(i, &s)| s == i as f32));
+    }
+}
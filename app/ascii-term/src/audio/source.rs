@@ -1,41 +1,109 @@
 //! デコードスレッドから PCM をストリーミングする rodio `Source` アダプタ
 
 use std::sync::Arc;
-use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::time::Duration;
 
-use crossbeam_channel::{Receiver, RecvTimeoutError};
 use rodio::Source;
 
+use super::envelope::GainEnvelope;
+use super::levels::{AudioLevels, LevelMeter};
+use super::ring_buffer::Consumer;
+
+/// 途切れを何回ごとにログへ出すか
+const UNDERRUN_LOG_INTERVAL: usize = 100;
+
+/// トラックの始まりと終わりの音量の変化（位置はサンプル数で数える）
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub(super) struct Fade {
+    fade_in: u64,
+    /// フェードアウトを始める位置（長さが分からなければ None）
+    fade_out_start: Option<u64>,
+    fade_out: u64,
+}
+
+impl Fade {
+    /// 始まりと終わりを `length` ずつフェードする。`remaining` はトラックの残りの長さ
+    pub(super) fn new(
+        length: Duration,
+        remaining: Option<Duration>,
+        sample_rate: u32,
+        channels: u16,
+    ) -> Self {
+        let samples = |duration: Duration| {
+            (duration.as_secs_f64() * sample_rate as f64) as u64 * channels as u64
+        };
+        let length = samples(length);
+        Self {
+            fade_in: length,
+            fade_out_start: remaining.map(|remaining| samples(remaining).saturating_sub(length)),
+            fade_out: length,
+        }
+    }
+
+    /// `position` 番目のサンプルに掛ける音量
+    fn gain(&self, position: u64) -> f32 {
+        let mut gain = 1.0;
+        if position < self.fade_in {
+            gain = position as f32 / self.fade_in as f32;
+        }
+        if let Some(start) = self.fade_out_start
+            && position >= start
+            && self.fade_out > 0
+        {
+            gain *= 1.0 - ((position - start) as f32 / self.fade_out as f32).min(1.0);
+        }
+        gain
+    }
+}
+
 pub(super) struct DirectAudioSource {
-    receiver: Receiver<Vec<f32>>,
+    consumer: Consumer,
     sample_rate: u32,
     channels: u16,
-    current_data: Vec<f32>,
-    position: usize,
+    /// バッファが空になった回数と、空の状態が続いているかどうか
     buffer_underrun_count: usize,
-    is_finished: Arc<AtomicBool>,
+    in_underrun: bool,
     total_samples_played: u64,
+    levels: Arc<AudioLevels>,
+    meter: LevelMeter,
+    fade: Fade,
+    /// 再生状態の変わり目の音量（同じキューのソースで共有）
+    envelope: Arc<GainEnvelope>,
+    envelope_step: f32,
+    /// 出力したサンプル数（再生位置を知るために `AudioPlayer` と共有）
+    played: Arc<AtomicU64>,
 }
 
 impl DirectAudioSource {
     pub(super) fn new(
-        receiver: Receiver<Vec<f32>>,
+        consumer: Consumer,
         sample_rate: u32,
         channels: u16,
-        is_finished: Arc<AtomicBool>,
+        levels: Arc<AudioLevels>,
+        fade: Fade,
+        envelope: Arc<GainEnvelope>,
+        played: Arc<AtomicU64>,
     ) -> Self {
         Self {
-            receiver,
+            consumer,
             sample_rate,
             channels,
-            current_data: Vec::new(),
-            position: 0,
             buffer_underrun_count: 0,
-            is_finished,
+            in_underrun: false,
             total_samples_played: 0,
+            levels,
+            meter: LevelMeter::new(sample_rate, channels),
+            fade,
+            envelope_step: envelope.step(sample_rate, channels),
+            envelope,
+            played,
         }
     }
+
+    fn played_seconds(&self) -> f64 {
+        self.total_samples_played as f64 / (self.sample_rate as f64 * self.channels as f64)
+    }
 }
 
 impl Source for DirectAudioSource {
@@ -60,57 +128,68 @@ impl Iterator for DirectAudioSource {
     type Item = f32;
 
     fn next(&mut self) -> Option<Self::Item> {
-        if self.position >= self.current_data.len() {
-            match self.receiver.recv_timeout(Duration::from_millis(500)) {
-                Ok(data) => {
-                    self.current_data = data;
-                    self.position = 0;
-                    self.buffer_underrun_count = 0;
-                }
-                Err(RecvTimeoutError::Timeout) => {
-                    if self.is_finished.load(Ordering::Relaxed) {
-                        if let Ok(data) = self.receiver.try_recv() {
-                            self.current_data = data;
-                            self.position = 0;
-                        } else {
-                            println!(
-                                "DirectAudioSource: Stream ended, played {:.1}s",
-                                self.total_samples_played as f64
-                                    / (self.sample_rate as f64 * self.channels as f64)
-                            );
-                            return None;
-                        }
-                    } else {
-                        self.buffer_underrun_count += 1;
-                        if self.buffer_underrun_count.is_multiple_of(200) {
-                            let played_seconds = self.total_samples_played as f64
-                                / (self.sample_rate as f64 * self.channels as f64);
-                            println!(
-                                "Audio underrun at {:.1}s, waiting for more data...",
-                                played_seconds
-                            );
-                        }
-                        return Some(0.0);
-                    }
-                }
-                Err(RecvTimeoutError::Disconnected) => {
-                    println!(
-                        "DirectAudioSource: Disconnected after {:.1}s",
-                        self.total_samples_played as f64
-                            / (self.sample_rate as f64 * self.channels as f64)
-                    );
-                    return None;
-                }
-            }
+        if let Some(sample) = self.consumer.pop() {
+            self.in_underrun = false;
+            let sample = sample * self.fade.gain(self.total_samples_played);
+            let sample = self.envelope.apply(sample, self.envelope_step);
+            self.total_samples_played += 1;
+            self.played
+                .store(self.total_samples_played, Ordering::Relaxed);
+            self.meter.push(sample, &self.levels);
+            return Some(sample);
         }
 
-        if self.position < self.current_data.len() {
-            let sample = self.current_data[self.position];
-            self.position += 1;
-            self.total_samples_played += 1;
-            Some(sample)
-        } else {
-            Some(0.0)
+        if self.consumer.is_drained() {
+            println!(
+                "DirectAudioSource: Stream ended, played {:.1}s",
+                self.played_seconds()
+            );
+            return None;
         }
+
+        // デコードが追いつかない間は、出力を止めずに無音で埋める（音量の変化は進める）
+        self.envelope.apply(0.0, self.envelope_step);
+        if !self.in_underrun {
+            self.in_underrun = true;
+            self.buffer_underrun_count += 1;
+            if self
+                .buffer_underrun_count
+                .is_multiple_of(UNDERRUN_LOG_INTERVAL)
+            {
+                println!(
+                    "Audio underrun at {:.1}s ({} times), waiting for more data...",
+                    self.played_seconds(),
+                    self.buffer_underrun_count
+                );
+            }
+        }
+        Some(0.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fade_gain() {
+        // 1 秒のトラックの始まりと終わりを 0.25 秒ずつ（1 チャンネル 100 Hz）
+        let fade = Fade::new(
+            Duration::from_millis(250),
+            Some(Duration::from_secs(1)),
+            100,
+            1,
+        );
+        let gains: Vec<f32> = [0, 10, 50, 75, 85, 120]
+            .iter()
+            .map(|&position| (fade.gain(position) * 100.0).round() / 100.0)
+            .collect();
+        assert_eq!(gains, [0.0, 0.4, 1.0, 1.0, 0.6, 0.0]);
+
+        // フェードしない場合と、長さの分からないトラック
+        assert_eq!(Fade::default().gain(0), 1.0);
+        let live = Fade::new(Duration::from_millis(250), None, 100, 2);
+        assert_eq!(live.gain(25), 0.5);
+        assert_eq!(live.gain(1_000_000), 1.0);
     }
 }
@@ -0,0 +1,171 @@
+//! 手続き的なソースで使う 3x5 のビットマップフォント
+//!
+//! 大きな文字をラスターに描き、レンダラーの文字マップで ASCII アートにする
+//! （figlet のような見た目になる）。英数字と一部の記号だけを持ち、
+//! それ以外の文字は空白として描く
+
+use codec::source::Raster;
+
+pub const GLYPH_WIDTH: u32 = 3;
+pub const GLYPH_HEIGHT: u32 = 5;
+
+/// 文字セルは縦長なので、横方向は縦の 2 倍に引き伸ばして描く
+const CELL_ASPECT: u32 = 2;
+
+/// 3x5 のビットマップ。各行の下位 3 ビットが左から右のピクセル
+pub fn glyph(ch: char) -> Option<[u8; 5]> {
+    let bits = match ch.to_ascii_uppercase() {
+        ' ' => [0; 5],
+        '0' => [0b111, 0b101, 0b101, 0b101, 0b111],
+        '1' => [0b010, 0b110, 0b010, 0b010, 0b111],
+        '2' => [0b111, 0b001, 0b111, 0b100, 0b111],
+        '3' => [0b111, 0b001, 0b111, 0b001, 0b111],
+        '4' => [0b101, 0b101, 0b111, 0b001, 0b001],
+        '5' => [0b111, 0b100, 0b111, 0b001, 0b111],
+        '6' => [0b111, 0b100, 0b111, 0b101, 0b111],
+        '7' => [0b111, 0b001, 0b010, 0b010, 0b010],
+        '8' => [0b111, 0b101, 0b111, 0b101, 0b111],
+        '9' => [0b111, 0b101, 0b111, 0b001, 0b111],
+        'A' => [0b010, 0b101, 0b111, 0b101, 0b101],
+        'B' => [0b110, 0b101, 0b110, 0b101, 0b110],
+        'C' => [0b111, 0b100, 0b100, 0b100, 0b111],
+        'D' => [0b110, 0b101, 0b101, 0b101, 0b110],
+        'E' => [0b111, 0b100, 0b111, 0b100, 0b111],
+        'F' => [0b111, 0b100, 0b111, 0b100, 0b100],
+        'G' => [0b111, 0b100, 0b101, 0b101, 0b111],
+        'H' => [0b101, 0b101, 0b111, 0b101, 0b101],
+        'I' => [0b111, 0b010, 0b010, 0b010, 0b111],
+        'J' => [0b001, 0b001, 0b001, 0b101, 0b111],
+        'K' => [0b101, 0b101, 0b110, 0b101, 0b101],
+        'L' => [0b100, 0b100, 0b100, 0b100, 0b111],
+        'M' => [0b101, 0b111, 0b111, 0b101, 0b101],
+        'N' => [0b110, 0b101, 0b101, 0b101, 0b101],
+        'O' => [0b010, 0b101, 0b101, 0b101, 0b010],
+        'P' => [0b111, 0b101, 0b111, 0b100, 0b100],
+        'Q' => [0b010, 0b101, 0b101, 0b110, 0b011],
+        'R' => [0b110, 0b101, 0b110, 0b101, 0b101],
+        'S' => [0b111, 0b100, 0b111, 0b001, 0b111],
+        'T' => [0b111, 0b010, 0b010, 0b010, 0b010],
+        'U' => [0b101, 0b101, 0b101, 0b101, 0b111],
+        'V' => [0b101, 0b101, 0b101, 0b101, 0b010],
+        'W' => [0b101, 0b101, 0b111, 0b111, 0b101],
+        'X' => [0b101, 0b101, 0b010, 0b101, 0b101],
+        'Y' => [0b101, 0b101, 0b010, 0b010, 0b010],
+        'Z' => [0b111, 0b001, 0b010, 0b100, 0b111],
+        ':' => [0b000, 0b010, 0b000, 0b010, 0b000],
+        '-' => [0b000, 0b000, 0b111, 0b000, 0b000],
+        '_' => [0b000, 0b000, 0b000, 0b000, 0b111],
+        '.' => [0b000, 0b000, 0b000, 0b000, 0b010],
+        ',' => [0b000, 0b000, 0b000, 0b010, 0b100],
+        '!' => [0b010, 0b010, 0b010, 0b000, 0b010],
+        '?' => [0b111, 0b001, 0b010, 0b000, 0b010],
+        '\'' => [0b010, 0b010, 0b000, 0b000, 0b000],
+        '/' => [0b001, 0b001, 0b010, 0b100, 0b100],
+        '(' => [0b001, 0b010, 0b010, 0b010, 0b001],
+        ')' => [0b100, 0b010, 0b010, 0b010, 0b100],
+        '&' => [0b010, 0b101, 0b010, 0b101, 0b011],
+        '+' => [0b000, 0b010, 0b111, 0b010, 0b000],
+        '=' => [0b000, 0b111, 0b000, 0b111, 0b000],
+        '*' => [0b101, 0b010, 0b111, 0b010, 0b101],
+        '#' => [0b101, 0b111, 0b101, 0b111, 0b101],
+        '%' => [0b101, 0b001, 0b010, 0b100, 0b101],
+        '@' => [0b111, 0b101, 0b111, 0b100, 0b111],
+        _ => return None,
+    };
+    Some(bits)
+}
+
+/// 文字をこのフォントで描けるか
+pub fn has_glyph(ch: char) -> bool {
+    glyph(ch).is_some()
+}
+
+/// `scale` 倍で描いたときの文字列の大きさ
+pub fn text_size(text: &str, scale: u32) -> (u32, u32) {
+    let count = text.chars().count() as u32;
+    let width = (count * (GLYPH_WIDTH + 1)).saturating_sub(1) * scale * CELL_ASPECT;
+    (width, GLYPH_HEIGHT * scale)
+}
+
+/// 幅 `width` に 1 倍で収まる文字数
+pub fn chars_per_line(width: u32) -> usize {
+    ((width / CELL_ASPECT + 1) / (GLYPH_WIDTH + 1)) as usize
+}
+
+pub fn draw_text(raster: &mut Raster, text: &str, x: i32, y: i32, scale: u32, color: [u8; 3]) {
+    let pixel_width = scale * CELL_ASPECT;
+    for (index, ch) in text.chars().enumerate() {
+        let origin_x = x + (index as u32 * (GLYPH_WIDTH + 1) * pixel_width) as i32;
+        let Some(bits) = glyph(ch) else {
+            continue;
+        };
+        for (row, bits) in bits.iter().enumerate() {
+            for col in 0..GLYPH_WIDTH {
+                if bits & (1 << (GLYPH_WIDTH - 1 - col)) != 0 {
+                    raster.fill_rect(
+                        origin_x + (col * pixel_width) as i32,
+                        y + (row as u32 * scale) as i32,
+                        pixel_width,
+                        scale,
+                        color,
+                    );
+                }
+            }
+        }
+    }
+}
+
+/// 単語の区切りで `max_chars` 文字ずつの行に分ける（長すぎる単語は途中で切る）
+pub fn wrap(text: &str, max_chars: usize) -> Vec<String> {
+    let max_chars = max_chars.max(1);
+    let mut lines: Vec<String> = Vec::new();
+    let mut line = String::new();
+
+    for word in text.split_whitespace() {
+        let mut word: Vec<char> = word.chars().collect();
+        loop {
+            let line_len = line.chars().count();
+            let needed = if line.is_empty() { 0 } else { line_len + 1 };
+            if needed + word.len() <= max_chars {
+                if !line.is_empty() {
+                    line.push(' ');
+                }
+                line.extend(word);
+                break;
+            }
+            if !line.is_empty() {
+                lines.push(std::mem::take(&mut line));
+                continue;
+            }
+            let rest = word.split_off(max_chars);
+            lines.push(word.into_iter().collect());
+            word = rest;
+        }
+    }
+    if !line.is_empty() {
+        lines.push(line);
+    }
+    lines
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_text_size_and_line_width() {
+        assert_eq!(text_size("AB", 1), (14, 5));
+        assert_eq!(text_size("AB", 2), (28, 10));
+        assert_eq!(chars_per_line(14), 2);
+        assert_eq!(chars_per_line(13), 1);
+        assert!("Track 01 - Intro!".chars().all(has_glyph));
+        assert!(!has_glyph('曲'));
+    }
+
+    #[test]
+    fn test_wrap() {
+        assert_eq!(wrap("one two three", 7), ["one two", "three"]);
+        assert_eq!(wrap("abcdefghij", 4), ["abcd", "efgh", "ij"]);
+        assert_eq!(wrap("  ", 4), Vec::<String>::new());
+    }
+}
@@ -0,0 +1,410 @@
+//! ターミナルとフォントに合わせた色の補正（チャンネルごとのガンマと黒レベル）
+//!
+//! `ascii_term calibrate` でテスト用のグラデーションを見ながら調整し、設定ファイルの
+//! `[calibration]` に保存する。再生時は出力する文字色・背景色をこの補正曲線に通す
+
+use std::io::{Write, stdout};
+
+use anyhow::Result;
+use crossterm::cursor::{Hide, Show};
+use crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+use crossterm::execute;
+use crossterm::terminal::{self, Clear, ClearType, EnterAlternateScreen, LeaveAlternateScreen};
+use serde::Deserialize;
+
+use crate::ansi::{self, ColorMode};
+use crate::canvas::{TextCanvas, truncate};
+use crate::renderer::{Cell, Rgb};
+
+/// 調整できるガンマの範囲
+const MIN_GAMMA: f32 = 0.25;
+const MAX_GAMMA: f32 = 4.0;
+/// ↑/↓ 1 回で変えるガンマ
+const GAMMA_STEP: f32 = 0.05;
+/// 黒レベルの上限（これより明るい黒は補正ではなく表示の問題）
+const MAX_BLACK_POINT: u8 = 64;
+
+/// グラデーションの段数
+const GRADIENT_STEPS: usize = 32;
+/// 黒レベルを見るパッチの数と、パッチごとの明るさの差
+const BLACK_PATCHES: usize = 16;
+const BLACK_PATCH_STEP: u8 = 2;
+/// リニアで 50% の明るさを sRGB で表した値（縞模様と並べて見比べる）
+const MID_GRAY: u8 = 188;
+/// 縞模様と単色のパッチの幅（文字数）
+const GAMMA_PATCH_WIDTH: usize = 6;
+
+const TEXT_COLOR: Rgb = [220, 220, 220];
+const SELECTED_COLOR: Rgb = [255, 200, 64];
+
+/// チャンネル（R・G・B）ごとの補正値
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Calibration {
+    /// 1.0 より大きいと中間の明るさを持ち上げる
+    pub gamma: [f32; 3],
+    /// 0 の入力を出力する明るさ（これより暗い色がつぶれるターミナル向け）
+    pub black_point: [u8; 3],
+}
+
+impl Default for Calibration {
+    fn default() -> Self {
+        Self {
+            gamma: [1.0; 3],
+            black_point: [0; 3],
+        }
+    }
+}
+
+impl Calibration {
+    /// 補正しない設定かどうか
+    pub fn is_identity(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// 範囲外の値を拒否する
+    pub fn validate(&self) -> Result<()> {
+        if let Some(gamma) = self
+            .gamma
+            .iter()
+            .find(|gamma| !(MIN_GAMMA..=MAX_GAMMA).contains(*gamma))
+        {
+            anyhow::bail!(
+                "gamma must be between {} and {} (got {})",
+                MIN_GAMMA,
+                MAX_GAMMA,
+                gamma
+            );
+        }
+        if let Some(black) = self.black_point.iter().find(|&&b| b > MAX_BLACK_POINT) {
+            anyhow::bail!(
+                "black_point must be between 0 and {} (got {})",
+                MAX_BLACK_POINT,
+                black
+            );
+        }
+        Ok(())
+    }
+
+    /// 設定ファイルに書く `[calibration]` の表
+    pub fn to_toml(self) -> String {
+        let [r, g, b] = self.gamma;
+        let [br, bg, bb] = self.black_point;
+        format!(
+            "[calibration]\ngamma = [{:.2}, {:.2}, {:.2}]\nblack_point = [{}, {}, {}]\n",
+            r, g, b, br, bg, bb
+        )
+    }
+
+    /// 再生時に使う補正曲線（補正しない設定なら None）
+    pub fn transfer_curve(&self) -> Option<TransferCurve> {
+        (!self.is_identity()).then(|| TransferCurve::new(self))
+    }
+}
+
+/// 8 ビットの値ごとに引いておいた補正曲線
+#[derive(Debug, Clone, PartialEq)]
+pub struct TransferCurve {
+    tables: [[u8; 256]; 3],
+}
+
+impl TransferCurve {
+    /// `black + (255 - black) * (x / 255)^(1 / gamma)`
+    pub fn new(calibration: &Calibration) -> Self {
+        let mut tables = [[0; 256]; 3];
+        for (channel, table) in tables.iter_mut().enumerate() {
+            let gamma = calibration.gamma[channel];
+            let black = calibration.black_point[channel] as f32;
+            for (value, out) in table.iter_mut().enumerate() {
+                let x = (value as f32 / 255.0).powf(1.0 / gamma);
+                *out = (black + (255.0 - black) * x).round() as u8;
+            }
+        }
+        Self { tables }
+    }
+
+    pub fn apply(&self, [r, g, b]: Rgb) -> Rgb {
+        [
+            self.tables[0][r as usize],
+            self.tables[1][g as usize],
+            self.tables[2][b as usize],
+        ]
+    }
+}
+
+/// 調整中のチャンネル（`All` は 3 つをまとめて動かす）
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Channel {
+    Red,
+    Green,
+    Blue,
+    All,
+}
+
+impl Channel {
+    const ORDER: [Channel; 4] = [Channel::Red, Channel::Green, Channel::Blue, Channel::All];
+
+    fn name(self) -> &'static str {
+        match self {
+            Channel::Red => "Red",
+            Channel::Green => "Green",
+            Channel::Blue => "Blue",
+            Channel::All => "Gray (all)",
+        }
+    }
+
+    fn indices(self) -> &'static [usize] {
+        match self {
+            Channel::Red => &[0],
+            Channel::Green => &[1],
+            Channel::Blue => &[2],
+            Channel::All => &[0, 1, 2],
+        }
+    }
+
+    /// このチャンネルだけ `level` にした色
+    fn color(self, level: u8) -> Rgb {
+        let mut rgb = [0; 3];
+        for &index in self.indices() {
+            rgb[index] = level;
+        }
+        rgb
+    }
+}
+
+/// キー操作の結果
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Outcome {
+    Continue,
+    Save,
+    Cancel,
+}
+
+/// 調整画面の状態
+#[derive(Debug, Clone)]
+struct Calibrator {
+    calibration: Calibration,
+    selected: usize,
+}
+
+impl Calibrator {
+    fn channel(&self) -> Channel {
+        Channel::ORDER[self.selected]
+    }
+
+    fn adjust_gamma(&mut self, delta: f32) {
+        for &index in self.channel().indices() {
+            let gamma = self.calibration.gamma[index] + delta;
+            // 0.05 刻みの表示とずれないように丸める
+            self.calibration.gamma[index] =
+                ((gamma * 100.0).round() / 100.0).clamp(MIN_GAMMA, MAX_GAMMA);
+        }
+    }
+
+    fn adjust_black_point(&mut self, delta: i16) {
+        for &index in self.channel().indices() {
+            let black = self.calibration.black_point[index] as i16 + delta;
+            self.calibration.black_point[index] = black.clamp(0, MAX_BLACK_POINT as i16) as u8;
+        }
+    }
+
+    fn handle_key(&mut self, code: KeyCode, modifiers: KeyModifiers) -> Outcome {
+        let count = Channel::ORDER.len();
+        match code {
+            KeyCode::Esc | KeyCode::Char('q') => return Outcome::Cancel,
+            KeyCode::Char('c') if modifiers.contains(KeyModifiers::CONTROL) => {
+                return Outcome::Cancel;
+            }
+            KeyCode::Enter | KeyCode::Char('s') => return Outcome::Save,
+            KeyCode::Tab | KeyCode::Right => self.selected = (self.selected + 1) % count,
+            KeyCode::BackTab | KeyCode::Left => self.selected = (self.selected + count - 1) % count,
+            KeyCode::Up => self.adjust_gamma(GAMMA_STEP),
+            KeyCode::Down => self.adjust_gamma(-GAMMA_STEP),
+            KeyCode::PageUp | KeyCode::Char('+') => self.adjust_black_point(1),
+            KeyCode::PageDown | KeyCode::Char('-') => self.adjust_black_point(-1),
+            KeyCode::Char('r') => self.calibration = Calibration::default(),
+            _ => {}
+        }
+        Outcome::Continue
+    }
+
+    /// 調整画面のフレーム
+    ///
+    /// チャンネルごとに、補正後のグラデーション、黒に近いパッチ（黒レベル用）、
+    /// 縞模様と 50% の単色（ガンマ用。離れて見て同じ明るさになるよう合わせる）を並べる
+    fn render(&self, width: u32, height: u32) -> TextCanvas {
+        let curve = TransferCurve::new(&self.calibration);
+        let mut canvas = TextCanvas::new(width, height);
+        let width = canvas.width();
+        let help = "←/→ channel  ↑/↓ gamma  PgUp/PgDn black point  R reset  Enter save  Esc cancel";
+        canvas.put_str(1, 0, &truncate(help, width.saturating_sub(2)), TEXT_COLOR);
+
+        let gradient_width = GRADIENT_STEPS.min(width.saturating_sub(2 + 3 * GAMMA_PATCH_WIDTH));
+        for (i, &channel) in Channel::ORDER.iter().enumerate() {
+            let top = 2 + i * 4;
+            let index = channel.indices()[0];
+            let label = format!(
+                "{} {:<11} gamma {:.2}   black point {:>2}",
+                if i == self.selected { '>' } else { ' ' },
+                channel.name(),
+                self.calibration.gamma[index],
+                self.calibration.black_point[index]
+            );
+            let color = if i == self.selected {
+                SELECTED_COLOR
+            } else {
+                TEXT_COLOR
+            };
+            canvas.put_str(1, top, &truncate(&label, width.saturating_sub(2)), color);
+
+            let block = |rgb: Rgb| Cell {
+                ch: ' ',
+                fg: rgb,
+                bg: Some(curve.apply(rgb)),
+            };
+            for x in 0..gradient_width {
+                let level = (x * 255 / GRADIENT_STEPS.saturating_sub(1).max(1)) as u8;
+                canvas.put_cell(3 + x, top + 1, block(channel.color(level)));
+            }
+            // 上半分が白、下半分が黒の縞は、リニアで 50% の明るさに見える
+            let stripes = Cell {
+                ch: '▀',
+                fg: curve.apply(channel.color(255)),
+                bg: Some(curve.apply(channel.color(0))),
+            };
+            let patches_left = 3 + gradient_width + GAMMA_PATCH_WIDTH;
+            for x in 0..GAMMA_PATCH_WIDTH {
+                canvas.put_cell(patches_left + x, top + 1, stripes);
+                canvas.put_cell(
+                    patches_left + GAMMA_PATCH_WIDTH + x,
+                    top + 1,
+                    block(channel.color(MID_GRAY)),
+                );
+            }
+
+            for patch in 0..BLACK_PATCHES {
+                let level = patch as u8 * BLACK_PATCH_STEP;
+                for dx in 0..2 {
+                    canvas.put_cell(3 + patch * 2 + dx, top + 2, block(channel.color(level)));
+                }
+            }
+        }
+        canvas
+    }
+}
+
+/// 調整画面を表示し、保存を選んだら調整後の値を返す（取り消したら None）
+pub fn run_interactive(initial: Calibration) -> Result<Option<Calibration>> {
+    let mut calibrator = Calibrator {
+        calibration: initial,
+        selected: Channel::ORDER.len() - 1,
+    };
+    let mut out = stdout();
+    execute!(out, EnterAlternateScreen, Hide)?;
+    terminal::enable_raw_mode()?;
+
+    let result = (|| -> Result<Outcome> {
+        loop {
+            let (width, height) = terminal::size().unwrap_or((80, 24));
+            let frame = calibrator.render(width as u32, height as u32).into_frame();
+            execute!(out, Clear(ClearType::All))?;
+            out.write_all(&ansi::encode(&frame, ColorMode::Truecolor))?;
+            out.flush()?;
+
+            if let Event::Key(KeyEvent {
+                code,
+                modifiers,
+                kind: KeyEventKind::Press,
+                ..
+            }) = event::read()?
+            {
+                match calibrator.handle_key(code, modifiers) {
+                    Outcome::Continue => {}
+                    outcome => return Ok(outcome),
+                }
+            }
+        }
+    })();
+
+    terminal::disable_raw_mode()?;
+    execute!(out, Show, LeaveAlternateScreen)?;
+    Ok((result? == Outcome::Save).then_some(calibrator.calibration))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_identity_curve() {
+        let calibration = Calibration::default();
+        assert!(calibration.is_identity());
+        assert_eq!(calibration.transfer_curve(), None);
+        let curve = TransferCurve::new(&calibration);
+        for value in [0, 1, 128, 254, 255] {
+            assert_eq!(curve.apply([value; 3]), [value; 3]);
+        }
+    }
+
+    #[test]
+    fn test_gamma_and_black_point() {
+        let calibration = Calibration {
+            gamma: [2.0, 1.0, 1.0],
+            black_point: [0, 16, 0],
+        };
+        let curve = calibration.transfer_curve().unwrap();
+        // ガンマ 2.0 は中間を持ち上げ、両端はそのまま
+        let [r, g, b] = curve.apply([64, 0, 255]);
+        assert_eq!(r, 128);
+        assert_eq!(g, 16);
+        assert_eq!(b, 255);
+        assert_eq!(curve.apply([255, 255, 0]), [255, 255, 0]);
+    }
+
+    #[test]
+    fn test_validate_and_toml() {
+        assert!(Calibration::default().validate().is_ok());
+        let mut calibration = Calibration {
+            gamma: [1.1, 0.95, 1.0],
+            black_point: [4, 0, 2],
+        };
+        assert_eq!(
+            calibration.to_toml(),
+            "[calibration]\ngamma = [1.10, 0.95, 1.00]\nblack_point = [4, 0, 2]\n"
+        );
+        calibration.gamma[1] = 10.0;
+        assert!(calibration.validate().is_err());
+        calibration.gamma[1] = 1.0;
+        calibration.black_point[0] = 100;
+        assert!(calibration.validate().is_err());
+    }
+
+    #[test]
+    fn test_keys_adjust_selected_channel() {
+        let mut calibrator = Calibrator {
+            calibration: Calibration::default(),
+            selected: 3,
+        };
+        calibrator.handle_key(KeyCode::Up, KeyModifiers::NONE);
+        assert_eq!(calibrator.calibration.gamma, [1.05; 3]);
+
+        calibrator.handle_key(KeyCode::Right, KeyModifiers::NONE);
+        assert_eq!(calibrator.channel(), Channel::Red);
+        calibrator.handle_key(KeyCode::PageUp, KeyModifiers::NONE);
+        calibrator.handle_key(KeyCode::PageDown, KeyModifiers::NONE);
+        calibrator.handle_key(KeyCode::PageDown, KeyModifiers::NONE);
+        calibrator.handle_key(KeyCode::Char('+'), KeyModifiers::NONE);
+        assert_eq!(calibrator.calibration.black_point, [1, 0, 0]);
+
+        assert_eq!(
+            calibrator.handle_key(KeyCode::Enter, KeyModifiers::NONE),
+            Outcome::Save
+        );
+        calibrator.handle_key(KeyCode::Char('r'), KeyModifiers::NONE);
+        assert!(calibrator.calibration.is_identity());
+        assert_eq!(
+            calibrator.handle_key(KeyCode::Esc, KeyModifiers::NONE),
+            Outcome::Cancel
+        );
+    }
+}
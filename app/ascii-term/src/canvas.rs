@@ -0,0 +1,167 @@
+//! テキスト描画用キャンバス
+//!
+//! 映像以外の画面（アルバム表示など）を文字単位で組み立て、
+//! `RenderedFrame` としてターミナルへ送るための補助
+
+use unicode_width::{UnicodeWidthChar, UnicodeWidthStr};
+
+use crate::renderer::{Cell, RenderedFrame, Rgb};
+
+/// 文字と前景色のグリッド
+pub struct TextCanvas {
+    width: usize,
+    height: usize,
+    cells: Vec<Cell>,
+}
+
+impl TextCanvas {
+    pub fn new(width: u32, height: u32) -> Self {
+        let width = width as usize;
+        let height = height as usize;
+        Self {
+            width,
+            height,
+            cells: vec![Cell::BLANK; width * height],
+        }
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    /// 指定位置から文字列を書き込む（はみ出した分は切り捨て、制御文字は書かない）
+    pub fn put_str(&mut self, x: usize, y: usize, text: &str, color: Rgb) {
+        if y >= self.height {
+            return;
+        }
+        for (i, ch) in text.chars().filter(|ch| !ch.is_control()).enumerate() {
+            let col = x + i;
+            if col >= self.width {
+                break;
+            }
+            self.cells[y * self.width + col] = Cell::new(ch, color);
+        }
+    }
+
+    /// 1 文字を書き込む（範囲外は無視する）
+    pub fn put_cell(&mut self, x: usize, y: usize, cell: Cell) {
+        if x < self.width && y < self.height {
+            self.cells[y * self.width + x] = cell;
+        }
+    }
+
+    /// 1 行を同じ文字で埋める
+    pub fn fill_row(&mut self, y: usize, ch: char, color: Rgb) {
+        if y >= self.height {
+            return;
+        }
+        self.cells[y * self.width..(y + 1) * self.width].fill(Cell::new(ch, color));
+    }
+
+    pub fn into_frame(self) -> RenderedFrame {
+        RenderedFrame {
+            cells: self.cells,
+            width: self.width as u32,
+            height: self.height as u32,
+            sixel: None,
+        }
+    }
+}
+
+/// 制御文字（ESC、BEL など）を取り除く
+///
+/// ネットワークやファイルから来た文字列をそのまま書くと、エスケープシーケンスで
+/// 画面・ウィンドウタイトル・クリップボードを書き換えられるため、表示する前に通す
+pub fn sanitize(text: &str) -> String {
+    text.chars().filter(|ch| !ch.is_control()).collect()
+}
+
+/// 制御文字を空白に置き換える（1 セル 1 文字の出力で桁を保つため、取り除かない）
+pub fn printable(ch: char) -> char {
+    if ch.is_control() { ' ' } else { ch }
+}
+
+/// ターミナルに表示したときの幅（全角文字は 2 桁）
+pub fn display_width(text: &str) -> usize {
+    UnicodeWidthStr::width(text)
+}
+
+/// 表示幅が `width` になるまで末尾を空白で埋める
+pub fn pad(text: &str, width: usize) -> String {
+    let mut padded = text.to_string();
+    padded.extend(std::iter::repeat_n(
+        ' ',
+        width.saturating_sub(display_width(text)),
+    ));
+    padded
+}
+
+/// 表示幅が `max` を超える場合は末尾を "…" にして切り詰める
+pub fn truncate(text: &str, max: usize) -> String {
+    if display_width(text) <= max {
+        return text.to_string();
+    }
+    if max == 0 {
+        return String::new();
+    }
+    let mut truncated = String::new();
+    let mut width = 0;
+    for ch in text.chars() {
+        let ch_width = UnicodeWidthChar::width(ch).unwrap_or(0);
+        if width + ch_width > max - 1 {
+            break;
+        }
+        width += ch_width;
+        truncated.push(ch);
+    }
+    truncated.push('…');
+    truncated
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_put_str_clips_to_bounds() {
+        let mut canvas = TextCanvas::new(4, 2);
+        canvas.put_str(2, 1, "abc", [255, 0, 0]);
+        canvas.put_str(0, 5, "ignored", [255, 0, 0]);
+
+        let frame = canvas.into_frame();
+        assert_eq!(frame.text(), "    \n  ab");
+        assert_eq!(frame.cells.len(), 4 * 2);
+        assert_eq!(frame.cells[6], Cell::new('a', [255, 0, 0]));
+    }
+
+    #[test]
+    fn test_control_chars_are_not_written() {
+        assert_eq!(sanitize("a\x1b]52;c;eA==\x07b\r\n"), "a]52;c;eA==b");
+        assert_eq!(printable('\x1b'), ' ');
+        assert_eq!(printable('あ'), 'あ');
+
+        let mut canvas = TextCanvas::new(4, 1);
+        canvas.put_str(0, 0, "\x1b[2J", [255, 255, 255]);
+        assert_eq!(canvas.into_frame().text(), "[2J ");
+    }
+
+    #[test]
+    fn test_truncate() {
+        assert_eq!(truncate("hello", 10), "hello");
+        assert_eq!(truncate("hello", 4), "hel…");
+        assert_eq!(truncate("hello", 0), "");
+    }
+
+    #[test]
+    fn test_truncate_wide_chars() {
+        assert_eq!(truncate("日本語", 6), "日本語");
+        assert_eq!(truncate("日本語", 5), "日本…");
+        assert_eq!(truncate("日本語", 4), "日…");
+        assert_eq!(display_width(&truncate("日本語の字幕", 7)), 7);
+        assert_eq!(pad("日本", 6), "日本  ");
+    }
+}
@@ -0,0 +1,123 @@
+//! チャプター一覧と、再生位置からの前後のチャプターの選択
+
+use std::time::Duration;
+
+/// チャプターの先頭からこれ以上進んでいれば、「前へ」でそのチャプターの先頭に戻る
+const RESTART_THRESHOLD: Duration = Duration::from_secs(3);
+
+/// 1 つのチャプター
+#[derive(Debug, Clone, PartialEq)]
+pub struct Chapter {
+    pub start: Duration,
+    pub title: String,
+}
+
+/// 開始時刻順に並んだチャプター
+#[derive(Debug, Clone, Default)]
+pub struct Chapters {
+    chapters: Vec<Chapter>,
+}
+
+impl Chapters {
+    pub fn new(mut chapters: Vec<Chapter>) -> Self {
+        chapters.sort_by_key(|chapter| chapter.start);
+        Self { chapters }
+    }
+
+    pub fn len(&self) -> usize {
+        self.chapters.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.chapters.is_empty()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Chapter> {
+        self.chapters.iter()
+    }
+
+    /// 再生位置の次のチャプター（番号は 0 始まり）
+    pub fn next(&self, position: Duration) -> Option<(usize, &Chapter)> {
+        let index = self
+            .chapters
+            .partition_point(|chapter| chapter.start <= position);
+        self.chapters.get(index).map(|chapter| (index, chapter))
+    }
+
+    /// 「前へ」で移動するチャプター
+    ///
+    /// 再生中のチャプターの先頭付近であれば 1 つ前、そうでなければ再生中のチャプターの先頭
+    pub fn previous(&self, position: Duration) -> Option<(usize, &Chapter)> {
+        let current = self
+            .chapters
+            .partition_point(|chapter| chapter.start <= position)
+            .checked_sub(1)?;
+        let index = if position - self.chapters[current].start < RESTART_THRESHOLD {
+            current.saturating_sub(1)
+        } else {
+            current
+        };
+        Some((index, &self.chapters[index]))
+    }
+}
+
+/// YouTube の動画情報からチャプター一覧を取得する
+pub async fn fetch_youtube_chapters(
+    url: &str,
+    network: &downloader::NetworkOptions,
+) -> anyhow::Result<Chapters> {
+    let info = downloader::get_video_info(url, network).await?;
+    let chapters = info
+        .chapters
+        .unwrap_or_default()
+        .into_iter()
+        .map(|chapter| Chapter {
+            start: Duration::from_secs_f64(chapter.start_time.max(0.0)),
+            title: chapter.title,
+        })
+        .collect();
+    Ok(Chapters::new(chapters))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn chapters() -> Chapters {
+        let chapter = |secs, title: &str| Chapter {
+            start: Duration::from_secs(secs),
+            title: title.to_string(),
+        };
+        Chapters::new(vec![
+            chapter(60, "Demo"),
+            chapter(0, "Intro"),
+            chapter(300, "Q&A"),
+        ])
+    }
+
+    #[test]
+    fn test_next_chapter() {
+        let chapters = chapters();
+        let (index, chapter) = chapters.next(Duration::from_secs(10)).unwrap();
+        assert_eq!((index, chapter.title.as_str()), (1, "Demo"));
+        // チャプターの先頭ちょうどにいる場合は次へ進む
+        let (index, _) = chapters.next(Duration::from_secs(60)).unwrap();
+        assert_eq!(index, 2);
+        assert!(chapters.next(Duration::from_secs(400)).is_none());
+    }
+
+    #[test]
+    fn test_previous_chapter() {
+        let chapters = chapters();
+        // チャプターの途中では、そのチャプターの先頭へ
+        let (index, _) = chapters.previous(Duration::from_secs(120)).unwrap();
+        assert_eq!(index, 1);
+        // 先頭付近では 1 つ前へ
+        let (index, _) = chapters.previous(Duration::from_secs(61)).unwrap();
+        assert_eq!(index, 0);
+        // 最初のチャプターの先頭付近ではそのまま
+        let (index, _) = chapters.previous(Duration::from_secs(1)).unwrap();
+        assert_eq!(index, 0);
+        assert!(Chapters::default().previous(Duration::ZERO).is_none());
+    }
+}
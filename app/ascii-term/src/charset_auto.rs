@@ -0,0 +1,194 @@
+//! 映像の内容に合わせた文字マップの自動選択
+//!
+//! 再生開始直後の数フレームから輝度ヒストグラムとエッジ密度を集計し、
+//! 写真的な映像には階調の細かい文字マップ、ベタ塗りのグラフィックには
+//! ブロック文字、細部の多い映像には点字を選ぶ
+
+use codec::color::luminance;
+use codec::video::VideoFrame;
+use codec::video::frame::FrameFormat;
+
+/// 判定に使うフレーム数
+const SAMPLE_FRAMES: usize = 8;
+
+/// 集計時の間引き間隔（ピクセル）
+const SAMPLE_STEP: usize = 4;
+
+/// 輝度ヒストグラムのビン数
+const HISTOGRAM_BINS: usize = 16;
+
+/// エッジとみなす隣接ピクセル間の輝度差
+const EDGE_THRESHOLD: u8 = 48;
+
+/// 上位 4 ビンにこの割合以上が集中していればベタ塗りのグラフィックとみなす
+const FLAT_CONCENTRATION: f32 = 0.9;
+
+/// エッジ密度がこれを超えれば細部の多い映像とみなす
+const DETAIL_EDGE_DENSITY: f32 = 0.2;
+
+const DENSE_RAMP: u8 = 1;
+const BLOCKS: u8 = 3;
+const BRAILLE: u8 = 4;
+
+/// 自動選択の結果
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CharsetChoice {
+    pub index: u8,
+    pub reason: &'static str,
+}
+
+/// 再生開始直後のフレームを集計して文字マップを決める
+#[derive(Debug, Default)]
+pub struct CharsetAdvisor {
+    histogram: [u64; HISTOGRAM_BINS],
+    edges: u64,
+    samples: u64,
+    frames: usize,
+}
+
+impl CharsetAdvisor {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// フレームを集計に加え、十分なフレームが集まったら選択結果を返す
+    pub fn observe(&mut self, frame: &VideoFrame) -> Option<CharsetChoice> {
+        if self.frames >= SAMPLE_FRAMES {
+            return None;
+        }
+
+        self.accumulate(frame);
+        self.frames += 1;
+
+        (self.frames == SAMPLE_FRAMES).then(|| self.choice())
+    }
+
+    fn accumulate(&mut self, frame: &VideoFrame) {
+        let bytes_per_pixel = match frame.format {
+            FrameFormat::RGB8 | FrameFormat::BGR8 => 3,
+            FrameFormat::RGBA8 | FrameFormat::BGRA8 => 4,
+            FrameFormat::Gray8 => 1,
+            FrameFormat::YUV420P => return,
+        };
+        let width = frame.width as usize;
+        let height = frame.height as usize;
+        if frame.data.len() < width * height * bytes_per_pixel {
+            return;
+        }
+
+        let luma = |x: usize, y: usize| -> u8 {
+            let i = (y * width + x) * bytes_per_pixel;
+            let px = &frame.data[i..i + bytes_per_pixel];
+            match frame.format {
+                FrameFormat::Gray8 => px[0],
+                FrameFormat::BGR8 | FrameFormat::BGRA8 => luminance([px[2], px[1], px[0]]),
+                _ => luminance([px[0], px[1], px[2]]),
+            }
+        };
+
+        for y in (0..height.saturating_sub(SAMPLE_STEP)).step_by(SAMPLE_STEP) {
+            for x in (0..width.saturating_sub(SAMPLE_STEP)).step_by(SAMPLE_STEP) {
+                let value = luma(x, y);
+                self.histogram[value as usize * HISTOGRAM_BINS / 256] += 1;
+
+                let dx = value.abs_diff(luma(x + SAMPLE_STEP, y));
+                let dy = value.abs_diff(luma(x, y + SAMPLE_STEP));
+                if dx.max(dy) >= EDGE_THRESHOLD {
+                    self.edges += 1;
+                }
+                self.samples += 1;
+            }
+        }
+    }
+
+    /// これまでの集計から文字マップを選ぶ
+    pub fn choice(&self) -> CharsetChoice {
+        if self.samples == 0 {
+            return CharsetChoice {
+                index: DENSE_RAMP,
+                reason: "no samples",
+            };
+        }
+
+        let mut bins = self.histogram;
+        bins.sort_unstable_by(|a, b| b.cmp(a));
+        let concentration = bins[..4].iter().sum::<u64>() as f32 / self.samples as f32;
+        let edge_density = self.edges as f32 / self.samples as f32;
+
+        if concentration >= FLAT_CONCENTRATION && edge_density < DETAIL_EDGE_DENSITY {
+            CharsetChoice {
+                index: BLOCKS,
+                reason: "flat graphics",
+            }
+        } else if edge_density >= DETAIL_EDGE_DENSITY {
+            CharsetChoice {
+                index: BRAILLE,
+                reason: "high detail",
+            }
+        } else {
+            CharsetChoice {
+                index: DENSE_RAMP,
+                reason: "photographic",
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    fn frame_from(width: u32, height: u32, pixel: impl Fn(u32, u32) -> u8) -> VideoFrame {
+        let mut data = Vec::with_capacity((width * height * 3) as usize);
+        for y in 0..height {
+            for x in 0..width {
+                let v = pixel(x, y);
+                data.extend_from_slice(&[v, v, v]);
+            }
+        }
+        VideoFrame::new(data, width, height, FrameFormat::RGB8, Duration::ZERO, 0)
+    }
+
+    fn decide(frame: &VideoFrame) -> CharsetChoice {
+        let mut advisor = CharsetAdvisor::new();
+        for _ in 0..SAMPLE_FRAMES - 1 {
+            assert!(advisor.observe(frame).is_none());
+        }
+        advisor.observe(frame).unwrap()
+    }
+
+    #[test]
+    fn test_flat_graphics_choose_blocks() {
+        // 2 色で大きく塗り分けたアニメ調の画面
+        let frame = frame_from(160, 90, |x, _| if x < 80 { 20 } else { 230 });
+        assert_eq!(decide(&frame).index, BLOCKS);
+    }
+
+    #[test]
+    fn test_smooth_gradient_chooses_dense_ramp() {
+        let frame = frame_from(256, 64, |x, y| ((x + y) / 2) as u8);
+        assert_eq!(decide(&frame).index, DENSE_RAMP);
+    }
+
+    #[test]
+    fn test_fine_texture_chooses_braille() {
+        // 細かい市松模様
+        let frame = frame_from(
+            160,
+            90,
+            |x, y| if (x / 4 + y / 4) % 2 == 0 { 0 } else { 255 },
+        );
+        assert_eq!(decide(&frame).index, BRAILLE);
+    }
+
+    #[test]
+    fn test_decision_is_made_once() {
+        let frame = frame_from(32, 32, |_, _| 128);
+        let mut advisor = CharsetAdvisor::new();
+        let decisions = (0..SAMPLE_FRAMES * 2)
+            .filter_map(|_| advisor.observe(&frame))
+            .count();
+        assert_eq!(decisions, 1);
+    }
+}
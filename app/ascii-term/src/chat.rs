@@ -0,0 +1,145 @@
+//! チャット（Discord / Twitch など）に貼り付けられるテキストへの書き出し
+//!
+//! ANSI エスケープを使わず、等幅でなくても崩れにくく Markdown として
+//! 解釈されにくい ASCII 文字だけで 1 フレームを描く。幅はメッセージの
+//! 文字数制限に収まるよう自動で狭める
+
+use anyhow::Result;
+use codec::color::luminance;
+use image::DynamicImage;
+
+use crate::char_maps;
+use crate::renderer::{AsciiRenderer, RenderConfig, Renderer};
+
+/// 既定の最大幅（文字数）。スマートフォンのチャット画面でも折り返しにくい幅
+pub const DEFAULT_CHAT_WIDTH: u32 = 60;
+
+/// 1 メッセージの文字数制限（Discord）
+const MESSAGE_LIMIT: usize = 2000;
+
+/// コードブロックの囲み
+const CODE_FENCE: &str = "```";
+
+/// 暗い順の文字。`*` `_` `#` `-` `>` `~` `|` や行頭の数字など、
+/// Markdown の書式になりうる文字は使わない
+const CHAT_CHARS: &str = " .,:;=ilO8@";
+
+/// チャット用の書き出し設定
+#[derive(Debug, Clone, Copy)]
+pub struct ChatOptions {
+    /// 最大幅（文字数）
+    pub max_width: u32,
+    /// コードブロック（```）で囲む
+    pub code_fence: bool,
+}
+
+impl Default for ChatOptions {
+    fn default() -> Self {
+        Self {
+            max_width: DEFAULT_CHAT_WIDTH,
+            code_fence: false,
+        }
+    }
+}
+
+/// 画像をチャットに貼り付けられるテキストに変換する
+pub fn render_chat(image: &DynamicImage, options: ChatOptions) -> Result<String> {
+    let (width, height) = chat_size(image.width(), image.height(), options);
+
+    let mut renderer = AsciiRenderer::new(RenderConfig {
+        target_width: width,
+        target_height: height,
+        ..Default::default()
+    });
+    let frame = renderer.render_image(image)?;
+
+    let mut lines: Vec<String> = frame
+        .rows()
+        .map(|row| {
+            let line: String = row
+                .iter()
+                .map(|cell| char_maps::luminance_to_char(luminance(cell.fg), CHAT_CHARS))
+                .collect();
+            // 行末の空白は表示に影響しないので、文字数を節約するため削る
+            line.trim_end().to_string()
+        })
+        .collect();
+
+    if options.code_fence {
+        lines.insert(0, CODE_FENCE.to_string());
+        lines.push(CODE_FENCE.to_string());
+    }
+    Ok(lines.join("\n"))
+}
+
+/// 書き出すサイズ（列数, 行数）を決める
+///
+/// 文字セルは縦長なので行数は縦横比の半分にし、全体が 1 メッセージに
+/// 収まるまで幅を狭める
+fn chat_size(src_width: u32, src_height: u32, options: ChatOptions) -> (u32, u32) {
+    let aspect = src_height.max(1) as f64 / src_width.max(1) as f64;
+    let fence_len = if options.code_fence {
+        (CODE_FENCE.len() + 1) * 2
+    } else {
+        0
+    };
+
+    let mut width = options.max_width.max(1);
+    loop {
+        let height = ((width as f64 * aspect / 2.0).round() as u32).max(1);
+        // 各行の文字と改行
+        let total = (width as usize + 1) * height as usize + fence_len;
+        if total <= MESSAGE_LIMIT || width == 1 {
+            return (width, height);
+        }
+        width -= 1;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{Rgb, RgbImage};
+
+    #[test]
+    fn test_chat_size_keeps_aspect() {
+        // 16:9 の映像は幅 60 で 17 行（セルの縦横比 2:1）
+        assert_eq!(chat_size(1920, 1080, ChatOptions::default()), (60, 17));
+    }
+
+    #[test]
+    fn test_chat_size_fits_message_limit() {
+        // 縦長の映像は 1 メッセージに収まるまで幅を狭める
+        let options = ChatOptions {
+            max_width: 120,
+            code_fence: true,
+        };
+        let (width, height) = chat_size(1080, 1920, options);
+        assert!(width < 120);
+        assert!((width as usize + 1) * height as usize + 8 <= MESSAGE_LIMIT);
+    }
+
+    #[test]
+    fn test_render_chat_is_plain_ascii() {
+        let mut image = RgbImage::from_pixel(40, 20, Rgb([255, 255, 255]));
+        for x in 0..20 {
+            for y in 0..20 {
+                image.put_pixel(x, y, Rgb([0, 0, 0]));
+            }
+        }
+        let options = ChatOptions {
+            max_width: 20,
+            code_fence: true,
+        };
+        let text = render_chat(&DynamicImage::ImageRgb8(image), options).unwrap();
+
+        assert!(text.starts_with("```\n") && text.ends_with("\n```"));
+        assert!(!text.contains('\x1b'));
+        assert!(text.is_ascii());
+        let body: Vec<&str> = text.lines().skip(1).take(5).collect();
+        assert_eq!(body.len(), 5);
+        // 左半分は黒（空白）、右半分は白
+        assert!(body.iter().all(|line| line.starts_with("        ")));
+        assert!(body.iter().all(|line| line.ends_with('@')));
+    }
+}
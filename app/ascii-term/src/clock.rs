@@ -0,0 +1,112 @@
+//! 映像の表示時刻を決める基準の時計（マスタークロック）
+//!
+//! 単調増加の時計で進め、音声がある場合は音声の再生位置に合わせ直す。
+//! 音声の位置は出力バッファの単位でしか進まないので毎回そのまま使うと映像が
+//! がたつく。`AUDIO_RESYNC_THRESHOLD` 以上ずれたときだけ合わせることで、
+//! 長い動画でも映像と音声がずれ続けないようにする
+
+use std::time::{Duration, Instant};
+
+/// 音声の位置とこれ以上ずれたら時計を音声に合わせる
+const AUDIO_RESYNC_THRESHOLD: Duration = Duration::from_millis(40);
+
+#[derive(Debug, Clone)]
+pub struct MasterClock {
+    /// `anchor` の時刻での再生位置
+    base: Duration,
+    anchor: Instant,
+    paused: bool,
+}
+
+impl MasterClock {
+    /// 再生位置 0 から進み始める時計
+    pub fn new() -> Self {
+        Self {
+            base: Duration::ZERO,
+            anchor: Instant::now(),
+            paused: false,
+        }
+    }
+
+    /// 現在の再生位置
+    pub fn now(&self) -> Duration {
+        if self.paused {
+            self.base
+        } else {
+            self.base + self.anchor.elapsed()
+        }
+    }
+
+    /// 再生位置を `position` にする（移動やループの後）
+    pub fn set(&mut self, position: Duration) {
+        self.base = position;
+        self.anchor = Instant::now();
+    }
+
+    /// 止める（既に止まっていれば何もしない）
+    pub fn pause(&mut self) {
+        if !self.paused {
+            self.base = self.now();
+            self.paused = true;
+        }
+    }
+
+    /// 止めた位置から進め直す（進んでいれば何もしない）
+    pub fn resume(&mut self) {
+        if self.paused {
+            self.anchor = Instant::now();
+            self.paused = false;
+        }
+    }
+
+    /// 音声の再生位置 `audio` と大きくずれていれば合わせる。合わせたら true
+    pub fn sync_to_audio(&mut self, audio: Duration) -> bool {
+        if self.now().abs_diff(audio) <= AUDIO_RESYNC_THRESHOLD {
+            return false;
+        }
+        self.set(audio);
+        true
+    }
+}
+
+impl Default for MasterClock {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_set_and_pause() {
+        let mut clock = MasterClock::new();
+        clock.set(Duration::from_secs(10));
+        assert!(clock.now() >= Duration::from_secs(10));
+        assert!(clock.now() < Duration::from_secs(11));
+
+        clock.pause();
+        let paused_at = clock.now();
+        std::thread::sleep(Duration::from_millis(5));
+        assert_eq!(clock.now(), paused_at);
+
+        clock.resume();
+        std::thread::sleep(Duration::from_millis(5));
+        assert!(clock.now() > paused_at);
+    }
+
+    #[test]
+    fn test_sync_to_audio() {
+        let mut clock = MasterClock::new();
+        clock.pause();
+        clock.set(Duration::from_secs(5));
+
+        // 少しのずれは出力バッファの粒度として無視する
+        assert!(!clock.sync_to_audio(Duration::from_millis(5_020)));
+        assert_eq!(clock.now(), Duration::from_secs(5));
+
+        assert!(clock.sync_to_audio(Duration::from_millis(4_500)));
+        assert_eq!(clock.now(), Duration::from_millis(4_500));
+    }
+}
@@ -0,0 +1,194 @@
+//! 色覚特性のシミュレーション・補正と、区別しやすいパレットへの減色
+//!
+//! シミュレーションは Machado ら (2009) の変換行列（重度）を線形 RGB に適用する。
+//! 補正は、シミュレーションで失われる差分を見分けやすい成分に振り替える
+//! （daltonize）。描画時にセルの文字色へ適用する
+
+use clap::ValueEnum;
+use codec::color::{linear_to_srgb, srgb_to_linear};
+use serde::Deserialize;
+
+use crate::renderer::Rgb;
+
+/// 色のフィルター
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, ValueEnum, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ColorFilter {
+    /// そのまま
+    #[default]
+    None,
+    /// 1 型色覚（赤を感じにくい）の見え方を再現する
+    Protanopia,
+    /// 2 型色覚（緑を感じにくい）の見え方を再現する
+    Deuteranopia,
+    /// 1 型色覚の人が区別しやすいように補正する
+    ProtanopiaAssist,
+    /// 2 型色覚の人が区別しやすいように補正する
+    DeuteranopiaAssist,
+    /// 色覚によらず区別しやすい高コントラストのパレット（Okabe-Ito）に減色する
+    HighContrast,
+}
+
+/// 1 型色覚のシミュレーション（線形 RGB）
+const PROTANOPIA: [[f32; 3]; 3] = [
+    [0.152286, 1.052583, -0.204868],
+    [0.114503, 0.786281, 0.099216],
+    [-0.003882, -0.048116, 1.051998],
+];
+
+/// 2 型色覚のシミュレーション（線形 RGB）
+const DEUTERANOPIA: [[f32; 3]; 3] = [
+    [0.367322, 0.860646, -0.227968],
+    [0.280085, 0.672501, 0.047413],
+    [-0.011820, 0.042940, 0.968881],
+];
+
+/// Okabe-Ito のカラーユニバーサルデザインのパレットに白を加えたもの
+const SAFE_PALETTE: [Rgb; 9] = [
+    [0, 0, 0],
+    [230, 159, 0],
+    [86, 180, 233],
+    [0, 158, 115],
+    [240, 228, 66],
+    [0, 114, 178],
+    [213, 94, 0],
+    [204, 121, 167],
+    [255, 255, 255],
+];
+
+impl ColorFilter {
+    /// 実行中に切り替えるときの次のフィルター
+    pub fn next(self) -> Self {
+        match self {
+            ColorFilter::None => ColorFilter::Protanopia,
+            ColorFilter::Protanopia => ColorFilter::Deuteranopia,
+            ColorFilter::Deuteranopia => ColorFilter::ProtanopiaAssist,
+            ColorFilter::ProtanopiaAssist => ColorFilter::DeuteranopiaAssist,
+            ColorFilter::DeuteranopiaAssist => ColorFilter::HighContrast,
+            ColorFilter::HighContrast => ColorFilter::None,
+        }
+    }
+
+    /// 表示名
+    pub fn name(self) -> &'static str {
+        match self {
+            ColorFilter::None => "none",
+            ColorFilter::Protanopia => "protanopia",
+            ColorFilter::Deuteranopia => "deuteranopia",
+            ColorFilter::ProtanopiaAssist => "protanopia assist",
+            ColorFilter::DeuteranopiaAssist => "deuteranopia assist",
+            ColorFilter::HighContrast => "high contrast",
+        }
+    }
+
+    /// 1 色にフィルターを適用する
+    pub fn apply(self, rgb: Rgb) -> Rgb {
+        match self {
+            ColorFilter::None => rgb,
+            ColorFilter::Protanopia => simulate(rgb, &PROTANOPIA),
+            ColorFilter::Deuteranopia => simulate(rgb, &DEUTERANOPIA),
+            ColorFilter::ProtanopiaAssist => daltonize(rgb, &PROTANOPIA),
+            ColorFilter::DeuteranopiaAssist => daltonize(rgb, &DEUTERANOPIA),
+            ColorFilter::HighContrast => nearest_safe_color(rgb),
+        }
+    }
+}
+
+fn simulate(rgb: Rgb, matrix: &[[f32; 3]; 3]) -> Rgb {
+    linear_to_srgb(transform(srgb_to_linear(rgb), matrix))
+}
+
+/// 見え方で失われる差分を、区別できる緑・青の成分に加える
+fn daltonize(rgb: Rgb, matrix: &[[f32; 3]; 3]) -> Rgb {
+    let original = srgb_to_linear(rgb);
+    let simulated = transform(original, matrix);
+    let error = [
+        original[0] - simulated[0],
+        original[1] - simulated[1],
+        original[2] - simulated[2],
+    ];
+    linear_to_srgb([
+        original[0],
+        original[1] + 0.7 * error[0] + error[1],
+        original[2] + 0.7 * error[0] + error[2],
+    ])
+}
+
+fn transform(color: [f32; 3], matrix: &[[f32; 3]; 3]) -> [f32; 3] {
+    matrix.map(|row| row[0] * color[0] + row[1] * color[1] + row[2] * color[2])
+}
+
+/// パレットのうち最も近い色（人の目の感度で重み付けした距離）
+fn nearest_safe_color(rgb: Rgb) -> Rgb {
+    let distance = |candidate: &Rgb| {
+        let mean_red = (rgb[0] as i32 + candidate[0] as i32) / 2;
+        let [dr, dg, db] = [0, 1, 2].map(|i| rgb[i] as i32 - candidate[i] as i32);
+        (((512 + mean_red) * dr * dr) >> 8) + 4 * dg * dg + (((767 - mean_red) * db * db) >> 8)
+    };
+    *SAFE_PALETTE
+        .iter()
+        .min_by_key(|candidate| distance(candidate))
+        .unwrap_or(&rgb)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn distance(a: Rgb, b: Rgb) -> i32 {
+        (0..3).map(|i| (a[i] as i32 - b[i] as i32).abs()).sum()
+    }
+
+    #[test]
+    fn test_simulation_keeps_grays() {
+        for filter in [ColorFilter::Protanopia, ColorFilter::Deuteranopia] {
+            for gray in [[0, 0, 0], [128, 128, 128], [255, 255, 255]] {
+                assert!(distance(filter.apply(gray), gray) <= 6, "{:?}", filter);
+            }
+        }
+    }
+
+    #[test]
+    fn test_protanopia_confuses_red_and_green() {
+        let (red, green) = ([200, 40, 40], [60, 140, 40]);
+        let before = distance(red, green);
+        let after = distance(
+            ColorFilter::Protanopia.apply(red),
+            ColorFilter::Protanopia.apply(green),
+        );
+        assert!(after < before / 2);
+    }
+
+    #[test]
+    fn test_assist_separates_confused_colors() {
+        // 補正後の色どうしは、補正なしより見え方の上で離れる
+        let (red, green) = ([200, 40, 40], [60, 140, 40]);
+        let seen = |rgb| ColorFilter::Deuteranopia.apply(rgb);
+        let plain = distance(seen(red), seen(green));
+        let assisted = distance(
+            seen(ColorFilter::DeuteranopiaAssist.apply(red)),
+            seen(ColorFilter::DeuteranopiaAssist.apply(green)),
+        );
+        assert!(assisted > plain);
+    }
+
+    #[test]
+    fn test_high_contrast_uses_palette() {
+        for rgb in [[10, 10, 10], [250, 240, 230], [220, 120, 10], [30, 90, 200]] {
+            assert!(SAFE_PALETTE.contains(&ColorFilter::HighContrast.apply(rgb)));
+        }
+        assert_eq!(
+            ColorFilter::HighContrast.apply([250, 250, 250]),
+            [255, 255, 255]
+        );
+    }
+
+    #[test]
+    fn test_next_cycles_through_all() {
+        let mut filter = ColorFilter::None;
+        for _ in 0..ColorFilter::value_variants().len() {
+            filter = filter.next();
+        }
+        assert_eq!(filter, ColorFilter::None);
+    }
+}
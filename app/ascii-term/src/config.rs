@@ -0,0 +1,418 @@
+//! 既定の設定を書いておく設定ファイル
+//!
+//! `~/.config/ascii-term/config.toml`（`--config` で別のファイルも指定できる）から
+//! 文字マップや色、音声、ダウンロード、キー割り当ての既定値を読む。
+//! コマンドラインで指定した項目はファイルの値より優先する。
+//! `ascii_term config init` でコメント付きのひな形を書き出し、
+//! `ascii_term calibrate` は調整した色の補正を `[calibration]` に書き込む
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use serde::Deserialize;
+
+use crate::ansi::ColorMode;
+use crate::audio::AudioBackendKind;
+use crate::calibration::Calibration;
+use crate::char_maps::CHAR_MAPS;
+use crate::input_policy::InputPolicy;
+use crate::terminal::KeyBindings;
+
+/// 設定ファイルの名前
+const CONFIG_FILE_NAME: &str = "config.toml";
+
+/// `config init` で書き出すひな形（すべての項目をコメントにしてある）
+const TEMPLATE: &str = r#"# ascii-term configuration
+#
+# Every setting is optional. Options given on the command line override this file.
+
+# Character map (0-9); picked from the video content when omitted
+# char_map = 3
+
+# Colors to output: "truecolor", "ansi256", "ansi16" or "mono"
+# (detected from COLORTERM / TERM when omitted)
+# color_mode = "ansi256"
+
+# Width modifier for character aspect ratio (1 or more)
+# width_mod = 1
+
+# Start in grayscale
+# gray = false
+
+[audio]
+# Play audio (false is the same as --no-audio)
+# enabled = true
+
+# Output: "rodio", "cpal", "pulse" or "aplay"
+# backend = "rodio"
+
+# Fade length in milliseconds when audio starts, pauses, stops or seeks
+# ramp_ms = 50
+
+[download]
+# Browser to take cookies from (for YouTube)
+# browser = "firefox"
+
+# Maximum download rate, e.g. "500K" or "2M" (bytes per second)
+# limit_rate = "2M"
+
+# Proxy for downloads and yt-dlp
+# proxy = "socks5h://127.0.0.1:9050"
+
+# Highest YouTube quality, e.g. "360p" or "720p", or "best"/"worst"
+# quality = "360p"
+
+[calibration]
+# Color correction for this terminal and font, written by `ascii_term calibrate`:
+# per-channel (R, G, B) gamma (0.25-4.0) and black point (0-64)
+# gamma = [1.0, 1.0, 1.0]
+# black_point = [0, 0, 0]
+
+[inputs]
+# Restrict what can be opened, for kiosks and shared machines where others can queue inputs.
+# A pattern without "/" or ":" matches the host of a URL (and its subdomains); any other
+# pattern matches the whole URL or the resolved local path. "*" matches anything, "?" one
+# character. deny always wins; when allow is set, anything it does not match is rejected.
+# allow = ["youtube.com", "*.example.org", "/srv/media/*"]
+# deny = ["private.example.org"]
+
+[keys]
+# Extra keys per action, used before the built-in ones.
+# Keys are a character or a name (Space, Esc, Enter, Tab, Left, Right, Up, Down,
+# Home, End, PageUp, PageDown, F1-F12), optionally with Ctrl+, Alt+ or Shift+.
+# Actions: quit, play-pause, mute, set-bookmark, jump-to-bookmark, next-chapter,
+# previous-chapter, step-forward, step-backward, ab-loop, seek-forward,
+# seek-backward, seek-forward-long, seek-backward-long, grayscale, scopes,
+# color-filter, audio-track, subtitles, qr-code, status-bar, help,
+# char-map-0 ... char-map-9
+# play-pause = ["k"]
+# seek-backward = ["j"]
+# seek-forward = ["l"]
+"#;
+
+/// 設定ファイルの内容
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Config {
+    pub char_map: Option<u8>,
+    pub color_mode: Option<ColorMode>,
+    pub width_mod: Option<u32>,
+    pub gray: Option<bool>,
+    #[serde(default)]
+    pub audio: AudioConfig,
+    #[serde(default)]
+    pub download: DownloadConfig,
+    #[serde(default)]
+    pub calibration: Calibration,
+    #[serde(default)]
+    pub inputs: InputPolicy,
+    /// 操作の名前ごとに追加するキー
+    #[serde(default)]
+    pub keys: BTreeMap<String, Vec<String>>,
+}
+
+/// `[audio]` の項目
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct AudioConfig {
+    pub enabled: Option<bool>,
+    pub backend: Option<AudioBackendKind>,
+    pub ramp_ms: Option<u64>,
+}
+
+/// `[download]` の項目
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct DownloadConfig {
+    pub browser: Option<String>,
+    /// `500K` や `2M` などの表記のまま持つ
+    pub limit_rate: Option<String>,
+    pub proxy: Option<String>,
+    /// `360p` や `best` などの表記のまま持つ
+    pub quality: Option<String>,
+}
+
+impl Config {
+    /// 設定ファイルを読み込む
+    pub fn load(path: &Path) -> Result<Self> {
+        let text = fs::read_to_string(path)
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+        Self::parse(&text).with_context(|| format!("Invalid {}", path.display()))
+    }
+
+    /// 既定の場所の設定ファイルを読み込む（なければ空の設定）
+    pub fn load_default() -> Result<Self> {
+        match default_path() {
+            Some(path) if path.exists() => Self::load(&path),
+            _ => Ok(Self::default()),
+        }
+    }
+
+    /// TOML テキストを読み込む
+    pub fn parse(text: &str) -> Result<Self> {
+        let config: Self = toml::from_str(text)?;
+        if let Some(index) = config.char_map
+            && index as usize >= CHAR_MAPS.len()
+        {
+            anyhow::bail!("char_map must be between 0 and {}", CHAR_MAPS.len() - 1);
+        }
+        if config.width_mod == Some(0) {
+            anyhow::bail!("width_mod must be 1 or more");
+        }
+        config.limit_rate()?;
+        config.quality()?;
+        config
+            .calibration
+            .validate()
+            .context("Invalid [calibration]")?;
+        config.key_bindings()?;
+        Ok(config)
+    }
+
+    /// `[download]` の `limit_rate`（バイト毎秒）
+    pub fn limit_rate(&self) -> Result<Option<u64>> {
+        self.download
+            .limit_rate
+            .as_deref()
+            .map(downloader::parse_rate)
+            .transpose()
+            .context("Invalid limit_rate")
+    }
+
+    /// `[download]` の `quality`
+    pub fn quality(&self) -> Result<Option<downloader::Quality>> {
+        self.download
+            .quality
+            .as_deref()
+            .map(downloader::parse_quality)
+            .transpose()
+            .context("Invalid quality")
+    }
+
+    /// `[keys]` のキー割り当て
+    pub fn key_bindings(&self) -> Result<KeyBindings> {
+        KeyBindings::parse(&self.keys).context("Invalid [keys]")
+    }
+}
+
+/// ひな形を書き出し、書き出したパスを返す
+///
+/// `path` がなければ既定の場所に書く。すでにあるファイルは `force` のときだけ上書きする
+pub fn init(path: Option<&Path>, force: bool) -> Result<PathBuf> {
+    let path = match path {
+        Some(path) => path.to_path_buf(),
+        None => default_path().context("Cannot find the configuration directory")?,
+    };
+    if path.exists() && !force {
+        anyhow::bail!(
+            "{} already exists (use --force to overwrite)",
+            path.display()
+        );
+    }
+    if let Some(dir) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+        fs::create_dir_all(dir).with_context(|| format!("Failed to create {}", dir.display()))?;
+    }
+    fs::write(&path, TEMPLATE).with_context(|| format!("Failed to write {}", path.display()))?;
+    Ok(path)
+}
+
+/// 色の補正を `[calibration]` に書き込み、書き込んだパスを返す
+///
+/// `path` がなければ既定の場所に書く。ほかの設定やコメントはそのまま残し、
+/// 前の `[calibration]` の表だけを置き換える（ファイルがなければ作る）
+pub fn save_calibration(path: Option<&Path>, calibration: &Calibration) -> Result<PathBuf> {
+    let path = match path {
+        Some(path) => path.to_path_buf(),
+        None => default_path().context("Cannot find the configuration directory")?,
+    };
+    let text = if path.exists() {
+        fs::read_to_string(&path).with_context(|| format!("Failed to read {}", path.display()))?
+    } else {
+        String::new()
+    };
+
+    let mut text = remove_table(&text, "calibration");
+    if !text.is_empty() {
+        text.push('\n');
+    }
+    text.push_str(&calibration.to_toml());
+    // 壊れた設定ファイルに書き足さない
+    Config::parse(&text).with_context(|| format!("Invalid {}", path.display()))?;
+
+    if let Some(dir) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+        fs::create_dir_all(dir).with_context(|| format!("Failed to create {}", dir.display()))?;
+    }
+    fs::write(&path, text).with_context(|| format!("Failed to write {}", path.display()))?;
+    Ok(path)
+}
+
+/// TOML テキストから `[name]` の表（次の表の見出しまで）を取り除く。末尾の空行も削る
+fn remove_table(text: &str, name: &str) -> String {
+    let header = format!("[{}]", name);
+    let mut in_table = false;
+    let mut kept = String::new();
+    for line in text.lines() {
+        let trimmed = line.trim();
+        if trimmed.starts_with('[') {
+            in_table = trimmed.split('#').next().unwrap_or_default().trim_end() == header;
+        }
+        if !in_table {
+            kept.push_str(line);
+            kept.push('\n');
+        }
+    }
+    let end = kept.trim_end().len();
+    kept.truncate(end);
+    if !kept.is_empty() {
+        kept.push('\n');
+    }
+    kept
+}
+
+/// 設定ファイルの既定の場所
+///
+/// `$XDG_CONFIG_HOME/ascii-term/`（未設定なら `~/.config/ascii-term/`）、
+/// Windows では `%APPDATA%\ascii-term\`
+fn default_path() -> Option<PathBuf> {
+    let non_empty = |name: &str| std::env::var_os(name).filter(|value| !value.is_empty());
+
+    let dir = if cfg!(windows) {
+        PathBuf::from(non_empty("APPDATA")?)
+    } else if let Some(config_home) = non_empty("XDG_CONFIG_HOME") {
+        PathBuf::from(config_home)
+    } else {
+        PathBuf::from(non_empty("HOME")?).join(".config")
+    };
+    Some(dir.join("ascii-term").join(CONFIG_FILE_NAME))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_config() {
+        let config = Config::parse(
+            r#"
+            char_map = 3
+            color_mode = "ansi256"
+            width_mod = 2
+
+            [audio]
+            enabled = false
+            backend = "pulse"
+
+            [download]
+            limit_rate = "2M"
+            proxy = "socks5h://127.0.0.1:9050"
+            quality = "480p"
+
+            [inputs]
+            allow = ["youtube.com"]
+
+            [keys]
+            play-pause = ["k"]
+            "#,
+        )
+        .unwrap();
+        assert_eq!(config.char_map, Some(3));
+        assert_eq!(config.color_mode, Some(ColorMode::Ansi256));
+        assert_eq!(config.width_mod, Some(2));
+        assert_eq!(config.audio.enabled, Some(false));
+        assert_eq!(config.audio.backend, Some(AudioBackendKind::Pulse));
+        assert_eq!(config.audio.ramp_ms, None);
+        assert_eq!(config.limit_rate().unwrap(), Some(2 * 1024 * 1024));
+        assert_eq!(
+            config.quality().unwrap(),
+            Some(downloader::Quality::Height(480))
+        );
+        assert_eq!(config.download.browser, None);
+        assert_eq!(config.keys["play-pause"], ["k"]);
+        assert_eq!(config.inputs.allow, ["youtube.com"]);
+        assert!(config.inputs.deny.is_empty());
+    }
+
+    #[test]
+    fn test_parse_rejects_invalid_values() {
+        assert!(Config::parse("char_map = 10").is_err());
+        assert!(Config::parse("width_mod = 0").is_err());
+        assert!(Config::parse("color_mode = \"sepia\"").is_err());
+        assert!(Config::parse("[download]\nlimit_rate = \"fast\"").is_err());
+        assert!(Config::parse("[download]\nquality = \"hd\"").is_err());
+        assert!(Config::parse("[keys]\nrewind = [\"r\"]").is_err());
+        assert!(Config::parse("volume = 3").is_err());
+        assert!(Config::parse("[calibration]\ngamma = [9.0, 1.0, 1.0]").is_err());
+    }
+
+    #[test]
+    fn test_template_is_valid() {
+        assert_eq!(Config::parse(TEMPLATE).unwrap(), Config::default());
+
+        // コメントを外しても読める
+        let uncommented: String = TEMPLATE
+            .lines()
+            .map(|line| match line.strip_prefix("# ") {
+                Some(setting) if setting.contains(" = ") => setting,
+                Some(_) => "",
+                None => line,
+            })
+            .collect::<Vec<_>>()
+            .join("\n");
+        let config = Config::parse(&uncommented).unwrap();
+        assert_eq!(config.char_map, Some(3));
+        assert_eq!(config.audio.backend, Some(AudioBackendKind::Rodio));
+        assert_eq!(config.keys.len(), 3);
+        assert!(config.calibration.is_identity());
+    }
+
+    #[test]
+    fn test_save_calibration_replaces_table() {
+        let path = std::env::temp_dir()
+            .join(format!("ascii-term-calibration-{}", std::process::id()))
+            .join(CONFIG_FILE_NAME);
+        let _ = fs::remove_file(&path);
+
+        let mut calibration = Calibration {
+            gamma: [1.2, 1.0, 0.9],
+            black_point: [8, 8, 8],
+        };
+        save_calibration(Some(&path), &calibration).unwrap();
+        assert_eq!(Config::load(&path).unwrap().calibration, calibration);
+
+        // ほかの表とコメントは残す
+        fs::write(
+            &path,
+            "# mine\nchar_map = 2\n\n[calibration]\ngamma = [2.0, 2.0, 2.0]\n\n[keys]\nhelp = [\"F1\"]\n",
+        )
+        .unwrap();
+        calibration.black_point = [0, 0, 4];
+        save_calibration(Some(&path), &calibration).unwrap();
+        let text = fs::read_to_string(&path).unwrap();
+        assert!(
+            text.starts_with("# mine\nchar_map = 2\n\n[keys]\nhelp = [\"F1\"]\n\n[calibration]")
+        );
+        let config = Config::load(&path).unwrap();
+        assert_eq!(config.calibration, calibration);
+        assert_eq!(config.char_map, Some(2));
+
+        let _ = fs::remove_dir_all(path.parent().unwrap());
+    }
+
+    #[test]
+    fn test_init_writes_template() {
+        let path = std::env::temp_dir()
+            .join(format!("ascii-term-config-{}", std::process::id()))
+            .join(CONFIG_FILE_NAME);
+        let _ = fs::remove_file(&path);
+
+        assert_eq!(init(Some(&path), false).unwrap(), path);
+        assert_eq!(fs::read_to_string(&path).unwrap(), TEMPLATE);
+        // 上書きは --force のときだけ
+        assert!(init(Some(&path), false).is_err());
+        assert!(init(Some(&path), true).is_ok());
+
+        let _ = fs::remove_dir_all(path.parent().unwrap());
+    }
+}
@@ -0,0 +1,378 @@
+//! `demo:` 入力（ライフゲーム・プラズマ・炎）
+//!
+//! 定番のデモエフェクトを手続き的なメディアソースとして実装し、
+//! 通常の映像と同じレンダラーで描画する。文字マップの見比べにも使える
+
+use std::time::Duration;
+
+use anyhow::Result;
+use codec::source::{MediaSource, Raster};
+use codec::video::VideoFrame;
+
+/// デモ入力の接頭辞
+const DEMO_SCHEME: &str = "demo:";
+
+/// パスがデモ入力かどうか
+pub fn is_demo_source(path: &str) -> bool {
+    path.starts_with(DEMO_SCHEME)
+}
+
+/// `demo:life` / `demo:plasma` / `demo:fire` を開く
+pub fn open_demo_source(path: &str) -> Result<Box<dyn MediaSource>> {
+    match path.strip_prefix(DEMO_SCHEME) {
+        Some("life") => Ok(Box::new(Life::default())),
+        Some("plasma") => Ok(Box::new(Plasma)),
+        Some("fire") => Ok(Box::new(Fire::default())),
+        _ => Err(anyhow::anyhow!(
+            "Unknown demo '{}' (expected demo:life, demo:plasma or demo:fire)",
+            path
+        )),
+    }
+}
+
+/// 状態を持つデモ用の簡易な擬似乱数（xorshift32）
+struct XorShift(u32);
+
+impl XorShift {
+    fn next(&mut self) -> u32 {
+        let mut x = self.0;
+        x ^= x << 13;
+        x ^= x >> 17;
+        x ^= x << 5;
+        self.0 = x;
+        x
+    }
+}
+
+/// この世代数を過ぎるか、盤面が変化しなくなったら新しい盤面で始め直す
+const LIFE_MAX_GENERATIONS: u32 = 600;
+
+/// コンウェイのライフゲーム（端はトーラス状につながる）
+struct Life {
+    width: u32,
+    height: u32,
+    cells: Vec<bool>,
+    /// 各セルが生きている世代数（色付けに使う）
+    ages: Vec<u8>,
+    generation: u32,
+    rng: XorShift,
+}
+
+impl Default for Life {
+    fn default() -> Self {
+        Self {
+            width: 0,
+            height: 0,
+            cells: Vec::new(),
+            ages: Vec::new(),
+            generation: 0,
+            rng: XorShift(0x2545_F491),
+        }
+    }
+}
+
+impl Life {
+    fn reseed(&mut self, width: u32, height: u32) {
+        self.width = width;
+        self.height = height;
+        let len = width as usize * height as usize;
+        self.cells = (0..len)
+            .map(|_| self.rng.next().is_multiple_of(4))
+            .collect();
+        self.ages = vec![0; len];
+        self.generation = 0;
+    }
+
+    fn alive_neighbours(&self, x: u32, y: u32) -> usize {
+        let mut count = 0;
+        for dy in [self.height - 1, 0, 1] {
+            for dx in [self.width - 1, 0, 1] {
+                if dx == 0 && dy == 0 {
+                    continue;
+                }
+                let nx = (x + dx) % self.width;
+                let ny = (y + dy) % self.height;
+                if self.cells[(ny * self.width + nx) as usize] {
+                    count += 1;
+                }
+            }
+        }
+        count
+    }
+
+    /// 1 世代進める。盤面が変化したかどうかを返す
+    fn step(&mut self) -> bool {
+        let mut next = vec![false; self.cells.len()];
+        for y in 0..self.height {
+            for x in 0..self.width {
+                let index = (y * self.width + x) as usize;
+                next[index] = matches!(
+                    (self.cells[index], self.alive_neighbours(x, y)),
+                    (true, 2) | (_, 3)
+                );
+            }
+        }
+
+        for (age, &alive) in self.ages.iter_mut().zip(&next) {
+            *age = if alive { age.saturating_add(1) } else { 0 };
+        }
+
+        let changed = next != self.cells;
+        self.cells = next;
+        self.generation += 1;
+        changed
+    }
+}
+
+impl MediaSource for Life {
+    fn name(&self) -> &str {
+        "life"
+    }
+
+    fn frame_rate(&self) -> f64 {
+        12.0
+    }
+
+    fn video_frame(
+        &mut self,
+        timestamp: Duration,
+        width: u32,
+        height: u32,
+    ) -> codec::Result<VideoFrame> {
+        let mut raster = Raster::new(width, height);
+        if width == 0 || height == 0 {
+            return Ok(raster.into_frame(timestamp));
+        }
+
+        let restart = (width, height) != (self.width, self.height)
+            || self.generation >= LIFE_MAX_GENERATIONS
+            || !self.step();
+        if restart {
+            self.reseed(width, height);
+        }
+
+        for (index, &age) in self.ages.iter().enumerate() {
+            if age == 0 {
+                continue;
+            }
+            // 生まれたばかりのセルは明るく、長生きするほど落ち着いた色になる
+            let fade = age.min(32) as u32 * 4;
+            let color = [
+                255u32.saturating_sub(fade * 2).max(40) as u8,
+                (255 - fade) as u8,
+                (120 + fade) as u8,
+            ];
+            raster.put(
+                (index as u32 % width) as i32,
+                (index as u32 / width) as i32,
+                color,
+            );
+        }
+
+        Ok(raster.into_frame(timestamp))
+    }
+}
+
+/// 正弦波を重ねたプラズマ
+struct Plasma;
+
+impl MediaSource for Plasma {
+    fn name(&self) -> &str {
+        "plasma"
+    }
+
+    fn video_frame(
+        &mut self,
+        timestamp: Duration,
+        width: u32,
+        height: u32,
+    ) -> codec::Result<VideoFrame> {
+        let mut raster = Raster::new(width, height);
+        let t = timestamp.as_secs_f32();
+
+        for y in 0..height {
+            for x in 0..width {
+                // 文字セルは縦長なので横方向の座標を半分にして円を保つ
+                let fx = x as f32 * 0.5;
+                let fy = y as f32;
+                let cx = fx - width as f32 * 0.25 + (t * 0.7).sin() * 10.0;
+                let cy = fy - height as f32 * 0.5 + (t * 0.5).cos() * 5.0;
+
+                let value = (fx * 0.16 + t).sin()
+                    + (fy * 0.21 - t * 1.3).sin()
+                    + ((fx + fy) * 0.1 + t * 0.8).sin()
+                    + ((cx * cx + cy * cy).sqrt() * 0.25 - t * 2.0).sin();
+                let phase = value * std::f32::consts::PI / 2.0;
+
+                let channel = |offset: f32| ((phase + offset).sin() * 127.5 + 127.5) as u8;
+                raster.put(
+                    x as i32,
+                    y as i32,
+                    [channel(0.0), channel(2.1), channel(4.2)],
+                );
+            }
+        }
+
+        Ok(raster.into_frame(timestamp))
+    }
+}
+
+/// 炎の熱の最大値（パレットの段数 - 1）
+const FIRE_MAX_HEAT: u8 = 36;
+
+/// 最下段から熱が立ち上る炎（いわゆる DOOM の炎）
+struct Fire {
+    width: u32,
+    height: u32,
+    heat: Vec<u8>,
+    rng: XorShift,
+}
+
+impl Default for Fire {
+    fn default() -> Self {
+        Self {
+            width: 0,
+            height: 0,
+            heat: Vec::new(),
+            rng: XorShift(0x9E37_79B9),
+        }
+    }
+}
+
+impl Fire {
+    fn resize(&mut self, width: u32, height: u32) {
+        self.width = width;
+        self.height = height;
+        self.heat = vec![0; width as usize * height as usize];
+        // 最下段を火元にする
+        let bottom = (height as usize - 1) * width as usize;
+        self.heat[bottom..].fill(FIRE_MAX_HEAT);
+    }
+
+    /// 熱を 1 段上へ運び、ランダムに冷まして横に揺らす
+    fn spread(&mut self) {
+        let width = self.width as usize;
+        for y in 1..self.height as usize {
+            for x in 0..width {
+                let from = y * width + x;
+                let random = self.rng.next();
+                let cooling = (random & 1) as u8;
+                let drift = (random >> 1) % 3;
+                let to_x = (x + width + drift as usize).saturating_sub(1) % width;
+                let to = (y - 1) * width + to_x;
+                self.heat[to] = self.heat[from].saturating_sub(cooling);
+            }
+        }
+    }
+}
+
+/// 熱を黒 → 赤 → 橙 → 黄 → 白の色に変換
+fn fire_color(heat: u8) -> [u8; 3] {
+    let t = heat as f32 / FIRE_MAX_HEAT as f32;
+    let r = (t * 3.0).min(1.0);
+    let g = (t * 3.0 - 1.0).clamp(0.0, 1.0);
+    let b = (t * 3.0 - 2.0).clamp(0.0, 1.0);
+    [(r * 255.0) as u8, (g * 255.0) as u8, (b * 255.0) as u8]
+}
+
+impl MediaSource for Fire {
+    fn name(&self) -> &str {
+        "fire"
+    }
+
+    fn frame_rate(&self) -> f64 {
+        24.0
+    }
+
+    fn video_frame(
+        &mut self,
+        timestamp: Duration,
+        width: u32,
+        height: u32,
+    ) -> codec::Result<VideoFrame> {
+        let mut raster = Raster::new(width, height);
+        if width == 0 || height == 0 {
+            return Ok(raster.into_frame(timestamp));
+        }
+
+        if (width, height) != (self.width, self.height) {
+            self.resize(width, height);
+        }
+        self.spread();
+
+        for (index, &heat) in self.heat.iter().enumerate() {
+            raster.put(
+                (index as u32 % width) as i32,
+                (index as u32 / width) as i32,
+                fire_color(heat),
+            );
+        }
+
+        Ok(raster.into_frame(timestamp))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_open_demo_source() {
+        assert!(is_demo_source("demo:fire"));
+        assert!(!is_demo_source("test:bars"));
+        for name in ["life", "plasma", "fire"] {
+            let source = open_demo_source(&format!("demo:{}", name)).unwrap();
+            assert_eq!(source.name(), name);
+        }
+        assert!(open_demo_source("demo:tunnel").is_err());
+    }
+
+    #[test]
+    fn test_life_blinker_oscillates() {
+        let mut life = Life::default();
+        life.reseed(5, 5);
+        life.cells.fill(false);
+        for x in 1..4 {
+            life.cells[2 * 5 + x] = true;
+        }
+        let horizontal = life.cells.clone();
+
+        assert!(life.step());
+        let vertical: Vec<usize> = (0..25).filter(|&i| life.cells[i]).collect();
+        assert_eq!(vertical, vec![7, 12, 17]);
+
+        life.step();
+        assert_eq!(life.cells, horizontal);
+    }
+
+    #[test]
+    fn test_fire_rises_from_the_bottom() {
+        let mut fire = Fire::default();
+        let first = fire.video_frame(Duration::ZERO, 20, 10).unwrap();
+        for _ in 0..20 {
+            fire.video_frame(Duration::ZERO, 20, 10).unwrap();
+        }
+        let later = fire.video_frame(Duration::ZERO, 20, 10).unwrap();
+
+        let row_heat = |frame: &VideoFrame, y: usize| -> u32 {
+            frame.data[y * 20 * 3..(y + 1) * 20 * 3]
+                .iter()
+                .map(|&c| c as u32)
+                .sum()
+        };
+        assert!(row_heat(&first, 9) > 0);
+        assert!(row_heat(&later, 6) > row_heat(&first, 6));
+        assert_eq!(fire_color(0), [0, 0, 0]);
+        assert_eq!(fire_color(FIRE_MAX_HEAT), [255, 255, 255]);
+    }
+
+    #[test]
+    fn test_plasma_animates() {
+        let mut plasma = Plasma;
+        let a = plasma.video_frame(Duration::ZERO, 16, 8).unwrap();
+        let b = plasma
+            .video_frame(Duration::from_millis(500), 16, 8)
+            .unwrap();
+        assert_ne!(a.data, b.data);
+    }
+}
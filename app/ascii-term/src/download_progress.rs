@@ -0,0 +1,131 @@
+//! URL 入力をダウンロードしている間の進捗表示
+//!
+//! 大きなファイルでも止まって見えないよう、受信量・速度・残り時間を 1 行のバーで
+//! 上書きしながら表示する。端末でなければ何も表示しない
+
+use std::io::{self, IsTerminal, Write};
+use std::time::{Duration, Instant};
+
+use downloader::DownloadProgress;
+
+use crate::estimate::format_bytes;
+use crate::status_bar::format_time;
+
+/// 表示を更新する最短の間隔
+const REDRAW_INTERVAL: Duration = Duration::from_millis(100);
+
+/// バーの幅（文字数）
+const BAR_WIDTH: usize = 24;
+
+/// 上書きしながら表示する進捗の行
+pub struct ProgressLine {
+    enabled: bool,
+    last_draw: Option<Instant>,
+    /// 間引いて表示しなかった最新の進捗
+    latest: Option<DownloadProgress>,
+}
+
+impl ProgressLine {
+    /// 標準出力に表示する
+    pub fn on_stdout() -> Self {
+        Self {
+            enabled: io::stdout().is_terminal(),
+            last_draw: None,
+            latest: None,
+        }
+    }
+
+    pub fn update(&mut self, progress: &DownloadProgress) {
+        if !self.enabled {
+            return;
+        }
+        if self
+            .last_draw
+            .is_some_and(|last| last.elapsed() < REDRAW_INTERVAL)
+        {
+            self.latest = Some(progress.clone());
+            return;
+        }
+        self.draw(progress);
+    }
+
+    fn draw(&mut self, progress: &DownloadProgress) {
+        self.last_draw = Some(Instant::now());
+        self.latest = None;
+        let mut stdout = io::stdout().lock();
+        let _ = write!(stdout, "\r{}\x1b[K", format_progress(progress));
+        let _ = stdout.flush();
+    }
+
+    /// 最後の進捗を表示して、行を改行で確定する
+    pub fn finish(&mut self) {
+        if let Some(progress) = self.latest.take() {
+            self.draw(&progress);
+        }
+        if self.last_draw.take().is_some() {
+            println!();
+        }
+    }
+}
+
+impl Drop for ProgressLine {
+    fn drop(&mut self) {
+        self.finish();
+    }
+}
+
+/// `[#####-------]  45%  12.3 MiB / 27.0 MiB  2.1 MiB/s  ETA 0:07`
+///
+/// 大きさが分からなければ、受信量と速度だけを表示する
+pub fn format_progress(progress: &DownloadProgress) -> String {
+    let mut parts = Vec::new();
+    match (progress.fraction(), progress.total) {
+        (Some(fraction), Some(total)) => {
+            let filled = (fraction * BAR_WIDTH as f64).round() as usize;
+            parts.push(format!(
+                "[{}{}] {:>3.0}%",
+                "#".repeat(filled),
+                "-".repeat(BAR_WIDTH - filled),
+                fraction * 100.0
+            ));
+            parts.push(format!(
+                "{} / {}",
+                format_bytes(progress.downloaded),
+                format_bytes(total)
+            ));
+        }
+        _ => parts.push(format_bytes(progress.downloaded)),
+    }
+    if let Some(speed) = progress.speed {
+        parts.push(format!("{}/s", format_bytes(speed as u64)));
+    }
+    if let Some(eta) = progress.eta {
+        parts.push(format!("ETA {}", format_time(eta)));
+    }
+    parts.join("  ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_progress() {
+        let progress = DownloadProgress {
+            downloaded: 5 * 1024 * 1024,
+            total: Some(20 * 1024 * 1024),
+            speed: Some(2.0 * 1024.0 * 1024.0),
+            eta: Some(Duration::from_secs(75)),
+        };
+        assert_eq!(
+            format_progress(&progress),
+            "[######------------------]  25%  5.0 MiB / 20.0 MiB  2.0 MiB/s  ETA 1:15"
+        );
+
+        let progress = DownloadProgress {
+            downloaded: 512,
+            ..Default::default()
+        };
+        assert_eq!(format_progress(&progress), "512 B");
+    }
+}
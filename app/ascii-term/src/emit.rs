@@ -0,0 +1,204 @@
+//! 描画結果をターミナルではなく標準出力へ機械可読な形式で書き出す
+//!
+//! Web フロントエンドやボットなど、後段のツールでフレームを使うためのモード。
+//! 標準出力をフレーム専用にするため、Unix では以降のログ出力を標準エラーへ移す
+
+use std::io::{self, Write};
+use std::time::{Duration, Instant};
+
+use anyhow::Result;
+use clap::ValueEnum;
+use serde::Serialize;
+
+use crate::ansimovie::AnsiMovieWriter;
+use crate::renderer::{Cell, RenderedFrame};
+
+/// 出力形式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum EmitFormat {
+    /// 1 フレームを 1 行の JSON で書き出す（JSON Lines）
+    Jsonl,
+    /// キーフレームと差分フレームを zstd で圧縮した録画（`.ansimovie`）
+    Ansimovie,
+}
+
+/// JSON Lines の 1 行
+#[derive(Serialize)]
+struct JsonFrame<'a> {
+    /// 最初のフレームからの経過時間（秒）
+    timestamp: f64,
+    width: u32,
+    height: u32,
+    /// 行優先で `width * height` 個のセル
+    cells: &'a [Cell],
+}
+
+/// 形式ごとの書き出し先
+enum Output {
+    Jsonl(Box<dyn Write + Send>),
+    Ansimovie(AnsiMovieWriter<Box<dyn Write + Send>>),
+}
+
+/// フレームを書き出す出力先
+pub struct Emitter {
+    output: Output,
+    started: Option<Instant>,
+}
+
+impl Emitter {
+    pub fn new(format: EmitFormat, out: Box<dyn Write + Send>) -> Result<Self> {
+        let output = match format {
+            EmitFormat::Jsonl => Output::Jsonl(out),
+            EmitFormat::Ansimovie => Output::Ansimovie(AnsiMovieWriter::new(out)?),
+        };
+        Ok(Self {
+            output,
+            started: None,
+        })
+    }
+
+    /// 標準出力へ書き出す。ログが混ざらないよう、標準出力への `println!` は標準エラーへ移す
+    pub fn to_stdout(format: EmitFormat) -> Result<Self> {
+        Self::new(format, take_stdout()?)
+    }
+
+    /// フレームを 1 つ書き出す（後段がすぐ読めるよう毎回フラッシュする）
+    pub fn emit(&mut self, frame: &RenderedFrame) -> Result<()> {
+        let timestamp = match self.started {
+            Some(started) => started.elapsed(),
+            None => {
+                self.started = Some(Instant::now());
+                Duration::ZERO
+            }
+        };
+        match &mut self.output {
+            Output::Jsonl(out) => {
+                let line = JsonFrame {
+                    timestamp: timestamp.as_secs_f64(),
+                    width: frame.width,
+                    height: frame.height,
+                    cells: &frame.cells,
+                };
+                serde_json::to_writer(&mut *out, &line)?;
+                out.write_all(b"\n")?;
+                out.flush()?;
+            }
+            Output::Ansimovie(writer) => {
+                writer.write_frame(timestamp, frame)?;
+                writer.flush()?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// `.ansimovie` は終わりに索引を書き込む（書けなくても索引なしで読める）
+impl Drop for Emitter {
+    fn drop(&mut self) {
+        if let Output::Ansimovie(writer) = &mut self.output {
+            let _ = writer.finish();
+        }
+    }
+}
+
+/// 元の標準出力を複製して返し、ファイル記述子 1 を標準エラーに付け替える
+#[cfg(unix)]
+fn take_stdout() -> Result<Box<dyn Write + Send>> {
+    use std::fs::File;
+    use std::os::fd::{AsFd, AsRawFd};
+
+    io::stdout().flush()?;
+    let original = File::from(io::stdout().as_fd().try_clone_to_owned()?);
+    // SAFETY: どちらも開いているファイル記述子で、dup2 は 1 を閉じてから複製するだけ
+    if unsafe { libc::dup2(io::stderr().as_raw_fd(), io::stdout().as_raw_fd()) } < 0 {
+        return Err(io::Error::last_os_error().into());
+    }
+    Ok(Box::new(original))
+}
+
+/// Unix 以外ではログを付け替えられないため、そのまま標準出力へ書き出す
+#[cfg(not(unix))]
+fn take_stdout() -> Result<Box<dyn Write + Send>> {
+    Ok(Box::new(io::stdout()))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use super::*;
+    use crate::ansimovie::AnsiMovieReader;
+
+    /// 書き込まれた内容をテストから読めるバッファ
+    #[derive(Clone, Default)]
+    struct SharedBuffer(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for SharedBuffer {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.lock().unwrap().write(buf)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_emit_jsonl() {
+        let buffer = SharedBuffer::default();
+        let mut emitter = Emitter::new(EmitFormat::Jsonl, Box::new(buffer.clone())).unwrap();
+        let frame = RenderedFrame {
+            cells: vec![
+                Cell::new('#', [255, 0, 0]),
+                Cell {
+                    bg: Some([0, 0, 255]),
+                    ..Cell::new(' ', [0, 0, 0])
+                },
+            ],
+            width: 2,
+            height: 1,
+            sixel: None,
+        };
+        emitter.emit(&frame).unwrap();
+        emitter.emit(&frame).unwrap();
+
+        let output = String::from_utf8(buffer.0.lock().unwrap().clone()).unwrap();
+        let lines: Vec<&str> = output.lines().collect();
+        assert_eq!(lines.len(), 2);
+
+        let first: serde_json::Value = serde_json::from_str(lines[0]).unwrap();
+        assert_eq!(first["width"], 2);
+        assert_eq!(first["height"], 1);
+        assert_eq!(first["timestamp"], 0.0);
+        assert_eq!(
+            first["cells"][0],
+            serde_json::json!({ "ch": "#", "fg": [255, 0, 0] })
+        );
+        assert_eq!(first["cells"][1]["bg"], serde_json::json!([0, 0, 255]));
+
+        let second: serde_json::Value = serde_json::from_str(lines[1]).unwrap();
+        assert!(second["timestamp"].as_f64().unwrap() >= 0.0);
+    }
+
+    #[test]
+    fn test_emit_ansimovie() {
+        let buffer = SharedBuffer::default();
+        let mut emitter = Emitter::new(EmitFormat::Ansimovie, Box::new(buffer.clone())).unwrap();
+        let frame = RenderedFrame {
+            cells: vec![Cell::new('#', [255, 0, 0]); 6],
+            width: 3,
+            height: 2,
+            sixel: None,
+        };
+        emitter.emit(&frame).unwrap();
+        emitter.emit(&frame).unwrap();
+        drop(emitter);
+
+        let bytes = buffer.0.lock().unwrap().clone();
+        let mut reader = AnsiMovieReader::open(io::Cursor::new(bytes)).unwrap();
+        assert_eq!(reader.index().len(), 1);
+        assert_eq!(reader.next_frame().unwrap().unwrap().1.cells, frame.cells);
+        assert_eq!(reader.next_frame().unwrap().unwrap().1.cells, frame.cells);
+        assert!(reader.next_frame().unwrap().is_none());
+    }
+}
@@ -0,0 +1,76 @@
+//! エラーをユーザー向けのメッセージにする
+//!
+//! FFmpeg などのエラーをそのまま見せるのではなく、よくある失敗（コーデックがない、
+//! ファイルが壊れているなど）を分類して対処方法を添える
+
+use codec::{ErrorCategory, MediaError};
+
+use crate::i18n::{Msg, tr};
+
+/// エラーの原因をたどって分類する
+fn category(error: &anyhow::Error) -> ErrorCategory {
+    error
+        .chain()
+        .find_map(|cause| {
+            cause
+                .downcast_ref::<MediaError>()
+                .map(MediaError::category)
+                .or_else(|| {
+                    cause
+                        .downcast_ref::<std::io::Error>()
+                        .map(ErrorCategory::from_io)
+                })
+        })
+        .unwrap_or(ErrorCategory::Other)
+}
+
+/// ターミナルに表示する、対処方法つきのメッセージ
+pub fn user_message(error: &anyhow::Error) -> String {
+    match category(error).hint() {
+        Some(hint) => format!("{:#}\nHint: {}", error, hint),
+        None => format!("{:#}", error),
+    }
+}
+
+/// OSD に表示する短いメッセージ
+pub fn osd_message(context: Msg, error: &anyhow::Error) -> String {
+    let label = match category(error) {
+        ErrorCategory::NotFound => Msg::FileNotFound,
+        ErrorCategory::PermissionDenied => Msg::PermissionDenied,
+        ErrorCategory::MissingCodec => Msg::CodecNotAvailable,
+        ErrorCategory::TruncatedFile => Msg::FileDamaged,
+        ErrorCategory::UnsupportedPixelFormat => Msg::UnsupportedPixelFormat,
+        ErrorCategory::Network => Msg::NetworkError,
+        ErrorCategory::Other => return tr(context).to_string(),
+    };
+    format!("{}: {}", tr(context), tr(label))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_user_message_with_hint() {
+        let error = anyhow::Error::from(MediaError::UnsupportedCodec("prores".to_string()))
+            .context("Failed to open clip.mov");
+        let message = user_message(&error);
+        assert!(message.starts_with("Failed to open clip.mov: Unsupported codec: prores"));
+        assert!(message.contains("\nHint: "));
+        assert_eq!(
+            osd_message(Msg::DecodingFailed, &error),
+            format!(
+                "{}: {}",
+                tr(Msg::DecodingFailed),
+                tr(Msg::CodecNotAvailable)
+            )
+        );
+    }
+
+    #[test]
+    fn test_user_message_without_hint() {
+        let error = anyhow::anyhow!("Unknown media type");
+        assert_eq!(user_message(&error), "Unknown media type");
+        assert_eq!(osd_message(Msg::SeekFailed, &error), tr(Msg::SeekFailed));
+    }
+}
@@ -0,0 +1,217 @@
+//! 出力サイズ・帯域・処理時間の見積もり
+//!
+//! 長い書き出し（`--emit`）やリモート端末での再生の前に、先頭の短い区間だけを
+//! 実際にデコード・描画・エンコードして、1 フレームあたりのバイト数と処理時間から
+//! 全体を見積もる
+
+use std::io::{self, Write};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+
+use anyhow::Result;
+use codec::video::VideoDecoder;
+
+use crate::ansi;
+use crate::emit::{EmitFormat, Emitter};
+use crate::renderer::{AsciiRenderer, RenderConfig, Renderer};
+use crate::status_bar::format_time;
+
+/// 見積もりのために処理する区間の長さ
+const SAMPLE_DURATION: Duration = Duration::from_secs(3);
+
+/// 処理するフレーム数の上限
+const MAX_SAMPLE_FRAMES: u64 = 120;
+
+/// 先頭区間を処理した結果
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SampleStats {
+    /// 処理したフレーム数
+    pub frames: u64,
+    /// ターミナルへ出力する ANSI のバイト数
+    pub ansi_bytes: u64,
+    /// `--emit` で書き出すバイト数（書き出さない場合は None）
+    pub emit_bytes: Option<u64>,
+    /// デコード・描画・エンコードにかかった時間
+    pub elapsed: Duration,
+}
+
+/// 全体の見積もり
+#[derive(Debug, Clone, PartialEq)]
+pub struct Estimate {
+    pub total_frames: u64,
+    /// ターミナル出力の帯域（バイト/秒）
+    pub ansi_bytes_per_second: f64,
+    /// `--emit` の出力サイズ
+    pub emit_bytes: Option<u64>,
+    /// 処理にかかる時間（再生は実時間なので、メディアの長さより短くはならない）
+    pub processing_time: Duration,
+    /// 処理が実時間に追いつかない場合 true
+    pub slower_than_realtime: bool,
+}
+
+impl SampleStats {
+    /// 長さ `duration`、フレームレート `fps` のメディア全体に当てはめる
+    pub fn extrapolate(&self, duration: Duration, fps: f64) -> Option<Estimate> {
+        if self.frames == 0 || fps <= 0.0 {
+            return None;
+        }
+
+        let total_frames = (duration.as_secs_f64() * fps).round() as u64;
+        let per_frame = |bytes: u64| bytes as f64 / self.frames as f64;
+        let frame_time = self.elapsed.as_secs_f64() / self.frames as f64;
+        let compute_time = Duration::from_secs_f64(frame_time * total_frames as f64);
+
+        Some(Estimate {
+            total_frames,
+            ansi_bytes_per_second: per_frame(self.ansi_bytes) * fps,
+            emit_bytes: self
+                .emit_bytes
+                .map(|bytes| (per_frame(bytes) * total_frames as f64).round() as u64),
+            processing_time: compute_time.max(duration),
+            slower_than_realtime: frame_time * fps > 1.0,
+        })
+    }
+}
+
+impl Estimate {
+    /// 表示用の複数行テキスト
+    pub fn summary(&self) -> String {
+        let mut lines = vec![
+            format!("  Frames: {}", self.total_frames),
+            format!(
+                "  Terminal bandwidth: {}/s",
+                format_bytes(self.ansi_bytes_per_second as u64)
+            ),
+        ];
+        if let Some(bytes) = self.emit_bytes {
+            lines.push(format!("  Output size: {}", format_bytes(bytes)));
+        }
+        lines.push(format!(
+            "  Time: {}{}",
+            format_time(self.processing_time),
+            if self.slower_than_realtime {
+                " (slower than real time; frames will be dropped during playback)"
+            } else {
+                ""
+            }
+        ));
+        lines.join("\n")
+    }
+}
+
+/// 先頭区間をデコード・描画・エンコードしてバイト数と処理時間を測る
+pub fn sample_video(
+    path: &str,
+    video_track: Option<usize>,
+    render_config: RenderConfig,
+    fps: f64,
+    emit: Option<EmitFormat>,
+) -> Result<SampleStats> {
+    let mut decoder = VideoDecoder::new(path, 0, 0, video_track)?;
+    let color_mode = render_config.color_mode;
+    let mut renderer = AsciiRenderer::new(render_config);
+    let emit_bytes = Arc::new(AtomicU64::new(0));
+    let mut emitter = emit
+        .map(|format| Emitter::new(format, Box::new(CountingWriter(Arc::clone(&emit_bytes)))))
+        .transpose()?;
+
+    let max_frames =
+        ((SAMPLE_DURATION.as_secs_f64() * fps).ceil() as u64).clamp(1, MAX_SAMPLE_FRAMES);
+    let mut stats = SampleStats::default();
+    let started = Instant::now();
+    while stats.frames < max_frames {
+        let Some(frame) = decoder.decode_one()? else {
+            break;
+        };
+        let rendered = renderer.render_video_frame(&frame)?;
+        stats.ansi_bytes += ansi::encode(&rendered, color_mode).len() as u64;
+        if let Some(emitter) = emitter.as_mut() {
+            emitter.emit(&rendered)?;
+        }
+        stats.frames += 1;
+    }
+    stats.elapsed = started.elapsed();
+    stats.emit_bytes = emitter.map(|_| emit_bytes.load(Ordering::Relaxed));
+    Ok(stats)
+}
+
+/// 書き込まれたバイト数だけを数える出力先
+struct CountingWriter(Arc<AtomicU64>);
+
+impl Write for CountingWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.fetch_add(buf.len() as u64, Ordering::Relaxed);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// バイト数を KiB / MiB / GiB で表示する
+pub fn format_bytes(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["B", "KiB", "MiB", "GiB"];
+    let mut value = bytes as f64;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{} B", bytes)
+    } else {
+        format!("{:.1} {}", value, UNITS[unit])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_extrapolate() {
+        let stats = SampleStats {
+            frames: 30,
+            ansi_bytes: 30 * 1000,
+            emit_bytes: Some(30 * 5000),
+            elapsed: Duration::from_millis(300),
+        };
+        let estimate = stats.extrapolate(Duration::from_secs(60), 30.0).unwrap();
+        assert_eq!(estimate.total_frames, 1800);
+        assert_eq!(estimate.ansi_bytes_per_second, 30_000.0);
+        assert_eq!(estimate.emit_bytes, Some(1800 * 5000));
+        // 処理は実時間より速いので、再生時間がそのままかかる
+        assert_eq!(estimate.processing_time, Duration::from_secs(60));
+        assert!(!estimate.slower_than_realtime);
+    }
+
+    #[test]
+    fn test_extrapolate_slow_processing() {
+        let stats = SampleStats {
+            frames: 10,
+            ansi_bytes: 10,
+            emit_bytes: None,
+            elapsed: Duration::from_secs(1),
+        };
+        // 1 フレーム 100ms は 30fps に追いつかない
+        let estimate = stats.extrapolate(Duration::from_secs(10), 30.0).unwrap();
+        assert!(estimate.slower_than_realtime);
+        assert_eq!(estimate.processing_time, Duration::from_secs(30));
+        assert!(estimate.summary().contains("slower than real time"));
+
+        assert_eq!(
+            SampleStats::default().extrapolate(Duration::from_secs(1), 30.0),
+            None
+        );
+    }
+
+    #[test]
+    fn test_format_bytes() {
+        assert_eq!(format_bytes(512), "512 B");
+        assert_eq!(format_bytes(1536), "1.5 KiB");
+        assert_eq!(format_bytes(5 * 1024 * 1024), "5.0 MiB");
+        assert_eq!(format_bytes(3 * 1024 * 1024 * 1024), "3.0 GiB");
+    }
+}
@@ -0,0 +1,156 @@
+//! 描画結果のハッシュ（描画の回帰テスト用）
+//!
+//! すべてのフレームを実時間に合わせずに描画し、フレーム番号・タイムスタンプ・
+//! セルのハッシュを 1 行ずつ書き出す。基準のメディアで出力を比べれば、
+//! フレームを丸ごと保存しなくても描画の変化に気づける。
+//! 結果がターミナルの大きさに左右されないよう、描画する大きさは固定する
+
+use std::io::Write;
+
+use anyhow::Result;
+use codec::video::VideoFrame;
+
+use crate::renderer::{AsciiRenderer, RenderConfig, RenderedFrame, Renderer};
+
+/// ハッシュを取るときの描画の大きさ（文字数）
+pub const HASH_COLUMNS: u32 = 80;
+pub const HASH_ROWS: u32 = 24;
+
+/// 終わりのない入力（`test:bars` など）で、数を指定しない場合にハッシュするフレーム数
+pub const DEFAULT_SOURCE_FRAMES: u64 = 150;
+
+const FNV_OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+const FNV_PRIME: u64 = 0x0000_0100_0000_01b3;
+
+/// バージョンやプラットフォームで変わらないハッシュ（FNV-1a 64 ビット）
+#[derive(Debug, Clone, Copy)]
+struct Fnv1a(u64);
+
+impl Fnv1a {
+    fn new() -> Self {
+        Self(FNV_OFFSET_BASIS)
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        for &byte in bytes {
+            self.0 ^= byte as u64;
+            self.0 = self.0.wrapping_mul(FNV_PRIME);
+        }
+    }
+}
+
+/// 大きさと、すべてのセルの文字・文字色・背景色から求めたハッシュ
+pub fn hash_frame(frame: &RenderedFrame) -> u64 {
+    let mut hasher = Fnv1a::new();
+    hasher.write(&frame.width.to_le_bytes());
+    hasher.write(&frame.height.to_le_bytes());
+    for cell in &frame.cells {
+        hasher.write(&(cell.ch as u32).to_le_bytes());
+        hasher.write(&cell.fg);
+        match cell.bg {
+            Some(bg) => {
+                hasher.write(&[1]);
+                hasher.write(&bg);
+            }
+            None => hasher.write(&[0]),
+        }
+    }
+    hasher.0
+}
+
+/// `frames` を順に描画し、`フレーム番号<TAB>タイムスタンプ（秒）<TAB>ハッシュ` を書き出す
+///
+/// `limit` があればその数で止める。書き出したフレーム数を返す
+pub fn hash_frames<I>(
+    frames: I,
+    render_config: RenderConfig,
+    limit: Option<u64>,
+    out: &mut impl Write,
+) -> Result<u64>
+where
+    I: IntoIterator<Item = Result<VideoFrame>>,
+{
+    let mut renderer = AsciiRenderer::new(render_config);
+    let mut count = 0;
+    for frame in frames {
+        if limit.is_some_and(|limit| count >= limit) {
+            break;
+        }
+        let frame = frame?;
+        let rendered = renderer.render_video_frame(&frame)?;
+        writeln!(
+            out,
+            "{}\t{:.6}\t{:016x}",
+            count,
+            frame.timestamp.as_secs_f64(),
+            hash_frame(&rendered)
+        )?;
+        count += 1;
+    }
+    out.flush()?;
+    Ok(count)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::renderer::Cell;
+    use codec::source::Raster;
+    use std::time::Duration;
+
+    fn frame(cells: Vec<Cell>, width: u32) -> RenderedFrame {
+        RenderedFrame {
+            height: cells.len() as u32 / width,
+            cells,
+            width,
+            sixel: None,
+        }
+    }
+
+    #[test]
+    fn test_hash_is_stable() {
+        // 書き出したハッシュと比べるので、値そのものが変わってはいけない
+        assert_eq!(Fnv1a::new().0, FNV_OFFSET_BASIS);
+        let mut hasher = Fnv1a::new();
+        hasher.write(b"a");
+        assert_eq!(hasher.0, 0xaf63_dc4c_8601_ec8c);
+
+        let blank = frame(vec![Cell::BLANK; 4], 2);
+        assert_eq!(hash_frame(&blank), hash_frame(&blank.clone()));
+    }
+
+    #[test]
+    fn test_hash_covers_layout_and_colors() {
+        let blank = frame(vec![Cell::BLANK; 4], 2);
+        let reshaped = frame(vec![Cell::BLANK; 4], 4);
+        assert_ne!(hash_frame(&blank), hash_frame(&reshaped));
+
+        let mut colored = blank.clone();
+        colored.cells[3].fg = [0, 0, 1];
+        assert_ne!(hash_frame(&blank), hash_frame(&colored));
+
+        let mut with_bg = blank.clone();
+        with_bg.cells[0].bg = Some([0, 0, 0]);
+        assert_ne!(hash_frame(&blank), hash_frame(&with_bg));
+    }
+
+    #[test]
+    fn test_hash_frames_output() {
+        let frames = (0..5).map(|i| {
+            let mut raster = Raster::new(32, 16);
+            raster.fill([i * 40, 0, 0]);
+            Ok(raster.into_frame(Duration::from_millis(i as u64 * 40)))
+        });
+        let mut out = Vec::new();
+        let count = hash_frames(frames, RenderConfig::default(), Some(3), &mut out).unwrap();
+        assert_eq!(count, 3);
+
+        let text = String::from_utf8(out).unwrap();
+        let lines: Vec<Vec<&str>> = text.lines().map(|l| l.split('\t').collect()).collect();
+        assert_eq!(lines.len(), 3);
+        assert_eq!(lines[1][0], "1");
+        assert_eq!(lines[1][1], "0.040000");
+        assert_eq!(lines[1][2].len(), 16);
+        assert_ne!(lines[0][2], lines[1][2]);
+    }
+}
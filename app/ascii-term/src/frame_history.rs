@@ -0,0 +1,137 @@
+//! 一時停止中のコマ送り・コマ戻しに使う、表示したフレームの履歴
+//!
+//! 描画に使ったフレーム（切り抜き後）を古い順に保持する。解像度の高い動画でも
+//! メモリを使いすぎないよう、フレーム数ではなく合計のバイト数で古いものから捨てる
+
+use std::collections::VecDeque;
+use std::time::Duration;
+
+use codec::video::VideoFrame;
+
+/// 履歴に保持するフレームの合計の大きさの既定値
+pub const DEFAULT_HISTORY_BYTES: usize = 64 * 1024 * 1024;
+
+/// 表示したフレームと、その再生位置
+#[derive(Debug)]
+pub struct FrameHistory {
+    frames: VecDeque<(Duration, VideoFrame)>,
+    max_bytes: usize,
+    bytes: usize,
+    /// 表示中のフレームの位置（0 が最新。コマ戻しで増える）
+    cursor: usize,
+}
+
+impl FrameHistory {
+    pub fn new(max_bytes: usize) -> Self {
+        Self {
+            frames: VecDeque::new(),
+            max_bytes,
+            bytes: 0,
+            cursor: 0,
+        }
+    }
+
+    /// 新しく表示したフレームを加える（コマ戻しの位置は最新に戻る）
+    ///
+    /// 上限を超えても最新の 1 枚は残す
+    pub fn push(&mut self, position: Duration, frame: VideoFrame) {
+        self.cursor = 0;
+        self.bytes += frame.data.len();
+        self.frames.push_back((position, frame));
+        while self.bytes > self.max_bytes && self.frames.len() > 1 {
+            if let Some((_, oldest)) = self.frames.pop_front() {
+                self.bytes -= oldest.data.len();
+            }
+        }
+    }
+
+    /// 最新のフレームを表示中かどうか（コマ送りで新しくデコードする必要がある）
+    pub fn at_latest(&self) -> bool {
+        self.cursor == 0
+    }
+
+    /// 1 つ前のフレーム。最も古いフレームを表示中なら None
+    pub fn step_backward(&mut self) -> Option<&(Duration, VideoFrame)> {
+        if self.cursor + 1 >= self.frames.len() {
+            return None;
+        }
+        self.cursor += 1;
+        self.current()
+    }
+
+    /// 1 つ後のフレーム。最新のフレームを表示中なら None
+    pub fn step_forward(&mut self) -> Option<&(Duration, VideoFrame)> {
+        if self.cursor == 0 {
+            return None;
+        }
+        self.cursor -= 1;
+        self.current()
+    }
+
+    fn current(&self) -> Option<&(Duration, VideoFrame)> {
+        self.frames
+            .get(self.frames.len().checked_sub(self.cursor + 1)?)
+    }
+
+    /// 移動やループで続きでなくなったフレームを捨てる
+    pub fn clear(&mut self) {
+        self.frames.clear();
+        self.bytes = 0;
+        self.cursor = 0;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use codec::video::frame::FrameFormat;
+
+    fn frame(size: usize) -> VideoFrame {
+        VideoFrame::new(vec![0; size], 1, 1, FrameFormat::RGB8, Duration::ZERO, 0)
+    }
+
+    fn seconds(entry: Option<&(Duration, VideoFrame)>) -> Option<u64> {
+        entry.map(|(position, _)| position.as_secs())
+    }
+
+    #[test]
+    fn test_step_backward_and_forward() {
+        let mut history = FrameHistory::new(1024);
+        for second in 1..=3 {
+            history.push(Duration::from_secs(second), frame(3));
+        }
+        assert!(history.at_latest());
+        assert_eq!(seconds(history.step_forward()), None);
+        assert_eq!(seconds(history.step_backward()), Some(2));
+        assert_eq!(seconds(history.step_backward()), Some(1));
+        assert_eq!(seconds(history.step_backward()), None);
+        assert!(!history.at_latest());
+        assert_eq!(seconds(history.step_forward()), Some(2));
+        assert_eq!(seconds(history.step_forward()), Some(3));
+        assert!(history.at_latest());
+
+        // 新しいフレームで最新に戻る
+        history.step_backward();
+        history.push(Duration::from_secs(4), frame(3));
+        assert!(history.at_latest());
+        assert_eq!(seconds(history.step_backward()), Some(3));
+    }
+
+    #[test]
+    fn test_oldest_frames_are_dropped() {
+        let mut history = FrameHistory::new(10);
+        for second in 1..=4 {
+            history.push(Duration::from_secs(second), frame(4));
+        }
+        // 4 バイトのフレームは 2 枚まで
+        assert_eq!(seconds(history.step_backward()), Some(3));
+        assert_eq!(seconds(history.step_backward()), None);
+
+        // 上限より大きくても最新の 1 枚は残す
+        history.push(Duration::from_secs(5), frame(100));
+        assert_eq!(history.frames.len(), 1);
+
+        history.clear();
+        assert_eq!(seconds(history.step_backward()), None);
+    }
+}
@@ -0,0 +1,173 @@
+//! 描画したフレームを外部コマンドの標準入力へ送る（`--pipe-frames`）
+//!
+//! LED ウォールやネットワークへの中継など、独自の出力先をこのクレートを変えずに
+//! 作れるようにする。1 フレームは「4 バイトのビッグエンディアンの長さ」と、
+//! その長さの ANSI エスケープシーケンス（Sixel 出力では Sixel）で送る。
+//! 書き込みは専用のスレッドで行い、コマンドが読むのに追いつかなければフレームを捨てる
+
+use std::io::{self, Write};
+use std::process::{Child, Command, Stdio};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use anyhow::{Context, Result};
+use crossbeam_channel::{Sender, bounded};
+
+use crate::ansi::{self, ColorMode};
+use crate::renderer::RenderedFrame;
+
+/// 書き込み待ちにしておくフレーム数
+const QUEUE_LENGTH: usize = 4;
+
+/// 標準入力を閉じてからコマンドの終了を待つ時間（過ぎたら終了させる）
+const EXIT_TIMEOUT: Duration = Duration::from_secs(2);
+
+pub struct FramePipe {
+    child: Child,
+    frame_tx: Option<Sender<Vec<u8>>>,
+    writer: Option<thread::JoinHandle<()>>,
+}
+
+impl FramePipe {
+    /// シェル経由で `command` を起動する
+    pub fn spawn(command: &str) -> Result<Self> {
+        let mut child = shell(command)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::null())
+            .spawn()
+            .with_context(|| format!("Failed to start --pipe-frames command: {}", command))?;
+        let stdin = child
+            .stdin
+            .take()
+            .context("--pipe-frames command has no stdin")?;
+
+        let (frame_tx, frame_rx) = bounded::<Vec<u8>>(QUEUE_LENGTH);
+        let writer = thread::spawn(move || {
+            let mut stdin = stdin;
+            for payload in frame_rx {
+                if let Err(e) = write_frame(&mut stdin, &payload) {
+                    eprintln!("Warning: --pipe-frames command stopped reading: {}", e);
+                    break;
+                }
+            }
+        });
+
+        Ok(Self {
+            child,
+            frame_tx: Some(frame_tx),
+            writer: Some(writer),
+        })
+    }
+
+    /// フレームを送る。コマンドが読むのに追いついていないか、終了していれば捨てる
+    pub fn send(&self, frame: &RenderedFrame) {
+        let Some(frame_tx) = &self.frame_tx else {
+            return;
+        };
+        // 捨てることになるフレームはエンコードしない
+        if frame_tx.is_full() {
+            return;
+        }
+        let payload = match &frame.sixel {
+            Some(sixel) => sixel.to_vec(),
+            None => ansi::encode(frame, ColorMode::Truecolor),
+        };
+        let _ = frame_tx.try_send(payload);
+    }
+}
+
+impl Drop for FramePipe {
+    fn drop(&mut self) {
+        // 標準入力を閉じて、コマンドが終わるのを待つ。読まずに止まっているコマンドで
+        // 終了できなくならないよう、時間内に終わらなければ終了させる
+        self.frame_tx = None;
+        let deadline = Instant::now() + EXIT_TIMEOUT;
+        while matches!(self.child.try_wait(), Ok(None)) && Instant::now() < deadline {
+            thread::sleep(Duration::from_millis(10));
+        }
+        if matches!(self.child.try_wait(), Ok(None)) {
+            let _ = self.child.kill();
+        }
+        let _ = self.child.wait();
+        // 終了したコマンドへの書き込みは失敗するので、書き込みのスレッドも終わる
+        if let Some(writer) = self.writer.take() {
+            let _ = writer.join();
+        }
+    }
+}
+
+/// 長さ（4 バイト、ビッグエンディアン）に続けてフレームを書き込む
+fn write_frame(out: &mut impl Write, payload: &[u8]) -> io::Result<()> {
+    let length = u32::try_from(payload.len())
+        .map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "frame too large"))?;
+    out.write_all(&length.to_be_bytes())?;
+    out.write_all(payload)?;
+    out.flush()
+}
+
+#[cfg(unix)]
+fn shell(command: &str) -> Command {
+    let mut shell = Command::new("sh");
+    shell.arg("-c").arg(command);
+    shell
+}
+
+#[cfg(not(unix))]
+fn shell(command: &str) -> Command {
+    let mut shell = Command::new("cmd");
+    shell.arg("/C").arg(command);
+    shell
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::renderer::Cell;
+
+    #[test]
+    fn test_write_frame() {
+        let mut out = Vec::new();
+        write_frame(&mut out, b"abc").unwrap();
+        write_frame(&mut out, b"").unwrap();
+        assert_eq!(out, [0, 0, 0, 3, b'a', b'b', b'c', 0, 0, 0, 0]);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_pipe_to_command() {
+        let path = std::env::temp_dir().join(format!("ascii-term-pipe-{}", std::process::id()));
+        let pipe = FramePipe::spawn(&format!("cat > '{}'", path.display())).unwrap();
+        let frame = RenderedFrame {
+            cells: vec![Cell::new('#', [255, 0, 0])],
+            width: 1,
+            height: 1,
+            sixel: None,
+        };
+        pipe.send(&frame);
+        drop(pipe);
+
+        let written = std::fs::read(&path).unwrap();
+        let _ = std::fs::remove_file(&path);
+        let payload = ansi::encode(&frame, ColorMode::Truecolor);
+        assert_eq!(written[..4], (payload.len() as u32).to_be_bytes());
+        assert_eq!(written[4..], payload[..]);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_drop_stops_command_that_does_not_read() {
+        let pipe = FramePipe::spawn("sleep 30").unwrap();
+        let frame = RenderedFrame {
+            cells: vec![Cell::new('#', [255, 0, 0]); 64 * 64],
+            width: 64,
+            height: 64,
+            sixel: None,
+        };
+        for _ in 0..QUEUE_LENGTH * 4 {
+            pipe.send(&frame);
+        }
+        let started = Instant::now();
+        drop(pipe);
+        assert!(started.elapsed() < EXIT_TIMEOUT * 5);
+    }
+}
@@ -0,0 +1,388 @@
+//! 画面に表示する文言の翻訳（英語・日本語）
+//!
+//! ヘルプと OSD の文言だけを対象にした静的な表。言語は `--lang` または
+//! 環境変数（`LC_ALL`、`LC_MESSAGES`、`LANG`）で決める
+
+use std::sync::OnceLock;
+
+use clap::ValueEnum;
+
+/// 表示言語
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Lang {
+    En,
+    Ja,
+}
+
+static LANG: OnceLock<Lang> = OnceLock::new();
+
+impl Lang {
+    /// ロケール名（`ja_JP.UTF-8` など）から言語を決める
+    fn from_locale(locale: &str) -> Self {
+        if locale.to_ascii_lowercase().starts_with("ja") {
+            Lang::Ja
+        } else {
+            Lang::En
+        }
+    }
+
+    /// 環境変数から言語を決める（最初に空でないものを使う）
+    pub fn detect() -> Self {
+        ["LC_ALL", "LC_MESSAGES", "LANG"]
+            .iter()
+            .filter_map(|name| std::env::var(name).ok())
+            .find(|value| !value.is_empty())
+            .map(|locale| Self::from_locale(&locale))
+            .unwrap_or(Lang::En)
+    }
+}
+
+/// 表示言語を設定する（指定がなければ環境変数から決める）
+pub fn init(lang: Option<Lang>) {
+    let _ = LANG.set(lang.unwrap_or_else(Lang::detect));
+}
+
+/// 現在の表示言語
+pub fn lang() -> Lang {
+    *LANG.get_or_init(Lang::detect)
+}
+
+/// 翻訳する文言。`{0}`、`{1}` … は引数に置き換える
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Msg {
+    HelpTitle,
+    KeyPlayPause,
+    KeyQuit,
+    KeyMute,
+    KeyGrayscale,
+    KeyColorFilter,
+    KeyAudioTrack,
+    KeySubtitles,
+    KeyScopes,
+    KeyCharMap,
+    KeySetBookmark,
+    KeyJumpToBookmark,
+    KeyShortSeek,
+    KeyLongSeek,
+    KeyChapter,
+    KeyFrameStep,
+    KeyAbLoop,
+    KeyQrCode,
+    KeyStatusBar,
+    KeyHelp,
+    NoChapters,
+    LoadingChapters,
+    LastChapter,
+    Chapter,
+    Skipped,
+    SwitchedToFallback,
+    QrCodeHint,
+    NoUrlToShare,
+    QrCodeUnavailable,
+    OpenFailed,
+    BookmarksUnavailable,
+    BookmarkSet,
+    BookmarkNotSaved,
+    BookmarkJump,
+    BookmarkNotSet,
+    Charset,
+    CharsetAuto,
+    ColorFilter,
+    AudioTrack,
+    NoOtherAudioTracks,
+    AudioTrackFailed,
+    ExtractingAudioTrack,
+    SubtitlesOn,
+    SubtitlesOff,
+    NoSubtitles,
+    Visualization,
+    SeekFailed,
+    SeekTo,
+    SeekUnavailable,
+    AbLoopStart,
+    AbLoop,
+    AbLoopCleared,
+    DecodingFailed,
+    FileNotFound,
+    PermissionDenied,
+    CodecNotAvailable,
+    FileDamaged,
+    UnsupportedPixelFormat,
+    NetworkError,
+    TerminalTooSmall,
+    PresetReloaded,
+    StatusVolume,
+    StatusMuted,
+    Volume,
+}
+
+impl Msg {
+    #[cfg(test)]
+    const ALL: &[Msg] = &[
+        Msg::HelpTitle,
+        Msg::KeyPlayPause,
+        Msg::KeyQuit,
+        Msg::KeyMute,
+        Msg::KeyGrayscale,
+        Msg::KeyColorFilter,
+        Msg::KeyAudioTrack,
+        Msg::KeySubtitles,
+        Msg::KeyScopes,
+        Msg::KeyCharMap,
+        Msg::KeySetBookmark,
+        Msg::KeyJumpToBookmark,
+        Msg::KeyShortSeek,
+        Msg::KeyLongSeek,
+        Msg::KeyChapter,
+        Msg::KeyFrameStep,
+        Msg::KeyAbLoop,
+        Msg::KeyQrCode,
+        Msg::KeyStatusBar,
+        Msg::KeyHelp,
+        Msg::NoChapters,
+        Msg::LoadingChapters,
+        Msg::LastChapter,
+        Msg::Chapter,
+        Msg::Skipped,
+        Msg::SwitchedToFallback,
+        Msg::QrCodeHint,
+        Msg::NoUrlToShare,
+        Msg::QrCodeUnavailable,
+        Msg::OpenFailed,
+        Msg::BookmarksUnavailable,
+        Msg::BookmarkSet,
+        Msg::BookmarkNotSaved,
+        Msg::BookmarkJump,
+        Msg::BookmarkNotSet,
+        Msg::Charset,
+        Msg::CharsetAuto,
+        Msg::ColorFilter,
+        Msg::AudioTrack,
+        Msg::NoOtherAudioTracks,
+        Msg::AudioTrackFailed,
+        Msg::ExtractingAudioTrack,
+        Msg::SubtitlesOn,
+        Msg::SubtitlesOff,
+        Msg::NoSubtitles,
+        Msg::Visualization,
+        Msg::SeekFailed,
+        Msg::SeekTo,
+        Msg::SeekUnavailable,
+        Msg::AbLoopStart,
+        Msg::AbLoop,
+        Msg::AbLoopCleared,
+        Msg::DecodingFailed,
+        Msg::FileNotFound,
+        Msg::PermissionDenied,
+        Msg::CodecNotAvailable,
+        Msg::FileDamaged,
+        Msg::UnsupportedPixelFormat,
+        Msg::NetworkError,
+        Msg::TerminalTooSmall,
+        Msg::PresetReloaded,
+        Msg::StatusVolume,
+        Msg::StatusMuted,
+        Msg::Volume,
+    ];
+
+    fn text(self, lang: Lang) -> &'static str {
+        match lang {
+            Lang::En => self.en(),
+            Lang::Ja => self.ja(),
+        }
+    }
+
+    fn en(self) -> &'static str {
+        match self {
+            Msg::HelpTitle => "Keys (H to hide)",
+            Msg::KeyPlayPause => "Play/Pause",
+            Msg::KeyQuit => "Quit",
+            Msg::KeyMute => "Mute/Unmute",
+            Msg::KeyGrayscale => "Toggle grayscale",
+            Msg::KeyColorFilter => "Cycle color-vision filter (visualization with --viz)",
+            Msg::KeyAudioTrack => "Next audio track",
+            Msg::KeySubtitles => "Toggle subtitles",
+            Msg::KeyScopes => "Toggle histogram / RGB parade",
+            Msg::KeyCharMap => "Change character map",
+            Msg::KeySetBookmark => "Set bookmark at the current position",
+            Msg::KeyJumpToBookmark => "Jump to bookmark",
+            Msg::KeyShortSeek => "Seek -/+5 seconds",
+            Msg::KeyLongSeek => "Seek -/+30 seconds",
+            Msg::KeyChapter => "Previous/Next chapter",
+            Msg::KeyFrameStep => "Step back/forward one frame (pauses)",
+            Msg::KeyAbLoop => "Set A-B loop start / end, or clear it",
+            Msg::KeyQrCode => "Show QR code of the stream URL",
+            Msg::KeyStatusBar => "Show/hide the status bar",
+            Msg::KeyHelp => "Show/hide this help",
+            Msg::NoChapters => "No chapters",
+            Msg::LoadingChapters => "Loading chapters",
+            Msg::LastChapter => "Last chapter",
+            Msg::Chapter => "Chapter {0}/{1}: {2}",
+            Msg::Skipped => "Skipped {0}",
+            Msg::SwitchedToFallback => "{0}; switched to fallback source",
+            Msg::QrCodeHint => "Press U to show a QR code of this URL",
+            Msg::NoUrlToShare => "No URL to share",
+            Msg::QrCodeUnavailable => "QR code unavailable: {0}",
+            Msg::OpenFailed => "Cannot open {0}",
+            Msg::BookmarksUnavailable => "Bookmarks are not available for this input",
+            Msg::BookmarkSet => "Bookmark {0} set at {1}",
+            Msg::BookmarkNotSaved => "Bookmark {0} set (not saved)",
+            Msg::BookmarkJump => "Bookmark {0}: {1}",
+            Msg::BookmarkNotSet => "Bookmark {0} is not set",
+            Msg::Charset => "Charset: {0}",
+            Msg::CharsetAuto => "Charset: {0} (auto: {1})",
+            Msg::ColorFilter => "Color filter: {0}",
+            Msg::AudioTrack => "Audio track {0}/{1}: {2}",
+            Msg::NoOtherAudioTracks => "No other audio tracks",
+            Msg::AudioTrackFailed => "Cannot switch the audio track: {0}",
+            Msg::ExtractingAudioTrack => "Extracting audio track {0}/{1}",
+            Msg::SubtitlesOn => "Subtitles on",
+            Msg::SubtitlesOff => "Subtitles off",
+            Msg::NoSubtitles => "No subtitles loaded",
+            Msg::Visualization => "Visualization: {0}",
+            Msg::SeekFailed => "Seek failed",
+            Msg::SeekTo => "Seek: {0}",
+            Msg::SeekUnavailable => "Seeking is not available for this input",
+            Msg::AbLoopStart => "A-B loop: A {0} (press again for B)",
+            Msg::AbLoop => "A-B loop: {0} - {1}",
+            Msg::AbLoopCleared => "A-B loop off",
+            Msg::DecodingFailed => "Decoding failed",
+            Msg::FileNotFound => "File not found",
+            Msg::PermissionDenied => "Permission denied",
+            Msg::CodecNotAvailable => "Codec not available",
+            Msg::FileDamaged => "File is truncated or damaged",
+            Msg::UnsupportedPixelFormat => "Unsupported pixel format",
+            Msg::NetworkError => "Network error",
+            Msg::TerminalTooSmall => "Terminal too small (need {0}x{1})",
+            Msg::PresetReloaded => "Preset reloaded",
+            Msg::StatusVolume => "Vol {0}%",
+            Msg::StatusMuted => "Muted",
+            Msg::Volume => "Volume: {0}%",
+        }
+    }
+
+    fn ja(self) -> &'static str {
+        match self {
+            Msg::HelpTitle => "操作（H で閉じる）",
+            Msg::KeyPlayPause => "再生 / 一時停止",
+            Msg::KeyQuit => "終了",
+            Msg::KeyMute => "ミュート切り替え",
+            Msg::KeyGrayscale => "グレースケール切り替え",
+            Msg::KeyColorFilter => "色覚フィルターの切り替え（--viz では視覚化）",
+            Msg::KeyAudioTrack => "次の音声トラック",
+            Msg::KeySubtitles => "字幕の表示切り替え",
+            Msg::KeyScopes => "ヒストグラム / RGB パレードの表示切り替え",
+            Msg::KeyCharMap => "文字マップの変更",
+            Msg::KeySetBookmark => "現在の位置をブックマーク",
+            Msg::KeyJumpToBookmark => "ブックマークへ移動",
+            Msg::KeyShortSeek => "5 秒戻る / 進む",
+            Msg::KeyLongSeek => "30 秒戻る / 進む",
+            Msg::KeyChapter => "前 / 次のチャプター",
+            Msg::KeyFrameStep => "1 コマ戻す / 進める（一時停止する）",
+            Msg::KeyAbLoop => "A-B リピートの始点・終点を設定 / 解除",
+            Msg::KeyQrCode => "配信 URL の QR コードを表示",
+            Msg::KeyStatusBar => "ステータスバーの表示切り替え",
+            Msg::KeyHelp => "このヘルプの表示切り替え",
+            Msg::NoChapters => "チャプターがありません",
+            Msg::LoadingChapters => "チャプターを読み込んでいます",
+            Msg::LastChapter => "最後のチャプターです",
+            Msg::Chapter => "チャプター {0}/{1}: {2}",
+            Msg::Skipped => "{0} をスキップしました",
+            Msg::SwitchedToFallback => "{0}。代わりの入力に切り替えました",
+            Msg::QrCodeHint => "U キーでこの URL の QR コードを表示",
+            Msg::NoUrlToShare => "共有する URL がありません",
+            Msg::QrCodeUnavailable => "QR コードを表示できません: {0}",
+            Msg::OpenFailed => "{0} を開けません",
+            Msg::BookmarksUnavailable => "この入力ではブックマークを使えません",
+            Msg::BookmarkSet => "ブックマーク {0} を {1} に設定しました",
+            Msg::BookmarkNotSaved => "ブックマーク {0} を設定しました（保存できませんでした）",
+            Msg::BookmarkJump => "ブックマーク {0}: {1}",
+            Msg::BookmarkNotSet => "ブックマーク {0} は設定されていません",
+            Msg::Charset => "文字セット: {0}",
+            Msg::CharsetAuto => "文字セット: {0}（自動: {1}）",
+            Msg::ColorFilter => "色覚フィルター: {0}",
+            Msg::AudioTrack => "音声トラック {0}/{1}: {2}",
+            Msg::NoOtherAudioTracks => "ほかの音声トラックはありません",
+            Msg::AudioTrackFailed => "音声トラックを切り替えられません: {0}",
+            Msg::ExtractingAudioTrack => "音声トラック {0}/{1} を取り出しています",
+            Msg::SubtitlesOn => "字幕: 表示",
+            Msg::SubtitlesOff => "字幕: 非表示",
+            Msg::NoSubtitles => "字幕が読み込まれていません",
+            Msg::Visualization => "視覚化: {0}",
+            Msg::SeekFailed => "移動できませんでした",
+            Msg::SeekTo => "移動: {0}",
+            Msg::SeekUnavailable => "この入力では移動できません",
+            Msg::AbLoopStart => "A-B リピート: A {0}（もう一度押して B）",
+            Msg::AbLoop => "A-B リピート: {0} - {1}",
+            Msg::AbLoopCleared => "A-B リピート解除",
+            Msg::DecodingFailed => "デコードに失敗しました",
+            Msg::FileNotFound => "ファイルが見つかりません",
+            Msg::PermissionDenied => "読み込む権限がありません",
+            Msg::CodecNotAvailable => "コーデックがありません",
+            Msg::FileDamaged => "ファイルが途中で切れているか壊れています",
+            Msg::UnsupportedPixelFormat => "対応していないピクセル形式です",
+            Msg::NetworkError => "ネットワークエラー",
+            Msg::TerminalTooSmall => "ターミナルが小さすぎます（{0}x{1} 以上が必要）",
+            Msg::PresetReloaded => "プリセットを再読み込みしました",
+            Msg::StatusVolume => "音量 {0}%",
+            Msg::StatusMuted => "ミュート",
+            Msg::Volume => "音量: {0}%",
+        }
+    }
+}
+
+/// 現在の言語の文言
+pub fn tr(msg: Msg) -> &'static str {
+    msg.text(lang())
+}
+
+/// 現在の言語の文言に引数を埋め込む
+pub fn tr_args(msg: Msg, args: &[&dyn std::fmt::Display]) -> String {
+    format_message(msg.text(lang()), args)
+}
+
+fn format_message(template: &str, args: &[&dyn std::fmt::Display]) -> String {
+    args.iter()
+        .enumerate()
+        .fold(template.to_string(), |text, (index, arg)| {
+            text.replace(&format!("{{{}}}", index), &arg.to_string())
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 文言に含まれる `{n}` の一覧
+    fn placeholders(text: &str) -> Vec<usize> {
+        (0..10)
+            .filter(|index| text.contains(&format!("{{{}}}", index)))
+            .collect()
+    }
+
+    #[test]
+    fn test_translations_have_same_placeholders() {
+        for &msg in Msg::ALL {
+            assert_eq!(placeholders(msg.en()), placeholders(msg.ja()), "{:?}", msg);
+        }
+    }
+
+    #[test]
+    fn test_format_message() {
+        assert_eq!(
+            format_message(Msg::BookmarkSet.ja(), &[&3, &"1:05"]),
+            "ブックマーク 3 を 1:05 に設定しました"
+        );
+        assert_eq!(
+            format_message(Msg::Chapter.en(), &[&2, &5, &"Demo"]),
+            "Chapter 2/5: Demo"
+        );
+    }
+
+    #[test]
+    fn test_lang_from_locale() {
+        assert_eq!(Lang::from_locale("ja_JP.UTF-8"), Lang::Ja);
+        assert_eq!(Lang::from_locale("en_US.UTF-8"), Lang::En);
+        assert_eq!(Lang::from_locale("C"), Lang::En);
+    }
+}
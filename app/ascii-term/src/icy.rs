@@ -0,0 +1,246 @@
+//! インターネットラジオ（Icecast/Shoutcast）の ICY メタデータ
+//!
+//! FFmpeg は音声だけを受け取るため、曲名を取得する目的で別途 HTTP 接続を張り、
+//! `icy-metaint` バイトごとに挿入されるメタデータブロックから `StreamTitle` を読み取る
+
+use anyhow::Result;
+use crossbeam_channel::{Receiver, unbounded};
+use reqwest::Response;
+
+use crate::canvas;
+
+/// ICY ストリームへの接続
+pub struct IcyStream {
+    response: Response,
+    /// 局名（`icy-name` ヘッダー）
+    pub station_name: Option<String>,
+    /// メタデータブロックの間隔（`icy-metaint` ヘッダー）
+    metaint: Option<usize>,
+}
+
+impl IcyStream {
+    /// URL に接続し、ICY ストリーム（終わりのない音声配信）であれば接続を返す
+    pub async fn connect(client: &reqwest::Client, url: &str) -> Result<Option<Self>> {
+        let response = client.get(url).header("Icy-MetaData", "1").send().await?;
+
+        let headers = response.headers();
+        let header = |name: &str| {
+            headers
+                .get(name)
+                .and_then(|value| value.to_str().ok())
+                .map(str::to_string)
+        };
+
+        let station_name = header("icy-name");
+        let metaint = header("icy-metaint")
+            .and_then(|value| value.trim().parse().ok())
+            .filter(|&metaint| metaint > 0);
+        let is_audio = header("content-type")
+            .map(|value| value.starts_with("audio/"))
+            .unwrap_or(false);
+        let has_icy_headers =
+            station_name.is_some() || metaint.is_some() || header("icy-br").is_some();
+
+        // 長さを持たない音声レスポンスもラジオとして扱う
+        let is_endless_audio = is_audio && response.content_length().is_none();
+        if !has_icy_headers && !is_endless_audio {
+            return Ok(None);
+        }
+
+        Ok(Some(Self {
+            response,
+            station_name,
+            metaint,
+        }))
+    }
+
+    /// バックグラウンドでメタデータを読み続け、曲名が変わるたびに送信する
+    pub fn spawn_title_watcher(self) -> Receiver<String> {
+        let (title_tx, title_rx) = unbounded();
+        let Some(metaint) = self.metaint else {
+            // メタデータ非対応の局。接続は閉じる
+            return title_rx;
+        };
+
+        let mut response = self.response;
+        tokio::spawn(async move {
+            let mut parser = IcyMetadataParser::new(metaint);
+            while let Ok(Some(chunk)) = response.chunk().await {
+                for title in parser.push(&chunk) {
+                    if title_tx.send(title).is_err() {
+                        return;
+                    }
+                }
+            }
+        });
+
+        title_rx
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum ParserState {
+    /// 音声データを読み飛ばしている（残りバイト数）
+    Audio(usize),
+    /// 次の 1 バイトがメタデータ長
+    Length,
+    /// メタデータを読んでいる（残りバイト数）
+    Metadata(usize),
+}
+
+/// 音声バイト列に挿入された ICY メタデータブロックを取り出すパーサー
+pub struct IcyMetadataParser {
+    metaint: usize,
+    state: ParserState,
+    metadata: Vec<u8>,
+    last_title: Option<String>,
+}
+
+impl IcyMetadataParser {
+    pub fn new(metaint: usize) -> Self {
+        Self {
+            metaint,
+            state: ParserState::Audio(metaint),
+            metadata: Vec::new(),
+            last_title: None,
+        }
+    }
+
+    /// 受信したバイト列を与え、新しく変わった曲名を返す
+    pub fn push(&mut self, mut data: &[u8]) -> Vec<String> {
+        let mut titles = Vec::new();
+
+        while !data.is_empty() {
+            match self.state {
+                ParserState::Audio(remaining) => {
+                    let skip = remaining.min(data.len());
+                    data = &data[skip..];
+                    self.state = if remaining == skip {
+                        ParserState::Length
+                    } else {
+                        ParserState::Audio(remaining - skip)
+                    };
+                }
+                ParserState::Length => {
+                    let length = data[0] as usize * 16;
+                    data = &data[1..];
+                    self.metadata.clear();
+                    self.state = if length == 0 {
+                        ParserState::Audio(self.metaint)
+                    } else {
+                        ParserState::Metadata(length)
+                    };
+                }
+                ParserState::Metadata(remaining) => {
+                    let take = remaining.min(data.len());
+                    self.metadata.extend_from_slice(&data[..take]);
+                    data = &data[take..];
+
+                    if remaining == take {
+                        self.state = ParserState::Audio(self.metaint);
+                        if let Some(title) = parse_stream_title(&self.metadata)
+                            && self.last_title.as_ref() != Some(&title)
+                        {
+                            self.last_title = Some(title.clone());
+                            titles.push(title);
+                        }
+                    } else {
+                        self.state = ParserState::Metadata(remaining - take);
+                    }
+                }
+            }
+        }
+
+        titles
+    }
+}
+
+/// `StreamTitle='Artist - Song';StreamUrl='';` から曲名を取り出す
+///
+/// 曲名は配信サーバーが自由に決められるので、ターミナルを操作する制御文字は取り除く
+fn parse_stream_title(block: &[u8]) -> Option<String> {
+    let end = block.iter().position(|&b| b == 0).unwrap_or(block.len());
+    let text = canvas::sanitize(&decode_metadata_text(&block[..end]));
+
+    let start = text.find("StreamTitle='")? + "StreamTitle='".len();
+    let rest = &text[start..];
+    // 曲名にアポストロフィが含まれることがあるため、終端は "';" で判定する
+    let title = rest.find("';").map(|end| &rest[..end]).unwrap_or(rest);
+    let title = title.trim_end_matches('\'').trim();
+
+    (!title.is_empty()).then(|| title.to_string())
+}
+
+/// ICY メタデータは UTF-8 と Latin-1 が混在するため、UTF-8 でなければ Latin-1 として扱う
+fn decode_metadata_text(bytes: &[u8]) -> String {
+    match std::str::from_utf8(bytes) {
+        Ok(text) => text.to_string(),
+        Err(_) => bytes.iter().map(|&b| b as char).collect(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn metadata_block(text: &str) -> Vec<u8> {
+        let blocks = text.len().div_ceil(16);
+        let mut block = vec![blocks as u8];
+        block.extend_from_slice(text.as_bytes());
+        block.resize(1 + blocks * 16, 0);
+        block
+    }
+
+    #[test]
+    fn test_parse_stream_title() {
+        assert_eq!(
+            parse_stream_title(b"StreamTitle='Artist - Song';StreamUrl='';\0\0"),
+            Some("Artist - Song".to_string())
+        );
+        assert_eq!(
+            parse_stream_title(b"StreamTitle='Don't Stop';"),
+            Some("Don't Stop".to_string())
+        );
+        assert_eq!(parse_stream_title(b"StreamTitle='';"), None);
+        assert_eq!(
+            parse_stream_title(b"StreamTitle='Song\x1b]0;pwned\x07\x1b[2J';"),
+            Some("Song]0;pwned[2J".to_string())
+        );
+        assert_eq!(parse_stream_title(b"StreamUrl='x';"), None);
+    }
+
+    #[test]
+    fn test_latin1_metadata() {
+        let block = b"StreamTitle='Caf\xe9';";
+        assert_eq!(parse_stream_title(block), Some("Café".to_string()));
+    }
+
+    #[test]
+    fn test_parser_across_chunk_boundaries() {
+        let mut stream = vec![0xAA; 8];
+        stream.extend(metadata_block("StreamTitle='First';"));
+        stream.extend(vec![0xAA; 8]);
+        stream.push(0); // 空のメタデータ
+        stream.extend(vec![0xAA; 8]);
+        stream.extend(metadata_block("StreamTitle='Second';"));
+
+        // 1 バイトずつ与えても結果が変わらないこと
+        let mut parser = IcyMetadataParser::new(8);
+        let titles: Vec<String> = stream.chunks(1).flat_map(|c| parser.push(c)).collect();
+        assert_eq!(titles, vec!["First", "Second"]);
+
+        let mut parser = IcyMetadataParser::new(8);
+        assert_eq!(parser.push(&stream), vec!["First", "Second"]);
+    }
+
+    #[test]
+    fn test_parser_ignores_repeated_title() {
+        let mut stream = vec![0xAA; 4];
+        stream.extend(metadata_block("StreamTitle='Same';"));
+        stream.extend(vec![0xAA; 4]);
+        stream.extend(metadata_block("StreamTitle='Same';"));
+
+        let mut parser = IcyMetadataParser::new(4);
+        assert_eq!(parser.push(&stream), vec!["Same"]);
+    }
+}
@@ -0,0 +1,217 @@
+//! 開いてよい入力の制限（設定ファイルの `[inputs]`）
+//!
+//! キオスクや共有端末のように、あとで見るキューなどを通して他人が入力を渡せる環境で、
+//! 任意の URL やファイルを開かせないようにする。パターンは次のどちらか:
+//!
+//! - `youtube.com` や `*.example.org` のように `/` と `:` を含まないものは URL のホスト名に
+//!   一致させる（ワイルドカードがなければサブドメインも含む）
+//! - それ以外は入力全体に一致させる（`https://cdn.example.com/videos/*`、`/srv/media/*`、
+//!   `test:*` など）。ローカルファイルはシンボリックリンクを解決した絶対パスで比べる
+//!
+//! `*` は `/` を含む任意の文字列、`?` は任意の 1 文字に一致する。
+//! `deny` に一致する入力は常に拒否し、`allow` が空でなければ一致するものだけを許す
+
+use std::path::Path;
+
+use serde::Deserialize;
+
+/// `[inputs]` の項目
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct InputPolicy {
+    #[serde(default)]
+    pub allow: Vec<String>,
+    #[serde(default)]
+    pub deny: Vec<String>,
+}
+
+impl InputPolicy {
+    /// 制限がなければ true
+    pub fn is_open(&self) -> bool {
+        self.allow.is_empty() && self.deny.is_empty()
+    }
+
+    /// 入力を開いてよいか確かめる（拒否した理由をエラーで返す）
+    pub fn check(&self, input: &str) -> anyhow::Result<()> {
+        if self.is_open() {
+            return Ok(());
+        }
+        let target = Target::new(input);
+        if let Some(pattern) = self.deny.iter().find(|pattern| target.matches(pattern)) {
+            anyhow::bail!(
+                "{} is blocked by the pattern '{}' in [inputs] deny of the configuration file",
+                input,
+                pattern
+            );
+        }
+        if !self.allow.is_empty() && !self.allow.iter().any(|pattern| target.matches(pattern)) {
+            anyhow::bail!(
+                "{} is not in [inputs] allow of the configuration file",
+                input
+            );
+        }
+        Ok(())
+    }
+}
+
+/// パターンと比べる形にした入力
+struct Target {
+    /// ローカルファイルは解決した絶対パス、それ以外は入力そのまま
+    full: String,
+    /// URL のホスト名
+    host: Option<String>,
+}
+
+impl Target {
+    fn new(input: &str) -> Self {
+        // URL は正規化した形（`..` を解決し、ホスト名を小文字にしたもの）で比べる
+        if let Ok(url) = url::Url::parse(input)
+            && let Some(host) = url.host_str()
+        {
+            return Self {
+                host: Some(host.to_string()),
+                full: url.to_string(),
+            };
+        }
+        // `..` やシンボリックリンクで許可したディレクトリの外へ出られないようにする
+        let full = Path::new(input)
+            .canonicalize()
+            .map(|path| path.to_string_lossy().into_owned())
+            .unwrap_or_else(|_| input.to_string());
+        Self { full, host: None }
+    }
+
+    fn matches(&self, pattern: &str) -> bool {
+        let pattern = pattern.trim();
+        if is_host_pattern(pattern) {
+            let Some(host) = &self.host else {
+                return false;
+            };
+            let pattern = pattern.to_ascii_lowercase();
+            if pattern.contains(['*', '?']) {
+                wildcard_match(&pattern, host)
+            } else {
+                host == &pattern || host.ends_with(&format!(".{}", pattern))
+            }
+        } else {
+            wildcard_match(pattern, &self.full)
+        }
+    }
+}
+
+/// ホスト名に一致させるパターンかどうか
+fn is_host_pattern(pattern: &str) -> bool {
+    !pattern.contains(['/', '\\', ':'])
+}
+
+/// `*`（任意の文字列）と `?`（任意の 1 文字）だけのワイルドカード
+fn wildcard_match(pattern: &str, text: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let text: Vec<char> = text.chars().collect();
+    let (mut p, mut t) = (0, 0);
+    // 最後に見た `*` の位置と、そこで読み飛ばし始めた位置
+    let mut star: Option<(usize, usize)> = None;
+    while t < text.len() {
+        if p < pattern.len() && (pattern[p] == '?' || pattern[p] == text[t]) {
+            p += 1;
+            t += 1;
+        } else if p < pattern.len() && pattern[p] == '*' {
+            star = Some((p, t));
+            p += 1;
+        } else if let Some((star_p, star_t)) = star {
+            p = star_p + 1;
+            t = star_t + 1;
+            star = Some((star_p, star_t + 1));
+        } else {
+            return false;
+        }
+    }
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn policy(allow: &[&str], deny: &[&str]) -> InputPolicy {
+        InputPolicy {
+            allow: allow.iter().map(|s| s.to_string()).collect(),
+            deny: deny.iter().map(|s| s.to_string()).collect(),
+        }
+    }
+
+    #[test]
+    fn test_wildcard_match() {
+        assert!(wildcard_match("*.example.org", "cdn.example.org"));
+        assert!(!wildcard_match("*.example.org", "example.org"));
+        assert!(wildcard_match("/srv/media/*", "/srv/media/a/b.mp4"));
+        assert!(wildcard_match("test:??", "test:ab"));
+        assert!(!wildcard_match("test:??", "test:abc"));
+        assert!(wildcard_match("a*b*c", "axxbyyc"));
+        assert!(!wildcard_match("a*b*c", "axxbyy"));
+    }
+
+    #[test]
+    fn test_open_policy_allows_everything() {
+        assert!(policy(&[], &[]).check("http://169.254.169.254/").is_ok());
+    }
+
+    #[test]
+    fn test_allow_hosts() {
+        let policy = policy(&["youtube.com", "*.example.org"], &[]);
+        assert!(policy.check("https://www.youtube.com/watch?v=x").is_ok());
+        assert!(policy.check("https://YOUTUBE.com/watch?v=x").is_ok());
+        assert!(policy.check("https://cdn.example.org/a.mp4").is_ok());
+        assert!(policy.check("https://notyoutube.com/").is_err());
+        assert!(policy.check("https://example.org/").is_err());
+        // ユーザー情報の後ろが本当のホスト名
+        assert!(policy.check("https://youtube.com@evil.test/").is_err());
+        assert!(policy.check("/etc/passwd").is_err());
+    }
+
+    #[test]
+    fn test_deny_wins_over_allow() {
+        let policy = policy(&["example.org"], &["private.example.org"]);
+        assert!(policy.check("https://example.org/a.mp4").is_ok());
+        let error = policy.check("https://private.example.org/").unwrap_err();
+        assert!(error.to_string().contains("private.example.org"));
+    }
+
+    #[test]
+    fn test_allow_url_prefix() {
+        let policy = policy(&["https://cdn.example.com/videos/*"], &[]);
+        assert!(policy.check("https://cdn.example.com/videos/a.mp4").is_ok());
+        assert!(
+            policy
+                .check("https://cdn.example.com/videos/../admin")
+                .is_err()
+        );
+        assert!(
+            policy
+                .check("https://cdn.example.com.evil.test/videos/a.mp4")
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn test_local_paths_are_resolved() {
+        let dir = std::env::temp_dir().join(format!("ascii-term-inputs-{}", std::process::id()));
+        std::fs::create_dir_all(dir.join("allowed")).unwrap();
+        let dir = dir.canonicalize().unwrap();
+        let allowed = dir.join("allowed");
+        std::fs::write(allowed.join("ok.mp4"), b"").unwrap();
+        std::fs::write(dir.join("secret.mp4"), b"").unwrap();
+
+        let policy = policy(&[&format!("{}/*", allowed.display())], &[]);
+        assert!(
+            policy
+                .check(&allowed.join("ok.mp4").to_string_lossy())
+                .is_ok()
+        );
+        // `..` で許可したディレクトリの外へは出られない
+        let escape = allowed.join("../secret.mp4");
+        assert!(policy.check(&escape.to_string_lossy()).is_err());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,174 @@
+//! 再生中にシステムのスリープ・画面の消灯を止める
+//!
+//! プレイヤーのイベントを購読し、再生中だけ抑止する。一時停止・停止で解除する
+//!
+//! - Linux: `systemd-inhibit`
+//! - macOS: `caffeinate`（IOKit の電源管理アサーション）
+//! - Windows: `SetThreadExecutionState`
+//!
+//! 外部コマンドは自分のプロセスの終了も待つので、異常終了しても抑止が残らない
+
+use std::io;
+use std::thread;
+
+use crossbeam_channel::Receiver;
+
+use crate::player_events::PlayerEvent;
+
+/// スリープの抑止。破棄すると解除する
+#[derive(Default)]
+struct KeepAwake {
+    inhibitor: Option<Inhibitor>,
+    /// 抑止できなかったことを一度だけ知らせる
+    warned: bool,
+}
+
+impl KeepAwake {
+    fn set(&mut self, awake: bool) {
+        if !awake {
+            self.inhibitor = None;
+            return;
+        }
+        if self.inhibitor.is_some() {
+            return;
+        }
+        match Inhibitor::new() {
+            Ok(inhibitor) => self.inhibitor = Some(inhibitor),
+            Err(e) if !self.warned => {
+                eprintln!("Warning: Failed to keep the system awake: {}", e);
+                self.warned = true;
+            }
+            Err(_) => {}
+        }
+    }
+}
+
+/// プレイヤーのイベントに合わせてスリープを抑止するスレッドを開始する
+pub fn spawn(events: Receiver<PlayerEvent>) {
+    thread::spawn(move || {
+        let mut keep_awake = KeepAwake::default();
+        for event in events {
+            // 読み込み・移動の間もスリープさせない
+            if let PlayerEvent::StateChanged(state) = event {
+                keep_awake.set(state.is_active());
+            }
+        }
+    });
+}
+
+/// 抑止に使うコマンド（`pid` のプロセスが終わると自動で解除される）
+#[cfg(target_os = "linux")]
+fn inhibit_command(pid: u32) -> (&'static str, Vec<String>) {
+    let args = vec![
+        "--what=idle:sleep".to_string(),
+        "--who=ascii-term".to_string(),
+        "--why=Playing media".to_string(),
+        "--mode=block".to_string(),
+        "tail".to_string(),
+        format!("--pid={}", pid),
+        "-f".to_string(),
+        "/dev/null".to_string(),
+    ];
+    ("systemd-inhibit", args)
+}
+
+#[cfg(target_os = "macos")]
+fn inhibit_command(pid: u32) -> (&'static str, Vec<String>) {
+    // -d: 画面の消灯、-i: アイドル時のスリープを止める
+    let args = vec!["-d".to_string(), "-i".to_string(), format!("-w{}", pid)];
+    ("caffeinate", args)
+}
+
+#[cfg(any(target_os = "linux", target_os = "macos"))]
+struct Inhibitor(std::process::Child);
+
+#[cfg(any(target_os = "linux", target_os = "macos"))]
+impl Inhibitor {
+    fn new() -> io::Result<Self> {
+        use std::process::{Command, Stdio};
+
+        let (program, args) = inhibit_command(std::process::id());
+        Command::new(program)
+            .args(args)
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn()
+            .map(Self)
+    }
+}
+
+#[cfg(any(target_os = "linux", target_os = "macos"))]
+impl Drop for Inhibitor {
+    fn drop(&mut self) {
+        let _ = self.0.kill();
+        let _ = self.0.wait();
+    }
+}
+
+/// 実行状態はスレッドごとなので、専用のスレッドで設定し、破棄されるまで保つ
+#[cfg(windows)]
+struct Inhibitor(std::sync::mpsc::Sender<()>);
+
+#[cfg(windows)]
+impl Inhibitor {
+    fn new() -> io::Result<Self> {
+        const ES_CONTINUOUS: u32 = 0x8000_0000;
+        const ES_SYSTEM_REQUIRED: u32 = 0x0000_0001;
+        const ES_DISPLAY_REQUIRED: u32 = 0x0000_0002;
+
+        #[link(name = "kernel32")]
+        unsafe extern "system" {
+            fn SetThreadExecutionState(flags: u32) -> u32;
+        }
+
+        let (release_tx, release_rx) = std::sync::mpsc::channel::<()>();
+        let (result_tx, result_rx) = std::sync::mpsc::channel();
+        thread::spawn(move || {
+            // SAFETY: 引数はフラグだけで、ポインターを渡さない
+            let previous = unsafe {
+                SetThreadExecutionState(ES_CONTINUOUS | ES_SYSTEM_REQUIRED | ES_DISPLAY_REQUIRED)
+            };
+            let _ = result_tx.send(previous != 0);
+            // 送信側が破棄されるまで待つ
+            let _ = release_rx.recv();
+            // SAFETY: 同上
+            unsafe { SetThreadExecutionState(ES_CONTINUOUS) };
+        });
+
+        match result_rx.recv() {
+            Ok(true) => Ok(Self(release_tx)),
+            _ => Err(io::Error::other("SetThreadExecutionState failed")),
+        }
+    }
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos", windows)))]
+struct Inhibitor;
+
+#[cfg(not(any(target_os = "linux", target_os = "macos", windows)))]
+impl Inhibitor {
+    fn new() -> io::Result<Self> {
+        Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "not supported on this platform",
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_inhibit_command_follows_our_process() {
+        let (program, args) = inhibit_command(42);
+        assert_eq!(program, "systemd-inhibit");
+        assert_eq!(
+            args.join(" "),
+            "--what=idle:sleep --who=ascii-term --why=Playing media --mode=block \
+             tail --pid=42 -f /dev/null"
+        );
+    }
+}
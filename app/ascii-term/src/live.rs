@@ -0,0 +1,116 @@
+//! ライブ配信（RTSP/RTMP カメラ等）の遅延トリミング
+//!
+//! ネットワークの揺らぎや一時停止で溜まった古いフレームを捨て、
+//! 常に最新の映像に追従する
+
+use std::time::Duration;
+
+/// ライブ配信でのフレームの扱い
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FrameAction {
+    /// そのまま表示する
+    Render,
+    /// 古いフレームなので表示せず捨てる
+    Drop,
+    /// 再生時計をこのフレームに合わせ直してから表示する
+    Resync,
+}
+
+/// 許容遅延を超えたフレームを捨てて配信に追従する
+#[derive(Debug)]
+pub struct LatencyTrimmer {
+    max_latency: Duration,
+    frame_duration: Duration,
+    dropped_frames: u64,
+}
+
+impl LatencyTrimmer {
+    pub fn new(max_latency: Duration, frame_duration: Duration) -> Self {
+        Self {
+            max_latency,
+            frame_duration,
+            dropped_frames: 0,
+        }
+    }
+
+    /// フレームの PTS と再生時計の経過時間、そのフレームのデコードにかかった時間から
+    /// 扱いを決める
+    ///
+    /// 遅れているフレームが即座にデコードできた場合は受信バッファに溜まった古いフレーム
+    /// なので捨てる。デコードに 1 フレーム分近くかかった場合は配信の最新位置に
+    /// 追いついているため、時計のほうを合わせ直す
+    pub fn decide(
+        &mut self,
+        frame_pts: Duration,
+        elapsed: Duration,
+        decode_time: Duration,
+    ) -> FrameAction {
+        // PTS の飛び（配信側の再起動など）で大きく先行した場合も合わせ直す
+        if frame_pts > elapsed + self.max_latency.max(Duration::from_secs(1)) {
+            return FrameAction::Resync;
+        }
+
+        let lag = elapsed.saturating_sub(frame_pts);
+        if lag <= self.max_latency {
+            FrameAction::Render
+        } else if decode_time < self.frame_duration / 2 {
+            self.dropped_frames += 1;
+            FrameAction::Drop
+        } else {
+            FrameAction::Resync
+        }
+    }
+
+    pub fn dropped_frames(&self) -> u64 {
+        self.dropped_frames
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FRAME: Duration = Duration::from_millis(40);
+
+    fn trimmer() -> LatencyTrimmer {
+        LatencyTrimmer::new(Duration::from_millis(500), FRAME)
+    }
+
+    #[test]
+    fn test_on_time_frames_render() {
+        let mut trimmer = trimmer();
+        let action = trimmer.decide(
+            Duration::from_secs(10),
+            Duration::from_millis(10_200),
+            FRAME,
+        );
+        assert_eq!(action, FrameAction::Render);
+    }
+
+    #[test]
+    fn test_buffered_stale_frames_are_dropped() {
+        let mut trimmer = trimmer();
+        let action = trimmer.decide(
+            Duration::from_secs(10),
+            Duration::from_secs(12),
+            Duration::from_millis(1),
+        );
+        assert_eq!(action, FrameAction::Drop);
+        assert_eq!(trimmer.dropped_frames(), 1);
+    }
+
+    #[test]
+    fn test_live_edge_after_stall_resyncs() {
+        let mut trimmer = trimmer();
+        let action = trimmer.decide(Duration::from_secs(10), Duration::from_secs(12), FRAME);
+        assert_eq!(action, FrameAction::Resync);
+        assert_eq!(trimmer.dropped_frames(), 0);
+    }
+
+    #[test]
+    fn test_pts_jump_forward_resyncs() {
+        let mut trimmer = trimmer();
+        let action = trimmer.decide(Duration::from_secs(60), Duration::from_secs(12), FRAME);
+        assert_eq!(action, FrameAction::Resync);
+    }
+}
@@ -1,24 +1,133 @@
+mod ab_loop;
+mod album_view;
+mod ansi;
+mod ansimovie;
+mod arg_values;
 mod audio;
+mod bitmap_font;
+mod calibration;
+mod canvas;
+mod chapters;
 mod char_maps;
+mod charset_auto;
+mod chat;
+mod clock;
+mod color_filter;
+mod config;
+mod demo;
+mod download_progress;
+mod emit;
+mod error_message;
+mod estimate;
+mod frame_hash;
+mod frame_history;
+mod frame_pipe;
+mod i18n;
+mod icy;
+mod input_policy;
+mod keep_awake;
+mod live;
+mod markers;
+mod model3d;
+mod now_playing;
+mod palette;
+mod playback_state;
 mod player;
+mod player_events;
+mod player_state;
+mod playlist;
+mod qr;
+mod raw_video;
+mod render_preset;
 mod renderer;
+mod replay;
+mod sandbox;
+mod scopes;
+mod screensaver;
+mod session_stats;
+mod sidecar;
+mod sixel;
+mod skip_segments;
+mod status_bar;
+mod subtitles;
 mod terminal;
+mod title_card;
+mod transition;
+mod visualizer;
+mod vu_meter;
+mod watch_queue;
+mod widgets;
 
-use anyhow::Result;
-use clap::Parser;
+use std::collections::VecDeque;
+use std::io;
+use std::process::ExitCode;
+use std::time::Duration;
+
+use anyhow::{Context, Result};
+use clap::parser::ValueSource;
+use clap::{ArgMatches, CommandFactory, FromArgMatches, Parser, Subcommand};
 
 use codec::MediaFile;
 
 #[derive(Parser, Debug)]
 #[command(name = "ascii_term")]
 #[command(about = "Terminal media player with ASCII art rendering")]
+#[command(args_conflicts_with_subcommands = true, subcommand_negates_reqs = true)]
 struct Args {
-    /// Input file path or URL
+    /// Input file path, directory of audio files, .m3u playlist, or URL
+    /// (the next entry of the watch-later queue when omitted)
     #[arg(value_name = "INPUT")]
-    input: String,
+    input: Option<String>,
 
-    /// Force specific frame rate
-    #[arg(short, long)]
+    /// Show a webcam (device number such as 0, or a path like /dev/video2) as a live mirror
+    #[arg(long, value_name = "DEVICE", conflicts_with_all = ["input", "enqueue"])]
+    camera: Option<String>,
+
+    /// Capture size for --camera, e.g. 1280x720 (the nearest size the camera supports) [default: 640x480]
+    #[arg(
+        long,
+        value_name = "WxH",
+        value_parser = arg_values::parse_size,
+        requires = "camera",
+        conflicts_with = "input"
+    )]
+    camera_size: Option<(u32, u32)>,
+
+    /// List webcams and the sizes they can capture, then exit
+    #[arg(long)]
+    list_cameras: bool,
+
+    /// Add INPUT to the watch-later queue and exit. A running player plays it after the current
+    /// media ends; otherwise the next launch without INPUT starts with it
+    #[arg(long, value_name = "INPUT", conflicts_with = "input")]
+    enqueue: Option<String>,
+
+    #[command(subcommand)]
+    command: Option<Command>,
+
+    /// Read defaults from this TOML file instead of ~/.config/ascii-term/config.toml
+    /// (options given on the command line win)
+    #[arg(long, value_name = "FILE")]
+    config: Option<std::path::PathBuf>,
+
+    /// Keys added by the `[keys]` table of the configuration file
+    #[arg(skip)]
+    key_bindings: terminal::KeyBindings,
+
+    /// Color correction from the `[calibration]` table of the configuration file
+    #[arg(skip)]
+    calibration: calibration::Calibration,
+
+    /// Inputs allowed or denied by the `[inputs]` table of the configuration file
+    #[arg(skip)]
+    input_policy: input_policy::InputPolicy,
+
+    /// Ignore the color correction saved by `calibrate`
+    #[arg(long)]
+    no_calibration: bool,
+
+    /// Force specific frame rate (0.1-240)
+    #[arg(short, long, value_parser = arg_values::parse_fps)]
     fps: Option<f64>,
 
     /// Browser for cookie extraction (for YouTube)
@@ -29,53 +138,577 @@ struct Args {
     #[arg(short, long)]
     loop_playback: bool,
 
-    /// Character map selection (0-9)
-    #[arg(short, long, default_value = "0")]
-    char_map: u8,
+    /// Repeat between two positions in seconds (START:END), like marking A and B with `a`
+    #[arg(long, value_name = "START:END")]
+    ab_loop: Option<ab_loop::AbLoop>,
+
+    /// Character map selection (0-9); picked from the video content when omitted
+    #[arg(short, long, value_parser = clap::value_parser!(u8).range(0..=9))]
+    char_map: Option<u8>,
 
     /// Enable grayscale mode
     #[arg(short, long)]
     gray: bool,
 
-    /// Width modifier for character aspect ratio
-    #[arg(short, long, default_value = "1")]
+    /// Width modifier for character aspect ratio (1 or more)
+    #[arg(short, long, default_value = "1", value_parser = clap::value_parser!(u32).range(1..))]
     width_mod: u32,
 
     /// Add newlines to output
     #[arg(short, long)]
     newlines: bool,
 
+    /// Draw below the prompt in ROWS rows (default 12) instead of the full-screen alternate screen,
+    /// leaving the last frame in the scrollback. Use as --inline or --inline=ROWS
+    #[arg(
+        long,
+        value_name = "ROWS",
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = "12",
+        value_parser = clap::value_parser!(u16).range(1..),
+        conflicts_with_all = ["emit", "newlines"]
+    )]
+    inline: Option<u16>,
+
+    /// Pause while the terminal window is not focused and resume when it is focused again
+    /// (terminals that report focus changes)
+    #[arg(long, conflicts_with = "emit")]
+    pause_on_blur: bool,
+
     /// Disable audio playback
     #[arg(long)]
     no_audio: bool,
 
+    /// Play this audio stream (the #N shown under Media Info) instead of the first one; # switches while playing
+    #[arg(long, value_name = "N", conflicts_with = "no_audio")]
+    audio_track: Option<u32>,
+
+    /// Play this video stream (the #N shown under Media Info) instead of the first one
+    #[arg(long, value_name = "N")]
+    video_track: Option<usize>,
+
+    /// Audio output: rodio (default), cpal, pulse (pipe to pacat) or aplay (pipe to aplay)
+    #[arg(long, value_enum, value_name = "BACKEND", default_value = "rodio")]
+    audio_backend: audio::AudioBackendKind,
+
+    /// Write the decoded audio to this WAV file (or FIFO) instead of playing it
+    #[arg(long, value_name = "PATH", conflicts_with = "audio_backend")]
+    audio_out: Option<std::path::PathBuf>,
+
+    /// Fade length (ms) when audio starts, pauses, stops or seeks, to avoid clicks (0 to disable)
+    #[arg(long, value_name = "MS", default_value_t = 50)]
+    audio_ramp: u64,
+
     /// Diagnose audio system
     #[arg(long)]
     diagnose_audio: bool,
+
+    /// Drop video frames more than this many ms behind the master clock (audio when present) [default: two frames]
+    #[arg(long, value_name = "MS")]
+    sync_threshold: Option<u64>,
+
+    /// Maximum latency (ms) for live streams before stale frames are dropped
+    #[arg(long, value_name = "MS")]
+    max_latency: Option<u64>,
+
+    /// Attract mode shown when there is nothing to play (empty or finished playlist)
+    #[arg(long, value_enum, value_name = "KIND")]
+    screensaver: Option<screensaver::ScreensaverKind>,
+
+    /// Crop video to the terminal aspect ratio, following the detected subject
+    #[arg(long)]
+    roi: bool,
+
+    /// Write rendered frames to stdout in this format instead of drawing them
+    #[arg(long, value_enum, value_name = "FORMAT")]
+    emit: Option<emit::EmitFormat>,
+
+    /// Print a single frame as plain text for pasting into chat (Discord, Twitch) and exit
+    #[arg(long, conflicts_with_all = ["emit", "dry_run"])]
+    chat: bool,
+
+    /// Print a stable hash of every rendered frame (index, timestamp, hash) for regression tests and exit;
+    /// N limits the frame count (test: and demo: sources default to 150)
+    #[arg(long, value_name = "N", conflicts_with_all = ["emit", "dry_run", "chat"])]
+    hash_frames: Option<Option<u64>>,
+
+    /// Maximum width of the --chat text in characters
+    #[arg(
+        long,
+        value_name = "COLS",
+        default_value_t = chat::DEFAULT_CHAT_WIDTH,
+        value_parser = clap::value_parser!(u32).range(1..),
+        requires = "chat"
+    )]
+    chat_width: u32,
+
+    /// Position of the --chat frame in seconds
+    #[arg(long, value_name = "SECONDS", value_parser = arg_values::parse_seconds, requires = "chat")]
+    chat_at: Option<f64>,
+
+    /// Wrap the --chat text in a ``` code block
+    #[arg(long, requires = "chat")]
+    code_fence: bool,
+
+    /// Download subtitles with yt-dlp (uploaded, or auto-generated) and show them (YouTube only)
+    #[arg(long)]
+    write_subs: bool,
+
+    /// Subtitle language for --write-subs
+    #[arg(long, value_name = "LANG", default_value = "en")]
+    sub_lang: String,
+
+    /// Show subtitles from a .srt, .ass or .vtt file; S hides/shows them while playing
+    #[arg(long, value_name = "FILE", conflicts_with_all = ["write_subs", "sub_track"])]
+    sub: Option<std::path::PathBuf>,
+
+    /// Show the embedded text subtitle stream #N (shown under Media Info; needs ffmpeg)
+    #[arg(long, value_name = "N", conflicts_with = "write_subs")]
+    sub_track: Option<u32>,
+
+    /// Skip sponsor and intro segments submitted to SponsorBlock (YouTube only)
+    #[arg(long)]
+    sponsorblock: bool,
+
+    /// Maximum download rate for URL inputs, e.g. 500K or 2M (bytes per second)
+    #[arg(long, value_name = "RATE", value_parser = downloader::parse_rate)]
+    limit_rate: Option<u64>,
+
+    /// Proxy for downloads and yt-dlp (http://, https://, socks5:// or socks5h://)
+    #[arg(long, value_name = "URL")]
+    proxy: Option<String>,
+
+    /// Highest YouTube quality to download, e.g. 360p or 720p, or best/worst
+    #[arg(long, value_name = "QUALITY", value_parser = downloader::parse_quality)]
+    quality: Option<downloader::Quality>,
+
+    /// yt-dlp format selector for YouTube downloads (overrides --quality)
+    #[arg(long, value_name = "FORMAT", conflicts_with = "quality")]
+    yt_format: Option<String>,
+
+    /// Refuse to play unless the media's SHA-256 matches (a `<media>.sha256` file is used when omitted)
+    #[arg(long, value_name = "HEX", value_parser = downloader::parse_sha256)]
+    expect_sha256: Option<String>,
+
+    /// Download YouTube videos completely before playing instead of streaming them
+    /// (seeking works from the start; implied by --expect-sha256)
+    #[arg(long)]
+    prefetch: bool,
+
+    /// Alternative URL or path, tried in order when the input cannot be opened or fails during playback
+    #[arg(long, value_name = "SOURCE")]
+    fallback: Vec<String>,
+
+    /// Language of the help and on-screen messages (detected from LANG when omitted)
+    #[arg(long, value_enum)]
+    lang: Option<i18n::Lang>,
+
+    /// Simulate or compensate for color-vision deficiency, or use a high-contrast palette
+    #[arg(long, value_enum, value_name = "FILTER", default_value = "none")]
+    color_filter: color_filter::ColorFilter,
+
+    /// How pixels become characters: ascii (character map), braille (2x4 dots per cell) or half-block (1x2 colored pixels per cell)
+    #[arg(long, value_enum, value_name = "MODE", default_value = "ascii")]
+    render_mode: renderer::RenderMode,
+
+    /// Colors to output: truecolor, ansi256, ansi16 or mono [default: detected from COLORTERM / TERM]
+    #[arg(long, value_enum, value_name = "MODE")]
+    color_mode: Option<ansi::ColorMode>,
+
+    /// YCbCr matrix used to convert video to RGB and to weight brightness:
+    /// bt601, bt709 or bt2020 [default: from the stream]
+    #[arg(long, value_name = "MATRIX")]
+    color_matrix: Option<codec::color::Matrix>,
+
+    /// YCbCr value range of the video: limited (16-235) or full (0-255) [default: from the stream]
+    #[arg(long, value_name = "RANGE")]
+    color_range: Option<codec::color::Range>,
+
+    /// Tone-mapping curve for HDR (10-bit BT.2020) video: hable, reinhard or none
+    #[arg(long, value_name = "CURVE", default_value = "hable")]
+    tonemap: codec::tonemap::ToneMap,
+
+    /// Transfer function of HDR video, which the decoder does not report: pq (HDR10) or hlg
+    #[arg(long, value_name = "TRANSFER", default_value = "pq")]
+    hdr_transfer: codec::tonemap::HdrTransfer,
+
+    /// Dithering when reducing brightness to the character map (or braille dots):
+    /// none, ordered (Bayer matrix) or floyd-steinberg (error diffusion)
+    #[arg(long, value_enum, value_name = "METHOD", default_value = "none")]
+    dither: renderer::Dither,
+
+    /// Output backend: ascii (characters with ANSI colors) or sixel (bitmap graphics for xterm, mlterm, foot)
+    #[arg(long, value_enum, value_name = "BACKEND", default_value = "ascii", conflicts_with_all = ["emit", "hash_frames"])]
+    backend: renderer::RenderBackend,
+
+    /// With the sixel backend, show media at its native pixel size or an integer multiple
+    /// (centered, no fractional scaling) using the terminal's cell size in pixels
+    #[arg(long)]
+    pixel_perfect: bool,
+
+    /// Show a title card with the next track's name for this many seconds between directory (album) tracks
+    #[arg(long, value_name = "SECONDS", value_parser = arg_values::parse_positive_seconds)]
+    title_card: Option<f64>,
+
+    /// Show a countdown on the --title-card
+    #[arg(long, requires = "title_card")]
+    title_card_countdown: bool,
+
+    /// Blend the end of a loop into its start, and fade audio in and out between tracks
+    #[arg(long, value_enum, value_name = "KIND")]
+    transition: Option<transition::TransitionKind>,
+
+    /// Length of the --transition in seconds
+    #[arg(
+        long,
+        value_name = "SECONDS",
+        default_value_t = 1.0,
+        value_parser = arg_values::parse_positive_seconds,
+        requires = "transition"
+    )]
+    transition_duration: f64,
+
+    /// TOML file with clock / ticker overlays for signage
+    #[arg(long, value_name = "FILE")]
+    widgets: Option<std::path::PathBuf>,
+
+    /// TOML file with render settings (charset, grayscale, color filter, VU meter), reloaded when saved
+    #[arg(long, value_name = "FILE")]
+    preset: Option<std::path::PathBuf>,
+
+    /// Write player events (position, state, track changes, errors) to this file as JSON Lines
+    #[arg(long, value_name = "FILE")]
+    event_log: Option<std::path::PathBuf>,
+
+    /// Keep the system from sleeping or blanking the screen while playing
+    #[arg(long)]
+    keep_awake: bool,
+
+    /// Write playback statistics (per-stage timing histograms, dropped frames) to this file as JSON
+    #[arg(long, value_name = "FILE", conflicts_with = "dry_run")]
+    stats_json: Option<std::path::PathBuf>,
+
+    /// Also send every frame to this shell command's stdin (4-byte big-endian length, then the ANSI or Sixel bytes)
+    #[arg(long, value_name = "CMD", conflicts_with_all = ["emit", "hash_frames", "dry_run"])]
+    pipe_frames: Option<String>,
+
+    /// Serve the rendered frames as raw RGB24 video on this TCP port or address, for `ffmpeg -f rawvideo -i tcp://...`
+    #[arg(long, value_name = "ADDR", value_parser = raw_video::parse_listen_addr, conflicts_with_all = ["emit", "hash_frames", "dry_run"])]
+    rawvideo_listen: Option<std::net::SocketAddr>,
+
+    /// Picture size of --rawvideo-listen, e.g. 1920x1080 [default: 1280x720]
+    #[arg(long, value_name = "WxH", value_parser = arg_values::parse_size, requires = "rawvideo_listen")]
+    rawvideo_size: Option<(u32, u32)>,
+
+    /// Frame rate of --rawvideo-listen [default: 30]
+    #[arg(long, value_name = "FPS", value_parser = arg_values::parse_fps, requires = "rawvideo_listen")]
+    rawvideo_fps: Option<f64>,
+
+    /// Show left/right audio level meters over the video
+    #[arg(long, conflicts_with = "no_audio")]
+    vu_meter: bool,
+
+    /// Animate audio-only media instead of showing the track information
+    #[arg(long, value_enum, value_name = "KIND", conflicts_with = "no_audio")]
+    viz: Option<visualizer::VisualizerKind>,
+
+    /// Linux: before parsing the media, restrict the process with Landlock to read-only files
+    /// (except temp, state and output locations) and no network unless playback streams from it
+    #[arg(long)]
+    sandbox: bool,
+
+    /// Unix: decode video in a child process that is restarted where it left off if it crashes
+    #[arg(long)]
+    decode_worker: bool,
+
+    /// Only estimate output size, bandwidth and time from a short sample, then exit
+    #[arg(long)]
+    dry_run: bool,
+
+    /// Write chapters, bookmarks and detected silence / black segments to this JSON file, then exit
+    #[arg(long, value_name = "FILE", conflicts_with_all = ["emit", "dry_run", "chat", "hash_frames"])]
+    export_markers: Option<std::path::PathBuf>,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Manage the configuration file
+    Config {
+        #[command(subcommand)]
+        action: ConfigAction,
+    },
+    /// Adjust gamma and black point for this terminal on test gradients,
+    /// and save them to the configuration file
+    Calibrate,
+    /// Play back an .ansimovie, asciicast v2 (.cast) or --emit jsonl recording
+    Replay {
+        #[arg(value_name = "FILE")]
+        path: std::path::PathBuf,
+
+        /// Playback speed (0.125-16; + and - change it while playing)
+        #[arg(long, default_value = "1", value_parser = replay::parse_speed)]
+        speed: f64,
+    },
+    /// Child process of --decode-worker
+    #[command(hide = true)]
+    DecodeWorker {
+        #[arg(long)]
+        color_matrix: Option<codec::color::Matrix>,
+        #[arg(long)]
+        color_range: Option<codec::color::Range>,
+        #[arg(long, default_value = "hable")]
+        tonemap: codec::tonemap::ToneMap,
+        #[arg(long, default_value = "pq")]
+        hdr_transfer: codec::tonemap::HdrTransfer,
+        input: String,
+        ring: std::path::PathBuf,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+enum ConfigAction {
+    /// Write a commented template to ~/.config/ascii-term/config.toml (or FILE)
+    Init {
+        #[arg(value_name = "FILE")]
+        path: Option<std::path::PathBuf>,
+
+        /// Overwrite an existing file
+        #[arg(long)]
+        force: bool,
+    },
 }
 
 #[tokio::main]
-async fn main() -> Result<()> {
-    let args = Args::parse();
+async fn main() -> ExitCode {
+    let result = match parse_args() {
+        Ok(args) => run(args).await,
+        Err(e) => Err(e),
+    };
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("Error: {}", error_message::user_message(&e));
+            ExitCode::FAILURE
+        }
+    }
+}
+
+/// コマンドラインを読み、指定されなかった項目に設定ファイルの値を使う
+fn parse_args() -> Result<Args> {
+    let matches = Args::command().get_matches();
+    let mut args = Args::from_arg_matches(&matches)?;
+    // ひな形を書き出すときは、既存の設定ファイルが壊れていても読まない
+    if args.command.is_none() {
+        let config = match &args.config {
+            Some(path) => config::Config::load(path)?,
+            None => config::Config::load_default()?,
+        };
+        apply_config(&mut args, &matches, &config)?;
+    }
+    Ok(args)
+}
+
+/// 設定ファイルの値を、コマンドラインで指定されなかった項目に反映する
+fn apply_config(args: &mut Args, matches: &ArgMatches, config: &config::Config) -> Result<()> {
+    let unset = |id: &str| matches.value_source(id) != Some(ValueSource::CommandLine);
+
+    if let Some(index) = config.char_map.filter(|_| unset("char_map")) {
+        args.char_map = Some(index);
+    }
+    if let Some(mode) = config.color_mode.filter(|_| unset("color_mode")) {
+        args.color_mode = Some(mode);
+    }
+    if let Some(width_mod) = config.width_mod.filter(|_| unset("width_mod")) {
+        args.width_mod = width_mod;
+    }
+    if let Some(gray) = config.gray.filter(|_| unset("gray")) {
+        args.gray = gray;
+    }
+    if let Some(enabled) = config.audio.enabled.filter(|_| unset("no_audio")) {
+        args.no_audio = !enabled;
+    }
+    if let Some(backend) = config.audio.backend.filter(|_| unset("audio_backend")) {
+        args.audio_backend = backend;
+    }
+    if let Some(ramp) = config.audio.ramp_ms.filter(|_| unset("audio_ramp")) {
+        args.audio_ramp = ramp;
+    }
+    if let Some(browser) = config
+        .download
+        .browser
+        .as_ref()
+        .filter(|_| unset("browser"))
+    {
+        args.browser = browser.clone();
+    }
+    if let Some(rate) = config.limit_rate()?.filter(|_| unset("limit_rate")) {
+        args.limit_rate = Some(rate);
+    }
+    if let Some(proxy) = config.download.proxy.as_ref().filter(|_| unset("proxy")) {
+        args.proxy = Some(proxy.clone());
+    }
+    if let Some(quality) = config
+        .quality()?
+        .filter(|_| unset("quality") && unset("yt_format"))
+    {
+        args.quality = Some(quality);
+    }
+    args.key_bindings = config.key_bindings()?;
+    if !args.no_calibration {
+        args.calibration = config.calibration;
+    }
+    args.input_policy = config.inputs.clone();
+    Ok(())
+}
+
+async fn run(args: Args) -> Result<()> {
+    i18n::init(args.lang);
+
+    match &args.command {
+        Some(Command::Config {
+            action: ConfigAction::Init { path, force },
+        }) => {
+            let path = config::init(path.as_deref(), *force)?;
+            println!("Wrote {}", path.display());
+            return Ok(());
+        }
+        Some(Command::Calibrate) => {
+            return calibrate(args.config.as_deref());
+        }
+        Some(Command::Replay { path, speed }) => {
+            return replay::run(path, *speed);
+        }
+        Some(Command::DecodeWorker {
+            color_matrix,
+            color_range,
+            tonemap,
+            hdr_transfer,
+            input,
+            ring,
+        }) => {
+            codec::video::override_yuv_format(codec::video::YuvOverride {
+                matrix: *color_matrix,
+                range: *color_range,
+            })?;
+            codec::video::set_hdr_settings(codec::video::HdrSettings {
+                tone_map: *tonemap,
+                transfer: *hdr_transfer,
+            })?;
+            return serve_decode_worker(input, ring);
+        }
+        None => {}
+    }
+    if args.list_cameras {
+        return list_cameras();
+    }
+    if let Some(device) = &args.camera {
+        let emitter = stdout_emitter(&args)?;
+        let source = codec::source::CameraSource::open(device, args.camera_size)?;
+        return play_procedural_source(Box::new(source), &args, emitter).await;
+    }
+    if let Some(input) = &args.enqueue {
+        args.input_policy.check(input)?;
+        let queue = watch_queue::WatchQueue::open_default()
+            .ok_or_else(|| anyhow::anyhow!("Cannot find the state directory"))?;
+        let count = queue.push(input)?;
+        println!("Queued {} ({} in the watch-later queue)", input, count);
+        return Ok(());
+    }
+    let input_arg = match args.input.clone() {
+        Some(input) => input,
+        None => watch_queue::WatchQueue::open_default()
+            .map(|queue| queue.pop())
+            .transpose()?
+            .flatten()
+            .ok_or_else(|| anyhow::anyhow!("No input given and the watch-later queue is empty"))?,
+    };
+    for input in std::iter::once(&input_arg).chain(&args.fallback) {
+        args.input_policy.check(input)?;
+    }
 
     if args.diagnose_audio {
         println!("Running audio system diagnostics...");
-        return audio::diagnose_audio_system();
+        return audio::diagnose_audio_system(&audio_output(&args));
     }
 
+    let emitter = stdout_emitter(&args)?;
+
     codec::init()?;
+    codec::video::override_yuv_format(yuv_override(&args))?;
+    codec::video::set_hdr_settings(codec::video::HdrSettings {
+        tone_map: args.tonemap,
+        transfer: args.hdr_transfer,
+    })?;
+    if args.decode_worker {
+        let program = std::env::current_exe()?;
+        codec::video::worker::isolate_decoding(codec::video::worker::WorkerCommand::new(
+            program,
+            decode_worker_args(&args),
+        ))?;
+    }
 
-    let media_path = if is_url(&args.input) {
-        handle_url_input(&args.input, &args.browser).await?
-    } else {
-        args.input.clone()
+    if codec::source::is_test_source(&input_arg) {
+        let source = codec::source::open_test_source(&input_arg)?;
+        return play_procedural_source(source, &args, emitter).await;
+    }
+    if demo::is_demo_source(&input_arg) {
+        let source = demo::open_demo_source(&input_arg)?;
+        return play_procedural_source(source, &args, emitter).await;
+    }
+    if model3d::is_model_source(&input_arg) {
+        enter_sandbox(&args, &input_arg)?;
+        let source = model3d::open_model_source(&input_arg)?;
+        return play_procedural_source(source, &args, emitter).await;
+    }
+
+    let network = network_options(&args);
+    // 開けなければ --fallback の入力を順に試す
+    let mut fallbacks: VecDeque<String> = args.fallback.iter().cloned().collect();
+    let mut input = input_arg;
+    let opened = loop {
+        match open_input(&input, &args, &network).await {
+            Ok(opened) => break opened,
+            Err(e) => match fallbacks.pop_front() {
+                Some(next) => {
+                    eprintln!("Warning: Failed to open {}: {:#}", input, e);
+                    println!("Trying fallback source: {}", next);
+                    input = next;
+                }
+                None => return Err(e),
+            },
+        }
     };
 
-    let media_file = MediaFile::open(&media_path)?;
+    if args.chat {
+        return export_chat_frame(&opened.media_path, &args);
+    }
+    if let Some(limit) = args.hash_frames {
+        return hash_video_frames(&opened.media_path, &args, limit);
+    }
+
+    let OpenedInput {
+        media_path,
+        media_file,
+        radio_stream,
+        video_stream: _video_stream,
+    } = opened;
+    let Some(media_file) = media_file else {
+        return play_playlist(&media_path, &args, emitter).await;
+    };
 
     println!("Media Info:");
     println!("  Type: {:?}", media_file.media_type);
-    println!("  Duration: {:?}", media_file.info.duration);
+    if let Some(format) = &media_file.info.format {
+        match media_file.info.bitrate {
+            Some(bitrate) => println!("  Container: {} ({} kb/s)", format, bitrate / 1000),
+            None => println!("  Container: {}", format),
+        }
+    }
+    match media_file.info.duration {
+        Some(duration) => println!("  Duration: {:?}", duration),
+        None => println!("  Duration: unknown (live stream)"),
+    }
     if let Some(fps) = media_file.info.fps {
         println!("  FPS: {:.2}", fps);
     }
@@ -99,12 +732,67 @@ async fn main() -> Result<()> {
             println!("  Audio Codec: {}", codec);
         }
     }
+    for stream in media_file.streams() {
+        println!("  {}", describe_stream(stream));
+    }
+    if let Some(track) = args.audio_track
+        && !media_file
+            .audio_tracks()
+            .any(|stream| stream.index == track)
+    {
+        anyhow::bail!(
+            "--audio-track {} is not an audio stream of {}",
+            track,
+            media_file.path
+        );
+    }
+
+    if let Some(track) = args.video_track
+        && !media_file
+            .video_tracks()
+            .any(|stream| stream.index as usize == track)
+    {
+        anyhow::bail!(
+            "--video-track {} is not a video stream of {}",
+            track,
+            media_file.path
+        );
+    }
+
+    if let Some(track) = args.sub_track
+        && !media_file
+            .subtitle_tracks()
+            .any(|stream| stream.index == track)
+    {
+        anyhow::bail!(
+            "--sub-track {} is not a subtitle stream of {}",
+            track,
+            media_file.path
+        );
+    }
+
+    if let Some(path) = &args.export_markers {
+        return export_markers(&media_file, &input, path, &args, &network).await;
+    }
+
+    // 長い書き出しの前や --dry-run では、先頭区間から出力量を見積もる
+    if args.dry_run || args.emit.is_some() {
+        if let Err(e) = print_estimate(&media_file, &args) {
+            eprintln!("Warning: Failed to estimate output: {}", e);
+        }
+        if args.dry_run {
+            return Ok(());
+        }
+    }
 
     let enable_audio = !args.no_audio && media_file.info.has_audio;
 
     if enable_audio {
         println!("Audio playback enabled");
-        if let Err(e) = audio::diagnose_audio_system() {
+        // --audio-out のファイル（FIFO）は再生時に一度だけ開く
+        if args.audio_out.is_none()
+            && let Err(e) = audio::diagnose_audio_system(&audio_output(&args))
+        {
             eprintln!("Warning: Audio system check failed: {}", e);
             eprintln!("Continuing with audio disabled...");
         }
@@ -112,55 +800,1070 @@ async fn main() -> Result<()> {
         println!("Audio playback disabled");
     }
 
-    let config = player::PlayerConfig {
+    let mut config = player::PlayerConfig {
         fps: args.fps,
         loop_playback: args.loop_playback,
+        ab_loop: args.ab_loop,
         char_map_index: args.char_map,
         grayscale: args.gray,
         width_modifier: args.width_mod,
         add_newlines: args.newlines,
+        inline: args.inline,
+        pause_on_blur: args.pause_on_blur,
+        key_bindings: args.key_bindings.clone(),
+        watch_queue: watch_queue::WatchQueue::open_default(),
+        input_policy: args.input_policy.clone(),
         enable_audio: !args.no_audio && media_file.info.has_audio,
+        audio_output: audio_output(&args),
+        audio_track: args.audio_track,
+        video_track: args.video_track,
+        audio_ramp: Duration::from_millis(args.audio_ramp),
+        max_latency: args.max_latency.map(Duration::from_millis),
+        sync_threshold: args.sync_threshold.map(Duration::from_millis),
+        screensaver: args.screensaver,
+        roi: args.roi,
+        color_filter: args.color_filter,
+        render_mode: args.render_mode,
+        backend: args.backend,
+        pixel_perfect: args.pixel_perfect,
+        dither: args.dither,
+        color_mode: color_mode(&args),
+        color_matrix: args.color_matrix,
+        calibration: args.calibration,
+        transition: transition(&args),
+        vu_meter: args.vu_meter,
+        visualizer: args.viz,
+        widgets: load_widgets(&args, &network)?,
+        preset: load_preset(&args)?,
+        stats_json: args.stats_json.clone(),
+        pipe_frames: args.pipe_frames.clone(),
+        raw_video: raw_video_config(&args),
+        ..Default::default()
     };
 
+    // ファイルの隣に置かれた設定（video.mp4.ascii-term.toml）を適用する
+    match sidecar::SidecarOptions::load_for(std::path::Path::new(&media_path)) {
+        Ok(Some(options)) => {
+            println!("Applying per-file options: {:?}", options);
+            options.apply(&mut config);
+        }
+        Ok(None) => {}
+        Err(e) => eprintln!("Warning: {:#}", e),
+    }
+
     let mut player = player::Player::new(media_file, config)?;
+    subscribe_events(&mut player, &args)?;
+    if let Some(emitter) = emitter {
+        player.set_emitter(emitter);
+    }
+    if codec::network::is_network_url(&input) {
+        player.set_source_url(input.clone());
+    }
+    if !fallbacks.is_empty() {
+        player.set_fallbacks(fallbacks);
+    }
+    if args.write_subs {
+        match load_youtube_subtitles(&input, &args.sub_lang, &network).await {
+            Ok(Some(subtitles)) => player.set_subtitles(subtitles),
+            Ok(None) => println!("No {} subtitles available", args.sub_lang),
+            Err(e) => eprintln!("Warning: Failed to load subtitles: {}", e),
+        }
+    }
+    if let Some(subtitles) = load_subtitles(&args, &media_path)? {
+        player.set_subtitles(subtitles);
+    }
+    if is_youtube_url(&input) {
+        player.set_chapter_source(input.clone(), network.clone());
+    }
+    if args.sponsorblock {
+        match load_sponsor_segments(&input, &network).await {
+            Ok(segments) if segments.is_empty() => println!("No SponsorBlock segments"),
+            Ok(segments) => {
+                println!("Loaded {} SponsorBlock segments", segments.len());
+                player.set_skip_segments(segments);
+            }
+            Err(e) => eprintln!("Warning: Failed to load SponsorBlock segments: {}", e),
+        }
+    }
+    if let Some(stream) = radio_stream {
+        let station_name = stream.station_name.clone();
+        if let Some(name) = &station_name {
+            println!("  Station: {}", name);
+        }
+        player.set_stream_metadata(station_name, stream.spawn_title_watcher());
+    }
     player.run().await?;
 
     Ok(())
 }
 
+/// テストパターンやデモなど、ファイルを使わない組み込み入力を再生
+async fn play_procedural_source(
+    mut source: Box<dyn codec::source::MediaSource>,
+    args: &Args,
+    emitter: Option<emit::Emitter>,
+) -> Result<()> {
+    if args.chat {
+        let frame = source.video_frame(chat_position(args), 640, 360)?;
+        return print_chat_frame(&frame.to_dynamic_image()?, args);
+    }
+    if let Some(limit) = args.hash_frames {
+        let frame_duration = 1.0 / source.frame_rate();
+        let frames = (0u32..).map(|index| {
+            let timestamp = Duration::from_secs_f64(index as f64 * frame_duration);
+            Ok(source.video_frame(timestamp, 640, 360)?)
+        });
+        let limit = limit.unwrap_or(frame_hash::DEFAULT_SOURCE_FRAMES);
+        frame_hash::hash_frames(
+            frames,
+            hash_render_config(args, args.color_matrix.unwrap_or_default()),
+            Some(limit),
+            &mut io::stdout(),
+        )?;
+        return Ok(());
+    }
+
+    let config = player::PlayerConfig {
+        fps: args.fps,
+        loop_playback: args.loop_playback,
+        char_map_index: args.char_map,
+        grayscale: args.gray,
+        width_modifier: args.width_mod,
+        add_newlines: args.newlines,
+        inline: args.inline,
+        pause_on_blur: args.pause_on_blur,
+        key_bindings: args.key_bindings.clone(),
+        enable_audio: !args.no_audio,
+        audio_output: audio_output(args),
+        audio_ramp: Duration::from_millis(args.audio_ramp),
+        max_latency: None,
+        screensaver: None,
+        roi: false,
+        widgets: load_widgets(args, &network_options(args))?,
+        preset: load_preset(args)?,
+        stats_json: args.stats_json.clone(),
+        pipe_frames: args.pipe_frames.clone(),
+        raw_video: raw_video_config(args),
+        render_mode: args.render_mode,
+        backend: args.backend,
+        pixel_perfect: args.pixel_perfect,
+        dither: args.dither,
+        color_mode: color_mode(args),
+        color_matrix: args.color_matrix,
+        calibration: args.calibration,
+        ..Default::default()
+    };
+
+    let mut player = player::Player::with_source(source, config)?;
+    subscribe_events(&mut player, args)?;
+    if let Some(emitter) = emitter {
+        player.set_emitter(emitter);
+    }
+    player.run().await
+}
+
+/// ディレクトリ内の音声ファイル、または再生リストのファイル（`.m3u`）をアルバムとして再生
+async fn play_playlist(input: &str, args: &Args, emitter: Option<emit::Emitter>) -> Result<()> {
+    if args.no_audio {
+        return Err(anyhow::anyhow!(
+            "Playlist playback is audio-only; remove --no-audio"
+        ));
+    }
+
+    let playlist = if std::path::Path::new(input).is_dir() {
+        playlist::Playlist::from_directory(input)?
+    } else {
+        playlist::Playlist::from_file(input, &args.input_policy)?
+    };
+    let title_card = title_card_duration(args);
+
+    let config = player::PlayerConfig {
+        fps: args.fps,
+        loop_playback: args.loop_playback,
+        char_map_index: args.char_map,
+        grayscale: args.gray,
+        width_modifier: args.width_mod,
+        add_newlines: args.newlines,
+        pause_on_blur: args.pause_on_blur,
+        key_bindings: args.key_bindings.clone(),
+        enable_audio: true,
+        audio_output: audio_output(args),
+        audio_ramp: Duration::from_millis(args.audio_ramp),
+        max_latency: None,
+        screensaver: args.screensaver,
+        roi: false,
+        title_card,
+        transition: transition(args),
+        title_card_countdown: args.title_card_countdown,
+        widgets: load_widgets(args, &network_options(args))?,
+        preset: load_preset(args)?,
+        stats_json: args.stats_json.clone(),
+        pipe_frames: args.pipe_frames.clone(),
+        raw_video: raw_video_config(args),
+        calibration: args.calibration,
+        ..Default::default()
+    };
+
+    if playlist.is_empty() {
+        if args.screensaver.is_none() {
+            return Err(anyhow::anyhow!(
+                "No playable audio files found in {}",
+                input
+            ));
+        }
+        println!("No playable audio files found in {}", input);
+        let mut player = player::Player::idle(config)?;
+        subscribe_events(&mut player, args)?;
+        if let Some(emitter) = emitter {
+            player.set_emitter(emitter);
+        }
+        return player.run().await;
+    }
+
+    println!("Album Info:");
+    println!("  Artist: {}", playlist.album_artist().unwrap_or("Unknown"));
+    println!("  Album: {}", playlist.album_title().unwrap_or("Unknown"));
+    for (index, entry) in playlist.entries().iter().enumerate() {
+        println!("  {:2}. {}", index + 1, playlist::track_title(entry));
+    }
+
+    let mut player = player::Player::with_playlist(playlist, config)?;
+    subscribe_events(&mut player, args)?;
+    if let Some(emitter) = emitter {
+        player.set_emitter(emitter);
+    }
+    player.run().await
+}
+
+/// 先頭区間を処理して、出力サイズ・帯域・処理時間の見積もりを表示する
+fn print_estimate(media_file: &MediaFile, args: &Args) -> Result<()> {
+    if media_file.media_type != codec::MediaType::Video {
+        println!("Estimate: only video inputs produce a frame stream");
+        return Ok(());
+    }
+    let Some(duration) = media_file.info.duration else {
+        println!("Estimate unavailable: unknown duration (live stream)");
+        return Ok(());
+    };
+
+    let fps = arg_values::frame_rate(args.fps, media_file.info.fps);
+    let (term_width, term_height) = crossterm::terminal::size().unwrap_or((80, 24));
+    let render_config = renderer::RenderConfig {
+        target_width: (term_width as u32).saturating_div(args.width_mod),
+        target_height: term_height as u32,
+        char_map_index: args.char_map.unwrap_or(0),
+        grayscale: args.gray,
+        color_filter: args.color_filter,
+        render_mode: args.render_mode,
+        pixel_perfect: args.pixel_perfect,
+        dither: args.dither,
+        color_mode: color_mode(args),
+        luma: player::luma_matrix(args.color_matrix, media_file),
+        transfer: args.calibration.transfer_curve(),
+    };
+
+    let stats = estimate::sample_video(
+        &media_file.path,
+        args.video_track,
+        render_config,
+        fps,
+        args.emit,
+    )?;
+    match stats.extrapolate(duration, fps) {
+        Some(estimate) => println!(
+            "Estimate (from {} sampled frames):\n{}",
+            stats.frames,
+            estimate.summary()
+        ),
+        None => println!("Estimate unavailable: no video frames decoded"),
+    }
+    Ok(())
+}
+
+/// メディアの 1 フレームをチャット用のテキストとして表示する
+fn export_chat_frame(path: &str, args: &Args) -> Result<()> {
+    let media_file = MediaFile::open(path)?;
+    let image = match media_file.media_type {
+        codec::MediaType::Image => codec::format::decode_image(path)?,
+        codec::MediaType::Video => {
+            let mut decoder = codec::video::VideoDecoder::new(path, 0, 0, args.video_track)?;
+            let position = chat_position(args);
+            if !position.is_zero() {
+                decoder.seek(position)?;
+            }
+            decoder
+                .decode_one()?
+                .ok_or_else(|| anyhow::anyhow!("No video frame at {:?}", position))?
+                .to_dynamic_image()?
+        }
+        _ => return Err(anyhow::anyhow!("{} has no picture to export", path)),
+    };
+    print_chat_frame(&image, args)
+}
+
+fn print_chat_frame(image: &image::DynamicImage, args: &Args) -> Result<()> {
+    let options = chat::ChatOptions {
+        max_width: args.chat_width,
+        code_fence: args.code_fence,
+    };
+    println!("{}", chat::render_chat(image, options)?);
+    Ok(())
+}
+
+/// 動画の各フレームのハッシュを表示する
+fn hash_video_frames(path: &str, args: &Args, limit: Option<u64>) -> Result<()> {
+    let media_file = MediaFile::open(path)?;
+    if media_file.media_type != codec::MediaType::Video {
+        return Err(anyhow::anyhow!("{} has no video to hash", path));
+    }
+    let mut decoder = codec::video::VideoDecoder::new(path, 0, 0, args.video_track)?;
+    let frames = std::iter::from_fn(|| decoder.decode_one().map_err(Into::into).transpose());
+    let config = hash_render_config(args, player::luma_matrix(args.color_matrix, &media_file));
+    frame_hash::hash_frames(frames, config, limit, &mut io::stdout())?;
+    Ok(())
+}
+
+/// `--hash-frames` の描画設定。文字セットを自動で選ばず、大きさも固定する
+fn hash_render_config(args: &Args, luma: codec::color::Matrix) -> renderer::RenderConfig {
+    renderer::RenderConfig {
+        target_width: frame_hash::HASH_COLUMNS / args.width_mod,
+        target_height: frame_hash::HASH_ROWS,
+        char_map_index: args.char_map.unwrap_or(0),
+        grayscale: args.gray,
+        color_filter: args.color_filter,
+        render_mode: args.render_mode,
+        pixel_perfect: args.pixel_perfect,
+        dither: args.dither,
+        // ハッシュがターミナルによって変わらないよう、指定がなければ truecolor
+        color_mode: args.color_mode.unwrap_or(ansi::ColorMode::Truecolor),
+        luma,
+        // ターミナルごとの色の補正もハッシュに含めない
+        transfer: None,
+    }
+}
+
+/// 調整画面で色の補正を調整し、設定ファイルに保存する
+fn calibrate(path: Option<&std::path::Path>) -> Result<()> {
+    let current = match path {
+        Some(path) if path.exists() => config::Config::load(path)?,
+        Some(_) => config::Config::default(),
+        None => config::Config::load_default()?,
+    };
+    match calibration::run_interactive(current.calibration)? {
+        Some(calibration) => {
+            let path = config::save_calibration(path, &calibration)?;
+            println!("Saved calibration to {}", path.display());
+        }
+        None => println!("Calibration cancelled"),
+    }
+    Ok(())
+}
+
+/// `--chat-at` の位置
+fn chat_position(args: &Args) -> Duration {
+    args.chat_at.map_or(Duration::ZERO, Duration::from_secs_f64)
+}
+
+fn network_options(args: &Args) -> downloader::NetworkOptions {
+    downloader::NetworkOptions {
+        limit_rate: args.limit_rate,
+        proxy: args.proxy.clone(),
+    }
+}
+
+/// `--quality` と `--yt-format` で選ぶ YouTube の形式
+fn format_selection(args: &Args) -> downloader::FormatSelection {
+    downloader::FormatSelection {
+        quality: args.quality,
+        format: args.yt_format.clone(),
+    }
+}
+
+/// `--color-matrix` と `--color-range` の指定
+fn yuv_override(args: &Args) -> codec::video::YuvOverride {
+    codec::video::YuvOverride {
+        matrix: args.color_matrix,
+        range: args.color_range,
+    }
+}
+
+/// `--decode-worker` の子プロセスの引数（この後に入力とリングのパスが付く）
+fn decode_worker_args(args: &Args) -> Vec<String> {
+    let mut worker_args = vec!["decode-worker".to_string()];
+    if let Some(matrix) = args.color_matrix {
+        worker_args.extend(["--color-matrix".to_string(), matrix.name().to_string()]);
+    }
+    if let Some(range) = args.color_range {
+        worker_args.extend(["--color-range".to_string(), range.name().to_string()]);
+    }
+    worker_args.extend([
+        "--tonemap".to_string(),
+        args.tonemap.name().to_string(),
+        "--hdr-transfer".to_string(),
+        args.hdr_transfer.name().to_string(),
+    ]);
+    worker_args
+}
+
+/// `--color-mode`、なければターミナルから推定した色の種類
+fn color_mode(args: &Args) -> ansi::ColorMode {
+    args.color_mode.unwrap_or_else(terminal::detect_color_mode)
+}
+
+/// `--audio-out` があればファイル、なければ `--audio-backend` のデバイス
+fn audio_output(args: &Args) -> audio::AudioOutput {
+    match &args.audio_out {
+        Some(path) => audio::AudioOutput::File(path.clone()),
+        None => audio::AudioOutput::Device(args.audio_backend),
+    }
+}
+
+/// `--widgets` の設定を読み込む
+fn load_widgets(
+    args: &Args,
+    network: &downloader::NetworkOptions,
+) -> Result<Option<widgets::Widgets>> {
+    let Some(path) = &args.widgets else {
+        return Ok(None);
+    };
+    let config = widgets::WidgetsConfig::load(path)?;
+    Ok(Some(widgets::Widgets::new(config, network.clone())))
+}
+
+/// `--preset` の設定を読み込む
+fn load_preset(args: &Args) -> Result<Option<render_preset::PresetWatcher>> {
+    args.preset
+        .as_deref()
+        .map(render_preset::PresetWatcher::open)
+        .transpose()
+}
+
+/// プレイヤーのイベントを `--event-log` のファイルへ書き出し、`--keep-awake` に使う
+fn subscribe_events(player: &mut player::Player, args: &Args) -> Result<()> {
+    if let Some(path) = &args.event_log {
+        player_events::spawn_log_writer(player.subscribe(), path)?;
+    }
+    if args.keep_awake {
+        keep_awake::spawn(player.subscribe());
+    }
+    Ok(())
+}
+
+/// `--transition` の種類と長さ
+fn transition(args: &Args) -> Option<transition::Transition> {
+    args.transition.map(|kind| transition::Transition {
+        kind,
+        duration: Duration::from_secs_f64(args.transition_duration),
+    })
+}
+
+/// "#1 audio: aac [eng] 2ch 48000 Hz" のようなストリームの一行説明
+fn describe_stream(stream: &codec::StreamInfo) -> String {
+    let mut text = match &stream.kind {
+        codec::StreamKind::Video { width, height, fps } => {
+            format!(
+                "#{} video: {} {}x{} {:.2} fps",
+                stream.index, stream.codec, width, height, fps
+            )
+        }
+        codec::StreamKind::Audio {
+            sample_rate,
+            channels,
+        } => format!(
+            "#{} audio: {} {}ch {} Hz",
+            stream.index, stream.codec, channels, sample_rate
+        ),
+        codec::StreamKind::Subtitle { .. } => {
+            format!("#{} subtitle: {}", stream.index, stream.codec)
+        }
+    };
+    if let Some(language) = &stream.language {
+        text.push_str(&format!(" [{}]", language));
+    }
+    if let Some(title) = &stream.title {
+        text.push_str(&format!(" \"{}\"", title));
+    }
+    if stream.disposition.forced {
+        text.push_str(" (forced)");
+    }
+    if stream.disposition.attached_pic {
+        text.push_str(" (cover art)");
+    }
+    text
+}
+
+/// `--rawvideo-listen` の配信設定
+fn raw_video_config(args: &Args) -> Option<raw_video::RawVideoConfig> {
+    args.rawvideo_listen.map(|addr| raw_video::RawVideoConfig {
+        addr,
+        size: args.rawvideo_size.unwrap_or(raw_video::DEFAULT_SIZE),
+        fps: args.rawvideo_fps.unwrap_or(raw_video::DEFAULT_FPS),
+    })
+}
+
+/// `--title-card` の表示時間
+fn title_card_duration(args: &Args) -> Option<Duration> {
+    args.title_card.map(Duration::from_secs_f64)
+}
+
 fn is_url(input: &str) -> bool {
     input.starts_with("http://") || input.starts_with("https://")
 }
 
-async fn handle_url_input(url: &str, browser: &str) -> Result<String> {
-    use url::Url;
+fn is_youtube_url(url: &str) -> bool {
+    url::Url::parse(url)
+        .ok()
+        .and_then(|parsed| parsed.domain().map(str::to_string))
+        .map(|domain| domain.contains("youtube.com") || domain.contains("youtu.be"))
+        .unwrap_or(false)
+}
 
-    let parsed_url = Url::parse(url)?;
+/// URL が Icecast/Shoutcast などのラジオ配信であれば接続を返す
+async fn probe_radio_stream(
+    url: &str,
+    network: &downloader::NetworkOptions,
+) -> Option<icy::IcyStream> {
+    if is_youtube_url(url) {
+        return None;
+    }
 
-    if let Some(domain) = parsed_url.domain()
-        && (domain.contains("youtube.com") || domain.contains("youtu.be"))
-    {
+    let client = match network.http_client() {
+        Ok(client) => client,
+        Err(e) => {
+            eprintln!("Warning: {}", e);
+            return None;
+        }
+    };
+    match icy::IcyStream::connect(&client, url).await {
+        Ok(stream) => stream,
+        Err(e) => {
+            eprintln!("Warning: Failed to probe stream: {}", e);
+            None
+        }
+    }
+}
+
+/// 開いた入力
+struct OpenedInput {
+    /// 再生するファイルのパス（ダウンロードした場合は一時ファイル）または URL
+    media_path: String,
+    /// ディレクトリの場合は None
+    media_file: Option<MediaFile>,
+    radio_stream: Option<icy::IcyStream>,
+    /// ダウンロードしながら再生している YouTube 動画（再生が終わるまで保持する）
+    video_stream: Option<downloader::VideoStream>,
+}
+
+/// 入力を再生できる状態にする（URL はダウンロードし、ハッシュを確認してから開く）
+async fn open_input(
+    input: &str,
+    args: &Args,
+    network: &downloader::NetworkOptions,
+) -> Result<OpenedInput> {
+    let expected_sha256 = args.expect_sha256.as_deref();
+    let mut radio_stream = None;
+    let mut video_stream = None;
+    let media_path = if codec::network::is_realtime_url(input) {
+        // RTSP/RTMP は FFmpeg のネットワークデマルチプレクサで直接受信する
+        println!("Live stream input: {}", input);
+        ensure_no_checksum_for_stream(expected_sha256)?;
+        input.to_string()
+    } else if is_url(input) {
+        // 終わりのないラジオ配信はダウンロードせず、FFmpeg で直接開く
+        match probe_radio_stream(input, network).await {
+            Some(stream) => {
+                println!("Internet radio stream detected");
+                ensure_no_checksum_for_stream(expected_sha256)?;
+                radio_stream = Some(stream);
+                input.to_string()
+            }
+            // ハッシュを確かめるときは、再生前にすべてダウンロードする
+            None if is_youtube_url(input) && !args.prefetch && expected_sha256.is_none() => {
+                println!("Streaming YouTube video...");
+                let stream = downloader::stream_video(
+                    input,
+                    &args.browser,
+                    &format_selection(args),
+                    network,
+                )
+                .await?;
+                let url = stream.url().to_string();
+                video_stream = Some(stream);
+                url
+            }
+            None => {
+                handle_url_input(
+                    input,
+                    &args.browser,
+                    &format_selection(args),
+                    network,
+                    expected_sha256,
+                )
+                .await?
+            }
+        }
+    } else {
+        verify_local_checksum(std::path::Path::new(input), expected_sha256)?;
+        input.to_string()
+    };
+
+    enter_sandbox(args, &media_path)?;
+    let path = std::path::Path::new(&media_path);
+    let media_file = if path.is_dir() || playlist::is_playlist_file(path) {
+        None
+    } else {
+        Some(MediaFile::open(&media_path)?)
+    };
+    Ok(OpenedInput {
+        media_path,
+        media_file,
+        radio_stream,
+        video_stream,
+    })
+}
+
+/// `--decode-worker` の子プロセス（標準入出力は親とのやり取りに使う）
+#[cfg(unix)]
+fn serve_decode_worker(input: &str, ring: &std::path::Path) -> Result<()> {
+    Ok(codec::video::worker::serve(input, ring)?)
+}
+
+#[cfg(not(unix))]
+fn serve_decode_worker(_input: &str, _ring: &std::path::Path) -> Result<()> {
+    anyhow::bail!("--decode-worker is only supported on Unix")
+}
+
+/// `--emit` の出力先として標準出力を確保する
+///
+/// 以降のログがフレームに混ざらないよう、ほかの出力より先に呼ぶ
+fn stdout_emitter(args: &Args) -> Result<Option<emit::Emitter>> {
+    match args.emit {
+        Some(format) if !args.dry_run => Ok(Some(emit::Emitter::to_stdout(format)?)),
+        _ => Ok(None),
+    }
+}
+
+/// `--list-cameras`: 映像を取り込めるデバイスと大きさを表示する
+fn list_cameras() -> Result<()> {
+    let cameras = codec::source::camera::list_cameras();
+    if cameras.is_empty() {
+        println!("No cameras found");
+    }
+    for camera in cameras {
+        let sizes: Vec<String> = camera
+            .sizes
+            .iter()
+            .map(|(width, height)| format!("{}x{}", width, height))
+            .collect();
+        println!("{}  {}", camera.path.display(), camera.name);
+        if !sizes.is_empty() {
+            println!("    {}", sizes.join(" "));
+        }
+    }
+    Ok(())
+}
+
+/// `--sandbox` なら、`media_path` を解析する前にプロセスの権限を絞る
+///
+/// 再生中も通信する入力・オプション（配信、URL の --fallback、字幕など）では通信は許す
+fn enter_sandbox(args: &Args, media_path: &str) -> Result<()> {
+    if !args.sandbox {
+        return Ok(());
+    }
+    let network = is_url(media_path)
+        || codec::network::is_realtime_url(media_path)
+        || args.fallback.iter().any(|fallback| is_url(fallback))
+        || playlist_has_url(media_path)
+        || args.write_subs
+        || args.sponsorblock
+        || args.widgets.is_some()
+        || args.pipe_frames.is_some()
+        || args.rawvideo_listen.is_some();
+    let outputs = [
+        &args.stats_json,
+        &args.event_log,
+        &args.audio_out,
+        &args.export_markers,
+    ];
+    outputs
+        .into_iter()
+        .flatten()
+        .fold(sandbox::Policy::new(network), |policy, path| {
+            policy.allow_output(path)
+        })
+        .enter()
+}
+
+/// 再生リストのファイルが URL を含むかどうか（読めなければ false）
+fn playlist_has_url(path: &str) -> bool {
+    let path = std::path::Path::new(path);
+    playlist::is_playlist_file(path)
+        && playlist::read_playlist_file(path)
+            .is_ok_and(|sources| sources.iter().flatten().any(|source| is_url(source)))
+}
+
+async fn handle_url_input(
+    url: &str,
+    browser: &str,
+    format: &downloader::FormatSelection,
+    network: &downloader::NetworkOptions,
+    expected_sha256: Option<&str>,
+) -> Result<String> {
+    url::Url::parse(url)?;
+
+    if is_youtube_url(url) {
         println!("Downloading YouTube video...");
-        let temp_path = downloader::download_video(url, browser).await?;
+        let mut progress = download_progress::ProgressLine::on_stdout();
+        let temp_path =
+            downloader::download_video_with_progress(url, browser, format, network, |p| {
+                progress.update(p)
+            })
+            .await?;
+        progress.finish();
+        if let Some(expected) = expected_sha256 {
+            downloader::verify_sha256(&downloader::sha256_file(&temp_path)?, expected)?;
+            println!("SHA-256 verified");
+        }
         return Ok(temp_path.to_string_lossy().to_string());
     }
 
+    // 期待するハッシュが指定されていなければ、URL の隣の .sha256 を探す
+    let expected = match expected_sha256 {
+        Some(expected) => Some(expected.to_string()),
+        None => downloader::fetch_sha256_sidecar(url, network)
+            .await
+            .unwrap_or_else(|e| {
+                eprintln!("Warning: Failed to fetch checksum file: {}", e);
+                None
+            }),
+    };
+
     // For other URLs, download directly
     println!("Downloading media file...");
-    let temp_path = download_url(url).await?;
-    Ok(temp_path)
+    let mut progress = download_progress::ProgressLine::on_stdout();
+    let temp_path =
+        downloader::download_file_with_progress(url, network, expected.as_deref(), |p| {
+            progress.update(p)
+        })
+        .await?;
+    progress.finish();
+    if expected.is_some() {
+        println!("SHA-256 verified");
+    }
+    Ok(temp_path.to_string_lossy().to_string())
 }
 
-async fn download_url(url: &str) -> Result<String> {
-    use std::io::Write;
-    use tempfile::NamedTempFile;
+/// ローカルファイルのハッシュを確認する（指定がなければ隣の .sha256 を使う）
+fn verify_local_checksum(path: &std::path::Path, expected_sha256: Option<&str>) -> Result<()> {
+    if !path.is_file() {
+        if expected_sha256.is_some() {
+            anyhow::bail!("--expect-sha256 needs a single media file");
+        }
+        return Ok(());
+    }
 
-    let response = reqwest::get(url).await?;
-    let content = response.bytes().await?;
+    let expected = match expected_sha256 {
+        Some(expected) => expected.to_string(),
+        None => match downloader::read_sha256_sidecar(path)? {
+            Some(expected) => expected,
+            None => return Ok(()),
+        },
+    };
+    downloader::verify_sha256(&downloader::sha256_file(path)?, &expected)?;
+    println!("SHA-256 verified");
+    Ok(())
+}
+
+/// 終わりのない配信はハッシュを確認できない
+fn ensure_no_checksum_for_stream(expected_sha256: Option<&str>) -> Result<()> {
+    if expected_sha256.is_some() {
+        anyhow::bail!("--expect-sha256 cannot verify live streams");
+    }
+    Ok(())
+}
+
+/// `--sub` の字幕ファイル、または `--sub-track` の埋め込み字幕を読み込む
+fn load_subtitles(args: &Args, media_path: &str) -> Result<Option<subtitles::Subtitles>> {
+    let cues = if let Some(path) = &args.sub {
+        codec::subtitle::load_file(path)
+            .with_context(|| format!("Failed to load subtitles from {}", path.display()))?
+    } else if let Some(track) = args.sub_track {
+        println!("Reading subtitle stream #{}...", track);
+        codec::subtitle::decode_stream(media_path, track)
+            .with_context(|| format!("Failed to read subtitle stream #{}", track))?
+    } else {
+        return Ok(None);
+    };
+    let subtitles = subtitles::Subtitles::from_cues(cues);
+    println!("Loaded {} subtitle cues", subtitles.len());
+    Ok(Some(subtitles))
+}
 
-    let mut temp_file = NamedTempFile::new()?;
-    temp_file.write_all(&content)?;
+/// YouTube の字幕を yt-dlp で取得する
+async fn load_youtube_subtitles(
+    url: &str,
+    lang: &str,
+    network: &downloader::NetworkOptions,
+) -> Result<Option<subtitles::Subtitles>> {
+    if !is_youtube_url(url) {
+        return Err(anyhow::anyhow!(
+            "--write-subs is only supported for YouTube URLs"
+        ));
+    }
 
-    let path = temp_file.into_temp_path();
-    Ok(path.to_string_lossy().to_string())
+    println!("Downloading {} subtitles...", lang);
+    let Some(vtt) = downloader::download_subtitles(url, lang, network).await? else {
+        return Ok(None);
+    };
+    let subtitles = subtitles::Subtitles::parse(&vtt);
+    println!("Loaded {} subtitle cues", subtitles.len());
+    Ok((!subtitles.is_empty()).then_some(subtitles))
+}
+
+/// チャプター・ブックマーク・検出した無音／黒画面の区間を JSON に書き出す
+async fn export_markers(
+    media_file: &codec::MediaFile,
+    input: &str,
+    path: &std::path::Path,
+    args: &Args,
+    network: &downloader::NetworkOptions,
+) -> Result<()> {
+    let chapters = if is_youtube_url(input) {
+        chapters::fetch_youtube_chapters(input, network)
+            .await
+            .unwrap_or_else(|e| {
+                eprintln!("Warning: Failed to load chapters: {}", e);
+                chapters::Chapters::default()
+            })
+    } else {
+        chapters::Chapters::default()
+    };
+    let skip_segments = if args.sponsorblock {
+        load_sponsor_segments(input, network)
+            .await
+            .unwrap_or_else(|e| {
+                eprintln!("Warning: Failed to load SponsorBlock segments: {}", e);
+                skip_segments::SkipSegments::default()
+            })
+    } else {
+        skip_segments::SkipSegments::default()
+    };
+
+    // 再生時と同じく、URL から開いた場合は URL でブックマークを引く
+    let bookmark_key = if codec::network::is_network_url(input) {
+        input
+    } else {
+        &media_file.path
+    };
+    let markers = markers::Markers::analyze(media_file, bookmark_key, chapters, &skip_segments)?;
+    markers.write_json(path, input)?;
+    println!(
+        "Wrote {} chapters, {} bookmarks and {} segments to {}",
+        markers.chapters.len(),
+        markers.bookmarks.len(),
+        markers.segments.len(),
+        path.display()
+    );
+    Ok(())
+}
+
+/// SponsorBlock から YouTube 動画の飛ばす区間を取得する
+async fn load_sponsor_segments(
+    url: &str,
+    network: &downloader::NetworkOptions,
+) -> Result<skip_segments::SkipSegments> {
+    let video_id = downloader::youtube_video_id(url)
+        .ok_or_else(|| anyhow::anyhow!("--sponsorblock is only supported for YouTube URLs"))?;
+
+    let segments =
+        downloader::fetch_sponsor_segments(&video_id, downloader::DEFAULT_CATEGORIES, network)
+            .await?;
+    let segments = segments
+        .into_iter()
+        .filter(|segment| segment.start() >= 0.0 && segment.end() > segment.start())
+        .map(|segment| skip_segments::SkipSegment {
+            start: Duration::from_secs_f64(segment.start()),
+            end: Duration::from_secs_f64(segment.end()),
+            category: segment.category,
+        })
+        .collect();
+    Ok(skip_segments::SkipSegments::new(segments))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::CommandFactory;
+
+    fn parse(extra: &[&str]) -> Result<Args, clap::Error> {
+        Args::try_parse_from(["ascii_term", "video.mp4"].iter().chain(extra))
+    }
+
+    #[test]
+    fn test_args_definition() {
+        Args::command().debug_assert();
+    }
+
+    #[test]
+    fn test_rejects_out_of_range_values() {
+        assert!(parse(&["--width-mod", "0"]).is_err());
+        assert!(parse(&["--char-map", "10"]).is_err());
+        assert!(parse(&["--fps", "-5"]).is_err());
+        assert!(parse(&["--fps", "0"]).is_err());
+        assert!(parse(&["--title-card", "0"]).is_err());
+        assert!(parse(&["--chat", "--chat-width", "0"]).is_err());
+        assert!(parse(&["--chat", "--chat-at", "-1"]).is_err());
+        assert!(parse(&["--ab-loop", "20:10"]).is_err());
+
+        let args = parse(&["--width-mod", "2", "--char-map", "9", "--fps", "12.5"]).unwrap();
+        assert_eq!(
+            (args.width_mod, args.char_map, args.fps),
+            (2, Some(9), Some(12.5))
+        );
+    }
+
+    #[test]
+    fn test_rejects_conflicting_options() {
+        assert!(parse(&["--vu-meter", "--no-audio"]).is_err());
+        assert!(parse(&["--viz", "spectrum", "--no-audio"]).is_err());
+        assert!(parse(&["--chat", "--emit", "jsonl"]).is_err());
+        assert!(parse(&["--chat-at", "5"]).is_err());
+        assert!(parse(&["--code-fence"]).is_err());
+        assert!(parse(&["--chat", "--chat-at", "5", "--code-fence"]).is_ok());
+        assert!(parse(&["--hash-frames", "--chat"]).is_err());
+    }
+
+    #[test]
+    fn test_enqueue_takes_the_input() {
+        let args = Args::try_parse_from(["ascii_term", "--enqueue", "video.mp4"]).unwrap();
+        assert_eq!(
+            (args.enqueue.as_deref(), args.input),
+            (Some("video.mp4"), None)
+        );
+        assert!(parse(&["--enqueue", "other.mp4"]).is_err());
+    }
+
+    #[test]
+    fn test_camera_replaces_the_input() {
+        let args =
+            Args::try_parse_from(["ascii_term", "--camera", "0", "--camera-size", "1280x720"])
+                .unwrap();
+        assert_eq!(
+            (args.camera.as_deref(), args.camera_size, args.input),
+            (Some("0"), Some((1280, 720)), None)
+        );
+        assert!(parse(&["--camera", "0"]).is_err());
+        assert!(parse(&["--camera-size", "1280x720"]).is_err());
+        assert!(Args::try_parse_from(["ascii_term", "--camera-size", "1280x720"]).is_err());
+    }
+
+    #[test]
+    fn test_hash_frames_limit_is_optional() {
+        assert_eq!(parse(&[]).unwrap().hash_frames, None);
+        assert_eq!(parse(&["--hash-frames"]).unwrap().hash_frames, Some(None));
+        assert_eq!(
+            parse(&["--hash-frames=30"]).unwrap().hash_frames,
+            Some(Some(30))
+        );
+    }
+
+    #[test]
+    fn test_audio_track() {
+        assert_eq!(parse(&["--audio-track", "2"]).unwrap().audio_track, Some(2));
+        assert!(parse(&["--audio-track", "2", "--no-audio"]).is_err());
+    }
+
+    #[test]
+    fn test_video_track() {
+        assert_eq!(parse(&[]).unwrap().video_track, None);
+        assert_eq!(parse(&["--video-track", "1"]).unwrap().video_track, Some(1));
+        assert!(parse(&["--video-track", "-1"]).is_err());
+    }
+
+    #[test]
+    fn test_subtitle_options() {
+        let args = parse(&["--sub", "movie.srt"]).unwrap();
+        assert_eq!(args.sub, Some(std::path::PathBuf::from("movie.srt")));
+        assert_eq!(parse(&["--sub-track", "3"]).unwrap().sub_track, Some(3));
+        assert!(parse(&["--sub", "movie.srt", "--sub-track", "3"]).is_err());
+        assert!(parse(&["--sub", "movie.srt", "--write-subs"]).is_err());
+    }
+
+    #[test]
+    fn test_rawvideo_options() {
+        let args = parse(&["--rawvideo-listen", "9000", "--rawvideo-fps", "60"]).unwrap();
+        let config = raw_video_config(&args).unwrap();
+        assert_eq!(config.addr, "127.0.0.1:9000".parse().unwrap());
+        assert_eq!(config.size, raw_video::DEFAULT_SIZE);
+        assert_eq!(config.fps, 60.0);
+        assert!(parse(&["--rawvideo-size", "640x360"]).is_err());
+        assert!(parse(&["--rawvideo-listen", "9000", "--emit", "jsonl"]).is_err());
+    }
+
+    #[test]
+    fn test_config_init_command() {
+        let args = Args::try_parse_from(["ascii_term", "config", "init", "--force"]).unwrap();
+        assert!(matches!(
+            args.command,
+            Some(Command::Config {
+                action: ConfigAction::Init {
+                    path: None,
+                    force: true
+                }
+            })
+        ));
+        let args =
+            Args::try_parse_from(["ascii_term", "replay", "demo.cast", "--speed", "2x"]).unwrap();
+        assert!(matches!(
+            args.command,
+            Some(Command::Replay { speed, .. }) if speed == 2.0
+        ));
+        // 入力を省くと、あとで見るキューの先頭を再生する
+        assert_eq!(Args::try_parse_from(["ascii_term"]).unwrap().input, None);
+    }
+
+    #[test]
+    fn test_calibration_from_config() {
+        let config = config::Config::parse("[calibration]\ngamma = [1.2, 1.0, 0.9]\n").unwrap();
+        let matches = Args::command()
+            .try_get_matches_from(["ascii_term", "video.mp4"])
+            .unwrap();
+        let mut args = Args::from_arg_matches(&matches).unwrap();
+        apply_config(&mut args, &matches, &config).unwrap();
+        assert_eq!(args.calibration.gamma, [1.2, 1.0, 0.9]);
+
+        // --no-calibration なら補正しない
+        let matches = Args::command()
+            .try_get_matches_from(["ascii_term", "--no-calibration", "video.mp4"])
+            .unwrap();
+        let mut args = Args::from_arg_matches(&matches).unwrap();
+        apply_config(&mut args, &matches, &config).unwrap();
+        assert!(args.calibration.is_identity());
+    }
+
+    #[test]
+    fn test_command_line_wins_over_config() {
+        let config = config::Config::parse(
+            r#"
+            char_map = 3
+            width_mod = 2
+            [audio]
+            enabled = false
+            [download]
+            browser = "chrome"
+            "#,
+        )
+        .unwrap();
+        let matches = Args::command()
+            .try_get_matches_from(["ascii_term", "video.mp4", "--char-map", "5"])
+            .unwrap();
+        let mut args = Args::from_arg_matches(&matches).unwrap();
+        apply_config(&mut args, &matches, &config).unwrap();
+
+        assert_eq!(args.char_map, Some(5));
+        assert_eq!(args.width_mod, 2);
+        assert!(args.no_audio);
+        assert_eq!(args.browser, "chrome");
+        assert_eq!(args.audio_ramp, 50);
+    }
 }
@@ -0,0 +1,265 @@
+//! チャプター・ブックマーク・無音／黒画面の区間を JSON に書き出す（`--export-markers`）
+//!
+//! 編集用のスクリプトなどから ascii-term の解析結果を使えるようにする。
+//! 無音は音声のピーク波形から、黒画面は映像をすべてデコードして輝度から求める
+
+use std::fs;
+use std::path::Path;
+use std::time::Duration;
+
+use anyhow::{Context, Result};
+use codec::MediaFile;
+use codec::color::luminance;
+use codec::video::VideoDecoder;
+use serde_json::json;
+
+use crate::chapters::Chapters;
+use crate::playback_state::PlaybackState;
+use crate::skip_segments::SkipSegments;
+
+/// 無音の判定に使う区間の長さ
+const SILENCE_RESOLUTION: Duration = Duration::from_millis(50);
+
+/// これ未満のピークを無音とみなす（約 -50 dBFS）
+const SILENCE_THRESHOLD: f32 = 0.003;
+
+/// これより暗い画素を黒とみなす
+const BLACK_PIXEL_THRESHOLD: u8 = 32;
+
+/// 黒い画素がこの割合以上のフレームを黒画面とみなす
+const BLACK_PICTURE_RATIO: f64 = 0.98;
+
+/// 黒画面の判定で輝度を調べる画素の間隔（全画素は調べない）
+const BLACK_SAMPLE_STEP: usize = 4;
+
+/// これより短い無音・黒画面は書き出さない
+const MIN_SEGMENT_DURATION: Duration = Duration::from_secs(2);
+
+/// 書き出す区間
+#[derive(Debug, Clone, PartialEq)]
+pub struct Segment {
+    pub start: Duration,
+    pub end: Duration,
+    /// "silence"、"black"、または SponsorBlock の分類
+    pub kind: String,
+}
+
+/// 書き出す内容
+#[derive(Debug, Default)]
+pub struct Markers {
+    pub chapters: Chapters,
+    /// ブックマーク番号と位置
+    pub bookmarks: Vec<(u8, Duration)>,
+    pub segments: Vec<Segment>,
+}
+
+impl Markers {
+    /// ブックマークを読み込み、無音・黒画面を検出する
+    ///
+    /// `bookmark_key` は再生時にブックマークを記録するキー（URL またはパス）
+    pub fn analyze(
+        media_file: &MediaFile,
+        bookmark_key: &str,
+        chapters: Chapters,
+        skip_segments: &SkipSegments,
+    ) -> Result<Self> {
+        let bookmarks = PlaybackState::load_default().bookmarks(bookmark_key);
+        let mut segments: Vec<Segment> = skip_segments
+            .iter()
+            .map(|segment| Segment {
+                start: segment.start,
+                end: segment.end,
+                kind: segment.category.clone(),
+            })
+            .collect();
+
+        match media_file.info.duration.filter(|d| !d.is_zero()) {
+            Some(duration) if media_file.info.has_audio => {
+                println!("Detecting silence...");
+                segments.extend(detect_silence(&media_file.path, duration)?);
+            }
+            _ => {}
+        }
+        if media_file.info.has_video {
+            println!("Detecting black frames...");
+            segments.extend(detect_black(&media_file.path, media_file.info.duration)?);
+        }
+        segments.sort_by_key(|segment| segment.start);
+
+        Ok(Self {
+            chapters,
+            bookmarks,
+            segments,
+        })
+    }
+
+    pub fn to_json(&self, input: &str) -> serde_json::Value {
+        json!({
+            "version": env!("CARGO_PKG_VERSION"),
+            "input": input,
+            "chapters": self
+                .chapters
+                .iter()
+                .map(|chapter| json!({
+                    "start": chapter.start.as_secs_f64(),
+                    "title": chapter.title,
+                }))
+                .collect::<Vec<_>>(),
+            "bookmarks": self
+                .bookmarks
+                .iter()
+                .map(|(slot, position)| json!({
+                    "slot": slot,
+                    "position": position.as_secs_f64(),
+                }))
+                .collect::<Vec<_>>(),
+            "segments": self
+                .segments
+                .iter()
+                .map(|segment| json!({
+                    "start": segment.start.as_secs_f64(),
+                    "end": segment.end.as_secs_f64(),
+                    "kind": segment.kind,
+                }))
+                .collect::<Vec<_>>(),
+        })
+    }
+
+    pub fn write_json(&self, path: &Path, input: &str) -> Result<()> {
+        let json = serde_json::to_string_pretty(&self.to_json(input))?;
+        fs::write(path, json + "\n").with_context(|| format!("Failed to write {}", path.display()))
+    }
+}
+
+/// 音声のピーク波形から無音の区間を求める
+fn detect_silence(path: &str, duration: Duration) -> Result<Vec<Segment>> {
+    let buckets = (duration.as_secs_f64() / SILENCE_RESOLUTION.as_secs_f64()).ceil() as usize;
+    let peaks = codec::audio::peak_waveform(path, duration, buckets)?;
+    let bucket = duration.div_f64(peaks.len().max(1) as f64);
+    let samples = peaks
+        .iter()
+        .enumerate()
+        .map(|(index, &peak)| (bucket * index as u32, peak < SILENCE_THRESHOLD));
+    Ok(to_segments(runs(samples, duration), "silence"))
+}
+
+/// 映像をすべてデコードし、黒いフレームが続く区間を求める
+fn detect_black(path: &str, duration: Option<Duration>) -> Result<Vec<Segment>> {
+    let mut decoder = VideoDecoder::new(path, 0, 0, None)?;
+    let mut samples = Vec::new();
+    let mut last = Duration::ZERO;
+    while let Some(frame) = decoder.decode_one()? {
+        last = last.max(frame.timestamp);
+        samples.push((frame.timestamp, is_black(&frame.data)));
+    }
+    Ok(to_segments(
+        runs(samples.into_iter(), duration.unwrap_or(last)),
+        "black",
+    ))
+}
+
+/// RGB24 の画素のほとんどが暗いかどうか
+fn is_black(rgb: &[u8]) -> bool {
+    let (mut dark, mut total) = (0usize, 0usize);
+    for pixel in rgb.chunks_exact(3).step_by(BLACK_SAMPLE_STEP) {
+        total += 1;
+        if luminance([pixel[0], pixel[1], pixel[2]]) < BLACK_PIXEL_THRESHOLD {
+            dark += 1;
+        }
+    }
+    total > 0 && dark as f64 >= total as f64 * BLACK_PICTURE_RATIO
+}
+
+/// 時刻順の (時刻, 該当するか) から、該当し続けた区間を求める
+///
+/// 区間は次に該当しなくなった時刻まで（最後まで続けば `end` まで）とし、
+/// `MIN_SEGMENT_DURATION` より短いものは除く
+fn runs(
+    samples: impl Iterator<Item = (Duration, bool)>,
+    end: Duration,
+) -> Vec<(Duration, Duration)> {
+    let mut runs = Vec::new();
+    let mut start = None;
+    for (time, matched) in samples {
+        match (matched, start) {
+            (true, None) => start = Some(time),
+            (false, Some(from)) => {
+                runs.push((from, time));
+                start = None;
+            }
+            _ => {}
+        }
+    }
+    if let Some(from) = start {
+        runs.push((from, end.max(from)));
+    }
+    runs.retain(|(from, to)| to.saturating_sub(*from) >= MIN_SEGMENT_DURATION);
+    runs
+}
+
+fn to_segments(runs: Vec<(Duration, Duration)>, kind: &str) -> Vec<Segment> {
+    runs.into_iter()
+        .map(|(start, end)| Segment {
+            start,
+            end,
+            kind: kind.to_string(),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chapters::Chapter;
+
+    fn secs(value: u64) -> Duration {
+        Duration::from_secs(value)
+    }
+
+    #[test]
+    fn test_runs() {
+        let samples = [
+            (secs(0), true),
+            (secs(1), false),
+            (secs(2), true),
+            (secs(5), false),
+            (secs(6), true),
+            (secs(8), true),
+        ];
+        // 0-1 は短いので除き、最後の区間は終端まで
+        assert_eq!(
+            runs(samples.into_iter(), secs(10)),
+            vec![(secs(2), secs(5)), (secs(6), secs(10))]
+        );
+    }
+
+    #[test]
+    fn test_is_black() {
+        assert!(is_black(&[0u8; 3 * 100]));
+        assert!(!is_black(&[200u8; 3 * 100]));
+        assert!(!is_black(&[]));
+    }
+
+    #[test]
+    fn test_to_json() {
+        let markers = Markers {
+            chapters: Chapters::new(vec![Chapter {
+                start: secs(60),
+                title: "Intro".to_string(),
+            }]),
+            bookmarks: vec![(3, Duration::from_millis(1500))],
+            segments: vec![Segment {
+                start: secs(2),
+                end: secs(5),
+                kind: "silence".to_string(),
+            }],
+        };
+        let json = markers.to_json("video.mp4");
+        assert_eq!(json["chapters"][0]["start"], 60.0);
+        assert_eq!(json["chapters"][0]["title"], "Intro");
+        assert_eq!(json["bookmarks"][0]["slot"], 3);
+        assert_eq!(json["bookmarks"][0]["position"], 1.5);
+        assert_eq!(json["segments"][0]["end"], 5.0);
+        assert_eq!(json["segments"][0]["kind"], "silence");
+    }
+}
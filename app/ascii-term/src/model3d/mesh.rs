@@ -0,0 +1,279 @@
+//! OBJ / STL の三角形メッシュ読み込み
+
+use std::path::Path;
+
+use anyhow::{Context, Result};
+
+pub type Vec3 = [f32; 3];
+
+/// 三角形の集合
+#[derive(Debug, Clone, Default)]
+pub struct Mesh {
+    pub triangles: Vec<[Vec3; 3]>,
+}
+
+impl Mesh {
+    /// 拡張子（.obj / .stl）に応じてファイルを読み込む
+    pub fn load(path: &Path) -> Result<Self> {
+        let extension = path
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .map(str::to_ascii_lowercase);
+        let bytes =
+            std::fs::read(path).with_context(|| format!("Failed to read {}", path.display()))?;
+
+        let mesh = match extension.as_deref() {
+            Some("obj") => Self::parse_obj(&String::from_utf8_lossy(&bytes))?,
+            Some("stl") => Self::parse_stl(&bytes)?,
+            _ => {
+                return Err(anyhow::anyhow!(
+                    "Unsupported model format: {} (expected .obj or .stl)",
+                    path.display()
+                ));
+            }
+        };
+
+        if mesh.triangles.is_empty() {
+            return Err(anyhow::anyhow!("No faces found in {}", path.display()));
+        }
+        Ok(mesh)
+    }
+
+    /// Wavefront OBJ を解析する（`v` と `f` のみ使い、多角形は扇形に三角形分割する）
+    pub fn parse_obj(text: &str) -> Result<Self> {
+        let mut vertices: Vec<Vec3> = Vec::new();
+        let mut triangles = Vec::new();
+
+        for (line_number, line) in text.lines().enumerate() {
+            let mut tokens = line.split_whitespace();
+            match tokens.next() {
+                Some("v") => {
+                    let coords: Vec<f32> = tokens
+                        .take(3)
+                        .map(str::parse)
+                        .collect::<std::result::Result<_, _>>()
+                        .with_context(|| format!("Invalid vertex on line {}", line_number + 1))?;
+                    let [x, y, z] = coords[..] else {
+                        return Err(anyhow::anyhow!(
+                            "Vertex on line {} needs 3 coordinates",
+                            line_number + 1
+                        ));
+                    };
+                    vertices.push([x, y, z]);
+                }
+                Some("f") => {
+                    // "7", "7/1", "7//3", "7/1/3" の先頭が頂点番号（1 始まり、負数は末尾から）
+                    let indices: Vec<usize> = tokens
+                        .map(|token| {
+                            let index: i64 = token.split('/').next().unwrap_or("").parse()?;
+                            let resolved = if index < 0 {
+                                vertices.len() as i64 + index
+                            } else {
+                                index - 1
+                            };
+                            usize::try_from(resolved)
+                                .ok()
+                                .filter(|&i| i < vertices.len())
+                                .ok_or_else(|| anyhow::anyhow!("index {} out of range", index))
+                        })
+                        .collect::<Result<_>>()
+                        .with_context(|| format!("Invalid face on line {}", line_number + 1))?;
+
+                    for i in 1..indices.len().saturating_sub(1) {
+                        triangles.push([
+                            vertices[indices[0]],
+                            vertices[indices[i]],
+                            vertices[indices[i + 1]],
+                        ]);
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        Ok(Self { triangles })
+    }
+
+    /// STL（ASCII / バイナリ）を解析する
+    pub fn parse_stl(bytes: &[u8]) -> Result<Self> {
+        // バイナリでも "solid" で始まるファイルがあるため、サイズが一致すればバイナリとみなす
+        if let Some(count) = bytes.get(80..84) {
+            let count = u32::from_le_bytes(count.try_into()?) as usize;
+            if bytes.len() == 84 + count * 50 {
+                return Ok(Self::parse_binary_stl(&bytes[84..]));
+            }
+        }
+
+        let text = std::str::from_utf8(bytes).context("STL is neither binary nor ASCII")?;
+        if !text.trim_start().starts_with("solid") {
+            return Err(anyhow::anyhow!("Invalid STL file"));
+        }
+
+        let mut vertices = Vec::new();
+        for line in text.lines() {
+            let mut tokens = line.split_whitespace();
+            if tokens.next() != Some("vertex") {
+                continue;
+            }
+            let coords: Vec<f32> = tokens
+                .take(3)
+                .map(str::parse)
+                .collect::<std::result::Result<_, _>>()
+                .context("Invalid STL vertex")?;
+            let [x, y, z] = coords[..] else {
+                return Err(anyhow::anyhow!("STL vertex needs 3 coordinates"));
+            };
+            vertices.push([x, y, z]);
+        }
+
+        let triangles = vertices
+            .chunks_exact(3)
+            .map(|v| [v[0], v[1], v[2]])
+            .collect();
+        Ok(Self { triangles })
+    }
+
+    /// バイナリ STL の三角形（法線 12 バイト + 頂点 36 バイト + 属性 2 バイト）
+    fn parse_binary_stl(records: &[u8]) -> Self {
+        let float = |bytes: &[u8], offset: usize| {
+            f32::from_le_bytes([
+                bytes[offset],
+                bytes[offset + 1],
+                bytes[offset + 2],
+                bytes[offset + 3],
+            ])
+        };
+        let triangles = records
+            .chunks_exact(50)
+            .map(|record| {
+                let vertex = |i: usize| {
+                    let base = 12 + i * 12;
+                    [
+                        float(record, base),
+                        float(record, base + 4),
+                        float(record, base + 8),
+                    ]
+                };
+                [vertex(0), vertex(1), vertex(2)]
+            })
+            .collect();
+        Self { triangles }
+    }
+
+    /// 中心を原点に移し、全体が半径 1 の球に収まるよう拡大縮小する
+    pub fn normalized(mut self) -> Self {
+        let mut min = [f32::MAX; 3];
+        let mut max = [f32::MIN; 3];
+        for vertex in self.triangles.iter().flatten() {
+            for axis in 0..3 {
+                min[axis] = min[axis].min(vertex[axis]);
+                max[axis] = max[axis].max(vertex[axis]);
+            }
+        }
+        let center = [0, 1, 2].map(|axis| (min[axis] + max[axis]) / 2.0);
+
+        let radius = self
+            .triangles
+            .iter()
+            .flatten()
+            .map(|v| {
+                let d = [0, 1, 2].map(|axis| v[axis] - center[axis]);
+                (d[0] * d[0] + d[1] * d[1] + d[2] * d[2]).sqrt()
+            })
+            .fold(0.0f32, f32::max)
+            .max(f32::EPSILON);
+
+        for vertex in self.triangles.iter_mut().flatten() {
+            *vertex = [0, 1, 2].map(|axis| (vertex[axis] - center[axis]) / radius);
+        }
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_obj_triangulates_polygons() {
+        let obj = "\
+# unit square and a triangle using negative indices
+v 0 0 0
+v 1 0 0
+v 1 1 0
+v 0 1 0
+vt 0 0
+vn 0 0 1
+f 1/1/1 2/1/1 3/1/1 4/1/1
+f -4//1 -3//1 -1//1
+";
+        let mesh = Mesh::parse_obj(obj).unwrap();
+        assert_eq!(mesh.triangles.len(), 3);
+        assert_eq!(
+            mesh.triangles[1],
+            [[0.0; 3], [1.0, 1.0, 0.0], [0.0, 1.0, 0.0]]
+        );
+        assert_eq!(mesh.triangles[2][2], [0.0, 1.0, 0.0]);
+    }
+
+    #[test]
+    fn test_parse_obj_rejects_bad_index() {
+        assert!(Mesh::parse_obj("v 0 0 0\nf 1 2 3\n").is_err());
+        assert!(Mesh::parse_obj("v 0 zero 0\n").is_err());
+    }
+
+    #[test]
+    fn test_parse_ascii_stl() {
+        let stl = "\
+solid tri
+  facet normal 0 0 1
+    outer loop
+      vertex 0 0 0
+      vertex 1 0 0
+      vertex 0 1 0
+    endloop
+  endfacet
+endsolid tri
+";
+        let mesh = Mesh::parse_stl(stl.as_bytes()).unwrap();
+        assert_eq!(
+            mesh.triangles,
+            vec![[[0.0; 3], [1.0, 0.0, 0.0], [0.0, 1.0, 0.0]]]
+        );
+    }
+
+    #[test]
+    fn test_parse_binary_stl() {
+        // ヘッダーが "solid" で始まるバイナリ STL
+        let mut stl = b"solid but actually binary".to_vec();
+        stl.resize(80, 0);
+        stl.extend_from_slice(&1u32.to_le_bytes());
+        for value in [
+            0.0f32, 0.0, 1.0, 0.0, 0.0, 0.0, 2.0, 0.0, 0.0, 0.0, 2.0, 0.0,
+        ] {
+            stl.extend_from_slice(&value.to_le_bytes());
+        }
+        stl.extend_from_slice(&[0, 0]);
+
+        let mesh = Mesh::parse_stl(&stl).unwrap();
+        assert_eq!(
+            mesh.triangles,
+            vec![[[0.0; 3], [2.0, 0.0, 0.0], [0.0, 2.0, 0.0]]]
+        );
+    }
+
+    #[test]
+    fn test_normalized_fits_unit_sphere() {
+        let mesh = Mesh {
+            triangles: vec![[[10.0, 0.0, 0.0], [14.0, 0.0, 0.0], [10.0, 3.0, 0.0]]],
+        }
+        .normalized();
+        let max_radius = mesh
+            .triangles
+            .iter()
+            .flatten()
+            .map(|v| (v[0] * v[0] + v[1] * v[1] + v[2] * v[2]).sqrt())
+            .fold(0.0f32, f32::max);
+        assert!((max_radius - 1.0).abs() < 1e-5);
+    }
+}
@@ -0,0 +1,150 @@
+//! 深度バッファ付きの三角形・線分のラスタライズ
+
+use std::time::Duration;
+
+use codec::source::Raster;
+use codec::source::raster::Rgb;
+use codec::video::VideoFrame;
+
+/// 画面上の点（x, y はピクセル座標、z は視点からの距離）
+pub type ScreenPoint = [f32; 3];
+
+/// 奥行きを考慮して描画するバッファ
+pub struct DepthRaster {
+    raster: Raster,
+    depth: Vec<f32>,
+}
+
+impl DepthRaster {
+    pub fn new(width: u32, height: u32) -> Self {
+        Self {
+            raster: Raster::new(width, height),
+            depth: vec![f32::INFINITY; width as usize * height as usize],
+        }
+    }
+
+    /// 手前にある場合だけピクセルを塗る
+    fn plot(&mut self, x: i32, y: i32, z: f32, color: Rgb) {
+        let (width, height) = (self.raster.width() as i32, self.raster.height() as i32);
+        if x < 0 || y < 0 || x >= width || y >= height {
+            return;
+        }
+        let index = (y * width + x) as usize;
+        if z < self.depth[index] {
+            self.depth[index] = z;
+            self.raster.put(x, y, color);
+        }
+    }
+
+    /// 三角形を塗りつぶす（ピクセル中心が内側にあるものを塗る。向きは問わない）
+    pub fn fill_triangle(&mut self, [a, b, c]: [ScreenPoint; 3], color: Rgb) {
+        let area = edge(a, b, c);
+        if area.abs() < f32::EPSILON {
+            return;
+        }
+
+        let max_x = self.raster.width() as f32 - 1.0;
+        let max_y = self.raster.height() as f32 - 1.0;
+        let x0 = a[0].min(b[0]).min(c[0]).floor().max(0.0) as i32;
+        let x1 = a[0].max(b[0]).max(c[0]).ceil().min(max_x) as i32;
+        let y0 = a[1].min(b[1]).min(c[1]).floor().max(0.0) as i32;
+        let y1 = a[1].max(b[1]).max(c[1]).ceil().min(max_y) as i32;
+
+        for y in y0..=y1 {
+            for x in x0..=x1 {
+                let p = [x as f32 + 0.5, y as f32 + 0.5, 0.0];
+                let wa = edge(b, c, p) / area;
+                let wb = edge(c, a, p) / area;
+                let wc = edge(a, b, p) / area;
+                if wa < 0.0 || wb < 0.0 || wc < 0.0 {
+                    continue;
+                }
+                let z = wa * a[2] + wb * b[2] + wc * c[2];
+                self.plot(x, y, z, color);
+            }
+        }
+    }
+
+    /// 線分を描く（Bresenham のアルゴリズム）
+    pub fn draw_line(&mut self, from: ScreenPoint, to: ScreenPoint, color: Rgb) {
+        let (mut x, mut y) = (from[0].round() as i32, from[1].round() as i32);
+        let (x1, y1) = (to[0].round() as i32, to[1].round() as i32);
+        let dx = (x1 - x).abs();
+        let dy = -(y1 - y).abs();
+        let sx = if x < x1 { 1 } else { -1 };
+        let sy = if y < y1 { 1 } else { -1 };
+        let steps = dx.max(-dy).max(1) as f32;
+        let mut err = dx + dy;
+        let mut step = 0.0;
+
+        loop {
+            let z = from[2] + (to[2] - from[2]) * (step / steps);
+            self.plot(x, y, z, color);
+            if x == x1 && y == y1 {
+                break;
+            }
+            let e2 = 2 * err;
+            if e2 >= dy {
+                err += dy;
+                x += sx;
+            }
+            if e2 <= dx {
+                err += dx;
+                y += sy;
+            }
+            step += 1.0;
+        }
+    }
+
+    pub fn into_frame(self, timestamp: Duration) -> VideoFrame {
+        self.raster.into_frame(timestamp)
+    }
+}
+
+/// 辺 a→b に対する点 p の符号付き面積（の 2 倍）
+fn edge(a: ScreenPoint, b: ScreenPoint, p: ScreenPoint) -> f32 {
+    (b[0] - a[0]) * (p[1] - a[1]) - (b[1] - a[1]) * (p[0] - a[0])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pixel(frame: &VideoFrame, x: usize, y: usize) -> [u8; 3] {
+        let i = (y * frame.width as usize + x) * 3;
+        [frame.data[i], frame.data[i + 1], frame.data[i + 2]]
+    }
+
+    #[test]
+    fn test_nearer_triangle_wins() {
+        let mut raster = DepthRaster::new(8, 8);
+        let square = |z: f32| {
+            [
+                [[0.0, 0.0, z], [8.0, 0.0, z], [0.0, 8.0, z]],
+                [[8.0, 0.0, z], [8.0, 8.0, z], [0.0, 8.0, z]],
+            ]
+        };
+        for triangle in square(1.0) {
+            raster.fill_triangle(triangle, [0, 255, 0]);
+        }
+        // 奥にある赤は描かれない（時計回りでも塗る）
+        raster.fill_triangle(
+            [[0.0, 0.0, 5.0], [0.0, 8.0, 5.0], [8.0, 0.0, 5.0]],
+            [255, 0, 0],
+        );
+
+        let frame = raster.into_frame(Duration::ZERO);
+        assert_eq!(pixel(&frame, 1, 1), [0, 255, 0]);
+        assert_eq!(pixel(&frame, 6, 6), [0, 255, 0]);
+    }
+
+    #[test]
+    fn test_draw_line_endpoints() {
+        let mut raster = DepthRaster::new(10, 5);
+        raster.draw_line([1.0, 1.0, 1.0], [8.0, 3.0, 1.0], [255, 255, 255]);
+        let frame = raster.into_frame(Duration::ZERO);
+        assert_eq!(pixel(&frame, 1, 1), [255, 255, 255]);
+        assert_eq!(pixel(&frame, 8, 3), [255, 255, 255]);
+        assert_eq!(pixel(&frame, 0, 4), [0, 0, 0]);
+    }
+}
@@ -0,0 +1,246 @@
+//! 音声のみの再生時に表示する「再生中」画面
+//!
+//! カバー画像が埋め込まれていれば左に描き、曲名・アーティスト・アルバム名を右に並べる。
+//! ターミナルが狭くて並ばなければ、曲の情報だけを中央に表示する
+
+use std::time::Duration;
+
+use crate::audio::ChannelLevel;
+use crate::canvas::{TextCanvas, truncate};
+use crate::player_state::PlayerState;
+use crate::renderer::RenderedFrame;
+use crate::status_bar;
+use crate::vu_meter;
+
+const TITLE_COLOR: [u8; 3] = [255, 255, 255];
+const SUBTITLE_COLOR: [u8; 3] = [160, 160, 160];
+const PROGRESS_COLOR: [u8; 3] = [255, 200, 64];
+
+/// カバー画像の左と上下に空ける文字数
+const COVER_MARGIN: u32 = 2;
+/// カバー画像と曲の情報の間の空白
+const PANEL_GAP: u32 = 3;
+/// カバー画像の横に並べるとき、曲の情報に最低限残す幅
+const MIN_PANEL_WIDTH: u32 = 24;
+/// これより小さくしか描けないカバー画像は表示しない
+const MIN_COVER_ROWS: u32 = 4;
+
+/// 再生中の曲の表示内容
+#[derive(Debug, Clone, Default)]
+pub struct NowPlaying {
+    pub title: String,
+    /// アーティストや局名
+    pub subtitle: Option<String>,
+    /// アルバム名（カバー画像の横に並べるときだけ表示する）
+    pub album: Option<String>,
+    /// 描画済みのカバー画像（`cover_size` の大きさ）
+    pub cover: Option<RenderedFrame>,
+    pub elapsed: Duration,
+    /// ライブストリームでは None
+    pub duration: Option<Duration>,
+    pub state: PlayerState,
+    /// 左右チャンネルの音声レベル（音声がなければ None）
+    pub levels: Option<[ChannelLevel; 2]>,
+}
+
+/// 幅・高さが `image_size` の画像を `width` x `height` の画面のカバー画像として描く大きさ
+///
+/// 上下 2 画素で 1 文字の `▀` で描くので、画像の 1 画素が文字幅 1 つ分の正方形になる。
+/// 曲の情報を横に並べる幅が残らなければ None
+pub fn cover_size(image_size: (u32, u32), width: u32, height: u32) -> Option<(u32, u32)> {
+    let (image_width, image_height) = image_size;
+    if image_width == 0 || image_height == 0 {
+        return None;
+    }
+    let max_columns = width.checked_sub(COVER_MARGIN + PANEL_GAP + MIN_PANEL_WIDTH)?;
+    let mut rows = height.saturating_sub(COVER_MARGIN * 2);
+    let mut columns = rows * 2 * image_width / image_height;
+    if columns > max_columns {
+        columns = max_columns;
+        rows = columns * image_height / (image_width * 2);
+    }
+    (rows >= MIN_COVER_ROWS).then_some((columns.max(1), rows))
+}
+
+/// 曲名・サブタイトル・再生位置・レベルメーターを表示するフレームを生成
+///
+/// カバー画像が横に並べば左にカバー画像、右に曲の情報を、並ばなければ曲の情報だけを
+/// 画面中央に描く
+pub fn render_now_playing(now_playing: &NowPlaying, width: u32, height: u32) -> RenderedFrame {
+    let mut canvas = TextCanvas::new(width, height);
+    let cover = now_playing.cover.as_ref().filter(|cover| {
+        COVER_MARGIN + cover.width + PANEL_GAP + MIN_PANEL_WIDTH <= width && cover.height <= height
+    });
+
+    match cover {
+        Some(cover) => {
+            let top = (height - cover.height) as usize / 2;
+            for (y, row) in cover.rows().enumerate() {
+                for (x, cell) in row.iter().enumerate() {
+                    canvas.put_cell(COVER_MARGIN as usize + x, top + y, *cell);
+                }
+            }
+            let left = (COVER_MARGIN + cover.width + PANEL_GAP) as usize;
+            let panel_width = canvas.width().saturating_sub(left + COVER_MARGIN as usize);
+            draw_info(&mut canvas, now_playing, left, panel_width, false);
+        }
+        None => {
+            let width = canvas.width();
+            draw_info(&mut canvas, now_playing, 0, width, true);
+        }
+    }
+
+    canvas.into_frame()
+}
+
+/// 曲の情報を `left` から幅 `width` の範囲に描く（`centered` なら中央揃え）
+fn draw_info(
+    canvas: &mut TextCanvas,
+    now_playing: &NowPlaying,
+    left: usize,
+    width: usize,
+    centered: bool,
+) {
+    // 中央に描くときはアルバム名を省き、これまでどおり 2 行にする
+    let texts = [
+        (Some(now_playing.title.as_str()), TITLE_COLOR),
+        (now_playing.subtitle.as_deref(), SUBTITLE_COLOR),
+        (
+            now_playing.album.as_deref().filter(|_| !centered),
+            SUBTITLE_COLOR,
+        ),
+    ];
+    let text_rows = if centered { 2 } else { 3 };
+    let lines = text_rows + if now_playing.levels.is_some() { 5 } else { 1 };
+    let top = canvas.height().saturating_sub(lines) / 2;
+    let line_width = if centered {
+        width.saturating_sub(4).min(72)
+    } else {
+        width.min(72)
+    };
+
+    let mut put = |y: usize, text: &str, color: [u8; 3]| {
+        let text = truncate(text, width);
+        let x = if centered {
+            width.saturating_sub(text.chars().count()) / 2
+        } else {
+            0
+        };
+        canvas.put_str(left + x, y, &text, color);
+    };
+
+    for (row, (text, color)) in texts.into_iter().take(text_rows).enumerate() {
+        if let Some(text) = text {
+            put(top + row, text, color);
+        }
+    }
+
+    let progress = status_bar::progress_line(
+        now_playing.elapsed,
+        now_playing.duration,
+        now_playing.state,
+        line_width,
+    );
+    let progress_row = top + text_rows + 1;
+    put(progress_row, &progress, PROGRESS_COLOR);
+
+    if let Some(levels) = now_playing.levels {
+        let meter_left = if centered {
+            left + width.saturating_sub(line_width) / 2
+        } else {
+            left
+        };
+        for (i, row) in vu_meter::meter_rows(levels, line_width)
+            .into_iter()
+            .enumerate()
+        {
+            for (x, cell) in row.into_iter().enumerate() {
+                canvas.put_cell(meter_left + x, progress_row + 2 + i, cell);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::renderer::Cell;
+
+    #[test]
+    fn test_now_playing_live_stream() {
+        let now_playing = NowPlaying {
+            title: "Artist - Song".to_string(),
+            subtitle: Some("Example FM".to_string()),
+            album: Some("Album".to_string()),
+            cover: None,
+            elapsed: Duration::from_secs(90),
+            duration: None,
+            state: PlayerState::Playing,
+            levels: None,
+        };
+
+        let frame = render_now_playing(&now_playing, 40, 10);
+        assert_eq!(frame.cells.len(), 400);
+        let text = frame.text();
+        assert!(text.contains("Artist - Song"));
+        assert!(text.contains("Example FM"));
+        assert!(text.contains("▶ 1:30  ● LIVE"));
+        // カバー画像がなければアルバム名は出さない
+        assert!(!text.contains("Album"));
+    }
+
+    #[test]
+    fn test_now_playing_level_meters() {
+        let now_playing = NowPlaying {
+            title: "Song".to_string(),
+            levels: Some([ChannelLevel::default(); 2]),
+            ..Default::default()
+        };
+
+        let frame = render_now_playing(&now_playing, 40, 12);
+        let rows: Vec<String> = frame.text().lines().map(str::to_string).collect();
+        assert!(rows.iter().any(|row| row.trim_start().starts_with("L ·")));
+        assert!(rows.iter().any(|row| row.trim_start().starts_with("R ·")));
+    }
+
+    #[test]
+    fn test_cover_size() {
+        // 正方形の画像は、行数の 2 倍の列数で描く
+        assert_eq!(cover_size((500, 500), 120, 24), Some((40, 20)));
+        // 狭い画面では曲の情報の幅を残すよう縮める
+        assert_eq!(cover_size((500, 500), 49, 24), Some((20, 10)));
+        assert_eq!(cover_size((500, 500), 30, 24), None);
+        assert_eq!(cover_size((0, 0), 120, 24), None);
+    }
+
+    #[test]
+    fn test_now_playing_with_cover() {
+        let cover = RenderedFrame {
+            cells: vec![Cell::new('▀', [255, 0, 0]); 8 * 4],
+            width: 8,
+            height: 4,
+            sixel: None,
+        };
+        let now_playing = NowPlaying {
+            title: "Song".to_string(),
+            subtitle: Some("Artist".to_string()),
+            album: Some("Album".to_string()),
+            cover: Some(cover),
+            ..Default::default()
+        };
+
+        let frame = render_now_playing(&now_playing, 60, 10);
+        let rows: Vec<String> = frame
+            .text()
+            .lines()
+            .map(|row| row.trim_end().to_string())
+            .collect();
+        assert_eq!(rows[3], format!("  {}   Song", "▀".repeat(8)));
+        assert!(rows[4].ends_with("   Artist"));
+        assert!(rows[5].ends_with("   Album"));
+
+        // 横に並ばなければ曲の情報だけを中央に描く
+        let frame = render_now_playing(&now_playing, 30, 10);
+        assert!(!frame.text().contains('▀'));
+    }
+}
@@ -0,0 +1,120 @@
+//! ANSI 256 色・16 色のパレットと、最も近いパレット色の選択
+//!
+//! truecolor に対応しないターミナル向けに、色をパレットの番号へ減らす
+
+use crate::renderer::Rgb;
+
+/// 16 色（xterm の既定値）。8〜15 は明るい色
+const ANSI16: [Rgb; 16] = [
+    [0, 0, 0],
+    [205, 0, 0],
+    [0, 205, 0],
+    [205, 205, 0],
+    [0, 0, 238],
+    [205, 0, 205],
+    [0, 205, 205],
+    [229, 229, 229],
+    [127, 127, 127],
+    [255, 0, 0],
+    [0, 255, 0],
+    [255, 255, 0],
+    [92, 92, 255],
+    [255, 0, 255],
+    [0, 255, 255],
+    [255, 255, 255],
+];
+
+/// 256 色の 6x6x6 の色立方体（16〜231）の各成分の明るさ
+const CUBE_LEVELS: [u8; 6] = [0, 95, 135, 175, 215, 255];
+
+/// 256 色の色立方体の先頭とグレースケール（232〜255）の先頭
+const CUBE_START: u8 = 16;
+const GRAY_START: u8 = 232;
+
+/// 256 色の `index` 番の色
+pub fn ansi256_rgb(index: u8) -> Rgb {
+    match index {
+        0..CUBE_START => ANSI16[index as usize],
+        CUBE_START..GRAY_START => {
+            let cube = index - CUBE_START;
+            [cube / 36, (cube / 6) % 6, cube % 6].map(|level| CUBE_LEVELS[level as usize])
+        }
+        _ => [8 + 10 * (index - GRAY_START); 3],
+    }
+}
+
+/// 256 色のうち最も近い色の番号（色立方体とグレースケールから選ぶ）
+///
+/// 0〜15 はターミナルの設定で色が変わるため使わない
+pub fn nearest_ansi256(rgb: Rgb) -> u8 {
+    let cube_level = |value: u8| match value {
+        0..48 => 0,
+        48..115 => 1,
+        _ => (value - 35) / 40,
+    };
+    let [r, g, b] = rgb.map(cube_level);
+    let cube = CUBE_START + 36 * r + 6 * g + b;
+
+    let average = rgb.iter().map(|&value| value as u32).sum::<u32>() / 3;
+    let gray = GRAY_START + (average.saturating_sub(3) / 10).min(23) as u8;
+
+    if distance(rgb, ansi256_rgb(gray)) < distance(rgb, ansi256_rgb(cube)) {
+        gray
+    } else {
+        cube
+    }
+}
+
+/// 16 色の `index` 番の色
+pub fn ansi16_rgb(index: u8) -> Rgb {
+    ANSI16[index as usize % ANSI16.len()]
+}
+
+/// 16 色のうち最も近い色の番号
+pub fn nearest_ansi16(rgb: Rgb) -> u8 {
+    (0..ANSI16.len() as u8)
+        .min_by_key(|&index| distance(rgb, ANSI16[index as usize]))
+        .unwrap_or(0)
+}
+
+/// 2 色の距離（RGB の差の二乗和）
+fn distance(a: Rgb, b: Rgb) -> u32 {
+    a.iter()
+        .zip(b)
+        .map(|(&a, b)| (a as i32 - b as i32).pow(2) as u32)
+        .sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ansi256_palette() {
+        assert_eq!(ansi256_rgb(16), [0, 0, 0]);
+        assert_eq!(ansi256_rgb(196), [255, 0, 0]);
+        assert_eq!(ansi256_rgb(231), [255, 255, 255]);
+        assert_eq!(ansi256_rgb(232), [8, 8, 8]);
+        assert_eq!(ansi256_rgb(255), [238, 238, 238]);
+    }
+
+    #[test]
+    fn test_nearest_ansi256() {
+        // パレットの色はそのまま選ばれる
+        for index in CUBE_START..=255 {
+            let rgb = ansi256_rgb(index);
+            assert_eq!(ansi256_rgb(nearest_ansi256(rgb)), rgb);
+        }
+        assert_eq!(nearest_ansi256([250, 10, 5]), 196);
+        // 灰色はグレースケールから選ぶ
+        assert_eq!(nearest_ansi256([128, 128, 128]), 244);
+    }
+
+    #[test]
+    fn test_nearest_ansi16() {
+        assert_eq!(nearest_ansi16([10, 10, 10]), 0);
+        assert_eq!(nearest_ansi16([250, 20, 20]), 9);
+        assert_eq!(nearest_ansi16([120, 130, 125]), 8);
+        assert_eq!(ansi16_rgb(nearest_ansi16([0, 0, 230])), [0, 0, 238]);
+    }
+}
@@ -0,0 +1,205 @@
+//! ファイルごとの再生状態（タイムラインのブックマーク）の保存
+//!
+//! 同じクリップを何度も見直すときのために、メディアごとの状態を
+//! JSON ファイルへ保存し、次回の起動時に読み込む
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+
+/// 状態ファイルの名前
+const STATE_FILE_NAME: &str = "playback-state.json";
+
+/// 1 つのメディアの状態
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+struct MediaState {
+    /// ブックマーク番号（0-9）ごとの再生位置（秒）
+    #[serde(default)]
+    bookmarks: BTreeMap<u8, f64>,
+}
+
+/// 全メディアの再生状態
+#[derive(Debug, Default)]
+pub struct PlaybackState {
+    /// 保存先（None の場合は保存しない）
+    path: Option<PathBuf>,
+    /// メディアのキー（絶対パスまたは URL）ごとの状態
+    media: BTreeMap<String, MediaState>,
+}
+
+impl PlaybackState {
+    /// 既定の場所から読み込む（保存先が決められない場合はメモリ上だけで保持）
+    pub fn load_default() -> Self {
+        match default_path() {
+            Some(path) => Self::load(path),
+            None => Self::default(),
+        }
+    }
+
+    /// 状態ファイルを読み込む。ファイルがない、または壊れている場合は空の状態から始める
+    pub fn load(path: PathBuf) -> Self {
+        let media = match fs::read_to_string(&path) {
+            Ok(json) => serde_json::from_str(&json).unwrap_or_else(|e| {
+                eprintln!(
+                    "Warning: Ignoring corrupt playback state {}: {}",
+                    path.display(),
+                    e
+                );
+                BTreeMap::new()
+            }),
+            Err(_) => BTreeMap::new(),
+        };
+
+        Self {
+            path: Some(path),
+            media,
+        }
+    }
+
+    /// ブックマークの位置を取得
+    pub fn bookmark(&self, media: &str, slot: u8) -> Option<Duration> {
+        let seconds = *self.media.get(&media_key(media))?.bookmarks.get(&slot)?;
+        Some(Duration::from_secs_f64(seconds.max(0.0)))
+    }
+
+    /// 設定されているすべてのブックマーク（番号順）
+    pub fn bookmarks(&self, media: &str) -> Vec<(u8, Duration)> {
+        self.media
+            .get(&media_key(media))
+            .map(|state| {
+                state
+                    .bookmarks
+                    .iter()
+                    .map(|(&slot, &seconds)| (slot, Duration::from_secs_f64(seconds.max(0.0))))
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    /// ブックマークを設定し、状態ファイルへ保存する
+    pub fn set_bookmark(&mut self, media: &str, slot: u8, position: Duration) -> Result<()> {
+        self.media
+            .entry(media_key(media))
+            .or_default()
+            .bookmarks
+            .insert(slot, position.as_secs_f64());
+        self.save()
+    }
+
+    fn save(&self) -> Result<()> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+
+        // 書き込み途中で終了しても元のファイルが壊れないよう、一時ファイルから置き換える
+        let temp_path = path.with_extension("json.tmp");
+        fs::write(&temp_path, serde_json::to_string_pretty(&self.media)?)?;
+        fs::rename(&temp_path, path)?;
+        Ok(())
+    }
+}
+
+/// メディアを識別するキー。ローカルファイルは作業ディレクトリによらないよう絶対パスにする
+pub fn media_key(media: &str) -> String {
+    fs::canonicalize(Path::new(media))
+        .map(|path| path.to_string_lossy().into_owned())
+        .unwrap_or_else(|_| media.to_string())
+}
+
+/// 状態ファイルの既定の場所
+fn default_path() -> Option<PathBuf> {
+    Some(state_dir()?.join(STATE_FILE_NAME))
+}
+
+/// 状態を保存するディレクトリ
+///
+/// `$XDG_STATE_HOME/ascii-term/`、なければ `~/.local/state/ascii-term/`
+/// （Windows では `%LOCALAPPDATA%\ascii-term\`）
+pub fn state_dir() -> Option<PathBuf> {
+    let non_empty = |name: &str| std::env::var_os(name).filter(|value| !value.is_empty());
+
+    let dir = if cfg!(windows) {
+        PathBuf::from(non_empty("LOCALAPPDATA")?)
+    } else if let Some(state_home) = non_empty("XDG_STATE_HOME") {
+        PathBuf::from(state_home)
+    } else {
+        PathBuf::from(non_empty("HOME")?)
+            .join(".local")
+            .join("state")
+    };
+    Some(dir.join("ascii-term"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_state_path(name: &str) -> PathBuf {
+        std::env::temp_dir()
+            .join(format!("ascii-term-state-{}-{}", name, std::process::id()))
+            .join(STATE_FILE_NAME)
+    }
+
+    #[test]
+    fn test_bookmarks_persist() {
+        let path = temp_state_path("persist");
+        let mut state = PlaybackState::load(path.clone());
+        assert_eq!(state.bookmark("clip.mp4", 1), None);
+
+        state
+            .set_bookmark("clip.mp4", 1, Duration::from_millis(12_500))
+            .unwrap();
+        state
+            .set_bookmark("clip.mp4", 1, Duration::from_secs(42))
+            .unwrap();
+        state
+            .set_bookmark("https://example.com/a.mp4", 1, Duration::from_secs(3))
+            .unwrap();
+
+        let reloaded = PlaybackState::load(path.clone());
+        assert_eq!(
+            reloaded.bookmark("clip.mp4", 1),
+            Some(Duration::from_secs(42))
+        );
+        assert_eq!(
+            reloaded.bookmark("https://example.com/a.mp4", 1),
+            Some(Duration::from_secs(3))
+        );
+        assert_eq!(reloaded.bookmark("clip.mp4", 2), None);
+        assert_eq!(
+            reloaded.bookmarks("clip.mp4"),
+            vec![(1, Duration::from_secs(42))]
+        );
+        assert!(reloaded.bookmarks("other.mp4").is_empty());
+
+        fs::remove_dir_all(path.parent().unwrap()).ok();
+    }
+
+    #[test]
+    fn test_corrupt_state_starts_empty() {
+        let path = temp_state_path("corrupt");
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(&path, "{ not json").unwrap();
+
+        let mut state = PlaybackState::load(path.clone());
+        assert_eq!(state.bookmark("clip.mp4", 0), None);
+
+        // 壊れたファイルは次の保存で上書きされる
+        state
+            .set_bookmark("clip.mp4", 0, Duration::from_secs(1))
+            .unwrap();
+        assert_eq!(
+            PlaybackState::load(path.clone()).bookmark("clip.mp4", 0),
+            Some(Duration::from_secs(1))
+        );
+
+        fs::remove_dir_all(path.parent().unwrap()).ok();
+    }
+}
@@ -1,42 +1,222 @@
+use std::collections::VecDeque;
+use std::path::PathBuf;
 use std::sync::Arc;
-use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
 use std::time::{Duration, Instant};
 
 use anyhow::Result;
-use codec::video::{AsyncVideoDecoder, VideoFrame};
+use codec::color::Matrix;
+use codec::network::ErrorRecovery;
+use codec::source::{AnimatedImage, MediaSource};
+use codec::track::{TrackFile, TrackKind};
+use codec::video::{AsyncVideoDecoder, Rect, RoiCropper, VideoFrame};
 use crossbeam_channel::{Receiver, Sender, unbounded};
+use image::DynamicImage;
 use tokio::time;
 
-use crate::audio::AudioPlayer;
-use crate::renderer::{AsciiRenderer, RenderConfig, RenderedFrame};
-use crate::terminal::Terminal;
-use codec::{MediaFile, MediaType};
+use crate::ab_loop::{AbLoop, AbMarks};
+use crate::album_view;
+use crate::ansi::ColorMode;
+use crate::arg_values;
+use crate::audio::{AudioOutput, AudioPlayer};
+use crate::calibration::Calibration;
+use crate::chapters::{Chapters, fetch_youtube_chapters};
+use crate::charset_auto::CharsetAdvisor;
+use crate::clock::MasterClock;
+use crate::color_filter::ColorFilter;
+use crate::emit::Emitter;
+use crate::error_message;
+use crate::frame_history::{DEFAULT_HISTORY_BYTES, FrameHistory};
+use crate::frame_pipe::FramePipe;
+use crate::i18n::{Msg, tr, tr_args};
+use crate::input_policy::InputPolicy;
+use crate::live::{FrameAction, LatencyTrimmer};
+use crate::now_playing::{self, NowPlaying};
+use crate::playback_state::PlaybackState;
+use crate::player_events::{EventHub, PlayerEvent};
+use crate::player_state::PlayerState;
+use crate::playlist::{self, Playlist};
+use crate::qr;
+use crate::raw_video::{RawVideoConfig, RawVideoServer};
+use crate::render_preset::{PresetWatcher, RenderPreset};
+use crate::renderer::{
+    AsciiRenderer, Dither, RenderBackend, RenderConfig, RenderMode, RenderedFrame, Renderer,
+};
+use crate::screensaver::ScreensaverKind;
+use crate::session_stats::SessionStats;
+use crate::skip_segments::SkipSegments;
+use crate::status_bar::{PlaybackInfo, format_time};
+use crate::subtitles::Subtitles;
+use crate::terminal::{self, KeyBindings, Terminal, TerminalUpdate, WindowTitle};
+use crate::title_card::TitleCard;
+use crate::transition::Transition;
+use crate::visualizer::{Visualizer, VisualizerKind};
+use crate::vu_meter;
+use crate::watch_queue::WatchQueue;
+use crate::widgets::Widgets;
+use codec::{MediaFile, MediaType, StreamInfo};
 
 #[derive(Debug, Clone)]
 pub struct PlayerConfig {
     pub fps: Option<f64>,
     pub loop_playback: bool,
-    pub char_map_index: u8,
+    /// 文字マップ（None の場合は映像の内容から自動選択）
+    pub char_map_index: Option<u8>,
     pub grayscale: bool,
     pub width_modifier: u32,
     pub add_newlines: bool,
     pub enable_audio: bool,
+    /// 音声の出力先
+    pub audio_output: AudioOutput,
+    /// 再生する音声トラックのストリーム番号（None なら最初の音声）
+    pub audio_track: Option<u32>,
+    /// 再生する映像トラックのストリーム番号（None なら最初の映像）
+    pub video_track: Option<usize>,
+    /// ライブ配信で許容する遅延（None の場合は既定値）
+    pub max_latency: Option<Duration>,
+    /// 再生するものがないときに表示するスクリーンセーバー
+    pub screensaver: Option<ScreensaverKind>,
+    /// 被写体を追従してターミナルの縦横比に切り出す
+    pub roi: bool,
+    /// 映像から固定の範囲を切り出す
+    pub crop: Option<Rect>,
+    /// 音声を遅らせる時間（ミリ秒、負の値で早める）
+    pub audio_delay_ms: i64,
+    /// 時計よりこれ以上遅れたフレームは表示しない（None の場合は 2 フレーム分）
+    pub sync_threshold: Option<Duration>,
+    /// 再生の開始・一時停止・停止・シークで音量を上げ下げする長さ
+    pub audio_ramp: Duration,
+    /// 再生開始位置
+    pub start_offset: Option<Duration>,
+    /// 始めから繰り返す区間（`--ab-loop`）
+    pub ab_loop: Option<AbLoop>,
+    /// 色覚フィルター
+    pub color_filter: ColorFilter,
+    /// 動画に音声レベルメーターを重ねる
+    pub vu_meter: bool,
+    /// 音声だけのメディアで曲の情報の代わりに表示する視覚化
+    pub visualizer: Option<VisualizerKind>,
+    /// 再生リストの曲間にタイトルカードを表示する時間
+    pub title_card: Option<Duration>,
+    /// タイトルカードに残り秒数を表示する
+    pub title_card_countdown: bool,
+    /// 時計・ティッカーのオーバーレイ
+    pub widgets: Option<Widgets>,
+    /// 再生中に読み直す描画設定のファイル
+    pub preset: Option<PresetWatcher>,
+    /// 動画の再生統計を書き出す JSON ファイル
+    pub stats_json: Option<PathBuf>,
+    /// 表示するフレームを標準入力へ送るコマンド
+    pub pipe_frames: Option<String>,
+    /// 表示するフレームを rawvideo で配信する
+    pub raw_video: Option<RawVideoConfig>,
+    pub render_mode: RenderMode,
+    /// 文字で描くか Sixel 画像で描くか
+    pub backend: RenderBackend,
+    /// 画像の出力で、ぼやけないよう整数倍で拡大・縮小する
+    pub pixel_perfect: bool,
+    /// 文字マップ・点字のディザリング
+    pub dither: Dither,
+    /// 出力する色の種類
+    pub color_mode: ColorMode,
+    /// 明るさの重みと YCbCr の変換に使う規格（None ならメディアに従う）
+    pub color_matrix: Option<Matrix>,
+    /// 代替画面を使わず、プロンプトの下のこの行数に描く
+    pub inline: Option<u16>,
+    /// ターミナルのフォーカスが外れている間は一時停止する
+    pub pause_on_blur: bool,
+    /// 設定ファイルで追加したキー割り当て
+    pub key_bindings: KeyBindings,
+    /// 見終えたら次の入力を取り出すキュー
+    pub watch_queue: Option<WatchQueue>,
+    /// キューや `load()` から開いてよい入力（設定ファイルの `[inputs]`）
+    pub input_policy: InputPolicy,
+    /// ループで先頭に戻るときの映像の切り替えと、曲の始まり・終わりのフェード
+    pub transition: Option<Transition>,
+    /// 出力する色のターミナルごとの補正
+    pub calibration: Calibration,
 }
 
+/// ウィンドウタイトルを更新する間隔
+const TITLE_UPDATE_INTERVAL: Duration = Duration::from_secs(2);
+
+/// ステータスバーに描く波形の区間数
+const WAVEFORM_BUCKETS: usize = 512;
+
+/// ステータスバーの再生状況を送る間隔
+const STATUS_UPDATE_INTERVAL: Duration = Duration::from_millis(500);
+
+/// 先へ移動するときに、終端からこれだけ手前で止める
+const SEEK_END_MARGIN: Duration = Duration::from_secs(1);
+
+/// 再生の開始・停止などで音量を上げ下げする長さの既定値
+const DEFAULT_AUDIO_RAMP: Duration = Duration::from_millis(50);
+
+/// ライブ配信で許容する遅延の既定値
+const DEFAULT_MAX_LATENCY: Duration = Duration::from_millis(500);
+
+/// 音声だけのメディアで曲の情報を描き直す間隔（レベルメーターが毎秒 10 回ほど動く）
+const NOW_PLAYING_INTERVAL: Duration = Duration::from_millis(100);
+
+/// `--viz` の視覚化を描き直す間隔
+const VISUALIZER_INTERVAL: Duration = Duration::from_millis(33);
+
 impl Default for PlayerConfig {
     fn default() -> Self {
         Self {
             fps: None,
             loop_playback: false,
-            char_map_index: 0,
+            char_map_index: None,
             grayscale: false,
             width_modifier: 1,
             add_newlines: false,
             enable_audio: true,
+            audio_output: AudioOutput::default(),
+            audio_track: None,
+            video_track: None,
+            max_latency: None,
+            screensaver: None,
+            roi: false,
+            crop: None,
+            audio_delay_ms: 0,
+            sync_threshold: None,
+            audio_ramp: DEFAULT_AUDIO_RAMP,
+            start_offset: None,
+            ab_loop: None,
+            color_filter: ColorFilter::None,
+            vu_meter: false,
+            visualizer: None,
+            title_card: None,
+            title_card_countdown: false,
+            widgets: None,
+            preset: None,
+            stats_json: None,
+            pipe_frames: None,
+            raw_video: None,
+            render_mode: RenderMode::Ascii,
+            backend: RenderBackend::Ascii,
+            pixel_perfect: false,
+            dither: Dither::None,
+            color_mode: ColorMode::Truecolor,
+            color_matrix: None,
+            inline: None,
+            pause_on_blur: false,
+            key_bindings: KeyBindings::default(),
+            watch_queue: None,
+            input_policy: InputPolicy::default(),
+            transition: None,
+            calibration: Calibration::default(),
         }
     }
 }
 
+/// 一時停止中に表示するフレームを動かす向き
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum FrameStep {
+    Forward,
+    Backward,
+}
+
 #[derive(Debug, Clone)]
 pub enum PlayerCommand {
     Play,
@@ -46,12 +226,115 @@ pub enum PlayerCommand {
     ToggleMute,
     SetCharMap(u8),
     ToggleGrayscale,
+    /// ターミナルの大きさが変わった（列数・行数）
+    Resize(u16, u16),
+    ToggleQrCode,
+    /// 色覚フィルターを切り替える
+    CycleColorFilter,
+    /// 次の音声トラックに切り替える
+    CycleAudioTrack,
+    /// 字幕の表示を切り替える
+    ToggleSubtitles,
+    /// 現在の再生位置をブックマーク（0-9）に記録する
+    SetBookmark(u8),
+    /// ブックマークの位置へ移動する
+    JumpToBookmark(u8),
+    /// 次のチャプターへ移動する
+    NextChapter,
+    /// 前のチャプター（またはチャプターの先頭）へ移動する
+    PreviousChapter,
+    /// 取得したチャプター一覧を設定し、待たせていた移動をする
+    /// （取得中にメディアを切り替えていれば捨てる）
+    ChaptersLoaded {
+        chapters: Chapters,
+        generation: usize,
+        forward: bool,
+    },
+    /// 取り出し終えた音声トラックに切り替える
+    /// （取り出し中にメディアを切り替えていれば捨てる）
+    AudioTrackExtracted {
+        stream: u32,
+        track: std::result::Result<Option<Arc<TrackFile>>, String>,
+        generation: usize,
+    },
+    /// 一時停止して 1 フレーム進める
+    StepForward,
+    /// 一時停止して 1 フレーム戻す（表示したフレームの履歴の範囲で）
+    StepBackward,
+    /// 現在の再生位置に A-B リピートの A・B を付ける（付いていれば解除する）
+    MarkAbLoop,
+    /// 指定した再生位置へ移動する
+    Seek(Duration),
+    /// 指定した時間だけ先へ移動する
+    SeekForward(Duration),
+    /// 指定した時間だけ前へ戻る
+    SeekBackward(Duration),
+    /// 音量を増減する（-1.0〜1.0）
+    AdjustVolume(f32),
+    /// 読み直した描画設定を反映する
+    ApplyPreset(RenderPreset),
+    /// 今の再生を終えて、別のメディア（パス）を開いて再生する
+    Load(String),
+}
+
+impl PlayerCommand {
+    /// 描画結果が変わるコマンドかどうか（静止画を描き直すかの判断に使う）
+    fn changes_rendering(&self) -> bool {
+        matches!(
+            self,
+            PlayerCommand::SetCharMap(_)
+                | PlayerCommand::ToggleGrayscale
+                | PlayerCommand::Resize(..)
+                | PlayerCommand::CycleColorFilter
+                | PlayerCommand::ApplyPreset(_)
+        )
+    }
 }
 
 pub struct Player {
     media_file: MediaFile,
+    playlist: Option<Playlist>,
+    station_name: Option<String>,
+    stream_titles: Option<Receiver<String>>,
+    source: Option<Box<dyn MediaSource>>,
+    /// 元の URL（ダウンロードして再生する場合も含む）。QR コードで共有する
+    source_url: Option<String>,
+    /// QR コードを表示中かどうか
+    qr_visible: bool,
+    /// ブックマークなどファイルごとの保存された状態
+    playback_state: PlaybackState,
+    /// 現在の再生位置
+    position: Duration,
+    /// 再生位置を移動できるかどうか（ファイルの動画・音声を再生中のみ）
+    seekable: bool,
+    /// 再生ループで処理する移動先
+    pending_seek: Option<Duration>,
+    /// 再生ループで処理するコマ送り・コマ戻し
+    pending_step: Option<FrameStep>,
+    /// A-B リピートの印
+    ab_marks: AbMarks,
+    /// 今の再生を終えてから切り替えるメディア
+    pending_media: Option<MediaFile>,
+    /// 作成時から別スレッドで開いている映像のデコーダー
+    video_prefetch: Option<VideoPrefetch>,
+    /// メディアを切り替えるたびに増やす（前のメディアの解析結果を捨てるため）
+    media_generation: Arc<AtomicUsize>,
+    /// 字幕と、表示中の字幕のテキスト
+    subtitles: Option<Subtitles>,
+    shown_subtitle: Option<String>,
+    /// 字幕を隠している（メディアを切り替えても引き継ぐ）
+    subtitles_hidden: bool,
+    /// チャプター一覧
+    chapters: Chapters,
+    /// 最初にチャプターを移動するときにチャプターを取得する YouTube の URL
+    chapter_source: Option<(String, downloader::NetworkOptions)>,
+    /// 自動で飛ばす区間
+    skip_segments: SkipSegments,
+    /// 再生中にデコードできなくなったときに切り替える入力（パスまたは URL）
+    fallbacks: VecDeque<String>,
     config: PlayerConfig,
-    state: Arc<AtomicBool>, // true = playing, false = paused
+    /// 再生状態（`set_state` で遷移を確かめてから変える）
+    state: PlayerState,
     stop_signal: Arc<AtomicBool>,
 
     // Channel
@@ -59,86 +342,1101 @@ pub struct Player {
     command_rx: Receiver<PlayerCommand>,
     frame_tx: Sender<RenderedFrame>,
     frame_rx: Receiver<RenderedFrame>,
+    update_tx: Sender<TerminalUpdate>,
+    update_rx: Receiver<TerminalUpdate>,
+    /// 最後にタイトルを更新した時刻と、そのときの再生状態
+    last_title_update: Option<(Instant, PlayerState)>,
+    /// 最後にステータスバーへ再生状況を送った時刻と、そのときの再生状態
+    last_status_update: Option<(Instant, PlayerState)>,
+    /// 状態の変化を購読しているチャンネル
+    events: EventHub,
 
     // Component
-    renderer: AsciiRenderer,
+    renderer: Box<dyn Renderer>,
+    /// 文字マップの自動選択（手動で選んだ場合や決定後は None）
+    charset_advisor: Option<CharsetAdvisor>,
+    /// 被写体を追従する切り出し（`roi` が無効な場合は None）
+    roi_cropper: Option<RoiCropper>,
+    /// 音声だけのメディアを再生中の視覚化（`--viz` を指定しなければ None）
+    visualizer: Option<Visualizer>,
     terminal: Option<Terminal>,
+    /// ターミナルの代わりにフレームを書き出す出力先
+    emitter: Option<Emitter>,
     audio_player: Option<AudioPlayer>,
+    /// 最初のもの以外の音声トラックを選んだときに取り出したファイル
+    audio_track: Option<Arc<TrackFile>>,
+    /// 別スレッドで取り出している音声トラック（ストリーム番号）
+    extracting_audio_track: Option<u32>,
+    /// 最初のもの以外の映像トラックを選んだときに取り出したファイル
+    video_track: Option<TrackFile>,
+}
+
+/// 映像の外にはみ出す切り抜き範囲を無視する
+fn check_crop(config: &mut PlayerConfig, media_file: &MediaFile) {
+    if let Some(rect) = config.crop
+        && let (Some(width), Some(height)) = (media_file.info.width, media_file.info.height)
+        && (rect.x + rect.width > width || rect.y + rect.height > height)
+    {
+        eprintln!(
+            "Warning: Ignoring crop {:?} outside the {}x{} video",
+            rect, width, height
+        );
+        config.crop = None;
+    }
+}
+
+/// 別スレッドで開いている映像のデコーダーと、取り出した映像トラック
+type VideoPrefetch = std::thread::JoinHandle<codec::Result<(AsyncVideoDecoder, Option<TrackFile>)>>;
+
+/// 動画なら映像のデコーダーを別スレッドで開き始める（開いた時点から先読みする）
+///
+/// `video_track` に最初のもの以外の映像を選んだときは、そのトラックを取り出すところから
+/// 別スレッドで行う。取り出せなければ最初の映像トラックで続ける
+fn prefetch_video(media_file: &MediaFile, video_track: Option<usize>) -> Option<VideoPrefetch> {
+    if media_file.media_type != MediaType::Video {
+        return None;
+    }
+    let path = media_file.path.clone();
+    std::thread::Builder::new()
+        .name("video-prefetch".to_string())
+        .spawn(move || {
+            let track =
+                TrackFile::select(&path, TrackKind::Video, video_track).unwrap_or_else(|e| {
+                    eprintln!("Warning: {}", e);
+                    eprintln!("Playing the first video track instead...");
+                    None
+                });
+            let decoder = AsyncVideoDecoder::open_blocking(
+                track.as_ref().map_or(path.as_str(), TrackFile::path),
+            )?;
+            Ok((decoder, track))
+        })
+        .ok()
+}
+
+/// 音声があれば音声プレイヤーを開く（開けなければ映像だけで再生を続ける）
+fn open_audio(
+    media_file: &MediaFile,
+    audio_track: &Option<Arc<TrackFile>>,
+    config: &PlayerConfig,
+) -> Option<AudioPlayer> {
+    if !config.enable_audio || !media_file.info.has_audio {
+        return None;
+    }
+    let fade = config
+        .transition
+        .map_or(Duration::ZERO, |transition| transition.duration);
+    match AudioPlayer::new(
+        audio_path(media_file, audio_track),
+        config.audio_delay_ms,
+        fade,
+        config.audio_ramp,
+        &config.audio_output,
+    ) {
+        Ok(player) => {
+            println!("Audio player initialized successfully");
+            Some(player)
+        }
+        Err(e) => {
+            eprintln!("Warning: Audio initialization failed: {}", e);
+            eprintln!("Continuing with video-only playback...");
+            None
+        }
+    }
+}
+
+/// 音声を読むパス（選んだトラックを取り出したファイルか、メディアそのもの）
+fn audio_path<'a>(media_file: &'a MediaFile, audio_track: &'a Option<Arc<TrackFile>>) -> &'a str {
+    audio_track
+        .as_deref()
+        .map_or(media_file.path.as_str(), TrackFile::path)
+}
+
+/// 映像を読むパス（選んだトラックを取り出したファイルか、メディアそのもの）
+fn video_path<'a>(media_file: &'a MediaFile, video_track: &'a Option<TrackFile>) -> &'a str {
+    video_track
+        .as_ref()
+        .map_or(media_file.path.as_str(), TrackFile::path)
+}
+
+/// 明るさの重みの規格。指定がなければ映像の変換行列（分からなければ BT.709）
+pub fn luma_matrix(color_matrix: Option<Matrix>, media_file: &MediaFile) -> Matrix {
+    color_matrix
+        .or(media_file.info.yuv.map(|yuv| yuv.matrix))
+        .unwrap_or_default()
+}
+
+/// ターミナルの大きさから描画する文字数を決める（最小サイズ未満にはしない）
+fn render_size(columns: u16, rows: u16, width_modifier: u32) -> (u32, u32) {
+    let width = (columns as u32).saturating_div(width_modifier.max(1));
+    (
+        width.max(terminal::MIN_WIDTH as u32),
+        (rows as u32).max(terminal::MIN_HEIGHT as u32),
+    )
 }
 
 impl Player {
-    pub fn new(media_file: MediaFile, config: PlayerConfig) -> Result<Self> {
+    pub fn new(media_file: MediaFile, mut config: PlayerConfig) -> Result<Self> {
+        check_crop(&mut config, &media_file);
+        // 音声出力の準備と並行して映像を開き、最初のフレームまでの時間を縮める
+        let video_prefetch = prefetch_video(&media_file, config.video_track);
+        if let Some(preset) = config
+            .preset
+            .as_ref()
+            .map(|watcher| watcher.preset().clone())
+        {
+            preset.apply_to(&mut config);
+        }
+
         let (command_tx, command_rx) = unbounded();
         let (frame_tx, frame_rx) = unbounded();
+        let (update_tx, update_rx) = unbounded();
 
         let (term_width, term_height) = crossterm::terminal::size().unwrap_or((80, 24));
         println!("Detected terminal size: {}x{}", term_width, term_height);
+        let term_height = config.inline.unwrap_or(term_height);
+        let (target_width, target_height) =
+            render_size(term_width, term_height, config.width_modifier);
         let render_config = RenderConfig {
-            target_width: (term_width as u32).saturating_div(config.width_modifier.max(1)),
-            target_height: term_height as u32,
-            char_map_index: config.char_map_index,
+            target_width,
+            target_height,
+            char_map_index: config.char_map_index.unwrap_or(0),
             grayscale: config.grayscale,
-            add_newlines: config.add_newlines,
+            color_filter: config.color_filter,
+            render_mode: config.render_mode,
+            pixel_perfect: config.pixel_perfect,
+            dither: config.dither,
+            color_mode: config.color_mode,
+            luma: luma_matrix(config.color_matrix, &media_file),
+            transfer: config.calibration.transfer_curve(),
         };
 
-        let renderer = AsciiRenderer::new(render_config);
+        // 文字セルは縦長なので、ピクセル単位の縦横比は列数 / (行数 * 2)
+        let roi_cropper = config.roi.then(|| {
+            let aspect =
+                render_config.target_width as f32 / (render_config.target_height.max(1) * 2) as f32;
+            RoiCropper::new(aspect)
+        });
 
-        let audio_player = if config.enable_audio && media_file.info.has_audio {
-            match AudioPlayer::new(&media_file.path) {
-                Ok(player) => {
-                    println!("Audio player initialized successfully");
-                    Some(player)
-                }
-                Err(e) => {
-                    eprintln!("Warning: Audio initialization failed: {}", e);
-                    eprintln!("Continuing with video-only playback...");
-                    None
-                }
-            }
-        } else {
-            None
-        };
+        let renderer = config.backend.create(render_config);
+        // 点字や Sixel では文字マップを使わない
+        let charset_advisor = (config.char_map_index.is_none()
+            && config.render_mode == RenderMode::Ascii
+            && config.backend == RenderBackend::Ascii)
+            .then(CharsetAdvisor::new);
 
-        Ok(Self {
+        // 選んだ音声トラックは取り出し終えるまで最初のトラックで再生する
+        let audio_player = open_audio(&media_file, &None, &config);
+        let audio_track = config.audio_track.take();
+
+        let mut player = Self {
             media_file,
+            playlist: None,
+            station_name: None,
+            stream_titles: None,
+            source: None,
+            source_url: None,
+            qr_visible: false,
+            playback_state: PlaybackState::load_default(),
+            position: Duration::ZERO,
+            seekable: false,
+            pending_seek: None,
+            pending_step: None,
+            ab_marks: AbMarks::Off,
+            pending_media: None,
+            video_prefetch,
+            media_generation: Arc::new(AtomicUsize::new(0)),
+            subtitles: None,
+            shown_subtitle: None,
+            subtitles_hidden: false,
+            chapters: Chapters::default(),
+            chapter_source: None,
+            skip_segments: SkipSegments::default(),
+            fallbacks: VecDeque::new(),
             config,
-            state: Arc::new(AtomicBool::new(false)),
+            state: PlayerState::default(),
             stop_signal: Arc::new(AtomicBool::new(false)),
             command_tx,
             command_rx,
             frame_tx,
             frame_rx,
+            update_tx,
+            update_rx,
+            last_title_update: None,
+            last_status_update: None,
+            events: EventHub::default(),
             renderer,
+            charset_advisor,
+            roi_cropper,
+            visualizer: None,
             terminal: None,
+            emitter: None,
             audio_player,
-        })
+            audio_track: None,
+            extracting_audio_track: None,
+            video_track: None,
+        };
+        if let Some(stream) = audio_track
+            && player.audio_player.is_some()
+            && player
+                .media_file
+                .audio_tracks()
+                .next()
+                .map(|track| track.index)
+                != Some(stream)
+        {
+            player.start_audio_track(stream);
+        }
+        Ok(player)
+    }
+
+    /// 再生リスト（アルバム）を再生するプレイヤーを作成
+    pub fn with_playlist(playlist: Playlist, config: PlayerConfig) -> Result<Self> {
+        let mut player = Self::new(playlist.current().clone(), config)?;
+        player.playlist = Some(playlist);
+        Ok(player)
+    }
+
+    /// 再生するメディアを持たず、スクリーンセーバーだけを表示するプレイヤーを作成
+    pub fn idle(config: PlayerConfig) -> Result<Self> {
+        let placeholder = MediaFile {
+            path: String::new(),
+            media_type: MediaType::Unknown,
+            info: Default::default(),
+        };
+        Self::new(placeholder, config)
+    }
+
+    /// 手続き的なメディアソース（テストパターンなど）を再生するプレイヤーを作成
+    pub fn with_source(source: Box<dyn MediaSource>, config: PlayerConfig) -> Result<Self> {
+        let mut player = Self::idle(config)?;
+        player.media_file.path = source.name().to_string();
+        player.source = Some(source);
+        Ok(player)
+    }
+
+    /// インターネットラジオの局名と曲名の更新通知を設定
+    pub fn set_stream_metadata(
+        &mut self,
+        station_name: Option<String>,
+        stream_titles: Receiver<String>,
+    ) {
+        self.station_name = station_name;
+        self.stream_titles = Some(stream_titles);
+    }
+
+    /// ターミナルに描画する代わりに、フレームを書き出すようにする
+    pub fn set_emitter(&mut self, emitter: Emitter) {
+        self.emitter = Some(emitter);
+    }
+
+    /// 再生位置に合わせて表示する字幕を設定
+    pub fn set_subtitles(&mut self, subtitles: Subtitles) {
+        self.subtitles = Some(subtitles);
+    }
+
+    /// 現在の再生位置の字幕を表示する（変わったときだけ更新を送る）
+    fn update_subtitle(&mut self) {
+        let Some(subtitles) = &self.subtitles else {
+            return;
+        };
+        let text = if self.subtitles_hidden {
+            None
+        } else {
+            subtitles.text_at(self.position)
+        };
+        if text == self.shown_subtitle.as_deref() {
+            return;
+        }
+
+        self.shown_subtitle = text.map(str::to_string);
+        let _ = self
+            .update_tx
+            .send(TerminalUpdate::Subtitle(self.shown_subtitle.clone()));
+    }
+
+    /// YouTube のチャプターを、最初に前後のチャプターへ移動するときに取得するようにする
+    ///
+    /// 再生を始める前に yt-dlp を余分に待たないよう、必要になるまで取得しない
+    pub fn set_chapter_source(&mut self, url: String, network: downloader::NetworkOptions) {
+        self.chapter_source = Some((url, network));
+    }
+
+    /// 再生中に自動で飛ばす区間を設定
+    pub fn set_skip_segments(&mut self, segments: SkipSegments) {
+        self.skip_segments = segments;
+    }
+
+    /// 再生中にデコードできなくなったときに、順に切り替える入力を設定
+    pub fn set_fallbacks(&mut self, fallbacks: VecDeque<String>) {
+        self.fallbacks = fallbacks;
+    }
+
+    /// 再生位置・状態・曲の変化などのイベントを受け取るチャンネルを返す
+    pub fn subscribe(&mut self) -> Receiver<PlayerEvent> {
+        self.events.subscribe()
+    }
+
+    /// 再生状態を移し、購読者へ知らせる。認められない遷移なら何もせず false を返す
+    fn set_state(&mut self, next: PlayerState) -> bool {
+        if !self.state.can_transition_to(next) {
+            return false;
+        }
+        self.state = next;
+        self.events.emit(PlayerEvent::StateChanged(next));
+        true
+    }
+
+    /// 次の入力に切り替え、現在の再生位置から続けるデコーダーを返す
+    ///
+    /// 再生中に切り替える入力はダウンロードせず、FFmpeg で直接開く
+    async fn fail_over(&mut self, reason: &str) -> Option<AsyncVideoDecoder> {
+        // 切り替え先がなければ、再生を終えるエラーとして run() が知らせる
+        if !self.fallbacks.is_empty() {
+            self.events.emit(PlayerEvent::Error(reason.to_string()));
+        }
+        while let Some(source) = self.fallbacks.pop_front() {
+            println!("Switching to fallback source: {}", source);
+            let resume = self.state;
+            self.set_state(PlayerState::Buffering);
+            self.events.emit(PlayerEvent::Buffering(true));
+            let opened = Self::open_at(&source, self.position).await;
+            self.events.emit(PlayerEvent::Buffering(false));
+            self.set_state(resume);
+            match opened {
+                Ok((media_file, decoder)) => {
+                    self.media_file = media_file;
+                    self.seek_audio(self.position);
+                    self.show_osd(tr_args(Msg::SwitchedToFallback, &[&reason]));
+                    return Some(decoder);
+                }
+                Err(e) => eprintln!("Warning: Failed to open {}: {}", source, e),
+            }
+        }
+        None
+    }
+
+    /// 音声をデコードできなくなったとき、次の入力の `position` から続ける
+    ///
+    /// 切り替えた入力のメディアを返す（切り替え先がなければ None）
+    fn fail_over_audio(&mut self, reason: &str, position: Duration) -> Option<MediaFile> {
+        if !self.fallbacks.is_empty() {
+            self.events.emit(PlayerEvent::Error(reason.to_string()));
+        }
+        while let Some(source) = self.fallbacks.pop_front() {
+            println!("Switching to fallback source: {}", source);
+            let audio_player = self.audio_player.as_mut()?;
+            let opened = MediaFile::open(&source)
+                .map_err(anyhow::Error::from)
+                .and_then(|media_file| {
+                    audio_player.seek(&source, position)?;
+                    Ok(media_file)
+                });
+            match opened {
+                Ok(media_file) => {
+                    self.show_osd(tr_args(Msg::SwitchedToFallback, &[&reason]));
+                    return Some(media_file);
+                }
+                Err(e) => eprintln!("Warning: Failed to open {}: {}", source, e),
+            }
+        }
+        None
+    }
+
+    /// 映像を開き、指定した位置へ移動する
+    async fn open_at(source: &str, position: Duration) -> Result<(MediaFile, AsyncVideoDecoder)> {
+        let media_file = MediaFile::open(source)?;
+        if !media_file.info.has_video {
+            return Err(anyhow::anyhow!("No video stream"));
+        }
+        let mut decoder = AsyncVideoDecoder::open(source).await?;
+        if !position.is_zero() {
+            decoder.seek(position).await?;
+        }
+        Ok((media_file, decoder))
+    }
+
+    /// 再生位置が飛ばす区間に入ったら、区間の終わりへの移動を予約する
+    fn skip_segment(&mut self) {
+        if !self.seekable || self.pending_seek.is_some() {
+            return;
+        }
+        let Some(segment) = self.skip_segments.segment_at(self.position) else {
+            return;
+        };
+
+        let end = segment.end;
+        println!(
+            "Skipping {} segment {:?} - {:?}",
+            segment.category, segment.start, end
+        );
+        self.show_osd(tr_args(Msg::Skipped, &[&segment.category]));
+        self.pending_seek = Some(end);
+    }
+
+    /// 再生を始める位置（開始位置の指定がなければ `--ab-loop` の A）
+    fn start_position(&self) -> Option<Duration> {
+        self.config
+            .start_offset
+            .or_else(|| self.config.ab_loop.map(|ab_loop| ab_loop.start))
+    }
+
+    /// 再生位置が A-B リピートの B に達したら、A への移動を予約する
+    ///
+    /// 移動は通常のシークと同じく再生ループで行い、音声もそこで合わせ直す
+    fn repeat_ab_loop(&mut self) {
+        if !self.seekable || self.pending_seek.is_some() {
+            return;
+        }
+        if let Some(start) = self
+            .ab_marks
+            .active()
+            .and_then(|ab_loop| ab_loop.restart_at(self.position))
+        {
+            self.pending_seek = Some(start);
+        }
+    }
+
+    /// 現在の再生位置に A-B リピートの次の印を付ける
+    fn mark_ab_loop(&mut self) {
+        if !self.seekable {
+            self.show_osd(tr(Msg::SeekUnavailable).to_string());
+            return;
+        }
+
+        self.ab_marks.mark(self.position);
+        let message = match self.ab_marks {
+            AbMarks::Off => tr(Msg::AbLoopCleared).to_string(),
+            AbMarks::Start(start) => tr_args(Msg::AbLoopStart, &[&format_time(start)]),
+            AbMarks::Looping(ab_loop) => tr_args(
+                Msg::AbLoop,
+                &[&format_time(ab_loop.start), &format_time(ab_loop.end)],
+            ),
+        };
+        println!("A-B loop: {:?}", self.ab_marks);
+        self.show_osd(message);
+    }
+
+    /// 前後のチャプターへの移動を予約する（実際の移動は再生ループで行う）
+    fn jump_to_chapter(&mut self, forward: bool) {
+        if self.seekable
+            && self.chapters.is_empty()
+            && let Some((url, network)) = self.chapter_source.take()
+        {
+            self.show_osd(tr(Msg::LoadingChapters).to_string());
+            let command_tx = self.command_tx.clone();
+            let generation = self.media_generation.load(Ordering::Relaxed);
+            tokio::spawn(async move {
+                let chapters = match fetch_youtube_chapters(&url, &network).await {
+                    Ok(chapters) => {
+                        println!("Loaded {} chapters", chapters.len());
+                        chapters
+                    }
+                    Err(e) => {
+                        eprintln!("Warning: Failed to load chapters: {}", e);
+                        Chapters::default()
+                    }
+                };
+                let _ = command_tx.send(PlayerCommand::ChaptersLoaded {
+                    chapters,
+                    generation,
+                    forward,
+                });
+            });
+            return;
+        }
+        if !self.seekable || self.chapters.is_empty() {
+            self.show_osd(tr(Msg::NoChapters).to_string());
+            return;
+        }
+
+        let target = if forward {
+            self.chapters.next(self.position)
+        } else {
+            self.chapters.previous(self.position)
+        };
+        match target {
+            Some((index, chapter)) => {
+                println!("Jumping to chapter {}: {}", index + 1, chapter.title);
+                self.show_osd(tr_args(
+                    Msg::Chapter,
+                    &[&(index + 1), &self.chapters.len(), &chapter.title],
+                ));
+                self.pending_seek = Some(chapter.start);
+            }
+            None => self.show_osd(tr(Msg::LastChapter).to_string()),
+        }
+    }
+
+    /// 字幕の表示を切り替える
+    fn toggle_subtitles(&mut self) {
+        if self.subtitles.is_none() {
+            self.show_osd(tr(Msg::NoSubtitles).to_string());
+            return;
+        }
+        self.subtitles_hidden = !self.subtitles_hidden;
+        self.update_subtitle();
+        let message = if self.subtitles_hidden {
+            Msg::SubtitlesOff
+        } else {
+            Msg::SubtitlesOn
+        };
+        self.show_osd(tr(message).to_string());
+    }
+
+    /// QR コードの表示を切り替える
+    fn toggle_qr_code(&mut self) {
+        if self.qr_visible {
+            self.qr_visible = false;
+            let _ = self.update_tx.send(TerminalUpdate::QrCode(None));
+            return;
+        }
+
+        let Some(url) = &self.source_url else {
+            self.show_osd(tr(Msg::NoUrlToShare).to_string());
+            return;
+        };
+        match qr::render_qr(url) {
+            Ok(lines) => {
+                self.qr_visible = true;
+                let _ = self.update_tx.send(TerminalUpdate::QrCode(Some(lines)));
+            }
+            Err(e) => self.show_osd(tr_args(Msg::QrCodeUnavailable, &[&e])),
+        }
+    }
+
+    /// ブックマークを保存するときのメディアのキー
+    ///
+    /// ダウンロードして再生する場合も同じ URL なら同じブックマークを使う
+    fn bookmark_key(&self) -> &str {
+        self.source_url.as_deref().unwrap_or(&self.media_file.path)
+    }
+
+    /// 現在の再生位置をブックマークに記録する
+    fn set_bookmark(&mut self, slot: u8) {
+        if !self.seekable {
+            self.show_osd(tr(Msg::BookmarksUnavailable).to_string());
+            return;
+        }
+
+        let position = self.position;
+        let key = self.bookmark_key().to_string();
+        match self.playback_state.set_bookmark(&key, slot, position) {
+            Ok(()) => {
+                println!("Bookmark {} set at {:?}", slot, position);
+                self.show_osd(tr_args(Msg::BookmarkSet, &[&slot, &format_time(position)]));
+            }
+            Err(e) => {
+                eprintln!("Warning: Failed to save bookmark: {}", e);
+                self.show_osd(tr_args(Msg::BookmarkNotSaved, &[&slot]));
+            }
+        }
+    }
+
+    /// ブックマークの位置への移動を予約する（実際の移動は再生ループで行う）
+    fn jump_to_bookmark(&mut self, slot: u8) {
+        if !self.seekable {
+            self.show_osd(tr(Msg::BookmarksUnavailable).to_string());
+            return;
+        }
+
+        match self.playback_state.bookmark(self.bookmark_key(), slot) {
+            Some(position) => {
+                println!("Jumping to bookmark {} at {:?}", slot, position);
+                self.show_osd(tr_args(Msg::BookmarkJump, &[&slot, &format_time(position)]));
+                self.pending_seek = Some(position);
+            }
+            None => self.show_osd(tr_args(Msg::BookmarkNotSet, &[&slot])),
+        }
+    }
+
+    /// 現在の位置から前後への移動を予約する（実際の移動は再生ループで行う）
+    ///
+    /// 移動が済む前に続けて押された場合は、予約済みの移動先から数える
+    fn seek_by(&mut self, forward: bool, amount: Duration) {
+        let current = self.pending_seek.unwrap_or(self.position);
+        self.seek_to(if forward {
+            current + amount
+        } else {
+            current.saturating_sub(amount)
+        });
+    }
+
+    /// 指定した位置への移動を予約する（実際の移動は再生ループで行う）
+    fn seek_to(&mut self, mut target: Duration) {
+        if !self.seekable {
+            self.show_osd(tr(Msg::SeekUnavailable).to_string());
+            return;
+        }
+
+        // 終端を越えないよう、最後の少し手前で止める
+        if let Some(duration) = self.media_file.info.duration {
+            target = target.min(duration.saturating_sub(SEEK_END_MARGIN));
+        }
+        println!("Seeking to {:?}", target);
+        self.show_osd(tr_args(Msg::SeekTo, &[&format_time(target)]));
+        self.pending_seek = Some(target);
+    }
+
+    /// コマ送り・コマ戻しを予約する（実際の表示は再生ループで行う）
+    ///
+    /// 再生中なら先に一時停止する
+    async fn request_step(&mut self, step: FrameStep) -> Result<()> {
+        if self.state == PlayerState::Playing {
+            Box::pin(self.handle_command(PlayerCommand::Pause)).await?;
+        }
+        if self.state == PlayerState::Paused {
+            self.pending_step = Some(step);
+        }
+        Ok(())
+    }
+
+    /// 音声を指定した位置から再生し直す
+    fn seek_audio(&mut self, position: Duration) {
+        if let Some(audio_player) = &mut self.audio_player
+            && let Err(e) =
+                audio_player.seek(audio_path(&self.media_file, &self.audio_track), position)
+        {
+            eprintln!("Warning: Failed to seek audio: {}", e);
+        }
+    }
+
+    /// 次の音声トラックに切り替え、今の再生位置から続ける
+    fn cycle_audio_track(&mut self) {
+        let tracks: Vec<StreamInfo> = self.media_file.audio_tracks().cloned().collect();
+        if tracks.len() < 2 || self.audio_player.is_none() {
+            self.show_osd(tr(Msg::NoOtherAudioTracks).to_string());
+            return;
+        }
+        // 取り出し中なら、そのトラックの次に進める
+        let current = self
+            .extracting_audio_track
+            .or(self.audio_track.as_ref().and(self.config.audio_track))
+            .and_then(|index| tracks.iter().position(|track| track.index == index))
+            .unwrap_or(0);
+        let next = (current + 1) % tracks.len();
+        self.start_audio_track(tracks[next].index);
+    }
+
+    /// `stream` 番の音声トラックを別スレッドで取り出し始める
+    ///
+    /// 最初のトラック以外はファイル全体を読むので、その間は今のトラックで再生を続け、
+    /// 取り出し終えたら `AudioTrackExtracted` で切り替える
+    fn start_audio_track(&mut self, stream: u32) {
+        let tracks: Vec<StreamInfo> = self.media_file.audio_tracks().cloned().collect();
+        let position = tracks.iter().position(|track| track.index == stream);
+        self.extracting_audio_track = Some(stream);
+        let generation = self.media_generation.load(Ordering::Relaxed);
+        // 最初のトラックは取り出さずにそのまま開ける
+        if position == Some(0) {
+            self.switch_audio_track(stream, Ok(None), generation);
+            return;
+        }
+        if let Some(position) = position {
+            self.show_osd(tr_args(
+                Msg::ExtractingAudioTrack,
+                &[&(position + 1), &tracks.len()],
+            ));
+        }
+        let path = self.media_file.path.clone();
+        let command_tx = self.command_tx.clone();
+        let spawned = std::thread::Builder::new()
+            .name("audio-track".to_string())
+            .spawn(move || {
+                let track = TrackFile::select(&path, TrackKind::Audio, Some(stream as usize))
+                    .map(|track| track.map(Arc::new))
+                    .map_err(|e| e.to_string());
+                let _ = command_tx.send(PlayerCommand::AudioTrackExtracted {
+                    stream,
+                    track,
+                    generation,
+                });
+            });
+        if let Err(e) = spawned {
+            self.extracting_audio_track = None;
+            self.show_osd(tr_args(Msg::AudioTrackFailed, &[&e]));
+        }
+    }
+
+    /// 取り出し終えた音声トラックに切り替え、今の再生位置から続ける
+    fn switch_audio_track(
+        &mut self,
+        stream: u32,
+        track: std::result::Result<Option<Arc<TrackFile>>, String>,
+        generation: usize,
+    ) {
+        // 取り出し中にメディアを切り替えたか、別のトラックを選び直していれば捨てる
+        if generation != self.media_generation.load(Ordering::Relaxed)
+            || self.extracting_audio_track != Some(stream)
+        {
+            return;
+        }
+        self.extracting_audio_track = None;
+        let audio_track = match track {
+            Ok(audio_track) => audio_track,
+            Err(e) => {
+                eprintln!("Warning: {}", e);
+                self.show_osd(tr_args(Msg::AudioTrackFailed, &[&e]));
+                return;
+            }
+        };
+        // 前のトラックのファイルは、デコードスレッドが止まってから消す
+        let previous = std::mem::replace(&mut self.audio_track, audio_track);
+        self.config.audio_track = Some(stream);
+        self.seek_audio(self.position);
+        drop(previous);
+
+        let tracks: Vec<StreamInfo> = self.media_file.audio_tracks().cloned().collect();
+        let Some(position) = tracks.iter().position(|track| track.index == stream) else {
+            return;
+        };
+        let track = &tracks[position];
+        let name = track
+            .language
+            .as_deref()
+            .or(track.title.as_deref())
+            .unwrap_or(&track.codec);
+        println!("Audio track: #{} ({})", stream, name);
+        self.show_osd(tr_args(
+            Msg::AudioTrack,
+            &[&(position + 1), &tracks.len(), &name],
+        ));
+    }
+
+    /// `--crop` と被写体の追従で映像を切り抜く
+    fn crop_frame(&mut self, frame: VideoFrame) -> Result<VideoFrame> {
+        let frame = match self.config.crop {
+            Some(rect) => frame.crop(rect)?,
+            None => frame,
+        };
+        Ok(
+            match self
+                .roi_cropper
+                .as_mut()
+                .and_then(|cropper| cropper.crop_rect(&frame))
+            {
+                Some(rect) => frame.crop(rect)?,
+                None => frame,
+            },
+        )
+    }
+
+    /// 一時停止中にコマ送り・コマ戻しで選んだフレームを表示する
+    fn show_step_frame(&mut self, position: Duration, frame: &VideoFrame) -> Result<()> {
+        let rendered_frame = self.renderer.render_video_frame(frame)?;
+        self.frame_tx.send(rendered_frame)?;
+        self.position = position;
+        self.update_subtitle();
+        // 止まったままでも位置の表示をすぐに更新する
+        self.last_title_update = None;
+        self.last_status_update = None;
+        Ok(())
+    }
+
+    /// OSD にメッセージを表示する
+    fn show_osd(&self, message: String) {
+        let _ = self.update_tx.send(TerminalUpdate::Osd(message));
+    }
+
+    /// 再生中のメディアの URL を設定し、QR コードで共有できるようにする
+    pub fn set_source_url(&mut self, url: String) {
+        self.show_osd(tr(Msg::QrCodeHint).to_string());
+        self.source_url = Some(url);
+    }
+
+    /// 再生開始直後のフレームから文字マップを自動選択する
+    fn advise_charset(&mut self, frame: &VideoFrame) {
+        let Some(advisor) = self.charset_advisor.as_mut() else {
+            return;
+        };
+        let Some(choice) = advisor.observe(frame) else {
+            return;
+        };
+
+        self.charset_advisor = None;
+        self.renderer.set_char_map(choice.index);
+        let name = crate::char_maps::get_char_map_name(choice.index);
+        println!("Character map auto-selected: {} ({})", name, choice.reason);
+        self.show_osd(tr_args(Msg::CharsetAuto, &[&name, &choice.reason]));
+    }
+
+    /// ウィンドウタイトルに再生状況を表示する
+    ///
+    /// 数秒おきに更新し、再生状態が変わったときはすぐに反映する
+    fn update_window_title(&mut self, title: &str, elapsed: Duration, duration: Option<Duration>) {
+        self.events.emit_position(elapsed, duration);
+        self.update_status(elapsed, duration);
+        let state = self.state;
+        if let Some((updated_at, last_state)) = self.last_title_update
+            && updated_at.elapsed() < TITLE_UPDATE_INTERVAL
+            && last_state == state
+        {
+            return;
+        }
+
+        self.last_title_update = Some((Instant::now(), state));
+        let _ = self.update_tx.send(TerminalUpdate::Title(WindowTitle {
+            title: title.to_string(),
+            elapsed,
+            duration,
+            state,
+        }));
+    }
+
+    /// 音声のピーク波形を別スレッドで解析し、終わったらステータスバーへ送る
+    ///
+    /// 再生とは別にファイル全体をデコードするので、長さの分かるローカルのファイルだけを対象にする
+    fn spawn_waveform_scan(&self) {
+        let Some(duration) = self.media_file.info.duration.filter(|d| !d.is_zero()) else {
+            return;
+        };
+        if self.audio_player.is_none()
+            || self.media_file.is_live()
+            || codec::network::is_network_url(&self.media_file.path)
+        {
+            return;
+        }
+
+        let path = self.media_file.path.clone();
+        let update_tx = self.update_tx.clone();
+        let media_generation = self.media_generation.clone();
+        let generation = media_generation.load(Ordering::Relaxed);
+        std::thread::spawn(move || {
+            match codec::audio::peak_waveform(&path, duration, WAVEFORM_BUCKETS) {
+                // 解析中に別のメディアへ切り替えていたら送らない
+                Ok(_) if media_generation.load(Ordering::Relaxed) != generation => {}
+                Ok(peaks) => {
+                    let _ = update_tx.send(TerminalUpdate::Waveform(Some(peaks)));
+                }
+                Err(e) => eprintln!("Warning: Failed to scan the audio waveform: {}", e),
+            }
+        });
+    }
+
+    /// ステータスバーに再生状況を送る
+    ///
+    /// 0.5 秒おきに送り、再生状態が変わったときはすぐに送る。
+    /// 間の経過時間はターミナル側で進める
+    fn update_status(&mut self, elapsed: Duration, duration: Option<Duration>) {
+        let state = self.state;
+        if let Some((updated_at, last_state)) = self.last_status_update
+            && updated_at.elapsed() < STATUS_UPDATE_INTERVAL
+            && last_state == state
+        {
+            return;
+        }
+
+        self.last_status_update = Some((Instant::now(), state));
+        let _ = self.update_tx.send(TerminalUpdate::Status(PlaybackInfo {
+            elapsed,
+            duration,
+            state,
+            volume: self.audio_player.as_ref().map(AudioPlayer::volume),
+            muted: self
+                .audio_player
+                .as_ref()
+                .is_some_and(AudioPlayer::is_muted),
+            char_map: self
+                .renderer
+                .char_map()
+                .map(crate::char_maps::get_char_map_name),
+        }));
     }
 
     pub async fn run(&mut self) -> Result<()> {
         let terminal = Terminal::new(
             self.command_tx.clone(),
             self.frame_rx.clone(),
+            self.update_rx.clone(),
             self.config.grayscale,
-        )?;
-        self.terminal = Some(terminal);
+            self.config.add_newlines,
+        )?
+        .with_color_mode(self.config.color_mode)
+        .with_key_bindings(self.config.key_bindings.clone());
+        let terminal = match self.config.inline {
+            Some(height) => terminal.with_inline(height),
+            None => terminal,
+        };
+        let terminal = if self.config.pause_on_blur {
+            terminal.with_pause_on_blur()
+        } else {
+            terminal
+        };
+        let terminal = match self.config.widgets.clone() {
+            Some(widgets) => terminal.with_widgets(widgets),
+            None => terminal,
+        };
+        let terminal = match &self.config.pipe_frames {
+            Some(command) => terminal.with_frame_pipe(FramePipe::spawn(command)?),
+            None => terminal,
+        };
+        let terminal = match self.config.raw_video {
+            Some(config) => terminal.with_raw_video(RawVideoServer::listen(config)?),
+            None => terminal,
+        };
+        self.terminal = Some(match self.emitter.take() {
+            Some(emitter) => terminal.with_emitter(emitter),
+            None => terminal,
+        });
+        if let Some(preset) = &self.config.preset {
+            preset.spawn(self.command_tx.clone());
+        }
+
+        let mut result = self.play().await;
+        while result.is_ok()
+            && let Some(media_file) = self.pending_media.take().or_else(|| self.next_queued())
+        {
+            self.replace_media(media_file);
+            result = self.play().await;
+        }
+        if let Err(e) = &result {
+            self.events
+                .emit(PlayerEvent::Error(error_message::user_message(e)));
+        }
+        self.set_state(PlayerState::Stopped);
+        result
+    }
+
+    /// 今の再生を終えて `input` の再生に切り替える（ターミナルはそのまま使う）
+    ///
+    /// 開けなかった場合は今の再生を続ける。切り替えは `run()` の中で行う
+    pub fn load(&mut self, input: &str) -> Result<()> {
+        self.config.input_policy.check(input)?;
+        let media_file = MediaFile::open(input)?;
+        println!("Loading {}", input);
+        self.pending_media = Some(media_file);
+        self.stop_signal.store(true, Ordering::Relaxed);
+        Ok(())
+    }
 
-        match self.media_file.media_type {
-            MediaType::Video => self.play_video().await,
-            MediaType::Audio => self.play_audio().await,
-            MediaType::Image => self.display_image().await,
-            MediaType::Unknown => Err(anyhow::anyhow!("Unknown media type")),
+    /// 最後まで再生し終えたとき、あとで見るキューの先頭から開けたメディア
+    ///
+    /// 終了のキーで止めた場合はキューに触れない。開けない入力は飛ばす
+    fn next_queued(&mut self) -> Option<MediaFile> {
+        if self.stop_signal.load(Ordering::Relaxed) {
+            return None;
+        }
+        let queue = self.config.watch_queue.as_ref()?;
+        loop {
+            let input = match queue.pop() {
+                Ok(input) => input?,
+                Err(e) => {
+                    eprintln!("Warning: Failed to read the watch-later queue: {:#}", e);
+                    return None;
+                }
+            };
+            if let Err(e) = self.config.input_policy.check(&input) {
+                eprintln!("Warning: Skipping queued input: {:#}", e);
+                continue;
+            }
+            match MediaFile::open(&input) {
+                Ok(media_file) => {
+                    println!("Playing next in queue: {}", input);
+                    return Some(media_file);
+                }
+                Err(e) => eprintln!("Warning: Failed to open {}: {}", input, e),
+            }
         }
     }
 
+    /// 前のメディアの音声と状態を片付け、`media_file` を再生できるようにする
+    fn replace_media(&mut self, media_file: MediaFile) {
+        self.media_generation.fetch_add(1, Ordering::Relaxed);
+        // 前の音声出力を閉じてから新しく開く
+        self.audio_player = None;
+        check_crop(&mut self.config, &media_file);
+        // 開始位置は最初のメディアにだけ使う
+        self.config.start_offset = None;
+        // トラックの番号はファイルごとに違うので、選んだトラックは引き継がない
+        self.video_track = None;
+        self.config.video_track = None;
+        self.video_prefetch = prefetch_video(&media_file, None);
+        self.audio_track = None;
+        self.extracting_audio_track = None;
+        self.config.audio_track = None;
+        self.audio_player = open_audio(&media_file, &None, &self.config);
+        self.renderer
+            .set_luma(luma_matrix(self.config.color_matrix, &media_file));
+        self.media_file = media_file;
+
+        self.playlist = None;
+        self.source = None;
+        self.source_url = None;
+        self.station_name = None;
+        self.stream_titles = None;
+        self.subtitles = None;
+        self.chapters = Chapters::default();
+        self.chapter_source = None;
+        self.skip_segments = SkipSegments::default();
+        self.fallbacks.clear();
+        self.position = Duration::ZERO;
+        self.seekable = false;
+        self.pending_seek = None;
+        self.pending_step = None;
+        self.last_title_update = None;
+        self.last_status_update = None;
+        self.stop_signal.store(false, Ordering::Relaxed);
+
+        // 前のメディアについて重ねていた表示を消す
+        if self.shown_subtitle.take().is_some() {
+            let _ = self.update_tx.send(TerminalUpdate::Subtitle(None));
+        }
+        if self.qr_visible {
+            self.qr_visible = false;
+            let _ = self.update_tx.send(TerminalUpdate::QrCode(None));
+        }
+        let _ = self.update_tx.send(TerminalUpdate::Waveform(None));
+    }
+
+    async fn play(&mut self) -> Result<()> {
+        self.set_state(PlayerState::Buffering);
+        let result = if self.playlist.is_some() {
+            self.play_album().await
+        } else if let Some(source) = self.source.take() {
+            self.play_source(source).await
+        } else {
+            match self.media_file.media_type {
+                MediaType::Video => self.play_video().await,
+                MediaType::Audio => self.play_audio().await,
+                MediaType::Image => self.display_image().await,
+                MediaType::Unknown if self.config.screensaver.is_some() => {
+                    self.attract_mode().await
+                }
+                MediaType::Unknown => Err(anyhow::anyhow!("Unknown media type")),
+            }
+        };
+
+        // 終了のキーや別の入力への切り替えで抜けた場合は、最後まで再生していない
+        if result.is_ok() && !self.stop_signal.load(Ordering::Relaxed) {
+            self.set_state(PlayerState::Finished);
+        }
+        result
+    }
+
     async fn play_video(&mut self) -> Result<()> {
-        let fps = self.config.fps.or(self.media_file.info.fps).unwrap_or(30.0);
+        let fps = arg_values::frame_rate(self.config.fps, self.media_file.info.fps);
         let frame_duration = Duration::from_secs_f64(1.0 / fps);
 
-        // AsyncVideoDecoder: decode_one().await は spawn_blocking を使い、
+        // AsyncVideoDecoder: 専用スレッドで先読みし、decode_one().await は
         // エグゼキューターをブロックしない → terminal タスクが確実に動く
-        let mut decoder = AsyncVideoDecoder::open(&self.media_file.path).await?;
+        self.events.emit(PlayerEvent::Buffering(true));
+        let mut decoder = match self.video_prefetch.take() {
+            Some(prefetch) => {
+                let (decoder, video_track) = tokio::task::spawn_blocking(move || prefetch.join())
+                    .await
+                    .map_err(|e| anyhow::anyhow!("Video prefetch panicked: {}", e))?
+                    .map_err(|_| anyhow::anyhow!("Video prefetch thread panicked"))??;
+                self.video_track = video_track;
+                decoder
+            }
+            None => {
+                AsyncVideoDecoder::open(video_path(&self.media_file, &self.video_track)).await?
+            }
+        };
+        self.events.emit(PlayerEvent::Buffering(false));
 
         println!("Video decoder started. Press 'space' to play/pause, 'q' to quit.");
+        self.spawn_waveform_scan();
 
         if let Some(terminal) = self.terminal.take() {
             tokio::spawn(async move {
@@ -148,7 +1446,7 @@ impl Player {
             });
         }
 
-        self.state.store(true, Ordering::Relaxed);
+        self.set_state(PlayerState::Playing);
 
         // 音声と動画を同期開始
         let audio_started = if let Some(audio_player) = &mut self.audio_player {
@@ -167,9 +1465,40 @@ impl Player {
         };
 
         let mut frame_count = 0u64;
-        let playback_start_time = Instant::now();
+        let mut stats = SessionStats::default();
+        let session_start = Instant::now();
+        // 音声があれば音声の再生位置に合わせて進む
+        let mut clock = MasterClock::new();
         let mut pending_frame: Option<VideoFrame> = None;
+        let mut pending_decode_time = Duration::ZERO;
         let mut pts_offset: Option<Duration> = None;
+        // 移動した直後で、再生時計をまだ合わせていない場合の移動先
+        let mut seek_target: Option<Duration> = None;
+        // ループで先頭に戻ったとき、前の周の最後のフレームから切り替える
+        let transition = self.config.transition;
+        let mut last_frame: Option<VideoFrame> = None;
+        let mut transition_from: Option<VideoFrame> = None;
+        // 一時停止中のコマ戻しに使う、表示したフレーム
+        let mut history = FrameHistory::new(DEFAULT_HISTORY_BYTES);
+
+        // ライブ配信では古いフレームを捨てて最新の映像に追従する
+        let mut trimmer = self.media_file.is_live().then(|| {
+            let max_latency = self.config.max_latency.unwrap_or(DEFAULT_MAX_LATENCY);
+            println!("Live source: trimming latency above {:?}", max_latency);
+            LatencyTrimmer::new(max_latency, frame_duration)
+        });
+        let mut recovery = ErrorRecovery::for_path(&self.media_file.path);
+        let title = playlist::track_title(&self.media_file);
+        self.events.emit(PlayerEvent::TrackChanged {
+            title: title.clone(),
+            index: None,
+        });
+        self.position = Duration::ZERO;
+        self.seekable = !self.media_file.is_live();
+        self.ab_marks = AbMarks::from(self.config.ab_loop);
+        if self.seekable {
+            self.pending_seek = self.start_position();
+        }
 
         loop {
             if self.stop_signal.load(Ordering::Relaxed) {
@@ -181,25 +1510,101 @@ impl Player {
                 self.handle_command(command).await?;
             }
 
-            if self.state.load(Ordering::Relaxed) {
-                // pending_frame がなければ次のフレームをデコード（非ブロッキング）
+            if let Some(target) = self.pending_seek.take() {
+                let resume = self.state;
+                self.set_state(PlayerState::Seeking);
+                self.events.emit(PlayerEvent::Buffering(true));
+                let sought = decoder.seek(target).await;
+                self.events.emit(PlayerEvent::Buffering(false));
+                self.set_state(resume);
+                match sought {
+                    Ok(()) => {
+                        self.seek_audio(target);
+                        pending_frame = None;
+                        history.clear();
+                        seek_target = Some(target);
+                        self.position = target;
+                    }
+                    Err(e) => {
+                        eprintln!("Warning: Failed to seek video: {}", e);
+                        let message = error_message::osd_message(Msg::SeekFailed, &e.into());
+                        self.events.emit(PlayerEvent::Error(message.clone()));
+                        self.show_osd(message);
+                    }
+                }
+            }
+
+            if self.state.is_playing() {
+                clock.resume();
+                // pending_frame がなければ先読みしたフレームを取り出す（非ブロッキング）
                 if pending_frame.is_none() {
-                    match decoder.decode_one().await? {
+                    let decode_start = Instant::now();
+                    let decoded = match decoder.decode_one().await {
+                        Ok(decoded) => {
+                            recovery.on_success();
+                            decoded
+                        }
+                        // パケットロス等で壊れたフレームは読み飛ばし、次のキーフレームを待つ
+                        Err(e) if recovery.on_error(&e) => {
+                            stats.decode_errors_skipped += 1;
+                            continue;
+                        }
+                        Err(e) => {
+                            eprintln!("Video decoding failed: {}", e);
+                            let e = anyhow::Error::from(e);
+                            let reason = error_message::osd_message(Msg::DecodingFailed, &e);
+                            let Some(fallback) = self.fail_over(&reason).await else {
+                                return Err(e);
+                            };
+                            // 切り替え先のタイムスタンプから再生時計を合わせ直す
+                            decoder = fallback;
+                            stats.fallbacks += 1;
+                            recovery = ErrorRecovery::for_path(&self.media_file.path);
+                            pending_frame = None;
+                            pts_offset = None;
+                            seek_target = Some(self.position);
+                            continue;
+                        }
+                    };
+                    match decoded {
                         Some(frame) => {
-                            if pts_offset.is_none() {
+                            if let Some(target) = seek_target.take() {
+                                // 移動直後のフレームに再生時計を合わせる。開始位置を指定した
+                                // 場合は、移動先から逆算してタイムスタンプの基準を決める
+                                let offset = *pts_offset
+                                    .get_or_insert(frame.timestamp.saturating_sub(target));
+                                clock.set(frame.timestamp.saturating_sub(offset));
+                            } else if pts_offset.is_none() {
                                 pts_offset = Some(frame.timestamp);
                             }
+                            pending_decode_time = decode_start.elapsed();
+                            stats.decode.record(pending_decode_time);
                             pending_frame = Some(frame);
                         }
                         None => {
                             println!("Video stream finished");
 
+                            // B が終端より後ろでも A から繰り返す
+                            if self.seekable
+                                && let Some(ab_loop) = self.ab_marks.active()
+                            {
+                                self.pending_seek = Some(ab_loop.start);
+                                continue;
+                            }
                             if self.config.loop_playback {
                                 println!("Restarting video loop...");
-                                decoder = AsyncVideoDecoder::open(&self.media_file.path).await?;
+                                decoder = AsyncVideoDecoder::open(video_path(
+                                    &self.media_file,
+                                    &self.video_track,
+                                ))
+                                .await?;
                                 frame_count = 0;
                                 pending_frame = None;
+                                history.clear();
                                 pts_offset = None;
+                                // 先頭のフレームに再生時計を合わせ直す
+                                seek_target = Some(Duration::ZERO);
+                                transition_from = last_frame.take();
                                 println!("Video loop restarted");
                             } else {
                                 println!("Video finished, waiting for audio to complete...");
@@ -213,34 +1618,144 @@ impl Player {
                 if let Some(ref frame) = pending_frame {
                     let offset = pts_offset.unwrap_or(Duration::ZERO);
                     let frame_pts = frame.timestamp.saturating_sub(offset);
-                    let elapsed = playback_start_time.elapsed();
+                    // ライブ配信は遅延の調整に任せ、それ以外は音声の位置に時計を合わせる
+                    if trimmer.is_none()
+                        && let Some(position) =
+                            self.audio_player.as_ref().and_then(AudioPlayer::position)
+                    {
+                        clock.sync_to_audio(position);
+                    }
+                    let mut elapsed = clock.now();
+                    // 時計から遅れすぎたフレームはスキップして追いつく（既定は 2 フレーム）
+                    let mut max_lag = self.config.sync_threshold.unwrap_or(frame_duration * 2);
+
+                    if let Some(trimmer) = trimmer.as_mut() {
+                        match trimmer.decide(frame_pts, elapsed, pending_decode_time) {
+                            FrameAction::Render => max_lag = Duration::MAX,
+                            FrameAction::Drop => {
+                                pending_frame = None;
+                                frame_count += 1;
+                                continue;
+                            }
+                            FrameAction::Resync => {
+                                clock.set(frame_pts);
+                                elapsed = frame_pts;
+                                max_lag = Duration::MAX;
+                            }
+                        }
+                    }
 
                     if elapsed >= frame_pts {
                         let frame = pending_frame.take().unwrap();
                         let lag = elapsed.saturating_sub(frame_pts);
 
-                        // 2フレーム以上遅れている場合はスキップして音声に追いつく
-                        if lag <= frame_duration * 2 {
-                            let rendered_frame = self.renderer.render_video_frame(&frame)?;
+                        if lag <= max_lag {
+                            stats.lag.record(lag);
+                            let render_start = Instant::now();
+                            let mut frame = self.crop_frame(frame)?;
+                            if let Some(transition) = transition {
+                                last_frame = Some(frame.clone());
+                                if let Some(from) = &transition_from {
+                                    let progress = transition.progress(frame_pts);
+                                    if progress < 1.0 {
+                                        frame = transition.blend(from, &frame, progress);
+                                    } else {
+                                        transition_from = None;
+                                    }
+                                }
+                            }
+                            self.advise_charset(&frame);
+                            let mut rendered_frame = self.renderer.render_video_frame(&frame)?;
+                            if self.config.vu_meter
+                                && let Some(audio_player) = &self.audio_player
+                            {
+                                vu_meter::overlay(&mut rendered_frame, audio_player.levels());
+                            }
+                            stats.render.record(render_start.elapsed());
                             if self.frame_tx.send(rendered_frame).is_err() {
                                 println!("Frame receiver closed");
                                 break;
                             }
+                            stats.frames_rendered += 1;
+                            history.push(frame_pts, frame);
+                        } else {
+                            stats.frames_dropped_late += 1;
                         }
 
                         frame_count += 1;
+                        self.position = frame_pts;
+                        self.update_subtitle();
+                        self.skip_segment();
+                        self.repeat_ab_loop();
+                        self.update_window_title(
+                            &title,
+                            self.position,
+                            self.media_file.info.duration,
+                        );
                     } else {
                         let wait = frame_pts - elapsed;
                         time::sleep(wait.min(Duration::from_millis(5))).await;
                     }
                 }
             } else {
-                // 一時停止中
+                // 一時停止中は時計も止める
+                clock.pause();
+                if let Some(step) = self.pending_step.take() {
+                    let stepped = match step {
+                        FrameStep::Backward => history.step_backward().cloned(),
+                        FrameStep::Forward if !history.at_latest() => {
+                            history.step_forward().cloned()
+                        }
+                        // 最新のフレームから先は 1 フレームだけデコードする
+                        FrameStep::Forward => {
+                            let decoded = match pending_frame.take() {
+                                Some(frame) => Some(frame),
+                                None => decoder.decode_one().await.unwrap_or_else(|e| {
+                                    eprintln!("Warning: Failed to decode the next frame: {}", e);
+                                    None
+                                }),
+                            };
+                            match decoded {
+                                Some(frame) => {
+                                    let target = seek_target.take().unwrap_or(Duration::ZERO);
+                                    let offset = *pts_offset
+                                        .get_or_insert(frame.timestamp.saturating_sub(target));
+                                    let frame_pts = frame.timestamp.saturating_sub(offset);
+                                    let frame = self.crop_frame(frame)?;
+                                    history.push(frame_pts, frame.clone());
+                                    frame_count += 1;
+                                    Some((frame_pts, frame))
+                                }
+                                None => None,
+                            }
+                        }
+                    };
+                    if let Some((position, frame)) = stepped {
+                        clock.set(position);
+                        self.show_step_frame(position, &frame)?;
+                    }
+                }
+                self.update_window_title(&title, self.position, self.media_file.info.duration);
                 time::sleep(Duration::from_millis(16)).await;
             }
         }
 
-        if audio_started && !self.config.loop_playback {
+        if let Some(trimmer) = &trimmer {
+            println!(
+                "Live source: dropped {} stale frames",
+                trimmer.dropped_frames()
+            );
+            stats.frames_dropped_live = trimmer.dropped_frames();
+        }
+        stats.playback_time = session_start.elapsed();
+        println!("{}", stats.summary());
+        if let Some(path) = &self.config.stats_json
+            && let Err(e) = stats.write_json(path, &self.media_file.path)
+        {
+            eprintln!("Warning: Failed to write playback statistics: {:#}", e);
+        }
+
+        if audio_started && !self.config.loop_playback && !self.media_file.is_live() {
             println!("Ensuring audio completion...");
             let audio_wait_start = Instant::now();
             const MAX_AUDIO_WAIT: Duration = Duration::from_secs(60);
@@ -280,7 +1795,7 @@ impl Player {
             }
         }
 
-        let total_playback_time = playback_start_time.elapsed().as_secs_f64();
+        let total_playback_time = clock.now().as_secs_f64();
         let expected_time = frame_count as f64 / fps;
         println!(
             "Video playback finished. Total frames: {}, playback: {:.1}s, expected: {:.1}s",
@@ -291,6 +1806,7 @@ impl Player {
 
     async fn play_audio(&mut self) -> Result<()> {
         println!("Starting audio-only playback");
+        self.spawn_waveform_scan();
 
         if let Some(audio_player) = &mut self.audio_player {
             if let Err(e) = audio_player.play() {
@@ -302,6 +1818,8 @@ impl Player {
             return Err(anyhow::anyhow!("No audio player available"));
         }
 
+        self.set_state(PlayerState::Playing);
+
         if let Some(terminal) = self.terminal.take() {
             let _terminal_handle = tokio::spawn(async move {
                 if let Err(e) = terminal.run().await {
@@ -311,6 +1829,42 @@ impl Player {
         }
 
         let playback_start = Instant::now();
+        let mut now_playing = NowPlaying {
+            title: playlist::track_title(&self.media_file),
+            subtitle: self
+                .station_name
+                .clone()
+                .or_else(|| self.media_file.info.tags.artist.clone()),
+            album: self.media_file.info.tags.album.clone(),
+            duration: self.media_file.info.duration,
+            ..Default::default()
+        };
+        let cover_art = match self.media_file.cover_art() {
+            Ok(cover_art) => cover_art,
+            Err(e) => {
+                eprintln!("Warning: Failed to decode cover art: {}", e);
+                None
+            }
+        };
+        // カバー画像はターミナルの大きさが変わったときだけ描き直す
+        let mut cover_size = None;
+        self.events.emit(PlayerEvent::TrackChanged {
+            title: now_playing.title.clone(),
+            index: None,
+        });
+        self.visualizer = self.config.visualizer.map(Visualizer::new);
+        let frame_interval = if self.visualizer.is_some() {
+            VISUALIZER_INTERVAL
+        } else {
+            NOW_PLAYING_INTERVAL
+        };
+        let mut last_tick = Instant::now();
+        self.position = Duration::ZERO;
+        self.seekable = !self.media_file.is_live();
+        self.ab_marks = AbMarks::from(self.config.ab_loop);
+        if self.seekable {
+            self.pending_seek = self.start_position();
+        }
 
         loop {
             if self.stop_signal.load(Ordering::Relaxed) {
@@ -322,8 +1876,36 @@ impl Player {
                 self.handle_command(command).await?;
             }
 
+            if let Some(target) = self.pending_seek.take() {
+                let resume = self.state;
+                self.set_state(PlayerState::Seeking);
+                self.seek_audio(target);
+                self.set_state(resume);
+                now_playing.elapsed = target;
+            }
+
+            // 音声をデコードできなくなったら、次の入力の同じ位置から続ける
+            if self
+                .audio_player
+                .as_ref()
+                .is_some_and(|audio_player| audio_player.failed_track().is_some())
+                && let Some(media_file) =
+                    self.fail_over_audio(tr(Msg::DecodingFailed), now_playing.elapsed)
+            {
+                self.media_file = media_file;
+                self.audio_track = None;
+                self.extracting_audio_track = None;
+            }
+
             if let Some(audio_player) = &self.audio_player {
-                if !audio_player.is_playing() {
+                if audio_player.is_finished() {
+                    // B が終端より後ろでも A から繰り返す
+                    if self.seekable
+                        && let Some(ab_loop) = self.ab_marks.active()
+                    {
+                        self.pending_seek = Some(ab_loop.start);
+                        continue;
+                    }
                     println!("Audio playback completed naturally");
                     break;
                 }
@@ -332,7 +1914,57 @@ impl Player {
                 break;
             }
 
-            time::sleep(Duration::from_millis(500)).await;
+            // ラジオの曲名が変わったら表示を更新
+            if let Some(titles) = &self.stream_titles {
+                while let Ok(title) = titles.try_recv() {
+                    self.events.emit(PlayerEvent::TrackChanged {
+                        title: title.clone(),
+                        index: None,
+                    });
+                    now_playing.title = title;
+                }
+            }
+
+            let now = Instant::now();
+            let tick = now - last_tick;
+            now_playing.state = self.state;
+            if now_playing.state.is_playing() {
+                now_playing.elapsed += tick;
+            }
+            last_tick = now;
+            self.position = now_playing.elapsed;
+            self.repeat_ab_loop();
+            now_playing.levels = self.audio_player.as_ref().map(AudioPlayer::levels);
+
+            self.update_window_title(
+                &now_playing.title,
+                now_playing.elapsed,
+                now_playing.duration,
+            );
+
+            let (width, height) = crossterm::terminal::size().unwrap_or((80, 24));
+            let frame = match (&mut self.visualizer, &self.audio_player) {
+                (Some(visualizer), Some(audio_player)) => {
+                    let audio = audio_player.recent_audio();
+                    let image = visualizer.frame(&audio, tick, width as u32, height as u32);
+                    self.renderer.render_video_frame(&image)?
+                }
+                _ => {
+                    if let Some(image) = &cover_art
+                        && cover_size != Some((width, height))
+                    {
+                        now_playing.cover = self.render_cover(image, width as u32, height as u32);
+                        cover_size = Some((width, height));
+                    }
+                    now_playing::render_now_playing(&now_playing, width as u32, height as u32)
+                }
+            };
+            if self.frame_tx.send(frame).is_err() {
+                println!("Frame receiver closed");
+                break;
+            }
+
+            time::sleep(frame_interval).await;
         }
 
         if let Some(audio_player) = &mut self.audio_player {
@@ -343,27 +1975,70 @@ impl Player {
             }
         }
 
+        self.visualizer = None;
         let total_time = playback_start.elapsed().as_secs_f64();
         println!("Audio playback finished. Total time: {:.1}s", total_time);
         Ok(())
     }
 
-    async fn display_image(&mut self) -> Result<()> {
-        let image = image::open(&self.media_file.path)?;
-        let rendered_frame = self.renderer.render_image(&image)?;
+    /// カバー画像を「再生中」画面の大きさに合わせて `▀` で描く
+    ///
+    /// 文字マップや Sixel の設定に関係なく、画像がはっきり見えるハーフブロックで描く
+    fn render_cover(&self, image: &DynamicImage, width: u32, height: u32) -> Option<RenderedFrame> {
+        let (columns, rows) =
+            now_playing::cover_size((image.width(), image.height()), width, height)?;
+        let mut renderer = AsciiRenderer::new(RenderConfig {
+            target_width: columns,
+            target_height: rows,
+            grayscale: self.config.grayscale,
+            color_filter: self.config.color_filter,
+            render_mode: RenderMode::HalfBlock,
+            color_mode: self.config.color_mode,
+            transfer: self.config.calibration.transfer_curve(),
+            ..Default::default()
+        });
+        match renderer.render_image(image) {
+            Ok(frame) => Some(frame),
+            Err(e) => {
+                eprintln!("Warning: Failed to render cover art: {}", e);
+                None
+            }
+        }
+    }
+
+    /// 再生リストをギャップレスで連続再生し、アルバム表示を更新する
+    async fn play_album(&mut self) -> Result<()> {
+        let Some(mut playlist) = self.playlist.take() else {
+            return Err(anyhow::anyhow!("No playlist loaded"));
+        };
+        let Some(audio_player) = self.audio_player.as_mut() else {
+            return Err(anyhow::anyhow!("No audio player available"));
+        };
+
+        if let Err(e) = audio_player.play() {
+            eprintln!("Warning: Failed to start audio: {}", e);
+            return Err(anyhow::anyhow!("Failed to start audio playback"));
+        }
 
         if let Some(terminal) = self.terminal.take() {
-            let _terminal_handle = tokio::spawn(async move {
+            tokio::spawn(async move {
                 if let Err(e) = terminal.run().await {
                     eprintln!("Terminal error: {}", e);
                 }
             });
         }
 
-        self.frame_tx.send(rendered_frame)?;
+        self.set_state(PlayerState::Playing);
+
+        // キュー内の位置 → 再生リスト上のインデックス
+        let mut queued_indices = vec![playlist.current_index()];
+        let mut displayed_track = usize::MAX;
+        let mut track_elapsed = Duration::ZERO;
+        let mut last_tick = Instant::now();
 
         loop {
             if self.stop_signal.load(Ordering::Relaxed) {
+                println!("Stop signal received");
                 break;
             }
 
@@ -371,7 +2046,311 @@ impl Player {
                 self.handle_command(command).await?;
             }
 
-            time::sleep(Duration::from_millis(100)).await;
+            let Some(queue_position) = self.audio_player.as_ref().map(AudioPlayer::current_track)
+            else {
+                break;
+            };
+            if queue_position != displayed_track {
+                displayed_track = queue_position;
+                track_elapsed = Duration::ZERO;
+                playlist.set_current(queued_indices[queue_position]);
+                let alternatives = playlist.alternatives(playlist.current_index());
+                self.set_fallbacks(alternatives.iter().cloned().collect());
+                self.events.emit(PlayerEvent::TrackChanged {
+                    title: playlist::track_title(playlist.current()),
+                    index: Some(playlist.current_index()),
+                });
+            }
+
+            // 再生中のトラックをデコードできなくなったら、そのトラックの代わりの入力の
+            // 同じ位置から続ける。キューを積み直すので、次のトラックも先読みし直す
+            let failed_track = self
+                .audio_player
+                .as_ref()
+                .and_then(AudioPlayer::failed_track);
+            if failed_track == Some(queue_position)
+                && self
+                    .fail_over_audio(tr(Msg::DecodingFailed), track_elapsed)
+                    .is_some()
+            {
+                queued_indices = vec![playlist.current_index()];
+                displayed_track = 0;
+            }
+
+            let Some(audio_player) = self.audio_player.as_mut() else {
+                break;
+            };
+
+            let last = *queued_indices.last().unwrap_or(&0);
+            let next = if last + 1 < playlist.len() {
+                Some(last + 1)
+            } else if self.config.loop_playback {
+                Some(0)
+            } else {
+                None
+            };
+
+            // タイトルカードを挟む場合は先読みせず、曲が終わってからカードを表示する
+            let card_duration = self.config.title_card;
+            if audio_player.is_finished() && (next.is_none() || card_duration.is_none()) {
+                println!("Album playback completed");
+                break;
+            }
+            let ready_to_queue = if card_duration.is_some() {
+                audio_player.is_finished()
+            } else {
+                audio_player.pending_tracks() == 0
+            };
+
+            // 次のトラックを 1 つ先読みしておき、曲間の無音をなくす
+            if ready_to_queue && let Some(next) = next {
+                if let Some(duration) = card_duration {
+                    let card = self.title_card(&playlist, next, duration);
+                    if !self.show_title_card(card).await? {
+                        break;
+                    }
+                }
+
+                let path = playlist.entries()[next].path.clone();
+                let Some(audio_player) = self.audio_player.as_mut() else {
+                    break;
+                };
+                match audio_player.enqueue(&path) {
+                    Ok(_) => queued_indices.push(next),
+                    Err(e) => {
+                        eprintln!("Warning: Failed to queue {}: {}", path, e);
+                        // キューが空のままだと同じカードを表示し続けるため終了する
+                        if card_duration.is_some() {
+                            break;
+                        }
+                    }
+                }
+            }
+            let now = Instant::now();
+            if self.state.is_playing() {
+                track_elapsed += now - last_tick;
+            }
+            last_tick = now;
+
+            let current = playlist.current();
+            let (title, duration) = (playlist::track_title(current), current.info.duration);
+            self.update_window_title(&title, track_elapsed, duration);
+
+            let (width, height) = crossterm::terminal::size().unwrap_or((80, 24));
+            let frame = album_view::render_album_view(
+                &playlist,
+                track_elapsed,
+                width as u32,
+                height as u32,
+            );
+            if self.frame_tx.send(frame).is_err() {
+                println!("Frame receiver closed");
+                break;
+            }
+
+            time::sleep(Duration::from_millis(250)).await;
+        }
+
+        if let Some(audio_player) = &mut self.audio_player {
+            if let Err(e) = audio_player.stop() {
+                eprintln!("Warning: Failed to stop audio: {}", e);
+            } else {
+                println!("Audio stopped successfully");
+            }
+        }
+
+        self.playlist = Some(playlist);
+
+        // 再生リストを最後まで再生したら、終了されるまでスクリーンセーバーを表示
+        if self.config.screensaver.is_some() && !self.stop_signal.load(Ordering::Relaxed) {
+            return self.attract_mode().await;
+        }
+        Ok(())
+    }
+
+    /// スクリーンセーバー（アトラクトモード）を終了されるまで表示する
+    /// 再生リストの `index` 番目の曲のタイトルカード
+    fn title_card(&self, playlist: &Playlist, index: usize, duration: Duration) -> TitleCard {
+        let entry = &playlist.entries()[index];
+        let mut details: Vec<String> = entry.info.tags.artist.iter().cloned().collect();
+        details.push(format!("{}/{}", index + 1, playlist.len()));
+        TitleCard::new(
+            &playlist::track_title(entry),
+            details,
+            duration,
+            self.config.title_card_countdown,
+        )
+    }
+
+    /// タイトルカードを表示時間が過ぎるまで表示する。途中で終了された場合は false
+    async fn show_title_card(&mut self, mut card: TitleCard) -> Result<bool> {
+        let frame_interval = Duration::from_secs_f64(1.0 / card.frame_rate().max(1.0));
+        let mut elapsed = Duration::ZERO;
+        let mut last_tick = Instant::now();
+
+        while elapsed < card.duration() {
+            if self.stop_signal.load(Ordering::Relaxed) {
+                return Ok(false);
+            }
+            while let Ok(command) = self.command_rx.try_recv() {
+                self.handle_command(command).await?;
+            }
+
+            // 一時停止中はカウントダウンも止める
+            let now = Instant::now();
+            if self.state.is_playing() {
+                elapsed += now - last_tick;
+            }
+            last_tick = now;
+
+            let (width, height) = crossterm::terminal::size().unwrap_or((80, 24));
+            let frame = card.video_frame(elapsed, width as u32, height as u32)?;
+            let rendered_frame = self.renderer.render_video_frame(&frame)?;
+            if self.frame_tx.send(rendered_frame).is_err() {
+                return Ok(false);
+            }
+
+            time::sleep(frame_interval.min(Duration::from_millis(50))).await;
+        }
+        Ok(!self.stop_signal.load(Ordering::Relaxed))
+    }
+
+    async fn attract_mode(&mut self) -> Result<()> {
+        let Some(kind) = self.config.screensaver else {
+            return Ok(());
+        };
+        self.play_source(kind.into_source()).await
+    }
+
+    /// 手続き的なメディアソースを終了されるまで再生する
+    async fn play_source(&mut self, mut source: Box<dyn MediaSource>) -> Result<()> {
+        println!("Procedural source: {}", source.name());
+
+        if self.config.enable_audio
+            && let Some(generator) = source.audio()
+        {
+            match AudioPlayer::from_generator(
+                generator,
+                self.config.audio_ramp,
+                &self.config.audio_output,
+            ) {
+                Ok(mut audio_player) => {
+                    audio_player.play()?;
+                    self.audio_player = Some(audio_player);
+                }
+                Err(e) => eprintln!("Warning: Audio initialization failed: {}", e),
+            }
+        }
+
+        if let Some(terminal) = self.terminal.take() {
+            tokio::spawn(async move {
+                if let Err(e) = terminal.run().await {
+                    eprintln!("Terminal error: {}", e);
+                }
+            });
+        }
+
+        self.set_state(PlayerState::Playing);
+        let frame_interval = Duration::from_secs_f64(1.0 / source.frame_rate().max(1.0));
+        let title = source.name().to_string();
+        self.events.emit(PlayerEvent::TrackChanged {
+            title: title.clone(),
+            index: None,
+        });
+        let mut now_playing = NowPlaying {
+            title: title.clone(),
+            ..Default::default()
+        };
+        let mut last_tick = Instant::now();
+        let mut next_frame_at = last_tick;
+
+        while !self.stop_signal.load(Ordering::Relaxed) {
+            while let Ok(command) = self.command_rx.try_recv() {
+                self.handle_command(command).await?;
+            }
+
+            // 一時停止中はアニメーションも止める
+            let now = Instant::now();
+            now_playing.state = self.state;
+            if now_playing.state.is_playing() {
+                now_playing.elapsed += now - last_tick;
+            }
+            last_tick = now;
+
+            // 低フレームレートのソースでもキー操作にはすぐ反応できるよう、短い間隔で回す
+            if now < next_frame_at {
+                time::sleep((next_frame_at - now).min(Duration::from_millis(50))).await;
+                continue;
+            }
+            next_frame_at = now + frame_interval;
+
+            let (width, height) = crossterm::terminal::size().unwrap_or((80, 24));
+            let rendered_frame = if source.has_video() {
+                let frame = source.video_frame(now_playing.elapsed, width as u32, height as u32)?;
+                self.renderer.render_video_frame(&frame)?
+            } else {
+                now_playing.levels = self.audio_player.as_ref().map(AudioPlayer::levels);
+                now_playing::render_now_playing(&now_playing, width as u32, height as u32)
+            };
+            if self.frame_tx.send(rendered_frame).is_err() {
+                println!("Frame receiver closed");
+                break;
+            }
+
+            self.update_window_title(&title, now_playing.elapsed, None);
+        }
+
+        if let Some(audio_player) = &mut self.audio_player
+            && let Err(e) = audio_player.stop()
+        {
+            eprintln!("Warning: Failed to stop audio: {}", e);
+        }
+
+        Ok(())
+    }
+
+    async fn display_image(&mut self) -> Result<()> {
+        // APNG・アニメーション WebP・複数ページの TIFF は動画と同じように再生する
+        if let Some(animation) = AnimatedImage::open(&self.media_file.path)? {
+            println!(
+                "Animated image: {} frames, {:?} per loop",
+                animation.frame_count(),
+                animation.loop_duration()
+            );
+            return self.play_source(Box::new(animation)).await;
+        }
+
+        let image = codec::format::decode_image(&self.media_file.path)?;
+        let rendered_frame = self.renderer.render_image(&image)?;
+        self.events.emit(PlayerEvent::TrackChanged {
+            title: playlist::track_title(&self.media_file),
+            index: None,
+        });
+
+        if let Some(terminal) = self.terminal.take() {
+            let _terminal_handle = tokio::spawn(async move {
+                if let Err(e) = terminal.run().await {
+                    eprintln!("Terminal error: {}", e);
+                }
+            });
+        }
+
+        self.frame_tx.send(rendered_frame)?;
+        self.set_state(PlayerState::Playing);
+
+        // 静止画は描き直す必要があるまで何もしないので、コマンドが届くまで眠る
+        while !self.stop_signal.load(Ordering::Relaxed) {
+            let command_rx = self.command_rx.clone();
+            let Ok(Ok(command)) = tokio::task::spawn_blocking(move || command_rx.recv()).await
+            else {
+                break;
+            };
+
+            let redraw = command.changes_rendering();
+            self.handle_command(command).await?;
+            if redraw {
+                self.frame_tx.send(self.renderer.render_image(&image)?)?;
+            }
         }
 
         Ok(())
@@ -381,7 +2360,12 @@ impl Player {
         match command {
             PlayerCommand::Play => {
                 println!("Play command received");
-                self.state.store(true, Ordering::Relaxed);
+                // 再開できるのは一時停止中だけ
+                if self.state != PlayerState::Paused {
+                    println!("Ignoring play while {}", self.state.name());
+                    return Ok(());
+                }
+                self.set_state(PlayerState::Playing);
                 if let Some(audio_player) = &mut self.audio_player {
                     if let Err(e) = audio_player.resume() {
                         eprintln!("Warning: Failed to resume audio: {}", e);
@@ -392,7 +2376,10 @@ impl Player {
             }
             PlayerCommand::Pause => {
                 println!("Pause command received");
-                self.state.store(false, Ordering::Relaxed);
+                if !self.set_state(PlayerState::Paused) {
+                    println!("Ignoring pause while {}", self.state.name());
+                    return Ok(());
+                }
                 if let Some(audio_player) = &mut self.audio_player {
                     if let Err(e) = audio_player.pause() {
                         eprintln!("Warning: Failed to pause audio: {}", e);
@@ -404,6 +2391,8 @@ impl Player {
             PlayerCommand::Stop => {
                 println!("Stop command received");
                 self.stop_signal.store(true, Ordering::Relaxed);
+                self.set_state(PlayerState::Stopped);
+                self.pending_media = None;
                 if let Some(audio_player) = &mut self.audio_player {
                     if let Err(e) = audio_player.stop() {
                         eprintln!("Warning: Failed to stop audio: {}", e);
@@ -412,14 +2401,15 @@ impl Player {
                     }
                 }
             }
-            PlayerCommand::TogglePlayPause => {
-                let current_state = self.state.load(Ordering::Relaxed);
-                if current_state {
+            PlayerCommand::TogglePlayPause => match self.state {
+                PlayerState::Playing => {
                     Box::pin(self.handle_command(PlayerCommand::Pause)).await?;
-                } else {
+                }
+                PlayerState::Paused => {
                     Box::pin(self.handle_command(PlayerCommand::Play)).await?;
                 }
-            }
+                state => println!("Ignoring play/pause while {}", state.name()),
+            },
             PlayerCommand::ToggleMute => {
                 if let Some(audio_player) = &mut self.audio_player {
                     if let Err(e) = audio_player.toggle_mute() {
@@ -433,17 +2423,90 @@ impl Player {
                 }
             }
             PlayerCommand::SetCharMap(index) => {
+                // 手動で選んだ文字マップは自動選択で上書きしない
+                self.charset_advisor = None;
                 self.renderer.set_char_map(index);
-                println!(
-                    "Character map changed to: {}",
-                    crate::char_maps::get_char_map_name(index)
-                );
+                let name = crate::char_maps::get_char_map_name(index);
+                println!("Character map changed to: {}", name);
+                self.show_osd(tr_args(Msg::Charset, &[&name]));
+            }
+            PlayerCommand::Resize(width, height) => {
+                let (width, height) = render_size(width, height, self.config.width_modifier);
+                self.renderer.set_target_size(width, height);
             }
             PlayerCommand::ToggleGrayscale => {
                 self.config.grayscale = !self.config.grayscale;
                 self.renderer.set_grayscale(self.config.grayscale);
                 println!("Grayscale mode: {}", self.config.grayscale);
             }
+            // 視覚化を表示している間は、色覚フィルターの代わりに視覚化を切り替える
+            PlayerCommand::CycleColorFilter if self.visualizer.is_some() => {
+                if let Some(visualizer) = &mut self.visualizer {
+                    let kind = visualizer.cycle();
+                    self.config.visualizer = Some(kind);
+                    println!("Visualization: {}", kind.name());
+                    self.show_osd(tr_args(Msg::Visualization, &[&kind.name()]));
+                }
+            }
+            PlayerCommand::CycleColorFilter => {
+                let filter = self.renderer.color_filter().next();
+                self.renderer.set_color_filter(filter);
+                self.config.color_filter = filter;
+                println!("Color filter: {}", filter.name());
+                self.show_osd(tr_args(Msg::ColorFilter, &[&filter.name()]));
+            }
+            PlayerCommand::CycleAudioTrack => self.cycle_audio_track(),
+            PlayerCommand::ToggleSubtitles => self.toggle_subtitles(),
+            PlayerCommand::ToggleQrCode => self.toggle_qr_code(),
+            PlayerCommand::SetBookmark(slot) => self.set_bookmark(slot),
+            PlayerCommand::JumpToBookmark(slot) => self.jump_to_bookmark(slot),
+            PlayerCommand::StepForward => self.request_step(FrameStep::Forward).await?,
+            PlayerCommand::StepBackward => self.request_step(FrameStep::Backward).await?,
+            PlayerCommand::MarkAbLoop => self.mark_ab_loop(),
+            PlayerCommand::NextChapter => self.jump_to_chapter(true),
+            PlayerCommand::ChaptersLoaded {
+                chapters,
+                generation,
+                forward,
+            } => {
+                if generation == self.media_generation.load(Ordering::Relaxed) {
+                    self.chapters = chapters;
+                    self.jump_to_chapter(forward);
+                }
+            }
+            PlayerCommand::AudioTrackExtracted {
+                stream,
+                track,
+                generation,
+            } => self.switch_audio_track(stream, track, generation),
+            PlayerCommand::PreviousChapter => self.jump_to_chapter(false),
+            PlayerCommand::Seek(target) => self.seek_to(target),
+            PlayerCommand::SeekForward(amount) => self.seek_by(true, amount),
+            PlayerCommand::SeekBackward(amount) => self.seek_by(false, amount),
+            PlayerCommand::AdjustVolume(delta) => {
+                if let Some(audio_player) = &mut self.audio_player {
+                    let volume = (audio_player.volume() + delta).clamp(0.0, 1.0);
+                    audio_player.set_volume(volume);
+                    self.show_osd(tr_args(Msg::Volume, &[&(volume * 100.0).round()]));
+                }
+            }
+            PlayerCommand::ApplyPreset(preset) => {
+                preset.apply_to(&mut self.config);
+                if let Some(index) = preset.char_map {
+                    self.charset_advisor = None;
+                    self.renderer.set_char_map(index);
+                }
+                self.renderer.set_grayscale(self.config.grayscale);
+                self.renderer.set_color_filter(self.config.color_filter);
+                println!("Preset reloaded");
+                self.show_osd(tr(Msg::PresetReloaded).to_string());
+            }
+            PlayerCommand::Load(input) => {
+                if let Err(e) = self.load(&input) {
+                    eprintln!("Warning: Failed to open {}: {}", input, e);
+                    self.show_osd(tr_args(Msg::OpenFailed, &[&input]));
+                }
+            }
         }
         Ok(())
     }
@@ -0,0 +1,171 @@
+//! プレイヤーの状態の変化を外部へ知らせるイベント
+//!
+//! IPC・MPRIS・Web リモコンなどはプレイヤーの内部を直接見ず、
+//! `Player::subscribe` で受け取ったチャンネルからこのイベントを読む
+
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+use anyhow::{Context, Result};
+use crossbeam_channel::{Receiver, Sender, unbounded};
+use serde_json::json;
+
+use crate::player_state::PlayerState;
+
+/// 再生位置のイベントを送る間隔
+const POSITION_INTERVAL: Duration = Duration::from_millis(250);
+
+/// プレイヤーのイベント
+#[derive(Debug, Clone, PartialEq)]
+pub enum PlayerEvent {
+    /// 再生位置（一定間隔で送る）
+    Position {
+        position: Duration,
+        duration: Option<Duration>,
+    },
+    /// 再生状態が変わった
+    StateChanged(PlayerState),
+    /// 再生する曲・動画が変わった（再生リストでは曲番号も）
+    TrackChanged { title: String, index: Option<usize> },
+    /// 入力を開いている・移動している間は true
+    Buffering(bool),
+    /// 再生中のエラー（切り替え先で続けられた場合も含む）
+    Error(String),
+}
+
+impl PlayerEvent {
+    /// JSON で表す（秒は小数）
+    pub fn to_json(&self) -> serde_json::Value {
+        match self {
+            PlayerEvent::Position { position, duration } => json!({
+                "event": "position",
+                "position": position.as_secs_f64(),
+                "duration": duration.map(|duration| duration.as_secs_f64()),
+            }),
+            PlayerEvent::StateChanged(state) => json!({
+                "event": "state_changed",
+                "state": state.name(),
+            }),
+            PlayerEvent::TrackChanged { title, index } => json!({
+                "event": "track_changed",
+                "title": title,
+                "index": index,
+            }),
+            PlayerEvent::Buffering(buffering) => json!({
+                "event": "buffering",
+                "buffering": buffering,
+            }),
+            PlayerEvent::Error(message) => json!({
+                "event": "error",
+                "message": message,
+            }),
+        }
+    }
+}
+
+/// イベントの購読者
+#[derive(Debug, Default)]
+pub struct EventHub {
+    subscribers: Vec<Sender<PlayerEvent>>,
+    last_position: Option<Instant>,
+}
+
+impl EventHub {
+    pub fn subscribe(&mut self) -> Receiver<PlayerEvent> {
+        let (tx, rx) = unbounded();
+        self.subscribers.push(tx);
+        rx
+    }
+
+    /// すべての購読者へ送る（受信側を閉じた購読者は外す）
+    pub fn emit(&mut self, event: PlayerEvent) {
+        self.subscribers.retain(|tx| tx.send(event.clone()).is_ok());
+    }
+
+    /// 再生位置を送る（前回から `POSITION_INTERVAL` 経っていなければ送らない）
+    pub fn emit_position(&mut self, position: Duration, duration: Option<Duration>) {
+        if self.subscribers.is_empty()
+            || self
+                .last_position
+                .is_some_and(|sent_at| sent_at.elapsed() < POSITION_INTERVAL)
+        {
+            return;
+        }
+        self.last_position = Some(Instant::now());
+        self.emit(PlayerEvent::Position { position, duration });
+    }
+}
+
+/// 受け取ったイベントを 1 行ずつ JSON でファイルへ書き出すスレッドを開始する
+pub fn spawn_log_writer(events: Receiver<PlayerEvent>, path: &Path) -> Result<()> {
+    let file =
+        File::create(path).with_context(|| format!("Failed to create {}", path.display()))?;
+    std::thread::spawn(move || {
+        let mut writer = BufWriter::new(file);
+        for event in events {
+            let written = writeln!(writer, "{}", event.to_json()).and_then(|_| writer.flush());
+            if let Err(e) = written {
+                eprintln!("Warning: Failed to write player event: {}", e);
+                break;
+            }
+        }
+    });
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_emit_to_all_subscribers() {
+        let mut hub = EventHub::default();
+        let first = hub.subscribe();
+        let second = hub.subscribe();
+        hub.emit(PlayerEvent::StateChanged(PlayerState::Paused));
+        assert_eq!(
+            first.try_recv(),
+            Ok(PlayerEvent::StateChanged(PlayerState::Paused))
+        );
+        assert_eq!(
+            second.try_recv(),
+            Ok(PlayerEvent::StateChanged(PlayerState::Paused))
+        );
+
+        drop(second);
+        hub.emit(PlayerEvent::Buffering(true));
+        assert_eq!(hub.subscribers.len(), 1);
+        assert_eq!(first.try_recv(), Ok(PlayerEvent::Buffering(true)));
+    }
+
+    #[test]
+    fn test_position_is_throttled() {
+        let mut hub = EventHub::default();
+        let events = hub.subscribe();
+        hub.emit_position(Duration::from_secs(1), None);
+        hub.emit_position(Duration::from_secs(2), None);
+        assert_eq!(events.try_iter().count(), 1);
+    }
+
+    #[test]
+    fn test_event_json() {
+        let event = PlayerEvent::Position {
+            position: Duration::from_millis(1500),
+            duration: Some(Duration::from_secs(60)),
+        };
+        assert_eq!(
+            event.to_json().to_string(),
+            r#"{"duration":60.0,"event":"position","position":1.5}"#
+        );
+        let event = PlayerEvent::TrackChanged {
+            title: "Intro".to_string(),
+            index: None,
+        };
+        assert_eq!(
+            event.to_json().to_string(),
+            r#"{"event":"track_changed","index":null,"title":"Intro"}"#
+        );
+    }
+}
@@ -0,0 +1,111 @@
+//! プレイヤーの再生状態と、状態を移してよいかどうかの規則
+
+/// プレイヤーの再生状態
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum PlayerState {
+    /// 再生していない（開始前、または停止のキーで止めた後）
+    #[default]
+    Stopped,
+    /// 入力を開いている・切り替えている
+    Buffering,
+    Playing,
+    Paused,
+    /// 再生位置を移動している
+    Seeking,
+    /// 最後まで再生し終えた
+    Finished,
+}
+
+impl PlayerState {
+    /// イベントの JSON などで使う名前
+    pub fn name(self) -> &'static str {
+        match self {
+            PlayerState::Stopped => "stopped",
+            PlayerState::Buffering => "buffering",
+            PlayerState::Playing => "playing",
+            PlayerState::Paused => "paused",
+            PlayerState::Seeking => "seeking",
+            PlayerState::Finished => "finished",
+        }
+    }
+
+    /// ステータスバーやウィンドウタイトルに表示する記号
+    pub fn icon(self) -> char {
+        match self {
+            PlayerState::Stopped => '■',
+            PlayerState::Buffering => '◌',
+            PlayerState::Playing => '▶',
+            PlayerState::Paused => '⏸',
+            PlayerState::Seeking => '»',
+            PlayerState::Finished => '✓',
+        }
+    }
+
+    /// 再生時間が進む状態かどうか
+    pub fn is_playing(self) -> bool {
+        self == PlayerState::Playing
+    }
+
+    /// 再生を続けるつもりの状態かどうか（読み込み・移動の間も含む）
+    pub fn is_active(self) -> bool {
+        matches!(
+            self,
+            PlayerState::Buffering | PlayerState::Playing | PlayerState::Seeking
+        )
+    }
+
+    /// `self` から `next` へ移ってよいか（同じ状態への遷移は認めない）
+    ///
+    /// 読み込みと移動は再生・一時停止のどちらからでも始まり、元の状態へ戻る。
+    /// 停止はいつでもでき、停止・再生終了の後は次の入力の読み込みか再生から始まる
+    pub fn can_transition_to(self, next: PlayerState) -> bool {
+        use PlayerState::*;
+        match (self, next) {
+            (current, next) if current == next => false,
+            (_, Stopped) => true,
+            (Stopped | Finished, Buffering | Playing) => true,
+            (Stopped | Finished, _) => false,
+            (Buffering, Playing | Paused | Finished) => true,
+            (Playing, Paused | Buffering | Seeking | Finished) => true,
+            (Paused, Playing | Buffering | Seeking | Finished) => true,
+            (Seeking, Playing | Paused) => true,
+            _ => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_transitions() {
+        use PlayerState::*;
+        assert!(Stopped.can_transition_to(Buffering));
+        assert!(Buffering.can_transition_to(Playing));
+        assert!(Playing.can_transition_to(Paused));
+        assert!(Paused.can_transition_to(Seeking));
+        assert!(Seeking.can_transition_to(Paused));
+        assert!(Playing.can_transition_to(Finished));
+        assert!(Finished.can_transition_to(Buffering));
+
+        // 一時停止は再生中だけ、再開は一時停止中だけ
+        assert!(!Playing.can_transition_to(Playing));
+        assert!(!Stopped.can_transition_to(Paused));
+        assert!(!Finished.can_transition_to(Paused));
+        assert!(!Seeking.can_transition_to(Buffering));
+        assert!(!Buffering.can_transition_to(Seeking));
+
+        for state in [Buffering, Playing, Paused, Seeking, Finished] {
+            assert!(state.can_transition_to(Stopped));
+        }
+    }
+
+    #[test]
+    fn test_is_active() {
+        assert!(PlayerState::Seeking.is_active());
+        assert!(!PlayerState::Seeking.is_playing());
+        assert!(!PlayerState::Paused.is_active());
+        assert!(!PlayerState::Finished.is_active());
+    }
+}
@@ -0,0 +1,315 @@
+//! 再生リスト
+//!
+//! ディレクトリ内の音声ファイルをタグ情報（ディスク番号・トラック番号）順に並べ、
+//! アルバムとして連続再生するためのトラック一覧を管理する。
+//!
+//! 再生リストのファイル（`.m3u`）も読める。1 行に 1 トラックで、`|` で区切って
+//! 同じトラックの代わりの入力（ミラーなど）を続けて書ける:
+//!
+//! ```text
+//! # コメント
+//! 01 - Intro.flac
+//! https://cdn1.example.com/02.mp3 | https://cdn2.example.com/02.mp3
+//! ```
+
+use std::path::Path;
+
+use anyhow::{Context, Result};
+use codec::{MediaFile, MediaType};
+
+use crate::input_policy::InputPolicy;
+
+/// アルバムとして扱う音声ファイルの拡張子
+const AUDIO_EXTENSIONS: &[&str] = &["mp3", "flac", "ogg", "opus", "m4a", "aac", "wav"];
+
+/// 再生リストのファイルの拡張子（`.m3u8` は HLS の再生リストなので含めない）
+const PLAYLIST_EXTENSION: &str = "m3u";
+
+/// 再生リストのファイルで、1 つのトラックの入力を区切る文字
+const SOURCE_SEPARATOR: char = '|';
+
+/// 再生リスト
+#[derive(Debug, Clone)]
+pub struct Playlist {
+    entries: Vec<MediaFile>,
+    /// トラックごとの、デコードできなくなったときに順に切り替える入力
+    alternatives: Vec<Vec<String>>,
+    current: usize,
+}
+
+impl Playlist {
+    /// トラックの一覧から再生リストを作成（タグ順に並べ替える）
+    pub fn new(mut entries: Vec<MediaFile>) -> Self {
+        entries.sort_by(|a, b| track_sort_key(a).cmp(&track_sort_key(b)));
+        Self {
+            alternatives: vec![Vec::new(); entries.len()],
+            entries,
+            current: 0,
+        }
+    }
+
+    /// 再生リストのファイルから、書かれた順の再生リストを作成
+    ///
+    /// 各行の入力を先頭から開き、最初に開けたものをトラックにする。残りの入力は
+    /// 再生中にデコードできなくなったときに使う。`policy` で禁止された入力は使わない
+    pub fn from_file<P: AsRef<Path>>(path: P, policy: &InputPolicy) -> Result<Self> {
+        let mut entries = Vec::new();
+        let mut alternatives = Vec::new();
+        for sources in read_playlist_file(path.as_ref())? {
+            let mut sources = sources
+                .into_iter()
+                .filter(|source| match policy.check(source) {
+                    Ok(()) => true,
+                    Err(e) => {
+                        eprintln!("Warning: Skipping {}", e);
+                        false
+                    }
+                });
+            let opened = sources
+                .by_ref()
+                .find_map(|source| match MediaFile::open(&source) {
+                    Ok(media_file) if media_file.info.has_audio => Some(media_file),
+                    Ok(_) => {
+                        eprintln!("Warning: Skipping {}: no audio stream", source);
+                        None
+                    }
+                    Err(e) => {
+                        eprintln!("Warning: Skipping {}: {}", source, e);
+                        None
+                    }
+                });
+            if let Some(media_file) = opened {
+                entries.push(media_file);
+                alternatives.push(sources.collect());
+            }
+        }
+
+        Ok(Self {
+            entries,
+            alternatives,
+            current: 0,
+        })
+    }
+
+    /// ディレクトリ内の音声ファイルから再生リストを作成（再生できるファイルがなければ空）
+    pub fn from_directory<P: AsRef<Path>>(dir: P) -> Result<Self> {
+        let mut paths: Vec<_> = std::fs::read_dir(dir.as_ref())?
+            .filter_map(|entry| entry.ok().map(|e| e.path()))
+            .filter(|path| path.is_file() && is_audio_extension(path))
+            .collect();
+        paths.sort();
+
+        let mut entries = Vec::with_capacity(paths.len());
+        for path in paths {
+            match MediaFile::open(&path) {
+                Ok(media_file) if media_file.media_type == MediaType::Audio => {
+                    entries.push(media_file)
+                }
+                Ok(_) => {}
+                Err(e) => eprintln!("Warning: Skipping {}: {}", path.display(), e),
+            }
+        }
+
+        Ok(Self::new(entries))
+    }
+
+    pub fn entries(&self) -> &[MediaFile] {
+        &self.entries
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn current_index(&self) -> usize {
+        self.current
+    }
+
+    pub fn current(&self) -> &MediaFile {
+        &self.entries[self.current]
+    }
+
+    /// `index` 番目のトラックをデコードできなくなったときに順に切り替える入力
+    pub fn alternatives(&self, index: usize) -> &[String] {
+        &self.alternatives[index]
+    }
+
+    /// 現在のトラックを変更する（範囲外の場合は末尾に丸める）
+    pub fn set_current(&mut self, index: usize) {
+        self.current = index.min(self.entries.len().saturating_sub(1));
+    }
+
+    /// アルバムアーティスト（なければ先頭トラックのアーティスト）
+    pub fn album_artist(&self) -> Option<&str> {
+        self.entries.iter().find_map(|entry| {
+            let tags = &entry.info.tags;
+            tags.album_artist.as_deref().or(tags.artist.as_deref())
+        })
+    }
+
+    /// アルバム名
+    pub fn album_title(&self) -> Option<&str> {
+        self.entries
+            .iter()
+            .find_map(|entry| entry.info.tags.album.as_deref())
+    }
+}
+
+/// トラックの表示名（タイトルタグ、なければファイル名）
+pub fn track_title(media_file: &MediaFile) -> String {
+    media_file.info.tags.title.clone().unwrap_or_else(|| {
+        Path::new(&media_file.path)
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().to_string())
+            .unwrap_or_else(|| media_file.path.clone())
+    })
+}
+
+/// 再生リストのファイルかどうか（拡張子で判断する）
+pub fn is_playlist_file(path: &Path) -> bool {
+    path.is_file()
+        && path
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .is_some_and(|ext| ext.eq_ignore_ascii_case(PLAYLIST_EXTENSION))
+}
+
+/// 再生リストのファイルを読み、トラックごとの入力の一覧を返す
+///
+/// 相対パスはファイルのあるディレクトリから解決する
+pub fn read_playlist_file(path: &Path) -> Result<Vec<Vec<String>>> {
+    let text = std::fs::read_to_string(path)
+        .with_context(|| format!("Failed to read {}", path.display()))?;
+    Ok(parse_playlist(
+        &text,
+        path.parent().unwrap_or_else(|| Path::new("")),
+    ))
+}
+
+/// 再生リストのテキストを読む（空行と `#` で始まる行は飛ばす）
+fn parse_playlist(text: &str, base_dir: &Path) -> Vec<Vec<String>> {
+    text.lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(|line| {
+            line.split(SOURCE_SEPARATOR)
+                .map(str::trim)
+                .filter(|source| !source.is_empty())
+                .map(|source| resolve_source(source, base_dir))
+                .collect()
+        })
+        .collect()
+}
+
+/// URL と絶対パスはそのまま、相対パスは `base_dir` からのパスにする
+fn resolve_source(source: &str, base_dir: &Path) -> String {
+    if source.contains("://") || Path::new(source).is_absolute() {
+        source.to_string()
+    } else {
+        base_dir.join(source).to_string_lossy().into_owned()
+    }
+}
+
+/// ディスク番号 → トラック番号 → パスの順で並べる。タグがないものは後ろへ
+fn track_sort_key(media_file: &MediaFile) -> (u32, u32, &str) {
+    let tags = &media_file.info.tags;
+    (
+        tags.disc_number.unwrap_or(1),
+        tags.track_number.unwrap_or(u32::MAX),
+        media_file.path.as_str(),
+    )
+}
+
+fn is_audio_extension(path: &Path) -> bool {
+    path.extension()
+        .and_then(|ext| ext.to_str())
+        .map(|ext| {
+            AUDIO_EXTENSIONS
+                .iter()
+                .any(|candidate| ext.eq_ignore_ascii_case(candidate))
+        })
+        .unwrap_or(false)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use codec::{MediaInfo, MediaTags};
+
+    fn track(path: &str, disc: Option<u32>, number: Option<u32>) -> MediaFile {
+        MediaFile {
+            path: path.to_string(),
+            media_type: MediaType::Audio,
+            info: MediaInfo {
+                has_audio: true,
+                tags: MediaTags {
+                    disc_number: disc,
+                    track_number: number,
+                    ..Default::default()
+                },
+                ..Default::default()
+            },
+        }
+    }
+
+    #[test]
+    fn test_playlist_sorted_by_tags() {
+        let playlist = Playlist::new(vec![
+            track("c.mp3", None, None),
+            track("b.mp3", Some(2), Some(1)),
+            track("a.mp3", Some(1), Some(2)),
+            track("z.mp3", Some(1), Some(1)),
+        ]);
+
+        let order: Vec<_> = playlist.entries().iter().map(|e| e.path.as_str()).collect();
+        assert_eq!(order, vec!["z.mp3", "a.mp3", "c.mp3", "b.mp3"]);
+    }
+
+    #[test]
+    fn test_track_title_falls_back_to_file_stem() {
+        let mut media_file = track("/music/01 - Intro.flac", None, Some(1));
+        assert_eq!(track_title(&media_file), "01 - Intro");
+
+        media_file.info.tags.title = Some("Intro".to_string());
+        assert_eq!(track_title(&media_file), "Intro");
+    }
+
+    #[test]
+    fn test_parse_playlist() {
+        let sources = parse_playlist(
+            "#EXTM3U\n\n01.flac\n\
+             https://a.example.com/02.mp3 | https://b.example.com/02.mp3 |\n\
+             /music/03.mp3|04.mp3\n",
+            Path::new("/albums/x"),
+        );
+        assert_eq!(
+            sources,
+            vec![
+                vec!["/albums/x/01.flac".to_string()],
+                vec![
+                    "https://a.example.com/02.mp3".to_string(),
+                    "https://b.example.com/02.mp3".to_string(),
+                ],
+                vec!["/music/03.mp3".to_string(), "/albums/x/04.mp3".to_string()],
+            ]
+        );
+    }
+
+    #[test]
+    fn test_new_playlist_has_no_alternatives() {
+        let playlist = Playlist::new(vec![track("a.mp3", None, Some(1))]);
+        assert!(playlist.alternatives(0).is_empty());
+    }
+
+    #[test]
+    fn test_audio_extension_filter() {
+        assert!(is_audio_extension(Path::new("song.MP3")));
+        assert!(is_audio_extension(Path::new("song.flac")));
+        assert!(!is_audio_extension(Path::new("cover.jpg")));
+        assert!(!is_audio_extension(Path::new("README")));
+    }
+}
@@ -0,0 +1,75 @@
+//! 再生中の URL を QR コードとして表示する
+//!
+//! 上半分・下半分のブロック文字で 1 文字に縦 2 モジュールを詰め、縦長の
+//! 文字セルでもほぼ正方形のモジュールになるようにする
+
+use anyhow::Result;
+use qrcode::{Color, EcLevel, QrCode};
+
+/// 周囲の余白（モジュール数）。規格上は 4 だが、端末では 2 でも読み取れる
+const QUIET_ZONE: usize = 2;
+
+/// テキストを QR コードの行に変換する
+///
+/// 各文字の前景色（暗）・背景色（明）で描く前提で、暗いモジュールを前景に割り当てる
+pub fn render_qr(text: &str) -> Result<Vec<String>> {
+    // 端末に収まりやすいよう、誤り訂正は最小のレベルにする
+    let code = QrCode::with_error_correction_level(text, EcLevel::L)
+        .map_err(|e| anyhow::anyhow!("Failed to encode QR code: {}", e))?;
+
+    let width = code.width();
+    let size = width + QUIET_ZONE * 2;
+    let is_dark = |x: usize, y: usize| -> bool {
+        let (Some(x), Some(y)) = (x.checked_sub(QUIET_ZONE), y.checked_sub(QUIET_ZONE)) else {
+            return false;
+        };
+        x < width && y < width && code[(x, y)] == Color::Dark
+    };
+
+    let lines = (0..size)
+        .step_by(2)
+        .map(|y| {
+            (0..size)
+                .map(|x| match (is_dark(x, y), is_dark(x, y + 1)) {
+                    (true, true) => '█',
+                    (true, false) => '▀',
+                    (false, true) => '▄',
+                    (false, false) => ' ',
+                })
+                .collect()
+        })
+        .collect();
+
+    Ok(lines)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_qr_size() {
+        let lines = render_qr("https://example.com/video.mp4").unwrap();
+        let size = lines[0].chars().count();
+
+        // バージョン 1 以上（21 モジュール以上）に余白を加えた正方形
+        assert!(size >= 21 + QUIET_ZONE * 2);
+        assert_eq!(lines.len(), size.div_ceil(2));
+        assert!(lines.iter().all(|line| line.chars().count() == size));
+    }
+
+    #[test]
+    fn test_render_qr_finder_pattern() {
+        let lines = render_qr("rtsp://192.168.1.20:554/stream1").unwrap();
+
+        // 余白の 1 行（2 モジュール）の次が左上のファインダーパターンの上端
+        assert!(lines[0].chars().all(|c| c == ' '));
+        let top: Vec<char> = lines[1].chars().collect();
+        assert_eq!(&top[..QUIET_ZONE], &[' ', ' ']);
+        assert!(
+            top[QUIET_ZONE..QUIET_ZONE + 7]
+                .iter()
+                .all(|&c| c == '█' || c == '▀')
+        );
+    }
+}
@@ -0,0 +1,372 @@
+//! 描画したフレームを rawvideo（RGB24）として TCP で配信する（`--rawvideo-listen`）
+//!
+//! 外部の ffmpeg から `-f rawvideo -pixel_format rgb24 -video_size WxH -i tcp://…` で
+//! 取り込めるようにし、画面キャプチャなしで ASCII アートを配信や録画に使えるようにする。
+//! セルは縦横 1:2 の箱に描き、出力の大きさに収まるよう中央に置く。
+//! rawvideo は一定のフレームレートを前提にするので、最後に表示したフレームを
+//! `--rawvideo-fps` の間隔で繰り返し送る。ffmpeg は何台つないでもよい
+
+use std::collections::HashMap;
+use std::io::{self, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use anyhow::{Context, Result};
+
+use crate::bitmap_font::{self, GLYPH_HEIGHT, GLYPH_WIDTH};
+use crate::char_maps::CHARS_FULL;
+use crate::renderer::{Cell, RenderedFrame, Rgb};
+
+/// 出力の大きさの既定値
+pub const DEFAULT_SIZE: (u32, u32) = (1280, 720);
+
+/// フレームレートの既定値
+pub const DEFAULT_FPS: f64 = 30.0;
+
+/// これより長く読まない ffmpeg は切り離す
+const WRITE_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// 点字の空白（U+2800）と、点 (x, y) のビット
+const BRAILLE_BLANK: u32 = 0x2800;
+const BRAILLE_BITS: [[u8; 2]; 4] = [[0x01, 0x08], [0x02, 0x10], [0x04, 0x20], [0x40, 0x80]];
+
+/// `--rawvideo-listen` の設定
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RawVideoConfig {
+    pub addr: SocketAddr,
+    pub size: (u32, u32),
+    pub fps: f64,
+}
+
+/// `9000` や `0.0.0.0:9000`（ポートだけならローカルからの接続だけを受け付ける）
+pub fn parse_listen_addr(text: &str) -> Result<SocketAddr, String> {
+    let text = text.trim();
+    if let Ok(port) = text.parse::<u16>() {
+        return Ok(SocketAddr::from(([127, 0, 0, 1], port)));
+    }
+    text.parse()
+        .map_err(|_| format!("'{}' is not a port or an address like 127.0.0.1:9000", text))
+}
+
+/// 受け付けた ffmpeg へフレームを送り続けるスレッド
+pub struct RawVideoServer {
+    latest: Arc<Mutex<Option<RenderedFrame>>>,
+    stop: Arc<AtomicBool>,
+    sender: Option<thread::JoinHandle<()>>,
+}
+
+impl RawVideoServer {
+    pub fn listen(config: RawVideoConfig) -> Result<Self> {
+        let listener = TcpListener::bind(config.addr)
+            .with_context(|| format!("Failed to listen for rawvideo on {}", config.addr))?;
+        listener.set_nonblocking(true)?;
+        let local_addr = listener.local_addr()?;
+        let (width, height) = config.size;
+        println!(
+            "Serving rawvideo on tcp://{}: ffmpeg -f rawvideo -pixel_format rgb24 -video_size {}x{} -framerate {} -i tcp://{} ...",
+            local_addr, width, height, config.fps, local_addr
+        );
+
+        let latest = Arc::new(Mutex::new(None));
+        let stop = Arc::new(AtomicBool::new(false));
+        let sender = {
+            let latest = Arc::clone(&latest);
+            let stop = Arc::clone(&stop);
+            thread::spawn(move || serve(listener, config, &latest, &stop))
+        };
+        Ok(Self {
+            latest,
+            stop,
+            sender: Some(sender),
+        })
+    }
+
+    /// 次に送るフレームを差し替える（Sixel 出力ではセルの内容を描く）
+    pub fn send(&self, frame: &RenderedFrame) {
+        if let Ok(mut latest) = self.latest.lock() {
+            *latest = Some(RenderedFrame {
+                sixel: None,
+                ..frame.clone()
+            });
+        }
+    }
+}
+
+impl Drop for RawVideoServer {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+        if let Some(sender) = self.sender.take() {
+            let _ = sender.join();
+        }
+    }
+}
+
+fn serve(
+    listener: TcpListener,
+    config: RawVideoConfig,
+    latest: &Mutex<Option<RenderedFrame>>,
+    stop: &AtomicBool,
+) {
+    let (width, height) = config.size;
+    let interval = Duration::from_secs_f64(1.0 / config.fps);
+    let mut clients: Vec<TcpStream> = Vec::new();
+    // 新しいフレームが来るまで同じ画像を送り直す（最初は黒）
+    let mut image = vec![0; image_len(width, height)];
+    let mut next_tick = Instant::now();
+
+    while !stop.load(Ordering::Relaxed) {
+        loop {
+            match listener.accept() {
+                Ok((stream, _)) => {
+                    let _ = stream.set_nonblocking(false);
+                    let _ = stream.set_nodelay(true);
+                    let _ = stream.set_write_timeout(Some(WRITE_TIMEOUT));
+                    clients.push(stream);
+                }
+                Err(e) if e.kind() == io::ErrorKind::WouldBlock => break,
+                Err(e) => {
+                    eprintln!("Warning: rawvideo accept failed: {}", e);
+                    break;
+                }
+            }
+        }
+
+        if let Some(frame) = latest.lock().ok().and_then(|mut latest| latest.take()) {
+            image = rasterize(&frame, width, height);
+        }
+        // 読むのをやめた ffmpeg は切り離す
+        clients.retain_mut(|client| client.write_all(&image).is_ok());
+
+        next_tick += interval;
+        let now = Instant::now();
+        if next_tick > now {
+            thread::sleep(next_tick - now);
+        } else {
+            // 遅れを取り戻そうとして連続で送らない
+            next_tick = now;
+        }
+    }
+}
+
+/// フレームを `width` x `height` の RGB24 画像に描く
+///
+/// セルは横 `scale`、縦 `scale * 2` 画素の箱に描く。出力に収まらなければ 1 画素ずつに
+/// して、はみ出した分は切り捨てる
+pub fn rasterize(frame: &RenderedFrame, width: u32, height: u32) -> Vec<u8> {
+    let mut image = vec![0; image_len(width, height)];
+    if frame.width == 0 || frame.height == 0 {
+        return image;
+    }
+    let scale = (width / frame.width)
+        .min(height / (frame.height * 2))
+        .max(1);
+    let (cell_width, cell_height) = (scale, scale * 2);
+    let left = width.saturating_sub(frame.width * cell_width) / 2;
+    let top = height.saturating_sub(frame.height * cell_height) / 2;
+
+    // 同じ文字の形は 1 回だけ求める
+    let mut masks: HashMap<char, Vec<u8>> = HashMap::new();
+    for (row, cells) in frame.rows().enumerate() {
+        for (column, cell) in cells.iter().enumerate() {
+            let mask = masks
+                .entry(cell.ch)
+                .or_insert_with(|| cell_mask(cell.ch, cell_width, cell_height));
+            let x0 = left + column as u32 * cell_width;
+            let y0 = top + row as u32 * cell_height;
+            for dy in 0..cell_height {
+                let y = y0 + dy;
+                if y >= height {
+                    break;
+                }
+                for dx in 0..cell_width {
+                    let x = x0 + dx;
+                    if x >= width {
+                        break;
+                    }
+                    let coverage = mask[(dy * cell_width + dx) as usize];
+                    let offset = (y as usize * width as usize + x as usize) * 3;
+                    image[offset..offset + 3].copy_from_slice(&blend(cell, coverage));
+                }
+            }
+        }
+    }
+    image
+}
+
+/// `width` x `height` の RGB24 の画像のバイト数（u32 では桁あふれする大きさも usize で数える）
+fn image_len(width: u32, height: u32) -> usize {
+    (width as usize)
+        .checked_mul(height as usize)
+        .and_then(|pixels| pixels.checked_mul(3))
+        .expect("rawvideo size overflows usize")
+}
+
+/// 背景色（なければ黒）に文字色を `coverage` / 255 だけ重ねる
+fn blend(cell: &Cell, coverage: u8) -> Rgb {
+    let background = cell.bg.unwrap_or_default();
+    let coverage = coverage as u32;
+    [0, 1, 2].map(|i| {
+        ((cell.fg[i] as u32 * coverage + background[i] as u32 * (255 - coverage)) / 255) as u8
+    })
+}
+
+/// `width` x `height` の箱に描いた文字の、画素ごとの文字色の割合（0〜255）
+fn cell_mask(ch: char, width: u32, height: u32) -> Vec<u8> {
+    let mut mask = Vec::with_capacity((width * height) as usize);
+    for y in 0..height {
+        for x in 0..width {
+            // 画素の中心の、箱の中の位置（0.0〜1.0）
+            let u = (x as f32 + 0.5) / width as f32;
+            let v = (y as f32 + 0.5) / height as f32;
+            mask.push((coverage(ch, u, v) * 255.0).round() as u8);
+        }
+    }
+    mask
+}
+
+/// 文字の (u, v) の位置の濃さ
+///
+/// ブロック要素と点字は形どおりに、ビットマップフォントにある文字はその形で描く。
+/// それ以外の文字は、文字マップでの明るさの順から求めた濃さで塗りつぶす
+fn coverage(ch: char, u: f32, v: f32) -> f32 {
+    let lit = |on: bool| if on { 1.0 } else { 0.0 };
+    match ch {
+        ' ' => 0.0,
+        '█' => 1.0,
+        '▀' => lit(v < 0.5),
+        '▄' => lit(v >= 0.5),
+        '▌' => lit(u < 0.5),
+        '▐' => lit(u >= 0.5),
+        '░' => 0.25,
+        '▒' => 0.5,
+        '▓' => 0.75,
+        // 下から 1/8〜7/8 の高さのブロック
+        '▁'..='▇' => {
+            let eighths = (ch as u32 - '▁' as u32 + 1) as f32;
+            lit(v >= 1.0 - eighths / 8.0)
+        }
+        '\u{2800}'..='\u{28ff}' => {
+            let bits = (ch as u32 - BRAILLE_BLANK) as u8;
+            let (column, row) = ((u * 2.0) as usize, (v * 4.0) as usize);
+            // 点は区画（正方形になる）の中央に丸く描く
+            let (du, dv) = (u * 2.0 - column as f32 - 0.5, v * 4.0 - row as f32 - 0.5);
+            lit(bits & BRAILLE_BITS[row.min(3)][column.min(1)] != 0 && du * du + dv * dv <= 0.12)
+        }
+        _ => match bitmap_font::glyph(ch) {
+            // 上下左右に 1 画素分ずつ空けて描く
+            Some(bits) => {
+                let gx = u * (GLYPH_WIDTH + 2) as f32 - 1.0;
+                let gy = v * (GLYPH_HEIGHT + 2) as f32 - 1.0;
+                if gx < 0.0 || gy < 0.0 || gx >= GLYPH_WIDTH as f32 || gy >= GLYPH_HEIGHT as f32 {
+                    return 0.0;
+                }
+                lit(bits[gy as usize] & (1 << (GLYPH_WIDTH - 1 - gx as u32)) != 0)
+            }
+            None => density(ch),
+        },
+    }
+}
+
+/// 明るさ順に並んだ文字マップでの位置から求めた濃さ（載っていない文字は半分）
+fn density(ch: char) -> f32 {
+    let count = CHARS_FULL.chars().count();
+    CHARS_FULL
+        .chars()
+        .position(|c| c == ch)
+        .map_or(0.5, |index| index as f32 / (count - 1) as f32)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Read;
+
+    use super::*;
+
+    fn frame(cells: Vec<Cell>, width: u32, height: u32) -> RenderedFrame {
+        RenderedFrame {
+            cells,
+            width,
+            height,
+            sixel: None,
+        }
+    }
+
+    fn pixel(image: &[u8], width: u32, x: u32, y: u32) -> [u8; 3] {
+        let offset = ((y * width + x) * 3) as usize;
+        [image[offset], image[offset + 1], image[offset + 2]]
+    }
+
+    #[test]
+    fn test_parse_listen_addr() {
+        assert_eq!(
+            parse_listen_addr("9000"),
+            Ok(SocketAddr::from(([127, 0, 0, 1], 9000)))
+        );
+        assert_eq!(
+            parse_listen_addr("0.0.0.0:9000"),
+            Ok(SocketAddr::from(([0, 0, 0, 0], 9000)))
+        );
+        assert!(parse_listen_addr("localhost").is_err());
+    }
+
+    #[test]
+    fn test_rasterize_blocks() {
+        let red = [255, 0, 0];
+        let cells = vec![
+            Cell::new('█', red),
+            Cell {
+                bg: Some([0, 0, 255]),
+                ..Cell::new('▀', red)
+            },
+        ];
+        // 1 セルは 4x8 画素、左右に 2 画素ずつ空く
+        let image = rasterize(&frame(cells, 2, 1), 12, 8);
+        assert_eq!(image.len(), 12 * 8 * 3);
+        assert_eq!(pixel(&image, 12, 0, 0), [0, 0, 0]);
+        assert_eq!(pixel(&image, 12, 2, 7), red);
+        assert_eq!(pixel(&image, 12, 6, 0), red);
+        assert_eq!(pixel(&image, 12, 6, 7), [0, 0, 255]);
+    }
+
+    #[test]
+    fn test_coverage() {
+        // '-' はビットマップフォントの中段だけ
+        assert_eq!(coverage('-', 0.5, 0.5), 1.0);
+        assert_eq!(coverage('-', 0.5, 0.1), 0.0);
+        // 点字の左上の点
+        assert_eq!(coverage('\u{2801}', 0.25, 0.125), 1.0);
+        assert_eq!(coverage('\u{2801}', 0.75, 0.125), 0.0);
+        assert_eq!(coverage('▁', 0.5, 0.95), 1.0);
+        assert_eq!(coverage('▁', 0.5, 0.5), 0.0);
+        assert!(density('$') > density('`'));
+    }
+
+    #[test]
+    fn test_server_sends_frames() {
+        // 空いているポートを探してから待ち受ける
+        let addr = TcpListener::bind(SocketAddr::from(([127, 0, 0, 1], 0)))
+            .and_then(|listener| listener.local_addr())
+            .unwrap();
+        let config = RawVideoConfig {
+            addr,
+            size: (4, 4),
+            fps: 50.0,
+        };
+        let server = RawVideoServer::listen(config).unwrap();
+        server.send(&frame(vec![Cell::new('█', [0, 255, 0])], 1, 1));
+
+        let mut client = TcpStream::connect(addr).unwrap();
+        client
+            .set_read_timeout(Some(Duration::from_secs(5)))
+            .unwrap();
+        let mut image = vec![0; 4 * 4 * 3];
+        client.read_exact(&mut image).unwrap();
+        // 1x1 のフレームは 2x4 の箱として中央に描く
+        assert_eq!(pixel(&image, 4, 1, 0), [0, 255, 0]);
+        assert_eq!(pixel(&image, 4, 0, 0), [0, 0, 0]);
+        drop(server);
+    }
+}
@@ -0,0 +1,162 @@
+//! 再生中に読み直す描画設定（プリセット）
+//!
+//! `--preset preset.toml` で指定したファイルを監視し、保存されるたびに
+//! キー操作と同じコマンドのチャンネルでプレイヤーへ送る。例:
+//!
+//! ```toml
+//! char_map = 3                 # 文字マップ（0〜9）
+//! grayscale = false
+//! color_filter = "high-contrast"
+//! vu_meter = true
+//! ```
+//!
+//! 書かれていない項目は今の設定のまま変えない
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+use anyhow::{Context, Result};
+use crossbeam_channel::Sender;
+use serde::Deserialize;
+
+use crate::char_maps::CHAR_MAPS;
+use crate::color_filter::ColorFilter;
+use crate::player::{PlayerCommand, PlayerConfig};
+
+/// ファイルの更新を確かめる間隔
+const POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+/// 描画設定
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RenderPreset {
+    pub char_map: Option<u8>,
+    pub grayscale: Option<bool>,
+    pub color_filter: Option<ColorFilter>,
+    pub vu_meter: Option<bool>,
+}
+
+impl RenderPreset {
+    /// 設定ファイルを読み込む
+    pub fn load(path: &Path) -> Result<Self> {
+        let text = fs::read_to_string(path)
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+        Self::parse(&text).with_context(|| format!("Invalid {}", path.display()))
+    }
+
+    /// TOML テキストを読み込む
+    pub fn parse(text: &str) -> Result<Self> {
+        let preset: Self = toml::from_str(text)?;
+        if let Some(index) = preset.char_map
+            && index as usize >= CHAR_MAPS.len()
+        {
+            anyhow::bail!("char_map must be between 0 and {}", CHAR_MAPS.len() - 1);
+        }
+        Ok(preset)
+    }
+
+    /// 書かれている項目をプレイヤーの設定に反映する
+    pub fn apply_to(&self, config: &mut PlayerConfig) {
+        if let Some(index) = self.char_map {
+            config.char_map_index = Some(index);
+        }
+        if let Some(grayscale) = self.grayscale {
+            config.grayscale = grayscale;
+        }
+        if let Some(filter) = self.color_filter {
+            config.color_filter = filter;
+        }
+        if let Some(vu_meter) = self.vu_meter {
+            config.vu_meter = vu_meter;
+        }
+    }
+}
+
+/// 監視中の設定ファイル
+#[derive(Debug, Clone)]
+pub struct PresetWatcher {
+    path: PathBuf,
+    preset: RenderPreset,
+    modified: Option<SystemTime>,
+}
+
+impl PresetWatcher {
+    /// 設定ファイルを読み込み、監視の起点にする
+    pub fn open(path: &Path) -> Result<Self> {
+        let modified = modified_time(path);
+        let preset = RenderPreset::load(path)?;
+        Ok(Self {
+            path: path.to_path_buf(),
+            preset,
+            modified,
+        })
+    }
+
+    /// 起動時に読み込んだ設定
+    pub fn preset(&self) -> &RenderPreset {
+        &self.preset
+    }
+
+    /// ファイルが保存されるたびに読み直し、`PlayerCommand::ApplyPreset` を送るタスクを開始する
+    pub fn spawn(&self, command_tx: Sender<PlayerCommand>) {
+        let path = self.path.clone();
+        let mut modified = self.modified;
+        tokio::spawn(async move {
+            loop {
+                tokio::time::sleep(POLL_INTERVAL).await;
+                let current = modified_time(&path);
+                // 保存の途中でファイルが一時的になくなることがある
+                if current.is_none() || current == modified {
+                    continue;
+                }
+                modified = current;
+
+                match RenderPreset::load(&path) {
+                    Ok(preset) => {
+                        if command_tx.send(PlayerCommand::ApplyPreset(preset)).is_err() {
+                            break;
+                        }
+                    }
+                    Err(e) => eprintln!("Warning: Keeping the current preset: {:#}", e),
+                }
+            }
+        });
+    }
+}
+
+fn modified_time(path: &Path) -> Option<SystemTime> {
+    fs::metadata(path).and_then(|meta| meta.modified()).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_preset() {
+        let preset = RenderPreset::parse("char_map = 3\ncolor_filter = \"high-contrast\"").unwrap();
+        assert_eq!(preset.char_map, Some(3));
+        assert_eq!(preset.color_filter, Some(ColorFilter::HighContrast));
+        assert_eq!(preset.grayscale, None);
+
+        assert!(RenderPreset::parse("char_map = 10").is_err());
+        assert!(RenderPreset::parse("color_filter = \"sepia\"").is_err());
+        assert!(RenderPreset::parse("brightness = 2").is_err());
+    }
+
+    #[test]
+    fn test_apply_keeps_missing_settings() {
+        let mut config = PlayerConfig {
+            char_map_index: Some(1),
+            grayscale: true,
+            ..Default::default()
+        };
+        RenderPreset::parse("char_map = 5\nvu_meter = true")
+            .unwrap()
+            .apply_to(&mut config);
+        assert_eq!(config.char_map_index, Some(5));
+        assert!(config.grayscale);
+        assert!(config.vu_meter);
+    }
+}
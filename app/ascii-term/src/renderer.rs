@@ -1,17 +1,112 @@
+use std::sync::Arc;
+
 use anyhow::Result;
+use clap::ValueEnum;
+use codec::color::Matrix;
 use fast_image_resize as fr;
 use image::{DynamicImage, ImageBuffer};
+use serde::{Deserialize, Serialize};
 
+use crate::ansi::ColorMode;
+use crate::calibration::TransferCurve;
 use crate::char_maps;
+use crate::color_filter::ColorFilter;
+use crate::sixel::SixelRenderer;
 use codec::video::VideoFrame;
 
+/// 描画結果をターミナルへどう出すか
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, ValueEnum)]
+pub enum RenderBackend {
+    /// 文字と ANSI エスケープシーケンス
+    #[default]
+    Ascii,
+    /// Sixel 画像（xterm・mlterm・foot など対応するターミナルのみ）
+    Sixel,
+}
+
+impl RenderBackend {
+    pub fn create(self, config: RenderConfig) -> Box<dyn Renderer> {
+        match self {
+            RenderBackend::Ascii => Box::new(AsciiRenderer::new(config)),
+            RenderBackend::Sixel => Box::new(SixelRenderer::new(config)),
+        }
+    }
+}
+
+/// 画像をターミナルに表示する `RenderedFrame` へ変換する
+pub trait Renderer: Send {
+    fn render_image(&mut self, image: &DynamicImage) -> Result<RenderedFrame>;
+
+    fn render_video_frame(&mut self, frame: &VideoFrame) -> Result<RenderedFrame> {
+        let dynamic_image = frame
+            .to_dynamic_image()
+            .map_err(|e| anyhow::anyhow!("Failed to convert frame to image: {}", e))?;
+
+        self.render_image(&dynamic_image)
+    }
+
+    /// 出力する文字数を変更する（ターミナルのリサイズ時）
+    fn set_target_size(&mut self, width: u32, height: u32);
+
+    /// 使用中の文字マップ（文字を使わない出力では None）
+    fn char_map(&self) -> Option<u8>;
+
+    /// 文字マップを変更する（文字を使わない出力では何もしない）
+    fn set_char_map(&mut self, index: u8);
+
+    fn set_grayscale(&mut self, grayscale: bool);
+
+    fn color_filter(&self) -> ColorFilter;
+
+    fn set_color_filter(&mut self, filter: ColorFilter);
+
+    /// 明るさを求めるときの重みの規格を変更する（メディアの切り替え時）
+    fn set_luma(&mut self, luma: Matrix);
+}
+
+/// 画素を文字に置き換える方法
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, ValueEnum)]
+pub enum RenderMode {
+    /// 1 文字 1 画素。明るさで文字マップの文字を選ぶ
+    #[default]
+    Ascii,
+    /// 1 文字 2x4 画素。点字（U+2800〜）の点ごとに明るさを二値化する
+    Braille,
+    /// 1 文字 1x2 画素。`▀` の文字色を上の画素、背景色を下の画素の色にする
+    HalfBlock,
+}
+
+/// 明るさを文字マップの段階（点字では点灯・消灯）に減らすときのディザリング
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, ValueEnum)]
+pub enum Dither {
+    /// 明るさの範囲で文字を選ぶだけ（点字は組織的ディザリング）
+    #[default]
+    None,
+    /// 4x4 の Bayer 行列でしきい値をずらす（点字は None と同じ）
+    Ordered,
+    /// 丸めた誤差を右と下の画素へ拡散する
+    FloydSteinberg,
+}
+
 #[derive(Debug, Clone)]
 pub struct RenderConfig {
     pub target_width: u32,
     pub target_height: u32,
     pub char_map_index: u8,
     pub grayscale: bool,
-    pub add_newlines: bool,
+    /// 文字色に適用する色覚フィルター
+    pub color_filter: ColorFilter,
+    pub render_mode: RenderMode,
+    /// 画像の出力で、元の画素数の整数倍（大きすぎれば整数分の 1）で表示する
+    pub pixel_perfect: bool,
+    /// 文字マップ・点字で明るさを段階に減らすときのディザリング
+    pub dither: Dither,
+    /// 出力できる色（256 色・16 色ではセルの色をパレットの色に減らす）
+    pub color_mode: ColorMode,
+    /// 文字や点を選ぶ明るさの重みの規格
+    pub luma: Matrix,
+    /// 出力する色に通すターミナルごとの補正（`[calibration]`。補正しなければ None）
+    pub transfer: Option<TransferCurve>,
 }
 
 impl Default for RenderConfig {
@@ -21,17 +116,82 @@ impl Default for RenderConfig {
             target_height: 24,
             char_map_index: 0,
             grayscale: false,
-            add_newlines: false,
+            color_filter: ColorFilter::None,
+            render_mode: RenderMode::Ascii,
+            pixel_perfect: false,
+            dither: Dither::None,
+            color_mode: ColorMode::Truecolor,
+            luma: Matrix::Bt709,
+            transfer: None,
         }
     }
 }
 
+pub use codec::color::Rgb;
+
+/// 点字 1 文字の点の数（横・縦）
+const BRAILLE_DOTS_X: u32 = 2;
+const BRAILLE_DOTS_Y: u32 = 4;
+
+/// 点字の空白（U+2800）。点 (x, y) は `BRAILLE_BITS[y][x]` のビットを立てる
+const BRAILLE_BLANK: u32 = 0x2800;
+const BRAILLE_BITS: [[u8; 2]; 4] = [[0x01, 0x08], [0x02, 0x10], [0x04, 0x20], [0x40, 0x80]];
+
+/// ハーフブロック 1 文字の縦の画素数
+const HALF_BLOCK_DOTS_Y: u32 = 2;
+
+/// グレースケールでハーフブロックの画素を点灯とみなす明るさ
+const HALF_BLOCK_THRESHOLD: u8 = 128;
+
+/// 組織的ディザリングのしきい値（4x4 の Bayer 行列）
+const BAYER_4X4: [[u8; 4]; 4] = [[0, 8, 2, 10], [12, 4, 14, 6], [3, 11, 1, 9], [15, 7, 13, 5]];
+
+/// 1 文字分のセル
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Cell {
+    pub ch: char,
+    /// 文字色
+    pub fg: Rgb,
+    /// 背景色（None の場合はターミナルの既定の背景）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bg: Option<Rgb>,
+}
+
+impl Cell {
+    /// 空白のセル
+    pub const BLANK: Cell = Cell::new(' ', [0, 0, 0]);
+
+    pub const fn new(ch: char, fg: Rgb) -> Self {
+        Self { ch, fg, bg: None }
+    }
+}
+
+/// 描画結果のセルのグリッド（行優先で `width * height` 個）
 #[derive(Debug, Clone)]
 pub struct RenderedFrame {
-    pub ascii_text: String,
-    pub rgb_data: Vec<u8>,
+    pub cells: Vec<Cell>,
     pub width: u32,
     pub height: u32,
+    /// セルの代わりに画面の左上から表示する Sixel 画像（`SixelRenderer` の出力）
+    pub sixel: Option<Arc<[u8]>>,
+}
+
+impl RenderedFrame {
+    /// 行ごとのセル
+    pub fn rows(&self) -> impl Iterator<Item = &[Cell]> {
+        self.cells
+            .chunks(self.width.max(1) as usize)
+            .take(self.height as usize)
+    }
+
+    /// 文字だけを行ごとに改行でつないだテキスト
+    #[cfg(test)]
+    pub fn text(&self) -> String {
+        self.rows()
+            .map(|row| row.iter().map(|cell| cell.ch).collect::<String>())
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
 }
 
 pub struct AsciiRenderer {
@@ -47,115 +207,327 @@ impl AsciiRenderer {
         }
     }
 
-    pub fn set_char_map(&mut self, index: u8) {
-        self.config.char_map_index = index;
-    }
+    fn image_to_cells(&self, rgb_image: &ImageBuffer<image::Rgb<u8>, Vec<u8>>) -> Vec<Cell> {
+        let char_map = char_maps::get_char_map(self.config.char_map_index);
+        let filter = self.config.color_filter;
+        let luma = self.config.luma;
+
+        if self.config.dither == Dither::None {
+            return rgb_image
+                .pixels()
+                .map(|pixel| {
+                    let [r, g, b] = pixel.0;
+
+                    // 文字は元の明るさで選び、色だけにフィルターをかける
+                    Cell::new(
+                        char_maps::luminance_to_char(luma.luminance([r, g, b]), char_map),
+                        filter.apply([r, g, b]),
+                    )
+                })
+                .collect();
+        }
 
-    pub fn set_grayscale(&mut self, grayscale: bool) {
-        self.config.grayscale = grayscale;
+        let chars: Vec<char> = char_map.chars().collect();
+        let lumas: Vec<u8> = rgb_image
+            .pixels()
+            .map(|pixel| luma.luminance(pixel.0))
+            .collect();
+        let levels = dither_levels(
+            &lumas,
+            rgb_image.width() as usize,
+            chars.len().max(1),
+            self.config.dither,
+        );
+        rgb_image
+            .pixels()
+            .zip(levels)
+            .map(|(pixel, level)| {
+                Cell::new(
+                    chars.get(level).copied().unwrap_or(' '),
+                    filter.apply(pixel.0),
+                )
+            })
+            .collect()
     }
 
-    pub fn render_video_frame(&mut self, frame: &VideoFrame) -> Result<RenderedFrame> {
-        let dynamic_image = frame
-            .to_dynamic_image()
-            .map_err(|e| anyhow::anyhow!("Failed to convert frame to image: {}", e))?;
+    /// 2x4 画素ずつ点字 1 文字にまとめる
+    ///
+    /// 点ごとの明るさを Bayer 行列のしきい値で二値化し（暗い背景に明るい点）、
+    /// 文字色は点灯した点の平均色にする
+    fn image_to_braille_cells(
+        &self,
+        rgb_image: &ImageBuffer<image::Rgb<u8>, Vec<u8>>,
+    ) -> Vec<Cell> {
+        let filter = self.config.color_filter;
+        let luma = self.config.luma;
+        let mut cells =
+            Vec::with_capacity((self.config.target_width * self.config.target_height) as usize);
+        // 誤差拡散では点灯する点を先に画像全体で決める
+        let diffused = (self.config.dither == Dither::FloydSteinberg).then(|| {
+            let lumas: Vec<u8> = rgb_image
+                .pixels()
+                .map(|pixel| luma.luminance(pixel.0))
+                .collect();
+            dither_levels(
+                &lumas,
+                rgb_image.width() as usize,
+                2,
+                Dither::FloydSteinberg,
+            )
+        });
+
+        for row in 0..self.config.target_height {
+            for column in 0..self.config.target_width {
+                let mut bits = 0u8;
+                let mut lit_sum = [0u32; 3];
+                let mut lit_count = 0;
+                let mut unlit_sum = [0u32; 3];
+
+                for dy in 0..BRAILLE_DOTS_Y {
+                    for dx in 0..BRAILLE_DOTS_X {
+                        let x = column * BRAILLE_DOTS_X + dx;
+                        let y = row * BRAILLE_DOTS_Y + dy;
+                        let rgb = rgb_image.get_pixel(x, y).0;
+                        let lit = match &diffused {
+                            Some(levels) => levels[(y * rgb_image.width() + x) as usize] > 0,
+                            None => dot_is_lit(luma.luminance(rgb), x, y),
+                        };
+                        let sum = if lit {
+                            bits |= BRAILLE_BITS[dy as usize][dx as usize];
+                            lit_count += 1;
+                            &mut lit_sum
+                        } else {
+                            &mut unlit_sum
+                        };
+                        for channel in 0..3 {
+                            sum[channel] += rgb[channel] as u32;
+                        }
+                    }
+                }
+
+                // 1 つも点灯しなければ、全体の平均色にする
+                let color = if lit_count > 0 {
+                    lit_sum.map(|sum| (sum / lit_count) as u8)
+                } else {
+                    unlit_sum.map(|sum| (sum / (BRAILLE_DOTS_X * BRAILLE_DOTS_Y)) as u8)
+                };
+                let ch = char::from_u32(BRAILLE_BLANK + bits as u32).unwrap_or(' ');
+                cells.push(Cell::new(ch, filter.apply(color)));
+            }
+        }
+        cells
+    }
 
-        self.render_image(&dynamic_image)
+    /// 上下 2 画素ずつ `▀` 1 文字にまとめる（文字色が上、背景色が下の画素）
+    ///
+    /// グレースケールでは色を出力しないので、明るい画素を `▀` `▄` `█` で描き分ける
+    fn image_to_half_block_cells(
+        &self,
+        rgb_image: &ImageBuffer<image::Rgb<u8>, Vec<u8>>,
+    ) -> Vec<Cell> {
+        let filter = self.config.color_filter;
+        let mut cells =
+            Vec::with_capacity((self.config.target_width * self.config.target_height) as usize);
+
+        for row in 0..self.config.target_height {
+            for column in 0..self.config.target_width {
+                let top = rgb_image.get_pixel(column, row * HALF_BLOCK_DOTS_Y).0;
+                let bottom = rgb_image.get_pixel(column, row * HALF_BLOCK_DOTS_Y + 1).0;
+
+                let cell = if self.config.grayscale {
+                    let ch = match (
+                        self.config.luma.luminance(top) >= HALF_BLOCK_THRESHOLD,
+                        self.config.luma.luminance(bottom) >= HALF_BLOCK_THRESHOLD,
+                    ) {
+                        (true, true) => '█',
+                        (true, false) => '▀',
+                        (false, true) => '▄',
+                        (false, false) => ' ',
+                    };
+                    Cell::new(ch, [255, 255, 255])
+                } else {
+                    Cell {
+                        ch: '▀',
+                        fg: filter.apply(top),
+                        bg: Some(filter.apply(bottom)),
+                    }
+                };
+                cells.push(cell);
+            }
+        }
+        cells
     }
+}
 
-    pub fn render_image(&mut self, image: &DynamicImage) -> Result<RenderedFrame> {
-        let resized_image = self.resize_image(image)?;
+impl Renderer for AsciiRenderer {
+    fn render_image(&mut self, image: &DynamicImage) -> Result<RenderedFrame> {
+        let (dots_x, dots_y) = match self.config.render_mode {
+            RenderMode::Ascii => (1, 1),
+            RenderMode::Braille => (BRAILLE_DOTS_X, BRAILLE_DOTS_Y),
+            RenderMode::HalfBlock => (1, HALF_BLOCK_DOTS_Y),
+        };
+        let resized_image = resize_image(
+            &mut self.resizer,
+            image,
+            self.config.target_width * dots_x,
+            self.config.target_height * dots_y,
+        )?;
 
         let rgb_image = resized_image.to_rgb8();
 
-        let (ascii_text, rgb_data) = self.image_to_ascii_with_color(&rgb_image);
+        let mut cells = match self.config.render_mode {
+            RenderMode::Ascii => self.image_to_cells(&rgb_image),
+            RenderMode::Braille => self.image_to_braille_cells(&rgb_image),
+            RenderMode::HalfBlock => self.image_to_half_block_cells(&rgb_image),
+        };
+        if let Some(curve) = &self.config.transfer {
+            for cell in &mut cells {
+                cell.fg = curve.apply(cell.fg);
+                cell.bg = cell.bg.map(|bg| curve.apply(bg));
+            }
+        }
+        let mode = self.config.color_mode;
+        if matches!(mode, ColorMode::Ansi256 | ColorMode::Ansi16) {
+            for cell in &mut cells {
+                cell.fg = mode.quantize(cell.fg);
+                cell.bg = cell.bg.map(|bg| mode.quantize(bg));
+            }
+        }
 
         Ok(RenderedFrame {
-            ascii_text,
-            rgb_data,
+            cells,
             width: self.config.target_width,
             height: self.config.target_height,
+            sixel: None,
         })
     }
 
-    fn resize_image(&mut self, image: &DynamicImage) -> Result<DynamicImage> {
-        let src_width = image.width();
-        let src_height = image.height();
+    fn set_target_size(&mut self, width: u32, height: u32) {
+        self.config.target_width = width;
+        self.config.target_height = height;
+    }
 
-        if src_width == self.config.target_width && src_height == self.config.target_height {
-            return Ok(image.clone());
-        }
+    fn char_map(&self) -> Option<u8> {
+        Some(self.config.char_map_index)
+    }
 
-        let rgb_image = image.to_rgb8();
+    fn set_char_map(&mut self, index: u8) {
+        self.config.char_map_index = index;
+    }
 
-        let src_image = fr::images::Image::from_vec_u8(
-            src_width,
-            src_height,
-            rgb_image.into_raw(),
-            fr::PixelType::U8x3,
-        )?;
+    fn set_grayscale(&mut self, grayscale: bool) {
+        self.config.grayscale = grayscale;
+    }
 
-        let mut dst_image = fr::images::Image::new(
-            self.config.target_width,
-            self.config.target_height,
-            fr::PixelType::U8x3,
-        );
+    fn color_filter(&self) -> ColorFilter {
+        self.config.color_filter
+    }
 
-        self.resizer.resize(
-            &src_image,
-            &mut dst_image,
-            &fr::ResizeOptions::new()
-                .resize_alg(fr::ResizeAlg::Convolution(fr::FilterType::Lanczos3)),
-        )?;
+    fn set_color_filter(&mut self, filter: ColorFilter) {
+        self.config.color_filter = filter;
+    }
 
-        let resized_data = dst_image.into_vec();
-        let resized_buffer = ImageBuffer::from_raw(
-            self.config.target_width,
-            self.config.target_height,
-            resized_data,
-        )
-        .ok_or_else(|| anyhow::anyhow!("Failed to create image buffer"))?;
+    fn set_luma(&mut self, luma: Matrix) {
+        self.config.luma = luma;
+    }
+}
 
-        Ok(DynamicImage::ImageRgb8(resized_buffer))
+/// 画像を `width` x `height` 画素に拡大・縮小する（Lanczos3）
+pub(crate) fn resize_image(
+    resizer: &mut fr::Resizer,
+    image: &DynamicImage,
+    width: u32,
+    height: u32,
+) -> Result<DynamicImage> {
+    let src_width = image.width();
+    let src_height = image.height();
+
+    if src_width == width && src_height == height {
+        return Ok(image.clone());
     }
 
-    fn image_to_ascii_with_color(
-        &self,
-        rgb_image: &ImageBuffer<image::Rgb<u8>, Vec<u8>>,
-    ) -> (String, Vec<u8>) {
-        let char_map = char_maps::get_char_map(self.config.char_map_index);
-        let (width, height) = rgb_image.dimensions();
+    let rgb_image = image.to_rgb8();
 
-        let mut ascii_text = String::with_capacity((width * height) as usize + height as usize);
-        let mut rgb_data = Vec::with_capacity((width * height * 3) as usize);
+    let src_image = fr::images::Image::from_vec_u8(
+        src_width,
+        src_height,
+        rgb_image.into_raw(),
+        fr::PixelType::U8x3,
+    )?;
 
-        for y in 0..height {
-            for x in 0..width {
-                let pixel = rgb_image.get_pixel(x, y);
-                let [r, g, b] = pixel.0;
+    let mut dst_image = fr::images::Image::new(width, height, fr::PixelType::U8x3);
 
-                // ITU-R BT.709
-                let luminance = (0.2126 * r as f32 + 0.7152 * g as f32 + 0.0722 * b as f32) as u8;
+    resizer.resize(
+        &src_image,
+        &mut dst_image,
+        &fr::ResizeOptions::new().resize_alg(fr::ResizeAlg::Convolution(fr::FilterType::Lanczos3)),
+    )?;
 
-                let ch = char_maps::luminance_to_char(luminance, char_map);
-                ascii_text.push(ch);
+    let resized_data = dst_image.into_vec();
+    let resized_buffer = ImageBuffer::from_raw(width, height, resized_data)
+        .ok_or_else(|| anyhow::anyhow!("Failed to create image buffer"))?;
 
-                rgb_data.push(r);
-                rgb_data.push(g);
-                rgb_data.push(b);
-            }
+    Ok(DynamicImage::ImageRgb8(resized_buffer))
+}
 
-            // Optional
-            if self.config.add_newlines && y < height - 1 {
-                ascii_text.push('\r');
-                ascii_text.push('\n');
+/// 画素 (x, y) の点を点灯するかどうか（組織的ディザリング）
+fn dot_is_lit(luminance: u8, x: u32, y: u32) -> bool {
+    let level = BAYER_4X4[(y % 4) as usize][(x % 4) as usize] as u32;
+    // しきい値は 8〜248 に散らばる
+    luminance as u32 > level * 16 + 8
+}
 
-                // Add RGB data for new line characters (fill with black)
-                rgb_data.extend_from_slice(&[0, 0, 0, 0, 0, 0]);
+/// 幅 `width` の画像の明るさを `levels` 段階（0 が最も暗い）に減らす
+///
+/// 段階 i は明るさ i * 255 / (levels - 1) を表す
+fn dither_levels(lumas: &[u8], width: usize, levels: usize, dither: Dither) -> Vec<usize> {
+    let max_level = levels.saturating_sub(1);
+    if max_level == 0 {
+        return vec![0; lumas.len()];
+    }
+    let step = 255.0 / max_level as f32;
+
+    match dither {
+        Dither::None => lumas
+            .iter()
+            .map(|&luma| (luma as usize * levels / 256).min(max_level))
+            .collect(),
+        Dither::Ordered => lumas
+            .iter()
+            .enumerate()
+            .map(|(index, &luma)| {
+                let (x, y) = (index % width.max(1), index / width.max(1));
+                let threshold = (BAYER_4X4[y % 4][x % 4] as f32 + 0.5) / 16.0;
+                ((luma as f32 / step + threshold) as usize).min(max_level)
+            })
+            .collect(),
+        Dither::FloydSteinberg => {
+            let width = width.max(1);
+            let mut values: Vec<f32> = lumas.iter().map(|&luma| luma as f32).collect();
+            let mut result = Vec::with_capacity(values.len());
+            for index in 0..values.len() {
+                let value = values[index];
+                let level = (value / step).round().clamp(0.0, max_level as f32);
+                result.push(level as usize);
+
+                let error = value - level * step;
+                let x = index % width;
+                let mut spread = |target: usize, weight: f32| {
+                    if let Some(value) = values.get_mut(target) {
+                        *value += error * weight;
+                    }
+                };
+                if x + 1 < width {
+                    spread(index + 1, 7.0 / 16.0);
+                    spread(index + width + 1, 1.0 / 16.0);
+                }
+                if x > 0 {
+                    spread(index + width - 1, 3.0 / 16.0);
+                }
+                spread(index + width, 5.0 / 16.0);
             }
+            result
         }
-
-        (ascii_text, rgb_data)
     }
 }
 
@@ -192,7 +564,113 @@ mod tests {
 
         assert_eq!(result.width, 4);
         assert_eq!(result.height, 2);
-        assert!(!result.ascii_text.is_empty());
-        assert_eq!(result.rgb_data.len(), 4 * 2 * 3); // width * height * RGB
+        assert_eq!(result.cells.len(), 4 * 2);
+        assert_eq!(result.rows().count(), 2);
+    }
+
+    #[test]
+    fn test_braille_mode() {
+        let config = RenderConfig {
+            target_width: 2,
+            target_height: 1,
+            render_mode: RenderMode::Braille,
+            ..Default::default()
+        };
+        let mut renderer = AsciiRenderer::new(config);
+
+        // 左の 2x4 画素は白、右は黒
+        let mut img = RgbImage::new(4, 4);
+        for y in 0..4 {
+            for x in 0..2 {
+                img.put_pixel(x, y, Rgb([255, 255, 255]));
+            }
+        }
+        let result = renderer
+            .render_image(&DynamicImage::ImageRgb8(img))
+            .unwrap();
+        assert_eq!(result.width, 2);
+        assert_eq!(result.text(), "\u{28ff}\u{2800}");
+        assert_eq!(result.cells[0].fg, [255, 255, 255]);
+    }
+
+    #[test]
+    fn test_half_block_mode() {
+        let config = RenderConfig {
+            target_width: 2,
+            target_height: 1,
+            render_mode: RenderMode::HalfBlock,
+            ..Default::default()
+        };
+        let mut renderer = AsciiRenderer::new(config);
+
+        // 上の行は黄、下の行は青
+        let mut img = RgbImage::new(2, 2);
+        for x in 0..2 {
+            img.put_pixel(x, 0, Rgb([255, 255, 0]));
+            img.put_pixel(x, 1, Rgb([0, 0, 255]));
+        }
+        let image = DynamicImage::ImageRgb8(img);
+        let result = renderer.render_image(&image).unwrap();
+        assert_eq!(result.text(), "▀▀");
+        assert_eq!(result.cells[0].fg, [255, 255, 0]);
+        assert_eq!(result.cells[0].bg, Some([0, 0, 255]));
+
+        // グレースケールでは明るい画素の位置をブロックの形で表す
+        renderer.set_grayscale(true);
+        let result = renderer.render_image(&image).unwrap();
+        assert_eq!(result.text(), "▀▀");
+        assert_eq!(result.cells[0].bg, None);
+    }
+
+    #[test]
+    fn test_palette_color_mode() {
+        let config = RenderConfig {
+            target_width: 1,
+            target_height: 1,
+            render_mode: RenderMode::HalfBlock,
+            color_mode: ColorMode::Ansi16,
+            ..Default::default()
+        };
+        let mut renderer = AsciiRenderer::new(config);
+
+        // 文字色・背景色とも 16 色のうち最も近い色になる
+        let mut img = RgbImage::new(1, 2);
+        img.put_pixel(0, 0, Rgb([240, 230, 20]));
+        img.put_pixel(0, 1, Rgb([10, 20, 200]));
+        let result = renderer
+            .render_image(&DynamicImage::ImageRgb8(img))
+            .unwrap();
+        assert_eq!(result.cells[0].fg, [255, 255, 0]);
+        assert_eq!(result.cells[0].bg, Some([0, 0, 238]));
+    }
+
+    #[test]
+    fn test_dither_levels() {
+        let gray = [128u8; 16];
+        // 丸めるだけでは同じ段階になる
+        assert_eq!(dither_levels(&gray, 4, 2, Dither::None), vec![1; 16]);
+        // ディザリングすると半分ずつ点灯する
+        for dither in [Dither::Ordered, Dither::FloydSteinberg] {
+            let levels = dither_levels(&gray, 4, 2, dither);
+            assert_eq!(levels.iter().filter(|&&level| level == 1).count(), 8);
+        }
+        // 黒と白は誤差が出ない
+        assert_eq!(
+            dither_levels(&[0, 255, 0, 255], 2, 5, Dither::FloydSteinberg),
+            vec![0, 4, 0, 4]
+        );
+        assert_eq!(dither_levels(&gray, 4, 1, Dither::Ordered), vec![0; 16]);
+    }
+
+    #[test]
+    fn test_braille_dithers_mid_gray() {
+        // 中間の明るさは一部の点だけが点灯する
+        let lit = (0..4)
+            .flat_map(|y| (0..4).map(move |x| (x, y)))
+            .filter(|&(x, y)| dot_is_lit(128, x, y))
+            .count();
+        assert_eq!(lit, 8);
+        assert!(!dot_is_lit(0, 0, 0));
+        assert!(dot_is_lit(255, 1, 2));
     }
 }
@@ -0,0 +1,588 @@
+//! 録画したファイルの再生（`replay` サブコマンド）
+//!
+//! asciicast v2（`.cast`）、`--emit jsonl` と `--emit ansimovie` の出力を、元のメディアや
+//! コーデックなしでターミナルに再生する。速度の変更と前後への移動ができる
+//!
+//! `.cast` の出力はそれまでの出力の続きとしてしか描けないため、移動するときは
+//! 画面を消して先頭から移動先までを一度に書き直す。JSON Lines は 1 行が 1 画面分なので、
+//! 移動先の直前の 1 フレームだけを描けばよい。`.ansimovie` は全体を読み込まずに
+//! 再生しながら読み、移動するときは索引で直前のキーフレームへ飛んでから描き直す
+
+use std::fs::File;
+use std::io::{BufReader, Read, Seek, Write, stdout};
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+use anyhow::{Context, Result};
+use crossterm::cursor::{Hide, Show};
+use crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+use crossterm::execute;
+use crossterm::terminal::{self, EnterAlternateScreen, LeaveAlternateScreen, SetTitle};
+use serde::Deserialize;
+use serde_json::Value;
+
+use crate::ansi::{self, ColorMode};
+use crate::ansimovie::{self, AnsiMovieReader};
+use crate::renderer::{Cell, RenderedFrame};
+use crate::status_bar::format_time;
+
+/// 指定できる再生速度
+pub const MIN_SPEED: f64 = 0.125;
+pub const MAX_SPEED: f64 = 16.0;
+
+/// ←/→ と ↓/↑ で移動する時間（再生中の操作と同じ）
+const SHORT_SEEK: Duration = Duration::from_secs(5);
+const LONG_SEEK: Duration = Duration::from_secs(30);
+
+/// キー入力を待つ最長の時間（ウィンドウタイトルの更新間隔も兼ねる）
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// 移動したときに画面を消すシーケンス
+const CLEAR_SCREEN: &[u8] = b"\x1b[0m\x1b[2J\x1b[H";
+
+/// `--speed`（0.125〜16）
+pub fn parse_speed(text: &str) -> Result<f64, String> {
+    let speed = text
+        .trim()
+        .trim_end_matches(['x', 'X'])
+        .parse::<f64>()
+        .map_err(|_| format!("'{}' is not a number", text))?;
+    if !(MIN_SPEED..=MAX_SPEED).contains(&speed) {
+        return Err(format!("must be between {} and {}", MIN_SPEED, MAX_SPEED));
+    }
+    Ok(speed)
+}
+
+/// ターミナルへ書き込む 1 回分の出力
+#[derive(Debug, Clone, PartialEq)]
+struct ReplayEvent {
+    time: Duration,
+    data: Vec<u8>,
+    /// これだけで画面全体が描ける（前の出力が要らない）
+    keyframe: bool,
+}
+
+/// 再生する出力の並び
+trait Replay {
+    fn duration(&self) -> Duration;
+
+    /// 次に書き込むイベント（読み進めない）。最後まで書いたら None
+    fn peek(&mut self) -> Result<Option<&ReplayEvent>>;
+
+    /// `peek` で見たイベントを書いたことにして、次へ進む
+    fn advance(&mut self);
+
+    /// `position` の画面を描く出力を返し、その次のイベントから再生を続ける
+    fn seek(&mut self, position: Duration) -> Result<Vec<u8>>;
+}
+
+/// 読み込んだテキストの録画
+#[derive(Debug)]
+pub struct Recording {
+    events: Vec<ReplayEvent>,
+}
+
+/// `--emit jsonl` の 1 行
+#[derive(Deserialize)]
+struct JsonFrame {
+    timestamp: f64,
+    width: u32,
+    height: u32,
+    cells: Vec<Cell>,
+}
+
+/// 録画を開く（`.ansimovie` は再生しながら読む）
+fn open(path: &Path) -> Result<Box<dyn Replay>> {
+    let mut file =
+        File::open(path).with_context(|| format!("Failed to read {}", path.display()))?;
+    let mut magic = [0; ansimovie::MAGIC.len()];
+    let is_movie = file.read_exact(&mut magic).is_ok() && &magic == ansimovie::MAGIC;
+    file.rewind()?;
+
+    let replay: Result<Box<dyn Replay>> = if is_movie {
+        MovieReplay::open(BufReader::new(file)).map(|movie| Box::new(movie) as Box<dyn Replay>)
+    } else {
+        let mut bytes = Vec::new();
+        file.read_to_end(&mut bytes)?;
+        String::from_utf8(bytes)
+            .context("The recording is neither text nor an .ansimovie file")
+            .and_then(|text| Recording::parse(&text))
+            .map(|recording| Box::new(EventReplay::new(recording)) as Box<dyn Replay>)
+    };
+    replay.with_context(|| format!("Invalid recording {}", path.display()))
+}
+
+impl Recording {
+    /// 1 行目から形式を判断して読み込む
+    pub fn parse(text: &str) -> Result<Self> {
+        let mut lines = text
+            .lines()
+            .enumerate()
+            .filter(|(_, line)| !line.trim().is_empty());
+        let Some((_, first)) = lines.next() else {
+            anyhow::bail!("The recording is empty");
+        };
+        let header: Value = serde_json::from_str(first).context("Line 1 is not JSON")?;
+
+        let mut events = Vec::new();
+        if let Some(version) = header.get("version") {
+            if version.as_u64() != Some(2) {
+                anyhow::bail!("Unsupported asciicast version {} (expected 2)", version);
+            }
+            for (index, line) in lines {
+                let (time, kind, data): (f64, String, String) = serde_json::from_str(line)
+                    .with_context(|| format!("Line {} is not an asciicast event", index + 1))?;
+                // 入力（"i"）やマーカー（"m"）は画面を変えない
+                if kind == "o" {
+                    events.push(ReplayEvent {
+                        time: seconds(time),
+                        data: data.into_bytes(),
+                        keyframe: false,
+                    });
+                }
+            }
+        } else {
+            for (index, line) in std::iter::once((0, first)).chain(lines) {
+                let frame: JsonFrame = serde_json::from_str(line)
+                    .with_context(|| format!("Line {} is not a frame", index + 1))?;
+                if frame.cells.len() != (frame.width * frame.height) as usize {
+                    anyhow::bail!("Line {} has the wrong number of cells", index + 1);
+                }
+                let rendered = RenderedFrame {
+                    cells: frame.cells,
+                    width: frame.width,
+                    height: frame.height,
+                    sixel: None,
+                };
+                events.push(ReplayEvent {
+                    time: seconds(frame.timestamp),
+                    data: ansi::encode(&rendered, ColorMode::Truecolor),
+                    keyframe: true,
+                });
+            }
+        }
+        // 時刻が戻る行があっても前から順に再生する
+        events.sort_by_key(|event| event.time);
+        Ok(Self { events })
+    }
+
+    pub fn duration(&self) -> Duration {
+        self.events
+            .last()
+            .map_or(Duration::ZERO, |event| event.time)
+    }
+
+    /// `position` の画面を描く出力と、その次に書き込むイベントの番号
+    fn seek(&self, position: Duration) -> (Vec<u8>, usize) {
+        let next = self.events.partition_point(|event| event.time <= position);
+        let start = self.events[..next]
+            .iter()
+            .rposition(|event| event.keyframe)
+            .unwrap_or(0);
+        let mut data = CLEAR_SCREEN.to_vec();
+        for event in &self.events[start..next] {
+            data.extend_from_slice(&event.data);
+        }
+        (data, next)
+    }
+}
+
+fn seconds(value: f64) -> Duration {
+    Duration::try_from_secs_f64(value).unwrap_or(Duration::ZERO)
+}
+
+/// 読み込んだテキストの録画の再生位置
+#[derive(Debug)]
+struct EventReplay {
+    recording: Recording,
+    /// 次に書き込むイベントの番号
+    next: usize,
+}
+
+impl EventReplay {
+    fn new(recording: Recording) -> Self {
+        Self { recording, next: 0 }
+    }
+}
+
+impl Replay for EventReplay {
+    fn duration(&self) -> Duration {
+        self.recording.duration()
+    }
+
+    fn peek(&mut self) -> Result<Option<&ReplayEvent>> {
+        Ok(self.recording.events.get(self.next))
+    }
+
+    fn advance(&mut self) {
+        self.next += 1;
+    }
+
+    fn seek(&mut self, position: Duration) -> Result<Vec<u8>> {
+        let (data, next) = self.recording.seek(position);
+        self.next = next;
+        Ok(data)
+    }
+}
+
+/// `.ansimovie` を読みながら再生する
+///
+/// キーフレームは画面全体、差分フレームは変わったセルだけを描く
+struct MovieReplay<R: Read + Seek> {
+    reader: AnsiMovieReader<R>,
+    duration: Duration,
+    /// 最後に読んだフレーム（差分を描くのに使う）
+    previous: Option<RenderedFrame>,
+    /// 読んだがまだ書き込んでいないイベント
+    pending: Option<ReplayEvent>,
+}
+
+impl<R: Read + Seek> MovieReplay<R> {
+    fn open(input: R) -> Result<Self> {
+        let mut reader = AnsiMovieReader::open(input)?;
+        // 長さは最後のキーフレームから終わりまで読んで求める
+        let mut duration = reader
+            .index()
+            .last()
+            .map_or(Duration::ZERO, |entry| entry.timestamp);
+        reader.seek(duration)?;
+        while let Some((time, _)) = reader.next_frame()? {
+            duration = time;
+        }
+        reader.seek(Duration::ZERO)?;
+        Ok(Self {
+            reader,
+            duration,
+            previous: None,
+            pending: None,
+        })
+    }
+
+    fn read_event(&mut self) -> Result<Option<ReplayEvent>> {
+        let Some((time, frame)) = self.reader.next_frame()? else {
+            return Ok(None);
+        };
+        let keyframe = self.reader.at_keyframe();
+        let data = match &self.previous {
+            Some(previous) if !keyframe => {
+                ansi::encode_changes(previous, &frame, ColorMode::Truecolor)
+            }
+            _ => ansi::encode(&frame, ColorMode::Truecolor),
+        };
+        self.previous = Some(frame);
+        Ok(Some(ReplayEvent {
+            time,
+            data,
+            keyframe,
+        }))
+    }
+}
+
+impl<R: Read + Seek> Replay for MovieReplay<R> {
+    fn duration(&self) -> Duration {
+        self.duration
+    }
+
+    fn peek(&mut self) -> Result<Option<&ReplayEvent>> {
+        if self.pending.is_none() {
+            self.pending = self.read_event()?;
+        }
+        Ok(self.pending.as_ref())
+    }
+
+    fn advance(&mut self) {
+        self.pending = None;
+    }
+
+    fn seek(&mut self, position: Duration) -> Result<Vec<u8>> {
+        self.reader.seek(position)?;
+        self.previous = None;
+        self.pending = None;
+        let mut data = CLEAR_SCREEN.to_vec();
+        while let Some(event) = self.peek()?
+            && event.time <= position
+        {
+            data.extend_from_slice(&event.data);
+            self.advance();
+        }
+        Ok(data)
+    }
+}
+
+/// 速度を変えたり止めたりできる再生位置
+#[derive(Debug)]
+struct ReplayClock {
+    /// `anchor` の時点の再生位置
+    base: Duration,
+    anchor: Instant,
+    speed: f64,
+    playing: bool,
+}
+
+impl ReplayClock {
+    fn new(speed: f64) -> Self {
+        Self {
+            base: Duration::ZERO,
+            anchor: Instant::now(),
+            speed,
+            playing: true,
+        }
+    }
+
+    fn position_at(&self, now: Instant) -> Duration {
+        if self.playing {
+            self.base + (now - self.anchor).mul_f64(self.speed)
+        } else {
+            self.base
+        }
+    }
+
+    /// 今の位置を基準にし直してから状態を変える
+    fn rebase(&mut self, now: Instant) {
+        self.base = self.position_at(now);
+        self.anchor = now;
+    }
+
+    fn set_speed(&mut self, speed: f64, now: Instant) {
+        self.rebase(now);
+        self.speed = speed.clamp(MIN_SPEED, MAX_SPEED);
+    }
+
+    fn set_playing(&mut self, playing: bool, now: Instant) {
+        self.rebase(now);
+        self.playing = playing;
+    }
+
+    fn seek(&mut self, position: Duration, now: Instant) {
+        self.base = position;
+        self.anchor = now;
+    }
+}
+
+/// 録画を再生する。q か Esc で終わる
+pub fn run(path: &Path, speed: f64) -> Result<()> {
+    let mut replay = open(path)?;
+    let name = path.file_name().map_or_else(
+        || path.display().to_string(),
+        |name| name.to_string_lossy().into_owned(),
+    );
+
+    let mut out = stdout();
+    execute!(out, EnterAlternateScreen, Hide)?;
+    terminal::enable_raw_mode()?;
+    let result = play(replay.as_mut(), &name, speed, &mut out);
+    terminal::disable_raw_mode()?;
+    execute!(out, Show, LeaveAlternateScreen)?;
+    result
+}
+
+fn play(replay: &mut dyn Replay, name: &str, speed: f64, out: &mut impl Write) -> Result<()> {
+    let duration = replay.duration();
+    let mut clock = ReplayClock::new(speed);
+    out.write_all(CLEAR_SCREEN)?;
+
+    loop {
+        let now = Instant::now();
+        let mut position = clock.position_at(now);
+        if position >= duration && clock.playing {
+            // 最後で止め、戻って見直せるようにする
+            clock.seek(duration, now);
+            clock.set_playing(false, now);
+            position = duration;
+        }
+        while let Some(event) = replay.peek()?
+            && event.time <= position
+        {
+            out.write_all(&event.data)?;
+            replay.advance();
+        }
+        let icon = if clock.playing { '▶' } else { '⏸' };
+        execute!(
+            out,
+            SetTitle(format!(
+                "{} {} {} / {} x{}",
+                icon,
+                name,
+                format_time(position),
+                format_time(duration),
+                clock.speed
+            ))
+        )?;
+        out.flush()?;
+
+        let timeout = match replay.peek()? {
+            Some(event) if clock.playing => (event.time - position)
+                .div_f64(clock.speed)
+                .min(POLL_INTERVAL),
+            _ => POLL_INTERVAL,
+        };
+        if !event::poll(timeout)? {
+            continue;
+        }
+        let Event::Key(KeyEvent {
+            code,
+            modifiers,
+            kind: KeyEventKind::Press,
+            ..
+        }) = event::read()?
+        else {
+            continue;
+        };
+
+        let now = Instant::now();
+        let position = clock.position_at(now);
+        let target = match code {
+            KeyCode::Char('q') | KeyCode::Char('Q') | KeyCode::Esc => return Ok(()),
+            KeyCode::Char('c') if modifiers.contains(KeyModifiers::CONTROL) => return Ok(()),
+            KeyCode::Char(' ') => {
+                // 最後で止まっていたら最初から再生する
+                if !clock.playing && position >= duration {
+                    Some(Duration::ZERO)
+                } else {
+                    clock.set_playing(!clock.playing, now);
+                    None
+                }
+            }
+            KeyCode::Char('+') | KeyCode::Char('=') => {
+                clock.set_speed(clock.speed * 2.0, now);
+                None
+            }
+            KeyCode::Char('-') => {
+                clock.set_speed(clock.speed / 2.0, now);
+                None
+            }
+            KeyCode::Left => Some(position.saturating_sub(SHORT_SEEK)),
+            KeyCode::Right => Some(position + SHORT_SEEK),
+            KeyCode::Down => Some(position.saturating_sub(LONG_SEEK)),
+            KeyCode::Up => Some(position + LONG_SEEK),
+            KeyCode::Home => Some(Duration::ZERO),
+            _ => None,
+        };
+        if let Some(target) = target {
+            let target = target.min(duration);
+            out.write_all(&replay.seek(target)?)?;
+            clock.seek(target, now);
+            if target < duration && !clock.playing {
+                clock.set_playing(true, now);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::*;
+
+    const CAST: &str = r#"{"version": 2, "width": 80, "height": 24}
+[0.5, "o", "hello"]
+[1.0, "i", "q"]
+[2.0, "o", " world"]
+"#;
+
+    #[test]
+    fn test_parse_asciicast() {
+        let recording = Recording::parse(CAST).unwrap();
+        assert_eq!(recording.events.len(), 2);
+        assert_eq!(recording.duration(), Duration::from_secs(2));
+
+        // 移動すると画面を消して最初から書き直す
+        let (data, next) = recording.seek(Duration::from_millis(1500));
+        assert_eq!(next, 1);
+        assert_eq!(data, [CLEAR_SCREEN, b"hello"].concat());
+        let (data, next) = recording.seek(Duration::from_secs(3));
+        assert_eq!(next, 2);
+        assert!(data.ends_with(b"hello world"));
+
+        assert!(Recording::parse(r#"{"version": 1}"#).is_err());
+        assert!(Recording::parse("").is_err());
+    }
+
+    #[test]
+    fn test_parse_jsonl_frames() {
+        let text = r#"{"timestamp":0.0,"width":1,"height":1,"cells":[{"ch":"a","fg":[255,0,0]}]}
+{"timestamp":0.5,"width":1,"height":1,"cells":[{"ch":"b","fg":[0,0,0],"bg":[0,0,255]}]}
+{"timestamp":1.0,"width":1,"height":1,"cells":[{"ch":"c","fg":[0,0,0]}]}"#;
+        let recording = Recording::parse(text).unwrap();
+        assert_eq!(recording.events.len(), 3);
+
+        // 1 行が 1 画面なので、直前のフレームだけを描く
+        let (data, next) = recording.seek(Duration::from_millis(700));
+        assert_eq!(next, 2);
+        let text = String::from_utf8(data).unwrap();
+        assert!(text.contains('b') && !text.contains('a'));
+
+        let broken = r#"{"timestamp":0.0,"width":2,"height":1,"cells":[]}"#;
+        assert!(Recording::parse(broken).is_err());
+    }
+
+    #[test]
+    fn test_parse_ansimovie() {
+        let mut bytes = Vec::new();
+        let mut writer = ansimovie::AnsiMovieWriter::new(&mut bytes).unwrap();
+        let mut frame = RenderedFrame {
+            cells: vec![Cell::new('a', [255, 255, 255]); 4],
+            width: 4,
+            height: 1,
+            sixel: None,
+        };
+        writer.write_frame(Duration::ZERO, &frame).unwrap();
+        frame.cells[3] = Cell::new('z', [255, 255, 255]);
+        writer.write_frame(Duration::from_secs(1), &frame).unwrap();
+        writer.finish().unwrap();
+        drop(writer);
+
+        let mut movie = MovieReplay::open(Cursor::new(bytes)).unwrap();
+        assert_eq!(movie.duration(), Duration::from_secs(1));
+        let mut events = Vec::new();
+        while let Some(event) = movie.peek().unwrap() {
+            events.push(event.clone());
+            movie.advance();
+        }
+        let keyframes: Vec<bool> = events.iter().map(|event| event.keyframe).collect();
+        assert_eq!(keyframes, [true, false]);
+        // 差分フレームは変わったセルだけを描く
+        let delta = String::from_utf8(events[1].data.clone()).unwrap();
+        assert!(delta.contains('z') && !delta.contains('a'));
+
+        // 移動するとキーフレームから移動先までを描き直し、その次から続ける
+        let data = String::from_utf8(movie.seek(Duration::from_millis(1500)).unwrap()).unwrap();
+        assert!(data.starts_with("\x1b[0m\x1b[2J") && data.contains('a') && data.contains('z'));
+        assert!(movie.peek().unwrap().is_none());
+        let data = String::from_utf8(movie.seek(Duration::ZERO).unwrap()).unwrap();
+        assert!(!data.contains('z'));
+        assert_eq!(movie.peek().unwrap().unwrap().time, Duration::from_secs(1));
+    }
+
+    #[test]
+    fn test_clock_speed_and_pause() {
+        let start = Instant::now();
+        let mut clock = ReplayClock::new(2.0);
+        clock.anchor = start;
+        let later = start + Duration::from_secs(1);
+        assert_eq!(clock.position_at(later), Duration::from_secs(2));
+
+        clock.set_playing(false, later);
+        assert_eq!(
+            clock.position_at(later + Duration::from_secs(5)),
+            Duration::from_secs(2)
+        );
+
+        clock.set_playing(true, later);
+        clock.set_speed(100.0, later);
+        assert_eq!(clock.speed, MAX_SPEED);
+        assert_eq!(
+            clock.position_at(later + Duration::from_secs(1)),
+            Duration::from_secs(18)
+        );
+    }
+
+    #[test]
+    fn test_parse_speed() {
+        assert_eq!(parse_speed("2"), Ok(2.0));
+        assert_eq!(parse_speed("0.5x"), Ok(0.5));
+        assert!(parse_speed("0").is_err());
+        assert!(parse_speed("fast").is_err());
+    }
+}
@@ -0,0 +1,130 @@
+//! 信頼できない入力を開く前にプロセスの権限を絞る（`--sandbox`、Linux のみ）
+//!
+//! メディアのパーサーは攻撃されやすいので、ダウンロードなどの準備が終わり、
+//! FFmpeg がファイルを解析し始める前に Landlock で制限する
+//!
+//! - ファイルは読み取りと実行だけ（デバイス・一時ディレクトリ・状態ディレクトリと、
+//!   出力先に指定したファイルのディレクトリは書き込める）
+//! - 再生中に通信しない場合は TCP の接続・待ち受けを禁止する（Linux 6.7 以降）
+//!
+//! 制限は子プロセス（`--pipe-frames` のコマンドなど）にも引き継がれ、解除できない
+
+use std::path::{Path, PathBuf};
+
+use anyhow::Result;
+
+use crate::playback_state;
+
+/// 制限の内容
+#[derive(Debug, Clone, PartialEq)]
+pub struct Policy {
+    /// 書き込みを許すディレクトリ
+    writable: Vec<PathBuf>,
+    /// 通信を許す
+    network: bool,
+}
+
+impl Policy {
+    /// デバイス・一時ディレクトリ・状態ディレクトリにだけ書き込める制限
+    pub fn new(network: bool) -> Self {
+        let mut writable = vec![PathBuf::from("/dev"), std::env::temp_dir()];
+        writable.extend(playback_state::state_dir());
+        Self { writable, network }
+    }
+
+    /// `file` を作成・上書きできるよう、そのディレクトリへの書き込みも許す
+    pub fn allow_output(mut self, file: &Path) -> Self {
+        let dir = match file.parent() {
+            Some(dir) if !dir.as_os_str().is_empty() => dir.to_path_buf(),
+            _ => PathBuf::from("."),
+        };
+        if !self.writable.contains(&dir) {
+            self.writable.push(dir);
+        }
+        self
+    }
+
+    /// 制限をこのプロセスにかける
+    pub fn enter(&self) -> Result<()> {
+        restrict(self)
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn restrict(policy: &Policy) -> Result<()> {
+    use landlock::{
+        ABI, Access, AccessFs, AccessNet, Ruleset, RulesetAttr, RulesetCreatedAttr, RulesetStatus,
+        path_beneath_rules,
+    };
+
+    let abi = ABI::V4;
+    let mut ruleset = Ruleset::default().handle_access(AccessFs::from_all(abi))?;
+    if !policy.network {
+        ruleset = ruleset.handle_access(AccessNet::from_all(abi))?;
+    }
+    // まだないディレクトリ（状態ディレクトリなど）は作れないままになる
+    let writable = policy.writable.iter().filter(|dir| dir.is_dir());
+    let status = ruleset
+        .create()?
+        .add_rules(path_beneath_rules(["/"], AccessFs::from_read(abi)))?
+        .add_rules(path_beneath_rules(writable, AccessFs::from_all(abi)))?
+        .restrict_self()?;
+
+    match status.ruleset {
+        RulesetStatus::FullyEnforced => {
+            println!("Sandbox enabled");
+            Ok(())
+        }
+        RulesetStatus::PartiallyEnforced => {
+            eprintln!(
+                "Warning: This kernel supports only part of the sandbox{}",
+                if policy.network {
+                    ""
+                } else {
+                    " (network access is not restricted before Linux 6.7)"
+                }
+            );
+            Ok(())
+        }
+        RulesetStatus::NotEnforced => {
+            anyhow::bail!("--sandbox requires Landlock (Linux 5.13 or later, enabled at boot)")
+        }
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+fn restrict(_policy: &Policy) -> Result<()> {
+    anyhow::bail!("--sandbox is only supported on Linux")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_policy_writable_dirs() {
+        let policy = Policy::new(false)
+            .allow_output(Path::new("stats.json"))
+            .allow_output(Path::new("/var/log/ascii-term/events.jsonl"))
+            .allow_output(Path::new("out/events.jsonl"))
+            .allow_output(Path::new("out/markers.json"));
+
+        assert!(!policy.network);
+        assert!(policy.writable.contains(&PathBuf::from("/dev")));
+        assert!(policy.writable.contains(&std::env::temp_dir()));
+        let outputs: Vec<&PathBuf> = policy
+            .writable
+            .iter()
+            .filter(|dir| !dir.starts_with("/dev") && **dir != std::env::temp_dir())
+            .filter(|dir| Some(dir.as_path()) != playback_state::state_dir().as_deref())
+            .collect();
+        assert_eq!(
+            outputs,
+            [
+                Path::new("."),
+                Path::new("/var/log/ascii-term"),
+                Path::new("out")
+            ]
+        );
+    }
+}
@@ -0,0 +1,147 @@
+//! 輝度ヒストグラムと RGB パレード
+//!
+//! 撮影素材の露出や色かぶりを手早く確認するため、描画済みフレームのセルの色から
+//! 小さなスコープを作り、画面の隅に重ねて表示する
+
+use codec::color::luminance;
+
+use crate::canvas::TextCanvas;
+use crate::renderer::{Cell, RenderedFrame, Rgb};
+
+/// スコープの高さ（行数）
+pub const HEIGHT: usize = 6;
+
+/// ヒストグラムの階級数（= 列数）
+const HISTOGRAM_BINS: usize = 24;
+
+/// パレードの 1 チャンネルあたりの列数
+const PARADE_COLUMNS: usize = 8;
+
+/// スコープ全体の幅
+pub const WIDTH: usize = HISTOGRAM_BINS + 1 + (PARADE_COLUMNS + 1) * 3 - 1;
+
+/// 下から 1/8 ずつ埋まるブロック
+const BARS: [char; 9] = [' ', '▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+
+const BACKGROUND: Rgb = [16, 16, 16];
+const HISTOGRAM_COLOR: Rgb = [220, 220, 220];
+const CHANNEL_COLORS: [Rgb; 3] = [[255, 80, 80], [80, 255, 80], [90, 140, 255]];
+
+/// フレームからスコープを描画する
+pub fn render_scopes(frame: &RenderedFrame) -> RenderedFrame {
+    let mut canvas = TextCanvas::new(WIDTH as u32, HEIGHT as u32);
+    for y in 0..HEIGHT {
+        canvas.fill_row(y, ' ', BACKGROUND);
+    }
+
+    draw_histogram(&mut canvas, frame);
+    for channel in 0..3 {
+        let left = HISTOGRAM_BINS + 1 + channel * (PARADE_COLUMNS + 1);
+        draw_parade(&mut canvas, frame, channel, left);
+    }
+
+    let mut scopes = canvas.into_frame();
+    for cell in &mut scopes.cells {
+        cell.bg = Some(BACKGROUND);
+    }
+    scopes
+}
+
+/// 輝度ヒストグラム（最も多い階級を全高とする棒グラフ）
+fn draw_histogram(canvas: &mut TextCanvas, frame: &RenderedFrame) {
+    let mut bins = [0usize; HISTOGRAM_BINS];
+    for cell in &frame.cells {
+        bins[luminance(cell.fg) as usize * HISTOGRAM_BINS / 256] += 1;
+    }
+    let max = bins.iter().copied().max().unwrap_or(0).max(1);
+
+    for (x, &count) in bins.iter().enumerate() {
+        let eighths = count * HEIGHT * 8 / max;
+        for y in 0..HEIGHT {
+            let level = HEIGHT - 1 - y;
+            let filled = eighths.saturating_sub(level * 8).min(8);
+            canvas.put_cell(x, y, Cell::new(BARS[filled], HISTOGRAM_COLOR));
+        }
+    }
+}
+
+/// 1 チャンネル分のパレード（横軸は画面の横位置、縦軸は値、濃さは画素の割合）
+fn draw_parade(canvas: &mut TextCanvas, frame: &RenderedFrame, channel: usize, left: usize) {
+    let width = frame.width.max(1) as usize;
+    let mut counts = [[0usize; HEIGHT]; PARADE_COLUMNS];
+    let mut totals = [0usize; PARADE_COLUMNS];
+    for row in frame.rows() {
+        for (x, cell) in row.iter().enumerate() {
+            let column = x * PARADE_COLUMNS / width;
+            let level = cell.fg[channel] as usize * HEIGHT / 256;
+            counts[column][level] += 1;
+            totals[column] += 1;
+        }
+    }
+
+    for column in 0..PARADE_COLUMNS {
+        for (level, &count) in counts[column].iter().enumerate() {
+            let y = HEIGHT - 1 - level;
+            let ch = density_char(count, totals[column]);
+            canvas.put_cell(left + column, y, Cell::new(ch, CHANNEL_COLORS[channel]));
+        }
+    }
+}
+
+/// 画素の割合を網掛けの濃さにする
+fn density_char(count: usize, total: usize) -> char {
+    if count == 0 || total == 0 {
+        return ' ';
+    }
+    match count * 100 / total {
+        0..5 => '░',
+        5..20 => '▒',
+        20..50 => '▓',
+        _ => '█',
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn solid(color: Rgb, width: u32, height: u32) -> RenderedFrame {
+        RenderedFrame {
+            cells: vec![Cell::new('#', color); (width * height) as usize],
+            width,
+            height,
+            sixel: None,
+        }
+    }
+
+    fn column(scopes: &RenderedFrame, x: usize) -> String {
+        scopes.rows().map(|row| row[x].ch).collect()
+    }
+
+    #[test]
+    fn test_histogram_of_black_frame() {
+        let scopes = render_scopes(&solid([0, 0, 0], 10, 4));
+        assert_eq!(scopes.width as usize, WIDTH);
+        assert_eq!(scopes.height as usize, HEIGHT);
+        assert_eq!(column(&scopes, 0), "██████");
+        assert_eq!(column(&scopes, HISTOGRAM_BINS - 1), "      ");
+    }
+
+    #[test]
+    fn test_parade_of_red_frame() {
+        let scopes = render_scopes(&solid([255, 0, 0], 16, 4));
+        let red = HISTOGRAM_BINS + 1;
+        let green = red + PARADE_COLUMNS + 1;
+        assert_eq!(column(&scopes, red), "█     ");
+        assert_eq!(column(&scopes, green), "     █");
+        assert!(scopes.cells.iter().all(|cell| cell.bg == Some(BACKGROUND)));
+    }
+
+    #[test]
+    fn test_density_char() {
+        assert_eq!(density_char(0, 10), ' ');
+        assert_eq!(density_char(1, 100), '░');
+        assert_eq!(density_char(30, 100), '▓');
+        assert_eq!(density_char(10, 10), '█');
+    }
+}
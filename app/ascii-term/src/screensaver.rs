@@ -0,0 +1,217 @@
+//! アトラクトモード（スクリーンセーバー）
+//!
+//! 再生リストが空のときや再生の合間に表示する映像。ファイルを使わない
+//! 手続き的なメディアソースとして実装し、通常の映像と同じレンダラーで描画する
+
+use std::time::Duration;
+
+use clap::ValueEnum;
+use codec::Result;
+use codec::source::{MediaSource, Raster};
+use codec::video::VideoFrame;
+
+use crate::bitmap_font::{draw_text, text_size};
+
+/// スクリーンセーバーの種類
+#[derive(Debug, Clone, Copy, PartialEq, ValueEnum)]
+pub enum ScreensaverKind {
+    /// 跳ね回るロゴ
+    Logo,
+    /// マトリックス風の文字の雨
+    Matrix,
+    /// 時計
+    Clock,
+}
+
+impl ScreensaverKind {
+    pub fn into_source(self) -> Box<dyn MediaSource> {
+        match self {
+            ScreensaverKind::Logo => Box::new(BouncingLogo),
+            ScreensaverKind::Matrix => Box::new(MatrixRain),
+            ScreensaverKind::Clock => Box::new(Clock),
+        }
+    }
+}
+
+/// 跳ね回る "ASCII-TERM" ロゴ。壁に当たるたびに色が変わる
+struct BouncingLogo;
+
+const LOGO_TEXT: &str = "ASCII-TERM";
+const LOGO_COLORS: [[u8; 3]; 6] = [
+    [255, 80, 80],
+    [255, 200, 60],
+    [80, 255, 120],
+    [60, 200, 255],
+    [160, 110, 255],
+    [255, 110, 220],
+];
+
+impl MediaSource for BouncingLogo {
+    fn name(&self) -> &str {
+        "logo"
+    }
+
+    fn frame_rate(&self) -> f64 {
+        20.0
+    }
+
+    fn video_frame(&mut self, timestamp: Duration, width: u32, height: u32) -> Result<VideoFrame> {
+        let mut raster = Raster::new(width, height);
+        let (logo_width, logo_height) = text_size(LOGO_TEXT, 1);
+
+        let t = timestamp.as_secs_f64();
+        let (x, x_bounces) = bounce(t * 12.0, width.saturating_sub(logo_width));
+        let (y, y_bounces) = bounce(t * 6.0, height.saturating_sub(logo_height));
+        let color = LOGO_COLORS[(x_bounces + y_bounces) % LOGO_COLORS.len()];
+
+        draw_text(&mut raster, LOGO_TEXT, x as i32, y as i32, 1, color);
+        Ok(raster.into_frame(timestamp))
+    }
+}
+
+/// 0..=range を往復する位置と、これまでに壁に当たった回数
+fn bounce(distance: f64, range: u32) -> (u32, usize) {
+    if range == 0 {
+        return (0, 0);
+    }
+    let range = range as f64;
+    let bounces = (distance / range) as usize;
+    let offset = distance % range;
+    let position = if bounces.is_multiple_of(2) {
+        offset
+    } else {
+        range - offset
+    };
+    (position as u32, bounces)
+}
+
+/// 列ごとに速さの違う緑の雨
+struct MatrixRain;
+
+/// 雨粒の尾の長さ
+const RAIN_TAIL: u32 = 12;
+
+impl MediaSource for MatrixRain {
+    fn name(&self) -> &str {
+        "matrix"
+    }
+
+    fn frame_rate(&self) -> f64 {
+        20.0
+    }
+
+    fn video_frame(&mut self, timestamp: Duration, width: u32, height: u32) -> Result<VideoFrame> {
+        let mut raster = Raster::new(width, height);
+        let t = timestamp.as_secs_f64();
+        let cycle = (height + RAIN_TAIL) as f64;
+
+        for x in 0..width {
+            let seed = hash(x);
+            let speed = 6.0 + (seed % 14) as f64;
+            let head = ((t * speed + (seed >> 8) as f64) % cycle) as i32;
+
+            for i in 0..RAIN_TAIL as i32 {
+                let color = if i == 0 {
+                    [200, 255, 200]
+                } else {
+                    let fade = 1.0 - i as f32 / RAIN_TAIL as f32;
+                    [0, (230.0 * fade) as u8 + 25, (60.0 * fade) as u8]
+                };
+                raster.put(x as i32, head - i, color);
+            }
+        }
+
+        Ok(raster.into_frame(timestamp))
+    }
+}
+
+/// 列番号から擬似乱数を作る（フレーム間で同じ値になるよう状態を持たない）
+fn hash(value: u32) -> u32 {
+    let mut x = value.wrapping_mul(0x9E37_79B9) ^ 0x85EB_CA6B;
+    x ^= x >> 15;
+    x = x.wrapping_mul(0xC2B2_AE35);
+    x ^ (x >> 13)
+}
+
+/// 画面中央の大きなデジタル時計
+struct Clock;
+
+impl MediaSource for Clock {
+    fn name(&self) -> &str {
+        "clock"
+    }
+
+    fn frame_rate(&self) -> f64 {
+        4.0
+    }
+
+    fn video_frame(&mut self, timestamp: Duration, width: u32, height: u32) -> Result<VideoFrame> {
+        let mut raster = Raster::new(width, height);
+        let text = chrono::Local::now().format("%H:%M:%S").to_string();
+
+        // 画面に収まる最大の倍率で描く
+        let (unit_width, unit_height) = text_size(&text, 1);
+        let scale = (width / unit_width.max(1))
+            .min(height / unit_height.max(1))
+            .max(1);
+        let (text_width, text_height) = text_size(&text, scale);
+        let x = width.saturating_sub(text_width) / 2;
+        let y = height.saturating_sub(text_height) / 2;
+
+        draw_text(
+            &mut raster,
+            &text,
+            x as i32,
+            y as i32,
+            scale,
+            [255, 255, 255],
+        );
+        Ok(raster.into_frame(timestamp))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lit_pixels(frame: &VideoFrame) -> usize {
+        frame
+            .data
+            .chunks_exact(3)
+            .filter(|pixel| pixel.iter().any(|&c| c > 0))
+            .count()
+    }
+
+    #[test]
+    fn test_bounce_reverses_at_walls() {
+        assert_eq!(bounce(3.0, 10), (3, 0));
+        assert_eq!(bounce(13.0, 10), (7, 1));
+        assert_eq!(bounce(23.0, 10), (3, 2));
+        assert_eq!(bounce(5.0, 0), (0, 0));
+    }
+
+    #[test]
+    fn test_screensavers_produce_frames_of_requested_size() {
+        for kind in [
+            ScreensaverKind::Logo,
+            ScreensaverKind::Matrix,
+            ScreensaverKind::Clock,
+        ] {
+            let mut source = kind.into_source();
+            let frame = source
+                .video_frame(Duration::from_millis(1500), 80, 24)
+                .unwrap();
+            assert_eq!((frame.width, frame.height), (80, 24));
+            assert_eq!(frame.data.len(), 80 * 24 * 3);
+            assert!(lit_pixels(&frame) > 0, "{} drew nothing", source.name());
+        }
+    }
+
+    #[test]
+    fn test_logo_moves_over_time() {
+        let mut source = ScreensaverKind::Logo.into_source();
+        let first = source.video_frame(Duration::ZERO, 120, 30).unwrap();
+        let later = source.video_frame(Duration::from_secs(1), 120, 30).unwrap();
+        assert_ne!(first.data, later.data);
+    }
+}
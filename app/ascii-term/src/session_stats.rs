@@ -0,0 +1,210 @@
+//! 再生セッションの統計
+//!
+//! 動画の再生で段階（デコード・描画・表示の遅れ）ごとの処理時間の分布と、
+//! 捨てたフレームの数を集める。再生の終わりに要約を表示し、`--stats-json` で
+//! JSON に書き出す（バージョン間で性能の変化を比べるため）
+
+use std::fs;
+use std::path::Path;
+use std::time::Duration;
+
+use anyhow::{Context, Result};
+use serde_json::json;
+
+/// 分布の区切り（ミリ秒）。最後の区切りより長いものは最後のバケットに入る
+const BUCKET_BOUNDS_MS: [f64; 9] = [0.5, 1.0, 2.0, 4.0, 8.0, 16.0, 33.0, 66.0, 133.0];
+
+/// 処理時間の分布
+#[derive(Debug, Clone, Default)]
+pub struct Histogram {
+    counts: [u64; BUCKET_BOUNDS_MS.len() + 1],
+    total: Duration,
+    max: Duration,
+}
+
+impl Histogram {
+    pub fn record(&mut self, duration: Duration) {
+        let ms = duration.as_secs_f64() * 1000.0;
+        let bucket = BUCKET_BOUNDS_MS
+            .iter()
+            .position(|&bound| ms <= bound)
+            .unwrap_or(BUCKET_BOUNDS_MS.len());
+        self.counts[bucket] += 1;
+        self.total += duration;
+        self.max = self.max.max(duration);
+    }
+
+    pub fn count(&self) -> u64 {
+        self.counts.iter().sum()
+    }
+
+    fn mean_ms(&self) -> f64 {
+        match self.count() {
+            0 => 0.0,
+            count => self.total.as_secs_f64() * 1000.0 / count as f64,
+        }
+    }
+
+    /// `ratio`（0.0〜1.0）の位置が入るバケットの上限（ミリ秒）。最後のバケットなら最大値
+    fn percentile_ms(&self, ratio: f64) -> f64 {
+        let count = self.count();
+        if count == 0 {
+            return 0.0;
+        }
+        let rank = ((count as f64 * ratio).ceil() as u64).max(1);
+        let mut seen = 0;
+        for (bucket, &bucket_count) in self.counts.iter().enumerate() {
+            seen += bucket_count;
+            if seen >= rank {
+                return BUCKET_BOUNDS_MS
+                    .get(bucket)
+                    .copied()
+                    .unwrap_or(self.max.as_secs_f64() * 1000.0);
+            }
+        }
+        self.max.as_secs_f64() * 1000.0
+    }
+
+    fn to_json(&self) -> serde_json::Value {
+        let buckets: Vec<serde_json::Value> = self
+            .counts
+            .iter()
+            .enumerate()
+            .map(|(bucket, count)| {
+                json!({
+                    "le_ms": BUCKET_BOUNDS_MS.get(bucket),
+                    "count": count,
+                })
+            })
+            .collect();
+        json!({
+            "count": self.count(),
+            "mean_ms": self.mean_ms(),
+            "p50_ms": self.percentile_ms(0.5),
+            "p95_ms": self.percentile_ms(0.95),
+            "max_ms": self.max.as_secs_f64() * 1000.0,
+            "buckets": buckets,
+        })
+    }
+
+    /// `平均 / p95 / 最大` の 1 行
+    fn summary(&self) -> String {
+        format!(
+            "mean {:.1}ms, p95 <= {:.1}ms, max {:.1}ms ({} samples)",
+            self.mean_ms(),
+            self.percentile_ms(0.95),
+            self.max.as_secs_f64() * 1000.0,
+            self.count()
+        )
+    }
+}
+
+/// 1 回の再生の統計
+#[derive(Debug, Clone, Default)]
+pub struct SessionStats {
+    /// 1 フレームのデコード
+    pub decode: Histogram,
+    /// 切り出しと文字への変換
+    pub render: Histogram,
+    /// 表示予定の時刻からの遅れ
+    pub lag: Histogram,
+    pub frames_rendered: u64,
+    /// 遅れすぎて表示しなかったフレーム
+    pub frames_dropped_late: u64,
+    /// ライブ配信の遅延を詰めるために捨てたフレーム
+    pub frames_dropped_live: u64,
+    /// 読み飛ばした壊れたフレーム
+    pub decode_errors_skipped: u64,
+    /// 切り替えた代わりの入力の数
+    pub fallbacks: u64,
+    /// 再生にかかった時間
+    pub playback_time: Duration,
+}
+
+impl SessionStats {
+    /// 再生の終わりに表示する要約
+    pub fn summary(&self) -> String {
+        [
+            "=== Playback Statistics ===".to_string(),
+            format!(
+                "Frames: {} rendered, {} dropped (late), {} dropped (live), {} corrupt skipped",
+                self.frames_rendered,
+                self.frames_dropped_late,
+                self.frames_dropped_live,
+                self.decode_errors_skipped
+            ),
+            format!("Decode: {}", self.decode.summary()),
+            format!("Render: {}", self.render.summary()),
+            format!("Lag:    {}", self.lag.summary()),
+            format!("Playback time: {:.1}s", self.playback_time.as_secs_f64()),
+            "=== End Playback Statistics ===".to_string(),
+        ]
+        .join("\n")
+    }
+
+    pub fn to_json(&self, input: &str) -> serde_json::Value {
+        json!({
+            "version": env!("CARGO_PKG_VERSION"),
+            "input": input,
+            "playback_seconds": self.playback_time.as_secs_f64(),
+            "frames": {
+                "rendered": self.frames_rendered,
+                "dropped_late": self.frames_dropped_late,
+                "dropped_live": self.frames_dropped_live,
+                "corrupt_skipped": self.decode_errors_skipped,
+            },
+            "fallbacks": self.fallbacks,
+            "stages": {
+                "decode": self.decode.to_json(),
+                "render": self.render.to_json(),
+                "lag": self.lag.to_json(),
+            },
+        })
+    }
+
+    pub fn write_json(&self, path: &Path, input: &str) -> Result<()> {
+        let json = serde_json::to_string_pretty(&self.to_json(input))?;
+        fs::write(path, json + "\n").with_context(|| format!("Failed to write {}", path.display()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_histogram_buckets_and_percentiles() {
+        let mut histogram = Histogram::default();
+        for ms in [0, 1, 3, 3, 3, 5, 10, 20, 40, 200] {
+            histogram.record(Duration::from_millis(ms));
+        }
+        assert_eq!(histogram.count(), 10);
+        assert_eq!(histogram.counts, [1, 1, 0, 3, 1, 1, 1, 1, 0, 1]);
+        assert_eq!(histogram.percentile_ms(0.5), 4.0);
+        assert_eq!(histogram.percentile_ms(0.9), 66.0);
+        // 最後のバケットは最大値
+        assert_eq!(histogram.percentile_ms(1.0), 200.0);
+        assert!((histogram.mean_ms() - 28.5).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_stats_json() {
+        let mut stats = SessionStats {
+            frames_rendered: 2,
+            frames_dropped_late: 1,
+            ..Default::default()
+        };
+        stats.decode.record(Duration::from_millis(3));
+        let json = stats.to_json("video.mp4");
+        assert_eq!(json["input"], "video.mp4");
+        assert_eq!(json["frames"]["rendered"], 2);
+        assert_eq!(json["frames"]["dropped_late"], 1);
+        assert_eq!(json["stages"]["decode"]["count"], 1);
+        assert_eq!(json["stages"]["decode"]["buckets"][3]["le_ms"], 4.0);
+        assert_eq!(
+            json["stages"]["decode"]["buckets"][9]["le_ms"],
+            serde_json::Value::Null
+        );
+        assert_eq!(json["stages"]["render"]["p95_ms"], 0.0);
+    }
+}
@@ -0,0 +1,207 @@
+//! メディアファイルごとの設定（サイドカーファイル）
+//!
+//! `video.mp4` の隣に `video.mp4.ascii-term.toml` があれば読み込み、
+//! そのファイルだけに適用する設定として使う。例:
+//!
+//! ```toml
+//! charset = 4           # 文字マップ番号（0-9）
+//! crop = "auto"         # 被写体を追従して切り出す。{ x, y, width, height } で固定範囲
+//! audio_delay_ms = 120  # 音声を遅らせる時間（負の値で早める）
+//! start = 12.5          # 再生を始める位置（秒）
+//! ```
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use anyhow::{Context, Result};
+use codec::video::Rect;
+use serde::Deserialize;
+
+use crate::player::PlayerConfig;
+
+/// サイドカーファイルの拡張子（メディアファイル名の後ろに付ける）
+const SIDECAR_SUFFIX: &str = ".ascii-term.toml";
+
+/// 切り出しの指定
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(untagged)]
+pub enum CropOption {
+    /// `"auto"`: 被写体を追従して切り出す（`--roi` と同じ）
+    Mode(CropMode),
+    /// 元の映像のピクセル単位で固定の範囲を切り出す
+    Rect {
+        x: u32,
+        y: u32,
+        width: u32,
+        height: u32,
+    },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CropMode {
+    Auto,
+}
+
+/// メディアファイルごとの設定
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SidecarOptions {
+    /// 文字マップ番号
+    pub charset: Option<u8>,
+    /// 切り出し
+    pub crop: Option<CropOption>,
+    /// 音声を遅らせる時間（ミリ秒、負の値で早める）
+    pub audio_delay_ms: Option<i64>,
+    /// 再生開始位置（秒）
+    pub start: Option<f64>,
+}
+
+impl SidecarOptions {
+    /// メディアファイルに対応するサイドカーファイルのパス
+    pub fn path_for(media_path: &Path) -> PathBuf {
+        let mut path = media_path.as_os_str().to_os_string();
+        path.push(SIDECAR_SUFFIX);
+        PathBuf::from(path)
+    }
+
+    /// メディアファイルの隣にサイドカーファイルがあれば読み込む
+    pub fn load_for(media_path: &Path) -> Result<Option<Self>> {
+        let path = Self::path_for(media_path);
+        if !path.is_file() {
+            return Ok(None);
+        }
+        let text = fs::read_to_string(&path)
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+        let options = Self::parse(&text).with_context(|| format!("Invalid {}", path.display()))?;
+        Ok(Some(options))
+    }
+
+    /// TOML テキストを読み込む
+    pub fn parse(text: &str) -> Result<Self> {
+        let options: Self = toml::from_str(text)?;
+        if let Some(charset) = options.charset
+            && charset > 9
+        {
+            anyhow::bail!("charset must be 0-9, got {}", charset);
+        }
+        if let Some(start) = options.start
+            && !(start.is_finite() && start >= 0.0)
+        {
+            anyhow::bail!("start must be a non-negative number of seconds");
+        }
+        if let Some(CropOption::Rect { width, height, .. }) = options.crop
+            && (width == 0 || height == 0)
+        {
+            anyhow::bail!("crop width and height must be greater than 0");
+        }
+        Ok(options)
+    }
+
+    /// 再生設定に反映する
+    ///
+    /// コマンドラインで指定した文字マップはサイドカーより優先する
+    pub fn apply(&self, config: &mut PlayerConfig) {
+        if config.char_map_index.is_none() {
+            config.char_map_index = self.charset;
+        }
+        match self.crop {
+            Some(CropOption::Mode(CropMode::Auto)) => config.roi = true,
+            Some(CropOption::Rect {
+                x,
+                y,
+                width,
+                height,
+            }) => {
+                config.crop = Some(Rect {
+                    x,
+                    y,
+                    width,
+                    height,
+                })
+            }
+            None => {}
+        }
+        if let Some(delay) = self.audio_delay_ms {
+            config.audio_delay_ms = delay;
+        }
+        if let Some(start) = self.start {
+            config.start_offset = Some(Duration::from_secs_f64(start));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_all_options() {
+        let options = SidecarOptions::parse(
+            r#"
+            charset = 4
+            crop = { x = 0, y = 420, width = 1080, height = 1080 }
+            audio_delay_ms = -80
+            start = 12.5
+            "#,
+        )
+        .unwrap();
+        assert_eq!(options.charset, Some(4));
+        assert_eq!(
+            options.crop,
+            Some(CropOption::Rect {
+                x: 0,
+                y: 420,
+                width: 1080,
+                height: 1080
+            })
+        );
+
+        let mut config = PlayerConfig::default();
+        options.apply(&mut config);
+        assert_eq!(config.char_map_index, Some(4));
+        assert_eq!(config.crop.map(|rect| rect.y), Some(420));
+        assert_eq!(config.audio_delay_ms, -80);
+        assert_eq!(config.start_offset, Some(Duration::from_millis(12_500)));
+    }
+
+    #[test]
+    fn test_command_line_charset_wins() {
+        let options = SidecarOptions::parse("charset = 4\ncrop = \"auto\"").unwrap();
+        let mut config = PlayerConfig {
+            char_map_index: Some(1),
+            ..Default::default()
+        };
+        options.apply(&mut config);
+        assert_eq!(config.char_map_index, Some(1));
+        assert!(config.roi);
+        assert_eq!(config.crop, None);
+    }
+
+    #[test]
+    fn test_reject_invalid_options() {
+        assert!(SidecarOptions::parse("charset = 12").is_err());
+        assert!(SidecarOptions::parse("start = -1.0").is_err());
+        assert!(SidecarOptions::parse("crop = \"center\"").is_err());
+        assert!(SidecarOptions::parse("crop = { x = 0, y = 0, width = 0, height = 9 }").is_err());
+        // 綴り間違いに気づけるよう、未知のキーはエラーにする
+        assert!(SidecarOptions::parse("audio_delay = 100").is_err());
+    }
+
+    #[test]
+    fn test_load_for_media_file() {
+        let dir = std::env::temp_dir().join(format!("ascii-term-sidecar-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let media = dir.join("clip.mp4");
+        assert_eq!(SidecarOptions::load_for(&media).unwrap(), None);
+
+        let sidecar = SidecarOptions::path_for(&media);
+        assert_eq!(sidecar, dir.join("clip.mp4.ascii-term.toml"));
+        fs::write(&sidecar, "start = 3").unwrap();
+        let options = SidecarOptions::load_for(&media).unwrap().unwrap();
+        assert_eq!(options.start, Some(3.0));
+
+        fs::remove_dir_all(dir).ok();
+    }
+}
@@ -0,0 +1,360 @@
+//! Sixel 画像での出力（`--backend sixel`）
+//!
+//! 文字の代わりに、ターミナルのセルの大きさに合わせた画素数の画像を Sixel で送る。
+//! 色はパレット（カラーは 6x6x6 の色立方体、グレースケールは 32 階調）に減色し、
+//! 6 行ずつの帯を色ごとにランレングス圧縮して書き出す。
+//! `pixel_perfect` では端数の倍率でぼやけないよう、元の画素数の整数倍
+//! （大きすぎれば整数分の 1）に拡大・縮小して中央に置く
+
+use std::sync::Arc;
+
+use anyhow::Result;
+use codec::color::Matrix;
+use fast_image_resize as fr;
+use image::imageops::{self, FilterType};
+use image::{DynamicImage, RgbImage};
+
+use crate::color_filter::ColorFilter;
+use crate::renderer::{Cell, RenderConfig, RenderedFrame, Renderer, Rgb, resize_image};
+
+/// ターミナルが画素数を返さない場合のセルの大きさ（幅・高さ）
+const DEFAULT_CELL_SIZE: (u32, u32) = (10, 20);
+
+/// Sixel の 1 文字が表す縦の画素数
+const SIXEL_HEIGHT: u32 = 6;
+
+/// グレースケールの階調数
+const GRAY_LEVELS: u32 = 32;
+
+/// 減色に使うパレット
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Palette {
+    /// 6x6x6 の色立方体（216 色）
+    Color,
+    /// `GRAY_LEVELS` 階調の灰色（この規格の重みで明るさを求める）
+    Gray(Matrix),
+}
+
+impl Palette {
+    fn len(self) -> usize {
+        match self {
+            Palette::Color => 216,
+            Palette::Gray(_) => GRAY_LEVELS as usize,
+        }
+    }
+
+    /// いちばん近い色の番号
+    fn index(self, rgb: Rgb) -> u8 {
+        let quantize = |value: u8, levels: u32| (value as u32 * (levels - 1) + 127) / 255;
+        match self {
+            Palette::Color => {
+                let [r, g, b] = rgb.map(|value| quantize(value, 6));
+                (r * 36 + g * 6 + b) as u8
+            }
+            Palette::Gray(luma) => quantize(luma.luminance(rgb), GRAY_LEVELS) as u8,
+        }
+    }
+
+    /// 番号の色（Sixel の色指定は 0〜100 の百分率）
+    fn percent(self, index: u8) -> [u32; 3] {
+        let index = index as u32;
+        match self {
+            Palette::Color => [index / 36, index / 6 % 6, index % 6].map(|level| level * 20),
+            Palette::Gray(_) => [index * 100 / (GRAY_LEVELS - 1); 3],
+        }
+    }
+}
+
+pub struct SixelRenderer {
+    config: RenderConfig,
+    resizer: fr::Resizer,
+    /// 1 セルの画素数（幅・高さ）
+    cell_size: (u32, u32),
+}
+
+impl SixelRenderer {
+    pub fn new(config: RenderConfig) -> Self {
+        Self {
+            config,
+            resizer: fr::Resizer::new(),
+            cell_size: cell_size(),
+        }
+    }
+
+    /// `integer_scale` の大きさにした画像を、`width` x `height` の黒い画像の中央に置く
+    fn fit_integer_scale(
+        &mut self,
+        image: &DynamicImage,
+        width: u32,
+        height: u32,
+    ) -> Result<RgbImage> {
+        let (scaled_width, scaled_height) =
+            integer_scale((image.width(), image.height()), (width, height));
+        let scaled = if scaled_width >= image.width() {
+            // 拡大は画素をそのまま複製する
+            imageops::resize(
+                &image.to_rgb8(),
+                scaled_width,
+                scaled_height,
+                FilterType::Nearest,
+            )
+        } else {
+            resize_image(&mut self.resizer, image, scaled_width, scaled_height)?.to_rgb8()
+        };
+
+        let mut canvas = RgbImage::new(width, height);
+        imageops::replace(
+            &mut canvas,
+            &scaled,
+            (width.saturating_sub(scaled_width) / 2) as i64,
+            (height.saturating_sub(scaled_height) / 2) as i64,
+        );
+        Ok(canvas)
+    }
+}
+
+/// `size` の整数倍で `area` に収まる最大の大きさ（1 倍でも収まらなければ整数分の 1）
+fn integer_scale((width, height): (u32, u32), (area_width, area_height): (u32, u32)) -> (u32, u32) {
+    if width == 0 || height == 0 {
+        return (width, height);
+    }
+    let factor = (area_width / width).min(area_height / height);
+    if factor >= 1 {
+        return (width * factor, height * factor);
+    }
+    let divisor = width
+        .div_ceil(area_width.max(1))
+        .max(height.div_ceil(area_height.max(1)));
+    ((width / divisor).max(1), (height / divisor).max(1))
+}
+
+impl Renderer for SixelRenderer {
+    fn render_image(&mut self, image: &DynamicImage) -> Result<RenderedFrame> {
+        let (columns, rows) = (self.config.target_width, self.config.target_height);
+        let (cell_width, cell_height) = self.cell_size;
+        // 最後の行まで描くと画像の下端でターミナルがスクロールするので 1 行空ける
+        let width = columns * cell_width;
+        let height = rows.saturating_sub(1).max(1) * cell_height;
+
+        let mut rgb_image = if self.config.pixel_perfect {
+            self.fit_integer_scale(image, width, height)?
+        } else {
+            resize_image(&mut self.resizer, image, width, height)?.to_rgb8()
+        };
+        let filter = self.config.color_filter;
+        if filter != ColorFilter::None {
+            for pixel in rgb_image.pixels_mut() {
+                pixel.0 = filter.apply(pixel.0);
+            }
+        }
+        if let Some(curve) = &self.config.transfer {
+            for pixel in rgb_image.pixels_mut() {
+                pixel.0 = curve.apply(pixel.0);
+            }
+        }
+        let palette = if self.config.grayscale {
+            Palette::Gray(self.config.luma)
+        } else {
+            Palette::Color
+        };
+
+        Ok(RenderedFrame {
+            cells: vec![Cell::BLANK; (columns * rows) as usize],
+            width: columns,
+            height: rows,
+            sixel: Some(Arc::from(encode(&rgb_image, palette))),
+        })
+    }
+
+    fn set_target_size(&mut self, width: u32, height: u32) {
+        self.config.target_width = width;
+        self.config.target_height = height;
+        // フォントの大きさが変わっていることもあるので測り直す
+        self.cell_size = cell_size();
+    }
+
+    fn char_map(&self) -> Option<u8> {
+        None
+    }
+
+    fn set_char_map(&mut self, _index: u8) {}
+
+    fn set_grayscale(&mut self, grayscale: bool) {
+        self.config.grayscale = grayscale;
+    }
+
+    fn color_filter(&self) -> ColorFilter {
+        self.config.color_filter
+    }
+
+    fn set_color_filter(&mut self, filter: ColorFilter) {
+        self.config.color_filter = filter;
+    }
+
+    fn set_luma(&mut self, luma: Matrix) {
+        self.config.luma = luma;
+    }
+}
+
+/// ターミナルの 1 セルの画素数。分からなければ `DEFAULT_CELL_SIZE`
+fn cell_size() -> (u32, u32) {
+    match crossterm::terminal::window_size() {
+        Ok(size) if size.columns > 0 && size.rows > 0 && size.width > 0 && size.height > 0 => (
+            (size.width / size.columns).max(1) as u32,
+            (size.height / size.rows).max(1) as u32,
+        ),
+        _ => DEFAULT_CELL_SIZE,
+    }
+}
+
+/// 画像を Sixel（DCS から ST まで）に変換する
+fn encode(image: &RgbImage, palette: Palette) -> Vec<u8> {
+    let (width, height) = image.dimensions();
+    let indices: Vec<u8> = image.pixels().map(|pixel| palette.index(pixel.0)).collect();
+
+    // P2=1: 点を打たない画素は透明（色ごとに重ねて描くため）
+    let mut out = format!("\x1bP0;1;0q\"1;1;{};{}", width, height);
+
+    let mut used = vec![false; palette.len()];
+    for &index in &indices {
+        used[index as usize] = true;
+    }
+    for (index, _) in used.iter().enumerate().filter(|(_, used)| **used) {
+        let [r, g, b] = palette.percent(index as u8);
+        out.push_str(&format!("#{};2;{};{};{}", index, r, g, b));
+    }
+
+    // 帯の中で使われた色ごとの、列ごとの 6 ビット
+    let mut bands = vec![Vec::new(); palette.len()];
+    let mut band_colors = Vec::new();
+    for top in (0..height).step_by(SIXEL_HEIGHT as usize) {
+        for dy in 0..SIXEL_HEIGHT.min(height - top) {
+            let row = ((top + dy) * width) as usize;
+            for (x, &index) in indices[row..row + width as usize].iter().enumerate() {
+                let bits = &mut bands[index as usize];
+                if bits.is_empty() {
+                    *bits = vec![0u8; width as usize];
+                    band_colors.push(index);
+                }
+                bits[x] |= 1 << dy;
+            }
+        }
+
+        if top > 0 {
+            out.push('-');
+        }
+        for (n, &index) in band_colors.iter().enumerate() {
+            if n > 0 {
+                // 帯の左端に戻って次の色を重ねる
+                out.push('$');
+            }
+            out.push_str(&format!("#{}", index));
+            push_run_length(&mut out, &bands[index as usize]);
+            bands[index as usize].clear();
+        }
+        band_colors.clear();
+    }
+
+    out.push_str("\x1b\\");
+    out.into_bytes()
+}
+
+/// 6 ビットの列を Sixel 文字にし、同じ文字が続く部分は `!回数` でまとめる
+fn push_run_length(out: &mut String, bits: &[u8]) {
+    let mut columns = bits.iter().peekable();
+    while let Some(&value) = columns.next() {
+        let mut count = 1;
+        while columns.next_if_eq(&&value).is_some() {
+            count += 1;
+        }
+        let ch = (b'?' + value) as char;
+        if count > 3 {
+            out.push_str(&format!("!{}{}", count, ch));
+        } else {
+            out.extend(std::iter::repeat_n(ch, count));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::Rgb;
+
+    #[test]
+    fn test_palette() {
+        assert_eq!(Palette::Color.index([0, 0, 0]), 0);
+        assert_eq!(Palette::Color.index([255, 255, 255]), 215);
+        assert_eq!(Palette::Color.index([255, 0, 0]), 180);
+        assert_eq!(Palette::Color.percent(180), [100, 0, 0]);
+        let gray = Palette::Gray(Matrix::Bt709);
+        assert_eq!(gray.index([255, 255, 255]), 31);
+        assert_eq!(gray.percent(31), [100, 100, 100]);
+    }
+
+    #[test]
+    fn test_integer_scale() {
+        // 3 倍まで収まる
+        assert_eq!(integer_scale((100, 50), (320, 200)), (300, 150));
+        assert_eq!(integer_scale((100, 50), (100, 50)), (100, 50));
+        // 大きすぎれば 1/2, 1/3...
+        assert_eq!(integer_scale((640, 480), (400, 300)), (320, 240));
+        assert_eq!(integer_scale((1920, 1080), (800, 600)), (640, 360));
+    }
+
+    #[test]
+    fn test_pixel_perfect_centers_image() {
+        let mut renderer = SixelRenderer::new(RenderConfig {
+            target_width: 4,
+            target_height: 3,
+            pixel_perfect: true,
+            ..Default::default()
+        });
+        // 40x20 の領域に 15x10 の白い画像は 2 倍の 30x20 で左右に 5 画素ずつ余白
+        let image = DynamicImage::ImageRgb8(RgbImage::from_pixel(15, 10, Rgb([255, 255, 255])));
+        let scaled = renderer.fit_integer_scale(&image, 40, 20).unwrap();
+        assert_eq!(scaled.get_pixel(4, 10).0, [0, 0, 0]);
+        assert_eq!(scaled.get_pixel(5, 0).0, [255, 255, 255]);
+        assert_eq!(scaled.get_pixel(34, 19).0, [255, 255, 255]);
+        assert_eq!(scaled.get_pixel(35, 10).0, [0, 0, 0]);
+    }
+
+    #[test]
+    fn test_encode_two_colors() {
+        // 上 6 行が赤、下 2 行が青の 5x8 画像
+        let mut image = RgbImage::from_pixel(5, 8, Rgb([255, 0, 0]));
+        for y in 6..8 {
+            for x in 0..5 {
+                image.put_pixel(x, y, Rgb([0, 0, 255]));
+            }
+        }
+        let sixel = String::from_utf8(encode(&image, Palette::Color)).unwrap();
+        assert_eq!(
+            sixel,
+            "\x1bP0;1;0q\"1;1;5;8#5;2;0;0;100#180;2;100;0;0#180!5~-#5!5B\x1b\\"
+        );
+    }
+
+    #[test]
+    fn test_run_length() {
+        let mut out = String::new();
+        push_run_length(&mut out, &[1, 1, 1, 2, 2, 2, 2, 0]);
+        assert_eq!(out, "@@@!4A?");
+    }
+
+    #[test]
+    fn test_sixel_renderer_frame() {
+        let mut renderer = SixelRenderer::new(RenderConfig {
+            target_width: 4,
+            target_height: 3,
+            ..Default::default()
+        });
+        let image = DynamicImage::ImageRgb8(RgbImage::from_pixel(8, 8, Rgb([0, 0, 0])));
+        let frame = renderer.render_image(&image).unwrap();
+        assert_eq!((frame.width, frame.height), (4, 3));
+        assert_eq!(frame.cells.len(), 12);
+        let sixel = frame.sixel.unwrap();
+        assert!(sixel.starts_with(b"\x1bP"));
+        assert!(sixel.ends_with(b"\x1b\\"));
+    }
+}
@@ -0,0 +1,95 @@
+//! 再生中に自動で飛ばす区間（SponsorBlock のスポンサー・イントロなど）
+
+use std::time::Duration;
+
+/// これより短い区間は飛ばさない（移動のほうが目立つため）
+const MIN_SEGMENT_DURATION: Duration = Duration::from_secs(1);
+
+/// 飛ばす区間
+#[derive(Debug, Clone, PartialEq)]
+pub struct SkipSegment {
+    pub start: Duration,
+    pub end: Duration,
+    /// 区間の種類（"sponsor"、"intro" など）
+    pub category: String,
+}
+
+/// 開始時刻順に並んだ飛ばす区間
+#[derive(Debug, Clone, Default)]
+pub struct SkipSegments {
+    segments: Vec<SkipSegment>,
+}
+
+impl SkipSegments {
+    pub fn new(segments: Vec<SkipSegment>) -> Self {
+        let mut segments: Vec<SkipSegment> = segments
+            .into_iter()
+            .filter(|segment| segment.end.saturating_sub(segment.start) >= MIN_SEGMENT_DURATION)
+            .collect();
+        segments.sort_by_key(|segment| segment.start);
+        Self { segments }
+    }
+
+    pub fn len(&self) -> usize {
+        self.segments.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.segments.is_empty()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &SkipSegment> {
+        self.segments.iter()
+    }
+
+    /// 再生位置を含む区間（重なっている場合は最も遅く終わるもの）
+    pub fn segment_at(&self, position: Duration) -> Option<&SkipSegment> {
+        let started = self
+            .segments
+            .partition_point(|segment| segment.start <= position);
+        self.segments[..started]
+            .iter()
+            .filter(|segment| position < segment.end)
+            .max_by_key(|segment| segment.end)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn segment(start: u64, end: u64, category: &str) -> SkipSegment {
+        SkipSegment {
+            start: Duration::from_secs(start),
+            end: Duration::from_secs(end),
+            category: category.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_segment_at() {
+        let segments = SkipSegments::new(vec![
+            segment(60, 90, "sponsor"),
+            segment(0, 10, "intro"),
+            segment(80, 120, "sponsor"),
+            segment(200, 200, "sponsor"),
+        ]);
+        // 長さ 0 の区間は捨てる
+        assert_eq!(segments.len(), 3);
+
+        assert_eq!(
+            segments
+                .segment_at(Duration::from_secs(5))
+                .unwrap()
+                .category,
+            "intro"
+        );
+        assert_eq!(segments.segment_at(Duration::from_secs(10)), None);
+        assert_eq!(segments.segment_at(Duration::from_secs(30)), None);
+        // 重なった区間はまとめて飛ばす
+        assert_eq!(
+            segments.segment_at(Duration::from_secs(85)).unwrap().end,
+            Duration::from_secs(120)
+        );
+    }
+}
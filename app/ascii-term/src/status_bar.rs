@@ -0,0 +1,256 @@
+//! 再生位置の表示（経過時間・プログレスバー）と、画面下端のステータスバー
+
+use std::time::Duration;
+
+use crate::canvas::truncate;
+use crate::i18n::{Msg, tr, tr_args};
+use crate::player_state::PlayerState;
+
+/// ステータスバーに表示する再生状況
+#[derive(Debug, Clone, PartialEq)]
+pub struct PlaybackInfo {
+    pub elapsed: Duration,
+    /// ライブストリームでは None
+    pub duration: Option<Duration>,
+    pub state: PlayerState,
+    /// 音量（0.0〜1.0。音声がなければ None）
+    pub volume: Option<f32>,
+    pub muted: bool,
+    /// 使用中の文字マップの名前（文字を使わない出力では None）
+    pub char_map: Option<&'static str>,
+}
+
+/// m:ss 形式（1 時間以上は h:mm:ss）
+pub fn format_time(duration: Duration) -> String {
+    let total = duration.as_secs();
+    let (hours, minutes, seconds) = (total / 3600, (total / 60) % 60, total % 60);
+    if hours > 0 {
+        format!("{}:{:02}:{:02}", hours, minutes, seconds)
+    } else {
+        format!("{}:{:02}", minutes, seconds)
+    }
+}
+
+/// `progress_line` のバーの位置（行頭からの文字数）と幅。バーを描かない場合は None
+fn bar_span(elapsed: Duration, duration: Option<Duration>, width: usize) -> Option<(usize, usize)> {
+    let duration = duration.filter(|d| !d.is_zero())?;
+    let elapsed_len = format_time(elapsed).chars().count();
+    let total_len = format_time(duration).chars().count();
+    let fixed = 2 + elapsed_len + 1 + 1 + total_len;
+    let bar_width = width.saturating_sub(fixed);
+    (bar_width > 0).then_some((2 + elapsed_len + 1, bar_width))
+}
+
+/// 再生状態と位置を 1 行にまとめる
+///
+/// 長さが分かる場合は `▶ 1:23 ━━━━──── 4:56`、
+/// ライブストリームなど長さが分からない場合は経過時間のみ `▶ 1:23 ● LIVE` を表示する。
+/// 先頭の記号は再生状態（`PlayerState::icon`）
+pub fn progress_line(
+    elapsed: Duration,
+    duration: Option<Duration>,
+    state: PlayerState,
+    width: usize,
+) -> String {
+    let icon = state.icon();
+    let elapsed_text = format_time(elapsed);
+
+    let Some(duration) = duration.filter(|d| !d.is_zero()) else {
+        return format!("{} {}  ● LIVE", icon, elapsed_text);
+    };
+
+    let total_text = format_time(duration);
+    let Some((_, bar_width)) = bar_span(elapsed, Some(duration), width) else {
+        return format!("{} {} / {}", icon, elapsed_text, total_text);
+    };
+
+    let ratio = (elapsed.as_secs_f64() / duration.as_secs_f64()).clamp(0.0, 1.0);
+    let filled = (ratio * bar_width as f64).round() as usize;
+    format!(
+        "{} {} {}{} {}",
+        icon,
+        elapsed_text,
+        "━".repeat(filled),
+        "─".repeat(bar_width - filled),
+        total_text
+    )
+}
+
+/// 波形の高さを表す文字（低い順）
+const WAVEFORM_LEVELS: [char; 8] = ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+
+/// 区間ごとのピーク（0.0〜1.0）を `width` 文字の波形にする
+///
+/// 1 文字に複数の区間が入る場合は最大のピークをとり、足りない場合は同じ区間を繰り返す
+pub fn waveform_line(peaks: &[f32], width: usize) -> String {
+    if peaks.is_empty() {
+        return " ".repeat(width);
+    }
+    (0..width)
+        .map(|column| {
+            let start = column * peaks.len() / width;
+            let end = ((column + 1) * peaks.len() / width).max(start + 1);
+            let peak = peaks[start..end.min(peaks.len())]
+                .iter()
+                .fold(0.0f32, |max, &peak| max.max(peak));
+            let level = (peak.clamp(0.0, 1.0) * (WAVEFORM_LEVELS.len() - 1) as f32).round();
+            WAVEFORM_LEVELS[level as usize]
+        })
+        .collect()
+}
+
+/// ステータスバーの右側に並べる音量と文字マップ
+fn status_items(info: &PlaybackInfo) -> String {
+    let mut items = Vec::new();
+    if let Some(volume) = info.volume {
+        items.push(if info.muted {
+            tr(Msg::StatusMuted).to_string()
+        } else {
+            tr_args(Msg::StatusVolume, &[&(volume * 100.0).round()])
+        });
+    }
+    if let Some(char_map) = info.char_map {
+        items.push(char_map.to_string());
+    }
+    items.iter().map(|item| format!("  {}", item)).collect()
+}
+
+/// ステータスバーのうち再生位置に使う幅（前後の空白と右側の表示を除く）
+fn progress_width(info: &PlaybackInfo, width: usize) -> usize {
+    width.saturating_sub(status_items(info).chars().count() + 2)
+}
+
+/// ステータスバーの 1 行（`width` 文字ちょうど）
+///
+/// 左に再生位置、右に音量と文字マップを `▶ 1:23 ━━──── 4:56  Vol 100%  Standard` のように並べる
+pub fn status_line(info: &PlaybackInfo, width: usize) -> String {
+    let progress = progress_line(
+        info.elapsed,
+        info.duration,
+        info.state,
+        progress_width(info, width),
+    );
+    let line = truncate(&format!(" {}{} ", progress, status_items(info)), width);
+    format!("{:<width$}", line)
+}
+
+/// ステータスバーの `column` 列目をクリックしたときの移動先（バーの外なら None）
+pub fn status_line_seek(info: &PlaybackInfo, width: usize, column: usize) -> Option<Duration> {
+    let duration = info.duration.filter(|d| !d.is_zero())?;
+    let (offset, bar_width) = bar_span(info.elapsed, Some(duration), progress_width(info, width))?;
+    // 行頭の空白の分だけずれる
+    let position = column.checked_sub(1 + offset).filter(|&x| x < bar_width)?;
+    Some(duration.mul_f64((position as f64 + 0.5) / bar_width as f64))
+}
+
+/// 全幅の波形の `column` 列目をクリックしたときの移動先
+pub fn waveform_seek(duration: Duration, width: usize, column: usize) -> Option<Duration> {
+    (column < width).then(|| duration.mul_f64((column as f64 + 0.5) / width as f64))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_time() {
+        assert_eq!(format_time(Duration::from_secs(5)), "0:05");
+        assert_eq!(format_time(Duration::from_secs(754)), "12:34");
+        assert_eq!(format_time(Duration::from_secs(3723)), "1:02:03");
+    }
+
+    #[test]
+    fn test_progress_line_with_duration() {
+        let line = progress_line(
+            Duration::from_secs(30),
+            Some(Duration::from_secs(60)),
+            PlayerState::Playing,
+            20,
+        );
+        assert_eq!(line.chars().count(), 20);
+        assert!(line.starts_with("▶ 0:30 "));
+        assert!(line.ends_with(" 1:00"));
+        assert_eq!(line.matches('━').count(), 4);
+    }
+
+    #[test]
+    fn test_progress_line_without_duration_is_elapsed_only() {
+        let line = progress_line(Duration::from_secs(75), None, PlayerState::Paused, 40);
+        assert_eq!(line, "⏸ 1:15  ● LIVE");
+
+        let zero = progress_line(
+            Duration::from_secs(75),
+            Some(Duration::ZERO),
+            PlayerState::Playing,
+            40,
+        );
+        assert_eq!(zero, "▶ 1:15  ● LIVE");
+
+        let buffering = progress_line(Duration::ZERO, None, PlayerState::Buffering, 40);
+        assert_eq!(buffering, "◌ 0:00  ● LIVE");
+    }
+
+    #[test]
+    fn test_status_line() {
+        let mut info = PlaybackInfo {
+            elapsed: Duration::from_secs(30),
+            duration: Some(Duration::from_secs(60)),
+            state: PlayerState::Playing,
+            volume: Some(1.0),
+            muted: false,
+            char_map: Some("Standard"),
+        };
+        let line = status_line(&info, 50);
+        assert_eq!(line.chars().count(), 50);
+        assert!(line.starts_with(" ▶ 0:30 ━"));
+        let volume = tr_args(Msg::StatusVolume, &[&100]);
+        assert!(line.ends_with(&format!(" 1:00  {}  Standard ", volume)));
+
+        info.muted = true;
+        info.char_map = None;
+        let muted = format!(" 1:00  {} ", tr(Msg::StatusMuted));
+        assert!(status_line(&info, 50).ends_with(&muted));
+
+        // 狭い場合も幅を超えない
+        assert_eq!(status_line(&info, 8).chars().count(), 8);
+    }
+
+    #[test]
+    fn test_waveform_line() {
+        assert_eq!(waveform_line(&[0.0, 1.0, 0.5, 0.0], 4), "▁█▅▁");
+        // 2 区間ずつまとめて大きい方をとる
+        assert_eq!(waveform_line(&[0.0, 1.0, 0.5, 0.0], 2), "█▅");
+        // 区間より幅が広ければ繰り返す
+        assert_eq!(waveform_line(&[1.0, 0.0], 4), "██▁▁");
+        assert_eq!(waveform_line(&[], 3), "   ");
+    }
+
+    #[test]
+    fn test_seek_positions() {
+        let info = PlaybackInfo {
+            elapsed: Duration::from_secs(30),
+            duration: Some(Duration::from_secs(100)),
+            state: PlayerState::Playing,
+            volume: None,
+            muted: false,
+            char_map: None,
+        };
+        // " ▶ 0:30 " の後ろの 1 列目からバー
+        let line = status_line(&info, 40);
+        let start = line.chars().position(|ch| ch == '━').unwrap();
+        assert_eq!(start, 8);
+        assert_eq!(status_line_seek(&info, 40, start - 1), None);
+        let first = status_line_seek(&info, 40, start).unwrap();
+        assert!(first < Duration::from_secs(5));
+        let chars: Vec<char> = line.chars().collect();
+        let bar_end = chars.iter().rposition(|&ch| ch == '─').unwrap();
+        assert!(status_line_seek(&info, 40, bar_end).unwrap() > Duration::from_secs(95));
+        assert_eq!(status_line_seek(&info, 40, bar_end + 1), None);
+
+        assert_eq!(
+            waveform_seek(Duration::from_secs(100), 10, 4),
+            Some(Duration::from_secs(45))
+        );
+        assert_eq!(waveform_seek(Duration::from_secs(100), 10, 10), None);
+    }
+}
@@ -0,0 +1,237 @@
+//! 字幕（WebVTT / SRT）の読み込みと、再生位置に応じた字幕の選択
+
+use std::time::Duration;
+
+/// これより短いキューは表示しない
+///
+/// YouTube の自動字幕は行を送るために 10ms 程度のキューを挟むため、
+/// そのまま表示するとちらつく
+const MIN_CUE_DURATION: Duration = Duration::from_millis(50);
+
+/// 1 つの字幕
+#[derive(Debug, Clone, PartialEq)]
+pub struct Cue {
+    pub start: Duration,
+    pub end: Duration,
+    pub text: String,
+}
+
+/// 開始時刻順に並んだ字幕
+#[derive(Debug, Clone, Default)]
+pub struct Subtitles {
+    cues: Vec<Cue>,
+}
+
+impl Subtitles {
+    /// WebVTT または SRT のテキストを読み込む（解釈できないブロックは読み飛ばす）
+    pub fn parse(text: &str) -> Self {
+        let mut cues = Vec::new();
+        let mut lines = text.lines();
+
+        while let Some(line) = lines.next() {
+            let Some((start, end)) = parse_timing(line) else {
+                continue;
+            };
+
+            // 空行までが字幕の本文（空白だけの行は本文の一部として読み捨てる）
+            let text = lines
+                .by_ref()
+                .take_while(|line| !line.is_empty())
+                .map(clean_line)
+                .filter(|line| !line.is_empty())
+                .collect::<Vec<_>>()
+                .join("\n");
+
+            if !text.is_empty() && end.saturating_sub(start) >= MIN_CUE_DURATION {
+                cues.push(Cue { start, end, text });
+            }
+        }
+
+        cues.sort_by_key(|cue| cue.start);
+        Self { cues }
+    }
+
+    /// コーデックで読み込んだ字幕（`--sub`、`--sub-track`）から作る
+    pub fn from_cues(cues: impl IntoIterator<Item = codec::subtitle::SubtitleCue>) -> Self {
+        let mut cues: Vec<Cue> = cues
+            .into_iter()
+            .filter(|cue| cue.end.saturating_sub(cue.start) >= MIN_CUE_DURATION)
+            .map(|cue| Cue {
+                start: cue.start,
+                end: cue.end,
+                text: cue.text,
+            })
+            .collect();
+        cues.sort_by_key(|cue| cue.start);
+        Self { cues }
+    }
+
+    pub fn len(&self) -> usize {
+        self.cues.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.cues.is_empty()
+    }
+
+    /// 再生位置で表示する字幕（重なっている場合は後から始まったもの）
+    pub fn text_at(&self, position: Duration) -> Option<&str> {
+        let started = self.cues.partition_point(|cue| cue.start <= position);
+        self.cues[..started]
+            .iter()
+            .rev()
+            .find(|cue| position < cue.end)
+            .map(|cue| cue.text.as_str())
+    }
+}
+
+/// `00:01:02.345 --> 00:01:04.000 align:start` の形式のタイミング行を読む
+fn parse_timing(line: &str) -> Option<(Duration, Duration)> {
+    let (start, rest) = line.split_once("-->")?;
+    // 終了時刻の後ろには WebVTT のキュー設定が続くことがある
+    let end = rest.split_whitespace().next()?;
+    Some((parse_timestamp(start.trim())?, parse_timestamp(end)?))
+}
+
+/// `HH:MM:SS.mmm`、`MM:SS.mmm`（WebVTT）または `HH:MM:SS,mmm`（SRT）
+fn parse_timestamp(text: &str) -> Option<Duration> {
+    let text = text.replace(',', ".");
+    let (clock, millis) = text.split_once('.').unwrap_or((&text, "0"));
+
+    let mut seconds = 0u64;
+    let parts: Vec<&str> = clock.split(':').collect();
+    if !(2..=3).contains(&parts.len()) {
+        return None;
+    }
+    for part in parts {
+        seconds = seconds * 60 + part.parse::<u64>().ok()?;
+    }
+
+    // ミリ秒は 3 桁に揃える（".5" は 500ms）
+    let digits: String = millis.chars().chain("000".chars()).take(3).collect();
+    let millis = digits.parse::<u64>().ok()?;
+    Some(Duration::from_secs(seconds) + Duration::from_millis(millis))
+}
+
+/// 字幕の 1 行からタグ（`<c>`、`<00:00:01.000>` など）を取り除き、文字参照を戻す
+fn clean_line(line: &str) -> String {
+    let mut text = String::with_capacity(line.len());
+    let mut in_tag = false;
+    for ch in line.chars() {
+        match ch {
+            '<' => in_tag = true,
+            '>' if in_tag => in_tag = false,
+            _ if !in_tag => text.push(ch),
+            _ => {}
+        }
+    }
+
+    text.replace("&nbsp;", " ")
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&amp;", "&")
+        .trim()
+        .to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_webvtt() {
+        let subtitles = Subtitles::parse(
+            "WEBVTT\n\
+             Kind: captions\n\
+             Language: en\n\
+             \n\
+             00:00.500 --> 00:02.000\n\
+             Hello &amp; <i>welcome</i>\n\
+             \n\
+             00:00:02.000 --> 00:00:04.250 align:start position:0%\n\
+             second line\n\
+             continues here\n",
+        );
+        assert_eq!(subtitles.len(), 2);
+        assert_eq!(subtitles.text_at(Duration::from_millis(400)), None);
+        assert_eq!(
+            subtitles.text_at(Duration::from_secs(1)),
+            Some("Hello & welcome")
+        );
+        assert_eq!(
+            subtitles.text_at(Duration::from_secs(2)),
+            Some("second line\ncontinues here")
+        );
+        assert_eq!(subtitles.text_at(Duration::from_secs(5)), None);
+    }
+
+    #[test]
+    fn test_parse_srt() {
+        let subtitles = Subtitles::parse(
+            "1\r\n00:00:01,000 --> 00:00:03,500\r\nFirst\r\n\r\n\
+             2\r\n00:01:00,000 --> 00:01:02,000\r\nSecond\r\n",
+        );
+        assert_eq!(subtitles.len(), 2);
+        assert_eq!(subtitles.text_at(Duration::from_secs(3)), Some("First"));
+        assert_eq!(subtitles.text_at(Duration::from_secs(61)), Some("Second"));
+    }
+
+    #[test]
+    fn test_youtube_auto_captions() {
+        // 単語ごとのタイミングタグと、行送り用の短いキューを含む自動字幕
+        let subtitles = Subtitles::parse(
+            "WEBVTT\n\n\
+             00:00:00.160 --> 00:00:02.869 align:start position:0%\n \n\
+             hello<00:00:00.480><c> world</c>\n\n\
+             00:00:02.869 --> 00:00:02.879 align:start position:0%\n\
+             hello world\n \n\n\
+             00:00:02.879 --> 00:00:05.000 align:start position:0%\n\
+             hello world\n\
+             next<00:00:03.000><c> line</c>\n",
+        );
+        assert_eq!(subtitles.len(), 2);
+        assert_eq!(
+            subtitles.text_at(Duration::from_secs(1)),
+            Some("hello world")
+        );
+        assert_eq!(
+            subtitles.text_at(Duration::from_secs(3)),
+            Some("hello world\nnext line")
+        );
+    }
+
+    #[test]
+    fn test_from_cues() {
+        let cue = |start: u64, end: u64, text: &str| codec::subtitle::SubtitleCue {
+            start: Duration::from_millis(start),
+            end: Duration::from_millis(end),
+            text: text.to_string(),
+        };
+        let subtitles = Subtitles::from_cues([
+            cue(2000, 3000, "second"),
+            cue(0, 10, "flash"),
+            cue(500, 1500, "first"),
+        ]);
+        assert_eq!(subtitles.len(), 2);
+        assert_eq!(subtitles.text_at(Duration::from_millis(5)), None);
+        assert_eq!(subtitles.text_at(Duration::from_secs(1)), Some("first"));
+        assert_eq!(
+            subtitles.text_at(Duration::from_millis(2500)),
+            Some("second")
+        );
+    }
+
+    #[test]
+    fn test_parse_timestamp() {
+        assert_eq!(
+            parse_timestamp("01:02:03.004"),
+            Some(Duration::from_millis(3_723_004))
+        );
+        assert_eq!(
+            parse_timestamp("02:03.5"),
+            Some(Duration::from_millis(123_500))
+        );
+        assert_eq!(parse_timestamp("not a time"), None);
+        assert_eq!(parse_timestamp("1:2:3:4.000"), None);
+    }
+}
@@ -0,0 +1,518 @@
+//! キー割り当て
+//!
+//! キー入力の処理とヘルプの表示の両方をこの表から作るので、
+//! 割り当てを変えればヘルプも変わる
+
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+use anyhow::{Context, Result};
+use crossterm::event::{KeyCode, KeyModifiers};
+
+use crate::canvas::pad;
+use crate::i18n::{Msg, tr};
+
+/// ←/→ と ↓/↑ で移動する時間
+const SHORT_SEEK: Duration = Duration::from_secs(5);
+const LONG_SEEK: Duration = Duration::from_secs(30);
+
+/// ヘルプでキーの表記に使う幅（文字数）
+const LABEL_WIDTH: usize = 12;
+
+/// キーで行う操作
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) enum Action {
+    Quit,
+    TogglePlayPause,
+    ToggleMute,
+    /// 数字が続けばブックマークを設定、続かなければミュート切り替え
+    SetBookmarkPrefix,
+    /// 数字が続けばブックマークへ移動
+    JumpToBookmarkPrefix,
+    NextChapter,
+    PreviousChapter,
+    StepForward,
+    StepBackward,
+    MarkAbLoop,
+    SeekForward(Duration),
+    SeekBackward(Duration),
+    ToggleGrayscale,
+    ToggleScopes,
+    SetCharMap(u8),
+    CycleColorFilter,
+    CycleAudioTrack,
+    ToggleSubtitles,
+    ToggleQrCode,
+    ToggleStatusBar,
+    ToggleHelp,
+}
+
+/// 1 つのキー。`modifiers` が空でなければ、その修飾キーも押されている必要がある
+#[derive(Debug, Clone, Copy)]
+pub(super) struct Key {
+    code: KeyCode,
+    modifiers: KeyModifiers,
+    action: Action,
+}
+
+const fn key(code: KeyCode, action: Action) -> Key {
+    Key {
+        code,
+        modifiers: KeyModifiers::NONE,
+        action,
+    }
+}
+
+const fn char_key(ch: char, action: Action) -> Key {
+    key(KeyCode::Char(ch), action)
+}
+
+/// ヘルプの 1 行と、それに対応するキー
+#[derive(Debug)]
+pub(super) struct Binding {
+    /// ヘルプでのキーの表記
+    label: &'static str,
+    description: Msg,
+    keys: &'static [Key],
+}
+
+pub(super) const KEYMAP: &[Binding] = &[
+    Binding {
+        label: "Space",
+        description: Msg::KeyPlayPause,
+        keys: &[char_key(' ', Action::TogglePlayPause)],
+    },
+    Binding {
+        label: "Q, Esc",
+        description: Msg::KeyQuit,
+        keys: &[
+            char_key('q', Action::Quit),
+            char_key('Q', Action::Quit),
+            key(KeyCode::Esc, Action::Quit),
+            Key {
+                code: KeyCode::Char('c'),
+                modifiers: KeyModifiers::CONTROL,
+                action: Action::Quit,
+            },
+        ],
+    },
+    Binding {
+        label: "M",
+        description: Msg::KeyMute,
+        keys: &[char_key('M', Action::ToggleMute)],
+    },
+    Binding {
+        label: "G",
+        description: Msg::KeyGrayscale,
+        keys: &[
+            char_key('g', Action::ToggleGrayscale),
+            char_key('G', Action::ToggleGrayscale),
+        ],
+    },
+    Binding {
+        label: "V",
+        description: Msg::KeyColorFilter,
+        keys: &[
+            char_key('v', Action::CycleColorFilter),
+            char_key('V', Action::CycleColorFilter),
+        ],
+    },
+    Binding {
+        label: "#",
+        description: Msg::KeyAudioTrack,
+        keys: &[char_key('#', Action::CycleAudioTrack)],
+    },
+    Binding {
+        label: "S",
+        description: Msg::KeySubtitles,
+        keys: &[
+            char_key('s', Action::ToggleSubtitles),
+            char_key('S', Action::ToggleSubtitles),
+        ],
+    },
+    Binding {
+        label: "W",
+        description: Msg::KeyScopes,
+        keys: &[
+            char_key('w', Action::ToggleScopes),
+            char_key('W', Action::ToggleScopes),
+        ],
+    },
+    Binding {
+        label: "0-9",
+        description: Msg::KeyCharMap,
+        keys: &[
+            char_key('0', Action::SetCharMap(0)),
+            char_key('1', Action::SetCharMap(1)),
+            char_key('2', Action::SetCharMap(2)),
+            char_key('3', Action::SetCharMap(3)),
+            char_key('4', Action::SetCharMap(4)),
+            char_key('5', Action::SetCharMap(5)),
+            char_key('6', Action::SetCharMap(6)),
+            char_key('7', Action::SetCharMap(7)),
+            char_key('8', Action::SetCharMap(8)),
+            char_key('9', Action::SetCharMap(9)),
+        ],
+    },
+    Binding {
+        label: "m 0-9",
+        description: Msg::KeySetBookmark,
+        keys: &[char_key('m', Action::SetBookmarkPrefix)],
+    },
+    Binding {
+        label: "' 0-9",
+        description: Msg::KeyJumpToBookmark,
+        keys: &[char_key('\'', Action::JumpToBookmarkPrefix)],
+    },
+    Binding {
+        label: "←/→",
+        description: Msg::KeyShortSeek,
+        keys: &[
+            key(KeyCode::Left, Action::SeekBackward(SHORT_SEEK)),
+            key(KeyCode::Right, Action::SeekForward(SHORT_SEEK)),
+        ],
+    },
+    Binding {
+        label: "↓/↑",
+        description: Msg::KeyLongSeek,
+        keys: &[
+            key(KeyCode::Down, Action::SeekBackward(LONG_SEEK)),
+            key(KeyCode::Up, Action::SeekForward(LONG_SEEK)),
+        ],
+    },
+    Binding {
+        label: "PgUp/PgDn",
+        description: Msg::KeyChapter,
+        keys: &[
+            key(KeyCode::PageUp, Action::PreviousChapter),
+            key(KeyCode::PageDown, Action::NextChapter),
+        ],
+    },
+    Binding {
+        label: ", / .",
+        description: Msg::KeyFrameStep,
+        keys: &[
+            char_key(',', Action::StepBackward),
+            char_key('.', Action::StepForward),
+        ],
+    },
+    Binding {
+        label: "A",
+        description: Msg::KeyAbLoop,
+        keys: &[
+            char_key('a', Action::MarkAbLoop),
+            char_key('A', Action::MarkAbLoop),
+        ],
+    },
+    Binding {
+        label: "U",
+        description: Msg::KeyQrCode,
+        keys: &[
+            char_key('u', Action::ToggleQrCode),
+            char_key('U', Action::ToggleQrCode),
+        ],
+    },
+    Binding {
+        label: "I",
+        description: Msg::KeyStatusBar,
+        keys: &[
+            char_key('i', Action::ToggleStatusBar),
+            char_key('I', Action::ToggleStatusBar),
+        ],
+    },
+    Binding {
+        label: "H, ?",
+        description: Msg::KeyHelp,
+        keys: &[
+            char_key('h', Action::ToggleHelp),
+            char_key('H', Action::ToggleHelp),
+            char_key('?', Action::ToggleHelp),
+        ],
+    },
+];
+
+/// 設定ファイルの `[keys]` で使う操作の名前（ほかに `char-map-0`〜`char-map-9`）
+const ACTION_NAMES: &[(&str, Action)] = &[
+    ("quit", Action::Quit),
+    ("play-pause", Action::TogglePlayPause),
+    ("mute", Action::ToggleMute),
+    ("set-bookmark", Action::SetBookmarkPrefix),
+    ("jump-to-bookmark", Action::JumpToBookmarkPrefix),
+    ("next-chapter", Action::NextChapter),
+    ("previous-chapter", Action::PreviousChapter),
+    ("step-forward", Action::StepForward),
+    ("step-backward", Action::StepBackward),
+    ("ab-loop", Action::MarkAbLoop),
+    ("seek-forward", Action::SeekForward(SHORT_SEEK)),
+    ("seek-backward", Action::SeekBackward(SHORT_SEEK)),
+    ("seek-forward-long", Action::SeekForward(LONG_SEEK)),
+    ("seek-backward-long", Action::SeekBackward(LONG_SEEK)),
+    ("grayscale", Action::ToggleGrayscale),
+    ("scopes", Action::ToggleScopes),
+    ("color-filter", Action::CycleColorFilter),
+    ("audio-track", Action::CycleAudioTrack),
+    ("subtitles", Action::ToggleSubtitles),
+    ("qr-code", Action::ToggleQrCode),
+    ("status-bar", Action::ToggleStatusBar),
+    ("help", Action::ToggleHelp),
+];
+
+/// 設定ファイルで追加したキー割り当て
+///
+/// 既定の割り当てより優先し、ヘルプでは既定のキーの後ろに並べる
+#[derive(Debug, Clone, Default)]
+pub struct KeyBindings {
+    /// キーと、設定ファイルでの表記
+    keys: Vec<(Key, String)>,
+}
+
+impl KeyBindings {
+    /// 操作の名前ごとのキーの表記（`k`、`Ctrl+l`、`Space`、`F1` など）から作る
+    pub fn parse(keys: &BTreeMap<String, Vec<String>>) -> Result<Self> {
+        let mut bindings = Self::default();
+        for (name, texts) in keys {
+            let action = parse_action(name)?;
+            for text in texts {
+                let (code, modifiers) =
+                    parse_key(text).with_context(|| format!("Invalid key for {}", name))?;
+                let key = Key {
+                    code,
+                    modifiers,
+                    action,
+                };
+                bindings.keys.push((key, text.clone()));
+            }
+        }
+        Ok(bindings)
+    }
+}
+
+/// 操作の名前
+fn parse_action(name: &str) -> Result<Action> {
+    if let Some(index) = name.strip_prefix("char-map-") {
+        return match index.parse::<u8>() {
+            Ok(index) if index <= 9 => Ok(Action::SetCharMap(index)),
+            _ => anyhow::bail!("Unknown action: {}", name),
+        };
+    }
+    ACTION_NAMES
+        .iter()
+        .find(|(action_name, _)| *action_name == name)
+        .map(|&(_, action)| action)
+        .ok_or_else(|| anyhow::anyhow!("Unknown action: {}", name))
+}
+
+/// キーの表記（`+` で区切った修飾キーとキー。大文字・小文字は名前では区別しない）
+fn parse_key(text: &str) -> Result<(KeyCode, KeyModifiers)> {
+    let mut parts: Vec<&str> = text.split('+').collect();
+    // `+` そのものや `Ctrl++` のキー
+    if text.ends_with("++") || text == "+" {
+        parts.truncate(parts.len() - 2);
+        parts.push("+");
+    }
+    let Some((name, modifier_names)) = parts.split_last() else {
+        anyhow::bail!("Empty key");
+    };
+
+    let mut modifiers = KeyModifiers::NONE;
+    for modifier in modifier_names {
+        modifiers |= match modifier.to_ascii_lowercase().as_str() {
+            "ctrl" | "control" => KeyModifiers::CONTROL,
+            "alt" => KeyModifiers::ALT,
+            "shift" => KeyModifiers::SHIFT,
+            _ => anyhow::bail!("Unknown modifier in {:?}", text),
+        };
+    }
+
+    let mut chars = name.chars();
+    let code = match (chars.next(), chars.next()) {
+        (Some(ch), None) => KeyCode::Char(ch),
+        _ => match name.to_ascii_lowercase().as_str() {
+            "space" => KeyCode::Char(' '),
+            "esc" | "escape" => KeyCode::Esc,
+            "enter" | "return" => KeyCode::Enter,
+            "tab" => KeyCode::Tab,
+            "backspace" => KeyCode::Backspace,
+            "delete" | "del" => KeyCode::Delete,
+            "insert" => KeyCode::Insert,
+            "left" => KeyCode::Left,
+            "right" => KeyCode::Right,
+            "up" => KeyCode::Up,
+            "down" => KeyCode::Down,
+            "home" => KeyCode::Home,
+            "end" => KeyCode::End,
+            "pageup" | "pgup" => KeyCode::PageUp,
+            "pagedown" | "pgdn" => KeyCode::PageDown,
+            lower => match lower.strip_prefix('f').and_then(|n| n.parse::<u8>().ok()) {
+                Some(n @ 1..=12) => KeyCode::F(n),
+                _ => anyhow::bail!("Unknown key: {:?}", text),
+            },
+        },
+    };
+    Ok((code, modifiers))
+}
+
+/// 押されたキーに割り当てられた操作（設定ファイルの割り当てを先に探す）
+pub(super) fn action_for(
+    bindings: &KeyBindings,
+    code: KeyCode,
+    modifiers: KeyModifiers,
+) -> Option<Action> {
+    bindings
+        .keys
+        .iter()
+        .map(|(key, _)| key)
+        .chain(KEYMAP.iter().flat_map(|binding| binding.keys))
+        .find(|key| key.code == code && modifiers.contains(key.modifiers))
+        .map(|key| key.action)
+}
+
+/// ヘルプに表示する行（見出しと、割り当てごとに 1 行）
+pub(super) fn help_lines(bindings: &KeyBindings) -> Vec<String> {
+    std::iter::once(tr(Msg::HelpTitle).to_string())
+        .chain(KEYMAP.iter().map(|binding| {
+            let mut label = binding.label.to_string();
+            for (key, text) in &bindings.keys {
+                if binding
+                    .keys
+                    .iter()
+                    .any(|default| default.action == key.action)
+                {
+                    label.push_str(", ");
+                    label.push_str(text);
+                }
+            }
+            format!(
+                "{} {}",
+                pad(&label, LABEL_WIDTH - 1),
+                tr(binding.description)
+            )
+        }))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_action_for() {
+        let none = KeyBindings::default();
+        assert_eq!(
+            action_for(&none, KeyCode::Char(' '), KeyModifiers::NONE),
+            Some(Action::TogglePlayPause)
+        );
+        assert_eq!(
+            action_for(&none, KeyCode::Char('Q'), KeyModifiers::SHIFT),
+            Some(Action::Quit)
+        );
+        assert_eq!(
+            action_for(&none, KeyCode::Char('c'), KeyModifiers::CONTROL),
+            Some(Action::Quit)
+        );
+        assert_eq!(
+            action_for(&none, KeyCode::Char('c'), KeyModifiers::NONE),
+            None
+        );
+        assert_eq!(
+            action_for(&none, KeyCode::Char('7'), KeyModifiers::NONE),
+            Some(Action::SetCharMap(7))
+        );
+        // `m` は数字を待ち、続かなければミュートを切り替える
+        assert_eq!(
+            action_for(&none, KeyCode::Char('m'), KeyModifiers::NONE),
+            Some(Action::SetBookmarkPrefix)
+        );
+        assert_eq!(
+            action_for(&none, KeyCode::Char('M'), KeyModifiers::NONE),
+            Some(Action::ToggleMute)
+        );
+        assert_eq!(
+            action_for(&none, KeyCode::Up, KeyModifiers::NONE),
+            Some(Action::SeekForward(LONG_SEEK))
+        );
+        assert_eq!(
+            action_for(&none, KeyCode::Char('.'), KeyModifiers::NONE),
+            Some(Action::StepForward)
+        );
+        assert_eq!(
+            action_for(&none, KeyCode::Char('a'), KeyModifiers::NONE),
+            Some(Action::MarkAbLoop)
+        );
+    }
+
+    #[test]
+    fn test_keys_are_unique() {
+        let keys: Vec<(KeyCode, KeyModifiers)> = KEYMAP
+            .iter()
+            .flat_map(|binding| binding.keys)
+            .map(|key| (key.code, key.modifiers))
+            .collect();
+        for (i, key) in keys.iter().enumerate() {
+            assert!(!keys[..i].contains(key), "{:?} is bound twice", key);
+        }
+    }
+
+    #[test]
+    fn test_help_lists_every_binding() {
+        let lines = help_lines(&KeyBindings::default());
+        assert_eq!(lines.len(), KEYMAP.len() + 1);
+        assert!(lines[1].starts_with("Space       "));
+    }
+
+    #[test]
+    fn test_parse_key() {
+        assert_eq!(
+            parse_key("k").unwrap(),
+            (KeyCode::Char('k'), KeyModifiers::NONE)
+        );
+        assert_eq!(
+            parse_key("Ctrl+Alt+l").unwrap(),
+            (
+                KeyCode::Char('l'),
+                KeyModifiers::CONTROL | KeyModifiers::ALT
+            )
+        );
+        assert_eq!(
+            parse_key("space").unwrap(),
+            (KeyCode::Char(' '), KeyModifiers::NONE)
+        );
+        assert_eq!(
+            parse_key("Ctrl++").unwrap(),
+            (KeyCode::Char('+'), KeyModifiers::CONTROL)
+        );
+        assert_eq!(parse_key("F5").unwrap().0, KeyCode::F(5));
+        assert!(parse_key("F13").is_err());
+        assert!(parse_key("Hyper+k").is_err());
+        assert!(parse_key("").is_err());
+    }
+
+    #[test]
+    fn test_custom_bindings() {
+        let keys = BTreeMap::from([
+            ("play-pause".to_string(), vec!["k".to_string()]),
+            ("seek-forward".to_string(), vec!["l".to_string()]),
+            ("char-map-3".to_string(), vec!["Ctrl+3".to_string()]),
+        ]);
+        let bindings = KeyBindings::parse(&keys).unwrap();
+        assert_eq!(
+            action_for(&bindings, KeyCode::Char('k'), KeyModifiers::NONE),
+            Some(Action::TogglePlayPause)
+        );
+        assert_eq!(
+            action_for(&bindings, KeyCode::Char('3'), KeyModifiers::CONTROL),
+            Some(Action::SetCharMap(3))
+        );
+        // 既定の割り当ても残る
+        assert_eq!(
+            action_for(&bindings, KeyCode::Char(' '), KeyModifiers::NONE),
+            Some(Action::TogglePlayPause)
+        );
+        let lines = help_lines(&bindings);
+        assert!(lines[1].starts_with("Space, k    "));
+        assert!(lines.iter().any(|line| line.starts_with("←/→, l ")));
+
+        let unknown = BTreeMap::from([("rewind".to_string(), vec!["r".to_string()])]);
+        assert!(KeyBindings::parse(&unknown).is_err());
+    }
+}
@@ -1,29 +1,148 @@
 //! ターミナルのライフサイクルとユーザー入力処理
 
 use std::io::{Write, stdout};
-use std::time::Duration;
+use std::time::{Duration, Instant};
 
 use anyhow::Result;
 use crossbeam_channel::{Receiver, Sender};
 use crossterm::{
-    cursor::{Hide, MoveTo, Show},
-    event::{self, Event, KeyCode, KeyEvent, KeyModifiers},
+    cursor::{self, Hide, MoveTo, Show},
+    event::{
+        self, DisableBracketedPaste, DisableFocusChange, DisableMouseCapture, EnableBracketedPaste,
+        EnableFocusChange, EnableMouseCapture, Event, KeyCode, KeyEvent, MouseButton, MouseEvent,
+        MouseEventKind,
+    },
     execute,
-    style::{Print, ResetColor},
+    style::ResetColor,
     terminal::{self, Clear, ClearType, EnterAlternateScreen, LeaveAlternateScreen, SetTitle},
 };
 
+use crate::ansi::ColorMode;
+use crate::emit::Emitter;
+use crate::frame_pipe::FramePipe;
 use crate::player::PlayerCommand;
+use crate::raw_video::RawVideoServer;
 use crate::renderer::RenderedFrame;
+use crate::status_bar::{self, PlaybackInfo};
+use crate::widgets::Widgets;
 
+mod keymap;
 mod output;
+mod title;
+
+use keymap::Action;
+
+pub use keymap::KeyBindings;
+pub use title::WindowTitle;
+
+/// プレイヤーからターミナルへの表示更新
+#[derive(Debug, Clone)]
+pub enum TerminalUpdate {
+    /// ウィンドウタイトルを変更する
+    Title(WindowTitle),
+    /// 画面上部に一定時間メッセージを重ねて表示する（OSD）
+    Osd(String),
+    /// QR コードを画面中央に重ねて表示する（None で非表示）
+    QrCode(Option<Vec<String>>),
+    /// 字幕を画面下部に重ねて表示する（None で非表示）
+    Subtitle(Option<String>),
+    /// ステータスバーの再生状況
+    Status(PlaybackInfo),
+    /// ステータスバーの下に描く音声のピーク波形（None で非表示）
+    Waveform(Option<Vec<f32>>),
+}
+
+/// OSD メッセージの表示時間
+const OSD_DURATION: Duration = Duration::from_secs(3);
+
+/// 映像を表示できる最小の列数・行数。これより小さいと案内だけを表示する
+pub const MIN_WIDTH: u16 = 20;
+pub const MIN_HEIGHT: u16 = 10;
+
+/// 時計・ティッカーを描き直す間隔
+const WIDGET_INTERVAL: Duration = Duration::from_millis(100);
+
+/// ステータスバーを描き直す間隔（フレームが止まっていても経過時間を進める）
+const STATUS_BAR_INTERVAL: Duration = Duration::from_millis(500);
+
+/// マウスホイール 1 段で変える音量
+const VOLUME_STEP: f32 = 0.05;
+
+/// `m` / `'` に続く数字を待つ時間
+const KEY_PREFIX_TIMEOUT: Duration = Duration::from_millis(800);
+
+/// 数字を続けて入力するキー
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum KeyPrefix {
+    /// `m`: ブックマークを設定（数字が続かなければミュート切り替え）
+    SetBookmark,
+    /// `'`: ブックマークへ移動
+    JumpToBookmark,
+}
+
+/// タイトル未設定時のウィンドウタイトル
+const DEFAULT_TITLE: &str = "ascii-term - Ascii Rendered Media Player";
+
+/// `--inline` で描画する、プロンプトの下の領域
+#[derive(Debug, Clone, Copy)]
+struct InlineArea {
+    /// 行数
+    height: u16,
+    /// 先頭の行（初期化時に空けた位置）
+    top: u16,
+}
 
 /// ターミナル表示とユーザー入力を管理
 pub struct Terminal {
     command_tx: Sender<PlayerCommand>,
     frame_rx: Receiver<RenderedFrame>,
+    update_rx: Receiver<TerminalUpdate>,
     grayscale_mode: bool,
+    /// 出力する色の種類（グレースケールのときは色なし）
+    color_mode: ColorMode,
+    /// ヒストグラム・パレードを表示するか
+    scopes_visible: bool,
+    /// 行をカーソル移動ではなく改行で区切って出力する
+    newlines: bool,
     last_frame: Option<RenderedFrame>,
+    /// 画面にそのまま描かれているフレーム。次のフレームは変わったセルだけを描く
+    /// （None なら次は全体を描く）
+    screen: Option<RenderedFrame>,
+    /// 表示中の OSD メッセージと表示期限
+    osd: Option<(String, Instant)>,
+    /// キー割り当てのヘルプを重ねて表示するか
+    help_visible: bool,
+    /// 画面下端にステータスバーを表示するか
+    status_visible: bool,
+    /// 最後に受け取った再生状況と、受け取った時刻
+    status: Option<(PlaybackInfo, Instant)>,
+    /// 最後にステータスバーを描いた時刻
+    status_drawn: Instant,
+    /// 音声のピーク波形（解析が終わるまでは None）
+    waveform: Option<Vec<f32>>,
+    /// 表示中の QR コード
+    qr_code: Option<Vec<String>>,
+    /// 表示中の字幕
+    subtitle: Option<String>,
+    /// 入力途中のキーと、数字を待つ期限
+    key_prefix: Option<(KeyPrefix, Instant)>,
+    /// ターミナルに描画せず、フレームを書き出す出力先
+    emitter: Option<Emitter>,
+    /// 時計・ティッカーのオーバーレイと、最後に描いた時刻
+    widgets: Option<(Widgets, Instant)>,
+    /// 表示したフレームを送る外部コマンド
+    frame_pipe: Option<FramePipe>,
+    raw_video: Option<RawVideoServer>,
+    /// 代替画面を使わず、プロンプトの下に描く領域
+    inline: Option<InlineArea>,
+    /// ターミナルのフォーカスが外れたら一時停止する
+    pause_on_blur: bool,
+    /// フォーカスが外れて一時停止した（戻ったら再開する）
+    paused_on_blur: bool,
+    /// ターミナルを初期化済みで、終了時に元へ戻す必要があるか
+    initialized: bool,
+    /// 設定ファイルで追加したキー割り当て
+    key_bindings: KeyBindings,
 }
 
 impl Terminal {
@@ -31,20 +150,103 @@ impl Terminal {
     pub fn new(
         command_tx: Sender<PlayerCommand>,
         frame_rx: Receiver<RenderedFrame>,
+        update_rx: Receiver<TerminalUpdate>,
         grayscale_mode: bool,
+        newlines: bool,
     ) -> Result<Self> {
         Ok(Self {
             command_tx,
             frame_rx,
+            update_rx,
             grayscale_mode,
+            color_mode: ColorMode::Truecolor,
+            scopes_visible: false,
+            newlines,
             last_frame: None,
+            screen: None,
+            osd: None,
+            help_visible: false,
+            status_visible: false,
+            status: None,
+            status_drawn: Instant::now(),
+            waveform: None,
+            qr_code: None,
+            subtitle: None,
+            key_prefix: None,
+            emitter: None,
+            widgets: None,
+            frame_pipe: None,
+            raw_video: None,
+            inline: None,
+            pause_on_blur: false,
+            paused_on_blur: false,
+            initialized: false,
+            key_bindings: KeyBindings::default(),
         })
     }
 
+    /// 描画の代わりにフレームを書き出すようにする
+    pub fn with_emitter(mut self, emitter: Emitter) -> Self {
+        self.emitter = Some(emitter);
+        self
+    }
+
+    /// 代替画面を使わず、プロンプトの下の `height` 行に描く
+    ///
+    /// 終了後も最後のフレームがスクロールバックに残る。重ねて表示する OSD や
+    /// ステータスバーなどは描かない
+    pub fn with_inline(mut self, height: u16) -> Self {
+        self.inline = Some(InlineArea { height, top: 0 });
+        self
+    }
+
+    /// フォーカスが外れたら一時停止し、戻ったら再開する
+    pub fn with_pause_on_blur(mut self) -> Self {
+        self.pause_on_blur = true;
+        self
+    }
+
+    /// 既定のキー割り当てに加えて使うキーを設定する
+    pub fn with_key_bindings(mut self, key_bindings: KeyBindings) -> Self {
+        self.key_bindings = key_bindings;
+        self
+    }
+
+    /// 出力する色の種類を設定する
+    pub fn with_color_mode(mut self, color_mode: ColorMode) -> Self {
+        self.color_mode = color_mode;
+        self
+    }
+
+    /// 表示したフレームを外部コマンドにも送る
+    pub fn with_frame_pipe(mut self, frame_pipe: FramePipe) -> Self {
+        self.frame_pipe = Some(frame_pipe);
+        self
+    }
+
+    /// 表示したフレームを rawvideo としても配信する
+    pub fn with_raw_video(mut self, raw_video: RawVideoServer) -> Self {
+        self.raw_video = Some(raw_video);
+        self
+    }
+
+    /// 時計・ティッカーを重ねて表示する
+    pub fn with_widgets(mut self, widgets: Widgets) -> Self {
+        self.widgets = Some((widgets, Instant::now()));
+        self
+    }
+
     /// ターミナルの実行を開始
     pub async fn run(mut self) -> Result<()> {
+        if let Some(emitter) = self.emitter.take() {
+            return self.run_emitter(emitter).await;
+        }
+
         // ターミナルの初期化
         self.init_terminal()?;
+        if let Some((widgets, _)) = &self.widgets {
+            widgets.spawn_refresh();
+        }
 
         // メインループ
         loop {
@@ -56,8 +258,29 @@ impl Terminal {
             // フレームの受信と描画
             if let Ok(frame) = self.frame_rx.try_recv() {
                 self.display_frame(&frame)?;
+                if let Some(frame_pipe) = &self.frame_pipe {
+                    frame_pipe.send(&frame);
+                }
+                if let Some(raw_video) = &self.raw_video {
+                    raw_video.send(&frame);
+                }
                 self.last_frame = Some(frame);
             }
+
+            self.expire_key_prefix()?;
+            self.apply_updates()?;
+
+            // フレームが止まっていても時計とティッカーは動かす
+            if self
+                .widgets
+                .as_ref()
+                .is_some_and(|(_, drawn)| drawn.elapsed() >= WIDGET_INTERVAL)
+            {
+                self.draw_widgets()?;
+            }
+            if self.status_visible && self.status_drawn.elapsed() >= STATUS_BAR_INTERVAL {
+                self.draw_status_bar()?;
+            }
         }
 
         // クリーンアップ
@@ -65,38 +288,203 @@ impl Terminal {
         Ok(())
     }
 
+    /// 受け取ったフレームを書き出し続ける（ターミナルの操作やキー入力は行わない）
+    async fn run_emitter(self, mut emitter: Emitter) -> Result<()> {
+        loop {
+            match self.frame_rx.try_recv() {
+                Ok(frame) => emitter.emit(&frame)?,
+                Err(crossbeam_channel::TryRecvError::Empty) => {
+                    tokio::time::sleep(Duration::from_millis(5)).await
+                }
+                Err(crossbeam_channel::TryRecvError::Disconnected) => break,
+            }
+            // OSD やタイトルは表示先がないため読み捨てる
+            while self.update_rx.try_recv().is_ok() {}
+        }
+        Ok(())
+    }
+
+    /// プレイヤーからの表示更新を反映する
+    fn apply_updates(&mut self) -> Result<()> {
+        let mut latest_title = None;
+        while let Ok(update) = self.update_rx.try_recv() {
+            match update {
+                TerminalUpdate::Title(title) => latest_title = Some(title),
+                TerminalUpdate::Osd(message) => {
+                    self.osd = Some((message, Instant::now() + OSD_DURATION));
+                    self.draw_osd()?;
+                }
+                TerminalUpdate::QrCode(qr_code) => {
+                    let hidden = qr_code.is_none();
+                    self.qr_code = qr_code;
+                    if hidden {
+                        // QR コードの下にあったフレームを描き直す
+                        self.clear_screen()?;
+                        self.redraw()?;
+                    } else {
+                        self.draw_qr_code()?;
+                    }
+                }
+                TerminalUpdate::Status(info) => self.status = Some((info, Instant::now())),
+                TerminalUpdate::Waveform(Some(peaks)) => {
+                    self.waveform = Some(peaks);
+                    self.draw_status_bar()?;
+                }
+                TerminalUpdate::Waveform(None) => {
+                    if self.waveform.take().is_some() {
+                        self.redraw()?;
+                    }
+                }
+                TerminalUpdate::Subtitle(subtitle) => {
+                    // 前の字幕を消すため、フレームごと描き直す
+                    self.subtitle = subtitle;
+                    self.redraw()?;
+                }
+            }
+        }
+
+        // 溜まったタイトル更新は最新のものだけ反映する
+        if let Some(title) = latest_title {
+            execute!(stdout(), SetTitle(title.format()))?;
+        }
+
+        if self
+            .osd
+            .as_ref()
+            .is_some_and(|(_, until)| Instant::now() >= *until)
+        {
+            // 次のフレームは全体を描いて OSD を消す
+            self.osd = None;
+            self.screen = None;
+        }
+        Ok(())
+    }
+
     /// ターミナルを初期化
-    fn init_terminal(&self) -> Result<()> {
-        execute!(
-            stdout(),
-            EnterAlternateScreen,
-            SetTitle("ascii-term - Ascii Rendered Media Player")
-        )?;
-        terminal::enable_raw_mode()?;
+    fn init_terminal(&mut self) -> Result<()> {
+        // 終了時に元のタイトルへ戻せるよう保存しておく
+        title::push_title(&mut stdout())?;
+        self.initialized = true;
+        if self.pause_on_blur {
+            execute!(stdout(), EnableFocusChange)?;
+        }
+        if let Some(area) = &mut self.inline {
+            // 改行して（足りなければスクロールさせて）カーソルの下に描く行を空ける
+            let mut out = stdout();
+            out.write_all("\n".repeat(area.height as usize).as_bytes())?;
+            execute!(out, EnableBracketedPaste, SetTitle(DEFAULT_TITLE))?;
+            terminal::enable_raw_mode()?;
+            let (_, row) = cursor::position()?;
+            area.top = row.saturating_sub(area.height);
+        } else {
+            execute!(
+                stdout(),
+                EnterAlternateScreen,
+                EnableMouseCapture,
+                EnableBracketedPaste,
+                SetTitle(DEFAULT_TITLE)
+            )?;
+            terminal::enable_raw_mode()?;
+        }
         self.clear_screen()?;
         Ok(())
     }
 
-    /// ターミナルをクリーンアップ
-    fn cleanup_terminal(&self) -> Result<()> {
-        execute!(
-            stdout(),
-            ResetColor,
-            Clear(ClearType::All),
-            Show,
-            LeaveAlternateScreen
-        )?;
+    /// ターミナルをクリーンアップ（初期化していなければ何もしない）
+    fn cleanup_terminal(&mut self) -> Result<()> {
+        if !std::mem::take(&mut self.initialized) {
+            return Ok(());
+        }
+        if self.pause_on_blur {
+            execute!(stdout(), DisableFocusChange)?;
+        }
+        if let Some(area) = self.inline {
+            // 最後のフレームを残し、その次の行にカーソルを置く
+            execute!(
+                stdout(),
+                ResetColor,
+                MoveTo(0, area.top + area.height),
+                Show,
+                DisableBracketedPaste
+            )?;
+        } else {
+            execute!(
+                stdout(),
+                ResetColor,
+                Clear(ClearType::All),
+                Show,
+                DisableMouseCapture,
+                DisableBracketedPaste,
+                LeaveAlternateScreen
+            )?;
+        }
+        title::pop_title(&mut stdout())?;
         terminal::disable_raw_mode()?;
         Ok(())
     }
 
-    /// 画面をクリア
-    fn clear_screen(&self) -> Result<()> {
-        execute!(stdout(), Clear(ClearType::All), Hide, MoveTo(0, 0),)?;
-        stdout().flush()?;
+    /// 画面をクリア（`--inline` では描く領域だけ）
+    fn clear_screen(&mut self) -> Result<()> {
+        let mut out = stdout();
+        match self.inline {
+            Some(area) => {
+                for row in area.top..area.top + area.height {
+                    execute!(out, MoveTo(0, row), Clear(ClearType::CurrentLine))?;
+                }
+                execute!(out, Hide)?;
+            }
+            None => execute!(out, Clear(ClearType::All), Hide, MoveTo(0, 0))?,
+        }
+        out.flush()?;
+        self.screen = None;
+        Ok(())
+    }
+
+    /// 最後のフレームを全体から描き直す（重ねた表示を消すときなど）
+    fn redraw(&mut self) -> Result<()> {
+        self.screen = None;
+        if let Some(frame) = self.last_frame.clone() {
+            self.display_frame(&frame)?;
+        }
+        Ok(())
+    }
+
+    /// 数字が続かないまま期限を過ぎた `m` をミュート切り替えとして扱う
+    fn expire_key_prefix(&mut self) -> Result<()> {
+        if let Some((prefix, deadline)) = self.key_prefix
+            && Instant::now() >= deadline
+        {
+            self.key_prefix = None;
+            if prefix == KeyPrefix::SetBookmark {
+                self.send_command(PlayerCommand::ToggleMute)?;
+            }
+        }
         Ok(())
     }
 
+    /// 入力途中のキーに続くキーを処理する。数字で完結した場合は true
+    fn complete_key_prefix(&mut self, code: KeyCode) -> Result<bool> {
+        let Some((prefix, _)) = self.key_prefix.take() else {
+            return Ok(false);
+        };
+
+        if let KeyCode::Char(digit) = code
+            && let Some(slot) = digit.to_digit(10)
+        {
+            let slot = slot as u8;
+            self.send_command(match prefix {
+                KeyPrefix::SetBookmark => PlayerCommand::SetBookmark(slot),
+                KeyPrefix::JumpToBookmark => PlayerCommand::JumpToBookmark(slot),
+            })?;
+            return Ok(true);
+        }
+
+        if prefix == KeyPrefix::SetBookmark {
+            self.send_command(PlayerCommand::ToggleMute)?;
+        }
+        Ok(false)
+    }
+
     /// 入力イベントを処理
     fn handle_input_event(&mut self) -> Result<bool> {
         let event = event::read()?;
@@ -105,58 +493,122 @@ impl Terminal {
             Event::Key(KeyEvent {
                 code, modifiers, ..
             }) => {
-                match (code, modifiers) {
-                    // 終了
-                    (KeyCode::Char('q'), _)
-                    | (KeyCode::Char('Q'), _)
-                    | (KeyCode::Esc, _)
-                    | (KeyCode::Char('c'), KeyModifiers::CONTROL) => {
+                if self.complete_key_prefix(code)? {
+                    return Ok(false);
+                }
+
+                let Some(action) = keymap::action_for(&self.key_bindings, code, modifiers) else {
+                    return Ok(false);
+                };
+                match action {
+                    Action::Quit => {
                         self.send_command(PlayerCommand::Stop)?;
                         return Ok(true);
                     }
-
-                    // 再生/一時停止
-                    (KeyCode::Char(' '), _) => {
+                    Action::TogglePlayPause => {
                         self.send_command(PlayerCommand::TogglePlayPause)?;
                     }
-
-                    // ミュート切り替え
-                    (KeyCode::Char('m'), _) | (KeyCode::Char('M'), _) => {
-                        self.send_command(PlayerCommand::ToggleMute)?;
+                    Action::ToggleMute => self.send_command(PlayerCommand::ToggleMute)?,
+                    Action::SetBookmarkPrefix => {
+                        self.key_prefix =
+                            Some((KeyPrefix::SetBookmark, Instant::now() + KEY_PREFIX_TIMEOUT));
                     }
-
-                    // グレースケール切り替え
-                    (KeyCode::Char('g'), _) | (KeyCode::Char('G'), _) => {
+                    Action::JumpToBookmarkPrefix => {
+                        self.key_prefix = Some((
+                            KeyPrefix::JumpToBookmark,
+                            Instant::now() + KEY_PREFIX_TIMEOUT,
+                        ));
+                    }
+                    Action::NextChapter => self.send_command(PlayerCommand::NextChapter)?,
+                    Action::PreviousChapter => {
+                        self.send_command(PlayerCommand::PreviousChapter)?;
+                    }
+                    Action::StepForward => self.send_command(PlayerCommand::StepForward)?,
+                    Action::StepBackward => self.send_command(PlayerCommand::StepBackward)?,
+                    Action::MarkAbLoop => self.send_command(PlayerCommand::MarkAbLoop)?,
+                    Action::SeekForward(amount) => {
+                        self.send_command(PlayerCommand::SeekForward(amount))?;
+                    }
+                    Action::SeekBackward(amount) => {
+                        self.send_command(PlayerCommand::SeekBackward(amount))?;
+                    }
+                    Action::ToggleGrayscale => {
                         self.grayscale_mode = !self.grayscale_mode;
                         self.send_command(PlayerCommand::ToggleGrayscale)?;
 
                         // 最後のフレームを再描画
-                        if let Some(frame) = self.last_frame.take() {
-                            self.display_frame(&frame)?;
-                        }
+                        self.redraw()?;
                     }
-
-                    // 文字マップ変更（0-9）
-                    (KeyCode::Char(digit), _) if digit.is_ascii_digit() => {
-                        let index = digit.to_digit(10).unwrap_or(0) as u8;
+                    Action::ToggleScopes => {
+                        self.scopes_visible = !self.scopes_visible;
+                        // 消すときはスコープの下にあったフレームを描き直す
+                        self.redraw()?;
+                    }
+                    Action::SetCharMap(index) => {
                         self.send_command(PlayerCommand::SetCharMap(index))?;
                     }
-
-                    // ヘルプ表示
-                    (KeyCode::Char('h'), _) | (KeyCode::Char('H'), _) => {
-                        self.show_help()?;
+                    Action::CycleColorFilter => {
+                        self.send_command(PlayerCommand::CycleColorFilter)?;
+                    }
+                    Action::CycleAudioTrack => {
+                        self.send_command(PlayerCommand::CycleAudioTrack)?;
+                    }
+                    Action::ToggleSubtitles => {
+                        self.send_command(PlayerCommand::ToggleSubtitles)?;
                     }
+                    Action::ToggleQrCode => self.send_command(PlayerCommand::ToggleQrCode)?,
+                    Action::ToggleStatusBar => {
+                        self.status_visible = !self.status_visible;
+                        if self.status_visible {
+                            self.draw_status_bar()?;
+                        } else {
+                            // ステータスバーの下にあったフレームを描き直す
+                            self.redraw()?;
+                        }
+                    }
+                    Action::ToggleHelp => {
+                        self.help_visible = !self.help_visible;
+                        if self.help_visible {
+                            self.draw_help()?;
+                        } else {
+                            // ヘルプの下にあったフレームを描き直す
+                            self.clear_screen()?;
+                            self.redraw()?;
+                        }
+                    }
+                }
+            }
+
+            Event::Mouse(mouse) => self.handle_mouse_event(mouse)?,
 
-                    _ => {}
+            // ファイルをドロップすると、多くのターミナルはパスを貼り付ける
+            Event::Paste(text) => {
+                if let Some(path) = dropped_path(&text) {
+                    self.send_command(PlayerCommand::Load(path))?;
                 }
             }
 
-            Event::Resize(_, _) => {
-                // 解像度は起動時に固定。画面クリアして最終フレームを再描画するだけ
+            // 自分で一時停止していた場合は、フォーカスが戻っても再開しない
+            Event::FocusLost
+                if self.pause_on_blur
+                    && self
+                        .current_status()
+                        .is_none_or(|info| info.state.is_playing()) =>
+            {
+                self.paused_on_blur = true;
+                self.send_command(PlayerCommand::Pause)?;
+            }
+            Event::FocusGained if std::mem::take(&mut self.paused_on_blur) => {
+                self.send_command(PlayerCommand::Play)?;
+            }
+
+            Event::Resize(columns, rows) => {
+                // 次のフレームから新しい大きさで描画する。それまでは最終フレームを描き直す
+                // （`--inline` では行数を変えない）
+                let rows = self.inline.map_or(rows, |area| area.height);
+                self.send_command(PlayerCommand::Resize(columns, rows))?;
                 self.clear_screen()?;
-                if let Some(ref frame) = self.last_frame.clone() {
-                    self.display_frame(frame)?;
-                }
+                self.redraw()?;
             }
 
             _ => {}
@@ -165,42 +617,48 @@ impl Terminal {
         Ok(false)
     }
 
-    /// ヘルプを表示
-    fn show_help(&mut self) -> Result<()> {
-        let help_text = r#"
-            ascii-term - Ascii Rendered Media Player
-
-            Controls:
-            Space       Play/Pause
-            Q, Esc      Quit
-            M           Mute/Unmute
-            G           Toggle Grayscale
-            0-9         Change character map
-            H           Show this help
-
-            Press any key to continue...
-        "#;
-
-        execute!(
-            stdout(),
-            Clear(ClearType::All),
-            MoveTo(0, 0),
-            Print(help_text)
-        )?;
-        stdout().flush()?;
-
-        // キー入力を待つ
-        event::read()?;
-
-        // 画面をクリアして前の状態に戻る
-        self.clear_screen()?;
-        if let Some(frame) = self.last_frame.take() {
-            self.display_frame(&frame)?;
+    /// ステータスバーのクリックで移動し、ホイールで音量を変える
+    fn handle_mouse_event(&mut self, mouse: MouseEvent) -> Result<()> {
+        match mouse.kind {
+            MouseEventKind::Down(MouseButton::Left) => {
+                if let Some(target) = self.seek_target_at(mouse.column, mouse.row) {
+                    self.send_command(PlayerCommand::Seek(target))?;
+                }
+            }
+            MouseEventKind::ScrollUp => {
+                self.send_command(PlayerCommand::AdjustVolume(VOLUME_STEP))?
+            }
+            MouseEventKind::ScrollDown => {
+                self.send_command(PlayerCommand::AdjustVolume(-VOLUME_STEP))?;
+            }
+            _ => {}
         }
-
         Ok(())
     }
 
+    /// 表示中のステータスバー・波形の上の位置に対応する再生位置
+    fn seek_target_at(&self, column: u16, row: u16) -> Option<Duration> {
+        if !self.status_visible {
+            return None;
+        }
+        let info = self.current_status()?;
+        let (columns, rows) = terminal::size().ok()?;
+        let (column, width) = (column as usize, columns as usize);
+        // 波形があればステータスバーはその上の行
+        let (status_row, waveform_row) = match self.waveform {
+            Some(_) => (rows.checked_sub(2)?, Some(rows - 1)),
+            None => (rows.checked_sub(1)?, None),
+        };
+
+        if row == status_row {
+            status_bar::status_line_seek(&info, width, column)
+        } else if Some(row) == waveform_row {
+            status_bar::waveform_seek(info.duration.filter(|d| !d.is_zero())?, width, column)
+        } else {
+            None
+        }
+    }
+
     /// コマンドを送信
     fn send_command(&self, command: PlayerCommand) -> Result<()> {
         self.command_tx
@@ -209,3 +667,92 @@ impl Terminal {
         Ok(())
     }
 }
+
+/// 再生が終わって Terminal のタスクごと破棄されたときも、ターミナルを元に戻す
+impl Drop for Terminal {
+    fn drop(&mut self) {
+        let _ = self.cleanup_terminal();
+    }
+}
+
+/// 環境変数 `COLORTERM` / `TERM` から、ターミナルが表示できる色の種類を推定する
+pub fn detect_color_mode() -> ColorMode {
+    color_mode_from_env(
+        std::env::var("COLORTERM").ok().as_deref(),
+        std::env::var("TERM").ok().as_deref(),
+    )
+}
+
+/// 色の種類の推定。分からなければ（Windows など `TERM` がない場合も）truecolor とする
+fn color_mode_from_env(colorterm: Option<&str>, term: Option<&str>) -> ColorMode {
+    if matches!(colorterm, Some("truecolor" | "24bit")) {
+        return ColorMode::Truecolor;
+    }
+    match term {
+        Some("dumb") => ColorMode::Mono,
+        Some(term) if term.ends_with("-direct") => ColorMode::Truecolor,
+        Some(term) if term.contains("256color") => ColorMode::Ansi256,
+        Some("linux" | "vt100" | "vt220" | "ansi" | "cygwin" | "xterm-color" | "xterm-16color") => {
+            ColorMode::Ansi16
+        }
+        _ => ColorMode::Truecolor,
+    }
+}
+
+/// 貼り付けられた文字列が既存のファイルのパスなら、そのパス
+///
+/// ターミナルによって引用符で囲んだり `file://` を付けたりするので取り除く
+fn dropped_path(text: &str) -> Option<String> {
+    let text = text.trim();
+    let text = ['\'', '"']
+        .iter()
+        .find_map(|&quote| text.strip_prefix(quote)?.strip_suffix(quote))
+        .unwrap_or(text);
+    let path = text.strip_prefix("file://").unwrap_or(text);
+    std::path::Path::new(path)
+        .is_file()
+        .then(|| path.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_color_mode_from_env() {
+        assert_eq!(
+            color_mode_from_env(Some("truecolor"), Some("xterm-256color")),
+            ColorMode::Truecolor
+        );
+        assert_eq!(
+            color_mode_from_env(None, Some("screen-256color")),
+            ColorMode::Ansi256
+        );
+        assert_eq!(
+            color_mode_from_env(None, Some("xterm-direct")),
+            ColorMode::Truecolor
+        );
+        assert_eq!(color_mode_from_env(None, Some("linux")), ColorMode::Ansi16);
+        assert_eq!(color_mode_from_env(None, Some("dumb")), ColorMode::Mono);
+        assert_eq!(color_mode_from_env(None, None), ColorMode::Truecolor);
+    }
+
+    #[test]
+    fn test_dropped_path() {
+        let file = std::env::temp_dir().join(format!("ascii-term-drop-{}.mp4", std::process::id()));
+        std::fs::write(&file, b"").unwrap();
+        let path = file.to_string_lossy().into_owned();
+
+        assert_eq!(dropped_path(&format!("{}\n", path)), Some(path.clone()));
+        assert_eq!(dropped_path(&format!("'{}' ", path)), Some(path.clone()));
+        assert_eq!(
+            dropped_path(&format!("file://{}", path)),
+            Some(path.clone())
+        );
+        assert_eq!(dropped_path("hello world"), None);
+        // ディレクトリは開かない
+        assert_eq!(dropped_path(&std::env::temp_dir().to_string_lossy()), None);
+
+        std::fs::remove_file(file).ok();
+    }
+}
@@ -1,75 +1,344 @@
-//! ターミナルへのフレーム描画（ANSI エンコード）
+//! ターミナルへのフレーム描画
 
 use std::io::{Write, stdout};
 
 use anyhow::Result;
-use crossterm::{
-    cursor::MoveTo,
-    execute,
-    style::{Color, Stylize},
-};
+use crossterm::{cursor::MoveTo, execute, style::Stylize, terminal};
 
+use crate::ansi::{self, ColorMode};
+use crate::canvas::{display_width, pad, sanitize, truncate};
+use crate::i18n::{Msg, tr_args};
 use crate::renderer::RenderedFrame;
+use crate::scopes;
+use crate::status_bar::{self, PlaybackInfo};
 
 impl super::Terminal {
     /// フレームを表示
     pub(super) fn display_frame(&mut self, frame: &RenderedFrame) -> Result<()> {
+        if let Ok((columns, rows)) = terminal::size()
+            && (columns < super::MIN_WIDTH || rows < super::MIN_HEIGHT)
+            && self.inline.is_none()
+        {
+            self.screen = None;
+            return draw_too_small(columns, rows);
+        }
+
+        let mode = self.output_color_mode();
+        let mut out = stdout();
+        if let Some(area) = self.inline {
+            // 描く領域が画面の途中なので、毎回その位置から全体を描く
+            match &frame.sixel {
+                Some(sixel) => {
+                    execute!(out, MoveTo(0, area.top))?;
+                    out.write_all(sixel)?;
+                }
+                None => out.write_all(&ansi::encode_at(frame, mode, 0, area.top))?,
+            }
+            out.flush()?;
+            return Ok(());
+        }
+        if let Some(sixel) = &frame.sixel {
+            // Sixel は画面の左上から描く（セルは空白なので文字としては出力しない）
+            out.write_all(b"\x1b[H")?;
+            out.write_all(sixel)?;
+        } else if self.newlines {
+            out.write_all(&ansi::encode_lines(frame, mode))?;
+        } else if let Some(previous) = self
+            .screen
+            .as_ref()
+            .filter(|previous| (previous.width, previous.height) == (frame.width, frame.height))
+        {
+            // 前のフレームから変わったセルだけを書き直す
+            out.write_all(&ansi::encode_changes(previous, frame, mode))?;
+        } else {
+            out.write_all(&ansi::encode(frame, mode))?;
+        }
+        out.flush()?;
+        self.screen = (frame.sixel.is_none() && !self.newlines).then(|| frame.clone());
+
+        self.draw_subtitle()?;
+        self.draw_widgets()?;
+        self.draw_scopes(frame, mode)?;
+        self.draw_status_bar()?;
+        self.draw_qr_code()?;
+        self.draw_help()?;
+        self.draw_osd()
+    }
+
+    /// フレームを出力する色の種類
+    fn output_color_mode(&self) -> ColorMode {
         if self.grayscale_mode {
-            self.display_grayscale_frame(frame)
+            ColorMode::Mono
         } else {
-            self.display_colored_frame(frame)
+            self.color_mode
         }
     }
 
-    /// グレースケールフレームを表示
-    fn display_grayscale_frame(&self, frame: &RenderedFrame) -> Result<()> {
-        let chars: Vec<char> = frame.ascii_text.chars().collect();
-        let width = frame.width as usize;
-        let height = frame.height as usize;
+    /// 字幕をフレームの下端に中央揃えで重ねる
+    pub(super) fn draw_subtitle(&self) -> Result<()> {
+        if self.inline.is_some() {
+            return Ok(());
+        }
+        let Some(subtitle) = &self.subtitle else {
+            return Ok(());
+        };
+
+        let (width, height) = self
+            .last_frame
+            .as_ref()
+            .map(|frame| (frame.width as usize, frame.height as usize))
+            .unwrap_or((80, 24));
+        // 字幕ファイルやダウンロードした字幕の制御文字は、測る前に取り除く
+        let lines: Vec<String> = subtitle.lines().map(sanitize).collect();
+        let top = height.saturating_sub(lines.len() + 1);
+
         let mut out = stdout();
+        for (i, line) in lines.iter().enumerate() {
+            let text = truncate(&format!(" {} ", line), width);
+            let x = width.saturating_sub(display_width(&text)) / 2;
+            execute!(out, MoveTo(x as u16, (top + i) as u16))?;
+            write!(out, "{}", text.white().on_black())?;
+        }
+        out.flush()?;
+        Ok(())
+    }
 
-        for y in 0..height {
-            let row_start = y * width;
-            let row_end = (row_start + width).min(chars.len());
-            let row: String = chars[row_start..row_end].iter().collect();
-            execute!(out, MoveTo(0, y as u16))?;
-            write!(out, "{}", row)?;
+    /// 時計とティッカーを重ねる
+    pub(super) fn draw_widgets(&mut self) -> Result<()> {
+        if self.inline.is_some() {
+            return Ok(());
+        }
+        let mode = self.output_color_mode();
+        let Some((widgets, drawn)) = &mut self.widgets else {
+            return Ok(());
+        };
+        if let Ok((columns, rows)) = terminal::size()
+            && (columns < super::MIN_WIDTH || rows < super::MIN_HEIGHT)
+        {
+            return Ok(());
         }
+        *drawn = std::time::Instant::now();
 
+        let (width, height) = self
+            .last_frame
+            .as_ref()
+            .map(|frame| (frame.width as usize, frame.height as usize))
+            .unwrap_or((80, 24));
+
+        let mut out = stdout();
+        for (column, row, overlay) in widgets.render(width, height) {
+            out.write_all(&ansi::encode_at(&overlay, mode, column, row))?;
+        }
         out.flush()?;
         Ok(())
     }
 
-    /// カラーフレームを表示
-    fn display_colored_frame(&self, frame: &RenderedFrame) -> Result<()> {
-        let chars: Vec<char> = frame.ascii_text.chars().collect();
-        let width = frame.width as usize;
-        let height = frame.height as usize;
+    /// 現在の再生状況
+    ///
+    /// 受け取ってからの経過時間を進めて、フレームが止まっていても時間を動かす
+    pub(super) fn current_status(&self) -> Option<PlaybackInfo> {
+        let (info, received) = self.status.as_ref()?;
+        let mut info = info.clone();
+        if info.state.is_playing() {
+            info.elapsed += received.elapsed();
+            if let Some(duration) = info.duration {
+                info.elapsed = info.elapsed.min(duration);
+            }
+        }
+        Some(info)
+    }
+
+    /// 再生状況を画面の最下行に重ねる（波形があれば、その下の最下行に波形を描く）
+    pub(super) fn draw_status_bar(&mut self) -> Result<()> {
+        if self.inline.is_some() {
+            return Ok(());
+        }
+        if !self.status_visible {
+            return Ok(());
+        }
+        self.status_drawn = std::time::Instant::now();
+        let Some(info) = self.current_status() else {
+            return Ok(());
+        };
+        let (columns, rows) = terminal::size().unwrap_or((80, 24));
+        if columns < super::MIN_WIDTH || rows < super::MIN_HEIGHT {
+            return Ok(());
+        }
+
+        let line = status_bar::status_line(&info, columns as usize);
         let mut out = stdout();
+        let Some(peaks) = &self.waveform else {
+            execute!(out, MoveTo(0, rows - 1))?;
+            write!(out, "{}", line.white().on_dark_grey())?;
+            out.flush()?;
+            return Ok(());
+        };
 
-        for y in 0..height {
-            let row_start = y * width;
-            let row_end = (row_start + width).min(chars.len());
-
-            execute!(out, MoveTo(0, y as u16))?;
-
-            let mut row_string = String::with_capacity(width * 20);
-            for (j, ch) in chars[row_start..row_end].iter().enumerate() {
-                let rgb_index = (row_start + j) * 3;
-                if rgb_index + 2 < frame.rgb_data.len() {
-                    let r = frame.rgb_data[rgb_index];
-                    let g = frame.rgb_data[rgb_index + 1];
-                    let b = frame.rgb_data[rgb_index + 2];
-                    let color = Color::Rgb { r, g, b };
-                    row_string.push_str(&format!("{}", ch.stylize().with(color)));
-                } else {
-                    row_string.push(*ch);
-                }
-            }
-            write!(out, "{}", row_string)?;
+        // 波形は最下行の全幅に描き、再生済みの部分を色で分ける
+        let waveform = status_bar::waveform_line(peaks, columns as usize);
+        let ratio = match info.duration.filter(|duration| !duration.is_zero()) {
+            Some(duration) => (info.elapsed.as_secs_f64() / duration.as_secs_f64()).clamp(0.0, 1.0),
+            None => 0.0,
+        };
+        let played = (ratio * columns as f64).round() as usize;
+        let (before, after): (String, String) = (
+            waveform.chars().take(played).collect(),
+            waveform.chars().skip(played).collect(),
+        );
+        execute!(out, MoveTo(0, rows - 2))?;
+        write!(out, "{}", line.white().on_dark_grey())?;
+        execute!(out, MoveTo(0, rows - 1))?;
+        write!(
+            out,
+            "{}{}",
+            before.yellow().on_black(),
+            after.dark_grey().on_black()
+        )?;
+        out.flush()?;
+        Ok(())
+    }
+
+    /// 輝度ヒストグラムと RGB パレードを右上に重ねる
+    fn draw_scopes(&self, frame: &RenderedFrame, mode: ColorMode) -> Result<()> {
+        if self.inline.is_some() {
+            return Ok(());
+        }
+        if !self.scopes_visible {
+            return Ok(());
         }
 
+        let column = (frame.width as usize).saturating_sub(scopes::WIDTH) as u16;
+        let encoded = ansi::encode_at(&scopes::render_scopes(frame), mode, column, 0);
+        let mut out = stdout();
+        out.write_all(&encoded)?;
         out.flush()?;
         Ok(())
     }
+
+    /// QR コードを画面中央に黒地・白背景で重ねる
+    pub(super) fn draw_qr_code(&self) -> Result<()> {
+        if self.inline.is_some() {
+            return Ok(());
+        }
+        let Some(lines) = &self.qr_code else {
+            return Ok(());
+        };
+
+        let (columns, rows) = terminal::size().unwrap_or((80, 24));
+        let width = lines.first().map_or(0, |line| display_width(line)) as u16;
+        let x = columns.saturating_sub(width) / 2;
+        let y = rows.saturating_sub(lines.len() as u16) / 2;
+
+        let mut out = stdout();
+        for (i, line) in lines.iter().enumerate() {
+            execute!(out, MoveTo(x, y + i as u16))?;
+            write!(out, "{}", line.as_str().black().on_white())?;
+        }
+        out.flush()?;
+        Ok(())
+    }
+
+    /// キー割り当てのヘルプを画面中央に重ねる（再生は止めない）
+    pub(super) fn draw_help(&self) -> Result<()> {
+        if self.inline.is_some() {
+            return Ok(());
+        }
+        if !self.help_visible {
+            return Ok(());
+        }
+
+        let (columns, rows) = terminal::size().unwrap_or((80, 24));
+        let lines: Vec<String> = super::keymap::help_lines(&self.key_bindings)
+            .iter()
+            .take(rows as usize)
+            .map(|line| truncate(&format!(" {} ", line), columns as usize))
+            .collect();
+        let width = lines
+            .iter()
+            .map(|line| display_width(line))
+            .max()
+            .unwrap_or(0);
+        let x = (columns as usize).saturating_sub(width) / 2;
+        let y = (rows as usize).saturating_sub(lines.len()) / 2;
+
+        let mut out = stdout();
+        for (i, line) in lines.iter().enumerate() {
+            execute!(out, MoveTo(x as u16, (y + i) as u16))?;
+            write!(out, "{}", pad(line, width).white().on_black())?;
+        }
+        out.flush()?;
+        Ok(())
+    }
+
+    /// OSD メッセージを左上に反転表示で重ねる
+    pub(super) fn draw_osd(&self) -> Result<()> {
+        if self.inline.is_some() {
+            return Ok(());
+        }
+        let Some((message, _)) = &self.osd else {
+            return Ok(());
+        };
+
+        let width = self
+            .last_frame
+            .as_ref()
+            .map(|frame| frame.width as usize)
+            .unwrap_or(80);
+        let text = truncate(&format!(" {} ", message), width);
+
+        let mut out = stdout();
+        execute!(out, MoveTo(0, 0))?;
+        write!(out, "{}", text.black().on_white())?;
+        out.flush()?;
+        Ok(())
+    }
+}
+
+/// 小さすぎるターミナルに、必要な大きさの案内を中央に表示する
+fn draw_too_small(columns: u16, rows: u16) -> Result<()> {
+    let mut out = stdout();
+    execute!(out, terminal::Clear(terminal::ClearType::All))?;
+    let lines = too_small_lines(columns as usize);
+    let top = (rows as usize).saturating_sub(lines.len()) / 2;
+    for (i, line) in lines.iter().enumerate().take(rows as usize) {
+        let x = (columns as usize).saturating_sub(display_width(line)) / 2;
+        execute!(out, MoveTo(x as u16, (top + i) as u16))?;
+        write!(out, "{}", line)?;
+    }
+    out.flush()?;
+    Ok(())
+}
+
+/// 案内の各行（幅に収まらなければ単語の区切りで折り返す）
+fn too_small_lines(width: usize) -> Vec<String> {
+    let message = tr_args(
+        Msg::TerminalTooSmall,
+        &[&super::MIN_WIDTH, &super::MIN_HEIGHT],
+    );
+    let mut lines: Vec<String> = Vec::new();
+    for word in message.split(' ') {
+        match lines.last_mut() {
+            Some(line) if display_width(line) + 1 + display_width(word) <= width => {
+                line.push(' ');
+                line.push_str(word);
+            }
+            _ => lines.push(truncate(word, width.max(1))),
+        }
+    }
+    lines
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_too_small_lines_fit_width() {
+        for width in [1, 5, 12, 40] {
+            let lines = too_small_lines(width);
+            assert!(!lines.is_empty());
+            assert!(lines.iter().all(|line| display_width(line) <= width));
+        }
+        assert_eq!(too_small_lines(200).len(), 1);
+    }
 }
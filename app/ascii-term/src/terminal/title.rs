@@ -0,0 +1,94 @@
+//! ウィンドウタイトルの保存・復元と再生状況の表示
+
+use std::io::Write;
+use std::time::Duration;
+
+use anyhow::Result;
+
+use crate::canvas::sanitize;
+use crate::player_state::PlayerState;
+use crate::status_bar::format_time;
+
+/// XTWINOPS: ウィンドウタイトルをスタックに保存する
+const PUSH_TITLE: &str = "\x1b[22;0t";
+/// XTWINOPS: スタックから保存したタイトルを復元する
+const POP_TITLE: &str = "\x1b[23;0t";
+
+/// ウィンドウタイトルに表示する再生状況
+#[derive(Debug, Clone, PartialEq)]
+pub struct WindowTitle {
+    pub title: String,
+    pub elapsed: Duration,
+    /// 長さ（ライブ配信などで不明な場合は None）
+    pub duration: Option<Duration>,
+    pub state: PlayerState,
+}
+
+impl WindowTitle {
+    /// `"Title — 12:34/45:00 ▶"` 形式の文字列を生成
+    ///
+    /// タイトルはメディアのタグから来るので、OSC を途中で終わらせる BEL や ESC などの
+    /// 制御文字は取り除く
+    pub fn format(&self) -> String {
+        let position = match self.duration {
+            Some(duration) => format!("{}/{}", format_time(self.elapsed), format_time(duration)),
+            None => format_time(self.elapsed),
+        };
+        format!(
+            "{} — {} {}",
+            sanitize(&self.title),
+            position,
+            self.state.icon()
+        )
+    }
+}
+
+/// 現在のタイトルを保存する（非対応のターミナルでは無視される）
+pub(super) fn push_title(out: &mut impl Write) -> Result<()> {
+    write!(out, "{}", PUSH_TITLE)?;
+    Ok(())
+}
+
+/// 保存したタイトルを復元する
+pub(super) fn pop_title(out: &mut impl Write) -> Result<()> {
+    write!(out, "{}", POP_TITLE)?;
+    out.flush()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_window_title() {
+        let title = WindowTitle {
+            title: "Bad Apple".to_string(),
+            elapsed: Duration::from_secs(754),
+            duration: Some(Duration::from_secs(2700)),
+            state: PlayerState::Playing,
+        };
+        assert_eq!(title.format(), "Bad Apple — 12:34/45:00 ▶");
+
+        let live = WindowTitle {
+            duration: None,
+            state: PlayerState::Paused,
+            ..title
+        };
+        assert_eq!(live.format(), "Bad Apple — 12:34 ⏸");
+
+        let injected = WindowTitle {
+            title: "Bad\x07\x1b]52;c;eA==\x1b\\Apple".to_string(),
+            ..live
+        };
+        assert_eq!(injected.format(), "Bad]52;c;eA==\\Apple — 12:34 ⏸");
+    }
+
+    #[test]
+    fn test_push_pop_sequences() {
+        let mut out = Vec::new();
+        push_title(&mut out).unwrap();
+        pop_title(&mut out).unwrap();
+        assert_eq!(out, b"\x1b[22;0t\x1b[23;0t");
+    }
+}
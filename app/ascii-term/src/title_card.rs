@@ -0,0 +1,170 @@
+//! 再生リストの曲間に表示するタイトルカード
+//!
+//! 次の曲名を大きな文字で、アーティストと曲番号をその下に描く。残り秒数の
+//! カウントダウンも表示できる。通常の映像と同じレンダラーで描く手続き的なソース
+
+use std::time::Duration;
+
+use codec::Result;
+use codec::source::{MediaSource, Raster};
+use codec::video::VideoFrame;
+
+use crate::bitmap_font::{chars_per_line, draw_text, has_glyph, text_size, wrap};
+
+/// タイトルに使う最大の行数
+const MAX_TITLE_LINES: usize = 3;
+
+const TITLE_COLOR: [u8; 3] = [255, 255, 255];
+const DETAIL_COLOR: [u8; 3] = [150, 150, 150];
+const COUNTDOWN_COLOR: [u8; 3] = [255, 200, 64];
+
+/// 曲間のタイトルカード
+pub struct TitleCard {
+    title: String,
+    /// アーティストや「3/12」などの補足
+    details: Vec<String>,
+    duration: Duration,
+    countdown: bool,
+}
+
+impl TitleCard {
+    pub fn new(title: &str, details: Vec<String>, duration: Duration, countdown: bool) -> Self {
+        Self {
+            title: title.to_string(),
+            details,
+            duration,
+            countdown,
+        }
+    }
+
+    /// 表示時間
+    pub fn duration(&self) -> Duration {
+        self.duration
+    }
+
+    /// 大きな文字で描く行（フォントにない文字は除く）
+    fn title_lines(&self, width: u32) -> Vec<String> {
+        let mut lines = wrap(&drawable(&self.title), chars_per_line(width));
+        if lines.len() > MAX_TITLE_LINES {
+            lines.truncate(MAX_TITLE_LINES);
+            if let Some(last) = lines.last_mut() {
+                last.pop();
+                last.push('.');
+            }
+        }
+        lines
+    }
+}
+
+/// フォントにない文字を空白にする
+fn drawable(text: &str) -> String {
+    text.chars()
+        .map(|ch| if has_glyph(ch) { ch } else { ' ' })
+        .collect()
+}
+
+impl MediaSource for TitleCard {
+    fn name(&self) -> &str {
+        "title card"
+    }
+
+    fn frame_rate(&self) -> f64 {
+        4.0
+    }
+
+    fn video_frame(&mut self, timestamp: Duration, width: u32, height: u32) -> Result<VideoFrame> {
+        let mut raster = Raster::new(width, height);
+
+        let title_lines = self.title_lines(width);
+        let details: Vec<String> = self
+            .details
+            .iter()
+            .flat_map(|detail| {
+                wrap(&drawable(detail), chars_per_line(width))
+                    .into_iter()
+                    .take(1)
+            })
+            .collect();
+        let countdown = self.countdown.then(|| {
+            let remaining = self.duration.saturating_sub(timestamp);
+            remaining.as_secs_f64().ceil().max(1.0).to_string()
+        });
+
+        // 行の高さは文字の 5 ピクセル + 行間 1。ブロックの間は 1 行空ける
+        let line_height = text_size("", 1).1 + 1;
+        let blocks = [
+            title_lines.len() as u32,
+            details.len() as u32,
+            countdown.is_some() as u32,
+        ];
+        let total_lines: u32 = blocks.iter().sum();
+        let gaps = blocks.iter().filter(|&&lines| lines > 0).count() as u32;
+        let content_height =
+            (total_lines * line_height + gaps.saturating_sub(1) * line_height).saturating_sub(1);
+        let mut y = height.saturating_sub(content_height) / 2;
+
+        let mut draw_block = |raster: &mut Raster, lines: &[String], color: [u8; 3]| {
+            if lines.is_empty() {
+                return;
+            }
+            for line in lines {
+                let x = width.saturating_sub(text_size(line, 1).0) / 2;
+                draw_text(raster, line, x as i32, y as i32, 1, color);
+                y += line_height;
+            }
+            y += line_height;
+        };
+
+        draw_block(&mut raster, &title_lines, TITLE_COLOR);
+        draw_block(&mut raster, &details, DETAIL_COLOR);
+        if let Some(countdown) = countdown {
+            draw_block(&mut raster, &[countdown], COUNTDOWN_COLOR);
+        }
+
+        Ok(raster.into_frame(timestamp))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lit_rows(frame: &VideoFrame) -> Vec<u32> {
+        (0..frame.height)
+            .filter(|&y| {
+                let row =
+                    &frame.data[(y * frame.width * 3) as usize..][..(frame.width * 3) as usize];
+                row.iter().any(|&c| c > 0)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_title_card_draws_centered_text() {
+        let mut card = TitleCard::new(
+            "Intro",
+            vec!["Artist".to_string()],
+            Duration::from_secs(3),
+            true,
+        );
+        let frame = card.video_frame(Duration::ZERO, 80, 40).unwrap();
+        assert_eq!((frame.width, frame.height), (80, 40));
+
+        let rows = lit_rows(&frame);
+        // タイトル・補足・カウントダウンの 3 行（各 5 ピクセル）
+        assert_eq!(rows.len(), 15);
+        let middle = (rows[0] + rows[rows.len() - 1]) / 2;
+        assert!((19..=21).contains(&middle));
+    }
+
+    #[test]
+    fn test_title_lines_wrap_and_replace_unknown_characters() {
+        let card = TitleCard::new(
+            "曲 Long Title Goes Here",
+            Vec::new(),
+            Duration::from_secs(3),
+            false,
+        );
+        assert_eq!(card.title_lines(80), ["Long Title", "Goes Here"]);
+    }
+}
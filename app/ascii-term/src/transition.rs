@@ -0,0 +1,166 @@
+//! 項目が切り替わるときのトランジション
+//!
+//! 前の項目の最後のフレームと次の項目の最初のフレームを RGB のまま合成する
+//! （ループ再生で先頭に戻るときなど）。音声は曲の終わりをフェードアウトし、
+//! 次の曲の始まりをフェードインする（`AudioPlayer` 側で行う）
+
+use std::time::Duration;
+
+use clap::ValueEnum;
+use codec::video::VideoFrame;
+
+/// 映像の切り替え方
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum TransitionKind {
+    /// 前のフレームから次のフレームへ少しずつ混ぜる
+    Crossfade,
+    /// 左から右へ次のフレームに置き換える
+    Wipe,
+    /// 画素ごとにばらばらの順で次のフレームに置き換える
+    Dissolve,
+}
+
+/// トランジションの種類と長さ
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Transition {
+    pub kind: TransitionKind,
+    pub duration: Duration,
+}
+
+impl Transition {
+    /// 切り替わってから `elapsed` 経ったときの進み具合（0.0〜1.0）
+    pub fn progress(&self, elapsed: Duration) -> f32 {
+        if self.duration.is_zero() {
+            return 1.0;
+        }
+        (elapsed.as_secs_f32() / self.duration.as_secs_f32()).clamp(0.0, 1.0)
+    }
+
+    /// `from` から `to` へ `progress` だけ進んだフレーム
+    ///
+    /// 大きさやピクセル形式が違う場合は合成できないので `to` をそのまま返す
+    pub fn blend(&self, from: &VideoFrame, to: &VideoFrame, progress: f32) -> VideoFrame {
+        let Some(bytes_per_pixel) = to.format.bytes_per_pixel() else {
+            return to.clone();
+        };
+        if progress >= 1.0
+            || from.width != to.width
+            || from.height != to.height
+            || from.format != to.format
+            || from.data.len() != to.data.len()
+        {
+            return to.clone();
+        }
+
+        let mut blended = to.clone();
+        let pixels = blended.data.chunks_exact_mut(bytes_per_pixel);
+        for (index, (pixel, old)) in pixels
+            .zip(from.data.chunks_exact(bytes_per_pixel))
+            .enumerate()
+        {
+            match self.kind {
+                TransitionKind::Crossfade => {
+                    for (new, &old) in pixel.iter_mut().zip(old) {
+                        *new = (old as f32 + (*new as f32 - old as f32) * progress).round() as u8;
+                    }
+                }
+                TransitionKind::Wipe => {
+                    let x = index as u32 % to.width.max(1);
+                    if x as f32 >= progress * to.width as f32 {
+                        pixel.copy_from_slice(old);
+                    }
+                }
+                TransitionKind::Dissolve => {
+                    if dissolve_threshold(index as u32) >= progress {
+                        pixel.copy_from_slice(old);
+                    }
+                }
+            }
+        }
+        blended
+    }
+}
+
+/// 画素ごとに決まった 0.0〜1.0 の値（この値を進み具合が越えた画素から置き換える）
+fn dissolve_threshold(index: u32) -> f32 {
+    // 整数のハッシュ（lowbias32）で、フレームごとに変わらない順番にする
+    let mut x = index;
+    x ^= x >> 16;
+    x = x.wrapping_mul(0x7feb_352d);
+    x ^= x >> 15;
+    x = x.wrapping_mul(0x846c_a68b);
+    x ^= x >> 16;
+    (x >> 8) as f32 / (1u32 << 24) as f32
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use codec::video::frame::FrameFormat;
+
+    fn frame(width: u32, height: u32, value: u8) -> VideoFrame {
+        VideoFrame::new(
+            vec![value; (width * height * 3) as usize],
+            width,
+            height,
+            FrameFormat::RGB8,
+            Duration::ZERO,
+            0,
+        )
+    }
+
+    fn transition(kind: TransitionKind) -> Transition {
+        Transition {
+            kind,
+            duration: Duration::from_secs(1),
+        }
+    }
+
+    #[test]
+    fn test_progress() {
+        let transition = transition(TransitionKind::Crossfade);
+        assert_eq!(transition.progress(Duration::ZERO), 0.0);
+        assert_eq!(transition.progress(Duration::from_millis(250)), 0.25);
+        assert_eq!(transition.progress(Duration::from_secs(3)), 1.0);
+    }
+
+    #[test]
+    fn test_crossfade() {
+        let blended =
+            transition(TransitionKind::Crossfade).blend(&frame(2, 2, 0), &frame(2, 2, 200), 0.25);
+        assert!(blended.data.iter().all(|&value| value == 50));
+    }
+
+    #[test]
+    fn test_wipe_from_left() {
+        let blended =
+            transition(TransitionKind::Wipe).blend(&frame(4, 1, 0), &frame(4, 1, 255), 0.5);
+        let columns: Vec<u8> = blended.data.chunks(3).map(|pixel| pixel[0]).collect();
+        assert_eq!(columns, [255, 255, 0, 0]);
+    }
+
+    #[test]
+    fn test_dissolve_replaces_pixels_progressively() {
+        let dissolve = transition(TransitionKind::Dissolve);
+        let replaced = |progress| {
+            dissolve
+                .blend(&frame(100, 100, 0), &frame(100, 100, 255), progress)
+                .data
+                .chunks(3)
+                .filter(|pixel| pixel[0] == 255)
+                .count()
+        };
+        assert_eq!(replaced(0.0), 0);
+        let half = replaced(0.5);
+        assert!((4000..6000).contains(&half), "{}", half);
+        assert!(replaced(0.75) > half);
+        assert_eq!(replaced(1.0), 10_000);
+    }
+
+    #[test]
+    fn test_mismatched_frames_are_not_blended() {
+        let to = frame(2, 2, 255);
+        let blended = transition(TransitionKind::Crossfade).blend(&frame(4, 4, 0), &to, 0.5);
+        assert_eq!(blended.data, to.data);
+    }
+}
@@ -0,0 +1,401 @@
+//! 音声だけのメディアを再生するときの視覚化（`--viz`、再生中は `v` で切り替え）
+//!
+//! 出力した最近のサンプルから画像を作り、映像と同じレンダラー（文字マップ）で
+//! 描画する。画像はターミナルの 1 列を 1 ピクセル、1 行を 4 ピクセルとして作る
+
+use std::f32::consts::PI;
+use std::time::Duration;
+
+use clap::ValueEnum;
+use codec::source::Raster;
+use codec::video::VideoFrame;
+
+use crate::audio::RecentAudio;
+
+/// FFT をかけるサンプル数（2 の累乗）
+const FFT_SIZE: usize = 2048;
+/// 表示する周波数の範囲（Hz）
+const MIN_FREQUENCY: f32 = 40.0;
+const MAX_FREQUENCY: f32 = 16_000.0;
+/// 棒の高さにする音量の範囲（dBFS）。これより小さい音は高さ 0
+const FLOOR_DB: f32 = -60.0;
+/// 棒が 1 秒で下がる高さ（全体の高さに対する割合）
+const FALL_PER_SECOND: f32 = 1.5;
+/// 棒の幅と、棒の間の隙間（列数）
+const BAR_WIDTH: u32 = 2;
+const BAR_GAP: u32 = 1;
+/// 1 行あたりのピクセル数
+const PIXELS_PER_ROW: u32 = 4;
+
+/// 棒の色（下から上へ）
+const LOW_COLOR: [u8; 3] = [40, 220, 90];
+const MID_COLOR: [u8; 3] = [250, 220, 50];
+const HIGH_COLOR: [u8; 3] = [250, 60, 40];
+
+/// オシロスコープで画面の幅に並べる時間
+const SCOPE_WINDOW: Duration = Duration::from_millis(50);
+/// 波形の線の太さ（ピクセル）。文字 1 つに縮めても薄くなりすぎないようにする
+const SCOPE_LINE_WIDTH: u32 = 2;
+const SCOPE_COLOR: [u8; 3] = [90, 255, 140];
+/// 無音の位置に引く線の色
+const SCOPE_AXIS_COLOR: [u8; 3] = [40, 70, 50];
+
+/// 視覚化の種類
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum VisualizerKind {
+    /// 周波数ごとの音量の棒グラフ
+    Spectrum,
+    /// 最近の波形を左へ流れる線で描く
+    #[value(alias = "scope")]
+    Oscilloscope,
+}
+
+impl VisualizerKind {
+    pub fn name(self) -> &'static str {
+        match self {
+            VisualizerKind::Spectrum => "spectrum",
+            VisualizerKind::Oscilloscope => "oscilloscope",
+        }
+    }
+
+    /// `v` キーで次に切り替える種類
+    pub fn next(self) -> Self {
+        match self {
+            VisualizerKind::Spectrum => VisualizerKind::Oscilloscope,
+            VisualizerKind::Oscilloscope => VisualizerKind::Spectrum,
+        }
+    }
+}
+
+/// 視覚化の状態（前のフレームからの棒の動きなど）
+#[derive(Debug)]
+pub struct Visualizer {
+    kind: VisualizerKind,
+    spectrum: Spectrum,
+}
+
+impl Visualizer {
+    pub fn new(kind: VisualizerKind) -> Self {
+        Self {
+            kind,
+            spectrum: Spectrum::new(),
+        }
+    }
+
+    /// 次の種類に切り替え、切り替えた種類を返す
+    pub fn cycle(&mut self) -> VisualizerKind {
+        self.kind = self.kind.next();
+        self.kind
+    }
+
+    /// ターミナルの大きさ（列数・行数）に合わせて 1 フレームを作る
+    ///
+    /// `elapsed` は前のフレームからの時間（棒が下がる速さに使う）
+    pub fn frame(
+        &mut self,
+        audio: &RecentAudio,
+        elapsed: Duration,
+        columns: u32,
+        rows: u32,
+    ) -> VideoFrame {
+        let width = columns.max(1);
+        let height = rows.max(1) * PIXELS_PER_ROW;
+        match self.kind {
+            VisualizerKind::Spectrum => {
+                let bands = (width / (BAR_WIDTH + BAR_GAP)).max(1) as usize;
+                self.spectrum.update(audio, bands, elapsed);
+                self.spectrum.render(width, height)
+            }
+            VisualizerKind::Oscilloscope => render_scope(audio, width, height),
+        }
+    }
+}
+
+/// 最近の `SCOPE_WINDOW` の波形を描く（右端が最新）
+///
+/// 1 列に入るサンプルの最小値から最大値までと、前の列から続く部分を塗るので、
+/// 列より多いサンプルを詰めても線が途切れない
+fn render_scope(audio: &RecentAudio, width: u32, height: u32) -> VideoFrame {
+    let mut raster = Raster::new(width, height);
+    let center = height / 2;
+    raster.fill_rect(0, center as i32, width, 1, SCOPE_AXIS_COLOR);
+
+    let window = (audio.sample_rate as f32 * SCOPE_WINDOW.as_secs_f32()) as usize;
+    let samples = &audio.samples[audio.samples.len().saturating_sub(window)..];
+    if samples.is_empty() {
+        return raster.into_frame(Duration::ZERO);
+    }
+
+    // 振幅 1.0 が上下の端になる
+    let to_y = |sample: f32| {
+        let y = (1.0 - sample.clamp(-1.0, 1.0)) * 0.5 * (height - 1) as f32;
+        y.round() as i32
+    };
+    // 足りない分は左側を無音にする
+    let offset = window.max(samples.len()) - samples.len();
+    let sample_at = |index: usize| index.checked_sub(offset).map_or(0.0, |i| samples[i]);
+    let mut previous = to_y(sample_at(0));
+    for x in 0..width as usize {
+        let first = x * window / width as usize;
+        let last = ((x + 1) * window / width as usize).max(first + 1);
+        let (mut top, mut bottom) = (previous, previous);
+        for index in first..last {
+            let y = to_y(sample_at(index));
+            top = top.min(y);
+            bottom = bottom.max(y);
+            previous = y;
+        }
+        raster.fill_rect(
+            x as i32,
+            top - (SCOPE_LINE_WIDTH / 2) as i32,
+            1,
+            (bottom - top) as u32 + SCOPE_LINE_WIDTH,
+            SCOPE_COLOR,
+        );
+    }
+    raster.into_frame(Duration::ZERO)
+}
+
+/// 周波数ごとの棒の高さ
+#[derive(Debug)]
+struct Spectrum {
+    /// Hann 窓
+    window: Vec<f32>,
+    /// 棒の高さ（0.0〜1.0、低い周波数から）
+    levels: Vec<f32>,
+}
+
+impl Spectrum {
+    fn new() -> Self {
+        let window = (0..FFT_SIZE)
+            .map(|i| 0.5 - 0.5 * (2.0 * PI * i as f32 / FFT_SIZE as f32).cos())
+            .collect();
+        Self {
+            window,
+            levels: Vec::new(),
+        }
+    }
+
+    /// 最近のサンプルから棒の高さを更新する。上がるときはすぐ、下がるときはゆっくり動かす
+    fn update(&mut self, audio: &RecentAudio, bands: usize, elapsed: Duration) {
+        if self.levels.len() != bands {
+            self.levels = vec![0.0; bands];
+        }
+        let targets = band_levels(&self.window, audio, bands);
+        let fall = FALL_PER_SECOND * elapsed.as_secs_f32();
+        for (level, target) in self.levels.iter_mut().zip(targets) {
+            *level = target.max(*level - fall).max(0.0);
+        }
+    }
+
+    fn render(&self, width: u32, height: u32) -> VideoFrame {
+        let mut raster = Raster::new(width, height);
+        for (band, &level) in self.levels.iter().enumerate() {
+            let x = band as u32 * (BAR_WIDTH + BAR_GAP);
+            let bar_height = (level * height as f32).round() as u32;
+            for y in height - bar_height.min(height)..height {
+                let color = bar_color(1.0 - y as f32 / height as f32);
+                raster.fill_rect(x as i32, y as i32, BAR_WIDTH, 1, color);
+            }
+        }
+        raster.into_frame(Duration::ZERO)
+    }
+}
+
+/// 高さ（0.0〜1.0）での棒の色
+fn bar_color(height: f32) -> [u8; 3] {
+    let mix = |from: [u8; 3], to: [u8; 3], t: f32| {
+        [0, 1, 2].map(|i| (from[i] as f32 + (to[i] as f32 - from[i] as f32) * t) as u8)
+    };
+    if height < 0.5 {
+        mix(LOW_COLOR, MID_COLOR, height * 2.0)
+    } else {
+        mix(MID_COLOR, HIGH_COLOR, (height - 0.5) * 2.0)
+    }
+}
+
+/// 対数で等間隔に分けた周波数帯ごとの音量（0.0〜1.0）
+fn band_levels(window: &[f32], audio: &RecentAudio, bands: usize) -> Vec<f32> {
+    if audio.samples.is_empty() || audio.sample_rate == 0 {
+        return vec![0.0; bands];
+    }
+
+    // 足りない分は先頭を無音で埋める
+    let mut re = vec![0.0; FFT_SIZE];
+    let mut im = vec![0.0; FFT_SIZE];
+    let samples = &audio.samples[audio.samples.len().saturating_sub(FFT_SIZE)..];
+    let offset = FFT_SIZE - samples.len();
+    for (i, &sample) in samples.iter().enumerate() {
+        re[offset + i] = sample * window[offset + i];
+    }
+    fft(&mut re, &mut im);
+
+    // 窓をかけた正弦波の振幅が 1.0 になるように割る
+    let scale = 2.0 / window.iter().sum::<f32>();
+    let bin_hz = audio.sample_rate as f32 / FFT_SIZE as f32;
+    let top = MAX_FREQUENCY.min(audio.sample_rate as f32 / 2.0);
+    let ratio = (top / MIN_FREQUENCY).max(1.0).powf(1.0 / bands as f32);
+    (0..bands)
+        .map(|band| {
+            let low = MIN_FREQUENCY * ratio.powi(band as i32);
+            let high = low * ratio;
+            let first = ((low / bin_hz) as usize).clamp(1, FFT_SIZE / 2 - 1);
+            let last = ((high / bin_hz).ceil() as usize).clamp(first + 1, FFT_SIZE / 2);
+            let amplitude = (first..last)
+                .map(|bin| (re[bin] * re[bin] + im[bin] * im[bin]).sqrt() * scale)
+                .fold(0.0, f32::max);
+            let db = 20.0 * amplitude.max(1e-9).log10();
+            ((db - FLOOR_DB) / -FLOOR_DB).clamp(0.0, 1.0)
+        })
+        .collect()
+}
+
+/// 基数 2 の FFT（その場で変換する。長さは 2 の累乗）
+fn fft(re: &mut [f32], im: &mut [f32]) {
+    let n = re.len();
+    debug_assert!(n.is_power_of_two() && im.len() == n);
+
+    // ビット反転の順に並べ替える
+    let mut j = 0;
+    for i in 1..n {
+        let mut bit = n >> 1;
+        while j & bit != 0 {
+            j ^= bit;
+            bit >>= 1;
+        }
+        j |= bit;
+        if i < j {
+            re.swap(i, j);
+            im.swap(i, j);
+        }
+    }
+
+    let mut length = 2;
+    while length <= n {
+        let angle = -2.0 * PI / length as f32;
+        for start in (0..n).step_by(length) {
+            for k in 0..length / 2 {
+                let (sin, cos) = (angle * k as f32).sin_cos();
+                let a = start + k;
+                let b = a + length / 2;
+                let t_re = re[b] * cos - im[b] * sin;
+                let t_im = re[b] * sin + im[b] * cos;
+                re[b] = re[a] - t_re;
+                im[b] = im[a] - t_im;
+                re[a] += t_re;
+                im[a] += t_im;
+            }
+        }
+        length <<= 1;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sine(frequency: f32, amplitude: f32) -> RecentAudio {
+        let sample_rate = 48_000;
+        RecentAudio {
+            samples: (0..FFT_SIZE)
+                .map(|i| amplitude * (2.0 * PI * frequency * i as f32 / sample_rate as f32).sin())
+                .collect(),
+            sample_rate,
+        }
+    }
+
+    #[test]
+    fn test_fft_of_impulse_and_constant() {
+        let mut re = vec![1.0, 0.0, 0.0, 0.0];
+        let mut im = vec![0.0; 4];
+        fft(&mut re, &mut im);
+        assert_eq!(re, [1.0; 4]);
+
+        let mut re = vec![1.0; 8];
+        let mut im = vec![0.0; 8];
+        fft(&mut re, &mut im);
+        assert!((re[0] - 8.0).abs() < 1e-5);
+        assert!(re[1..].iter().chain(&im).all(|v| v.abs() < 1e-5));
+    }
+
+    #[test]
+    fn test_sine_peaks_in_its_band() {
+        let spectrum = Spectrum::new();
+        let levels = band_levels(&spectrum.window, &sine(1000.0, 0.5), 24);
+        let loudest = (0..levels.len())
+            .max_by(|&a, &b| levels[a].total_cmp(&levels[b]))
+            .unwrap();
+        let ratio = (MAX_FREQUENCY / MIN_FREQUENCY).powf(1.0 / 24.0);
+        let low = MIN_FREQUENCY * ratio.powi(loudest as i32);
+        // 帯の境目は FFT の周波数の刻み（約 23 Hz）に丸められる
+        let bin_hz = 48_000.0 / FFT_SIZE as f32;
+        let bin = (1000.0 / bin_hz).round();
+        assert!(
+            (low / bin_hz).floor() <= bin && bin < (low * ratio / bin_hz).ceil(),
+            "band {}",
+            loudest
+        );
+        // -6 dBFS 付近
+        assert!((levels[loudest] - 0.9).abs() < 0.05, "{}", levels[loudest]);
+        assert!(levels[0] < 0.3);
+
+        let silence = band_levels(&spectrum.window, &RecentAudio::default(), 24);
+        assert_eq!(silence, vec![0.0; 24]);
+    }
+
+    #[test]
+    fn test_bars_fall_slowly() {
+        let mut spectrum = Spectrum::new();
+        spectrum.update(&sine(1000.0, 0.5), 8, Duration::ZERO);
+        let peak = spectrum.levels.iter().cloned().fold(0.0, f32::max);
+        spectrum.update(&RecentAudio::default(), 8, Duration::from_millis(100));
+        let fallen = spectrum.levels.iter().cloned().fold(0.0, f32::max);
+        assert!((peak - fallen - 0.15).abs() < 1e-4);
+    }
+
+    #[test]
+    fn test_scope_draws_the_waveform() {
+        let mut visualizer = Visualizer::new(VisualizerKind::Oscilloscope);
+        let pixel = |frame: &VideoFrame, x: u32, y: u32| {
+            let index = ((y * frame.width + x) * 3) as usize;
+            [
+                frame.data[index],
+                frame.data[index + 1],
+                frame.data[index + 2],
+            ]
+        };
+
+        // 無音は中央の線だけ
+        let frame = visualizer.frame(&RecentAudio::default(), Duration::ZERO, 40, 10);
+        assert_eq!(pixel(&frame, 0, 20), SCOPE_AXIS_COLOR);
+        assert_eq!(pixel(&frame, 0, 0), [0, 0, 0]);
+
+        // 振幅 1.0 の正弦波は上下の端まで届く
+        let frame = visualizer.frame(&sine(200.0, 1.0), Duration::ZERO, 40, 10);
+        let column_touches = |y: u32| (0..40).any(|x| pixel(&frame, x, y) == SCOPE_COLOR);
+        assert!(column_touches(0));
+        assert!(column_touches(39));
+    }
+
+    #[test]
+    fn test_cycle_kinds() {
+        let mut visualizer = Visualizer::new(VisualizerKind::Spectrum);
+        assert_eq!(visualizer.cycle(), VisualizerKind::Oscilloscope);
+        assert_eq!(visualizer.cycle().name(), "spectrum");
+    }
+
+    #[test]
+    fn test_frame_size() {
+        let mut visualizer = Visualizer::new(VisualizerKind::Spectrum);
+        let frame = visualizer.frame(&sine(440.0, 1.0), Duration::ZERO, 80, 24);
+        assert_eq!((frame.width, frame.height), (80, 96));
+        // 上の端は背景、下の端には棒がある
+        assert_eq!(frame.data[..3], [0, 0, 0]);
+        let bottom = (95 * 80) * 3;
+        assert!(
+            frame.data[bottom..]
+                .chunks(3)
+                .any(|pixel| pixel != [0, 0, 0])
+        );
+    }
+}
@@ -0,0 +1,159 @@
+//! 左右チャンネルの音声レベルメーター（VU メーター）
+//!
+//! RMS をバーの長さ、ピークを縦線で表す。`-60 dB`〜`0 dB` を線形に割り当て、
+//! `-15 dB` から黄色、`-6 dB` から赤で表示する
+
+use crate::audio::ChannelLevel;
+use crate::renderer::{Cell, RenderedFrame, Rgb};
+
+/// メーターの下限
+const MIN_DB: f32 = -60.0;
+
+/// 黄色・赤に変わる位置（バーの長さに対する割合）
+const WARN_RATIO: f32 = (MIN_DB + 15.0) / MIN_DB;
+const CLIP_RATIO: f32 = (MIN_DB + 6.0) / MIN_DB;
+
+const SAFE_COLOR: Rgb = [80, 220, 80];
+const WARN_COLOR: Rgb = [230, 200, 60];
+const CLIP_COLOR: Rgb = [240, 70, 60];
+const EMPTY_COLOR: Rgb = [70, 70, 70];
+const TEXT_COLOR: Rgb = [200, 200, 200];
+
+/// 動画に重ねるときのメーターの最大幅
+const OVERLAY_WIDTH: usize = 40;
+
+/// 振幅を dBFS にする（下限で打ち切る）
+fn to_db(amplitude: f32) -> f32 {
+    if amplitude <= 0.0 {
+        MIN_DB
+    } else {
+        (20.0 * amplitude.log10()).max(MIN_DB)
+    }
+}
+
+/// バーの長さに対する割合
+fn to_ratio(amplitude: f32) -> f32 {
+    1.0 - to_db(amplitude) / MIN_DB
+}
+
+/// 1 チャンネル分のメーター（`L ██████····│·· -12 dB`）
+pub fn meter_cells(label: char, level: ChannelLevel, width: usize) -> Vec<Cell> {
+    let db = to_db(level.rms);
+    let text = if db <= MIN_DB {
+        "  -inf".to_string()
+    } else {
+        format!("{:>3.0} dB", db)
+    };
+
+    let bar_width = width.saturating_sub(3 + text.chars().count());
+    let filled = (to_ratio(level.rms) * bar_width as f32).round() as usize;
+    let peak = (to_ratio(level.peak) * bar_width as f32).round() as usize;
+
+    let mut cells = vec![Cell::new(label, TEXT_COLOR), Cell::BLANK];
+    cells.extend((0..bar_width).map(|i| {
+        let position = (i as f32 + 0.5) / bar_width as f32;
+        let color = if position >= CLIP_RATIO {
+            CLIP_COLOR
+        } else if position >= WARN_RATIO {
+            WARN_COLOR
+        } else {
+            SAFE_COLOR
+        };
+        if i < filled {
+            Cell::new('█', color)
+        } else if peak > 0 && i == peak - 1 {
+            Cell::new('│', color)
+        } else {
+            Cell::new('·', EMPTY_COLOR)
+        }
+    }));
+    cells.push(Cell::BLANK);
+    cells.extend(text.chars().map(|ch| Cell::new(ch, TEXT_COLOR)));
+    cells.truncate(width);
+    cells
+}
+
+/// 左右のメーター
+pub fn meter_rows(levels: [ChannelLevel; 2], width: usize) -> [Vec<Cell>; 2] {
+    [
+        meter_cells('L', levels[0], width),
+        meter_cells('R', levels[1], width),
+    ]
+}
+
+/// 動画の左下にメーターを黒地で重ねる
+pub fn overlay(frame: &mut RenderedFrame, levels: [ChannelLevel; 2]) {
+    let (width, height) = (frame.width as usize, frame.height as usize);
+    if height < 2 {
+        return;
+    }
+
+    for (i, row) in meter_rows(levels, width.min(OVERLAY_WIDTH))
+        .iter()
+        .enumerate()
+    {
+        let start = (height - 2 + i) * width;
+        for (x, cell) in row.iter().enumerate() {
+            frame.cells[start + x] = Cell {
+                bg: Some([0, 0, 0]),
+                ..*cell
+            };
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn text(cells: &[Cell]) -> String {
+        cells.iter().map(|cell| cell.ch).collect()
+    }
+
+    #[test]
+    fn test_meter_levels() {
+        let silent = meter_cells('L', ChannelLevel::default(), 20);
+        assert_eq!(text(&silent), "L ···········   -inf");
+        assert_eq!(silent.len(), 20);
+
+        let full = meter_cells(
+            'R',
+            ChannelLevel {
+                peak: 1.0,
+                rms: 1.0,
+            },
+            20,
+        );
+        assert_eq!(text(&full), "R ███████████   0 dB");
+        assert_eq!(full[2].fg, SAFE_COLOR);
+        assert_eq!(full[12].fg, CLIP_COLOR);
+    }
+
+    #[test]
+    fn test_meter_peak_marker() {
+        // RMS 約 -30 dB、ピーク約 -6 dB
+        let level = ChannelLevel {
+            peak: 0.5,
+            rms: 0.03,
+        };
+        let cells = meter_cells('L', level, 22);
+        assert_eq!(text(&cells), "L ██████·····│· -30 dB");
+    }
+
+    #[test]
+    fn test_overlay_bottom_left() {
+        let mut frame = RenderedFrame {
+            cells: vec![Cell::new('#', [9; 3]); 30 * 4],
+            width: 30,
+            height: 4,
+            sixel: None,
+        };
+        overlay(&mut frame, [ChannelLevel::default(); 2]);
+
+        let rows: Vec<String> = frame.rows().map(text).collect();
+        assert_eq!(rows[1], "#".repeat(30));
+        assert!(rows[2].starts_with("L ·"));
+        assert!(rows[3].starts_with("R ·"));
+        assert_eq!(frame.cells[2 * 30].bg, Some([0, 0, 0]));
+    }
+}
@@ -0,0 +1,203 @@
+//! あとで見る入力のキュー
+//!
+//! `--enqueue` で入力を 1 行ずつファイルへ追記しておき、再生中のプレイヤーが
+//! 今のメディアを見終えたとき、または次に入力なしで起動したときに先頭から取り出す。
+//! テキストファイルなので、手で並べ替えたり消したりしてもよい。
+//! 複数のプレイヤーから同時に追加・取り出しをしても入力を失わないよう、
+//! 読み書きの間はキューの隣のロックファイルを排他ロックする
+
+use std::fs::{self, File, OpenOptions};
+use std::io::{ErrorKind, Write};
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use tempfile::NamedTempFile;
+
+use crate::playback_state;
+
+/// キューのファイルの名前
+const QUEUE_FILE_NAME: &str = "watch-later.txt";
+
+/// あとで見る入力のキュー
+#[derive(Debug, Clone)]
+pub struct WatchQueue {
+    path: PathBuf,
+}
+
+impl WatchQueue {
+    /// 状態ファイルと同じディレクトリのキュー（場所が決められなければ None）
+    pub fn open_default() -> Option<Self> {
+        Some(Self::new(
+            playback_state::state_dir()?.join(QUEUE_FILE_NAME),
+        ))
+    }
+
+    pub fn new(path: PathBuf) -> Self {
+        Self { path }
+    }
+
+    /// 末尾に追加し、追加後の件数を返す
+    ///
+    /// ローカルファイルは別のディレクトリで起動したプレイヤーからも開けるよう絶対パスにする
+    pub fn push(&self, input: &str) -> Result<usize> {
+        let _lock = self.lock()?;
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)
+            .with_context(|| format!("Failed to open {}", self.path.display()))?;
+        writeln!(file, "{}", playback_state::media_key(input.trim()))?;
+        Ok(self.entries()?.len())
+    }
+
+    /// 先頭の入力を取り出す（空なら None）
+    pub fn pop(&self) -> Result<Option<String>> {
+        let _lock = self.lock()?;
+        let mut entries = self.entries()?;
+        if entries.is_empty() {
+            return Ok(None);
+        }
+        let first = entries.remove(0);
+
+        // 書き込み途中で終了しても残りが失われないよう、同じディレクトリに作った
+        // 一時ファイル（名前は毎回変わる）から置き換える
+        let rest: String = entries.iter().map(|entry| format!("{}\n", entry)).collect();
+        let mut temp_file = NamedTempFile::new_in(self.dir())?;
+        temp_file.write_all(rest.as_bytes())?;
+        temp_file
+            .persist(&self.path)
+            .with_context(|| format!("Failed to update {}", self.path.display()))?;
+        Ok(Some(first))
+    }
+
+    /// キューのあるディレクトリ
+    fn dir(&self) -> &Path {
+        self.path.parent().unwrap_or(Path::new("."))
+    }
+
+    /// ロックファイルを排他ロックする（返したファイルを閉じると外れる）
+    ///
+    /// 別のプレイヤーが読み書き中なら終わるまで待つ
+    fn lock(&self) -> Result<File> {
+        let dir = self.dir();
+        fs::create_dir_all(dir).with_context(|| format!("Failed to create {}", dir.display()))?;
+        let lock_path = self.path.with_extension("txt.lock");
+        let file = OpenOptions::new()
+            .create(true)
+            .truncate(false)
+            .write(true)
+            .open(&lock_path)
+            .with_context(|| format!("Failed to open {}", lock_path.display()))?;
+        file.lock()
+            .with_context(|| format!("Failed to lock {}", lock_path.display()))?;
+        Ok(file)
+    }
+
+    /// キューの入力（空行は除く）
+    fn entries(&self) -> Result<Vec<String>> {
+        match fs::read_to_string(&self.path) {
+            Ok(text) => Ok(text
+                .lines()
+                .map(str::trim)
+                .filter(|line| !line.is_empty())
+                .map(str::to_string)
+                .collect()),
+            Err(e) if e.kind() == ErrorKind::NotFound => Ok(Vec::new()),
+            Err(e) => Err(e).with_context(|| format!("Failed to read {}", self.path.display())),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_queue(name: &str) -> WatchQueue {
+        let path = std::env::temp_dir()
+            .join(format!("ascii-term-queue-{}-{}", name, std::process::id()))
+            .join(QUEUE_FILE_NAME);
+        let _ = fs::remove_file(&path);
+        WatchQueue::new(path)
+    }
+
+    #[test]
+    fn test_push_and_pop_in_order() {
+        let queue = temp_queue("order");
+        assert_eq!(queue.pop().unwrap(), None);
+
+        assert_eq!(queue.push("https://example.com/a.mp4").unwrap(), 1);
+        assert_eq!(queue.push("https://example.com/b.mp4\n").unwrap(), 2);
+        assert_eq!(
+            queue.pop().unwrap().as_deref(),
+            Some("https://example.com/a.mp4")
+        );
+        assert_eq!(
+            queue.pop().unwrap().as_deref(),
+            Some("https://example.com/b.mp4")
+        );
+        assert_eq!(queue.pop().unwrap(), None);
+
+        let _ = fs::remove_dir_all(queue.path.parent().unwrap());
+    }
+
+    #[test]
+    fn test_local_files_become_absolute() {
+        let queue = temp_queue("absolute");
+        let dir = queue.path.parent().unwrap().to_path_buf();
+        fs::create_dir_all(&dir).unwrap();
+        let media = dir.join("clip.mp4");
+        fs::write(&media, b"").unwrap();
+
+        queue
+            .push(&dir.join(".").join("clip.mp4").to_string_lossy())
+            .unwrap();
+        // 手で書いた空行は読み飛ばす
+        fs::write(
+            &queue.path,
+            format!("\n{}\n\n", fs::read_to_string(&queue.path).unwrap()),
+        )
+        .unwrap();
+        assert_eq!(
+            queue.pop().unwrap(),
+            Some(
+                fs::canonicalize(&media)
+                    .unwrap()
+                    .to_string_lossy()
+                    .into_owned()
+            )
+        );
+
+        let _ = fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn test_concurrent_push_and_pop_keep_every_entry() {
+        let queue = temp_queue("concurrent");
+        let pushers: Vec<_> = (0..8)
+            .map(|i| {
+                let queue = queue.clone();
+                std::thread::spawn(move || queue.push(&format!("https://example.com/{}.mp4", i)))
+            })
+            .collect();
+        for pusher in pushers {
+            pusher.join().unwrap().unwrap();
+        }
+
+        let poppers: Vec<_> = (0..8)
+            .map(|_| {
+                let queue = queue.clone();
+                std::thread::spawn(move || queue.pop())
+            })
+            .collect();
+        let mut popped: Vec<String> = poppers
+            .into_iter()
+            .map(|popper| popper.join().unwrap().unwrap().unwrap())
+            .collect();
+        popped.sort();
+        popped.dedup();
+        assert_eq!(popped.len(), 8);
+        assert_eq!(queue.pop().unwrap(), None);
+
+        let _ = fs::remove_dir_all(queue.path.parent().unwrap());
+    }
+}
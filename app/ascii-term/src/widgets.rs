@@ -0,0 +1,357 @@
+//! サイネージ向けのオーバーレイ（時計・ティッカー）
+//!
+//! `--widgets widgets.toml` で指定した設定に従い、映像の上に重ねて表示する。例:
+//!
+//! ```toml
+//! [clock]
+//! position = "top-right"   # top-left / top-right / bottom-left / bottom-right
+//! format = "%H:%M"         # strftime 形式
+//!
+//! [ticker]
+//! file = "news.txt"        # または url = "https://example.com/news.txt"
+//! position = "bottom"      # top / bottom
+//! speed = 12               # 1 秒あたりに流れる文字数
+//! refresh = 60             # 読み直す間隔（秒）
+//! ```
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, Instant};
+
+use anyhow::{Context, Result};
+use chrono::format::{Item, StrftimeItems};
+use downloader::NetworkOptions;
+use serde::Deserialize;
+
+use crate::canvas::sanitize;
+use crate::renderer::{Cell, RenderedFrame, Rgb};
+
+const CLOCK_COLOR: Rgb = [255, 255, 255];
+const TICKER_COLOR: Rgb = [255, 220, 120];
+const BACKGROUND: Rgb = [0, 0, 0];
+
+/// ティッカーの文の区切り（ファイルの行の間と、末尾から先頭へ戻る所）
+const TICKER_SEPARATOR: &str = "   •   ";
+
+/// 時計の位置
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Corner {
+    TopLeft,
+    #[default]
+    TopRight,
+    BottomLeft,
+    BottomRight,
+}
+
+/// ティッカーの位置
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Edge {
+    Top,
+    #[default]
+    Bottom,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ClockConfig {
+    #[serde(default)]
+    pub position: Corner,
+    #[serde(default = "default_clock_format")]
+    pub format: String,
+}
+
+fn default_clock_format() -> String {
+    "%H:%M:%S".to_string()
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TickerConfig {
+    /// 表示する文のファイル（1 行ずつ区切って流す）
+    pub file: Option<PathBuf>,
+    /// 表示する文の URL
+    pub url: Option<String>,
+    #[serde(default)]
+    pub position: Edge,
+    /// 1 秒あたりに流れる文字数
+    #[serde(default = "default_ticker_speed")]
+    pub speed: f64,
+    /// 読み直す間隔（秒）
+    #[serde(default = "default_ticker_refresh")]
+    pub refresh: u64,
+}
+
+fn default_ticker_speed() -> f64 {
+    10.0
+}
+
+fn default_ticker_refresh() -> u64 {
+    60
+}
+
+/// オーバーレイの設定
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct WidgetsConfig {
+    pub clock: Option<ClockConfig>,
+    pub ticker: Option<TickerConfig>,
+}
+
+impl WidgetsConfig {
+    /// 設定ファイルを読み込む
+    pub fn load(path: &Path) -> Result<Self> {
+        let text = fs::read_to_string(path)
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+        let mut config =
+            Self::parse(&text).with_context(|| format!("Invalid {}", path.display()))?;
+
+        // ティッカーのファイルは設定ファイルからの相対パス
+        if let Some(file) = config
+            .ticker
+            .as_mut()
+            .and_then(|ticker| ticker.file.as_mut())
+            && file.is_relative()
+            && let Some(dir) = path.parent()
+        {
+            *file = dir.join(&*file);
+        }
+        Ok(config)
+    }
+
+    /// TOML テキストを読み込む
+    pub fn parse(text: &str) -> Result<Self> {
+        let config: Self = toml::from_str(text)?;
+        if let Some(clock) = &config.clock
+            && StrftimeItems::new(&clock.format).any(|item| matches!(item, Item::Error))
+        {
+            anyhow::bail!("Invalid clock format: {}", clock.format);
+        }
+        if let Some(ticker) = &config.ticker {
+            if ticker.file.is_some() == ticker.url.is_some() {
+                anyhow::bail!("ticker needs either file or url");
+            }
+            if !(ticker.speed.is_finite() && ticker.speed > 0.0) {
+                anyhow::bail!("ticker speed must be greater than 0");
+            }
+            if ticker.refresh == 0 {
+                anyhow::bail!("ticker refresh must be at least 1 second");
+            }
+        }
+        Ok(config)
+    }
+}
+
+/// 表示中のオーバーレイ（ティッカーの文はバックグラウンドで読み直す）
+#[derive(Debug, Clone)]
+pub struct Widgets {
+    config: WidgetsConfig,
+    network: NetworkOptions,
+    ticker_text: Arc<RwLock<String>>,
+    started: Instant,
+}
+
+impl Widgets {
+    pub fn new(config: WidgetsConfig, network: NetworkOptions) -> Self {
+        Self {
+            config,
+            network,
+            ticker_text: Arc::default(),
+            started: Instant::now(),
+        }
+    }
+
+    /// ティッカーの文を定期的に読み直すタスクを開始する
+    pub fn spawn_refresh(&self) {
+        let Some(ticker) = self.config.ticker.clone() else {
+            return;
+        };
+        let network = self.network.clone();
+        let ticker_text = self.ticker_text.clone();
+        tokio::spawn(async move {
+            loop {
+                match load_ticker_text(&ticker, &network).await {
+                    Ok(text) => {
+                        if let Ok(mut current) = ticker_text.write() {
+                            *current = text;
+                        }
+                    }
+                    Err(e) => eprintln!("Warning: Failed to load ticker text: {:#}", e),
+                }
+                tokio::time::sleep(Duration::from_secs(ticker.refresh)).await;
+            }
+        });
+    }
+
+    /// 画面の大きさに合わせて、左上の位置（列・行）とオーバーレイの組を返す
+    pub fn render(&self, width: usize, height: usize) -> Vec<(u16, u16, RenderedFrame)> {
+        let mut overlays = Vec::new();
+        if width == 0 || height == 0 {
+            return overlays;
+        }
+
+        let ticker_row = self
+            .config
+            .ticker
+            .as_ref()
+            .map(|ticker| match ticker.position {
+                Edge::Top => 0,
+                Edge::Bottom => height - 1,
+            });
+
+        if let Some(ticker) = &self.config.ticker {
+            let text = self
+                .ticker_text
+                .read()
+                .map(|text| text.clone())
+                .unwrap_or_default();
+            if !text.is_empty() {
+                let offset = (self.started.elapsed().as_secs_f64() * ticker.speed) as usize;
+                let line = ticker_window(&text, offset, width);
+                overlays.push((0, ticker_row.unwrap_or(0) as u16, row(&line, TICKER_COLOR)));
+            }
+        }
+
+        if let Some(clock) = &self.config.clock {
+            let text = format!(" {} ", chrono::Local::now().format(&clock.format));
+            let text: String = text.chars().take(width).collect();
+            let text_width = text.chars().count();
+            let (x, mut y) = match clock.position {
+                Corner::TopLeft => (0, 0),
+                Corner::TopRight => (width - text_width, 0),
+                Corner::BottomLeft => (0, height - 1),
+                Corner::BottomRight => (width - text_width, height - 1),
+            };
+            // ティッカーと同じ行なら 1 行内側へずらす
+            if ticker_row == Some(y) && height > 1 {
+                y = if y == 0 { 1 } else { y - 1 };
+            }
+            overlays.push((x as u16, y as u16, row(&text, CLOCK_COLOR)));
+        }
+
+        overlays
+    }
+}
+
+/// 1 行のオーバーレイ
+fn row(text: &str, color: Rgb) -> RenderedFrame {
+    let cells: Vec<Cell> = text
+        .chars()
+        .map(|ch| Cell {
+            bg: Some(BACKGROUND),
+            ..Cell::new(ch, color)
+        })
+        .collect();
+    RenderedFrame {
+        width: cells.len() as u32,
+        height: 1,
+        cells,
+        sixel: None,
+    }
+}
+
+/// 流れる文のうち、`offset` 文字目から `width` 文字分
+fn ticker_window(text: &str, offset: usize, width: usize) -> String {
+    let looped: Vec<char> = text.chars().chain(TICKER_SEPARATOR.chars()).collect();
+    (0..width)
+        .map(|i| looped[(offset + i) % looped.len()])
+        .collect()
+}
+
+/// ファイル・URL の文を 1 行にまとめる（URL から取得した文もあるので、制御文字は取り除く）
+fn join_lines(text: &str) -> String {
+    text.lines()
+        .map(|line| sanitize(&line.split_whitespace().collect::<Vec<_>>().join(" ")))
+        .filter(|line| !line.is_empty())
+        .collect::<Vec<_>>()
+        .join(TICKER_SEPARATOR)
+}
+
+async fn load_ticker_text(ticker: &TickerConfig, network: &NetworkOptions) -> Result<String> {
+    let text = match (&ticker.file, &ticker.url) {
+        (Some(file), _) => fs::read_to_string(file)
+            .with_context(|| format!("Failed to read {}", file.display()))?,
+        (None, Some(url)) => {
+            network
+                .http_client()?
+                .get(url)
+                .send()
+                .await
+                .and_then(reqwest::Response::error_for_status)
+                .with_context(|| format!("Failed to fetch {}", url))?
+                .text()
+                .await?
+        }
+        (None, None) => String::new(),
+    };
+    Ok(join_lines(&text))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn text(frame: &RenderedFrame) -> String {
+        frame.cells.iter().map(|cell| cell.ch).collect()
+    }
+
+    #[test]
+    fn test_parse_widgets() {
+        let config = WidgetsConfig::parse(
+            r#"
+            [clock]
+            position = "bottom-left"
+            format = "%H:%M"
+
+            [ticker]
+            url = "https://example.com/news.txt"
+            position = "top"
+            "#,
+        )
+        .unwrap();
+        let clock = config.clock.unwrap();
+        assert_eq!(clock.position, Corner::BottomLeft);
+        assert_eq!(clock.format, "%H:%M");
+        let ticker = config.ticker.unwrap();
+        assert_eq!(ticker.position, Edge::Top);
+        assert_eq!((ticker.speed, ticker.refresh), (10.0, 60));
+
+        assert!(WidgetsConfig::parse("[ticker]\nspeed = 5").is_err());
+        assert!(WidgetsConfig::parse("[clock]\nformat = \"%Q\"").is_err());
+        assert!(WidgetsConfig::parse("[weather]").is_err());
+    }
+
+    #[test]
+    fn test_ticker_window_wraps_around() {
+        assert_eq!(ticker_window("abc", 0, 5), "abc  ");
+        assert_eq!(ticker_window("abc", 2, 3), "c  ");
+        let cycle = 3 + TICKER_SEPARATOR.chars().count();
+        assert_eq!(ticker_window("abc", cycle + 1, 2), "bc");
+        assert_eq!(
+            join_lines("First  news\n\n Second\n"),
+            format!("First news{}Second", TICKER_SEPARATOR)
+        );
+        assert_eq!(join_lines("\x1b[2JBreaking\x07\n"), "[2JBreaking");
+    }
+
+    #[test]
+    fn test_clock_moves_off_ticker_row() {
+        let config = WidgetsConfig::parse(
+            "[clock]\nposition = \"bottom-right\"\nformat = \"12:00\"\n[ticker]\nfile = \"news.txt\"",
+        )
+        .unwrap();
+        let widgets = Widgets::new(config, NetworkOptions::default());
+        *widgets.ticker_text.write().unwrap() = "News".to_string();
+
+        let overlays = widgets.render(40, 10);
+        assert_eq!(overlays.len(), 2);
+        let (x, y, ticker) = &overlays[0];
+        assert_eq!((*x, *y, ticker.width), (0, 9, 40));
+        assert!(text(ticker).starts_with("News"));
+        let (x, y, clock) = &overlays[1];
+        assert_eq!(text(clock), " 12:00 ");
+        assert_eq!((*x, *y), (33, 8));
+    }
+}
@@ -1,22 +1,37 @@
+use std::time::Duration;
+
+use avio::SeekMode;
+
 use crate::audio::frame::AudioFrame;
 use crate::errors::{MediaError, Result};
+use crate::network;
+use crate::track::{TrackFile, TrackKind};
 
 /// オーディオデコーダー
 pub struct AudioDecoder {
     inner: avio::AudioDecoder,
     frame_count: u64,
+    /// 最初のもの以外の音声トラックを選んだときに取り出したファイル（デコード中は消さない）
+    _track: Option<TrackFile>,
 }
 
 impl AudioDecoder {
-    /// パスからオーディオデコーダーを作成
-    pub fn new(path: &str) -> Result<Self> {
-        let inner = avio::AudioDecoder::open(path)
-            .build()
-            .map_err(MediaError::Decode)?;
+    /// パスからオーディオデコーダーを作成（URL の場合は再接続を有効にする）
+    ///
+    /// `stream` はデコードする音声ストリームのコンテナ内の番号（None なら最初の音声）
+    pub fn new(path: &str, stream: Option<u32>) -> Result<Self> {
+        let track = TrackFile::select(path, TrackKind::Audio, stream.map(|i| i as usize))?;
+        let path = track.as_ref().map_or(path, TrackFile::path);
+        let mut builder = avio::AudioDecoder::open(path);
+        if network::is_network_url(path) {
+            builder = builder.network(network::network_options(path));
+        }
+        let inner = builder.build().map_err(MediaError::Decode)?;
 
         Ok(Self {
             inner,
             frame_count: 0,
+            _track: track,
         })
     }
 
@@ -33,6 +48,18 @@ impl AudioDecoder {
         }
     }
 
+    /// 指定した再生位置へ移動する
+    pub fn seek(&mut self, position: Duration) -> Result<()> {
+        self.inner
+            .seek(position, SeekMode::Exact)
+            .map_err(MediaError::Decode)
+    }
+
+    /// ライブストリーム（終端・長さのない入力）かどうか
+    pub fn is_live(&self) -> bool {
+        self.inner.is_live()
+    }
+
     /// デコード済みフレーム数を取得
     pub fn frame_count(&self) -> u64 {
         self.frame_count
@@ -1,5 +1,7 @@
 pub mod decoder;
 pub mod frame;
+pub mod waveform;
 
 pub use decoder::AudioDecoder;
 pub use frame::{AudioFormat, AudioFrame};
+pub use waveform::{WaveformBuilder, peak_waveform};
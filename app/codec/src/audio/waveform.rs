@@ -0,0 +1,123 @@
+//! 音声全体を走査して、区間ごとの最大振幅（ピーク波形）を求める解析
+//!
+//! 再生位置のバーの下に小さな波形を描き、静かな部分・大きな部分を見分けられるようにする。
+//! 再生とは別にファイルを最初から最後までデコードするので、呼び出し側で別スレッドから使う
+
+use std::time::Duration;
+
+use crate::audio::decoder::AudioDecoder;
+use crate::audio::frame::AudioFrame;
+use crate::errors::Result;
+
+/// 読み飛ばせるエラーがこれだけ続いたら解析をやめる
+const MAX_CONSECUTIVE_ERRORS: u32 = 100;
+
+/// 区間ごとのピークを集める
+#[derive(Debug, Clone)]
+pub struct WaveformBuilder {
+    /// 1 区間の長さ（秒）
+    bucket_secs: f64,
+    peaks: Vec<f32>,
+}
+
+impl WaveformBuilder {
+    /// `duration` を `buckets` 個の区間に分ける
+    pub fn new(duration: Duration, buckets: usize) -> Self {
+        let buckets = buckets.max(1);
+        Self {
+            bucket_secs: duration.as_secs_f64() / buckets as f64,
+            peaks: vec![0.0; buckets],
+        }
+    }
+
+    /// フレームのサンプルを、タイムスタンプから求めた区間に加える
+    pub fn push(&mut self, frame: &AudioFrame) -> Result<()> {
+        let samples = frame.samples_as_f32()?;
+        let channels = frame.channels.max(1) as usize;
+        let start = frame.timestamp.as_secs_f64();
+        let last = self.peaks.len() - 1;
+
+        for (index, sample_frame) in samples.chunks(channels).enumerate() {
+            let time = start + index as f64 / frame.sample_rate.max(1) as f64;
+            let bucket = if self.bucket_secs > 0.0 {
+                ((time / self.bucket_secs) as usize).min(last)
+            } else {
+                last
+            };
+            let peak = sample_frame
+                .iter()
+                .fold(0.0f32, |peak, sample| peak.max(sample.abs()));
+            self.peaks[bucket] = self.peaks[bucket].max(peak.min(1.0));
+        }
+        Ok(())
+    }
+
+    /// 区間ごとのピーク（0.0〜1.0）
+    pub fn finish(self) -> Vec<f32> {
+        self.peaks
+    }
+}
+
+/// `path` の音声をすべてデコードし、`duration` を `buckets` 個に分けたピーク波形を返す
+///
+/// 壊れたフレームは飛ばす。ライブストリームは終わらないので渡さないこと
+pub fn peak_waveform(path: &str, duration: Duration, buckets: usize) -> Result<Vec<f32>> {
+    let mut decoder = AudioDecoder::new(path, None)?;
+    let mut builder = WaveformBuilder::new(duration, buckets);
+    let mut errors = 0;
+    loop {
+        match decoder.decode_one() {
+            Ok(Some(frame)) => {
+                errors = 0;
+                builder.push(&frame)?;
+            }
+            Ok(None) => break,
+            Err(e) if e.is_recoverable() && errors < MAX_CONSECUTIVE_ERRORS => errors += 1,
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(builder.finish())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::audio::frame::AudioFormat;
+
+    fn frame(samples: &[f32], channels: u16, timestamp: Duration) -> AudioFrame {
+        let data = samples.iter().flat_map(|s| s.to_le_bytes()).collect();
+        AudioFrame::new(
+            data,
+            samples.len() / channels as usize,
+            channels,
+            4,
+            AudioFormat::F32LE,
+            timestamp,
+            0,
+            false,
+        )
+    }
+
+    #[test]
+    fn test_peaks_per_bucket() {
+        // 4 Hz で 2 秒を 2 区間に分ける
+        let mut builder = WaveformBuilder::new(Duration::from_secs(2), 2);
+        builder
+            .push(&frame(&[0.1, -0.5, 0.2, 0.3], 1, Duration::ZERO))
+            .unwrap();
+        builder
+            .push(&frame(&[0.0, 0.25, -0.1, 0.0], 1, Duration::from_secs(1)))
+            .unwrap();
+        assert_eq!(builder.finish(), vec![0.5, 0.25]);
+    }
+
+    #[test]
+    fn test_samples_past_the_end_go_to_last_bucket() {
+        let mut builder = WaveformBuilder::new(Duration::from_secs(1), 4);
+        // ステレオは両チャンネルの大きい方をとる
+        builder
+            .push(&frame(&[0.1, -0.8], 2, Duration::from_secs(3)))
+            .unwrap();
+        assert_eq!(builder.finish(), vec![0.0, 0.0, 0.0, 0.8]);
+    }
+}
@@ -0,0 +1,147 @@
+//! 多数の静止画をまとめてデコード・加工する
+//!
+//! rayon のスレッドプールで並列に処理し、結果は入力の順に呼び出し元のスレッドで受け取る。
+//! 同時に処理する枚数を `max_in_flight` までに抑え、デコードした画像は縮小してから
+//! 加工するので、枚数が多くてもメモリ使用量は増えない（サムネイルの一括生成など）
+
+use std::path::Path;
+
+use image::DynamicImage;
+use rayon::ThreadPoolBuilder;
+use rayon::prelude::*;
+
+use crate::errors::{MediaError, Result};
+use crate::format::decode_image;
+
+/// まとめて処理するときの設定
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BatchConfig {
+    /// デコードした画像をこの大きさ（幅・高さ）に収まるよう縮小する（縦横比は保つ）
+    pub max_size: Option<(u32, u32)>,
+    /// 同時に処理する枚数の上限
+    pub max_in_flight: usize,
+    /// スレッド数（None なら CPU のコア数）
+    pub threads: Option<usize>,
+}
+
+impl Default for BatchConfig {
+    fn default() -> Self {
+        Self {
+            max_size: None,
+            max_in_flight: 16,
+            threads: None,
+        }
+    }
+}
+
+/// `paths` の画像をデコードして `process` で加工し、結果を入力の順に `sink` へ渡す
+///
+/// `process` は並列に呼ばれる。`sink` には入力の番号と結果を渡し、読み込めなかった
+/// 画像はエラーとして渡す（残りの処理は続ける）。スレッドプールを作れない場合だけエラーを返す
+pub fn render_batch<P, T, F, S>(
+    paths: &[P],
+    config: &BatchConfig,
+    process: F,
+    mut sink: S,
+) -> Result<()>
+where
+    P: AsRef<Path> + Sync,
+    T: Send,
+    F: Fn(&Path, DynamicImage) -> T + Sync,
+    S: FnMut(usize, Result<T>),
+{
+    let pool = ThreadPoolBuilder::new()
+        .num_threads(config.threads.unwrap_or(0))
+        .build()
+        .map_err(|e| MediaError::Pipeline(format!("Failed to start the thread pool: {}", e)))?;
+
+    let chunk_size = config.max_in_flight.max(1);
+    for (chunk_index, chunk) in paths.chunks(chunk_size).enumerate() {
+        let results: Vec<Result<T>> = pool.install(|| {
+            chunk
+                .par_iter()
+                .map(|path| {
+                    let path = path.as_ref();
+                    let image = fit(decode_image(path)?, config.max_size);
+                    Ok(process(path, image))
+                })
+                .collect()
+        });
+        for (offset, result) in results.into_iter().enumerate() {
+            sink(chunk_index * chunk_size + offset, result);
+        }
+    }
+    Ok(())
+}
+
+/// `max_size` より大きければ縮小する
+pub(crate) fn fit(image: DynamicImage, max_size: Option<(u32, u32)>) -> DynamicImage {
+    match max_size {
+        Some((width, height)) if image.width() > width || image.height() > height => {
+            image.thumbnail(width, height)
+        }
+        _ => image,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    /// 一時ディレクトリに `width` x `height` の PNG を書き出す
+    fn fixture(name: &str, width: u32, height: u32) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("codec-batch-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join(name);
+        image::RgbImage::from_pixel(width, height, image::Rgb([0, 128, 255]))
+            .save(&path)
+            .unwrap();
+        path
+    }
+
+    #[test]
+    fn test_results_keep_input_order() {
+        let paths: Vec<PathBuf> = (1..=7)
+            .map(|i| fixture(&format!("{}.png", i), i * 10, 5))
+            .collect();
+        let config = BatchConfig {
+            max_in_flight: 3,
+            threads: Some(2),
+            ..Default::default()
+        };
+
+        let mut widths = Vec::new();
+        render_batch(
+            &paths,
+            &config,
+            |_, image| image.width(),
+            |index, result| widths.push((index, result.unwrap())),
+        )
+        .unwrap();
+        let expected: Vec<(usize, u32)> = (0..7).map(|i| (i, (i as u32 + 1) * 10)).collect();
+        assert_eq!(widths, expected);
+    }
+
+    #[test]
+    fn test_downscales_and_reports_failures() {
+        let paths = vec![
+            fixture("large.png", 400, 200),
+            std::env::temp_dir().join("codec-batch-missing.png"),
+        ];
+        let config = BatchConfig {
+            max_size: Some((100, 100)),
+            ..Default::default()
+        };
+
+        let mut results = Vec::new();
+        render_batch(
+            &paths,
+            &config,
+            |_, image| (image.width(), image.height()),
+            |index, result| results.push((index, result.ok())),
+        )
+        .unwrap();
+        assert_eq!(results, [(0, Some((100, 50))), (1, None)]);
+    }
+}
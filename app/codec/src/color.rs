@@ -0,0 +1,367 @@
+//! 色の変換（sRGB とリニア、RGB と HSV / HSL、YCbCr、輝度）
+//!
+//! 描画・パレット・ディザリングなどで共通に使う基本の計算。RGB は 8 ビットの
+//! `[r, g, b]`、色相は度（0〜360）、彩度・明度・輝度は 0.0〜1.0 で表す
+
+use std::str::FromStr;
+
+/// 8 ビットの RGB
+pub type Rgb = [u8; 3];
+
+/// 輝度の重み（YCbCr の変換行列）の規格
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Matrix {
+    /// ITU-R BT.601（SD 映像や JPEG）
+    Bt601,
+    /// ITU-R BT.709（HD 映像）
+    #[default]
+    Bt709,
+    /// ITU-R BT.2020（UHD・HDR 映像。定輝度でない方式）
+    Bt2020,
+}
+
+impl Matrix {
+    /// 赤・緑・青の重み（合計 1.0）
+    pub fn weights(self) -> [f32; 3] {
+        match self {
+            Matrix::Bt601 => [0.299, 0.587, 0.114],
+            Matrix::Bt709 => [0.2126, 0.7152, 0.0722],
+            Matrix::Bt2020 => [0.2627, 0.6780, 0.0593],
+        }
+    }
+
+    /// `--color-matrix` などで指定する名前
+    pub fn name(self) -> &'static str {
+        match self {
+            Matrix::Bt601 => "bt601",
+            Matrix::Bt709 => "bt709",
+            Matrix::Bt2020 => "bt2020",
+        }
+    }
+
+    /// この規格の重みでの輝度
+    pub fn luminance(self, [r, g, b]: Rgb) -> u8 {
+        let [kr, kg, kb] = self.weights();
+        (kr * r as f32 + kg * g as f32 + kb * b as f32) as u8
+    }
+}
+
+impl FromStr for Matrix {
+    type Err = String;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        match text.trim().to_ascii_lowercase().replace('.', "").as_str() {
+            "bt601" | "601" => Ok(Matrix::Bt601),
+            "bt709" | "709" => Ok(Matrix::Bt709),
+            "bt2020" | "2020" => Ok(Matrix::Bt2020),
+            _ => Err(format!(
+                "unknown color matrix '{}' (expected bt601, bt709 or bt2020)",
+                text
+            )),
+        }
+    }
+}
+
+/// YCbCr の値の範囲
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Range {
+    /// 限定範囲（TV レンジ。Y は 16〜235、Cb / Cr は 16〜240）
+    #[default]
+    Limited,
+    /// フルレンジ（PC レンジ。すべて 0〜255）
+    Full,
+}
+
+impl Range {
+    /// `--color-range` などで指定する名前
+    pub fn name(self) -> &'static str {
+        match self {
+            Range::Limited => "limited",
+            Range::Full => "full",
+        }
+    }
+}
+
+impl FromStr for Range {
+    type Err = String;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        match text.trim().to_ascii_lowercase().as_str() {
+            "limited" | "tv" => Ok(Range::Limited),
+            "full" | "pc" => Ok(Range::Full),
+            _ => Err(format!(
+                "unknown color range '{}' (expected limited or full)",
+                text
+            )),
+        }
+    }
+}
+
+/// YCbCr の値の解釈（変換行列と範囲）
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct YuvFormat {
+    pub matrix: Matrix,
+    pub range: Range,
+}
+
+impl YuvFormat {
+    /// YCbCr を RGB に変換する
+    pub fn to_rgb(self, y: u8, cb: u8, cr: u8) -> Rgb {
+        let [kr, kg, kb] = self.matrix.weights();
+        // Y は 0.0〜1.0、Cb / Cr は -0.5〜0.5 にする
+        let (y, cb, cr) = match self.range {
+            Range::Limited => (
+                (y as f32 - 16.0) / 219.0,
+                (cb as f32 - 128.0) / 224.0,
+                (cr as f32 - 128.0) / 224.0,
+            ),
+            Range::Full => (
+                y as f32 / 255.0,
+                (cb as f32 - 128.0) / 255.0,
+                (cr as f32 - 128.0) / 255.0,
+            ),
+        };
+        let r = y + 2.0 * (1.0 - kr) * cr;
+        let b = y + 2.0 * (1.0 - kb) * cb;
+        let g = (y - kr * r - kb * b) / kg;
+        [r, g, b].map(|c| (c * 255.0).round().clamp(0.0, 255.0) as u8)
+    }
+}
+
+/// ITU-R BT.709 の輝度（sRGB の値をそのまま重み付けする）
+pub fn luminance(rgb: Rgb) -> u8 {
+    Matrix::Bt709.luminance(rgb)
+}
+
+/// ITU-R BT.601 の輝度（SD 映像や JPEG の Y）
+pub fn luminance_bt601(rgb: Rgb) -> u8 {
+    Matrix::Bt601.luminance(rgb)
+}
+
+/// BT.601（限定範囲）の YCbCr を RGB に変換する
+pub fn ycbcr_to_rgb(y: u8, cb: u8, cr: u8) -> Rgb {
+    let c = (y as i32 - 16) * 298;
+    let d = cb as i32 - 128;
+    let e = cr as i32 - 128;
+    let clamp = |value: i32| ((value + 128) >> 8).clamp(0, 255) as u8;
+    [
+        clamp(c + 409 * e),
+        clamp(c - 100 * d - 208 * e),
+        clamp(c + 516 * d),
+    ]
+}
+
+/// sRGB の値をリニア（光の強さに比例する 0.0〜1.0）にする
+pub fn srgb_to_linear(rgb: Rgb) -> [f32; 3] {
+    rgb.map(|channel| {
+        let c = channel as f32 / 255.0;
+        if c <= 0.04045 {
+            c / 12.92
+        } else {
+            ((c + 0.055) / 1.055).powf(2.4)
+        }
+    })
+}
+
+/// リニアの値を sRGB に戻す（範囲外は切り詰める）
+pub fn linear_to_srgb(linear: [f32; 3]) -> Rgb {
+    linear.map(|c| {
+        let c = c.clamp(0.0, 1.0);
+        let c = if c <= 0.0031308 {
+            c * 12.92
+        } else {
+            1.055 * c.powf(1.0 / 2.4) - 0.055
+        };
+        (c * 255.0).round() as u8
+    })
+}
+
+/// 色相（度）と、最大・最小の成分
+fn hue_of([r, g, b]: [f32; 3]) -> (f32, f32, f32) {
+    let max = r.max(g).max(b);
+    let min = r.min(g).min(b);
+    let delta = max - min;
+    let hue = if delta == 0.0 {
+        0.0
+    } else if max == r {
+        60.0 * ((g - b) / delta).rem_euclid(6.0)
+    } else if max == g {
+        60.0 * ((b - r) / delta + 2.0)
+    } else {
+        60.0 * ((r - g) / delta + 4.0)
+    };
+    (hue, max, min)
+}
+
+fn unit(rgb: Rgb) -> [f32; 3] {
+    rgb.map(|channel| channel as f32 / 255.0)
+}
+
+/// 色相・彩度・最大成分・最小成分の差から RGB を組み立てる
+fn from_hue(hue: f32, chroma: f32, min: f32) -> Rgb {
+    let h = hue.rem_euclid(360.0) / 60.0;
+    let x = chroma * (1.0 - (h % 2.0 - 1.0).abs());
+    let [r, g, b] = match h as u32 {
+        0 => [chroma, x, 0.0],
+        1 => [x, chroma, 0.0],
+        2 => [0.0, chroma, x],
+        3 => [0.0, x, chroma],
+        4 => [x, 0.0, chroma],
+        _ => [chroma, 0.0, x],
+    };
+    [r, g, b].map(|c| ((c + min) * 255.0).round().clamp(0.0, 255.0) as u8)
+}
+
+/// RGB を HSV（色相・彩度・明度）にする
+pub fn rgb_to_hsv(rgb: Rgb) -> (f32, f32, f32) {
+    let (hue, max, min) = hue_of(unit(rgb));
+    let saturation = if max == 0.0 { 0.0 } else { (max - min) / max };
+    (hue, saturation, max)
+}
+
+/// HSV を RGB にする
+pub fn hsv_to_rgb(hue: f32, saturation: f32, value: f32) -> Rgb {
+    let (saturation, value) = (saturation.clamp(0.0, 1.0), value.clamp(0.0, 1.0));
+    let chroma = value * saturation;
+    from_hue(hue, chroma, value - chroma)
+}
+
+/// RGB を HSL（色相・彩度・輝度）にする
+pub fn rgb_to_hsl(rgb: Rgb) -> (f32, f32, f32) {
+    let (hue, max, min) = hue_of(unit(rgb));
+    let lightness = (max + min) / 2.0;
+    let saturation = if max == min {
+        0.0
+    } else {
+        (max - min) / (1.0 - (2.0 * lightness - 1.0).abs())
+    };
+    (hue, saturation, lightness)
+}
+
+/// HSL を RGB にする
+pub fn hsl_to_rgb(hue: f32, saturation: f32, lightness: f32) -> Rgb {
+    let (saturation, lightness) = (saturation.clamp(0.0, 1.0), lightness.clamp(0.0, 1.0));
+    let chroma = (1.0 - (2.0 * lightness - 1.0).abs()) * saturation;
+    from_hue(hue, chroma, lightness - chroma / 2.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAMPLES: [Rgb; 8] = [
+        [0, 0, 0],
+        [255, 255, 255],
+        [255, 0, 0],
+        [0, 255, 0],
+        [0, 0, 255],
+        [255, 200, 0],
+        [12, 140, 200],
+        [128, 64, 200],
+    ];
+
+    #[test]
+    fn test_luminance() {
+        assert_eq!(luminance([255, 255, 255]), 255);
+        assert_eq!(luminance([0, 255, 0]), 182);
+        assert_eq!(luminance_bt601([0, 255, 0]), 149);
+        assert_eq!(luminance_bt601([255, 255, 255]), 255);
+        assert_eq!(Matrix::Bt2020.luminance([0, 255, 0]), 172);
+    }
+
+    #[test]
+    fn test_ycbcr_to_rgb() {
+        assert_eq!(ycbcr_to_rgb(16, 128, 128), [0, 0, 0]);
+        assert_eq!(ycbcr_to_rgb(235, 128, 128), [255, 255, 255]);
+        // 赤
+        let [r, g, b] = ycbcr_to_rgb(81, 90, 240);
+        assert!(r > 250 && g < 5 && b < 5, "{:?}", [r, g, b]);
+    }
+
+    #[test]
+    fn test_yuv_format_to_rgb() {
+        let bt709 = YuvFormat {
+            matrix: Matrix::Bt709,
+            range: Range::Limited,
+        };
+        assert_eq!(bt709.to_rgb(16, 128, 128), [0, 0, 0]);
+        assert_eq!(bt709.to_rgb(235, 128, 128), [255, 255, 255]);
+        let [r, g, b] = bt709.to_rgb(63, 102, 240);
+        assert!(r > 250 && g < 5 && b < 5, "{:?}", [r, g, b]);
+
+        // 限定範囲の値をフルレンジとして読むと、黒が浮いて白がくすむ
+        let full = YuvFormat {
+            range: Range::Full,
+            ..bt709
+        };
+        assert_eq!(full.to_rgb(0, 128, 128), [0, 0, 0]);
+        assert_eq!(full.to_rgb(255, 128, 128), [255, 255, 255]);
+        assert_eq!(full.to_rgb(16, 128, 128), [16, 16, 16]);
+
+        // BT.601 の赤を BT.709 で読むと色がずれる
+        let bt601 = YuvFormat {
+            matrix: Matrix::Bt601,
+            range: Range::Limited,
+        };
+        let exact = bt601.to_rgb(81, 90, 240);
+        let fixed = ycbcr_to_rgb(81, 90, 240);
+        assert!(
+            (0..3).all(|i| exact[i].abs_diff(fixed[i]) <= 1),
+            "{:?}",
+            exact
+        );
+        assert_ne!(bt709.to_rgb(81, 90, 240), bt601.to_rgb(81, 90, 240));
+    }
+
+    #[test]
+    fn test_parse_matrix_and_range() {
+        assert_eq!("bt.709".parse(), Ok(Matrix::Bt709));
+        assert_eq!("BT2020".parse(), Ok(Matrix::Bt2020));
+        assert_eq!("601".parse(), Ok(Matrix::Bt601));
+        assert!("srgb".parse::<Matrix>().is_err());
+        assert_eq!("full".parse(), Ok(Range::Full));
+        assert_eq!("tv".parse(), Ok(Range::Limited));
+        assert!("auto".parse::<Range>().is_err());
+        for matrix in [Matrix::Bt601, Matrix::Bt709, Matrix::Bt2020] {
+            assert_eq!(matrix.name().parse(), Ok(matrix));
+        }
+        for range in [Range::Limited, Range::Full] {
+            assert_eq!(range.name().parse(), Ok(range));
+        }
+    }
+
+    #[test]
+    fn test_srgb_linear_round_trip() {
+        assert_eq!(srgb_to_linear([0, 255, 0]), [0.0, 1.0, 0.0]);
+        // sRGB の中間値はリニアでは暗い側に寄る
+        assert!((srgb_to_linear([128, 128, 128])[0] - 0.2159).abs() < 1e-3);
+        for value in 0..=255u8 {
+            let rgb = [value, 255 - value, value / 2];
+            assert_eq!(linear_to_srgb(srgb_to_linear(rgb)), rgb);
+        }
+    }
+
+    #[test]
+    fn test_hsv() {
+        assert_eq!(rgb_to_hsv([255, 0, 0]), (0.0, 1.0, 1.0));
+        assert_eq!(rgb_to_hsv([0, 0, 255]), (240.0, 1.0, 1.0));
+        assert_eq!(rgb_to_hsv([128, 128, 128]).1, 0.0);
+        assert_eq!(hsv_to_rgb(120.0, 1.0, 1.0), [0, 255, 0]);
+        assert_eq!(hsv_to_rgb(-60.0, 1.0, 1.0), [255, 0, 255]);
+        for rgb in SAMPLES {
+            let (h, s, v) = rgb_to_hsv(rgb);
+            assert_eq!(hsv_to_rgb(h, s, v), rgb);
+        }
+    }
+
+    #[test]
+    fn test_hsl() {
+        assert_eq!(rgb_to_hsl([255, 0, 0]), (0.0, 1.0, 0.5));
+        assert_eq!(rgb_to_hsl([255, 255, 255]), (0.0, 0.0, 1.0));
+        assert_eq!(hsl_to_rgb(60.0, 1.0, 0.5), [255, 255, 0]);
+        for rgb in SAMPLES {
+            let (h, s, l) = rgb_to_hsl(rgb);
+            assert_eq!(hsl_to_rgb(h, s, l), rgb);
+        }
+    }
+}
@@ -30,4 +30,158 @@ pub enum MediaError {
 
     #[error("Pipeline error: {0}")]
     Pipeline(String),
+
+    /// 子プロセスでのデコードのエラー（`video::worker`）
+    #[error("Decode error: {message}")]
+    Worker { message: String, recoverable: bool },
+}
+
+impl MediaError {
+    /// ストリームの一時的な破損など、読み飛ばして続行できるエラーかどうか
+    pub fn is_recoverable(&self) -> bool {
+        match self {
+            MediaError::Decode(e) => e.is_recoverable(),
+            MediaError::Worker { recoverable, .. } => *recoverable,
+            _ => false,
+        }
+    }
+
+    /// ユーザーに案内するためのエラーの分類
+    pub fn category(&self) -> ErrorCategory {
+        let category = match self {
+            MediaError::Decode(e) => match e {
+                avio::DecodeError::FileNotFound { .. } => ErrorCategory::NotFound,
+                avio::DecodeError::UnsupportedCodec { .. }
+                | avio::DecodeError::DecoderUnavailable { .. } => ErrorCategory::MissingCodec,
+                avio::DecodeError::StreamCorrupted { .. } => ErrorCategory::TruncatedFile,
+                avio::DecodeError::NetworkTimeout { .. }
+                | avio::DecodeError::ConnectionFailed { .. }
+                | avio::DecodeError::StreamInterrupted { .. } => ErrorCategory::Network,
+                avio::DecodeError::Io(e) => ErrorCategory::from_io(e),
+                _ => ErrorCategory::Other,
+            },
+            MediaError::Probe(e) => match e {
+                avio::ProbeError::FileNotFound { .. } => ErrorCategory::NotFound,
+                avio::ProbeError::Io(e) => ErrorCategory::from_io(e),
+                _ => ErrorCategory::Other,
+            },
+            MediaError::Io(e) => ErrorCategory::from_io(e),
+            MediaError::UnsupportedCodec(_) => ErrorCategory::MissingCodec,
+            _ => ErrorCategory::Other,
+        };
+
+        // FFmpeg のエラーは種類ではなくメッセージで区別するしかない
+        match category {
+            ErrorCategory::Other => ErrorCategory::from_message(&self.to_string()),
+            category => category,
+        }
+    }
+}
+
+/// ユーザーに案内するためのエラーの分類
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorCategory {
+    NotFound,
+    PermissionDenied,
+    /// FFmpeg にデコーダーが含まれていない
+    MissingCodec,
+    /// ファイルが途中で切れている、または壊れている
+    TruncatedFile,
+    UnsupportedPixelFormat,
+    Network,
+    Other,
+}
+
+impl ErrorCategory {
+    /// 入出力エラーの分類
+    pub fn from_io(error: &std::io::Error) -> Self {
+        match error.kind() {
+            std::io::ErrorKind::NotFound => ErrorCategory::NotFound,
+            std::io::ErrorKind::PermissionDenied => ErrorCategory::PermissionDenied,
+            std::io::ErrorKind::UnexpectedEof => ErrorCategory::TruncatedFile,
+            _ => ErrorCategory::from_message(&error.to_string()),
+        }
+    }
+
+    /// FFmpeg などのエラーメッセージから分類する
+    fn from_message(message: &str) -> Self {
+        let message = message.to_ascii_lowercase();
+        let contains_any = |patterns: &[&str]| patterns.iter().any(|p| message.contains(p));
+
+        if contains_any(&["permission denied", "operation not permitted"]) {
+            ErrorCategory::PermissionDenied
+        } else if contains_any(&["no such file", "not found in"]) {
+            ErrorCategory::NotFound
+        } else if contains_any(&["decoder not found", "codec not supported", "unknown codec"]) {
+            ErrorCategory::MissingCodec
+        } else if contains_any(&["pixel format", "pix_fmt"]) {
+            ErrorCategory::UnsupportedPixelFormat
+        } else if contains_any(&[
+            "end of file",
+            "truncated",
+            "moov atom not found",
+            "invalid data found",
+        ]) {
+            ErrorCategory::TruncatedFile
+        } else {
+            ErrorCategory::Other
+        }
+    }
+
+    /// 対処方法
+    pub fn hint(self) -> Option<&'static str> {
+        match self {
+            ErrorCategory::NotFound => {
+                Some("Check the path or URL; quote paths that contain spaces.")
+            }
+            ErrorCategory::PermissionDenied => Some(
+                "Make the file readable (e.g. `chmod +r <file>`) or run as a user who can read it.",
+            ),
+            ErrorCategory::MissingCodec => Some(
+                "This FFmpeg build lacks the decoder. Install a full FFmpeg build, or convert with \
+                 `ffmpeg -i <input> -c:v libx264 -c:a aac output.mp4`.",
+            ),
+            ErrorCategory::TruncatedFile => Some(
+                "The file looks incomplete. Re-download it, or try remuxing with \
+                 `ffmpeg -i <input> -c copy fixed.mp4`.",
+            ),
+            ErrorCategory::UnsupportedPixelFormat => Some(
+                "Convert to a common pixel format with `ffmpeg -i <input> -pix_fmt yuv420p output.mp4`.",
+            ),
+            ErrorCategory::Network => {
+                Some("Check the network connection and the URL, then try again.")
+            }
+            ErrorCategory::Other => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_category_from_error_kind() {
+        let error = MediaError::Decode(avio::DecodeError::UnsupportedCodec {
+            codec: "prores".to_string(),
+        });
+        assert_eq!(error.category(), ErrorCategory::MissingCodec);
+
+        let error = MediaError::Io(std::io::Error::from(std::io::ErrorKind::PermissionDenied));
+        assert_eq!(error.category(), ErrorCategory::PermissionDenied);
+        assert!(error.category().hint().unwrap().contains("chmod"));
+
+        let error = MediaError::Pipeline("channel closed".to_string());
+        assert_eq!(error.category(), ErrorCategory::Other);
+        assert_eq!(error.category().hint(), None);
+    }
+
+    #[test]
+    fn test_category_from_ffmpeg_message() {
+        let error = MediaError::Video("moov atom not found".to_string());
+        assert_eq!(error.category(), ErrorCategory::TruncatedFile);
+
+        let error = MediaError::Video("Unsupported pixel format: 0x1234".to_string());
+        assert_eq!(error.category(), ErrorCategory::UnsupportedPixelFormat);
+    }
 }
@@ -0,0 +1,380 @@
+//! 画像形式の判定と静止画の読み込み
+//!
+//! ファイル先頭のマジックナンバー（なければ拡張子）から画像形式を判定する。
+//! PNG や JPEG は image クレートで、スマートフォンの写真に多い AVIF / HEIC /
+//! JPEG XL は FFmpeg で最初のフレームをデコードして読み込む
+
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+
+use image::{DynamicImage, ImageReader};
+
+use crate::errors::{MediaError, Result};
+use crate::video::VideoDecoder;
+
+/// 判定に必要なヘッダーの長さ
+const HEADER_LEN: usize = 32;
+
+/// 対応している画像形式
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SupportedImageFormat {
+    Png,
+    Jpeg,
+    Gif,
+    Bmp,
+    WebP,
+    Tiff,
+    Avif,
+    Heic,
+    Jxl,
+}
+
+impl SupportedImageFormat {
+    /// 拡張子から画像形式を判定（大文字小文字は区別しない）
+    pub fn from_extension(extension: &str) -> Option<Self> {
+        match extension.to_ascii_lowercase().as_str() {
+            "png" => Some(Self::Png),
+            "jpg" | "jpeg" => Some(Self::Jpeg),
+            "gif" => Some(Self::Gif),
+            "bmp" => Some(Self::Bmp),
+            "webp" => Some(Self::WebP),
+            "tif" | "tiff" => Some(Self::Tiff),
+            "avif" => Some(Self::Avif),
+            "heic" | "heif" => Some(Self::Heic),
+            "jxl" => Some(Self::Jxl),
+            _ => None,
+        }
+    }
+
+    /// ファイルの画像形式を判定する。ヘッダーで判定できなければ拡張子を使う
+    pub fn detect<P: AsRef<Path>>(path: P) -> Result<Option<Self>> {
+        let path = path.as_ref();
+        if let Some(format) = FormatDetector::detect_from_file(path)? {
+            return Ok(Some(format));
+        }
+        Ok(path
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .and_then(Self::from_extension))
+    }
+
+    /// 静止画として表示する形式かどうか
+    ///
+    /// GIF はアニメーションの可能性があるため FFmpeg で動画として扱う
+    pub fn is_still_image(self) -> bool {
+        self != Self::Gif
+    }
+
+    /// image クレートでデコードできる形式かどうか（それ以外は FFmpeg を使う）
+    pub fn decodes_natively(self) -> bool {
+        matches!(
+            self,
+            Self::Png | Self::Jpeg | Self::Gif | Self::Bmp | Self::WebP | Self::Tiff
+        )
+    }
+}
+
+/// 静止画を読み込む
+pub fn decode_image<P: AsRef<Path>>(path: P) -> Result<DynamicImage> {
+    let path = path.as_ref();
+    let format = SupportedImageFormat::detect(path)?;
+
+    if format.is_none_or(SupportedImageFormat::decodes_natively) {
+        // 拡張子がなくても読めるよう、形式はファイルの内容から推測する
+        return Ok(ImageReader::open(path)?.with_guessed_format()?.decode()?);
+    }
+
+    // AVIF / HEIC / JPEG XL は FFmpeg で最初のフレームを取り出す
+    let path_str = path
+        .to_str()
+        .ok_or_else(|| MediaError::InvalidFormat("Invalid path".to_string()))?;
+    let mut decoder = VideoDecoder::new(path_str, 0, 0, None)?;
+    let frame = decoder
+        .decode_one()?
+        .ok_or_else(|| MediaError::InvalidFormat(format!("No image data in {}", path.display())))?;
+    frame.to_dynamic_image()
+}
+
+/// マジックナンバーによる形式判定
+pub struct FormatDetector;
+
+impl FormatDetector {
+    /// ファイル先頭のバイト列から画像形式を判定
+    pub fn detect_from_header(header: &[u8]) -> Option<SupportedImageFormat> {
+        const PNG: &[u8] = b"\x89PNG\r\n\x1a\n";
+        const JXL_CONTAINER: &[u8] = b"\x00\x00\x00\x0cJXL \r\n\x87\n";
+
+        if header.starts_with(PNG) {
+            Some(SupportedImageFormat::Png)
+        } else if header.starts_with(&[0xFF, 0xD8, 0xFF]) {
+            Some(SupportedImageFormat::Jpeg)
+        } else if header.starts_with(b"GIF87a") || header.starts_with(b"GIF89a") {
+            Some(SupportedImageFormat::Gif)
+        } else if header.starts_with(b"BM") && header.get(6..10) == Some(&[0; 4][..]) {
+            Some(SupportedImageFormat::Bmp)
+        } else if header.starts_with(b"RIFF") && header.get(8..12) == Some(&b"WEBP"[..]) {
+            Some(SupportedImageFormat::WebP)
+        } else if header.starts_with(b"II*\0") || header.starts_with(b"MM\0*") {
+            Some(SupportedImageFormat::Tiff)
+        } else if header.starts_with(&[0xFF, 0x0A]) || header.starts_with(JXL_CONTAINER) {
+            Some(SupportedImageFormat::Jxl)
+        } else {
+            Self::detect_heif_brand(header)
+        }
+    }
+
+    /// ISO BMFF（`ftyp` ボックス）のブランドから AVIF / HEIC を判定
+    fn detect_heif_brand(header: &[u8]) -> Option<SupportedImageFormat> {
+        if header.get(4..8) != Some(&b"ftyp"[..]) {
+            return None;
+        }
+        let box_len = u32::from_be_bytes(header.get(0..4)?.try_into().ok()?) as usize;
+        let box_end = box_len.min(header.len());
+
+        // メジャーブランドと互換ブランド（4 バイトずつ、マイナーバージョンは飛ばす）
+        let brands = std::iter::once(header.get(8..12)?)
+            .chain(header.get(16..box_end).unwrap_or_default().chunks_exact(4));
+
+        let mut heic = false;
+        for brand in brands {
+            match brand {
+                b"avif" | b"avis" => return Some(SupportedImageFormat::Avif),
+                b"heic" | b"heix" | b"heim" | b"heis" | b"hevc" | b"hevx" => heic = true,
+                _ => {}
+            }
+        }
+        heic.then_some(SupportedImageFormat::Heic)
+    }
+
+    /// ファイルの先頭を読んで画像形式を判定
+    pub fn detect_from_file<P: AsRef<Path>>(path: P) -> Result<Option<SupportedImageFormat>> {
+        let mut header = Vec::with_capacity(HEADER_LEN);
+        File::open(path)?
+            .take(HEADER_LEN as u64)
+            .read_to_end(&mut header)?;
+        Ok(Self::detect_from_header(&header))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+    use std::process::Command;
+
+    use tempfile::TempDir;
+
+    use super::*;
+    use crate::errors::ErrorCategory;
+
+    fn ftyp(major: &[u8; 4], compatible: &[&[u8; 4]]) -> Vec<u8> {
+        let len = 16 + compatible.len() * 4;
+        let mut header = (len as u32).to_be_bytes().to_vec();
+        header.extend_from_slice(b"ftyp");
+        header.extend_from_slice(major);
+        header.extend_from_slice(&[0, 0, 0, 0]);
+        for brand in compatible {
+            header.extend_from_slice(*brand);
+        }
+        header
+    }
+
+    #[test]
+    fn test_detect_common_formats() {
+        let cases: [(&[u8], SupportedImageFormat); 7] = [
+            (b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR", SupportedImageFormat::Png),
+            (
+                &[0xFF, 0xD8, 0xFF, 0xE0, 0, 0x10],
+                SupportedImageFormat::Jpeg,
+            ),
+            (b"GIF89a\x01\0\x01\0", SupportedImageFormat::Gif),
+            (b"BM\x36\0\0\0\0\0\0\0\x36\0\0\0", SupportedImageFormat::Bmp),
+            (b"RIFF\x24\0\0\0WEBPVP8 ", SupportedImageFormat::WebP),
+            (b"II*\0\x08\0\0\0", SupportedImageFormat::Tiff),
+            (b"MM\0*\0\0\0\x08", SupportedImageFormat::Tiff),
+        ];
+        for (header, expected) in cases {
+            assert_eq!(FormatDetector::detect_from_header(header), Some(expected));
+        }
+    }
+
+    #[test]
+    fn test_reject_partial_signatures() {
+        // PNG の先頭 3 バイトだけでは判定しない
+        assert_eq!(FormatDetector::detect_from_header(b"\x89PN"), None);
+        assert_eq!(
+            FormatDetector::detect_from_header(b"\x89PNX\r\n\x1a\n"),
+            None
+        );
+        // "BM" で始まるだけのテキストは BMP ではない（予約フィールドが 0 でない）
+        assert_eq!(FormatDetector::detect_from_header(b"BM"), None);
+        assert_eq!(FormatDetector::detect_from_header(b"BMW owners club"), None);
+        assert_eq!(
+            FormatDetector::detect_from_header(b"RIFF\0\0\0\0WAVEfmt "),
+            None
+        );
+        assert_eq!(FormatDetector::detect_from_header(b""), None);
+    }
+
+    #[test]
+    fn test_detect_modern_formats() {
+        assert_eq!(
+            FormatDetector::detect_from_header(&ftyp(b"avif", &[b"mif1", b"miaf"])),
+            Some(SupportedImageFormat::Avif)
+        );
+        assert_eq!(
+            FormatDetector::detect_from_header(&ftyp(b"mif1", &[b"heic"])),
+            Some(SupportedImageFormat::Heic)
+        );
+        // MP4 動画は画像ではない
+        assert_eq!(
+            FormatDetector::detect_from_header(&ftyp(b"isom", &[b"iso2", b"mp41"])),
+            None
+        );
+        assert_eq!(
+            FormatDetector::detect_from_header(&[0xFF, 0x0A, 0xFA]),
+            Some(SupportedImageFormat::Jxl)
+        );
+        assert_eq!(
+            FormatDetector::detect_from_header(b"\0\0\0\x0cJXL \r\n\x87\n\0\0"),
+            Some(SupportedImageFormat::Jxl)
+        );
+    }
+
+    #[test]
+    fn test_still_image_formats() {
+        assert!(SupportedImageFormat::Png.is_still_image());
+        assert!(SupportedImageFormat::Heic.is_still_image());
+        assert!(!SupportedImageFormat::Gif.is_still_image());
+        assert!(SupportedImageFormat::Jpeg.decodes_natively());
+        for format in [
+            SupportedImageFormat::Avif,
+            SupportedImageFormat::Heic,
+            SupportedImageFormat::Jxl,
+        ] {
+            assert!(!format.decodes_natively());
+        }
+    }
+
+    #[test]
+    fn test_from_extension() {
+        assert_eq!(
+            SupportedImageFormat::from_extension("HEIF"),
+            Some(SupportedImageFormat::Heic)
+        );
+        assert_eq!(
+            SupportedImageFormat::from_extension("jxl"),
+            Some(SupportedImageFormat::Jxl)
+        );
+        assert_eq!(SupportedImageFormat::from_extension("mp4"), None);
+    }
+
+    /// 一時ディレクトリにテスト用のファイルを書き出す
+    fn fixture(dir: &TempDir, name: &str, data: &[u8]) -> PathBuf {
+        let path = dir.path().join(name);
+        std::fs::write(&path, data).unwrap();
+        path
+    }
+
+    /// testdata のファイル（16x16 の赤一色）
+    fn testdata(name: &str) -> PathBuf {
+        Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("testdata")
+            .join(name)
+    }
+
+    /// FFmpeg で読む形式の画像が 16x16 の赤一色になることを確かめる
+    ///
+    /// FFmpeg にデコーダーが含まれていなければ飛ばす
+    fn assert_decodes_red(path: &Path) {
+        let decoded = match decode_image(path) {
+            Ok(decoded) => decoded,
+            Err(e) if e.category() == ErrorCategory::MissingCodec => {
+                eprintln!("Skipping {}: {}", path.display(), e);
+                return;
+            }
+            Err(e) => panic!("Failed to decode {}: {}", path.display(), e),
+        };
+        assert_eq!((decoded.width(), decoded.height()), (16, 16));
+        // 非可逆圧縮なので多少の誤差は許す
+        let [r, g, b] = decoded.to_rgb8().get_pixel(8, 8).0;
+        assert!(r > 200 && g < 60 && b < 60, "{:?}", [r, g, b]);
+    }
+
+    #[test]
+    fn test_detect_fixture_files() {
+        let dir = tempfile::tempdir().unwrap();
+        // 拡張子が違っていてもヘッダーを優先する
+        let avif = fixture(&dir, "photo.jpg", &ftyp(b"avif", &[b"mif1"]));
+        assert_eq!(
+            SupportedImageFormat::detect(&avif).unwrap(),
+            Some(SupportedImageFormat::Avif)
+        );
+
+        // ヘッダーで判定できなければ拡張子を使う
+        let heic = fixture(&dir, "IMG_0001.HEIC", b"\0\0\0\x18unknown");
+        assert_eq!(
+            SupportedImageFormat::detect(&heic).unwrap(),
+            Some(SupportedImageFormat::Heic)
+        );
+
+        let text = fixture(&dir, "notes", b"plain text");
+        assert_eq!(SupportedImageFormat::detect(&text).unwrap(), None);
+    }
+
+    #[test]
+    fn test_decode_png_without_extension() {
+        let mut png = Vec::new();
+        image::RgbImage::from_pixel(2, 1, image::Rgb([255, 0, 0]))
+            .write_to(&mut std::io::Cursor::new(&mut png), image::ImageFormat::Png)
+            .unwrap();
+        let dir = tempfile::tempdir().unwrap();
+        let path = fixture(&dir, "download", &png);
+
+        let decoded = decode_image(&path).unwrap();
+        assert_eq!((decoded.width(), decoded.height()), (2, 1));
+        assert_eq!(decoded.to_rgb8().get_pixel(1, 0).0, [255, 0, 0]);
+    }
+
+    #[test]
+    fn test_decode_avif_and_heic() {
+        for name in ["red.avif", "red.heic"] {
+            let path = testdata(name);
+            assert!(
+                SupportedImageFormat::detect(&path)
+                    .unwrap()
+                    .is_some_and(|format| !format.decodes_natively())
+            );
+            assert_decodes_red(&path);
+        }
+    }
+
+    #[test]
+    fn test_decode_jxl() {
+        // JPEG XL のエンコーダーは libjxl 付きの FFmpeg にしかないので、その場で書き出す
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("red.jxl");
+        let encoded = Command::new(crate::subtitle::FFMPEG)
+            .args([
+                "-nostdin",
+                "-v",
+                "error",
+                "-f",
+                "lavfi",
+                "-i",
+                "color=c=red:s=16x16",
+            ])
+            .args(["-frames:v", "1", "-c:v", "libjxl", "-update", "1"])
+            .arg(&path)
+            .output();
+        if !encoded.is_ok_and(|output| output.status.success()) {
+            eprintln!("Skipping JPEG XL: ffmpeg cannot encode it");
+            return;
+        }
+        assert_eq!(
+            SupportedImageFormat::detect(&path).unwrap(),
+            Some(SupportedImageFormat::Jxl)
+        );
+        assert_decodes_red(&path);
+    }
+}
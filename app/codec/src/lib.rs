@@ -1,10 +1,20 @@
 pub mod audio;
+pub mod batch;
+pub mod color;
 pub mod errors;
+pub mod format;
 pub mod media;
+pub mod network;
+pub mod source;
+pub mod subtitle;
+pub mod tonemap;
+pub mod track;
 pub mod video;
 
-pub use errors::{MediaError, Result};
-pub use media::{MediaFile, MediaInfo, MediaType};
+pub use errors::{ErrorCategory, MediaError, Result};
+pub use media::{
+    MediaFile, MediaInfo, MediaTags, MediaType, StreamDisposition, StreamInfo, StreamKind,
+};
 
 /// ライブラリの初期化
 pub fn init() -> Result<()> {
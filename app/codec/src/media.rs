@@ -1,7 +1,14 @@
 use std::path::Path;
 use std::time::Duration;
 
+use image::DynamicImage;
+
+use crate::batch::fit;
+use crate::color::YuvFormat;
 use crate::errors::{MediaError, Result};
+use crate::format::{self, SupportedImageFormat};
+use crate::video::VideoDecoder;
+use crate::video::decoder::detect_yuv_format;
 
 /// メディアファイルの種類を表す列挙型
 #[derive(Debug, Clone, PartialEq)]
@@ -12,6 +19,152 @@ pub enum MediaType {
     Unknown,
 }
 
+/// メディアファイルに埋め込まれたタグ情報
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct MediaTags {
+    pub title: Option<String>,
+    pub artist: Option<String>,
+    pub album: Option<String>,
+    pub album_artist: Option<String>,
+    pub track_number: Option<u32>,
+    pub disc_number: Option<u32>,
+    /// 表示するときに時計回りに回す角度（0, 90, 180, 270）
+    pub rotation: Option<u32>,
+}
+
+impl MediaTags {
+    /// avio のメタデータからタグを抽出
+    fn from_avio_info(info: &avio::MediaInfo) -> Self {
+        Self {
+            title: info.title().map(str::to_string),
+            artist: info.artist().map(str::to_string),
+            album: info.album().map(str::to_string),
+            album_artist: info.metadata_value("album_artist").map(str::to_string),
+            track_number: info.metadata_value("track").and_then(parse_position_tag),
+            disc_number: info.metadata_value("disc").and_then(parse_position_tag),
+            rotation: info.metadata_value("rotate").and_then(parse_rotation_tag),
+        }
+    }
+}
+
+/// ストリームの種類と、種類ごとの情報
+#[derive(Debug, Clone, PartialEq)]
+pub enum StreamKind {
+    Video { width: u32, height: u32, fps: f64 },
+    Audio { sample_rate: u32, channels: u16 },
+    Subtitle { text_based: bool },
+}
+
+/// ストリームの扱い（FFmpeg の disposition のうち avio から分かるもの）
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct StreamDisposition {
+    /// 常に表示する字幕
+    pub forced: bool,
+    /// 音声ファイルに埋め込まれたカバー画像
+    pub attached_pic: bool,
+}
+
+/// コンテナ内の 1 本のストリーム
+#[derive(Debug, Clone, PartialEq)]
+pub struct StreamInfo {
+    /// コンテナ内のストリーム番号（全種類を通した番号）
+    pub index: u32,
+    pub kind: StreamKind,
+    pub codec: String,
+    /// "eng" や "jpn" のような言語コード
+    pub language: Option<String>,
+    pub title: Option<String>,
+    /// ビット毎秒
+    pub bitrate: Option<u64>,
+    pub disposition: StreamDisposition,
+}
+
+impl StreamInfo {
+    fn from_video(stream: &avio::VideoStreamInfo) -> Self {
+        Self {
+            index: stream.index(),
+            kind: StreamKind::Video {
+                width: stream.width(),
+                height: stream.height(),
+                fps: stream.fps(),
+            },
+            codec: stream.codec_name().to_string(),
+            language: None,
+            title: None,
+            bitrate: stream.bitrate(),
+            disposition: StreamDisposition {
+                attached_pic: is_cover_art(stream.codec_name(), stream.fps()),
+                ..Default::default()
+            },
+        }
+    }
+
+    fn from_audio(stream: &avio::AudioStreamInfo) -> Self {
+        Self {
+            index: stream.index(),
+            kind: StreamKind::Audio {
+                sample_rate: stream.sample_rate(),
+                channels: stream.channels() as u16,
+            },
+            codec: stream.codec_name().to_string(),
+            language: stream.language().map(str::to_string),
+            title: None,
+            bitrate: stream.bitrate(),
+            disposition: StreamDisposition::default(),
+        }
+    }
+
+    fn from_subtitle(stream: &avio::SubtitleStreamInfo) -> Self {
+        Self {
+            index: stream.index(),
+            kind: StreamKind::Subtitle {
+                text_based: crate::subtitle::is_text_stream(stream),
+            },
+            codec: stream.codec_name().to_string(),
+            language: stream.language().map(str::to_string),
+            title: stream.title().map(str::to_string),
+            bitrate: None,
+            disposition: StreamDisposition {
+                forced: stream.is_forced(),
+                ..Default::default()
+            },
+        }
+    }
+}
+
+/// サムネイルを取る位置が終端を越えるとき、終端からこれだけ手前にする
+const THUMBNAIL_END_MARGIN: Duration = Duration::from_secs(1);
+
+/// 埋め込まれたカバー画像を縮小する大きさ（ターミナルに描くには十分）
+const COVER_ART_MAX_SIZE: (u32, u32) = (512, 512);
+
+/// 動画として扱わない 1 枚絵のコーデック（名前の一部に含まれれば一致とする。mjpeg など）
+const STILL_IMAGE_CODECS: &[&str] = &["jpeg", "png", "bmp", "gif", "webp", "tiff"];
+
+/// 映像ストリームが音声ファイルに埋め込まれたカバー画像かどうか
+///
+/// FFmpeg はカバー画像（attached picture）を 1 フレームだけの映像ストリームとして見せ、
+/// フレームレートを 0 か 90000 のようなありえない値にする。静止画のコーデックで
+/// フレームレートが動画の範囲（1〜240）になければカバー画像とみなす
+fn is_cover_art(codec_name: &str, fps: f64) -> bool {
+    let codec_name = codec_name.to_ascii_lowercase().replace(['-', ' '], "");
+    let still = STILL_IMAGE_CODECS
+        .iter()
+        .any(|codec| codec_name.contains(codec));
+    still && !(1.0..=240.0).contains(&fps)
+}
+
+/// "3" や "3/12" 形式のトラック番号・ディスク番号を解析
+fn parse_position_tag(value: &str) -> Option<u32> {
+    value.split('/').next()?.trim().parse().ok()
+}
+
+/// "90" や "-90" 形式の回転タグを 0〜359 度の時計回りの角度にする
+fn parse_rotation_tag(value: &str) -> Option<u32> {
+    let degrees: i64 = value.trim().parse().ok()?;
+    Some(degrees.rem_euclid(360) as u32)
+}
+
 /// メディアファイルの情報を保持する構造体
 #[derive(Debug, Clone, Default)]
 pub struct MediaInfo {
@@ -23,8 +176,19 @@ pub struct MediaInfo {
     pub has_audio: bool,
     pub video_codec: Option<String>,
     pub audio_codec: Option<String>,
+    /// 映像の YCbCr の変換行列と範囲（YCbCr でない映像や映像がなければ None）
+    pub yuv: Option<YuvFormat>,
     pub sample_rate: Option<u32>,
     pub channels: Option<u16>,
+    pub tags: MediaTags,
+    /// 音声ファイルにカバー画像が埋め込まれている（カバー画像は `has_video` に含めない）
+    pub has_cover_art: bool,
+    /// コンテナの形式名（"mov,mp4,m4a,3gp,3g2,mj2" や "matroska,webm" など）
+    pub format: Option<String>,
+    /// 全体のビット毎秒
+    pub bitrate: Option<u64>,
+    /// ストリーム番号の順に並べたすべてのストリーム
+    pub streams: Vec<StreamInfo>,
 }
 
 /// メディアファイルを表現する構造体
@@ -44,21 +208,19 @@ impl MediaFile {
             .ok_or_else(|| MediaError::InvalidFormat("Invalid path".to_string()))?
             .to_string();
 
-        let avio_info = avio::open(&path_str)?;
-
-        let info = MediaInfo {
-            duration: Some(avio_info.duration()),
-            width: avio_info.resolution().map(|(w, _)| w),
-            height: avio_info.resolution().map(|(_, h)| h),
-            fps: avio_info.frame_rate(),
-            has_video: avio_info.has_video(),
-            has_audio: avio_info.has_audio(),
-            video_codec: avio_info.primary_video().map(|v| format!("{:?}", v)),
-            audio_codec: avio_info.primary_audio().map(|a| format!("{:?}", a)),
-            sample_rate: avio_info.sample_rate(),
-            channels: avio_info.channels().map(|c| c as u16),
-        };
+        // 静止画は FFmpeg に渡す前に判定する（拡張子がなければ先頭のバイト列で判定）
+        if path.as_ref().is_file()
+            && let Some(format) = SupportedImageFormat::detect(&path)?
+            && format.is_still_image()
+        {
+            return Ok(MediaFile {
+                path: path_str,
+                media_type: MediaType::Image,
+                info: MediaInfo::default(),
+            });
+        }
 
+        let info = extract_media_info(&avio::open(&path_str)?);
         let media_type = Self::determine_media_type(&info);
 
         Ok(MediaFile {
@@ -68,6 +230,87 @@ impl MediaFile {
         })
     }
 
+    /// コンテナ内のストリームをストリーム番号の順に返す
+    pub fn streams(&self) -> impl Iterator<Item = &StreamInfo> {
+        self.info.streams.iter()
+    }
+
+    /// 映像トラック（別アングルなど）をストリーム番号の順に返す
+    pub fn video_tracks(&self) -> impl Iterator<Item = &StreamInfo> {
+        self.streams()
+            .filter(|stream| matches!(stream.kind, StreamKind::Video { .. }))
+    }
+
+    /// 音声トラック（吹き替えや解説など）をストリーム番号の順に返す
+    pub fn audio_tracks(&self) -> impl Iterator<Item = &StreamInfo> {
+        self.streams()
+            .filter(|stream| matches!(stream.kind, StreamKind::Audio { .. }))
+    }
+
+    /// 字幕トラックをストリーム番号の順に返す
+    pub fn subtitle_tracks(&self) -> impl Iterator<Item = &StreamInfo> {
+        self.streams()
+            .filter(|stream| matches!(stream.kind, StreamKind::Subtitle { .. }))
+    }
+
+    /// 長さの分からない（終わりのない）ストリームかどうか
+    pub fn is_live(&self) -> bool {
+        self.info.duration.is_none()
+    }
+
+    /// `at` 付近のフレームを 1 枚デコードし、`size`（幅・高さ）に収まるよう縮小して返す
+    ///
+    /// ファイルブラウザーやシークのプレビュー向けに、速さを優先して `at` の直前の
+    /// キーフレームを使う。静止画は `at` に関係なくその画像を、音声はカバー画像を縮小する
+    pub fn thumbnail(&self, at: Duration, size: (u32, u32)) -> Result<DynamicImage> {
+        let image = match self.media_type {
+            MediaType::Image => format::decode_image(&self.path)?,
+            MediaType::Video => {
+                let mut decoder = VideoDecoder::new(&self.path, 0, 0, None)?;
+                // 終端を越えると何もデコードできないので、長さの内側に収める
+                let at = match self.info.duration {
+                    Some(duration) => at.min(duration.saturating_sub(THUMBNAIL_END_MARGIN)),
+                    None => at,
+                };
+                if !at.is_zero() {
+                    decoder.seek_keyframe(at)?;
+                }
+                decoder
+                    .decode_one()?
+                    .ok_or_else(|| MediaError::Video(format!("No video frame near {:?}", at)))?
+                    .to_dynamic_image()?
+            }
+            MediaType::Audio | MediaType::Unknown => {
+                // カバー画像があれば、音声ファイルのサムネイルにする
+                return self
+                    .cover_art()?
+                    .map(|cover| fit(cover, Some(size)))
+                    .ok_or_else(|| {
+                        MediaError::InvalidFormat(format!("{} has no picture", self.path))
+                    });
+            }
+        };
+        Ok(fit(image, Some(size)))
+    }
+
+    /// 音声ファイルに埋め込まれたカバー画像を 512x512 に収まるよう縮小して返す
+    ///
+    /// カバー画像がなければ None
+    pub fn cover_art(&self) -> Result<Option<DynamicImage>> {
+        if !self.info.has_cover_art {
+            return Ok(None);
+        }
+        // 映像のないファイルでは、FFmpeg はカバー画像のストリームをデコードする
+        let mut decoder = VideoDecoder::new(&self.path, 0, 0, None)?;
+        let Some(frame) = decoder.decode_one()? else {
+            return Ok(None);
+        };
+        Ok(Some(fit(
+            frame.to_dynamic_image()?,
+            Some(COVER_ART_MAX_SIZE),
+        )))
+    }
+
     /// メディアタイプを判定
     fn determine_media_type(info: &MediaInfo) -> MediaType {
         if info.has_video {
@@ -79,3 +322,153 @@ impl MediaFile {
         }
     }
 }
+
+/// avio のプローブ結果から MediaInfo を作る
+fn extract_media_info(avio_info: &avio::MediaInfo) -> MediaInfo {
+    // ライブストリームなど長さが分からない場合、avio は Duration::ZERO を返す
+    let duration = Some(avio_info.duration()).filter(|d| !d.is_zero());
+
+    let mut streams: Vec<StreamInfo> = avio_info
+        .video_streams()
+        .iter()
+        .map(StreamInfo::from_video)
+        .chain(avio_info.audio_streams().iter().map(StreamInfo::from_audio))
+        .chain(
+            avio_info
+                .subtitle_streams()
+                .iter()
+                .map(StreamInfo::from_subtitle),
+        )
+        .collect();
+    streams.sort_by_key(|stream| stream.index);
+
+    // カバー画像しかない音声ファイルは、映像のない音声として扱う
+    let (covers, videos): (Vec<_>, Vec<_>) = streams
+        .iter()
+        .filter(|stream| matches!(stream.kind, StreamKind::Video { .. }))
+        .partition(|stream| stream.disposition.attached_pic);
+    let has_video = !videos.is_empty();
+    let has_cover_art = !has_video && !covers.is_empty();
+    let primary_video = avio_info.primary_video().filter(|_| has_video);
+
+    MediaInfo {
+        duration,
+        width: avio_info.resolution().filter(|_| has_video).map(|(w, _)| w),
+        height: avio_info.resolution().filter(|_| has_video).map(|(_, h)| h),
+        fps: avio_info.frame_rate().filter(|_| has_video),
+        has_video,
+        has_audio: avio_info.has_audio(),
+        video_codec: primary_video.map(|v| format!("{:?}", v)),
+        audio_codec: avio_info.primary_audio().map(|a| format!("{:?}", a)),
+        yuv: primary_video.and_then(detect_yuv_format),
+        sample_rate: avio_info.sample_rate(),
+        channels: avio_info.channels().map(|c| c as u16),
+        tags: MediaTags::from_avio_info(avio_info),
+        has_cover_art,
+        format: Some(avio_info.format().to_string()).filter(|format| !format.is_empty()),
+        bitrate: avio_info.bitrate().filter(|&bitrate| bitrate > 0),
+        streams,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_position_tag() {
+        assert_eq!(parse_position_tag("3"), Some(3));
+        assert_eq!(parse_position_tag("3/12"), Some(3));
+        assert_eq!(parse_position_tag(" 07 / 10"), Some(7));
+        assert_eq!(parse_position_tag(""), None);
+        assert_eq!(parse_position_tag("A1"), None);
+    }
+
+    #[test]
+    fn test_parse_rotation_tag() {
+        assert_eq!(parse_rotation_tag("90"), Some(90));
+        assert_eq!(parse_rotation_tag("-90"), Some(270));
+        assert_eq!(parse_rotation_tag("360"), Some(0));
+        assert_eq!(parse_rotation_tag("left"), None);
+    }
+
+    #[test]
+    fn test_extract_media_info() {
+        let avio_info = avio::MediaInfo::builder()
+            .format("mov,mp4,m4a,3gp,3g2,mj2")
+            .duration(Duration::from_secs(60))
+            .bitrate(1_500_000)
+            .subtitle_stream(
+                avio::SubtitleStreamInfo::builder()
+                    .index(2)
+                    .codec(avio::SubtitleCodec::Srt)
+                    .codec_name("subrip")
+                    .language("jpn")
+                    .forced(true)
+                    .build(),
+            )
+            .audio_stream(
+                avio::AudioStreamInfo::builder()
+                    .index(1)
+                    .codec_name("aac")
+                    .language("eng")
+                    .build(),
+            )
+            .video_stream(
+                avio::VideoStreamInfo::builder()
+                    .index(0)
+                    .codec_name("h264")
+                    .width(1920)
+                    .height(1080)
+                    .frame_rate(avio::Rational::new(30, 1))
+                    .build(),
+            )
+            .metadata("title", "Clip")
+            .metadata("rotate", "-90")
+            .build();
+
+        let info = extract_media_info(&avio_info);
+        assert_eq!(info.format.as_deref(), Some("mov,mp4,m4a,3gp,3g2,mj2"));
+        assert_eq!(info.bitrate, Some(1_500_000));
+        assert_eq!(info.tags.title.as_deref(), Some("Clip"));
+        assert_eq!(info.tags.rotation, Some(270));
+        assert!(info.has_video && !info.has_cover_art);
+
+        let indices: Vec<u32> = info.streams.iter().map(|stream| stream.index).collect();
+        assert_eq!(indices, [0, 1, 2]);
+        assert_eq!(info.streams[1].language.as_deref(), Some("eng"));
+        assert!(info.streams[2].disposition.forced);
+        assert_eq!(
+            info.streams[2].kind,
+            StreamKind::Subtitle { text_based: true }
+        );
+    }
+
+    #[test]
+    fn test_is_cover_art() {
+        // MP3 や M4A のカバー画像は 90000 fps の 1 枚絵として見える
+        assert!(is_cover_art("mjpeg", 90000.0));
+        assert!(is_cover_art("PNG", 0.0));
+        // Motion JPEG の動画はカバー画像ではない
+        assert!(!is_cover_art("mjpeg", 30.0));
+        assert!(!is_cover_art("h264", 90000.0));
+    }
+
+    #[test]
+    fn test_image_thumbnail() {
+        let path = std::env::temp_dir().join(format!("codec-thumbnail-{}.png", std::process::id()));
+        image::RgbImage::from_pixel(320, 240, image::Rgb([10, 20, 30]))
+            .save(&path)
+            .unwrap();
+
+        let media_file = MediaFile::open(&path).unwrap();
+        let thumbnail = media_file
+            .thumbnail(Duration::from_secs(5), (64, 64))
+            .unwrap();
+        assert_eq!((thumbnail.width(), thumbnail.height()), (64, 48));
+
+        // 指定より小さい画像は拡大しない
+        let thumbnail = media_file.thumbnail(Duration::ZERO, (640, 480)).unwrap();
+        assert_eq!((thumbnail.width(), thumbnail.height()), (320, 240));
+    }
+}
@@ -0,0 +1,287 @@
+//! 複数フレームの画像（APNG・アニメーション WebP・複数ページの TIFF）
+//!
+//! 全フレームを最初にデコードしておき、経過時間に応じたフレームを返す
+//! メディアソースとして再生する。最後のフレームの後は先頭に戻ってループする
+
+use std::fs::File;
+use std::io::BufReader;
+use std::path::Path;
+use std::time::Duration;
+
+use image::codecs::png::PngDecoder;
+use image::codecs::webp::WebPDecoder;
+use image::{AnimationDecoder, DynamicImage, Frames, RgbaImage};
+use tiff::ColorType;
+use tiff::decoder::{Decoder as TiffDecoder, DecodingResult};
+
+use crate::errors::{MediaError, Result};
+use crate::format::SupportedImageFormat;
+use crate::source::MediaSource;
+use crate::video::VideoFrame;
+use crate::video::frame::FrameFormat;
+
+/// 読み込むフレーム数の上限（メモリを使い切らないように）
+const MAX_FRAMES: usize = 1000;
+
+/// これより短い表示時間は、ブラウザと同様に既定値として扱う
+const MIN_FRAME_DELAY: Duration = Duration::from_millis(20);
+
+/// 表示時間が指定されていない、または短すぎるフレームの表示時間
+const DEFAULT_FRAME_DELAY: Duration = Duration::from_millis(100);
+
+/// 複数ページの TIFF で 1 ページを表示する時間
+const TIFF_PAGE_DURATION: Duration = Duration::from_secs(3);
+
+/// 複数フレームの画像
+pub struct AnimatedImage {
+    name: String,
+    frames: Vec<VideoFrame>,
+    /// 各フレームの表示終了時刻（先頭からの累積）
+    ends: Vec<Duration>,
+}
+
+impl AnimatedImage {
+    /// 複数フレームの画像を開く（静止画や対応していない形式の場合は None）
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Option<Self>> {
+        let path = path.as_ref();
+        let frames = match SupportedImageFormat::detect(path)? {
+            Some(SupportedImageFormat::Png) => {
+                let decoder = PngDecoder::new(BufReader::new(File::open(path)?))?;
+                if !decoder.is_apng()? {
+                    return Ok(None);
+                }
+                collect_frames(decoder.apng()?.into_frames())?
+            }
+            Some(SupportedImageFormat::WebP) => {
+                let decoder = WebPDecoder::new(BufReader::new(File::open(path)?))?;
+                if !decoder.has_animation() {
+                    return Ok(None);
+                }
+                collect_frames(decoder.into_frames())?
+            }
+            // 16 ビットなど読めないページがあれば、静止画として image クレートに任せる
+            Some(SupportedImageFormat::Tiff) => match read_tiff_pages(path) {
+                Ok(pages) => pages,
+                Err(_) => return Ok(None),
+            },
+            _ => return Ok(None),
+        };
+
+        if frames.len() < 2 {
+            return Ok(None);
+        }
+
+        let name = path
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        Ok(Some(Self::new(name, frames)))
+    }
+
+    /// RGBA の画像と表示時間の組からソースを作成
+    pub fn new(name: String, frames: Vec<(RgbaImage, Duration)>) -> Self {
+        let mut elapsed = Duration::ZERO;
+        let mut ends = Vec::with_capacity(frames.len());
+        let frames = frames
+            .into_iter()
+            .enumerate()
+            .map(|(index, (image, delay))| {
+                elapsed += delay;
+                ends.push(elapsed);
+
+                let rgb = DynamicImage::ImageRgba8(image).to_rgb8();
+                let (width, height) = rgb.dimensions();
+                VideoFrame::new(
+                    rgb.into_raw(),
+                    width,
+                    height,
+                    FrameFormat::RGB8,
+                    elapsed - delay,
+                    index as i64,
+                )
+            })
+            .collect();
+
+        Self { name, frames, ends }
+    }
+
+    pub fn frame_count(&self) -> usize {
+        self.frames.len()
+    }
+
+    /// 1 ループの長さ
+    pub fn loop_duration(&self) -> Duration {
+        self.ends.last().copied().unwrap_or_default()
+    }
+
+    /// 経過時間 `timestamp` に表示するフレームの番号
+    fn frame_index_at(&self, timestamp: Duration) -> usize {
+        let total = self.loop_duration();
+        if total.is_zero() {
+            return 0;
+        }
+        let position = Duration::from_nanos((timestamp.as_nanos() % total.as_nanos()) as u64);
+        self.ends
+            .partition_point(|end| *end <= position)
+            .min(self.frames.len() - 1)
+    }
+}
+
+impl MediaSource for AnimatedImage {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// 最も短いフレームを取りこぼさない程度の頻度で描画する
+    fn frame_rate(&self) -> f64 {
+        let shortest = self
+            .ends
+            .iter()
+            .scan(Duration::ZERO, |start, &end| {
+                let delay = end - *start;
+                *start = end;
+                Some(delay)
+            })
+            .min()
+            .unwrap_or(DEFAULT_FRAME_DELAY);
+        (1.0 / shortest.as_secs_f64().max(f64::EPSILON)).clamp(1.0, 60.0)
+    }
+
+    /// 画像の元の大きさのまま返す（出力サイズへの縮小はレンダラーが行う）
+    fn video_frame(
+        &mut self,
+        timestamp: Duration,
+        _width: u32,
+        _height: u32,
+    ) -> Result<VideoFrame> {
+        let mut frame = self.frames[self.frame_index_at(timestamp)].clone();
+        frame.timestamp = timestamp;
+        Ok(frame)
+    }
+}
+
+/// APNG / WebP のフレームを表示時間とともに取り出す
+fn collect_frames(frames: Frames<'_>) -> Result<Vec<(RgbaImage, Duration)>> {
+    frames
+        .take(MAX_FRAMES)
+        .map(|frame| {
+            let frame = frame?;
+            let (numer, denom) = frame.delay().numer_denom_ms();
+            let delay = Duration::from_secs_f64(numer as f64 / denom.max(1) as f64 / 1000.0);
+            let delay = if delay < MIN_FRAME_DELAY {
+                DEFAULT_FRAME_DELAY
+            } else {
+                delay
+            };
+            Ok((frame.into_buffer(), delay))
+        })
+        .collect()
+}
+
+/// TIFF の全ページを読み込む（8 ビットのグレースケール・RGB・RGBA に対応）
+fn read_tiff_pages(path: &Path) -> Result<Vec<(RgbaImage, Duration)>> {
+    let tiff_error = |e: tiff::TiffError| MediaError::InvalidFormat(format!("TIFF: {}", e));
+    let mut decoder = TiffDecoder::new(BufReader::new(File::open(path)?)).map_err(tiff_error)?;
+
+    let mut pages = Vec::new();
+    loop {
+        let (width, height) = decoder.dimensions().map_err(tiff_error)?;
+        let color_type = decoder.colortype().map_err(tiff_error)?;
+        let DecodingResult::U8(data) = decoder.read_image().map_err(tiff_error)? else {
+            return Err(MediaError::UnsupportedCodec(format!(
+                "TIFF page with {:?} samples",
+                color_type
+            )));
+        };
+
+        let rgba: Vec<u8> = match color_type {
+            ColorType::Gray(8) => data.iter().flat_map(|&v| [v, v, v, 255]).collect(),
+            ColorType::RGB(8) => data
+                .chunks_exact(3)
+                .flat_map(|px| [px[0], px[1], px[2], 255])
+                .collect(),
+            ColorType::RGBA(8) => data,
+            other => {
+                return Err(MediaError::UnsupportedCodec(format!(
+                    "TIFF page with {:?} pixels",
+                    other
+                )));
+            }
+        };
+        let image = RgbaImage::from_raw(width, height, rgba)
+            .ok_or_else(|| MediaError::InvalidFormat("TIFF page size mismatch".to_string()))?;
+        pages.push((image, TIFF_PAGE_DURATION));
+
+        if !decoder.more_images() || pages.len() >= MAX_FRAMES {
+            break;
+        }
+        decoder.next_image().map_err(tiff_error)?;
+    }
+
+    Ok(pages)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::Rgba;
+
+    fn solid(color: [u8; 4]) -> RgbaImage {
+        RgbaImage::from_pixel(2, 2, Rgba(color))
+    }
+
+    #[test]
+    fn test_frames_follow_their_durations() {
+        let mut animation = AnimatedImage::new(
+            "test".to_string(),
+            vec![
+                (solid([255, 0, 0, 255]), Duration::from_millis(100)),
+                (solid([0, 255, 0, 255]), Duration::from_millis(300)),
+            ],
+        );
+        assert_eq!(animation.frame_count(), 2);
+        assert_eq!(animation.loop_duration(), Duration::from_millis(400));
+
+        let color_at = |animation: &mut AnimatedImage, ms: u64| {
+            let frame = animation
+                .video_frame(Duration::from_millis(ms), 80, 24)
+                .unwrap();
+            [frame.data[0], frame.data[1], frame.data[2]]
+        };
+        assert_eq!(color_at(&mut animation, 0), [255, 0, 0]);
+        assert_eq!(color_at(&mut animation, 99), [255, 0, 0]);
+        assert_eq!(color_at(&mut animation, 100), [0, 255, 0]);
+        assert_eq!(color_at(&mut animation, 399), [0, 255, 0]);
+        // ループして先頭に戻る
+        assert_eq!(color_at(&mut animation, 450), [255, 0, 0]);
+        assert_eq!(animation.frame_rate(), 10.0);
+    }
+
+    #[test]
+    fn test_open_multi_page_tiff() {
+        let path = std::env::temp_dir().join(format!("codec-pages-{}.tiff", std::process::id()));
+        {
+            let mut encoder =
+                tiff::encoder::TiffEncoder::new(File::create(&path).unwrap()).unwrap();
+            for value in [10u8, 200] {
+                encoder
+                    .write_image::<tiff::encoder::colortype::Gray8>(2, 2, &[value; 4])
+                    .unwrap();
+            }
+        }
+
+        let mut pages = AnimatedImage::open(&path).unwrap().unwrap();
+        assert_eq!(pages.frame_count(), 2);
+        let second = pages.video_frame(TIFF_PAGE_DURATION, 80, 24).unwrap();
+        assert_eq!(&second.data[..3], &[200, 200, 200]);
+        std::fs::remove_file(path).ok();
+    }
+
+    #[test]
+    fn test_still_png_is_not_animated() {
+        let path = std::env::temp_dir().join(format!("codec-still-{}.png", std::process::id()));
+        solid([1, 2, 3, 255]).save(&path).unwrap();
+        assert!(AnimatedImage::open(&path).unwrap().is_none());
+        std::fs::remove_file(path).ok();
+    }
+}
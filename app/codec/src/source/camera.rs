@@ -0,0 +1,367 @@
+//! Web カメラの入力（Linux の Video4Linux2）
+//!
+//! 取り込みは専用のスレッドで続け、プレイヤーが描くときには最新のフレームを返す。
+//! 自分を映す鏡として使うため、左右を反転して返す
+
+#[cfg(target_os = "linux")]
+mod v4l2;
+
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::JoinHandle;
+use std::time::Duration;
+
+use crate::color::ycbcr_to_rgb;
+use crate::errors::{MediaError, Result};
+use crate::source::{MediaSource, Raster};
+use crate::video::VideoFrame;
+use crate::video::frame::FrameFormat;
+
+/// 大きさを指定しなかったときに取り込む大きさ
+pub const DEFAULT_SIZE: (u32, u32) = (640, 480);
+
+/// 最初のフレームを待つ時間
+#[cfg(target_os = "linux")]
+const FIRST_FRAME_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// 取り込みスレッドが停止の指示を確かめる間隔
+#[cfg(target_os = "linux")]
+const POLL_INTERVAL: Duration = Duration::from_millis(200);
+
+/// 接続されているカメラ
+#[derive(Debug, Clone, PartialEq)]
+pub struct CameraInfo {
+    pub path: PathBuf,
+    pub name: String,
+    /// 取り込める大きさ（幅, 高さ）
+    pub sizes: Vec<(u32, u32)>,
+}
+
+/// `0` のような番号なら `/dev/video0`、それ以外はデバイスのパスとして扱う
+pub fn device_path(device: &str) -> PathBuf {
+    match device.parse::<u32>() {
+        Ok(index) => PathBuf::from(format!("/dev/video{}", index)),
+        Err(_) => PathBuf::from(device),
+    }
+}
+
+/// 取り込んだフレームと、取り込みが止まった理由
+#[derive(Default)]
+struct Latest {
+    frame: Option<VideoFrame>,
+    error: Option<String>,
+}
+
+/// Web カメラのソース。破棄すると取り込みを止める
+pub struct CameraSource {
+    name: String,
+    latest: Arc<Mutex<Latest>>,
+    stop: Arc<AtomicBool>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl MediaSource for CameraSource {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn video_frame(&mut self, timestamp: Duration, width: u32, height: u32) -> Result<VideoFrame> {
+        let latest = self
+            .latest
+            .lock()
+            .map_err(|_| MediaError::Video("Camera capture thread panicked".to_string()))?;
+        if let Some(error) = &latest.error {
+            return Err(MediaError::Video(error.clone()));
+        }
+        let mut frame = match &latest.frame {
+            Some(frame) => frame.clone(),
+            None => Raster::new(width, height).into_frame(timestamp),
+        };
+        frame.timestamp = timestamp;
+        Ok(frame)
+    }
+}
+
+impl Drop for CameraSource {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+/// YUYV（4:2:2）を RGB に変換する。`mirror` なら左右を反転する
+fn yuyv_to_rgb(data: &[u8], width: u32, height: u32, bytes_per_line: u32, mirror: bool) -> Vec<u8> {
+    let (width, height) = (width as usize, height as usize);
+    let stride = (bytes_per_line as usize).max(width * 2);
+    let mut rgb = vec![0; width * height * 3];
+    for (y, row) in data.chunks(stride).take(height).enumerate() {
+        // 2 ピクセルで Y0 U Y1 V の 4 バイトを共有するので、4 バイトそろわない組は飛ばす
+        // （短い行や奇数の幅でも範囲外を読まない）
+        for x in 0..width.min(row.len() / 4 * 2) {
+            let pair = &row[x / 2 * 4..x / 2 * 4 + 4];
+            let luma = pair[if x % 2 == 0 { 0 } else { 2 }];
+            let out_x = if mirror { width - 1 - x } else { x };
+            let index = (y * width + out_x) * 3;
+            rgb[index..index + 3].copy_from_slice(&ycbcr_to_rgb(luma, pair[1], pair[3]));
+        }
+    }
+    rgb
+}
+
+#[cfg(target_os = "linux")]
+mod platform {
+    use std::path::Path;
+    use std::thread;
+    use std::time::Instant;
+
+    use super::v4l2::{self, Device, PixFormat};
+    use super::*;
+
+    const YUYV: u32 = v4l2::fourcc(b"YUYV");
+    const MJPEG: u32 = v4l2::fourcc(b"MJPG");
+
+    /// デバイスのバッファ数
+    const BUFFER_COUNT: u32 = 4;
+
+    /// `/dev/video*` のうち映像を取り込めるデバイス
+    pub fn list_cameras() -> Vec<CameraInfo> {
+        let Ok(entries) = std::fs::read_dir("/dev") else {
+            return Vec::new();
+        };
+        let mut paths: Vec<PathBuf> = entries
+            .flatten()
+            .map(|entry| entry.path())
+            .filter(|path| {
+                path.file_name()
+                    .and_then(|name| name.to_str())
+                    .and_then(|name| name.strip_prefix("video"))
+                    .is_some_and(|index| index.parse::<u32>().is_ok())
+            })
+            .collect();
+        paths.sort_by_key(|path| {
+            path.to_string_lossy()
+                .trim_start_matches("/dev/video")
+                .parse::<u32>()
+                .unwrap_or(u32::MAX)
+        });
+
+        paths
+            .into_iter()
+            .filter_map(|path| {
+                let device = Device::open(&path).ok()?;
+                let (name, capture) = device.query().ok()?;
+                if !capture {
+                    return None;
+                }
+                let mut sizes = device.frame_sizes(YUYV);
+                for size in device.frame_sizes(MJPEG) {
+                    if !sizes.contains(&size) {
+                        sizes.push(size);
+                    }
+                }
+                Some(CameraInfo { path, name, sizes })
+            })
+            .collect()
+    }
+
+    impl CameraSource {
+        /// `device`（番号またはパス）のカメラを `size` の大きさ（近い大きさ）で開く
+        ///
+        /// 最初のフレームが届くまで待つ
+        pub fn open(device: &str, size: Option<(u32, u32)>) -> Result<Self> {
+            let path = device_path(device);
+            let device = Device::open(&path).map_err(|e| open_error(&path, e))?;
+            let (card, can_capture) = device.query().map_err(|e| open_error(&path, e))?;
+            if !can_capture {
+                return Err(MediaError::Video(format!(
+                    "{} is not a video capture device",
+                    path.display()
+                )));
+            }
+
+            let (width, height) = size.unwrap_or(DEFAULT_SIZE);
+            let format = negotiate_format(&device, width, height)
+                .map_err(|e| open_error(&path, e))?
+                .ok_or_else(|| {
+                    MediaError::UnsupportedCodec(format!(
+                        "{} supports neither YUYV nor MJPEG",
+                        path.display()
+                    ))
+                })?;
+            let mut stream = device
+                .start(BUFFER_COUNT)
+                .map_err(|e| open_error(&path, e))?;
+
+            let latest = Arc::new(Mutex::new(Latest::default()));
+            let stop = Arc::new(AtomicBool::new(false));
+            let thread = {
+                let latest = Arc::clone(&latest);
+                let stop = Arc::clone(&stop);
+                thread::Builder::new()
+                    .name("camera".to_string())
+                    .spawn(move || capture(&mut stream, format, &latest, &stop))?
+            };
+
+            let source = Self {
+                name: format!("{} ({}x{})", card, format.width, format.height),
+                latest,
+                stop,
+                thread: Some(thread),
+            };
+            source.wait_first_frame(&path)?;
+            Ok(source)
+        }
+
+        fn wait_first_frame(&self, path: &Path) -> Result<()> {
+            let deadline = Instant::now() + FIRST_FRAME_TIMEOUT;
+            while Instant::now() < deadline {
+                if let Ok(latest) = self.latest.lock() {
+                    if let Some(error) = &latest.error {
+                        return Err(MediaError::Video(error.clone()));
+                    }
+                    if latest.frame.is_some() {
+                        return Ok(());
+                    }
+                }
+                thread::sleep(Duration::from_millis(20));
+            }
+            Err(MediaError::Video(format!(
+                "No frames from {} within {} seconds",
+                path.display(),
+                FIRST_FRAME_TIMEOUT.as_secs()
+            )))
+        }
+    }
+
+    fn open_error(path: &Path, error: std::io::Error) -> MediaError {
+        MediaError::Video(format!("Cannot open camera {}: {}", path.display(), error))
+    }
+
+    /// YUYV、だめなら MJPEG で取り込めるよう設定する
+    fn negotiate_format(
+        device: &Device,
+        width: u32,
+        height: u32,
+    ) -> std::io::Result<Option<PixFormat>> {
+        for pixel_format in [YUYV, MJPEG] {
+            let format = device.set_format(width, height, pixel_format)?;
+            if format.pixel_format == pixel_format {
+                return Ok(Some(format));
+            }
+        }
+        Ok(None)
+    }
+
+    /// 止められるまでフレームを取り込み、最新のフレームを `latest` に置く
+    fn capture(
+        stream: &mut v4l2::Stream,
+        format: PixFormat,
+        latest: &Mutex<Latest>,
+        stop: &AtomicBool,
+    ) {
+        while !stop.load(Ordering::Relaxed) {
+            let mut decoded = None;
+            let result = stream.next_frame(POLL_INTERVAL, |data| {
+                decoded = Some(decode(data, &format));
+            });
+            let Ok(mut latest) = latest.lock() else {
+                return;
+            };
+            match (result, decoded) {
+                (Err(e), _) => {
+                    latest.error = Some(format!("Camera capture failed: {}", e));
+                    return;
+                }
+                // 壊れた MJPEG のフレームは読み飛ばす
+                (Ok(_), Some(Ok(frame))) => latest.frame = Some(frame),
+                _ => {}
+            }
+        }
+    }
+
+    /// 取り込んだバッファを左右反転した RGB のフレームにする
+    fn decode(data: &[u8], format: &PixFormat) -> Result<VideoFrame> {
+        let (width, height, rgb) = if format.pixel_format == YUYV {
+            let rgb = yuyv_to_rgb(
+                data,
+                format.width,
+                format.height,
+                format.bytes_per_line,
+                true,
+            );
+            (format.width, format.height, rgb)
+        } else {
+            let image = image::load_from_memory_with_format(data, image::ImageFormat::Jpeg)?;
+            let image = image::imageops::flip_horizontal(&image.to_rgb8());
+            (image.width(), image.height(), image.into_raw())
+        };
+        Ok(VideoFrame::new(
+            rgb,
+            width,
+            height,
+            FrameFormat::RGB8,
+            Duration::ZERO,
+            0,
+        ))
+    }
+}
+
+#[cfg(target_os = "linux")]
+pub use platform::list_cameras;
+
+#[cfg(not(target_os = "linux"))]
+pub fn list_cameras() -> Vec<CameraInfo> {
+    Vec::new()
+}
+
+#[cfg(not(target_os = "linux"))]
+impl CameraSource {
+    pub fn open(_device: &str, _size: Option<(u32, u32)>) -> Result<Self> {
+        Err(MediaError::Video(
+            "Camera input is only supported on Linux".to_string(),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_device_path() {
+        assert_eq!(device_path("0"), PathBuf::from("/dev/video0"));
+        assert_eq!(device_path("12"), PathBuf::from("/dev/video12"));
+        assert_eq!(
+            device_path("/dev/v4l/by-id/usb-cam"),
+            PathBuf::from("/dev/v4l/by-id/usb-cam")
+        );
+    }
+
+    #[test]
+    fn test_yuyv_to_rgb() {
+        // 2x2: 各行は黒・白の 2 ピクセル（色差は灰色）。行末に 4 バイトの余白
+        let row = [16, 128, 235, 128, 0, 0, 0, 0];
+        let data = [row, row].concat();
+
+        let rgb = yuyv_to_rgb(&data, 2, 2, 8, false);
+        assert_eq!(rgb, [0, 0, 0, 255, 255, 255, 0, 0, 0, 255, 255, 255]);
+
+        let mirrored = yuyv_to_rgb(&data, 2, 2, 8, true);
+        assert_eq!(mirrored, [255, 255, 255, 0, 0, 0, 255, 255, 255, 0, 0, 0]);
+
+        // 足りないデータは黒のまま
+        assert_eq!(yuyv_to_rgb(&row[..4], 2, 2, 4, false).len(), 12);
+        // 組の途中で切れた行と奇数の幅
+        assert_eq!(
+            yuyv_to_rgb(&row[..6], 4, 1, 8, false),
+            [0, 0, 0, 255, 255, 255, 0, 0, 0, 0, 0, 0]
+        );
+        assert_eq!(
+            yuyv_to_rgb(&row[..6], 3, 1, 6, false),
+            [0, 0, 0, 255, 255, 255, 0, 0, 0]
+        );
+    }
+}
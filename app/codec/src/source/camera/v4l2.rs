@@ -0,0 +1,376 @@
+//! Video4Linux2 の ioctl（カメラ入力に使う分だけ）
+//!
+//! 構造体は `<linux/videodev2.h>` と同じ並びで定義する。メモリマップしたバッファを
+//! キューに入れて順に受け取る、ストリーミング入出力だけを使う
+
+use std::ffi::c_void;
+use std::fs::{File, OpenOptions};
+use std::io;
+use std::os::fd::AsRawFd;
+use std::path::Path;
+use std::ptr;
+use std::time::Duration;
+
+const IOC_WRITE: u32 = 1;
+const IOC_READ: u32 = 2;
+
+/// `_IOC(dir, 'V', nr, size)`
+const fn ioc(dir: u32, nr: u32, size: usize) -> u32 {
+    (dir << 30) | ((size as u32) << 16) | ((b'V' as u32) << 8) | nr
+}
+
+const VIDIOC_QUERYCAP: u32 = ioc(IOC_READ, 0, size_of::<Capability>());
+const VIDIOC_S_FMT: u32 = ioc(IOC_READ | IOC_WRITE, 5, size_of::<Format>());
+const VIDIOC_REQBUFS: u32 = ioc(IOC_READ | IOC_WRITE, 8, size_of::<RequestBuffers>());
+const VIDIOC_QUERYBUF: u32 = ioc(IOC_READ | IOC_WRITE, 9, size_of::<Buffer>());
+const VIDIOC_QBUF: u32 = ioc(IOC_READ | IOC_WRITE, 15, size_of::<Buffer>());
+const VIDIOC_DQBUF: u32 = ioc(IOC_READ | IOC_WRITE, 17, size_of::<Buffer>());
+const VIDIOC_STREAMON: u32 = ioc(IOC_WRITE, 18, size_of::<i32>());
+const VIDIOC_STREAMOFF: u32 = ioc(IOC_WRITE, 19, size_of::<i32>());
+const VIDIOC_ENUM_FRAMESIZES: u32 = ioc(IOC_READ | IOC_WRITE, 74, size_of::<FrameSizeEnum>());
+
+const BUF_TYPE_VIDEO_CAPTURE: u32 = 1;
+const MEMORY_MMAP: u32 = 1;
+const FIELD_NONE: u32 = 1;
+const CAP_VIDEO_CAPTURE: u32 = 0x0000_0001;
+const CAP_STREAMING: u32 = 0x0400_0000;
+const CAP_DEVICE_CAPS: u32 = 0x8000_0000;
+const FRMSIZE_TYPE_DISCRETE: u32 = 1;
+
+/// ピクセル形式の FourCC
+pub const fn fourcc(code: &[u8; 4]) -> u32 {
+    u32::from_le_bytes(*code)
+}
+
+#[repr(C)]
+struct Capability {
+    driver: [u8; 16],
+    card: [u8; 32],
+    bus_info: [u8; 32],
+    version: u32,
+    capabilities: u32,
+    device_caps: u32,
+    reserved: [u32; 3],
+}
+
+/// 取り込む映像の形式（`struct v4l2_pix_format`）
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct PixFormat {
+    pub width: u32,
+    pub height: u32,
+    pub pixel_format: u32,
+    field: u32,
+    pub bytes_per_line: u32,
+    size_image: u32,
+    colorspace: u32,
+    private: u32,
+    flags: u32,
+    ycbcr_enc: u32,
+    quantization: u32,
+    xfer_func: u32,
+}
+
+/// `struct v4l2_format` の共用体（ポインターを含む形式があるため、その境界にそろう）
+#[repr(C)]
+union FormatData {
+    pix: PixFormat,
+    raw: [u8; 200],
+    _align: [*mut c_void; 0],
+}
+
+#[repr(C)]
+struct Format {
+    buffer_type: u32,
+    data: FormatData,
+}
+
+#[repr(C)]
+struct RequestBuffers {
+    count: u32,
+    buffer_type: u32,
+    memory: u32,
+    capabilities: u32,
+    flags: u8,
+    reserved: [u8; 3],
+}
+
+#[repr(C)]
+struct Timecode {
+    timecode_type: u32,
+    flags: u32,
+    frames: u8,
+    seconds: u8,
+    minutes: u8,
+    hours: u8,
+    userbits: [u8; 4],
+}
+
+#[repr(C)]
+union BufferLocation {
+    offset: u32,
+    userptr: libc::c_ulong,
+    planes: *mut c_void,
+    fd: i32,
+}
+
+#[repr(C)]
+struct Buffer {
+    index: u32,
+    buffer_type: u32,
+    bytes_used: u32,
+    flags: u32,
+    field: u32,
+    timestamp: libc::timeval,
+    timecode: Timecode,
+    sequence: u32,
+    memory: u32,
+    location: BufferLocation,
+    length: u32,
+    reserved2: u32,
+    request_fd: i32,
+}
+
+#[repr(C)]
+struct FrameSizeEnum {
+    index: u32,
+    pixel_format: u32,
+    size_type: u32,
+    /// 固定の大きさなら先頭 2 つが幅と高さ。段階的なら最小・最大・刻み
+    size: [u32; 6],
+    reserved: [u32; 2],
+}
+
+#[cfg(target_pointer_width = "64")]
+const _: () = {
+    assert!(size_of::<Capability>() == 104);
+    assert!(size_of::<Format>() == 208);
+    assert!(size_of::<RequestBuffers>() == 20);
+    assert!(size_of::<Buffer>() == 88);
+    assert!(size_of::<FrameSizeEnum>() == 44);
+};
+
+/// すべてのフィールドが 0 の値（V4L2 の構造体は 0 埋めで渡す決まり）
+fn zeroed<T>() -> T {
+    // SAFETY: このモジュールの構造体は整数・配列・ポインターだけで、0 は有効な値
+    unsafe { std::mem::zeroed() }
+}
+
+/// C の文字列（NUL 終端）の部分
+fn c_string(bytes: &[u8]) -> String {
+    let end = bytes.iter().position(|&b| b == 0).unwrap_or(bytes.len());
+    String::from_utf8_lossy(&bytes[..end]).into_owned()
+}
+
+/// 開いたビデオデバイス
+pub struct Device {
+    file: File,
+}
+
+impl Device {
+    pub fn open(path: &Path) -> io::Result<Self> {
+        let file = OpenOptions::new().read(true).write(true).open(path)?;
+        Ok(Self { file })
+    }
+
+    fn ioctl<T>(&self, request: u32, arg: &mut T) -> io::Result<()> {
+        loop {
+            // SAFETY: `request` は `T` の大きさで作った番号で、`arg` は有効な `T` を指す
+            let result = unsafe {
+                libc::ioctl(
+                    self.file.as_raw_fd(),
+                    request as _,
+                    arg as *mut T as *mut c_void,
+                )
+            };
+            if result != -1 {
+                return Ok(());
+            }
+            let error = io::Error::last_os_error();
+            if error.kind() != io::ErrorKind::Interrupted {
+                return Err(error);
+            }
+        }
+    }
+
+    /// デバイス名と、映像を取り込めるかどうか
+    pub fn query(&self) -> io::Result<(String, bool)> {
+        let mut capability: Capability = zeroed();
+        self.ioctl(VIDIOC_QUERYCAP, &mut capability)?;
+        let caps = if capability.capabilities & CAP_DEVICE_CAPS != 0 {
+            capability.device_caps
+        } else {
+            capability.capabilities
+        };
+        let capture = caps & CAP_VIDEO_CAPTURE != 0 && caps & CAP_STREAMING != 0;
+        Ok((c_string(&capability.card), capture))
+    }
+
+    /// `pixel_format` で取り込める大きさ（段階的に選べる場合は最大の大きさだけ）
+    pub fn frame_sizes(&self, pixel_format: u32) -> Vec<(u32, u32)> {
+        let mut sizes = Vec::new();
+        for index in 0.. {
+            let mut size = FrameSizeEnum {
+                index,
+                pixel_format,
+                ..zeroed()
+            };
+            if self.ioctl(VIDIOC_ENUM_FRAMESIZES, &mut size).is_err() {
+                break;
+            }
+            if size.size_type == FRMSIZE_TYPE_DISCRETE {
+                sizes.push((size.size[0], size.size[1]));
+            } else {
+                sizes.push((size.size[1], size.size[3]));
+                break;
+            }
+        }
+        sizes
+    }
+
+    /// 取り込む形式を設定し、デバイスが実際に選んだ形式を返す
+    pub fn set_format(&self, width: u32, height: u32, pixel_format: u32) -> io::Result<PixFormat> {
+        let mut format = Format {
+            buffer_type: BUF_TYPE_VIDEO_CAPTURE,
+            data: FormatData { raw: [0; 200] },
+        };
+        format.data.pix = PixFormat {
+            width,
+            height,
+            pixel_format,
+            field: FIELD_NONE,
+            ..PixFormat::default()
+        };
+        self.ioctl(VIDIOC_S_FMT, &mut format)?;
+        // SAFETY: 映像の取り込みでは共用体のうち `pix` が使われる
+        Ok(unsafe { format.data.pix })
+    }
+
+    /// `count` 個のバッファをメモリマップして取り込みを始める
+    pub fn start(self, count: u32) -> io::Result<Stream> {
+        let mut request = RequestBuffers {
+            count,
+            buffer_type: BUF_TYPE_VIDEO_CAPTURE,
+            memory: MEMORY_MMAP,
+            ..zeroed()
+        };
+        self.ioctl(VIDIOC_REQBUFS, &mut request)?;
+
+        let mut stream = Stream {
+            device: self,
+            buffers: Vec::new(),
+            streaming: false,
+        };
+        for index in 0..request.count {
+            let mut buffer = new_buffer(index);
+            stream.device.ioctl(VIDIOC_QUERYBUF, &mut buffer)?;
+            // SAFETY: QUERYBUF が返した位置と長さでデバイスのバッファをマップする
+            let address = unsafe {
+                libc::mmap(
+                    ptr::null_mut(),
+                    buffer.length as usize,
+                    libc::PROT_READ | libc::PROT_WRITE,
+                    libc::MAP_SHARED,
+                    stream.device.file.as_raw_fd(),
+                    buffer.location.offset as libc::off_t,
+                )
+            };
+            if address == libc::MAP_FAILED {
+                return Err(io::Error::last_os_error());
+            }
+            stream.buffers.push((address, buffer.length as usize));
+            stream.device.ioctl(VIDIOC_QBUF, &mut buffer)?;
+        }
+
+        let mut buffer_type = BUF_TYPE_VIDEO_CAPTURE as i32;
+        stream.device.ioctl(VIDIOC_STREAMON, &mut buffer_type)?;
+        stream.streaming = true;
+        Ok(stream)
+    }
+}
+
+fn new_buffer(index: u32) -> Buffer {
+    Buffer {
+        index,
+        buffer_type: BUF_TYPE_VIDEO_CAPTURE,
+        memory: MEMORY_MMAP,
+        ..zeroed()
+    }
+}
+
+/// 取り込み中のデバイス。破棄すると取り込みを止めてバッファを解放する
+pub struct Stream {
+    device: Device,
+    buffers: Vec<(*mut c_void, usize)>,
+    streaming: bool,
+}
+
+// SAFETY: マップしたバッファはこの値だけが使う
+unsafe impl Send for Stream {}
+
+impl Stream {
+    /// 次のフレームを待って `f` に渡す。`timeout` までに届かなければ false
+    pub fn next_frame(&mut self, timeout: Duration, f: impl FnOnce(&[u8])) -> io::Result<bool> {
+        let mut poll_fd = libc::pollfd {
+            fd: self.device.file.as_raw_fd(),
+            events: libc::POLLIN,
+            revents: 0,
+        };
+        // SAFETY: 1 つの有効な pollfd を渡す
+        let ready = unsafe { libc::poll(&mut poll_fd, 1, timeout.as_millis() as libc::c_int) };
+        if ready < 0 {
+            let error = io::Error::last_os_error();
+            return match error.kind() {
+                io::ErrorKind::Interrupted => Ok(false),
+                _ => Err(error),
+            };
+        }
+        if ready == 0 {
+            return Ok(false);
+        }
+
+        let mut buffer = new_buffer(0);
+        self.device.ioctl(VIDIOC_DQBUF, &mut buffer)?;
+        if let Some(&(address, length)) = self.buffers.get(buffer.index as usize) {
+            let used = (buffer.bytes_used as usize).min(length);
+            // SAFETY: キューから外したバッファは、戻すまでデバイスが書き込まない
+            let data = unsafe { std::slice::from_raw_parts(address as *const u8, used) };
+            f(data);
+        }
+        self.device.ioctl(VIDIOC_QBUF, &mut buffer)?;
+        Ok(true)
+    }
+}
+
+impl Drop for Stream {
+    fn drop(&mut self) {
+        if self.streaming {
+            let mut buffer_type = BUF_TYPE_VIDEO_CAPTURE as i32;
+            let _ = self.device.ioctl(VIDIOC_STREAMOFF, &mut buffer_type);
+        }
+        for &(address, length) in &self.buffers {
+            // SAFETY: start() でマップした範囲
+            unsafe {
+                libc::munmap(address, length);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ioctl_numbers() {
+        // <linux/videodev2.h> の値（64 ビット）
+        assert_eq!(VIDIOC_QUERYCAP, 0x8068_5600);
+        assert_eq!(VIDIOC_STREAMON, 0x4004_5612);
+        #[cfg(target_pointer_width = "64")]
+        {
+            assert_eq!(VIDIOC_S_FMT, 0xc0d0_5605);
+            assert_eq!(VIDIOC_DQBUF, 0xc058_5611);
+        }
+        assert_eq!(fourcc(b"YUYV"), 0x5659_5559);
+    }
+}
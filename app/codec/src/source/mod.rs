@@ -0,0 +1,107 @@
+//! ファイルを使わずにフレームを生成する手続き的なメディアソース
+//!
+//! スクリーンセーバーやテストパターンのように、時刻と出力サイズだけから
+//! 映像を組み立てるソースの共通インターフェース。FFmpeg を通さずに読み込む
+//! 複数フレームの画像や Web カメラも同じインターフェースで再生する
+
+pub mod animated;
+pub mod camera;
+pub mod raster;
+pub mod test_pattern;
+
+use std::time::Duration;
+
+use crate::errors::{MediaError, Result};
+use crate::video::VideoFrame;
+
+pub use animated::AnimatedImage;
+pub use camera::CameraSource;
+pub use raster::Raster;
+
+/// 組み込みテスト入力の接頭辞（`test:bars` など）
+pub const TEST_SCHEME: &str = "test:";
+
+/// 手続き的にフレームを生成するメディアソース
+pub trait MediaSource: Send {
+    /// 表示用のソース名
+    fn name(&self) -> &str;
+
+    /// フレームレート
+    fn frame_rate(&self) -> f64 {
+        30.0
+    }
+
+    /// 映像を持つかどうか（音声だけのソースは false）
+    fn has_video(&self) -> bool {
+        true
+    }
+
+    /// 再生開始からの経過時間 `timestamp` における `width` x `height` の RGB フレームを生成
+    fn video_frame(&mut self, timestamp: Duration, width: u32, height: u32) -> Result<VideoFrame>;
+
+    /// 音声を持つ場合は、再生スレッドに渡す音声ジェネレーターを返す
+    fn audio(&mut self) -> Option<Box<dyn AudioGenerator>> {
+        None
+    }
+}
+
+/// 手続き的に PCM を生成する音声ジェネレーター
+pub trait AudioGenerator: Send {
+    fn sample_rate(&self) -> u32;
+
+    fn channels(&self) -> u16;
+
+    /// 続きのサンプル（インターリーブ済み f32）で `out` を埋める
+    fn fill(&mut self, out: &mut [f32]);
+}
+
+/// パスが組み込みテスト入力かどうか
+pub fn is_test_source(path: &str) -> bool {
+    path.starts_with(TEST_SCHEME)
+}
+
+/// `test:bars` / `test:gradient` / `test:tone440` などの組み込みテスト入力を開く
+pub fn open_test_source(path: &str) -> Result<Box<dyn MediaSource>> {
+    let name = path
+        .strip_prefix(TEST_SCHEME)
+        .ok_or_else(|| MediaError::InvalidFormat(format!("Not a test source: {}", path)))?;
+
+    match name {
+        "bars" => Ok(Box::new(test_pattern::ColorBars)),
+        "gradient" => Ok(Box::new(test_pattern::Gradient)),
+        _ => {
+            let frequency = name
+                .strip_prefix("tone")
+                .and_then(|hz| hz.parse::<f32>().ok())
+                .filter(|hz| *hz > 0.0 && *hz < 20_000.0)
+                .ok_or_else(|| {
+                    MediaError::InvalidFormat(format!(
+                        "Unknown test source '{}' (expected bars, gradient or tone<Hz>)",
+                        name
+                    ))
+                })?;
+            Ok(Box::new(test_pattern::Tone::new(frequency)))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_open_test_source() {
+        assert!(is_test_source("test:bars"));
+        assert!(!is_test_source("video.mp4"));
+
+        let bars = open_test_source("test:bars").unwrap();
+        assert_eq!(bars.name(), "bars");
+
+        let mut tone = open_test_source("test:tone440").unwrap();
+        assert!(!tone.has_video());
+        assert!(tone.audio().is_some());
+
+        assert!(open_test_source("test:tone").is_err());
+        assert!(open_test_source("test:unknown").is_err());
+    }
+}
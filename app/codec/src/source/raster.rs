@@ -0,0 +1,103 @@
+//! 手続き的ソース用の RGB 描画バッファ
+
+use std::time::Duration;
+
+use crate::video::VideoFrame;
+use crate::video::frame::FrameFormat;
+
+pub type Rgb = [u8; 3];
+
+/// RGB8 のピクセルバッファ
+#[derive(Debug, Clone)]
+pub struct Raster {
+    width: u32,
+    height: u32,
+    data: Vec<u8>,
+}
+
+impl Raster {
+    /// 黒で塗りつぶしたバッファを作成
+    pub fn new(width: u32, height: u32) -> Self {
+        Self {
+            width,
+            height,
+            data: vec![0; width as usize * height as usize * 3],
+        }
+    }
+
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    /// 1 ピクセルを塗る（範囲外は無視する）
+    pub fn put(&mut self, x: i32, y: i32, color: Rgb) {
+        if x < 0 || y < 0 || x >= self.width as i32 || y >= self.height as i32 {
+            return;
+        }
+        let index = (y as usize * self.width as usize + x as usize) * 3;
+        self.data[index..index + 3].copy_from_slice(&color);
+    }
+
+    /// 指定位置のピクセルを取得
+    pub fn get(&self, x: u32, y: u32) -> Option<Rgb> {
+        if x >= self.width || y >= self.height {
+            return None;
+        }
+        let index = (y as usize * self.width as usize + x as usize) * 3;
+        Some([self.data[index], self.data[index + 1], self.data[index + 2]])
+    }
+
+    /// 矩形を塗りつぶす（範囲外ははみ出した分を切り捨てる）
+    pub fn fill_rect(&mut self, x: i32, y: i32, width: u32, height: u32, color: Rgb) {
+        for dy in 0..height as i32 {
+            for dx in 0..width as i32 {
+                self.put(x + dx, y + dy, color);
+            }
+        }
+    }
+
+    /// 全体を塗りつぶす
+    pub fn fill(&mut self, color: Rgb) {
+        for pixel in self.data.chunks_exact_mut(3) {
+            pixel.copy_from_slice(&color);
+        }
+    }
+
+    /// ビデオフレームに変換
+    pub fn into_frame(self, timestamp: Duration) -> VideoFrame {
+        VideoFrame::new(
+            self.data,
+            self.width,
+            self.height,
+            FrameFormat::RGB8,
+            timestamp,
+            timestamp.as_millis() as i64,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_raster_clips_out_of_bounds() {
+        let mut raster = Raster::new(4, 3);
+        raster.fill_rect(2, 1, 5, 5, [255, 0, 0]);
+        raster.put(-1, 0, [0, 255, 0]);
+
+        assert_eq!(raster.get(3, 2), Some([255, 0, 0]));
+        assert_eq!(raster.get(1, 1), Some([0, 0, 0]));
+        assert_eq!(raster.get(0, 0), Some([0, 0, 0]));
+        assert_eq!(raster.get(4, 0), None);
+
+        let frame = raster.into_frame(Duration::from_millis(40));
+        assert_eq!(frame.data.len(), 4 * 3 * 3);
+        assert_eq!(frame.format, FrameFormat::RGB8);
+        assert_eq!(frame.pts, 40);
+    }
+}
@@ -0,0 +1,243 @@
+//! キャリブレーション用のテストパターンとテストトーン
+//!
+//! 文字マップや色、音声出力をサンプルファイルなしで確認するための組み込み入力
+
+use std::f32::consts::TAU;
+use std::time::Duration;
+
+use super::{AudioGenerator, MediaSource, Raster};
+use crate::errors::Result;
+use crate::video::VideoFrame;
+
+/// 75% 強度のカラーバー（白・黄・シアン・緑・マゼンタ・赤・青）
+const BARS: [[u8; 3]; 7] = [
+    [191, 191, 191],
+    [191, 191, 0],
+    [0, 191, 191],
+    [0, 191, 0],
+    [191, 0, 191],
+    [191, 0, 0],
+    [0, 0, 191],
+];
+
+/// カラーバー下の逆順ストリップ（青・黒・マゼンタ・黒・シアン・黒・白）
+const CASTELLATIONS: [[u8; 3]; 7] = [
+    [0, 0, 191],
+    [19, 19, 19],
+    [191, 0, 191],
+    [19, 19, 19],
+    [0, 191, 191],
+    [19, 19, 19],
+    [191, 191, 191],
+];
+
+/// 最下段（-I・白・+Q・黒・PLUGE の 3 段階・黒）
+const BOTTOM: [[u8; 3]; 8] = [
+    [0, 33, 76],
+    [255, 255, 255],
+    [50, 0, 106],
+    [19, 19, 19],
+    [9, 9, 9],
+    [19, 19, 19],
+    [29, 29, 29],
+    [19, 19, 19],
+];
+
+/// SMPTE 風のカラーバー
+pub struct ColorBars;
+
+impl MediaSource for ColorBars {
+    fn name(&self) -> &str {
+        "bars"
+    }
+
+    fn frame_rate(&self) -> f64 {
+        1.0
+    }
+
+    fn video_frame(&mut self, timestamp: Duration, width: u32, height: u32) -> Result<VideoFrame> {
+        let mut raster = Raster::new(width, height);
+        let bars_bottom = height * 2 / 3;
+        let strip_bottom = height * 3 / 4;
+
+        for y in 0..height {
+            let colors: &[[u8; 3]] = if y < bars_bottom {
+                &BARS
+            } else if y < strip_bottom {
+                &CASTELLATIONS
+            } else {
+                &BOTTOM
+            };
+            for x in 0..width {
+                let index = (x as usize * colors.len()) / width.max(1) as usize;
+                raster.put(x as i32, y as i32, colors[index]);
+            }
+        }
+
+        Ok(raster.into_frame(timestamp))
+    }
+}
+
+/// グレー・赤・緑・青の 4 段の横方向グラデーション
+pub struct Gradient;
+
+impl MediaSource for Gradient {
+    fn name(&self) -> &str {
+        "gradient"
+    }
+
+    fn frame_rate(&self) -> f64 {
+        1.0
+    }
+
+    fn video_frame(&mut self, timestamp: Duration, width: u32, height: u32) -> Result<VideoFrame> {
+        let mut raster = Raster::new(width, height);
+        let span = width.saturating_sub(1).max(1);
+
+        for y in 0..height {
+            let band = (y * 4 / height.max(1)).min(3);
+            for x in 0..width {
+                let level = (x * 255 / span) as u8;
+                let color = match band {
+                    0 => [level, level, level],
+                    1 => [level, 0, 0],
+                    2 => [0, level, 0],
+                    _ => [0, 0, level],
+                };
+                raster.put(x as i32, y as i32, color);
+            }
+        }
+
+        Ok(raster.into_frame(timestamp))
+    }
+}
+
+/// テストトーンのサンプルレート
+const TONE_SAMPLE_RATE: u32 = 48_000;
+
+/// テストトーンの振幅（-12 dBFS）
+const TONE_AMPLITUDE: f32 = 0.25;
+
+/// 正弦波のテストトーン（映像なし）
+pub struct Tone {
+    name: String,
+    frequency: f32,
+}
+
+impl Tone {
+    pub fn new(frequency: f32) -> Self {
+        Self {
+            name: format!("tone {} Hz", frequency),
+            frequency,
+        }
+    }
+}
+
+impl MediaSource for Tone {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn has_video(&self) -> bool {
+        false
+    }
+
+    fn video_frame(&mut self, timestamp: Duration, width: u32, height: u32) -> Result<VideoFrame> {
+        Ok(Raster::new(width, height).into_frame(timestamp))
+    }
+
+    fn audio(&mut self) -> Option<Box<dyn AudioGenerator>> {
+        Some(Box::new(SineWave::new(self.frequency, TONE_SAMPLE_RATE, 2)))
+    }
+}
+
+/// 位相を連続させたまま正弦波を生成する
+pub struct SineWave {
+    frequency: f32,
+    sample_rate: u32,
+    channels: u16,
+    phase: f32,
+}
+
+impl SineWave {
+    pub fn new(frequency: f32, sample_rate: u32, channels: u16) -> Self {
+        Self {
+            frequency,
+            sample_rate,
+            channels,
+            phase: 0.0,
+        }
+    }
+}
+
+impl AudioGenerator for SineWave {
+    fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    fn channels(&self) -> u16 {
+        self.channels
+    }
+
+    fn fill(&mut self, out: &mut [f32]) {
+        let step = TAU * self.frequency / self.sample_rate as f32;
+        for frame in out.chunks_mut(self.channels as usize) {
+            frame.fill(self.phase.sin() * TONE_AMPLITUDE);
+            self.phase = (self.phase + step) % TAU;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pixel(frame: &VideoFrame, x: u32, y: u32) -> [u8; 3] {
+        let index = ((y * frame.width + x) * 3) as usize;
+        [
+            frame.data[index],
+            frame.data[index + 1],
+            frame.data[index + 2],
+        ]
+    }
+
+    #[test]
+    fn test_color_bars_layout() {
+        let frame = ColorBars.video_frame(Duration::ZERO, 70, 12).unwrap();
+        assert_eq!(pixel(&frame, 0, 0), [191, 191, 191]);
+        assert_eq!(pixel(&frame, 15, 0), [191, 191, 0]);
+        assert_eq!(pixel(&frame, 69, 0), [0, 0, 191]);
+        assert_eq!(pixel(&frame, 0, 8), [0, 0, 191]);
+        assert_eq!(pixel(&frame, 10, 11), [255, 255, 255]);
+    }
+
+    #[test]
+    fn test_gradient_spans_full_range() {
+        let frame = Gradient.video_frame(Duration::ZERO, 64, 8).unwrap();
+        assert_eq!(pixel(&frame, 0, 0), [0, 0, 0]);
+        assert_eq!(pixel(&frame, 63, 0), [255, 255, 255]);
+        assert_eq!(pixel(&frame, 63, 2), [255, 0, 0]);
+        assert_eq!(pixel(&frame, 63, 7), [0, 0, 255]);
+    }
+
+    #[test]
+    fn test_sine_wave_frequency_and_level() {
+        let mut sine = SineWave::new(1000.0, 48_000, 2);
+        let mut samples = vec![0.0; 48_000 * 2];
+        sine.fill(&mut samples);
+
+        // 左右チャンネルは同じ値
+        assert!(samples.chunks(2).all(|frame| frame[0] == frame[1]));
+
+        // 1 秒間に 1000 周期 = 上向きのゼロ交差が 1000 回
+        let left: Vec<f32> = samples.iter().step_by(2).copied().collect();
+        let crossings = left
+            .windows(2)
+            .filter(|w| w[0] < 0.0 && w[1] >= 0.0)
+            .count();
+        assert!((999..=1001).contains(&crossings), "{}", crossings);
+
+        let peak = left.iter().fold(0.0f32, |max, s| max.max(s.abs()));
+        assert!((peak - TONE_AMPLITUDE).abs() < 0.01);
+    }
+}
@@ -0,0 +1,200 @@
+//! 字幕の読み込み
+//!
+//! 外部の字幕ファイル（SRT / ASS / WebVTT）は avio のパーサーで読む。avio は字幕
+//! パケットをデコードできないので、コンテナに埋め込まれたテキスト字幕（SubRip / ASS /
+//! mov_text / WebVTT）は ffmpeg コマンドで SRT に変換してから同じパーサーで読む
+
+use std::path::Path;
+use std::process::{Command, Stdio};
+use std::time::Duration;
+
+use crate::errors::{MediaError, Result};
+use crate::network;
+
+/// 埋め込み字幕の変換（と映像トラックの取り出し）に使うコマンド
+pub(crate) const FFMPEG: &str = "ffmpeg";
+
+/// avio が `SubtitleCodec::Other` に分類するテキスト字幕のコーデック名
+const OTHER_TEXT_CODECS: &[&str] = &["mov_text", "text"];
+
+/// 1 つの字幕（タグを取り除いた本文）
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SubtitleCue {
+    pub start: Duration,
+    pub end: Duration,
+    pub text: String,
+}
+
+/// 字幕ファイルを読む（形式は拡張子 `.srt` / `.ass` / `.ssa` / `.vtt` で決める）
+pub fn load_file(path: &Path) -> Result<Vec<SubtitleCue>> {
+    match avio::SubtitleTrack::from_file(path) {
+        Ok(track) => Ok(cues(track)),
+        Err(avio::SubtitleError::NoEvents) => Ok(Vec::new()),
+        Err(avio::SubtitleError::Io(e)) => Err(e.into()),
+        Err(e) => Err(MediaError::InvalidFormat(format!(
+            "Failed to read subtitles from {}: {}",
+            path.display(),
+            e
+        ))),
+    }
+}
+
+/// `path` の `stream_index` 番（コンテナ内のストリーム番号）の字幕をすべて読む
+///
+/// 入力全体を読むので、ネットワーク入力には使えない。画像の字幕（PGS、DVB）はエラー
+pub fn decode_stream(path: &str, stream_index: u32) -> Result<Vec<SubtitleCue>> {
+    if network::is_network_url(path) {
+        return Err(MediaError::InvalidFormat(
+            "Embedded subtitles of a network stream cannot be read".to_string(),
+        ));
+    }
+    let info = avio::open(path)?;
+    let streams = info.subtitle_streams();
+    let Some(stream) = streams.iter().find(|s| s.index() == stream_index) else {
+        return Err(MediaError::InvalidFormat(format!(
+            "Stream #{} is not a subtitle stream (subtitle streams: {})",
+            stream_index,
+            streams
+                .iter()
+                .map(|s| format!("#{}", s.index()))
+                .collect::<Vec<_>>()
+                .join(", ")
+        )));
+    };
+    if !is_text_stream(stream) {
+        return Err(MediaError::UnsupportedCodec(format!(
+            "{} subtitles are images and cannot be shown as text",
+            stream.codec_name()
+        )));
+    }
+
+    let output = Command::new(FFMPEG)
+        .args(["-nostdin", "-v", "error", "-i", path, "-map"])
+        .arg(format!("0:{}", stream_index))
+        .args(["-f", "srt", "-"])
+        .stdin(Stdio::null())
+        .output()
+        .map_err(|e| match e.kind() {
+            std::io::ErrorKind::NotFound => MediaError::Pipeline(format!(
+                "Reading embedded subtitles needs the {} command on PATH",
+                FFMPEG
+            )),
+            _ => e.into(),
+        })?;
+    if !output.status.success() {
+        return Err(MediaError::Pipeline(format!(
+            "Failed to convert subtitle stream #{}: {}",
+            stream_index,
+            String::from_utf8_lossy(&output.stderr).trim()
+        )));
+    }
+    parse_srt(&String::from_utf8_lossy(&output.stdout))
+}
+
+/// テキストとして表示できる字幕ストリームかどうか
+pub fn is_text_stream(stream: &avio::SubtitleStreamInfo) -> bool {
+    stream.is_text_based() || OTHER_TEXT_CODECS.contains(&stream.codec_name())
+}
+
+/// SRT のテキストを読む（字幕が 1 つもなければ空）
+fn parse_srt(text: &str) -> Result<Vec<SubtitleCue>> {
+    match avio::SubtitleTrack::from_srt(text) {
+        Ok(track) => Ok(cues(track)),
+        Err(avio::SubtitleError::NoEvents) => Ok(Vec::new()),
+        Err(e) => Err(MediaError::InvalidFormat(e.to_string())),
+    }
+}
+
+/// 本文が空のものを除き、開始時刻順に並べる
+fn cues(track: avio::SubtitleTrack) -> Vec<SubtitleCue> {
+    let mut cues: Vec<SubtitleCue> = track
+        .events
+        .into_iter()
+        .filter_map(|event| {
+            let text = event
+                .text
+                .lines()
+                .map(str::trim)
+                .filter(|line| !line.is_empty())
+                .collect::<Vec<_>>()
+                .join("\n");
+            (!text.is_empty()).then_some(SubtitleCue {
+                start: event.start,
+                end: event.end,
+                text,
+            })
+        })
+        .collect();
+    cues.sort_by_key(|cue| cue.start);
+    cues
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_srt() {
+        let cues = parse_srt(
+            "2\n00:00:05,000 --> 00:00:06,000\n<i>second</i>\n\n\
+             1\n00:00:01,000 --> 00:00:02,500\nfirst\n  line two \n\n",
+        )
+        .unwrap();
+        assert_eq!(
+            cues,
+            vec![
+                SubtitleCue {
+                    start: Duration::from_secs(1),
+                    end: Duration::from_millis(2500),
+                    text: "first\nline two".to_string(),
+                },
+                SubtitleCue {
+                    start: Duration::from_secs(5),
+                    end: Duration::from_secs(6),
+                    text: "second".to_string(),
+                },
+            ]
+        );
+        assert!(parse_srt("").unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_load_file() {
+        let path = std::env::temp_dir().join(format!("codec-subtitle-{}.ass", std::process::id()));
+        std::fs::write(
+            &path,
+            "[Script Info]\nScriptType: v4.00+\n\n[Events]\n\
+             Format: Layer, Start, End, Style, Name, MarginL, MarginR, MarginV, Effect, Text\n\
+             Dialogue: 0,0:00:01.00,0:00:03.00,Default,,0,0,0,,{\\i1}Hello\\NWorld\n",
+        )
+        .unwrap();
+        let cues = load_file(&path);
+        std::fs::remove_file(&path).unwrap();
+
+        let cues = cues.unwrap();
+        assert_eq!(cues.len(), 1);
+        assert_eq!(cues[0].text, "Hello\nWorld");
+        assert_eq!(cues[0].end, Duration::from_secs(3));
+
+        assert!(load_file(Path::new("subtitles.txt")).is_err());
+    }
+
+    #[test]
+    fn test_is_text_stream() {
+        let stream = |codec, name: &str| {
+            avio::SubtitleStreamInfo::builder()
+                .codec(codec)
+                .codec_name(name)
+                .build()
+        };
+        assert!(is_text_stream(&stream(avio::SubtitleCodec::Srt, "subrip")));
+        assert!(is_text_stream(&stream(
+            avio::SubtitleCodec::Other("mov_text".to_string()),
+            "mov_text"
+        )));
+        assert!(!is_text_stream(&stream(
+            avio::SubtitleCodec::Hdmv,
+            "hdmv_pgs_subtitle"
+        )));
+    }
+}
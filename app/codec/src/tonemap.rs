@@ -0,0 +1,256 @@
+//! HDR（PQ / HLG）の映像を SDR にするトーンマッピング
+//!
+//! BT.2020 の非線形 RGB を光の強さ（SDR の白を 1.0）に戻し、BT.709 の色域に移してから
+//! 曲線で 0.0〜1.0 に収め、sRGB にする。チャンネルごとの伝達関数は表引きにする
+
+use std::str::FromStr;
+
+use rayon::prelude::*;
+
+use crate::color::Rgb;
+
+/// SDR の白の明るさ（cd/m²、ITU-R BT.2408 の基準白）
+const SDR_WHITE_NITS: f32 = 203.0;
+
+/// 曲線で白に合わせる明るさ（HDR10 のマスタリングで一般的なピーク）
+const HDR_PEAK_NITS: f32 = 1000.0;
+
+/// PQ（SMPTE ST 2084）の定数
+const PQ_MAX_NITS: f32 = 10000.0;
+const PQ_M1: f32 = 2610.0 / 16384.0;
+const PQ_M2: f32 = 2523.0 / 4096.0 * 128.0;
+const PQ_C1: f32 = 3424.0 / 4096.0;
+const PQ_C2: f32 = 2413.0 / 4096.0 * 32.0;
+const PQ_C3: f32 = 2392.0 / 4096.0 * 32.0;
+
+/// HLG（ARIB STD-B67）の定数
+const HLG_A: f32 = 0.178_832_77;
+const HLG_B: f32 = 0.284_668_92;
+const HLG_C: f32 = 0.559_910_7;
+/// HLG を表示するときのシステムガンマ（1000 cd/m² のディスプレイ）
+const HLG_SYSTEM_GAMMA: f32 = 1.2;
+
+/// リニアから sRGB への表の大きさ
+const SRGB_TABLE_SIZE: usize = 4096;
+
+/// BT.2020 から BT.709 への色域の変換（リニア RGB）
+const BT2020_TO_BT709: [[f32; 3]; 3] = [
+    [1.6605, -0.5876, -0.0728],
+    [-0.1246, 1.1329, -0.0083],
+    [-0.0182, -0.1006, 1.1187],
+];
+
+/// 明るさを SDR に収める曲線
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ToneMap {
+    /// トーンマッピングしない（HDR の値をそのまま SDR として表示する）
+    None,
+    /// Hable（Uncharted 2）のフィルミック曲線。暗部と明部をなだらかに寄せる
+    #[default]
+    Hable,
+    /// 拡張 Reinhard。ピークがちょうど白になる
+    Reinhard,
+}
+
+impl ToneMap {
+    /// `--tonemap` などで指定する名前
+    pub fn name(self) -> &'static str {
+        match self {
+            ToneMap::None => "none",
+            ToneMap::Hable => "hable",
+            ToneMap::Reinhard => "reinhard",
+        }
+    }
+}
+
+impl FromStr for ToneMap {
+    type Err = String;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        match text.trim().to_ascii_lowercase().as_str() {
+            "none" | "off" => Ok(ToneMap::None),
+            "hable" => Ok(ToneMap::Hable),
+            "reinhard" => Ok(ToneMap::Reinhard),
+            _ => Err(format!(
+                "unknown tone map '{}' (expected hable, reinhard or none)",
+                text
+            )),
+        }
+    }
+}
+
+/// HDR の伝達関数
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum HdrTransfer {
+    /// PQ（SMPTE ST 2084、HDR10）
+    #[default]
+    Pq,
+    /// Hybrid Log-Gamma（放送の HDR）
+    Hlg,
+}
+
+impl HdrTransfer {
+    /// `--hdr-transfer` などで指定する名前
+    pub fn name(self) -> &'static str {
+        match self {
+            HdrTransfer::Pq => "pq",
+            HdrTransfer::Hlg => "hlg",
+        }
+    }
+
+    /// 符号値（0.0〜1.0）を明るさ（cd/m²）にする
+    fn to_nits(self, code: f32) -> f32 {
+        match self {
+            HdrTransfer::Pq => {
+                let p = code.powf(1.0 / PQ_M2);
+                let y = ((p - PQ_C1).max(0.0) / (PQ_C2 - PQ_C3 * p)).powf(1.0 / PQ_M1);
+                y * PQ_MAX_NITS
+            }
+            HdrTransfer::Hlg => {
+                let scene = if code <= 0.5 {
+                    code * code / 3.0
+                } else {
+                    (((code - HLG_C) / HLG_A).exp() + HLG_B) / 12.0
+                };
+                scene.powf(HLG_SYSTEM_GAMMA) * HDR_PEAK_NITS
+            }
+        }
+    }
+}
+
+impl FromStr for HdrTransfer {
+    type Err = String;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        match text.trim().to_ascii_lowercase().as_str() {
+            "pq" | "smpte2084" | "hdr10" => Ok(HdrTransfer::Pq),
+            "hlg" | "arib-std-b67" => Ok(HdrTransfer::Hlg),
+            _ => Err(format!(
+                "unknown HDR transfer '{}' (expected pq or hlg)",
+                text
+            )),
+        }
+    }
+}
+
+/// Hable の曲線（正規化前）
+fn hable(x: f32) -> f32 {
+    const A: f32 = 0.15;
+    const B: f32 = 0.50;
+    const C: f32 = 0.10;
+    const D: f32 = 0.20;
+    const E: f32 = 0.02;
+    const F: f32 = 0.30;
+    (x * (A * x + C * B) + D * E) / (x * (A * x + B) + D * F) - E / F
+}
+
+/// HDR の非線形 BT.2020 RGB を SDR の sRGB にする変換
+#[derive(Debug, Clone)]
+pub struct ToneMapper {
+    /// 8 ビットの符号値 → 明るさ（SDR の白 = 1.0）
+    to_linear: [f32; 256],
+    curve: ToneMap,
+    /// 曲線で白に合わせる明るさ（SDR の白 = 1.0）
+    peak: f32,
+    /// 0.0〜1.0 のリニアを `SRGB_TABLE_SIZE` 段階に分けた sRGB の値
+    to_srgb: Vec<u8>,
+}
+
+impl ToneMapper {
+    /// `curve` が `ToneMap::None` なら None
+    pub fn new(transfer: HdrTransfer, curve: ToneMap) -> Option<Self> {
+        if curve == ToneMap::None {
+            return None;
+        }
+        let mut to_linear = [0.0; 256];
+        for (code, linear) in to_linear.iter_mut().enumerate() {
+            *linear = transfer.to_nits(code as f32 / 255.0) / SDR_WHITE_NITS;
+        }
+        let to_srgb = (0..SRGB_TABLE_SIZE)
+            .map(|index| {
+                let c = index as f32 / (SRGB_TABLE_SIZE - 1) as f32;
+                let c = if c <= 0.003_130_8 {
+                    c * 12.92
+                } else {
+                    1.055 * c.powf(1.0 / 2.4) - 0.055
+                };
+                (c * 255.0).round() as u8
+            })
+            .collect();
+        Some(Self {
+            to_linear,
+            curve,
+            peak: HDR_PEAK_NITS / SDR_WHITE_NITS,
+            to_srgb,
+        })
+    }
+
+    /// リニアの値（SDR の白 = 1.0）を 0.0〜1.0 に収める
+    fn compress(&self, x: f32) -> f32 {
+        let x = x.clamp(0.0, self.peak);
+        match self.curve {
+            ToneMap::None => x.min(1.0),
+            ToneMap::Hable => hable(x) / hable(self.peak),
+            ToneMap::Reinhard => x * (1.0 + x / (self.peak * self.peak)) / (1.0 + x),
+        }
+    }
+
+    /// 1 画素を変換する
+    pub fn map(&self, rgb: Rgb) -> Rgb {
+        let linear = rgb.map(|c| self.to_linear[c as usize]);
+        BT2020_TO_BT709.map(|row| {
+            let c = row[0] * linear[0] + row[1] * linear[1] + row[2] * linear[2];
+            let index = (self.compress(c) * (SRGB_TABLE_SIZE - 1) as f32).round() as usize;
+            self.to_srgb[index.min(SRGB_TABLE_SIZE - 1)]
+        })
+    }
+
+    /// RGB8 の画素列をその場で変換する
+    pub fn map_rgb8(&self, data: &mut [u8]) {
+        data.par_chunks_mut(3 * 1024).for_each(|chunk| {
+            for pixel in chunk.chunks_exact_mut(3) {
+                let mapped = self.map([pixel[0], pixel[1], pixel[2]]);
+                pixel.copy_from_slice(&mapped);
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_transfer_functions() {
+        // PQ の 0.58 付近が 203 cd/m²（SDR の白）
+        assert!((HdrTransfer::Pq.to_nits(0.5806) - 203.0).abs() < 2.0);
+        assert!((HdrTransfer::Pq.to_nits(1.0) - PQ_MAX_NITS).abs() < 1.0);
+        assert_eq!(HdrTransfer::Pq.to_nits(0.0), 0.0);
+        assert!((HdrTransfer::Hlg.to_nits(1.0) - HDR_PEAK_NITS).abs() < 1.0);
+    }
+
+    #[test]
+    fn test_tone_mapper() {
+        assert!(ToneMapper::new(HdrTransfer::Pq, ToneMap::None).is_none());
+        for curve in [ToneMap::Hable, ToneMap::Reinhard] {
+            let mapper = ToneMapper::new(HdrTransfer::Pq, curve).unwrap();
+            assert_eq!(mapper.map([0, 0, 0]), [0, 0, 0]);
+            // ピークを超える値は白に収まる
+            assert_eq!(mapper.map([255, 255, 255]), [255, 255, 255]);
+            // SDR の白の灰色は暗すぎず、白まで飛ばない
+            let [r, g, b] = mapper.map([148, 148, 148]);
+            assert!(r == g && g == b && (100..250).contains(&r), "{:?}", curve);
+        }
+    }
+
+    #[test]
+    fn test_parse_names() {
+        for curve in [ToneMap::None, ToneMap::Hable, ToneMap::Reinhard] {
+            assert_eq!(curve.name().parse(), Ok(curve));
+        }
+        for transfer in [HdrTransfer::Pq, HdrTransfer::Hlg] {
+            assert_eq!(transfer.name().parse(), Ok(transfer));
+        }
+        assert!("aces".parse::<ToneMap>().is_err());
+    }
+}
@@ -0,0 +1,185 @@
+//! 音声・映像トラックの選択
+//!
+//! avio のデコーダーは常に最初の音声・映像ストリームをデコードするので、ほかのトラック
+//! （吹き替えや別アングルなど）はそのストリームだけを再エンコードせずにコピーした
+//! 一時ファイル（Matroska）にしてから開く。音声は avio の `AudioExtractor` でコピーし、
+//! 映像は avio に同じ API がないので ffmpeg コマンドでコピーする。
+//!
+//! 一時ファイルは tempfile で作る推測できない名前のディレクトリ（所有者だけが読み書きできる）
+//! の中に書くので、共有の一時ディレクトリに置かれたシンボリックリンクを上書きすることはない
+
+use std::path::Path;
+use std::process::{Command, Stdio};
+
+use tempfile::TempDir;
+
+use crate::errors::{MediaError, Result};
+use crate::network;
+use crate::subtitle::FFMPEG;
+
+/// トラックの種類
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TrackKind {
+    Audio,
+    Video,
+}
+
+impl TrackKind {
+    fn name(self) -> &'static str {
+        match self {
+            TrackKind::Audio => "audio",
+            TrackKind::Video => "video",
+        }
+    }
+
+    fn file_name(self) -> &'static str {
+        match self {
+            TrackKind::Audio => "track.mka",
+            TrackKind::Video => "track.mkv",
+        }
+    }
+
+    /// この種類のストリームの番号（コンテナ内の番号）
+    fn stream_indices(self, info: &avio::MediaInfo) -> Vec<usize> {
+        match self {
+            TrackKind::Audio => info
+                .audio_streams()
+                .iter()
+                .map(|s| s.index() as usize)
+                .collect(),
+            TrackKind::Video => info
+                .video_streams()
+                .iter()
+                .map(|s| s.index() as usize)
+                .collect(),
+        }
+    }
+
+    fn error(self, message: String) -> MediaError {
+        match self {
+            TrackKind::Audio => MediaError::Audio(message),
+            TrackKind::Video => MediaError::Video(message),
+        }
+    }
+}
+
+/// 1 本のトラックだけを取り出した一時ファイル（破棄するとディレクトリごと消す）
+#[derive(Debug)]
+pub struct TrackFile {
+    path: String,
+    _dir: TempDir,
+}
+
+impl TrackFile {
+    /// `path` の `stream_index` 番（コンテナ内のストリーム番号）のトラックを使えるようにする
+    ///
+    /// その種類の最初のストリーム（または None）ならそのまま開けるので None を返す。
+    /// 別の種類のストリームを指定したときはエラー。ファイル全体を読むことがあるので、
+    /// 非同期ランタイムのスレッドからは呼ばないこと
+    pub fn select(
+        path: &str,
+        kind: TrackKind,
+        stream_index: Option<usize>,
+    ) -> Result<Option<Self>> {
+        let Some(stream_index) = stream_index else {
+            return Ok(None);
+        };
+        let streams = kind.stream_indices(&avio::open(path)?);
+        match streams.iter().position(|&index| index == stream_index) {
+            Some(0) => Ok(None),
+            Some(_) => Self::extract(path, kind, stream_index).map(Some),
+            None => Err(MediaError::InvalidFormat(format!(
+                "Stream #{} is not {} {} stream ({} streams: {})",
+                stream_index,
+                if kind == TrackKind::Audio { "an" } else { "a" },
+                kind.name(),
+                kind.name(),
+                streams
+                    .iter()
+                    .map(|index| format!("#{}", index))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ))),
+        }
+    }
+
+    /// `path` の `stream_index` 番のストリームを一時ファイルにコピーする
+    ///
+    /// 入力全体を読むので、ネットワーク入力には使えない
+    pub fn extract(path: &str, kind: TrackKind, stream_index: usize) -> Result<Self> {
+        if network::is_network_url(path) {
+            return Err(kind.error(format!(
+                "Only the first {} track of a network stream can be played",
+                kind.name()
+            )));
+        }
+        let dir = tempfile::Builder::new().prefix("codec-track-").tempdir()?;
+        let output = dir.path().join(kind.file_name());
+        match kind {
+            TrackKind::Audio => avio::AudioExtractor::new(path, &output)
+                .stream_index(stream_index)
+                .run()
+                .map_err(|e| e.to_string()),
+            TrackKind::Video => copy_stream(path, stream_index, &output),
+        }
+        .map_err(|e| {
+            kind.error(format!(
+                "Failed to extract {} stream #{}: {}",
+                kind.name(),
+                stream_index,
+                e
+            ))
+        })?;
+
+        Ok(Self {
+            path: output.to_string_lossy().into_owned(),
+            _dir: dir,
+        })
+    }
+
+    /// 一時ファイルのパス
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+}
+
+/// ffmpeg コマンドで 1 本のストリームを `output` にコピーする（既にあれば上書きしない）
+fn copy_stream(path: &str, stream_index: usize, output: &Path) -> std::result::Result<(), String> {
+    let result = Command::new(FFMPEG)
+        .args(["-nostdin", "-v", "error", "-n", "-i", path, "-map"])
+        .arg(format!("0:{}", stream_index))
+        .args(["-c", "copy"])
+        .arg(output)
+        .stdin(Stdio::null())
+        .output();
+    match result {
+        Ok(result) if result.status.success() => Ok(()),
+        Ok(result) => Err(String::from_utf8_lossy(&result.stderr).trim().to_string()),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+            Err(format!("the {} command is not on PATH", FFMPEG))
+        }
+        Err(e) => Err(e.to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_network_tracks_are_not_extracted() {
+        let error = TrackFile::extract("https://example.com/movie.mkv", TrackKind::Video, 1)
+            .unwrap_err()
+            .to_string();
+        assert!(error.contains("first video track"), "{}", error);
+    }
+
+    #[test]
+    fn test_first_track_is_not_extracted() {
+        assert!(
+            TrackFile::select("movie.mkv", TrackKind::Audio, None)
+                .unwrap()
+                .is_none()
+        );
+    }
+}
@@ -1,51 +1,230 @@
-use std::sync::{Arc, Mutex};
+use std::sync::OnceLock;
+use std::thread;
+use std::time::Duration;
 
-use avio::PixelFormat;
+use avio::{PixelFormat, SeekMode};
+use crossbeam_channel::{Receiver, Sender, TryRecvError, bounded, select, unbounded};
 
+use crate::color::{Matrix, Range, YuvFormat};
 use crate::errors::{MediaError, Result};
+use crate::network;
+use crate::tonemap::{HdrTransfer, ToneMap, ToneMapper};
+use crate::track::{TrackFile, TrackKind};
 use crate::video::frame::VideoFrame;
+use crate::video::worker;
 
 /// ビデオデコーダー
 pub struct VideoDecoder {
-    inner: avio::VideoDecoder,
+    inner: RgbDecoder,
     width: u32,
     height: u32,
     frame_count: u64,
+    /// 最初のもの以外の映像トラックを選んだときに取り出したファイル（デコード中は消さない）
+    _track: Option<TrackFile>,
 }
 
-/// 非同期ビデオデコーダー（tokio::task::spawn_blocking でエグゼキューターをブロックしない）
+/// 先読みするフレーム数（デコードスレッドが描画より先に進める上限）
+const DECODE_AHEAD_FRAMES: usize = 4;
+
+/// 先読みしたフレームと、それをデコードしたときの移動の回数
+pub(super) type DecodedFrame = (u64, Result<Option<VideoFrame>>);
+
+/// 非同期ビデオデコーダー
+///
+/// 専用のスレッドで `DECODE_AHEAD_FRAMES` 枚先までデコードしておくので、
+/// 描画ループはデコードを待たずに次のフレームを取り出せる
 pub struct AsyncVideoDecoder {
-    inner: Arc<Mutex<avio::VideoDecoder>>,
+    frame_rx: Receiver<DecodedFrame>,
+    request_tx: Sender<Request>,
+    /// 移動した回数。これと異なるフレームは移動前に先読みしたもの
+    generation: u64,
     frame_count: u64,
 }
 
+/// ストリームの情報の代わりに使う YCbCr の解釈（None の項目はストリームに従う）
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct YuvOverride {
+    pub matrix: Option<Matrix>,
+    pub range: Option<Range>,
+}
+
+impl YuvOverride {
+    /// ストリームから読み取った解釈に指定を重ねる
+    pub fn apply(self, detected: YuvFormat) -> YuvFormat {
+        YuvFormat {
+            matrix: self.matrix.unwrap_or(detected.matrix),
+            range: self.range.unwrap_or(detected.range),
+        }
+    }
+}
+
+static YUV_OVERRIDE: OnceLock<YuvOverride> = OnceLock::new();
+
+/// 以降に開くデコーダーで、YCbCr をストリームの情報ではなく `yuv` の指定で RGB にする
+pub fn override_yuv_format(yuv: YuvOverride) -> Result<()> {
+    YUV_OVERRIDE
+        .set(yuv)
+        .map_err(|_| MediaError::Pipeline("YUV format override is already set".to_string()))
+}
+
+/// HDR の映像を SDR にする方法
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct HdrSettings {
+    pub tone_map: ToneMap,
+    /// デコーダーからは PQ と HLG を区別できないので、指定に従う
+    pub transfer: HdrTransfer,
+}
+
+static HDR_SETTINGS: OnceLock<HdrSettings> = OnceLock::new();
+
+/// 以降に開くデコーダーで、HDR の映像を `settings` に従って SDR にする
+pub fn set_hdr_settings(settings: HdrSettings) -> Result<()> {
+    HDR_SETTINGS
+        .set(settings)
+        .map_err(|_| MediaError::Pipeline("HDR settings are already set".to_string()))
+}
+
+/// 映像ストリームの YCbCr の解釈（RGB などの YCbCr でない映像は None）
+///
+/// 変換行列が不明・未対応なら BT.709、範囲が不明なら限定範囲とみなす
+pub fn detect_yuv_format(info: &avio::VideoStreamInfo) -> Option<YuvFormat> {
+    if !info.pixel_format().is_yuv() {
+        return None;
+    }
+    let matrix = match info.color_space() {
+        avio::ColorSpace::Bt601 => Matrix::Bt601,
+        avio::ColorSpace::Bt2020 => Matrix::Bt2020,
+        _ => Matrix::Bt709,
+    };
+    let range = match info.color_range() {
+        avio::ColorRange::Full => Range::Full,
+        _ => Range::Limited,
+    };
+    Some(YuvFormat { matrix, range })
+}
+
+/// avio デコーダーを開く。ネットワーク入力には接続設定を適用し、
+/// リアルタイム配信ではフレームスレッディングによる数フレーム分の遅延を避けるため
+/// デコードスレッドを 1 本にする
+fn open_avio_decoder(
+    path: &str,
+    format: PixelFormat,
+) -> std::result::Result<avio::VideoDecoder, avio::DecodeError> {
+    let mut builder = avio::VideoDecoder::open(path).output_format(format);
+    if network::is_network_url(path) {
+        builder = builder.network(network::network_options(path));
+    }
+    if network::is_realtime_url(path) {
+        builder = builder.thread_count(1);
+    }
+    builder.build()
+}
+
+/// RGB24 のフレームを返す avio デコーダー
+///
+/// FFmpeg の RGB への変換は変換行列を BT.601、範囲を限定範囲と決めてかかるので、
+/// YCbCr の映像は YUV420P で受け取り、ストリームの変換行列と範囲で RGB にする。
+/// HDR の映像はさらにトーンマッピングで SDR にする
+pub(super) struct RgbDecoder {
+    inner: avio::VideoDecoder,
+    /// YUV420P で受け取るときの解釈（RGB24 で受け取るときは None）
+    yuv: Option<YuvFormat>,
+    /// HDR の映像のトーンマッピング
+    tone_mapper: Option<ToneMapper>,
+}
+
+impl RgbDecoder {
+    pub(super) fn open(path: &str) -> std::result::Result<Self, avio::DecodeError> {
+        let inner = open_avio_decoder(path, PixelFormat::Yuv420p)?;
+        let Some(detected) = detect_yuv_format(inner.stream_info()) else {
+            // RGB などの映像は YUV を経由せず、FFmpeg に RGB24 にしてもらう
+            return Ok(Self {
+                inner: open_avio_decoder(path, PixelFormat::Rgb24)?,
+                yuv: None,
+                tone_mapper: None,
+            });
+        };
+        let yuv = YUV_OVERRIDE
+            .get()
+            .map_or(detected, |yuv_override| yuv_override.apply(detected));
+        let tone_mapper = if inner.stream_info().is_hdr() {
+            let settings = HDR_SETTINGS.get().copied().unwrap_or_default();
+            ToneMapper::new(settings.transfer, settings.tone_map)
+        } else {
+            None
+        };
+        Ok(Self {
+            inner,
+            yuv: Some(yuv),
+            tone_mapper,
+        })
+    }
+
+    pub(super) fn decode_one(&mut self) -> Result<Option<VideoFrame>> {
+        let Some(frame) = self.inner.decode_one().map_err(MediaError::Decode)? else {
+            return Ok(None);
+        };
+        let frame = match self.yuv {
+            Some(yuv) if frame.format() == PixelFormat::Yuv420p => {
+                let mut frame = VideoFrame::from_avio_yuv_frame(&frame, yuv)?;
+                if let Some(tone_mapper) = &self.tone_mapper {
+                    tone_mapper.map_rgb8(&mut frame.data);
+                }
+                frame
+            }
+            _ => VideoFrame::from_avio_frame(&frame)?,
+        };
+        Ok(Some(frame))
+    }
+
+    pub(super) fn seek(&mut self, position: Duration, mode: SeekMode) -> Result<()> {
+        self.inner.seek(position, mode).map_err(MediaError::Decode)
+    }
+
+    pub(super) fn width(&self) -> u32 {
+        self.inner.width()
+    }
+
+    pub(super) fn height(&self) -> u32 {
+        self.inner.height()
+    }
+}
+
 impl VideoDecoder {
     /// パスからビデオデコーダーを作成
-    pub fn new(path: &str, width: u32, height: u32) -> Result<Self> {
-        let inner = avio::VideoDecoder::open(path)
-            .output_format(PixelFormat::Rgb24)
-            .build()
-            .map_err(MediaError::Decode)?;
+    ///
+    /// `stream_index` はデコードする映像ストリームのコンテナ内の番号（None なら最初の映像）
+    pub fn new(path: &str, width: u32, height: u32, stream_index: Option<usize>) -> Result<Self> {
+        let track = TrackFile::select(path, TrackKind::Video, stream_index)?;
+        let path = track.as_ref().map_or(path, TrackFile::path);
+        let inner = RgbDecoder::open(path).map_err(MediaError::Decode)?;
 
         Ok(Self {
             inner,
             width,
             height,
             frame_count: 0,
+            _track: track,
         })
     }
 
     /// 次のフレームをデコード
     pub fn decode_one(&mut self) -> Result<Option<VideoFrame>> {
-        match self.inner.decode_one() {
-            Ok(Some(frame)) => {
-                let video_frame = VideoFrame::from_avio_frame(&frame)?;
-                self.frame_count += 1;
-                Ok(Some(video_frame))
-            }
-            Ok(None) => Ok(None),
-            Err(e) => Err(MediaError::Decode(e)),
+        let frame = self.inner.decode_one()?;
+        if frame.is_some() {
+            self.frame_count += 1;
         }
+        Ok(frame)
+    }
+
+    /// 指定した再生位置へ移動する（次にデコードされるのはその位置のフレーム）
+    pub fn seek(&mut self, position: Duration) -> Result<()> {
+        self.inner.seek(position, SeekMode::Exact)
+    }
+
+    /// 指定した再生位置の直前のキーフレームへ移動する（正確さより速さを優先する場合）
+    pub fn seek_keyframe(&mut self, position: Duration) -> Result<()> {
+        self.inner.seek(position, SeekMode::Keyframe)
     }
 
     /// デコーダーの情報を取得
@@ -63,48 +242,178 @@ impl VideoDecoder {
 }
 
 impl AsyncVideoDecoder {
-    /// パスから非同期ビデオデコーダーを作成
+    /// パスから非同期ビデオデコーダーを作成し、先読みを始める
     pub async fn open(path: &str) -> Result<Self> {
         let path = path.to_string();
-        let decoder = tokio::task::spawn_blocking(move || {
-            avio::VideoDecoder::open(&path)
-                .output_format(PixelFormat::Rgb24)
-                .build()
-        })
-        .await
-        .map_err(|e| MediaError::Pipeline(format!("spawn_blocking panicked: {e}")))?
-        .map_err(MediaError::Decode)?;
+        tokio::task::spawn_blocking(move || Self::open_blocking(&path))
+            .await
+            .map_err(|e| MediaError::Pipeline(format!("spawn_blocking panicked: {e}")))?
+    }
+
+    /// `open` と同じだが、開き終わるまで呼び出したスレッドをブロックする
+    ///
+    /// 非同期ランタイムの外（音声の準備と並行して開くスレッドなど）から使う
+    ///
+    /// [`worker::isolate_decoding`] を呼んだ後は、デコードを子プロセスで行う
+    pub fn open_blocking(path: &str) -> Result<Self> {
+        let (frame_tx, frame_rx) = bounded(DECODE_AHEAD_FRAMES);
+        let (request_tx, request_rx) = unbounded();
+        match worker::command() {
+            #[cfg(unix)]
+            Some(command) => worker::open(path, command, frame_tx, request_rx)?,
+            _ => {
+                let decoder = RgbDecoder::open(path).map_err(MediaError::Decode)?;
+                thread::Builder::new()
+                    .name("video-decode".to_string())
+                    .spawn(move || decode_ahead(decoder, frame_tx, request_rx))
+                    .map_err(|e| {
+                        MediaError::Pipeline(format!("failed to spawn decode thread: {e}"))
+                    })?;
+            }
+        }
 
         Ok(Self {
-            inner: Arc::new(Mutex::new(decoder)),
+            frame_rx,
+            request_tx,
+            generation: 0,
             frame_count: 0,
         })
     }
 
-    /// 次のフレームを非同期でデコード
+    /// 次のフレームを取り出す。先読みが追いついていなければデコードされるまで待つ
     pub async fn decode_one(&mut self) -> Result<Option<VideoFrame>> {
-        let inner = Arc::clone(&self.inner);
-
-        let avio_frame =
-            tokio::task::spawn_blocking(move || -> Result<Option<avio::VideoFrame>> {
-                let mut guard = inner
-                    .lock()
-                    .map_err(|_| MediaError::Pipeline("VideoDecoder mutex poisoned".to_string()))?;
-                guard.decode_one().map_err(MediaError::Decode)
-            })
-            .await
-            .map_err(|e| MediaError::Pipeline(format!("spawn_blocking panicked: {e}")))??;
-
-        match avio_frame {
-            Some(frame) => {
+        loop {
+            let (generation, decoded) = match self.frame_rx.try_recv() {
+                Ok(decoded) => decoded,
+                Err(TryRecvError::Empty) => {
+                    let frame_rx = self.frame_rx.clone();
+                    tokio::task::spawn_blocking(move || frame_rx.recv())
+                        .await
+                        .map_err(|e| MediaError::Pipeline(format!("spawn_blocking panicked: {e}")))?
+                        .map_err(|_| decode_thread_stopped())?
+                }
+                Err(TryRecvError::Disconnected) => return Err(decode_thread_stopped()),
+            };
+            // 移動する前に先読みしたフレームは捨てる
+            if generation != self.generation {
+                continue;
+            }
+            if let Ok(Some(_)) = &decoded {
                 self.frame_count += 1;
-                Ok(Some(VideoFrame::from_avio_frame(&frame)?))
             }
-            None => Ok(None),
+            return decoded;
         }
     }
 
+    /// 指定した再生位置へ非同期で移動する
+    ///
+    /// 先読みしたフレームは捨て、移動先から先読みし直す
+    pub async fn seek(&mut self, position: Duration) -> Result<()> {
+        self.generation += 1;
+        let (reply_tx, reply_rx) = bounded(1);
+        self.request_tx
+            .send(Request::Seek {
+                position,
+                generation: self.generation,
+                reply: reply_tx,
+            })
+            .map_err(|_| decode_thread_stopped())?;
+
+        tokio::task::spawn_blocking(move || reply_rx.recv())
+            .await
+            .map_err(|e| MediaError::Pipeline(format!("spawn_blocking panicked: {e}")))?
+            .map_err(|_| decode_thread_stopped())?
+    }
+
     pub fn frame_count(&self) -> u64 {
         self.frame_count
     }
 }
+
+fn decode_thread_stopped() -> MediaError {
+    MediaError::Pipeline("video decode thread stopped".to_string())
+}
+
+/// デコードスレッドへの要求
+pub(super) enum Request {
+    /// 再生位置を移動し、以降のフレームに `generation` を付ける
+    Seek {
+        position: Duration,
+        generation: u64,
+        reply: Sender<Result<()>>,
+    },
+}
+
+/// デコードスレッドの状態
+struct DecodeWorker {
+    decoder: RgbDecoder,
+    /// 以降に送るフレームに付ける移動の回数
+    generation: u64,
+    /// 終端まで読んだか
+    finished: bool,
+}
+
+impl DecodeWorker {
+    fn handle(&mut self, request: Request) {
+        let Request::Seek {
+            position,
+            generation,
+            reply,
+        } = request;
+        let result = self.decoder.seek(position, SeekMode::Exact);
+        self.generation = generation;
+        self.finished = false;
+        let _ = reply.send(result);
+    }
+
+    fn decode(&mut self) -> Result<Option<VideoFrame>> {
+        let decoded = self.decoder.decode_one();
+        self.finished = matches!(decoded, Ok(None));
+        decoded
+    }
+}
+
+/// デコードスレッドの本体
+///
+/// `frame_tx` が埋まっている間は止まって待つ（これが先読みの上限になる）。
+/// 待っている間も移動の要求には応じる。受け取る側が閉じたら終了する
+fn decode_ahead(
+    decoder: RgbDecoder,
+    frame_tx: Sender<DecodedFrame>,
+    request_rx: Receiver<Request>,
+) {
+    let mut worker = DecodeWorker {
+        decoder,
+        generation: 0,
+        finished: false,
+    };
+
+    loop {
+        while let Ok(request) = request_rx.try_recv() {
+            worker.handle(request);
+        }
+
+        // 終端まで読んだら、移動の要求が来るまで待つ
+        if worker.finished {
+            match request_rx.recv() {
+                Ok(request) => worker.handle(request),
+                Err(_) => return,
+            }
+            continue;
+        }
+
+        let decoded = worker.decode();
+        select! {
+            send(frame_tx, (worker.generation, decoded)) -> sent => {
+                if sent.is_err() {
+                    return;
+                }
+            }
+            recv(request_rx) -> request => match request {
+                // 送れなかったフレームは移動前のものなので捨ててよい
+                Ok(request) => worker.handle(request),
+                Err(_) => return,
+            },
+        }
+    }
+}
@@ -1,7 +1,9 @@
 use std::time::Duration;
 
 use image::{DynamicImage, ImageBuffer};
+use rayon::prelude::*;
 
+use crate::color::YuvFormat;
 use crate::errors::{MediaError, Result};
 
 /// フレームのピクセルフォーマット
@@ -15,6 +17,27 @@ pub enum FrameFormat {
     Gray8,
 }
 
+impl FrameFormat {
+    /// 1 ピクセルあたりのバイト数（プレーナー形式は None）
+    pub fn bytes_per_pixel(self) -> Option<usize> {
+        match self {
+            FrameFormat::RGB8 | FrameFormat::BGR8 => Some(3),
+            FrameFormat::RGBA8 | FrameFormat::BGRA8 => Some(4),
+            FrameFormat::Gray8 => Some(1),
+            FrameFormat::YUV420P => None,
+        }
+    }
+}
+
+/// フレーム上の矩形領域（ピクセル単位）
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Rect {
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+}
+
 /// ビデオフレームを表現する構造体
 #[derive(Debug, Clone)]
 pub struct VideoFrame {
@@ -58,6 +81,55 @@ impl VideoFrame {
         Ok(Self::new(data, width, height, format, timestamp, pts))
     }
 
+    /// avio の YUV420P のフレームを `yuv` の変換行列と範囲で RGB8 にして VideoFrame を作成
+    pub fn from_avio_yuv_frame(frame: &avio::VideoFrame, yuv: YuvFormat) -> Result<Self> {
+        let width = frame.width() as usize;
+        let height = frame.height() as usize;
+        let chroma_width = width.div_ceil(2);
+        let chroma_height = height.div_ceil(2);
+
+        // 各プレーンが (行数, 1 行の画素数) を収められるか確かめてから読む
+        let plane = |index: usize, rows: usize, columns: usize| {
+            let data = frame.plane(index)?;
+            let stride = frame.stride(index)?;
+            let needed = rows.saturating_sub(1) * stride + columns;
+            (stride >= columns && data.len() >= needed).then_some((data, stride))
+        };
+        let (Some((luma, luma_stride)), Some((cb, cb_stride)), Some((cr, cr_stride))) = (
+            plane(0, height, width),
+            plane(1, chroma_height, chroma_width),
+            plane(2, chroma_height, chroma_width),
+        ) else {
+            return Err(MediaError::Video(format!(
+                "Malformed {:?} frame of {}x{}",
+                frame.format(),
+                width,
+                height
+            )));
+        };
+
+        let mut data = vec![0u8; width * height * 3];
+        data.par_chunks_mut((width * 3).max(1))
+            .enumerate()
+            .for_each(|(row, out)| {
+                let luma = &luma[row * luma_stride..];
+                let cb = &cb[row / 2 * cb_stride..];
+                let cr = &cr[row / 2 * cr_stride..];
+                for (x, pixel) in out.chunks_exact_mut(3).enumerate() {
+                    pixel.copy_from_slice(&yuv.to_rgb(luma[x], cb[x / 2], cr[x / 2]));
+                }
+            });
+
+        Ok(Self::new(
+            data,
+            frame.width(),
+            frame.height(),
+            FrameFormat::RGB8,
+            frame.timestamp().as_duration(),
+            frame.timestamp().pts(),
+        ))
+    }
+
     /// image クレートの DynamicImage に変換
     pub fn to_dynamic_image(&self) -> Result<DynamicImage> {
         match self.format {
@@ -169,6 +241,41 @@ impl VideoFrame {
         }
     }
 
+    /// 指定した矩形を切り出す（パック形式のフォーマットのみ対応）
+    pub fn crop(&self, rect: Rect) -> Result<Self> {
+        let bytes_per_pixel = self
+            .format
+            .bytes_per_pixel()
+            .ok_or_else(|| MediaError::Video(format!("Cannot crop {:?} frames", self.format)))?;
+        if rect.width == 0
+            || rect.height == 0
+            || rect.x + rect.width > self.width
+            || rect.y + rect.height > self.height
+        {
+            return Err(MediaError::Video(format!(
+                "Crop {:?} is outside the {}x{} frame",
+                rect, self.width, self.height
+            )));
+        }
+
+        let stride = self.width as usize * bytes_per_pixel;
+        let row_len = rect.width as usize * bytes_per_pixel;
+        let mut data = Vec::with_capacity(row_len * rect.height as usize);
+        for y in rect.y..rect.y + rect.height {
+            let start = y as usize * stride + rect.x as usize * bytes_per_pixel;
+            data.extend_from_slice(&self.data[start..start + row_len]);
+        }
+
+        Ok(Self::new(
+            data,
+            rect.width,
+            rect.height,
+            self.format,
+            self.timestamp,
+            self.pts,
+        ))
+    }
+
     /// avio のピクセルフォーマットを変換
     fn convert_avio_format(format: avio::PixelFormat) -> Result<FrameFormat> {
         match format {
@@ -185,3 +292,64 @@ impl VideoFrame {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_avio_yuv_frame() {
+        use crate::color::{Matrix, Range};
+
+        // 左の列は限定範囲の白、右の列は黒
+        let mut frame = avio::VideoFrame::empty(4, 2, avio::PixelFormat::Yuv420p).unwrap();
+        let stride = frame.stride(0).unwrap();
+        let luma = frame.plane_mut(0).unwrap();
+        for y in 0..2 {
+            luma[y * stride..y * stride + 4].copy_from_slice(&[235, 235, 16, 16]);
+        }
+        frame.plane_mut(1).unwrap().fill(128);
+        frame.plane_mut(2).unwrap().fill(128);
+
+        let limited = YuvFormat {
+            matrix: Matrix::Bt709,
+            range: Range::Limited,
+        };
+        let converted = VideoFrame::from_avio_yuv_frame(&frame, limited).unwrap();
+        assert_eq!(converted.format, FrameFormat::RGB8);
+        assert_eq!(
+            &converted.data[..12],
+            &[255, 255, 255, 255, 255, 255, 0, 0, 0, 0, 0, 0]
+        );
+
+        // フルレンジとして読むと黒が浮く
+        let full = YuvFormat {
+            range: Range::Full,
+            ..limited
+        };
+        let converted = VideoFrame::from_avio_yuv_frame(&frame, full).unwrap();
+        assert_eq!(&converted.data[6..9], &[16, 16, 16]);
+    }
+
+    #[test]
+    fn test_crop_rgb_frame() {
+        // 4x2 の各ピクセルに (x, y, 0) を入れる
+        let data = (0..2u8)
+            .flat_map(|y| (0..4u8).flat_map(move |x| [x, y, 0]))
+            .collect();
+        let frame = VideoFrame::new(data, 4, 2, FrameFormat::RGB8, Duration::ZERO, 0);
+
+        let rect = Rect {
+            x: 1,
+            y: 1,
+            width: 2,
+            height: 1,
+        };
+        let cropped = frame.crop(rect).unwrap();
+        assert_eq!((cropped.width, cropped.height), (2, 1));
+        assert_eq!(cropped.data, vec![1, 1, 0, 2, 1, 0]);
+
+        let outside = Rect { x: 3, ..rect };
+        assert!(frame.crop(outside).is_err());
+    }
+}
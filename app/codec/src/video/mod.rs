@@ -1,5 +1,11 @@
 pub mod decoder;
 pub mod frame;
+pub mod roi;
+pub mod worker;
 
-pub use decoder::{AsyncVideoDecoder, VideoDecoder};
-pub use frame::VideoFrame;
+pub use decoder::{
+    AsyncVideoDecoder, HdrSettings, VideoDecoder, YuvOverride, override_yuv_format,
+    set_hdr_settings,
+};
+pub use frame::{Rect, VideoFrame};
+pub use roi::{RoiCropper, SaliencyDetector, SubjectDetector};
@@ -0,0 +1,280 @@
+//! 被写体を追従するアスペクト比クロップ
+//!
+//! 縦長のスマートフォン動画を横長の画面に合わせて切り出すと、中央固定では
+//! 顔や被写体が切れてしまうことがある。被写体を検出し、その位置を中心に
+//! 切り出し範囲を滑らかに動かす
+//!
+//! 検出には OpenCV（Haar カスケードや DNN の顔検出）を使わず、肌色とエッジから
+//! 推定する [`SaliencyDetector`] を使う。`opencv` クレートはビルド時に OpenCV の
+//! 開発ファイルと libclang を必要とし、実行時にもモデルファイルを配布する必要が
+//! あるため、FFmpeg 以外に依存を増やさないことを優先した。より正確な検出器は
+//! [`SubjectDetector`] を実装すれば差し替えられる
+
+use crate::color::luminance;
+use crate::video::frame::{FrameFormat, Rect, VideoFrame};
+
+/// 被写体の検出器
+pub trait SubjectDetector: Send {
+    /// 主な被写体の領域を検出（見つからなければ None）
+    fn detect(&mut self, frame: &VideoFrame) -> Option<Rect>;
+}
+
+/// 集計するブロックの大きさ（ピクセル）
+const BLOCK_SIZE: u32 = 16;
+
+/// 肌色の割合にかける重み（エッジより顔を優先する）
+const SKIN_WEIGHT: f32 = 3.0;
+
+/// 最大スコアに対してこの割合以上のブロックを被写体とみなす
+const BLOCK_THRESHOLD: f32 = 0.6;
+
+/// これ未満のスコアしかない画面では被写体なしとする
+const MIN_SCORE: f32 = 0.05;
+
+/// 肌色とエッジの強さから被写体を推定する検出器
+///
+/// 顔検出モデルを使わない軽量な推定で、肌色の多い領域（人物の顔）を優先し、
+/// 人物がいなければ細部の多い領域を被写体とみなす
+#[derive(Debug, Default)]
+pub struct SaliencyDetector;
+
+impl SubjectDetector for SaliencyDetector {
+    fn detect(&mut self, frame: &VideoFrame) -> Option<Rect> {
+        let bytes_per_pixel = match frame.format {
+            FrameFormat::RGB8 | FrameFormat::BGR8 => 3,
+            FrameFormat::RGBA8 | FrameFormat::BGRA8 => 4,
+            _ => return None,
+        };
+        let width = frame.width;
+        let height = frame.height;
+        if width < BLOCK_SIZE || height < BLOCK_SIZE {
+            return None;
+        }
+
+        let bgr = matches!(frame.format, FrameFormat::BGR8 | FrameFormat::BGRA8);
+        let rgb = |x: u32, y: u32| -> [u8; 3] {
+            let i = (y as usize * width as usize + x as usize) * bytes_per_pixel;
+            let px = &frame.data[i..i + 3];
+            if bgr {
+                [px[2], px[1], px[0]]
+            } else {
+                [px[0], px[1], px[2]]
+            }
+        };
+
+        let blocks_x = width / BLOCK_SIZE;
+        let blocks_y = height / BLOCK_SIZE;
+        let mut scores = Vec::with_capacity((blocks_x * blocks_y) as usize);
+
+        for by in 0..blocks_y {
+            for bx in 0..blocks_x {
+                let mut skin = 0u32;
+                let mut edge = 0u32;
+                let mut samples = 0u32;
+                for y in (by * BLOCK_SIZE..(by + 1) * BLOCK_SIZE - 2).step_by(2) {
+                    for x in (bx * BLOCK_SIZE..(bx + 1) * BLOCK_SIZE - 2).step_by(2) {
+                        let pixel = rgb(x, y);
+                        if is_skin(pixel) {
+                            skin += 1;
+                        }
+                        let luma = luminance(pixel);
+                        let dx = luma.abs_diff(luminance(rgb(x + 2, y)));
+                        let dy = luma.abs_diff(luminance(rgb(x, y + 2)));
+                        edge += dx.max(dy) as u32;
+                        samples += 1;
+                    }
+                }
+                let skin_ratio = skin as f32 / samples as f32;
+                let edge_energy = edge as f32 / samples as f32 / 255.0;
+                scores.push(skin_ratio * SKIN_WEIGHT + edge_energy);
+            }
+        }
+
+        let max_score = scores.iter().copied().fold(0.0f32, f32::max);
+        if max_score < MIN_SCORE {
+            return None;
+        }
+
+        // しきい値を超えたブロックの重み付き重心と広がりから被写体の矩形を求める
+        let (mut total, mut sum_x, mut sum_y, mut sum_xx, mut sum_yy) = (0.0, 0.0, 0.0, 0.0, 0.0);
+        for (index, &score) in scores.iter().enumerate() {
+            if score < max_score * BLOCK_THRESHOLD {
+                continue;
+            }
+            let cx = ((index as u32 % blocks_x) * BLOCK_SIZE + BLOCK_SIZE / 2) as f32;
+            let cy = ((index as u32 / blocks_x) * BLOCK_SIZE + BLOCK_SIZE / 2) as f32;
+            total += score;
+            sum_x += cx * score;
+            sum_y += cy * score;
+            sum_xx += cx * cx * score;
+            sum_yy += cy * cy * score;
+        }
+
+        let mean_x = sum_x / total;
+        let mean_y = sum_y / total;
+        let spread_x = (sum_xx / total - mean_x * mean_x).max(0.0).sqrt() + BLOCK_SIZE as f32;
+        let spread_y = (sum_yy / total - mean_y * mean_y).max(0.0).sqrt() + BLOCK_SIZE as f32;
+
+        let x0 = (mean_x - spread_x).max(0.0) as u32;
+        let y0 = (mean_y - spread_y).max(0.0) as u32;
+        let x1 = ((mean_x + spread_x) as u32).min(width);
+        let y1 = ((mean_y + spread_y) as u32).min(height);
+        Some(Rect {
+            x: x0,
+            y: y0,
+            width: x1.saturating_sub(x0).max(1),
+            height: y1.saturating_sub(y0).max(1),
+        })
+    }
+}
+
+/// RGB の肌色判定（Kovac らの規則）
+fn is_skin([r, g, b]: [u8; 3]) -> bool {
+    let max = r.max(g).max(b);
+    let min = r.min(g).min(b);
+    r > 95 && g > 40 && b > 20 && max - min > 15 && r.abs_diff(g) > 15 && r > g && r > b
+}
+
+/// 被写体を検出する間隔（フレーム数）
+const DETECT_INTERVAL: u32 = 6;
+
+/// 1 フレームあたりに切り出し位置を目標へ近づける割合
+const SMOOTHING: f32 = 0.15;
+
+/// 被写体を中心に、出力先のアスペクト比で切り出す範囲を決める
+pub struct RoiCropper {
+    detector: Box<dyn SubjectDetector>,
+    /// 切り出す範囲のアスペクト比（幅 / 高さ、ピクセル単位）
+    aspect: f32,
+    center: Option<(f32, f32)>,
+    target: Option<(f32, f32)>,
+    frames_until_detect: u32,
+}
+
+impl RoiCropper {
+    /// 既定の検出器（`SaliencyDetector`）で作成
+    pub fn new(aspect: f32) -> Self {
+        Self::with_detector(aspect, Box::new(SaliencyDetector))
+    }
+
+    /// 検出器を指定して作成
+    pub fn with_detector(aspect: f32, detector: Box<dyn SubjectDetector>) -> Self {
+        Self {
+            detector,
+            aspect,
+            center: None,
+            target: None,
+            frames_until_detect: 0,
+        }
+    }
+
+    /// 切り出す範囲を返す（アスペクト比が同じで切り出しが不要な場合は None）
+    pub fn crop_rect(&mut self, frame: &VideoFrame) -> Option<Rect> {
+        let (width, height) = (frame.width as f32, frame.height as f32);
+        if width == 0.0 || height == 0.0 || self.aspect <= 0.0 {
+            return None;
+        }
+
+        let (crop_width, crop_height) = if width / height > self.aspect {
+            (height * self.aspect, height)
+        } else {
+            (width, width / self.aspect)
+        };
+        let crop_width = (crop_width.round() as u32).clamp(1, frame.width);
+        let crop_height = (crop_height.round() as u32).clamp(1, frame.height);
+        if crop_width == frame.width && crop_height == frame.height {
+            return None;
+        }
+
+        // 検出は数フレームおき。見失ったら最後の目標を保つ
+        if self.frames_until_detect == 0 {
+            if let Some(subject) = self.detector.detect(frame) {
+                self.target = Some((
+                    subject.x as f32 + subject.width as f32 / 2.0,
+                    subject.y as f32 + subject.height as f32 / 2.0,
+                ));
+            }
+            self.frames_until_detect = DETECT_INTERVAL;
+        }
+        self.frames_until_detect -= 1;
+
+        let target = self.target.unwrap_or((width / 2.0, height / 2.0));
+        let center = match self.center {
+            Some((x, y)) => (
+                x + (target.0 - x) * SMOOTHING,
+                y + (target.1 - y) * SMOOTHING,
+            ),
+            None => target,
+        };
+        self.center = Some(center);
+
+        let max_x = (frame.width - crop_width) as f32;
+        let max_y = (frame.height - crop_height) as f32;
+        Some(Rect {
+            x: (center.0 - crop_width as f32 / 2.0).clamp(0.0, max_x) as u32,
+            y: (center.1 - crop_height as f32 / 2.0).clamp(0.0, max_y) as u32,
+            width: crop_width,
+            height: crop_height,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    /// 黒背景の縦長フレームに肌色の四角（顔）を置く
+    fn portrait_with_face(face_y: u32) -> VideoFrame {
+        let (width, height) = (180u32, 320u32);
+        let mut data = vec![0u8; (width * height * 3) as usize];
+        for y in face_y..face_y + 48 {
+            for x in 66..114 {
+                let i = ((y * width + x) * 3) as usize;
+                data[i..i + 3].copy_from_slice(&[224, 172, 140]);
+            }
+        }
+        VideoFrame::new(data, width, height, FrameFormat::RGB8, Duration::ZERO, 0)
+    }
+
+    #[test]
+    fn test_saliency_finds_skin_region() {
+        let frame = portrait_with_face(40);
+        let subject = SaliencyDetector.detect(&frame).unwrap();
+        let center_y = subject.y + subject.height / 2;
+        assert!((40..88).contains(&center_y), "{:?}", subject);
+    }
+
+    #[test]
+    fn test_crop_follows_subject_near_top() {
+        let frame = portrait_with_face(16);
+        let mut cropper = RoiCropper::new(16.0 / 9.0);
+
+        let rect = cropper.crop_rect(&frame).unwrap();
+        // 幅いっぱい、高さは 16:9 に合わせる
+        assert_eq!((rect.width, rect.height), (180, 101));
+        // 中央固定（y = 109）ではなく、上端の顔を含む位置になる
+        assert!(rect.y <= 16, "{:?}", rect);
+        assert!(frame.crop(rect).is_ok());
+    }
+
+    #[test]
+    fn test_crop_centers_without_subject() {
+        let frame = VideoFrame::new(
+            vec![0; 180 * 320 * 3],
+            180,
+            320,
+            FrameFormat::RGB8,
+            Duration::ZERO,
+            0,
+        );
+        let rect = RoiCropper::new(16.0 / 9.0).crop_rect(&frame).unwrap();
+        assert_eq!(rect.y, (320 - 101) / 2);
+    }
+
+    #[test]
+    fn test_no_crop_when_aspect_matches() {
+        let frame = portrait_with_face(0);
+        assert!(RoiCropper::new(180.0 / 320.0).crop_rect(&frame).is_none());
+    }
+}
@@ -0,0 +1,962 @@
+//! 映像のデコードを子プロセスで行うモード（Unix のみ）
+//!
+//! 壊れたファイルやストリームで FFmpeg がクラッシュしても UI を巻き込まないよう、
+//! `AsyncVideoDecoder` のデコードスレッドの代わりに子プロセスでデコードする。
+//! ピクセルは共有メモリのリング（一時ファイルをメモリマップした固定長のスロット）で受け渡し、
+//! パイプには 1 行のメッセージだけを流す。子プロセスが落ちたら、最後に受け取った
+//! フレームの位置から開き直して再生を続ける
+//!
+//! 子プロセスとして起動するのは [`isolate_decoding`] に渡したコマンドで、
+//! 入力とリングのパスを引数に付けて呼ぶ。コマンドの側では [`serve`] を実行する
+
+use std::ffi::OsString;
+use std::path::PathBuf;
+use std::sync::OnceLock;
+
+use crate::errors::{MediaError, Result};
+
+#[cfg(unix)]
+pub(super) use unix::open;
+#[cfg(unix)]
+pub use unix::serve;
+
+/// 子プロセスとして起動するコマンド（入力とリングのパスが後ろに付く）
+#[derive(Debug, Clone)]
+pub struct WorkerCommand {
+    program: PathBuf,
+    args: Vec<OsString>,
+}
+
+impl WorkerCommand {
+    pub fn new(
+        program: impl Into<PathBuf>,
+        args: impl IntoIterator<Item = impl Into<OsString>>,
+    ) -> Self {
+        Self {
+            program: program.into(),
+            args: args.into_iter().map(Into::into).collect(),
+        }
+    }
+}
+
+static WORKER_COMMAND: OnceLock<WorkerCommand> = OnceLock::new();
+
+/// 以降に開く `AsyncVideoDecoder` のデコードを、`command` で起動する子プロセスで行う
+pub fn isolate_decoding(command: WorkerCommand) -> Result<()> {
+    if cfg!(not(unix)) {
+        return Err(MediaError::Pipeline(
+            "Decoding in a worker process is only supported on Unix".to_string(),
+        ));
+    }
+    WORKER_COMMAND
+        .set(command)
+        .map_err(|_| MediaError::Pipeline("Decode worker is already configured".to_string()))
+}
+
+/// 子プロセスでデコードする設定になっていれば、そのコマンド
+pub(super) fn command() -> Option<&'static WorkerCommand> {
+    WORKER_COMMAND.get()
+}
+
+#[cfg(unix)]
+mod unix {
+    use std::fs::{self, File, OpenOptions};
+    use std::io::{self, BufRead, BufReader, BufWriter, Read, Write};
+    use std::os::fd::AsRawFd;
+    use std::path::{Path, PathBuf};
+    use std::process::{Child, ChildStdin, Command, Stdio};
+    use std::sync::atomic::{AtomicU64, Ordering};
+    use std::sync::{Arc, Mutex};
+    use std::thread;
+    use std::time::Duration;
+    use std::{fmt, ptr};
+
+    use avio::SeekMode;
+    use crossbeam_channel::{Receiver, Sender, bounded, select, unbounded};
+
+    use super::WorkerCommand;
+    use crate::errors::{MediaError, Result};
+    use crate::video::decoder::{DecodedFrame, Request, RgbDecoder};
+    use crate::video::frame::{FrameFormat, VideoFrame};
+
+    /// リングのスロット数（受け取った側はすぐに複製して返すので、少なくてよい）
+    const RING_SLOTS: usize = 3;
+
+    /// 受け取ったフレームがないまま続けて再起動する上限
+    const MAX_RESTARTS: u32 = 3;
+
+    /// 子プロセスから親へのメッセージ
+    #[derive(Debug, Clone, PartialEq)]
+    enum Message {
+        /// 開けた（リングの 1 スロットの大きさ）
+        Ready { slot_size: usize },
+        /// 開けなかった
+        Failed { message: String },
+        /// フレームをスロットに書いた（`slot` が None ならメッセージの直後に `len` バイト続く）
+        Frame {
+            generation: u64,
+            slot: Option<usize>,
+            header: FrameHeader,
+        },
+        /// 終端まで読んだ
+        End { generation: u64 },
+        /// フレームをデコードできなかった
+        Error {
+            generation: u64,
+            recoverable: bool,
+            message: String,
+        },
+        /// 移動した（失敗したときはその理由）
+        Seeked {
+            generation: u64,
+            error: Option<String>,
+        },
+    }
+
+    /// 親から子プロセスへの指示
+    #[derive(Debug, Clone, PartialEq)]
+    enum Instruction {
+        /// スロットを読み終えた
+        Free { slot: usize },
+        /// 再生位置を移動し、以降のフレームに `generation` を付ける
+        Seek { generation: u64, position: Duration },
+    }
+
+    /// ピクセル以外のフレームの情報
+    #[derive(Debug, Clone, Copy, PartialEq)]
+    struct FrameHeader {
+        len: usize,
+        width: u32,
+        height: u32,
+        format: FrameFormat,
+        timestamp: Duration,
+        pts: i64,
+    }
+
+    impl FrameHeader {
+        fn of(frame: &VideoFrame) -> Self {
+            Self {
+                len: frame.data.len(),
+                width: frame.width,
+                height: frame.height,
+                format: frame.format,
+                timestamp: frame.timestamp,
+                pts: frame.pts,
+            }
+        }
+
+        fn into_frame(self, data: Vec<u8>) -> VideoFrame {
+            VideoFrame::new(
+                data,
+                self.width,
+                self.height,
+                self.format,
+                self.timestamp,
+                self.pts,
+            )
+        }
+    }
+
+    fn format_name(format: FrameFormat) -> &'static str {
+        match format {
+            FrameFormat::RGB8 => "rgb8",
+            FrameFormat::RGBA8 => "rgba8",
+            FrameFormat::BGR8 => "bgr8",
+            FrameFormat::BGRA8 => "bgra8",
+            FrameFormat::YUV420P => "yuv420p",
+            FrameFormat::Gray8 => "gray8",
+        }
+    }
+
+    fn parse_format(name: &str) -> Option<FrameFormat> {
+        Some(match name {
+            "rgb8" => FrameFormat::RGB8,
+            "rgba8" => FrameFormat::RGBA8,
+            "bgr8" => FrameFormat::BGR8,
+            "bgra8" => FrameFormat::BGRA8,
+            "yuv420p" => FrameFormat::YUV420P,
+            "gray8" => FrameFormat::Gray8,
+            _ => return None,
+        })
+    }
+
+    /// メッセージに載せる文字列（1 行に収める）
+    fn one_line(message: &str) -> String {
+        message.replace(['\r', '\n'], " ")
+    }
+
+    impl fmt::Display for Message {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            match self {
+                Message::Ready { slot_size } => write!(f, "ready {}", slot_size),
+                Message::Failed { message } => write!(f, "failed {}", one_line(message)),
+                Message::Frame {
+                    generation,
+                    slot,
+                    header,
+                } => {
+                    let slot = slot.map_or("-".to_string(), |slot| slot.to_string());
+                    write!(
+                        f,
+                        "frame {} {} {} {} {} {} {} {}",
+                        generation,
+                        slot,
+                        header.len,
+                        header.width,
+                        header.height,
+                        format_name(header.format),
+                        header.timestamp.as_nanos(),
+                        header.pts
+                    )
+                }
+                Message::End { generation } => write!(f, "end {}", generation),
+                Message::Error {
+                    generation,
+                    recoverable,
+                    message,
+                } => write!(
+                    f,
+                    "error {} {} {}",
+                    generation,
+                    u8::from(*recoverable),
+                    one_line(message)
+                ),
+                Message::Seeked {
+                    generation,
+                    error: None,
+                } => write!(f, "seeked {}", generation),
+                Message::Seeked {
+                    generation,
+                    error: Some(message),
+                } => write!(f, "seek-failed {} {}", generation, one_line(message)),
+            }
+        }
+    }
+
+    impl Message {
+        fn parse(line: &str) -> Option<Self> {
+            let (kind, rest) = line.split_once(' ').unwrap_or((line, ""));
+            let mut fields = rest.splitn(3, ' ');
+            let mut next = || fields.next();
+            Some(match kind {
+                "ready" => Message::Ready {
+                    slot_size: rest.parse().ok()?,
+                },
+                "failed" => Message::Failed {
+                    message: rest.to_string(),
+                },
+                "frame" => {
+                    let fields: Vec<&str> = rest.split(' ').collect();
+                    let [generation, slot, len, width, height, format, timestamp, pts] =
+                        fields.as_slice()
+                    else {
+                        return None;
+                    };
+                    Message::Frame {
+                        generation: generation.parse().ok()?,
+                        slot: match *slot {
+                            "-" => None,
+                            slot => Some(slot.parse().ok()?),
+                        },
+                        header: FrameHeader {
+                            len: len.parse().ok()?,
+                            width: width.parse().ok()?,
+                            height: height.parse().ok()?,
+                            format: parse_format(format)?,
+                            timestamp: Duration::from_nanos(timestamp.parse().ok()?),
+                            pts: pts.parse().ok()?,
+                        },
+                    }
+                }
+                "end" => Message::End {
+                    generation: rest.parse().ok()?,
+                },
+                "error" => Message::Error {
+                    generation: next()?.parse().ok()?,
+                    recoverable: next()? == "1",
+                    message: next().unwrap_or_default().to_string(),
+                },
+                "seeked" => Message::Seeked {
+                    generation: rest.parse().ok()?,
+                    error: None,
+                },
+                "seek-failed" => {
+                    let (generation, message) = rest.split_once(' ').unwrap_or((rest, ""));
+                    Message::Seeked {
+                        generation: generation.parse().ok()?,
+                        error: Some(message.to_string()),
+                    }
+                }
+                _ => return None,
+            })
+        }
+    }
+
+    impl fmt::Display for Instruction {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            match self {
+                Instruction::Free { slot } => write!(f, "free {}", slot),
+                Instruction::Seek {
+                    generation,
+                    position,
+                } => write!(f, "seek {} {}", generation, position.as_nanos()),
+            }
+        }
+    }
+
+    impl Instruction {
+        fn parse(line: &str) -> Option<Self> {
+            let fields: Vec<&str> = line.split(' ').collect();
+            Some(match fields.as_slice() {
+                ["free", slot] => Instruction::Free {
+                    slot: slot.parse().ok()?,
+                },
+                ["seek", generation, position] => Instruction::Seek {
+                    generation: generation.parse().ok()?,
+                    position: Duration::from_nanos(position.parse().ok()?),
+                },
+                _ => return None,
+            })
+        }
+    }
+
+    /// 2 つのプロセスでメモリマップしたフレームのリング
+    struct Ring {
+        address: *mut u8,
+        slot_size: usize,
+    }
+
+    // SAFETY: 各スロットを使うのは、指示とメッセージで受け渡した側だけ
+    unsafe impl Send for Ring {}
+    unsafe impl Sync for Ring {}
+
+    impl Ring {
+        fn map(file: &File, slot_size: usize) -> io::Result<Self> {
+            // SAFETY: 大きさを設定済みのファイル全体を共有でマップする
+            let address = unsafe {
+                libc::mmap(
+                    ptr::null_mut(),
+                    slot_size * RING_SLOTS,
+                    libc::PROT_READ | libc::PROT_WRITE,
+                    libc::MAP_SHARED,
+                    file.as_raw_fd(),
+                    0,
+                )
+            };
+            if address == libc::MAP_FAILED {
+                return Err(io::Error::last_os_error());
+            }
+            Ok(Self {
+                address: address as *mut u8,
+                slot_size,
+            })
+        }
+
+        fn write(&self, slot: usize, data: &[u8]) {
+            assert!(slot < RING_SLOTS && data.len() <= self.slot_size);
+            // SAFETY: 範囲内で、このスロットは親が読み終えて返したもの
+            unsafe {
+                ptr::copy_nonoverlapping(
+                    data.as_ptr(),
+                    self.address.add(slot * self.slot_size),
+                    data.len(),
+                );
+            }
+        }
+
+        fn read(&self, slot: usize, len: usize) -> Option<Vec<u8>> {
+            if slot >= RING_SLOTS || len > self.slot_size {
+                return None;
+            }
+            // SAFETY: 範囲内で、このスロットは子プロセスが書き終えて渡したもの
+            let data =
+                unsafe { std::slice::from_raw_parts(self.address.add(slot * self.slot_size), len) };
+            Some(data.to_vec())
+        }
+    }
+
+    impl Drop for Ring {
+        fn drop(&mut self) {
+            // SAFETY: map() でマップした範囲
+            unsafe {
+                libc::munmap(
+                    self.address as *mut libc::c_void,
+                    self.slot_size * RING_SLOTS,
+                );
+            }
+        }
+    }
+
+    /// 子プロセスの本体。`path` を開き、フレームを `ring_path` のリングへ書く
+    ///
+    /// 標準入力から指示を読み、標準出力へメッセージを書く。標準入力が閉じたら終了する
+    pub fn serve(path: &str, ring_path: &Path) -> Result<()> {
+        let mut out = BufWriter::new(io::stdout().lock());
+        let mut send = |message: &Message, data: Option<&[u8]>| -> io::Result<()> {
+            writeln!(out, "{}", message)?;
+            if let Some(data) = data {
+                out.write_all(data)?;
+            }
+            out.flush()
+        };
+
+        let mut decoder = match RgbDecoder::open(path) {
+            Ok(decoder) => decoder,
+            Err(e) => {
+                send(
+                    &Message::Failed {
+                        message: e.to_string(),
+                    },
+                    None,
+                )?;
+                return Err(MediaError::Decode(e));
+            }
+        };
+        let slot_size = (decoder.width() as usize * decoder.height() as usize * 3).max(1);
+        let file = OpenOptions::new().read(true).write(true).open(ring_path)?;
+        file.set_len((slot_size * RING_SLOTS) as u64)?;
+        let ring = Ring::map(&file, slot_size)?;
+        send(&Message::Ready { slot_size }, None)?;
+
+        let (instruction_tx, instruction_rx) = unbounded();
+        thread::Builder::new()
+            .name("decode-worker-input".to_string())
+            .spawn(move || {
+                for line in io::stdin().lock().lines() {
+                    let Ok(line) = line else { break };
+                    if let Some(instruction) = Instruction::parse(&line)
+                        && instruction_tx.send(instruction).is_err()
+                    {
+                        break;
+                    }
+                }
+            })?;
+
+        let mut free: Vec<usize> = (0..RING_SLOTS).rev().collect();
+        let mut generation = 0;
+        let mut finished = false;
+        loop {
+            // 書けるスロットがないか終端まで読んだら、指示が来るまで待つ
+            let mut instructions: Vec<Instruction> = instruction_rx.try_iter().collect();
+            if instructions.is_empty() && (free.is_empty() || finished) {
+                match instruction_rx.recv() {
+                    Ok(instruction) => instructions.push(instruction),
+                    Err(_) => return Ok(()),
+                }
+            }
+            for instruction in instructions {
+                match instruction {
+                    Instruction::Free { slot } => free.push(slot),
+                    Instruction::Seek {
+                        generation: next,
+                        position,
+                    } => {
+                        let error = decoder
+                            .seek(position, SeekMode::Exact)
+                            .err()
+                            .map(|e| e.to_string());
+                        generation = next;
+                        finished = false;
+                        send(&Message::Seeked { generation, error }, None)?;
+                    }
+                }
+            }
+            if free.is_empty() || finished {
+                continue;
+            }
+
+            match decoder.decode_one() {
+                Ok(Some(frame)) => {
+                    let header = FrameHeader::of(&frame);
+                    // 途中で解像度が大きくなったフレームはパイプで送る
+                    if frame.data.len() <= slot_size {
+                        let slot = free.pop();
+                        if let Some(slot) = slot {
+                            ring.write(slot, &frame.data);
+                        }
+                        send(
+                            &Message::Frame {
+                                generation,
+                                slot,
+                                header,
+                            },
+                            None,
+                        )?;
+                    } else {
+                        let message = Message::Frame {
+                            generation,
+                            slot: None,
+                            header,
+                        };
+                        send(&message, Some(&frame.data))?;
+                    }
+                }
+                Ok(None) => {
+                    finished = true;
+                    send(&Message::End { generation }, None)?;
+                }
+                Err(e) => send(
+                    &Message::Error {
+                        generation,
+                        recoverable: e.is_recoverable(),
+                        message: e.to_string(),
+                    },
+                    None,
+                )?,
+            }
+        }
+    }
+
+    /// 子プロセスから届いたもの
+    enum Event {
+        Frame(DecodedFrame),
+        Seeked(u64, Result<()>),
+        /// 子プロセスが終了した（またはメッセージを読めなくなった）
+        Exited,
+    }
+
+    /// 起動した子プロセス
+    struct Worker {
+        child: Child,
+        stdin: Arc<Mutex<ChildStdin>>,
+        events: Receiver<Event>,
+    }
+
+    /// リングのファイルの名前に付ける連番
+    static RING_COUNT: AtomicU64 = AtomicU64::new(0);
+
+    /// リングのファイルを作る場所（メモリ上のファイルシステムがあればそこ）
+    fn ring_path() -> PathBuf {
+        let shm = Path::new("/dev/shm");
+        let dir = if shm.is_dir() {
+            shm.to_path_buf()
+        } else {
+            std::env::temp_dir()
+        };
+        dir.join(format!(
+            "ascii-term-decode-{}-{}",
+            std::process::id(),
+            RING_COUNT.fetch_add(1, Ordering::Relaxed)
+        ))
+    }
+
+    fn worker_stopped() -> MediaError {
+        MediaError::Pipeline("video decode worker stopped".to_string())
+    }
+
+    impl Worker {
+        /// 子プロセスを起動し、入力を開き終えるまで待つ
+        fn spawn(path: &str, command: &WorkerCommand) -> Result<Self> {
+            let ring_path = ring_path();
+            File::create_new(&ring_path)?;
+            let spawned = Self::start(path, command, &ring_path);
+            // 両方のプロセスがマップしたら、ファイルの名前はもういらない
+            let _ = fs::remove_file(&ring_path);
+            spawned
+        }
+
+        fn start(path: &str, command: &WorkerCommand, ring_path: &Path) -> Result<Self> {
+            let mut child = Command::new(&command.program)
+                .args(&command.args)
+                .arg(path)
+                .arg(ring_path)
+                .stdin(Stdio::piped())
+                .stdout(Stdio::piped())
+                // FFmpeg のログが画面に混ざらないようにする
+                .stderr(Stdio::null())
+                .spawn()?;
+            let stdin = child.stdin.take().ok_or_else(worker_stopped)?;
+            let mut stdout = BufReader::new(child.stdout.take().ok_or_else(worker_stopped)?);
+
+            let mut line = String::new();
+            stdout.read_line(&mut line)?;
+            let ring = match Message::parse(line.trim_end()) {
+                Some(Message::Ready { slot_size }) => {
+                    let file = OpenOptions::new().read(true).write(true).open(ring_path)?;
+                    Ring::map(&file, slot_size)?
+                }
+                Some(Message::Failed { message }) => {
+                    let _ = child.wait();
+                    return Err(MediaError::Video(message));
+                }
+                _ => {
+                    let _ = child.kill();
+                    let _ = child.wait();
+                    return Err(MediaError::Pipeline(format!(
+                        "video decode worker exited before opening {}",
+                        path
+                    )));
+                }
+            };
+
+            let stdin = Arc::new(Mutex::new(stdin));
+            let (event_tx, events) = bounded(RING_SLOTS);
+            let reader_stdin = Arc::clone(&stdin);
+            thread::Builder::new()
+                .name("decode-worker-output".to_string())
+                .spawn(move || read_messages(stdout, ring, reader_stdin, event_tx))?;
+
+            Ok(Self {
+                child,
+                stdin,
+                events,
+            })
+        }
+
+        fn send(&self, instruction: &Instruction) -> io::Result<()> {
+            let mut stdin = self.stdin.lock().unwrap_or_else(|e| e.into_inner());
+            writeln!(stdin, "{}", instruction)?;
+            stdin.flush()
+        }
+    }
+
+    impl Drop for Worker {
+        fn drop(&mut self) {
+            let _ = self.child.kill();
+            let _ = self.child.wait();
+        }
+    }
+
+    /// 子プロセスのメッセージを読み、フレームをリングから複製して `event_tx` へ送る
+    fn read_messages(
+        mut stdout: BufReader<std::process::ChildStdout>,
+        ring: Ring,
+        stdin: Arc<Mutex<ChildStdin>>,
+        event_tx: Sender<Event>,
+    ) {
+        let mut line = String::new();
+        loop {
+            line.clear();
+            let message = match stdout.read_line(&mut line) {
+                Ok(0) | Err(_) => None,
+                Ok(_) => Message::parse(line.trim_end()),
+            };
+            let event = match message {
+                Some(Message::Frame {
+                    generation,
+                    slot: Some(slot),
+                    header,
+                }) => {
+                    let Some(data) = ring.read(slot, header.len) else {
+                        break;
+                    };
+                    let mut stdin = stdin.lock().unwrap_or_else(|e| e.into_inner());
+                    if writeln!(stdin, "{}", Instruction::Free { slot })
+                        .and_then(|()| stdin.flush())
+                        .is_err()
+                    {
+                        break;
+                    }
+                    Event::Frame((generation, Ok(Some(header.into_frame(data)))))
+                }
+                Some(Message::Frame {
+                    generation,
+                    slot: None,
+                    header,
+                }) => {
+                    let mut data = vec![0; header.len];
+                    if stdout.read_exact(&mut data).is_err() {
+                        break;
+                    }
+                    Event::Frame((generation, Ok(Some(header.into_frame(data)))))
+                }
+                Some(Message::End { generation }) => Event::Frame((generation, Ok(None))),
+                Some(Message::Error {
+                    generation,
+                    recoverable,
+                    message,
+                }) => Event::Frame((
+                    generation,
+                    Err(MediaError::Worker {
+                        message,
+                        recoverable,
+                    }),
+                )),
+                Some(Message::Seeked { generation, error }) => Event::Seeked(
+                    generation,
+                    match error {
+                        None => Ok(()),
+                        Some(message) => Err(MediaError::Video(message)),
+                    },
+                ),
+                Some(Message::Ready { .. } | Message::Failed { .. }) | None => break,
+            };
+            if event_tx.send(event).is_err() {
+                return;
+            }
+        }
+        let _ = event_tx.send(Event::Exited);
+    }
+
+    /// 子プロセスでデコードする `AsyncVideoDecoder` のチャンネルをつなぐ
+    pub(in crate::video) fn open(
+        path: &str,
+        command: &'static WorkerCommand,
+        frame_tx: Sender<DecodedFrame>,
+        request_rx: Receiver<Request>,
+    ) -> Result<()> {
+        let worker = Worker::spawn(path, command)?;
+        let mut bridge = Bridge {
+            path: path.to_string(),
+            command,
+            worker,
+            frame_tx,
+            generation: 0,
+            pending_seek: None,
+            position: None,
+            resume_after: None,
+            restarts: 0,
+        };
+        thread::Builder::new()
+            .name("video-decode".to_string())
+            .spawn(move || bridge.run(request_rx))
+            .map_err(|e| MediaError::Pipeline(format!("failed to spawn decode thread: {e}")))?;
+        Ok(())
+    }
+
+    /// 子プロセスと `AsyncVideoDecoder` の間を取り持つスレッドの状態
+    struct Bridge {
+        path: String,
+        command: &'static WorkerCommand,
+        worker: Worker,
+        frame_tx: Sender<DecodedFrame>,
+        generation: u64,
+        /// 返事を待っている移動の要求
+        pending_seek: Option<Sender<Result<()>>>,
+        /// 開き直すときに移動する位置（最後に渡したフレームか、移動先）
+        position: Option<Duration>,
+        /// 開き直した後、このタイムスタンプまでのフレームは渡し済みなので捨てる
+        resume_after: Option<Duration>,
+        /// フレームを受け取らないまま再起動した回数
+        restarts: u32,
+    }
+
+    impl Bridge {
+        /// 受け取る側が閉じるか、子プロセスを開き直せなくなるまで続ける
+        fn run(&mut self, request_rx: Receiver<Request>) {
+            loop {
+                let events = self.worker.events.clone();
+                select! {
+                    recv(request_rx) -> request => match request {
+                        Ok(request) => self.handle(request),
+                        Err(_) => return,
+                    },
+                    recv(events) -> event => match event {
+                        Ok(Event::Frame(decoded)) => {
+                            if !self.forward(decoded, &request_rx) {
+                                return;
+                            }
+                        }
+                        Ok(Event::Seeked(generation, result)) => {
+                            if generation == self.generation
+                                && let Some(reply) = self.pending_seek.take()
+                            {
+                                let _ = reply.send(result);
+                            }
+                        }
+                        Ok(Event::Exited) | Err(_) => {
+                            if !self.restart() {
+                                return;
+                            }
+                        }
+                    },
+                }
+            }
+        }
+
+        fn handle(&mut self, request: Request) {
+            let Request::Seek {
+                position,
+                generation,
+                reply,
+            } = request;
+            self.generation = generation;
+            self.position = Some(position);
+            self.resume_after = None;
+            self.pending_seek = Some(reply);
+            // 送れなければ子プロセスは終了しているので、開き直したときに移動する
+            let _ = self.worker.send(&Instruction::Seek {
+                generation,
+                position,
+            });
+        }
+
+        /// 今の移動の回数のフレームを渡す。受け取る側が閉じていたら false
+        fn forward(
+            &mut self,
+            (generation, decoded): DecodedFrame,
+            request_rx: &Receiver<Request>,
+        ) -> bool {
+            if generation != self.generation {
+                return true;
+            }
+            if let Ok(Some(frame)) = &decoded {
+                if self
+                    .resume_after
+                    .is_some_and(|after| frame.timestamp <= after)
+                {
+                    return true;
+                }
+                self.resume_after = None;
+                self.position = Some(frame.timestamp);
+                self.restarts = 0;
+            }
+            select! {
+                send(self.frame_tx, (generation, decoded)) -> sent => sent.is_ok(),
+                recv(request_rx) -> request => match request {
+                    // 送れなかったフレームは移動前のものなので捨ててよい
+                    Ok(request) => {
+                        self.handle(request);
+                        true
+                    }
+                    Err(_) => false,
+                },
+            }
+        }
+
+        /// 子プロセスを開き直し、続きの位置へ移動する。開き直せなければ false
+        fn restart(&mut self) -> bool {
+            loop {
+                self.restarts += 1;
+                if self.restarts > MAX_RESTARTS {
+                    let error =
+                        || MediaError::Pipeline("video decode worker keeps crashing".to_string());
+                    if let Some(reply) = self.pending_seek.take() {
+                        let _ = reply.send(Err(error()));
+                    }
+                    let _ = self.frame_tx.send((self.generation, Err(error())));
+                    return false;
+                }
+                match Worker::spawn(&self.path, self.command) {
+                    Ok(worker) => {
+                        self.worker = worker;
+                        break;
+                    }
+                    Err(_) => thread::sleep(Duration::from_millis(100)),
+                }
+            }
+
+            // 移動の途中でなければ、最後に渡したフレームの次から続ける
+            if self.pending_seek.is_none() {
+                self.resume_after = self.position;
+            }
+            match self.position {
+                Some(position) => self
+                    .worker
+                    .send(&Instruction::Seek {
+                        generation: self.generation,
+                        position,
+                    })
+                    .is_ok(),
+                None => true,
+            }
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        #[test]
+        fn test_message_round_trip() {
+            let messages = [
+                Message::Ready { slot_size: 921_600 },
+                Message::Failed {
+                    message: "No such file\nor directory".to_string(),
+                },
+                Message::Frame {
+                    generation: 2,
+                    slot: Some(1),
+                    header: FrameHeader {
+                        len: 921_600,
+                        width: 640,
+                        height: 480,
+                        format: FrameFormat::RGB8,
+                        timestamp: Duration::from_millis(1_234),
+                        pts: -3,
+                    },
+                },
+                Message::Frame {
+                    generation: 0,
+                    slot: None,
+                    header: FrameHeader {
+                        len: 12,
+                        width: 2,
+                        height: 2,
+                        format: FrameFormat::Gray8,
+                        timestamp: Duration::ZERO,
+                        pts: 0,
+                    },
+                },
+                Message::End { generation: 4 },
+                Message::Error {
+                    generation: 1,
+                    recoverable: true,
+                    message: "corrupt packet".to_string(),
+                },
+                Message::Seeked {
+                    generation: 3,
+                    error: None,
+                },
+                Message::Seeked {
+                    generation: 3,
+                    error: Some("out of range".to_string()),
+                },
+            ];
+            for message in messages {
+                let expected = match &message {
+                    Message::Failed { message } => Message::Failed {
+                        message: one_line(message),
+                    },
+                    message => message.clone(),
+                };
+                assert_eq!(Message::parse(&message.to_string()), Some(expected));
+            }
+            assert_eq!(Message::parse("frame 1 2 3"), None);
+            assert_eq!(Message::parse("hello"), None);
+        }
+
+        #[test]
+        fn test_instruction_round_trip() {
+            for instruction in [
+                Instruction::Free { slot: 2 },
+                Instruction::Seek {
+                    generation: 7,
+                    position: Duration::from_secs_f64(90.5),
+                },
+            ] {
+                assert_eq!(
+                    Instruction::parse(&instruction.to_string()),
+                    Some(instruction)
+                );
+            }
+            assert_eq!(Instruction::parse("free"), None);
+        }
+
+        #[test]
+        fn test_ring_slots() {
+            let path = ring_path();
+            let file = File::options()
+                .read(true)
+                .write(true)
+                .create_new(true)
+                .open(&path)
+                .unwrap();
+            file.set_len(4 * RING_SLOTS as u64).unwrap();
+            let writer = Ring::map(&file, 4).unwrap();
+            let reader = Ring::map(
+                &File::options().read(true).write(true).open(&path).unwrap(),
+                4,
+            )
+            .unwrap();
+            fs::remove_file(&path).unwrap();
+
+            writer.write(2, &[1, 2, 3, 4]);
+            writer.write(0, &[9]);
+            assert_eq!(reader.read(2, 4), Some(vec![1, 2, 3, 4]));
+            assert_eq!(reader.read(0, 1), Some(vec![9]));
+            assert_eq!(reader.read(0, 5), None);
+            assert_eq!(reader.read(RING_SLOTS, 1), None);
+        }
+    }
+}
@@ -0,0 +1,140 @@
+use std::fs::File;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+use sha2::{Digest, Sha256};
+
+use crate::errors::{DownloaderError, Result};
+use crate::network::NetworkOptions;
+
+/// Extension of checksum files placed next to the media (`video.mp4.sha256`)
+const SIDECAR_EXTENSION: &str = "sha256";
+
+/// Parse a SHA-256 digest from a hex string or a `sha256sum` line (`<hex>  <file>`)
+pub fn parse_sha256(text: &str) -> Result<String> {
+    let hex = text.split_whitespace().next().unwrap_or_default();
+    if hex.len() != 64 || !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+        return Err(DownloaderError::Parse(format!(
+            "Invalid SHA-256 digest: {}",
+            text.trim()
+        )));
+    }
+    Ok(hex.to_ascii_lowercase())
+}
+
+/// Hex digest of a finished hash
+pub(crate) fn to_hex(hasher: Sha256) -> String {
+    hasher
+        .finalize()
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect()
+}
+
+/// Compute the SHA-256 of a file without reading it into memory
+pub fn sha256_file(path: &Path) -> Result<String> {
+    let mut file = File::open(path)?;
+    let mut hasher = Sha256::new();
+    let mut buffer = vec![0; 64 * 1024];
+    loop {
+        let read = file.read(&mut buffer)?;
+        if read == 0 {
+            break;
+        }
+        hasher.update(&buffer[..read]);
+    }
+    Ok(to_hex(hasher))
+}
+
+/// Check a computed digest against the expected one
+pub fn verify_sha256(actual: &str, expected: &str) -> Result<()> {
+    if actual.eq_ignore_ascii_case(expected) {
+        Ok(())
+    } else {
+        Err(DownloaderError::ChecksumMismatch {
+            expected: expected.to_string(),
+            actual: actual.to_string(),
+        })
+    }
+}
+
+/// Read `<path>.sha256` next to a local file, if present
+pub fn read_sha256_sidecar(path: &Path) -> Result<Option<String>> {
+    let mut sidecar = path.as_os_str().to_owned();
+    sidecar.push(".");
+    sidecar.push(SIDECAR_EXTENSION);
+    let sidecar = PathBuf::from(sidecar);
+
+    match std::fs::read_to_string(&sidecar) {
+        Ok(text) => parse_sha256(&text).map(Some),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// Fetch `<url>.sha256` published next to a download, if present
+pub async fn fetch_sha256_sidecar(url: &str, options: &NetworkOptions) -> Result<Option<String>> {
+    let sidecar_url = format!("{}.{}", url, SIDECAR_EXTENSION);
+    let http_error =
+        |e: reqwest::Error| DownloaderError::Download(format!("{}: {}", sidecar_url, e));
+
+    let response = options
+        .http_client()?
+        .get(&sidecar_url)
+        .send()
+        .await
+        .map_err(http_error)?;
+    if response.status() == reqwest::StatusCode::NOT_FOUND {
+        return Ok(None);
+    }
+    let text = response
+        .error_for_status()
+        .map_err(http_error)?
+        .text()
+        .await
+        .map_err(http_error)?;
+    parse_sha256(&text).map(Some)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// SHA-256 of "abc"
+    const ABC_SHA256: &str = "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad";
+
+    #[test]
+    fn test_parse_sha256() {
+        let upper = ABC_SHA256.to_ascii_uppercase();
+        assert_eq!(parse_sha256(&upper).unwrap(), ABC_SHA256);
+        assert_eq!(
+            parse_sha256(&format!("{}  video.mp4\n", ABC_SHA256)).unwrap(),
+            ABC_SHA256
+        );
+        assert!(parse_sha256("abc").is_err());
+        assert!(parse_sha256(&"z".repeat(64)).is_err());
+    }
+
+    #[test]
+    fn test_sha256_file_and_sidecar() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("video.mp4");
+        std::fs::write(&path, b"abc").unwrap();
+
+        let actual = sha256_file(&path).unwrap();
+        assert_eq!(actual, ABC_SHA256);
+        assert_eq!(read_sha256_sidecar(&path).unwrap(), None);
+
+        std::fs::write(
+            dir.path().join("video.mp4.sha256"),
+            format!("{}  video.mp4\n", ABC_SHA256),
+        )
+        .unwrap();
+        let expected = read_sha256_sidecar(&path).unwrap().unwrap();
+        assert!(verify_sha256(&actual, &expected).is_ok());
+        assert!(matches!(
+            verify_sha256(&actual, &"0".repeat(64)),
+            Err(DownloaderError::ChecksumMismatch { .. })
+        ));
+    }
+}
@@ -18,4 +18,7 @@ pub enum DownloaderError {
 
     #[error("Dependency missing: {0}")]
     DependencyMissing(String),
+
+    #[error("Checksum mismatch: expected SHA-256 {expected}, got {actual}")]
+    ChecksumMismatch { expected: String, actual: String },
 }
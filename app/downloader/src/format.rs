@@ -0,0 +1,94 @@
+use crate::errors::{DownloaderError, Result};
+
+/// Video quality for YouTube downloads
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Quality {
+    Best,
+    Worst,
+    /// Tallest format no higher than this many lines
+    Height(u32),
+}
+
+/// Parse a quality such as `360p`, `720`, `best` or `worst`
+pub fn parse_quality(text: &str) -> Result<Quality> {
+    let trimmed = text.trim();
+    match trimmed.to_ascii_lowercase().as_str() {
+        "best" => return Ok(Quality::Best),
+        "worst" => return Ok(Quality::Worst),
+        _ => {}
+    }
+
+    let number = trimmed.strip_suffix(['p', 'P']).unwrap_or(trimmed);
+    match number.parse::<u32>() {
+        Ok(height) if height > 0 => Ok(Quality::Height(height)),
+        _ => Err(DownloaderError::Parse(format!("Invalid quality: {}", text))),
+    }
+}
+
+/// Which YouTube format yt-dlp should download
+///
+/// Only single-file (progressive) formats are selected, so the result can be
+/// played or streamed without merging separate video and audio.
+#[derive(Debug, Clone, Default)]
+pub struct FormatSelection {
+    pub quality: Option<Quality>,
+    /// Raw yt-dlp `-f` selector, used instead of `quality`
+    pub format: Option<String>,
+}
+
+impl FormatSelection {
+    /// yt-dlp `-f` selector for this selection
+    pub(crate) fn ytdlp_selector(&self) -> String {
+        if let Some(format) = &self.format {
+            return format.clone();
+        }
+        match self.quality.unwrap_or(Quality::Best) {
+            Quality::Best => "best[ext=mp4]/best".to_string(),
+            Quality::Worst => "worst[ext=mp4]/worst".to_string(),
+            // Fall back to the smallest format when none is low enough
+            Quality::Height(height) => format!(
+                "best[height<={0}][ext=mp4]/best[height<={0}]/worst[ext=mp4]/worst",
+                height
+            ),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_quality() {
+        assert_eq!(parse_quality("360p").unwrap(), Quality::Height(360));
+        assert_eq!(parse_quality("720").unwrap(), Quality::Height(720));
+        assert_eq!(parse_quality("1080P").unwrap(), Quality::Height(1080));
+        assert_eq!(parse_quality("Best").unwrap(), Quality::Best);
+        assert_eq!(parse_quality("worst").unwrap(), Quality::Worst);
+        assert!(parse_quality("0p").is_err());
+        assert!(parse_quality("hd").is_err());
+    }
+
+    #[test]
+    fn test_ytdlp_selector() {
+        assert_eq!(
+            FormatSelection::default().ytdlp_selector(),
+            "best[ext=mp4]/best"
+        );
+
+        let selection = FormatSelection {
+            quality: Some(Quality::Height(360)),
+            format: None,
+        };
+        assert_eq!(
+            selection.ytdlp_selector(),
+            "best[height<=360][ext=mp4]/best[height<=360]/worst[ext=mp4]/worst"
+        );
+
+        let selection = FormatSelection {
+            quality: Some(Quality::Worst),
+            format: Some("18".to_string()),
+        };
+        assert_eq!(selection.ytdlp_selector(), "18");
+    }
+}
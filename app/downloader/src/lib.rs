@@ -1,5 +1,24 @@
+mod checksum;
 mod errors;
+mod format;
+mod network;
+mod progress;
+mod sponsorblock;
+mod stream;
 mod youtube;
 
+pub use checksum::{
+    fetch_sha256_sidecar, parse_sha256, read_sha256_sidecar, sha256_file, verify_sha256,
+};
 pub use errors::{DownloaderError, Result};
-pub use youtube::{FormatInfo, VideoInfo, download_video, get_video_info, list_formats};
+pub use format::{FormatSelection, Quality, parse_quality};
+pub use network::{NetworkOptions, download_file, download_file_with_progress, parse_rate};
+pub use progress::DownloadProgress;
+pub use sponsorblock::{
+    DEFAULT_CATEGORIES, SponsorSegment, fetch_sponsor_segments, youtube_video_id,
+};
+pub use stream::{VideoStream, stream_video};
+pub use youtube::{
+    ChapterInfo, FormatInfo, VideoInfo, download_subtitles, download_video,
+    download_video_with_progress, get_video_info, list_formats,
+};
@@ -0,0 +1,183 @@
+use std::io::Write;
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+
+use sha2::{Digest, Sha256};
+use tempfile::NamedTempFile;
+
+use crate::checksum::{to_hex, verify_sha256};
+
+use crate::errors::{DownloaderError, Result};
+use crate::progress::DownloadProgress;
+
+/// Network settings shared by yt-dlp and the HTTP downloads
+#[derive(Debug, Clone, Default)]
+pub struct NetworkOptions {
+    /// Maximum download rate in bytes per second
+    pub limit_rate: Option<u64>,
+    /// Proxy URL (`http://`, `https://`, `socks5://` or `socks5h://`)
+    pub proxy: Option<String>,
+}
+
+impl NetworkOptions {
+    /// Extra yt-dlp arguments for these settings
+    pub(crate) fn ytdlp_args(&self) -> Vec<String> {
+        let mut args = Vec::new();
+        if let Some(rate) = self.limit_rate {
+            args.push("--limit-rate".to_string());
+            args.push(rate.to_string());
+        }
+        if let Some(proxy) = &self.proxy {
+            args.push("--proxy".to_string());
+            args.push(proxy.clone());
+        }
+        args
+    }
+
+    /// HTTP client that goes through the proxy
+    pub fn http_client(&self) -> Result<reqwest::Client> {
+        let mut builder = reqwest::Client::builder();
+        if let Some(proxy) = &self.proxy {
+            let proxy = reqwest::Proxy::all(proxy).map_err(|e| {
+                DownloaderError::Download(format!("Invalid proxy {}: {}", proxy, e))
+            })?;
+            builder = builder.proxy(proxy);
+        }
+        builder
+            .build()
+            .map_err(|e| DownloaderError::Download(format!("Failed to build HTTP client: {}", e)))
+    }
+}
+
+/// Parse a rate such as `500K`, `2M` or `1.5MiB` into bytes per second
+///
+/// Suffixes are binary multiples, matching yt-dlp's `--limit-rate`.
+pub fn parse_rate(text: &str) -> Result<u64> {
+    let invalid = || DownloaderError::Parse(format!("Invalid rate: {}", text));
+
+    let trimmed = text.trim();
+    let trimmed = trimmed.strip_suffix("/s").unwrap_or(trimmed);
+    let trimmed = trimmed
+        .strip_suffix("iB")
+        .or_else(|| trimmed.strip_suffix('B'))
+        .unwrap_or(trimmed);
+
+    let (number, multiplier) = match trimmed.chars().last() {
+        Some(unit) if unit.is_ascii_alphabetic() => {
+            let multiplier = match unit.to_ascii_uppercase() {
+                'K' => 1u64 << 10,
+                'M' => 1 << 20,
+                'G' => 1 << 30,
+                _ => return Err(invalid()),
+            };
+            (&trimmed[..trimmed.len() - 1], multiplier)
+        }
+        _ => (trimmed, 1),
+    };
+
+    let value: f64 = number.trim().parse().map_err(|_| invalid())?;
+    let rate = (value * multiplier as f64).round();
+    if !rate.is_finite() || rate < 1.0 {
+        return Err(invalid());
+    }
+    Ok(rate as u64)
+}
+
+/// Download a URL to a temporary file, honouring the proxy and rate limit
+///
+/// When `expected_sha256` is given, the file is hashed while it is written and
+/// discarded if the digest does not match.
+pub async fn download_file(
+    url: &str,
+    options: &NetworkOptions,
+    expected_sha256: Option<&str>,
+) -> Result<PathBuf> {
+    download_file_with_progress(url, options, expected_sha256, |_| {}).await
+}
+
+/// [`download_file`], calling `on_progress` after every received chunk
+pub async fn download_file_with_progress(
+    url: &str,
+    options: &NetworkOptions,
+    expected_sha256: Option<&str>,
+    mut on_progress: impl FnMut(&DownloadProgress),
+) -> Result<PathBuf> {
+    let http_error = |e: reqwest::Error| DownloaderError::Download(format!("{}: {}", url, e));
+
+    let mut response = options
+        .http_client()?
+        .get(url)
+        .send()
+        .await
+        .and_then(reqwest::Response::error_for_status)
+        .map_err(http_error)?;
+
+    let total = response.content_length();
+    let mut temp_file = NamedTempFile::new()?;
+    let started = Instant::now();
+    let mut received = 0u64;
+    let mut hasher = Sha256::new();
+    while let Some(chunk) = response.chunk().await.map_err(http_error)? {
+        temp_file.write_all(&chunk)?;
+        hasher.update(&chunk);
+        received += chunk.len() as u64;
+        on_progress(&DownloadProgress::measured(
+            received,
+            total,
+            started.elapsed(),
+        ));
+
+        // Sleep until the average rate falls back to the limit
+        if let Some(rate) = options.limit_rate {
+            let expected = Duration::from_secs_f64(received as f64 / rate as f64);
+            if let Some(wait) = expected.checked_sub(started.elapsed()) {
+                tokio::time::sleep(wait).await;
+            }
+        }
+    }
+
+    // Dropping the temporary file removes a download that failed verification
+    if let Some(expected) = expected_sha256 {
+        verify_sha256(&to_hex(hasher), expected)?;
+    }
+    Ok(temp_file.into_temp_path().keep().map_err(|e| e.error)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_rate() {
+        assert_eq!(parse_rate("2M").unwrap(), 2 * 1024 * 1024);
+        assert_eq!(parse_rate("500k").unwrap(), 500 * 1024);
+        assert_eq!(parse_rate("1.5MiB").unwrap(), 1536 * 1024);
+        assert_eq!(parse_rate("64KB/s").unwrap(), 64 * 1024);
+        assert_eq!(parse_rate("1000").unwrap(), 1000);
+        assert!(parse_rate("fast").is_err());
+        assert!(parse_rate("2X").is_err());
+        assert!(parse_rate("0").is_err());
+    }
+
+    #[test]
+    fn test_ytdlp_args() {
+        let options = NetworkOptions {
+            limit_rate: Some(2048),
+            proxy: Some("socks5://127.0.0.1:1080".to_string()),
+        };
+        assert_eq!(
+            options.ytdlp_args(),
+            ["--limit-rate", "2048", "--proxy", "socks5://127.0.0.1:1080"]
+        );
+        assert!(NetworkOptions::default().ytdlp_args().is_empty());
+    }
+
+    #[test]
+    fn test_http_client_proxy() {
+        let options = NetworkOptions {
+            proxy: Some("socks5://127.0.0.1:1080".to_string()),
+            ..Default::default()
+        };
+        assert!(options.http_client().is_ok());
+    }
+}
@@ -0,0 +1,112 @@
+use std::time::Duration;
+
+/// Progress of a running download
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct DownloadProgress {
+    /// Bytes received so far
+    pub downloaded: u64,
+    /// Expected size (an estimate for some yt-dlp formats), if known
+    pub total: Option<u64>,
+    /// Current speed in bytes per second
+    pub speed: Option<f64>,
+    /// Estimated time left
+    pub eta: Option<Duration>,
+}
+
+/// Prefix of the progress lines requested from yt-dlp
+pub(crate) const YTDLP_PROGRESS_PREFIX: &str = "ascii-term-progress ";
+
+/// yt-dlp arguments that print one JSON progress line per update
+pub(crate) fn ytdlp_progress_args() -> [String; 3] {
+    [
+        "--newline".to_string(),
+        "--progress-template".to_string(),
+        format!("download:{}%(progress)j", YTDLP_PROGRESS_PREFIX),
+    ]
+}
+
+/// Fields of yt-dlp's progress dictionary (numbers may be floats or null)
+#[derive(serde::Deserialize)]
+struct YtdlpProgress {
+    downloaded_bytes: Option<f64>,
+    total_bytes: Option<f64>,
+    total_bytes_estimate: Option<f64>,
+    speed: Option<f64>,
+    eta: Option<f64>,
+}
+
+impl DownloadProgress {
+    /// Progress measured from the bytes received since `elapsed` ago
+    pub(crate) fn measured(downloaded: u64, total: Option<u64>, elapsed: Duration) -> Self {
+        let seconds = elapsed.as_secs_f64();
+        let speed = (seconds > 0.0 && downloaded > 0).then(|| downloaded as f64 / seconds);
+        let eta = match (total, speed) {
+            (Some(total), Some(speed)) => Some(Duration::from_secs_f64(
+                total.saturating_sub(downloaded) as f64 / speed,
+            )),
+            _ => None,
+        };
+        Self {
+            downloaded,
+            total,
+            speed,
+            eta,
+        }
+    }
+
+    /// Parse a progress line printed with [`ytdlp_progress_args`]
+    pub(crate) fn from_ytdlp_line(line: &str) -> Option<Self> {
+        let json = line.trim().strip_prefix(YTDLP_PROGRESS_PREFIX)?;
+        let progress: YtdlpProgress = serde_json::from_str(json).ok()?;
+        let non_negative =
+            |value: Option<f64>| value.filter(|value| value.is_finite() && *value >= 0.0);
+        Some(Self {
+            downloaded: non_negative(progress.downloaded_bytes)? as u64,
+            total: non_negative(progress.total_bytes.or(progress.total_bytes_estimate))
+                .map(|total| total as u64),
+            speed: non_negative(progress.speed),
+            eta: non_negative(progress.eta).map(Duration::from_secs_f64),
+        })
+    }
+
+    /// Fraction done (0.0–1.0), if the size is known
+    pub fn fraction(&self) -> Option<f64> {
+        self.total
+            .filter(|total| *total > 0)
+            .map(|total| (self.downloaded as f64 / total as f64).min(1.0))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_ytdlp_progress() {
+        let line = format!(
+            "{}{{\"status\": \"downloading\", \"downloaded_bytes\": 1048576, \"total_bytes\": null, \
+             \"total_bytes_estimate\": 4194304.0, \"speed\": 524288.5, \"eta\": 6}}",
+            YTDLP_PROGRESS_PREFIX
+        );
+        let progress = DownloadProgress::from_ytdlp_line(&line).unwrap();
+        assert_eq!(progress.downloaded, 1024 * 1024);
+        assert_eq!(progress.total, Some(4 * 1024 * 1024));
+        assert_eq!(progress.fraction(), Some(0.25));
+        assert_eq!(progress.eta, Some(Duration::from_secs(6)));
+
+        assert!(DownloadProgress::from_ytdlp_line("[download] Destination: a.mp4").is_none());
+    }
+
+    #[test]
+    fn test_measured_progress() {
+        let progress = DownloadProgress::measured(300, Some(1000), Duration::from_secs(3));
+        assert_eq!(progress.speed, Some(100.0));
+        assert_eq!(progress.eta, Some(Duration::from_secs(7)));
+
+        let progress = DownloadProgress::measured(0, None, Duration::ZERO);
+        assert_eq!(
+            (progress.speed, progress.eta, progress.fraction()),
+            (None, None, None)
+        );
+    }
+}
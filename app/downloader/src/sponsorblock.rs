@@ -0,0 +1,131 @@
+use crate::errors::{DownloaderError, Result};
+use crate::network::NetworkOptions;
+
+/// SponsorBlock API endpoint for skip segments
+const SKIP_SEGMENTS_URL: &str = "https://sponsor.ajay.app/api/skipSegments";
+
+/// Categories fetched when none are specified
+pub const DEFAULT_CATEGORIES: &[&str] = &["sponsor", "intro"];
+
+/// A community-submitted segment of a video
+#[derive(Debug, Clone, PartialEq, serde::Deserialize)]
+pub struct SponsorSegment {
+    /// Start and end of the segment in seconds
+    pub segment: (f64, f64),
+    pub category: String,
+}
+
+impl SponsorSegment {
+    pub fn start(&self) -> f64 {
+        self.segment.0
+    }
+
+    pub fn end(&self) -> f64 {
+        self.segment.1
+    }
+}
+
+/// Fetch the segments of `categories` submitted for a YouTube video
+///
+/// Returns an empty list when the video has no segments.
+pub async fn fetch_sponsor_segments(
+    video_id: &str,
+    categories: &[&str],
+    options: &NetworkOptions,
+) -> Result<Vec<SponsorSegment>> {
+    let categories = serde_json::to_string(categories)
+        .map_err(|e| DownloaderError::Parse(format!("Failed to encode categories: {}", e)))?;
+
+    let response = options
+        .http_client()?
+        .get(SKIP_SEGMENTS_URL)
+        .query(&[("videoID", video_id), ("categories", categories.as_str())])
+        .send()
+        .await
+        .map_err(|e| DownloaderError::Download(format!("SponsorBlock request failed: {}", e)))?;
+
+    // The API answers 404 when nothing has been submitted for the video
+    if response.status() == reqwest::StatusCode::NOT_FOUND {
+        return Ok(Vec::new());
+    }
+    let body = response
+        .error_for_status()
+        .map_err(|e| DownloaderError::Download(format!("SponsorBlock request failed: {}", e)))?
+        .bytes()
+        .await
+        .map_err(|e| DownloaderError::Download(format!("SponsorBlock request failed: {}", e)))?;
+
+    serde_json::from_slice(&body)
+        .map_err(|e| DownloaderError::Parse(format!("Failed to parse sponsor segments: {}", e)))
+}
+
+/// Extract the video id from a YouTube URL
+///
+/// Supports `watch?v=`, `youtu.be/`, `shorts/`, `embed/` and `live/` URLs.
+pub fn youtube_video_id(url: &str) -> Option<String> {
+    let parsed = url::Url::parse(url).ok()?;
+    let domain = parsed.domain()?;
+    // The host itself or a subdomain of it, but not lookalikes such as `notyoutube.com`
+    let is_domain = |name: &str| {
+        domain == name
+            || domain
+                .strip_suffix(name)
+                .is_some_and(|sub| sub.ends_with('.'))
+    };
+
+    let id = if is_domain("youtu.be") {
+        parsed.path_segments()?.next()?.to_string()
+    } else if is_domain("youtube.com") {
+        let mut segments = parsed.path_segments()?;
+        match segments.next()? {
+            "watch" => parsed
+                .query_pairs()
+                .find(|(key, _)| key == "v")
+                .map(|(_, value)| value.into_owned())?,
+            "shorts" | "embed" | "live" => segments.next()?.to_string(),
+            _ => return None,
+        }
+    } else {
+        return None;
+    };
+
+    (!id.is_empty()).then_some(id)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_youtube_video_id() {
+        assert_eq!(
+            youtube_video_id("https://www.youtube.com/watch?v=dQw4w9WgXcQ&t=42").as_deref(),
+            Some("dQw4w9WgXcQ")
+        );
+        assert_eq!(
+            youtube_video_id("https://youtu.be/dQw4w9WgXcQ?si=abc").as_deref(),
+            Some("dQw4w9WgXcQ")
+        );
+        assert_eq!(
+            youtube_video_id("https://m.youtube.com/shorts/abc123").as_deref(),
+            Some("abc123")
+        );
+        assert_eq!(youtube_video_id("https://www.youtube.com/@channel"), None);
+        assert_eq!(youtube_video_id("https://example.com/watch?v=abc"), None);
+        assert_eq!(youtube_video_id("https://notyoutube.com/watch?v=abc"), None);
+        assert_eq!(youtube_video_id("https://fakeyoutu.be/abc"), None);
+    }
+
+    #[test]
+    fn test_parse_sponsor_segments() {
+        let json = r#"[
+            {"segment": [12.5, 40.0], "UUID": "a", "category": "sponsor", "actionType": "skip", "votes": 3},
+            {"segment": [0, 8.2], "UUID": "b", "category": "intro", "actionType": "skip", "votes": 0}
+        ]"#;
+        let segments: Vec<SponsorSegment> = serde_json::from_str(json).unwrap();
+        assert_eq!(segments.len(), 2);
+        assert_eq!(segments[0].start(), 12.5);
+        assert_eq!(segments[0].end(), 40.0);
+        assert_eq!(segments[1].category, "intro");
+    }
+}
@@ -0,0 +1,446 @@
+use std::io::{self, BufRead, BufReader, Read, Seek, SeekFrom, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command, Stdio};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Condvar, Mutex, MutexGuard};
+use std::thread;
+use std::time::Duration;
+
+use tempfile::{NamedTempFile, TempPath};
+
+use crate::errors::{DownloaderError, Result};
+use crate::format::FormatSelection;
+use crate::network::NetworkOptions;
+use crate::youtube::check_ytdlp_installed;
+
+/// How often waiting connections check whether the stream was dropped
+const POLL_INTERVAL: Duration = Duration::from_millis(200);
+
+/// Largest request head accepted from a client
+const MAX_REQUEST_HEAD: usize = 8 * 1024;
+
+/// A video that plays while yt-dlp is still downloading it
+///
+/// yt-dlp writes the video to stdout (`-o -`), which is copied into a temporary
+/// file. The file is served over HTTP on a loopback port, so every reader
+/// (probe, video and audio decoders) gets its own connection and waits for
+/// bytes that have not arrived yet instead of seeing an early end of file.
+/// Byte ranges are served once the download has finished.
+///
+/// Dropping the stream stops yt-dlp and removes the temporary file.
+pub struct VideoStream {
+    url: String,
+    address: SocketAddr,
+    shared: Arc<Shared>,
+    child: Option<Arc<Mutex<Child>>>,
+    _file: TempPath,
+}
+
+/// Download progress shared by the copy thread and the connections
+#[derive(Debug, Default)]
+struct Progress {
+    /// Bytes written to the temporary file so far
+    written: u64,
+    /// The source reached its end (or failed)
+    done: bool,
+    /// Why the download failed
+    error: Option<String>,
+}
+
+#[derive(Default)]
+struct Shared {
+    progress: Mutex<Progress>,
+    changed: Condvar,
+    closed: AtomicBool,
+}
+
+impl Shared {
+    fn lock(&self) -> MutexGuard<'_, Progress> {
+        self.progress.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn update(&self, f: impl FnOnce(&mut Progress)) {
+        f(&mut self.lock());
+        self.changed.notify_all();
+    }
+
+    /// Wait until `ready` holds, returning `None` once the stream is dropped
+    fn wait_until(&self, ready: impl Fn(&Progress) -> bool) -> Option<MutexGuard<'_, Progress>> {
+        let mut progress = self.lock();
+        loop {
+            if self.closed.load(Ordering::Relaxed) {
+                return None;
+            }
+            if ready(&progress) {
+                return Some(progress);
+            }
+            progress = self
+                .changed
+                .wait_timeout(progress, POLL_INTERVAL)
+                .unwrap_or_else(|e| e.into_inner())
+                .0;
+        }
+    }
+}
+
+/// Start streaming a YouTube video
+///
+/// Returns as soon as yt-dlp has produced the first bytes; play [`VideoStream::url`].
+/// Only single-file formats can be written to stdout, which is all that
+/// [`FormatSelection`] selects.
+pub async fn stream_video(
+    url: &str,
+    _browser: &str,
+    format: &FormatSelection,
+    options: &NetworkOptions,
+) -> Result<VideoStream> {
+    check_ytdlp_installed().await?;
+
+    let selector = format.ytdlp_selector();
+    let mut child = Command::new("yt-dlp")
+        .args([url, "-f", &selector, "-o", "-", "--quiet"])
+        .args(options.ytdlp_args())
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| DownloaderError::Process(format!("Failed to execute yt-dlp: {}", e)))?;
+    let stdout = child
+        .stdout
+        .take()
+        .ok_or_else(|| DownloaderError::Process("yt-dlp stdout is not available".to_string()))?;
+    let mut stderr = child.stderr.take();
+    let child = Arc::new(Mutex::new(child));
+
+    let waited = Arc::clone(&child);
+    let finish = move || {
+        let mut message = String::new();
+        if let Some(stderr) = &mut stderr {
+            let _ = stderr.read_to_string(&mut message);
+        }
+        let status = waited.lock().unwrap_or_else(|e| e.into_inner()).wait();
+        match status {
+            Ok(status) if status.success() => None,
+            Ok(_) => Some(format!("yt-dlp failed: {}", message.trim())),
+            Err(e) => Some(format!("yt-dlp failed: {}", e)),
+        }
+    };
+
+    let mut stream = VideoStream::start(stdout, finish)?;
+    stream.child = Some(child);
+
+    let shared = Arc::clone(&stream.shared);
+    let started = tokio::task::spawn_blocking(move || {
+        shared
+            .wait_until(|progress| progress.written > 0 || progress.done)
+            .map(|progress| match &progress.error {
+                Some(error) => Err(error.clone()),
+                None if progress.written == 0 => Err("yt-dlp produced no data".to_string()),
+                None => Ok(()),
+            })
+    })
+    .await
+    .map_err(|e| DownloaderError::Process(format!("Stream task panicked: {}", e)))?;
+    match started {
+        Some(Ok(())) => Ok(stream),
+        Some(Err(message)) => Err(DownloaderError::Download(message)),
+        None => Err(DownloaderError::Download("Stream closed".to_string())),
+    }
+}
+
+impl VideoStream {
+    /// Copy `source` into a temporary file and serve it on a loopback port
+    ///
+    /// `finish` runs when `source` ends and returns why the download failed, if it did.
+    fn start(
+        source: impl Read + Send + 'static,
+        finish: impl FnOnce() -> Option<String> + Send + 'static,
+    ) -> Result<Self> {
+        let file = NamedTempFile::new()?;
+        let path = file.path().to_path_buf();
+        let (output, file) = file.into_parts();
+
+        let listener = TcpListener::bind(("127.0.0.1", 0))?;
+        let address = listener.local_addr()?;
+        let shared = Arc::new(Shared::default());
+
+        let copy_shared = Arc::clone(&shared);
+        thread::Builder::new()
+            .name("stream-copy".to_string())
+            .spawn(move || {
+                let error = copy(source, output, &copy_shared)
+                    .err()
+                    .map(|e| e.to_string());
+                let error = finish().or(error);
+                copy_shared.update(|progress| {
+                    progress.done = true;
+                    progress.error = error;
+                });
+            })?;
+
+        let server_shared = Arc::clone(&shared);
+        thread::Builder::new()
+            .name("stream-server".to_string())
+            .spawn(move || accept(listener, path, server_shared))?;
+
+        Ok(Self {
+            url: format!("http://{}/video", address),
+            address,
+            shared,
+            child: None,
+            _file: file,
+        })
+    }
+
+    /// Loopback URL to open the video from
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+
+    /// Whether yt-dlp has finished downloading
+    pub fn is_complete(&self) -> bool {
+        self.shared.lock().done
+    }
+}
+
+impl Drop for VideoStream {
+    fn drop(&mut self) {
+        self.shared.closed.store(true, Ordering::Relaxed);
+        self.shared.changed.notify_all();
+        if let Some(child) = &self.child {
+            let _ = child.lock().unwrap_or_else(|e| e.into_inner()).kill();
+        }
+        // Wake the accept loop so it notices the stream is closed
+        let _ = TcpStream::connect(self.address);
+    }
+}
+
+/// Append `source` to the temporary file, publishing progress after each chunk
+fn copy(mut source: impl Read, mut output: std::fs::File, shared: &Shared) -> io::Result<()> {
+    let mut buffer = vec![0; 64 * 1024];
+    loop {
+        let read = match source.read(&mut buffer) {
+            Ok(0) => return Ok(()),
+            Ok(read) => read,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e),
+        };
+        output.write_all(&buffer[..read])?;
+        shared.update(|progress| progress.written += read as u64);
+        if shared.closed.load(Ordering::Relaxed) {
+            return Ok(());
+        }
+    }
+}
+
+fn accept(listener: TcpListener, path: PathBuf, shared: Arc<Shared>) {
+    for connection in listener.incoming() {
+        if shared.closed.load(Ordering::Relaxed) {
+            return;
+        }
+        let Ok(connection) = connection else {
+            continue;
+        };
+        let path = path.clone();
+        let shared = Arc::clone(&shared);
+        let _ = thread::Builder::new()
+            .name("stream-connection".to_string())
+            .spawn(move || {
+                let _ = respond(connection, &path, &shared);
+            });
+    }
+}
+
+/// Start offset of a `Range: bytes=N-` header
+fn parse_range(request: &str) -> Option<u64> {
+    request.lines().skip(1).find_map(|line| {
+        let (name, value) = line.split_once(':')?;
+        if !name.trim().eq_ignore_ascii_case("range") {
+            return None;
+        }
+        let (start, _) = value.trim().strip_prefix("bytes=")?.split_once('-')?;
+        start.trim().parse().ok()
+    })
+}
+
+fn read_request_head(connection: &TcpStream) -> io::Result<String> {
+    let mut reader = BufReader::new(connection.take(MAX_REQUEST_HEAD as u64));
+    let mut head = String::new();
+    loop {
+        let mut line = String::new();
+        if reader.read_line(&mut line)? == 0 || line.trim_end().is_empty() {
+            return Ok(head);
+        }
+        head.push_str(&line);
+    }
+}
+
+/// Serve one GET request
+///
+/// While downloading, the response has no length and follows the download until
+/// it ends. A range that starts past the beginning waits for the download to
+/// finish, so it can be answered with an exact `Content-Range`.
+fn respond(mut connection: TcpStream, path: &Path, shared: &Shared) -> io::Result<()> {
+    let request = read_request_head(&connection)?;
+    let head_only = request.starts_with("HEAD ");
+    let start = parse_range(&request).unwrap_or(0);
+
+    let Some(progress) = shared.wait_until(|progress| start == 0 || progress.done) else {
+        return Ok(());
+    };
+    let (done, total, failed) = (progress.done, progress.written, progress.error.is_some());
+    drop(progress);
+
+    if failed && total == 0 {
+        return connection.write_all(
+            b"HTTP/1.1 502 Bad Gateway\r\nContent-Length: 0\r\nConnection: close\r\n\r\n",
+        );
+    }
+    if done && start > 0 && start >= total {
+        let head = format!(
+            "HTTP/1.1 416 Range Not Satisfiable\r\nContent-Range: bytes */{}\r\nContent-Length: 0\r\nConnection: close\r\n\r\n",
+            total
+        );
+        return connection.write_all(head.as_bytes());
+    }
+
+    let head = if !done {
+        "HTTP/1.1 200 OK\r\nContent-Type: application/octet-stream\r\nConnection: close\r\n\r\n"
+            .to_string()
+    } else if start > 0 {
+        format!(
+            "HTTP/1.1 206 Partial Content\r\nContent-Type: application/octet-stream\r\nAccept-Ranges: bytes\r\nContent-Range: bytes {}-{}/{}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+            start,
+            total - 1,
+            total,
+            total - start
+        )
+    } else {
+        format!(
+            "HTTP/1.1 200 OK\r\nContent-Type: application/octet-stream\r\nAccept-Ranges: bytes\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+            total
+        )
+    };
+    connection.write_all(head.as_bytes())?;
+    if head_only {
+        return Ok(());
+    }
+
+    let mut file = std::fs::File::open(path)?;
+    file.seek(SeekFrom::Start(start))?;
+    let mut position = start;
+    let mut buffer = vec![0; 64 * 1024];
+    loop {
+        let Some(progress) =
+            shared.wait_until(|progress| progress.written > position || progress.done)
+        else {
+            return Ok(());
+        };
+        let available = progress.written;
+        drop(progress);
+        if available <= position {
+            return Ok(());
+        }
+
+        let want = ((available - position) as usize).min(buffer.len());
+        file.read_exact(&mut buffer[..want])?;
+        connection.write_all(&buffer[..want])?;
+        position += want as u64;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::mpsc;
+
+    /// Reads chunks from a channel, like a pipe that is still being written
+    struct ChunkReader(mpsc::Receiver<Vec<u8>>, Vec<u8>);
+
+    impl Read for ChunkReader {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            if self.1.is_empty() {
+                match self.0.recv() {
+                    Ok(chunk) => self.1 = chunk,
+                    Err(_) => return Ok(0),
+                }
+            }
+            let len = self.1.len().min(buf.len());
+            buf[..len].copy_from_slice(&self.1[..len]);
+            self.1.drain(..len);
+            Ok(len)
+        }
+    }
+
+    fn get(stream: &VideoStream, range: Option<u64>) -> String {
+        let mut connection = TcpStream::connect(stream.address).unwrap();
+        let range = range.map_or(String::new(), |start| {
+            format!("Range: bytes={}-\r\n", start)
+        });
+        write!(
+            connection,
+            "GET /video HTTP/1.1\r\nHost: localhost\r\n{}\r\n",
+            range
+        )
+        .unwrap();
+        let mut response = String::new();
+        connection.read_to_string(&mut response).unwrap();
+        response
+    }
+
+    #[test]
+    fn test_parse_range() {
+        assert_eq!(
+            parse_range("GET / HTTP/1.1\r\nRange: bytes=100-\r\n"),
+            Some(100)
+        );
+        assert_eq!(
+            parse_range("GET / HTTP/1.1\r\nrange:bytes=0-99\r\n"),
+            Some(0)
+        );
+        assert_eq!(parse_range("GET / HTTP/1.1\r\nHost: x\r\n"), None);
+    }
+
+    #[test]
+    fn test_serves_while_downloading() {
+        let (chunk_tx, chunk_rx) = mpsc::channel();
+        let stream = VideoStream::start(ChunkReader(chunk_rx, Vec::new()), || None).unwrap();
+        chunk_tx.send(b"hello ".to_vec()).unwrap();
+
+        let url = stream.url().to_string();
+        assert!(url.starts_with("http://127.0.0.1:"));
+        let reader = thread::spawn({
+            let address = stream.address;
+            move || {
+                let mut connection = TcpStream::connect(address).unwrap();
+                write!(connection, "GET /video HTTP/1.1\r\n\r\n").unwrap();
+                let mut response = String::new();
+                connection.read_to_string(&mut response).unwrap();
+                response
+            }
+        });
+        thread::sleep(Duration::from_millis(50));
+        chunk_tx.send(b"world".to_vec()).unwrap();
+        drop(chunk_tx);
+
+        let response = reader.join().unwrap();
+        assert!(response.starts_with("HTTP/1.1 200 OK"));
+        assert!(response.ends_with("\r\n\r\nhello world"));
+        assert!(stream.is_complete());
+
+        // Once complete, ranges are answered exactly
+        let response = get(&stream, Some(6));
+        assert!(response.starts_with("HTTP/1.1 206 Partial Content"));
+        assert!(response.contains("Content-Range: bytes 6-10/11"));
+        assert!(response.ends_with("\r\n\r\nworld"));
+        assert!(get(&stream, Some(11)).starts_with("HTTP/1.1 416"));
+        assert!(get(&stream, None).contains("Content-Length: 11"));
+    }
+
+    #[test]
+    fn test_reports_failed_download() {
+        let stream = VideoStream::start(io::empty(), || Some("yt-dlp failed".to_string())).unwrap();
+        assert!(get(&stream, None).starts_with("HTTP/1.1 502"));
+    }
+}
@@ -1,12 +1,33 @@
 use std::path::PathBuf;
+use std::process::Stdio;
 
 use tempfile::NamedTempFile;
+use tokio::io::{AsyncBufReadExt, AsyncReadExt, BufReader};
 use tokio::process::Command;
 
 use crate::errors::{DownloaderError, Result};
+use crate::format::FormatSelection;
+use crate::network::NetworkOptions;
+use crate::progress::{DownloadProgress, ytdlp_progress_args};
 
-/// Download YouTube video
-pub async fn download_video(url: &str, _browser: &str) -> Result<PathBuf> {
+/// Download YouTube video in the selected format
+pub async fn download_video(
+    url: &str,
+    browser: &str,
+    format: &FormatSelection,
+    options: &NetworkOptions,
+) -> Result<PathBuf> {
+    download_video_with_progress(url, browser, format, options, |_| {}).await
+}
+
+/// [`download_video`], calling `on_progress` for each progress update from yt-dlp
+pub async fn download_video_with_progress(
+    url: &str,
+    _browser: &str,
+    format: &FormatSelection,
+    options: &NetworkOptions,
+    mut on_progress: impl FnMut(&DownloadProgress),
+) -> Result<PathBuf> {
     check_ytdlp_installed().await?;
 
     let temp_file = NamedTempFile::new().map_err(DownloaderError::Io)?;
@@ -15,22 +36,101 @@ pub async fn download_video(url: &str, _browser: &str) -> Result<PathBuf> {
         .to_str()
         .ok_or_else(|| DownloaderError::Process("Temporary path is not valid UTF-8".to_string()))?;
 
+    let selector = format.ytdlp_selector();
+    let mut child = Command::new("yt-dlp")
+        .args([url, "-f", &selector, "-o", temp_path_str])
+        .args(ytdlp_progress_args())
+        .args(options.ytdlp_args())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| DownloaderError::Process(format!("Failed to execute yt-dlp: {}", e)))?;
+
+    // Read stderr alongside so a chatty yt-dlp cannot block on a full pipe
+    let stderr = child.stderr.take().map(|mut stderr| {
+        tokio::spawn(async move {
+            let mut message = String::new();
+            let _ = stderr.read_to_string(&mut message).await;
+            message
+        })
+    });
+    if let Some(stdout) = child.stdout.take() {
+        let mut lines = BufReader::new(stdout).lines();
+        while let Some(line) = lines.next_line().await? {
+            if let Some(progress) = DownloadProgress::from_ytdlp_line(&line) {
+                on_progress(&progress);
+            }
+        }
+    }
+
+    let status = child.wait().await?;
+    if !status.success() {
+        let error_msg = match stderr {
+            Some(task) => task.await.unwrap_or_default(),
+            None => String::new(),
+        };
+        return Err(DownloaderError::Download(format!(
+            "yt-dlp failed: {}",
+            error_msg
+        )));
+    }
+
+    let persistent_path = temp_file.into_temp_path();
+    Ok(persistent_path.to_path_buf())
+}
+
+/// Download subtitles for a video as WebVTT text
+///
+/// Uploaded subtitles are preferred; auto-generated captions are used when the
+/// video has none for `lang`. Returns `None` if no subtitles exist in that language.
+pub async fn download_subtitles(
+    url: &str,
+    lang: &str,
+    options: &NetworkOptions,
+) -> Result<Option<String>> {
+    check_ytdlp_installed().await?;
+
+    let temp_dir = tempfile::tempdir().map_err(DownloaderError::Io)?;
+    let template = temp_dir.path().join("subtitles");
+    let template_str = template
+        .to_str()
+        .ok_or_else(|| DownloaderError::Process("Temporary path is not valid UTF-8".to_string()))?;
+
     run_ytdlp(
-        &[url, "-f", "best[ext=mp4]/best", "-o", temp_path_str],
-        "yt-dlp failed",
+        &[
+            url,
+            "--skip-download",
+            "--write-subs",
+            "--write-auto-subs",
+            "--sub-langs",
+            lang,
+            "--sub-format",
+            "vtt",
+            "-o",
+            template_str,
+        ],
+        options,
+        "Failed to download subtitles",
     )
     .await?;
 
-    let persistent_path = temp_file.into_temp_path();
-    Ok(persistent_path.to_path_buf())
+    // yt-dlp names the file "<template>.<lang>.vtt"
+    for entry in std::fs::read_dir(temp_dir.path())? {
+        let path = entry?.path();
+        if path.extension().is_some_and(|ext| ext == "vtt") {
+            return Ok(Some(std::fs::read_to_string(path)?));
+        }
+    }
+    Ok(None)
 }
 
 /// Get video information (metadata only)
-pub async fn get_video_info(url: &str) -> Result<VideoInfo> {
+pub async fn get_video_info(url: &str, options: &NetworkOptions) -> Result<VideoInfo> {
     check_ytdlp_installed().await?;
 
     let stdout = run_ytdlp(
         &[url, "--dump-json", "--no-download"],
+        options,
         "Failed to get video info",
     )
     .await?;
@@ -38,11 +138,12 @@ pub async fn get_video_info(url: &str) -> Result<VideoInfo> {
 }
 
 /// Get available formats
-pub async fn list_formats(url: &str) -> Result<Vec<FormatInfo>> {
+pub async fn list_formats(url: &str, options: &NetworkOptions) -> Result<Vec<FormatInfo>> {
     check_ytdlp_installed().await?;
 
     let stdout = run_ytdlp(
         &[url, "--list-formats", "--dump-json"],
+        options,
         "Failed to list formats",
     )
     .await?;
@@ -50,7 +151,7 @@ pub async fn list_formats(url: &str) -> Result<Vec<FormatInfo>> {
 }
 
 /// Check if yt-dlp is installed
-async fn check_ytdlp_installed() -> Result<()> {
+pub(crate) async fn check_ytdlp_installed() -> Result<()> {
     let output = Command::new("yt-dlp").arg("--version").output().await;
 
     match output {
@@ -63,9 +164,14 @@ async fn check_ytdlp_installed() -> Result<()> {
 }
 
 /// Run yt-dlp with the given arguments, returning captured stdout on success.
-async fn run_ytdlp(args: &[&str], failure_context: &str) -> Result<Vec<u8>> {
+async fn run_ytdlp(
+    args: &[&str],
+    options: &NetworkOptions,
+    failure_context: &str,
+) -> Result<Vec<u8>> {
     let output = Command::new("yt-dlp")
         .args(args)
+        .args(options.ytdlp_args())
         .output()
         .await
         .map_err(|e| DownloaderError::Process(format!("Failed to execute yt-dlp: {}", e)))?;
@@ -103,6 +209,16 @@ pub struct VideoInfo {
     pub height: Option<i32>,
     pub fps: Option<f64>,
     pub formats: Vec<FormatInfo>,
+    /// Chapter list (`null` when the video has no chapters)
+    pub chapters: Option<Vec<ChapterInfo>>,
+}
+
+/// Chapter information structure
+#[derive(Debug, Clone, serde::Deserialize)]
+pub struct ChapterInfo {
+    pub start_time: f64,
+    pub end_time: f64,
+    pub title: String,
 }
 
 /// Format information structure
@@ -125,6 +241,26 @@ pub struct FormatInfo {
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_parse_video_info_chapters() {
+        let json = br#"{
+            "id": "abc", "title": "Talk", "formats": [],
+            "chapters": [
+                {"start_time": 0.0, "end_time": 95.5, "title": "Intro"},
+                {"start_time": 95.5, "end_time": 600.0, "title": "Demo"}
+            ]
+        }"#;
+        let info: VideoInfo = parse_json(json, "video info").unwrap();
+        let chapters = info.chapters.unwrap();
+        assert_eq!(chapters.len(), 2);
+        assert_eq!(chapters[1].title, "Demo");
+        assert_eq!(chapters[1].start_time, 95.5);
+
+        let json = br#"{"id": "abc", "title": "Clip", "formats": [], "chapters": null}"#;
+        let info: VideoInfo = parse_json(json, "video info").unwrap();
+        assert!(info.chapters.is_none());
+    }
+
     #[tokio::test]
     async fn test_ytdlp_check() {
         // Test yt-dlp availability check